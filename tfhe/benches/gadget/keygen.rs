@@ -0,0 +1,44 @@
+//! Server key generation of the gadget module on a single thread and on every available core,
+//! the ratio of the two being the speedup of the parallel key generation.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rayon::ThreadPoolBuilder;
+use tfhe::gadget::client_key::ClientKey;
+use tfhe::gadget::parameters::{
+    GadgetParameters, PLAINTEXT_2_BITS_PARAMETERS, PLAINTEXT_4_BITS_PARAMETERS,
+};
+use tfhe::gadget::server_key::{CompressedServerKey, ServerKey};
+
+const PARAMETERS: [(&str, GadgetParameters); 2] = [
+    ("PLAINTEXT_2_BITS", PLAINTEXT_2_BITS_PARAMETERS),
+    ("PLAINTEXT_4_BITS", PLAINTEXT_4_BITS_PARAMETERS),
+];
+
+fn bench_server_key_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("gadget-keygen");
+    group.sample_size(10);
+
+    let thread_counts = [1, rayon::current_num_threads()];
+    for (name, parameters) in PARAMETERS.iter() {
+        let client_key: ClientKey = ClientKey::new(parameters);
+        for thread_count in thread_counts {
+            let pool = ThreadPoolBuilder::new()
+                .num_threads(thread_count)
+                .build()
+                .unwrap();
+
+            group.bench_function(format!("server_key::{name}::{thread_count}_threads"), |b| {
+                b.iter(|| pool.install(|| black_box(ServerKey::new(&client_key))));
+            });
+            group.bench_function(
+                format!("compressed_server_key::{name}::{thread_count}_threads"),
+                |b| b.iter(|| pool.install(|| black_box(CompressedServerKey::new(&client_key)))),
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(keygen_benches, bench_server_key_generation);
+criterion_main!(keygen_benches);
@@ -1,7 +1,7 @@
 const crypto = require('crypto');
 const test = require('node:test');
 const assert = require('node:assert').strict;
-const { Boolean, Shortint, BooleanParameterSet } = require("../pkg");
+const { Boolean, Shortint, Gadget, BooleanParameterSet, GadgetParameterSet } = require("../pkg");
 
 function genRandomBigIntWithBytes(byteCount) {
     return BigInt('0x' + crypto.randomBytes(byteCount).toString('hex'))
@@ -231,3 +231,60 @@ test('shortint_deterministic_keygen', (t) => {
         assert.deepStrictEqual(decrypt_other, random_message);
     }
 });
+
+// Gadget tests
+test('gadget_encrypt_decrypt', (t) => {
+    let params = Gadget.get_parameters(GadgetParameterSet.Boolean);
+    let cks = Gadget.new_client_key(params);
+    let ct = Gadget.encrypt(cks, true);
+
+    let serialized_cks = Gadget.serialize_client_key(cks);
+    let deserialized_cks = Gadget.deserialize_client_key(serialized_cks);
+
+    let serialized_ct = Gadget.serialize_ciphertext(ct);
+    let deserialized_ct = Gadget.deserialize_ciphertext(serialized_ct);
+
+    let decrypted = Gadget.decrypt(deserialized_cks, deserialized_ct);
+    assert.deepStrictEqual(decrypted, true);
+
+    let sks = Gadget.new_compressed_server_key(cks);
+
+    let serialized_sks = Gadget.serialize_compressed_server_key(sks);
+    let deserialized_sks = Gadget.deserialize_compressed_server_key(serialized_sks);
+
+    // No equality tests here, as wasm stores pointers which will always differ
+});
+
+test('gadget_compressed_modular_encrypt_decrypt', (t) => {
+    let params = Gadget.get_parameters(GadgetParameterSet.Plaintext3Bits);
+    let cks = Gadget.new_client_key(params);
+
+    for (let message = 0; message < 7; message++) {
+        let ct = Gadget.encrypt_modular_compressed(cks, message, 7);
+
+        let serialized_ct = Gadget.serialize_compressed_ciphertext(ct);
+        let deserialized_ct = Gadget.deserialize_compressed_ciphertext(serialized_ct);
+
+        let decompressed_ct = Gadget.decompress_ciphertext(deserialized_ct);
+
+        let decrypted = Gadget.decrypt_modular(cks, decompressed_ct);
+        assert.deepStrictEqual(decrypted, message);
+    }
+});
+
+test('gadget_deterministic_keygen', (t) => {
+    const TEST_LOOP_COUNT = 128;
+
+    let seed_high_bytes = genRandomBigIntWithBytes(8);
+    let seed_low_bytes = genRandomBigIntWithBytes(8);
+
+    let params = Gadget.get_parameters(GadgetParameterSet.Boolean);
+    let cks = Gadget.new_client_key_from_seed_and_parameters(seed_high_bytes, seed_low_bytes, params);
+    let other_cks = Gadget.new_client_key_from_seed_and_parameters(seed_high_bytes, seed_low_bytes, params);
+
+    for (let i = 0; i < TEST_LOOP_COUNT; i++) {
+        let ct = Gadget.encrypt(cks, i % 2 == 0);
+        let decrypted_other = Gadget.decrypt(other_cks, ct);
+        assert.deepStrictEqual(decrypted_other, i % 2 == 0);
+    }
+});
@@ -64,6 +64,25 @@ fn test_seeded_lwe_ksk_gen_equivalence<Scalar: UnsignedTorus + Send + Sync>(
 
         assert!(check_content_respects_mod(&ksk, ciphertext_modulus));
 
+        let mut deterministic_seeder =
+            DeterministicSeeder::<ActivatedRandomGenerator>::new(deterministic_seeder_seed);
+        let mut encryption_generator = EncryptionRandomGenerator::<ActivatedRandomGenerator>::new(
+            mask_seed,
+            &mut deterministic_seeder,
+        );
+
+        let par_ksk = par_allocate_and_generate_new_lwe_keyswitch_key(
+            &input_lwe_secret_key,
+            &output_lwe_secret_key,
+            decomp_base_log,
+            decomp_level_count,
+            lwe_modular_std_dev,
+            ciphertext_modulus,
+            &mut encryption_generator,
+        );
+
+        assert_eq!(ksk, par_ksk);
+
         let mut seeded_ksk = SeededLweKeyswitchKey::new(
             Scalar::ZERO,
             decomp_base_log,
@@ -87,6 +106,29 @@ fn test_seeded_lwe_ksk_gen_equivalence<Scalar: UnsignedTorus + Send + Sync>(
 
         assert!(check_content_respects_mod(&seeded_ksk, ciphertext_modulus));
 
+        let mut par_seeded_ksk = SeededLweKeyswitchKey::new(
+            Scalar::ZERO,
+            decomp_base_log,
+            decomp_level_count,
+            input_lwe_dimension,
+            output_lwe_dimension,
+            mask_seed.into(),
+            ciphertext_modulus,
+        );
+
+        let mut deterministic_seeder =
+            DeterministicSeeder::<ActivatedRandomGenerator>::new(deterministic_seeder_seed);
+
+        par_generate_seeded_lwe_keyswitch_key(
+            &input_lwe_secret_key,
+            &output_lwe_secret_key,
+            &mut par_seeded_ksk,
+            lwe_modular_std_dev,
+            &mut deterministic_seeder,
+        );
+
+        assert_eq!(seeded_ksk, par_seeded_ksk);
+
         let ser_decompressed_ksk = seeded_ksk.clone().decompress_into_lwe_keyswitch_key();
 
         assert_eq!(ksk, ser_decompressed_ksk);
@@ -21,6 +21,11 @@ impl ComputationBuffers {
         self.memory.resize(capacity, 0);
     }
 
+    /// Return the size in bytes of the underlying memory buffer.
+    pub(crate) fn size_in_bytes(&self) -> usize {
+        self.memory.len()
+    }
+
     /// Return a `PodStack` borrowoing from the managed memory buffer for use with optimized fft
     /// primitives or other functions using `PodStack` to manage temporary memory.
     pub fn stack(&mut self) -> PodStack<'_> {
@@ -0,0 +1,152 @@
+//! Asynchronous evaluation for services running on a tokio runtime.
+//!
+//! Bootstraps are CPU-bound and a circuit evaluation can take seconds: running it on an executor
+//! thread would stall every other task of that thread. The methods of this module move the
+//! evaluation to the blocking pool of the runtime with [`tokio::task::spawn_blocking`] and
+//! return futures resolving to its result.
+//!
+//! This module is only available with the `p-encoding-async` feature.
+
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::circuit::progress::CancellationToken;
+use crate::gadget::circuit::Circuit;
+use crate::gadget::encoding::Encoding;
+use crate::gadget::engine::GadgetScalar;
+use crate::gadget::error::GadgetError;
+use crate::gadget::server_key::ServerKey;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::task::{spawn_blocking, JoinError};
+
+/// Cancels its token when dropped, i.e. when the future awaiting the evaluation is dropped.
+struct CancelOnDrop(CancellationToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+/// Returns the output of a blocking task, resuming its panic if it panicked.
+fn join_output<T>(output: Result<Result<T, GadgetError>, JoinError>) -> Result<T, GadgetError> {
+    match output {
+        Ok(output) => output,
+        Err(error) if error.is_panic() => std::panic::resume_unwind(error.into_panic()),
+        Err(_) => Err(GadgetError::EvaluationCancelled),
+    }
+}
+
+impl<Scalar: GadgetScalar> ServerKey<Scalar> {
+    /// Evaluates the gate described by `encoding` on the blocking pool of the current tokio
+    /// runtime, see [`ServerKey::evaluate_gate`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub async fn evaluate_gate_async(
+        self: Arc<Self>,
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+        encoding: Encoding,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        join_output(spawn_blocking(move || self.evaluate_gate(input_ciphertexts, &encoding)).await)
+    }
+}
+
+impl ServerKey {
+    /// Evaluates `circuit` on the blocking pool of the current tokio runtime, see
+    /// [`ServerKey::evaluate_circuit`].
+    ///
+    /// Dropping the returned future cancels the evaluation before its next gate, the gates
+    /// already being bootstrapped still complete in the background.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub async fn evaluate_circuit_async(
+        self: Arc<Self>,
+        circuit: Arc<Circuit>,
+        inputs: HashMap<String, Ciphertext>,
+    ) -> Result<HashMap<String, Ciphertext>, GadgetError> {
+        let cancellation = CancellationToken::new();
+        let _guard = CancelOnDrop(cancellation.clone());
+
+        join_output(
+            spawn_blocking(move || {
+                self.evaluate_circuit_with_observer(&circuit, &inputs, &(), &cancellation)
+            })
+            .await,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean::engine::WithThreadLocalEngine;
+    use crate::gadget::circuit::Gate;
+    use crate::gadget::engine::GadgetEngine;
+    use crate::gadget::gen_keys;
+    use crate::gadget::parameters::PLAINTEXT_3_BITS_PARAMETERS;
+    use tokio::runtime::Builder;
+
+    #[test]
+    fn async_evaluation() {
+        // y = !a & b
+        let gates = vec![
+            Gate::new(vec!["a".to_string()], "t".to_string(), 0b01),
+            Gate::new(
+                vec!["t".to_string(), "b".to_string()],
+                "y".to_string(),
+                0b1000,
+            ),
+        ];
+        let circuit = Arc::new(
+            Circuit::new(
+                "and_not".to_string(),
+                vec!["a".to_string(), "b".to_string()],
+                vec!["y".to_string()],
+                gates,
+            )
+            .unwrap(),
+        );
+
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let server_key = Arc::new(server_key);
+        let runtime = Builder::new_current_thread().build().unwrap();
+
+        for (a, b) in [(0, 0), (0, 1), (1, 1)] {
+            let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                [("a", a), ("b", b)]
+                    .into_iter()
+                    .map(|(name, bit)| (name.to_string(), engine.encrypt(bit, &client_key, 3)))
+                    .collect::<HashMap<_, _>>()
+            });
+
+            let outputs = runtime
+                .block_on(
+                    server_key
+                        .clone()
+                        .evaluate_circuit_async(circuit.clone(), inputs),
+                )
+                .unwrap();
+            let y = GadgetEngine::with_thread_local_mut(|engine| {
+                engine.decrypt(&outputs["y"], &client_key)
+            });
+            assert_eq!(y, (1 - a) & b);
+        }
+
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+            vec![
+                engine.encrypt(1, &client_key, 3),
+                engine.encrypt(1, &client_key, 3),
+            ]
+        });
+        let out = runtime
+            .block_on(server_key.clone().evaluate_gate_async(inputs, encoding))
+            .unwrap();
+        let decrypted =
+            GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&out, &client_key));
+        assert_eq!(decrypted, 1);
+    }
+}
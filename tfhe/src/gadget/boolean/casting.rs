@@ -0,0 +1,188 @@
+//! Conversion between the gadget boolean ciphertexts and the upstream
+//! [`boolean`](crate::boolean) ciphertexts, so that circuits can mix gates of both modules.
+//!
+//! The two modules encode booleans differently: the gadget encrypts false as 1 and true as 2
+//! modulo 3 while the upstream module encrypts them as `-1/8` and `1/8` of the torus. A cast
+//! keyswitches the ciphertext to the key of the other module and bootstraps it with the gadget
+//! server key to translate the encoding.
+
+use serde::{Deserialize, Serialize};
+
+use crate::boolean::ciphertext::Ciphertext as BooleanCiphertext;
+use crate::boolean::client_key::ClientKey as BooleanClientKey;
+use crate::boolean::engine::WithThreadLocalEngine;
+use crate::boolean::parameters::{BooleanKeySwitchingParameters, EncryptionKeyChoice};
+use crate::core_crypto::prelude::{
+    keyswitch_lwe_ciphertext, LweCiphertext, LweKeyswitchKeyOwned, LweSecretKey,
+};
+use crate::gadget::ciphertext::{Ciphertext, NoiseLevel};
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::engine::GadgetEngine;
+use crate::gadget::error::GadgetError;
+use crate::gadget::server_key::ServerKey;
+
+use super::{BOOLEAN_ENCODINGS, BOOLEAN_PLAINTEXT_MODULUS, UPSTREAM_PLAINTEXT_MODULUS};
+
+/// Keyswitching keys between the LWE key of a gadget [`ClientKey`] and the encryption key of an
+/// upstream boolean [`BooleanClientKey`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CastingKey {
+    to_boolean: LweKeyswitchKeyOwned<u32>,
+    from_boolean: LweKeyswitchKeyOwned<u32>,
+}
+
+impl CastingKey {
+    /// Generates the casting key between `client_key` and `boolean_client_key`, both
+    /// keyswitching keys being decomposed with `parameters`.
+    ///
+    /// Returns [`GadgetError::UnsupportedCiphertextModulus`] if the ciphertext modulus of
+    /// `client_key` is not the native one, as upstream boolean ciphertexts live on the native 32
+    /// bits torus.
+    pub fn new(
+        client_key: &ClientKey,
+        boolean_client_key: &BooleanClientKey,
+        parameters: BooleanKeySwitchingParameters,
+    ) -> Result<Self, GadgetError> {
+        let ciphertext_modulus = client_key.ciphertext_modulus();
+        if !ciphertext_modulus.is_native_modulus() {
+            return Err(GadgetError::UnsupportedCiphertextModulus(
+                ciphertext_modulus.get_custom_modulus(),
+            ));
+        }
+
+        let (gadget_key, gadget_noise) = client_key.encryption_key();
+        let boolean_parameters = &boolean_client_key.parameters;
+        let (boolean_key, boolean_noise) = match boolean_parameters.encryption_key_choice {
+            EncryptionKeyChoice::Big => (
+                boolean_client_key.glwe_secret_key.as_lwe_secret_key(),
+                boolean_parameters.glwe_modular_std_dev,
+            ),
+            EncryptionKeyChoice::Small => (
+                LweSecretKey::from_container(boolean_client_key.lwe_secret_key.as_ref()),
+                boolean_parameters.lwe_modular_std_dev,
+            ),
+        };
+
+        Ok(GadgetEngine::with_thread_local_mut(|engine| Self {
+            to_boolean: engine.create_keyswitch_key(
+                &gadget_key,
+                &boolean_key,
+                parameters.ks_base_log,
+                parameters.ks_level,
+                boolean_noise,
+                ciphertext_modulus,
+            ),
+            from_boolean: engine.create_keyswitch_key(
+                &boolean_key,
+                &gadget_key,
+                parameters.ks_base_log,
+                parameters.ks_level,
+                gadget_noise,
+                ciphertext_modulus,
+            ),
+        }))
+    }
+
+    /// Casts the gadget boolean `ct` to an upstream boolean ciphertext, costing a bootstrap and
+    /// a keyswitch.
+    pub fn cast_to_boolean(
+        &self,
+        ct: &Ciphertext,
+        server_key: &ServerKey,
+    ) -> Result<BooleanCiphertext, GadgetError> {
+        match ct {
+            Ciphertext::Encrypted(_, plaintext_modulus, _) => {
+                if *plaintext_modulus != BOOLEAN_PLAINTEXT_MODULUS {
+                    return Err(GadgetError::PlaintextModulusMismatch {
+                        pin: 0,
+                        expected: BOOLEAN_PLAINTEXT_MODULUS,
+                        actual: *plaintext_modulus,
+                    });
+                }
+
+                let translated =
+                    server_key.bootstrap(ct.clone(), &BOOLEAN_ENCODINGS["to_upstream"])?;
+                let Ciphertext::Encrypted(lwe, _, _) = translated else {
+                    unreachable!("bootstrapping outputs an encrypted ciphertext")
+                };
+
+                let mut output = LweCiphertext::new(
+                    0u32,
+                    self.to_boolean.output_lwe_size(),
+                    lwe.ciphertext_modulus(),
+                );
+                keyswitch_lwe_ciphertext(&self.to_boolean, &lwe, &mut output);
+                Ok(BooleanCiphertext::Encrypted(output))
+            }
+            Ciphertext::Trivial(value) => Ok(BooleanCiphertext::Trivial(*value)),
+            Ciphertext::Placeholder => Err(GadgetError::PlaceholderCiphertext),
+        }
+    }
+
+    /// Casts the upstream boolean `ct` to a gadget boolean ciphertext, costing a keyswitch and
+    /// a bootstrap.
+    pub fn cast_from_boolean(
+        &self,
+        ct: &BooleanCiphertext,
+        server_key: &ServerKey,
+    ) -> Result<Ciphertext, GadgetError> {
+        match ct {
+            BooleanCiphertext::Encrypted(lwe) => {
+                let mut input = LweCiphertext::new(
+                    0u32,
+                    self.from_boolean.output_lwe_size(),
+                    lwe.ciphertext_modulus(),
+                );
+                keyswitch_lwe_ciphertext(&self.from_boolean, lwe, &mut input);
+
+                // -1/8 and 1/8 of the torus decode to 6 and 1 modulo 7
+                server_key.bootstrap(
+                    Ciphertext::Encrypted(input, UPSTREAM_PLAINTEXT_MODULUS, NoiseLevel::NOMINAL),
+                    &BOOLEAN_ENCODINGS["from_upstream"],
+                )
+            }
+            BooleanCiphertext::Trivial(value) => Ok(Ciphertext::Trivial(*value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean::parameters::DEFAULT_PARAMETERS;
+    use crate::gadget::boolean::BOOLEAN_PARAMETERS;
+    use crate::gadget::gen_keys;
+
+    #[test]
+    fn cast_between_gadget_and_boolean() -> Result<(), GadgetError> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let boolean_client_key = BooleanClientKey::new(&DEFAULT_PARAMETERS);
+        let casting_key = CastingKey::new(
+            &client_key,
+            &boolean_client_key,
+            BooleanKeySwitchingParameters::new(
+                DEFAULT_PARAMETERS.ks_base_log,
+                DEFAULT_PARAMETERS.ks_level,
+            ),
+        )?;
+
+        for value in [false, true, false, true] {
+            let ct = client_key.encrypt(value);
+            let boolean_ct = casting_key.cast_to_boolean(&ct, &server_key)?;
+            assert_eq!(boolean_client_key.decrypt(&boolean_ct), value);
+
+            let boolean_ct = boolean_client_key.encrypt(value);
+            let ct = casting_key.cast_from_boolean(&boolean_ct, &server_key)?;
+            assert_eq!(client_key.decrypt(&ct), value);
+
+            // the cast ciphertext goes through gadget gates
+            let out = server_key.xor(&ct, &client_key.encrypt(true))?;
+            assert_eq!(client_key.decrypt(&out), !value);
+        }
+
+        let ct = casting_key.cast_from_boolean(&BooleanCiphertext::Trivial(true), &server_key)?;
+        assert!(matches!(ct, Ciphertext::Trivial(true)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1980 @@
+use crate::boolean::engine::WithThreadLocalEngine;
+use crate::core_crypto::prelude::{
+    lwe_ciphertext_add, lwe_ciphertext_add_assign, lwe_ciphertext_opposite_assign,
+    lwe_ciphertext_plaintext_add_assign, lwe_ciphertext_plaintext_sub_assign,
+    lwe_ciphertext_sub_assign, LweCiphertext, Plaintext,
+};
+use crate::gadget::ciphertext::{Ciphertext, CompressedCiphertext, NoiseLevel};
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::engine::encode;
+use crate::gadget::error::{DecryptionError, GadgetError};
+use crate::gadget::server_key::ServerKey;
+use lazy_static::lazy_static;
+use rayon::prelude::*;
+use std::borrow::Cow;
+
+use super::encoding::{synthesize_with_modulus, Encoding, EncodingBuilder};
+use super::engine::{check_bootstrappable, GadgetEngine};
+
+pub mod casting;
+pub mod ops;
+
+pub use casting::CastingKey;
+pub use ops::KeyedCiphertext;
+
+pub const BOOLEAN_PARAMETERS: crate::gadget::GadgetParameters =
+    crate::gadget::parameters::PLAINTEXT_2_BITS_PARAMETERS;
+
+/// Plaintext modulus for boolean gates across all encodings is fixed to 3
+static BOOLEAN_PLAINTEXT_MODULUS: u32 = 3;
+static BOOLEAN_MESSAGE_TRUE: u32 = 2;
+static BOOLEAN_MESSAGE_FALSE: u32 = 1;
+/// Plaintext modulus of the canonical pins of [`ServerKey::maj3_pins`]: the number of set pins, 0
+/// to 3, fits below the padding bit of the largest modulus of [`BOOLEAN_PARAMETERS`]
+static THREE_INPUT_PLAINTEXT_MODULUS: u32 = 4;
+/// Largest number of pins of the encodings of [`ServerKey::reduce_pins`], the truth table of an
+/// encoding holding up to 7 pins
+static MAX_WIDE_PIN_COUNT: usize = 7;
+/// Plaintext modulus under which the upstream boolean encoding, false being `-1/8` and true
+/// `1/8` of the torus, decodes to 6 and 1
+static UPSTREAM_PLAINTEXT_MODULUS: u32 = 7;
+
+lazy_static! {
+    /// All boolean gates respect the following input encoding:
+    /// 0 -> 1
+    /// 1 -> 2
+    ///
+    /// For any given input wire 0 must always map to 1 and 1 must map to 2.
+    ///
+    /// The benefit of this encoding over naively setting [0,1] is that it -E{1} = E{0}
+    /// and -E{0} = E{1}. Thus we can evaluate NOT gate without bootstrapping. Another benefit,
+    /// as highligted in paper, is we switch p to 2 with PBS evalaute multiple XOR operations
+    /// and switch back p to 3 for evaluating next gates. Although, the API for now ignores the
+    /// latter point.
+    static ref BOOLEAN_ENCODINGS: std::collections::HashMap<&'static str, Encoding> = {
+        let mut encodings = std::collections::HashMap::new();
+
+        encodings.insert(
+            "and",
+            Encoding::new(
+                8,
+                2,
+                vec![BOOLEAN_MESSAGE_FALSE; 2],
+                vec![BOOLEAN_MESSAGE_TRUE; 2],
+                vec![0, 2],
+                vec![1],
+                BOOLEAN_MESSAGE_FALSE,
+                BOOLEAN_MESSAGE_TRUE,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        encodings.insert(
+            "nand",
+            Encoding::new(
+                7,
+                2,
+                vec![BOOLEAN_MESSAGE_FALSE; 2],
+                vec![BOOLEAN_MESSAGE_TRUE; 2],
+                vec![1],
+                vec![0, 2],
+                BOOLEAN_MESSAGE_FALSE,
+                BOOLEAN_MESSAGE_TRUE,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        encodings.insert(
+            "or",
+            Encoding::new(
+                14,
+                2,
+                vec![BOOLEAN_MESSAGE_FALSE; 2],
+                vec![BOOLEAN_MESSAGE_TRUE; 2],
+                vec![2],
+                vec![0, 1],
+                BOOLEAN_MESSAGE_FALSE,
+                BOOLEAN_MESSAGE_TRUE,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        encodings.insert(
+            "nor",
+            Encoding::new(
+                1,
+                2,
+                vec![BOOLEAN_MESSAGE_FALSE; 2],
+                vec![BOOLEAN_MESSAGE_TRUE; 2],
+                vec![0, 1],
+                vec![2],
+                BOOLEAN_MESSAGE_FALSE,
+                BOOLEAN_MESSAGE_TRUE,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+
+        encodings.insert(
+            "xor",
+            Encoding::new(
+                6,
+                2,
+                vec![BOOLEAN_MESSAGE_FALSE; 2],
+                vec![BOOLEAN_MESSAGE_TRUE; 2],
+                vec![1, 2],
+                vec![0],
+                BOOLEAN_MESSAGE_FALSE,
+                BOOLEAN_MESSAGE_TRUE,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        encodings.insert(
+            "xnor",
+            Encoding::new(
+                9,
+                2,
+                vec![BOOLEAN_MESSAGE_FALSE; 2],
+                vec![BOOLEAN_MESSAGE_TRUE; 2],
+                vec![0],
+                vec![1, 2],
+                BOOLEAN_MESSAGE_FALSE,
+                BOOLEAN_MESSAGE_TRUE,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        // The encodings below take the lhs pin negated (0 -> 2, 1 -> 1), see
+        // `ServerKey::signed_boolean_gate`.
+        encodings.insert(
+            "imply",
+            Encoding::new(
+                13,
+                2,
+                vec![BOOLEAN_MESSAGE_FALSE, 2],
+                vec![BOOLEAN_MESSAGE_TRUE, 1],
+                vec![2],
+                vec![0, 1],
+                BOOLEAN_MESSAGE_FALSE,
+                BOOLEAN_MESSAGE_TRUE,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        // `!lhs & rhs`, i.e. `lhs < rhs`, bootstrapped from the same sum as `imply`, i.e.
+        // `lhs <= rhs`, see `ServerKey::lt_le`.
+        encodings.insert(
+            "lt",
+            Encoding::new(
+                4,
+                2,
+                vec![BOOLEAN_MESSAGE_FALSE, 2],
+                vec![BOOLEAN_MESSAGE_TRUE, 1],
+                vec![0, 2],
+                vec![1],
+                BOOLEAN_MESSAGE_FALSE,
+                BOOLEAN_MESSAGE_TRUE,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        // The encodings below take the rhs pin negated.
+        encodings.insert(
+            "andnot",
+            Encoding::new(
+                2,
+                2,
+                vec![2, BOOLEAN_MESSAGE_FALSE],
+                vec![1, BOOLEAN_MESSAGE_TRUE],
+                vec![0, 2],
+                vec![1],
+                BOOLEAN_MESSAGE_FALSE,
+                BOOLEAN_MESSAGE_TRUE,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        encodings.insert(
+            "ornot",
+            Encoding::new(
+                11,
+                2,
+                vec![2, BOOLEAN_MESSAGE_FALSE],
+                vec![1, BOOLEAN_MESSAGE_TRUE],
+                vec![2],
+                vec![0, 1],
+                BOOLEAN_MESSAGE_FALSE,
+                BOOLEAN_MESSAGE_TRUE,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        // No linear combination modulo 3 of three boolean inputs separates the rows of the MUX,
+        // it is evaluated in two bootstraps. The first one computes the canonical bit
+        // t = (select + then - else == 1) from the pins (select, then, else), the else pin
+        // being negated (0 -> 2, 1 -> 1).
+        encodings.insert(
+            "mux_select",
+            Encoding::new(
+                134,
+                3,
+                vec![2, BOOLEAN_MESSAGE_FALSE, BOOLEAN_MESSAGE_FALSE],
+                vec![1, BOOLEAN_MESSAGE_TRUE, BOOLEAN_MESSAGE_TRUE],
+                vec![0, 1],
+                vec![2],
+                0,
+                1,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        // The second one outputs select ? then : else from the pins (t, select, else), select
+        // and else being negated.
+        encodings.insert(
+            "mux",
+            Encoding::new(
+                148,
+                3,
+                vec![2, 2, 0],
+                vec![1, 1, 1],
+                vec![1, 2],
+                vec![0],
+                BOOLEAN_MESSAGE_FALSE,
+                BOOLEAN_MESSAGE_TRUE,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        // The carry of a full adder from the pins (a, b, c, s), s being the sum a ^ b ^ c and
+        // the other pins being negated. Rows where s is not the sum never occur, they are set
+        // to the output their sum falls in.
+        encodings.insert(
+            "full_adder_carry",
+            Encoding::new(
+                33128,
+                4,
+                vec![BOOLEAN_MESSAGE_FALSE, 2, 2, 2],
+                vec![BOOLEAN_MESSAGE_TRUE, 1, 1, 1],
+                vec![0, 1],
+                vec![2],
+                BOOLEAN_MESSAGE_FALSE,
+                BOOLEAN_MESSAGE_TRUE,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        // Translates to the upstream boolean encoding, false and true being the slots 7 and 1 of
+        // the torus split in 8 slots, i.e. of the even modulus 4 with its padding bit.
+        encodings.insert(
+            "to_upstream",
+            Encoding::new(
+                2,
+                1,
+                vec![BOOLEAN_MESSAGE_FALSE],
+                vec![BOOLEAN_MESSAGE_TRUE],
+                vec![BOOLEAN_MESSAGE_FALSE],
+                vec![0, BOOLEAN_MESSAGE_TRUE],
+                7,
+                1,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                4,
+            ),
+        );
+
+        // Translates from the upstream boolean encoding read modulo 7, see
+        // `UPSTREAM_PLAINTEXT_MODULUS`.
+        encodings.insert(
+            "from_upstream",
+            Encoding::new(
+                2,
+                1,
+                vec![6],
+                vec![1],
+                vec![4, 5, 6],
+                vec![0, 1, 2, 3],
+                BOOLEAN_MESSAGE_FALSE,
+                BOOLEAN_MESSAGE_TRUE,
+                UPSTREAM_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        // Three boolean inputs cannot be told apart by a single linear combination modulo 3: the
+        // sum of the pins only depends on the number of set pins modulo 3, and negating pins
+        // does not help. The majority costs two bootstraps, the first one computing the canonical
+        // bit t = (a + b - c != 0) from the pins (a, b, c), c being negated.
+        encodings.insert(
+            "maj3_partial",
+            Encoding::new(
+                231,
+                3,
+                vec![2, BOOLEAN_MESSAGE_FALSE, BOOLEAN_MESSAGE_FALSE],
+                vec![1, BOOLEAN_MESSAGE_TRUE, BOOLEAN_MESSAGE_TRUE],
+                vec![0],
+                vec![1, 2],
+                0,
+                1,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        // The second one outputs the majority from the pins (t, c).
+        encodings.insert(
+            "maj3",
+            Encoding::new(
+                9,
+                2,
+                vec![BOOLEAN_MESSAGE_FALSE, 0],
+                vec![BOOLEAN_MESSAGE_TRUE, 1],
+                vec![2],
+                vec![0, 1],
+                BOOLEAN_MESSAGE_FALSE,
+                BOOLEAN_MESSAGE_TRUE,
+                BOOLEAN_PLAINTEXT_MODULUS,
+                BOOLEAN_PLAINTEXT_MODULUS,
+            ),
+        );
+
+        // Canonical pins modulo `THREE_INPUT_PLAINTEXT_MODULUS` can be told apart, their sum
+        // being the number of set pins, see `ServerKey::maj3_pins`.
+        let mut encoding = synthesize_with_modulus(0xe8, 3, THREE_INPUT_PLAINTEXT_MODULUS)
+            .filter(|encoding| check_bootstrappable(encoding).is_ok())
+            .expect("no encoding of maj3 modulo 4");
+        encoding.new_0 = BOOLEAN_MESSAGE_FALSE;
+        encoding.new_1 = BOOLEAN_MESSAGE_TRUE;
+        encoding.new_p = BOOLEAN_PLAINTEXT_MODULUS;
+        encodings.insert("maj3_pins", encoding);
+
+        encodings
+    };
+}
+
+/// Returns the number of bootstraps and the depth of [`ServerKey::reduce_pins`] reducing `n`
+/// canonical pins in chunks of `capacity` pins.
+fn reduce_pins_cost(n: usize, capacity: usize) -> (usize, usize) {
+    let (mut bootstraps, mut depth, mut level) = (0, 0, n);
+    while level > 1 {
+        let chunks = (level + capacity - 1) / capacity;
+        // a single pin left over by the chunks is carried to the next level as is
+        bootstraps += if chunks > 1 && level % capacity == 1 {
+            chunks - 1
+        } else {
+            chunks
+        };
+        depth += 1;
+        level = chunks;
+    }
+    (bootstraps, depth)
+}
+
+/// Returns `ct` as a [`Ciphertext::Trivial`] if it is a trivial LWE encryption of a boolean, see
+/// [`ServerKey::trivial_encrypt_modular`], so that the gates evaluate constants for free however
+/// they were injected.
+fn canonical(ct: &Ciphertext) -> Cow<'_, Ciphertext> {
+    match ct {
+        Ciphertext::Encrypted(_, plaintext_modulus, _)
+            if *plaintext_modulus == BOOLEAN_PLAINTEXT_MODULUS =>
+        {
+            match ct.trivial_message() {
+                Some(message) if message == BOOLEAN_MESSAGE_TRUE => {
+                    Cow::Owned(Ciphertext::Trivial(true))
+                }
+                Some(message) if message == BOOLEAN_MESSAGE_FALSE => {
+                    Cow::Owned(Ciphertext::Trivial(false))
+                }
+                _ => Cow::Borrowed(ct),
+            }
+        }
+        _ => Cow::Borrowed(ct),
+    }
+}
+
+/// Returns `encoding`, outputting a boolean, as is if `to_boolean` is set and outputting a
+/// canonical pin modulo [`THREE_INPUT_PLAINTEXT_MODULUS`] otherwise.
+fn three_input_output(encoding: &Encoding, to_boolean: bool) -> Cow<'_, Encoding> {
+    if to_boolean {
+        return Cow::Borrowed(encoding);
+    }
+
+    let mut encoding = encoding.clone();
+    encoding.new_0 = 0;
+    encoding.new_1 = 1;
+    encoding.new_p = THREE_INPUT_PLAINTEXT_MODULUS;
+    Cow::Owned(encoding)
+}
+
+/// Checks that `ct`, the input of `pin` of a gate, is a boolean ciphertext.
+fn check_boolean(ct: &Ciphertext, pin: usize) -> Result<(), GadgetError> {
+    match ct {
+        Ciphertext::Encrypted(_, plaintext_modulus, _)
+            if *plaintext_modulus != BOOLEAN_PLAINTEXT_MODULUS =>
+        {
+            Err(GadgetError::PlaintextModulusMismatch {
+                pin,
+                expected: BOOLEAN_PLAINTEXT_MODULUS,
+                actual: *plaintext_modulus,
+            })
+        }
+        Ciphertext::Placeholder => Err(GadgetError::PlaceholderCiphertext),
+        _ => Ok(()),
+    }
+}
+
+impl ServerKey {
+    /// Encodes a trivial boolean as a plaintext of the boolean encoding.
+    fn boolean_plaintext(&self, value: bool) -> Plaintext<u32> {
+        let message = if value {
+            BOOLEAN_MESSAGE_TRUE
+        } else {
+            BOOLEAN_MESSAGE_FALSE
+        };
+        Plaintext(encode(
+            message,
+            BOOLEAN_PLAINTEXT_MODULUS,
+            self.ciphertext_modulus(),
+        ))
+    }
+
+    fn boolean_gate(
+        &self,
+        gate_str: &str,
+        gate_fn: fn(lhs: bool, rhs: bool) -> bool,
+        lhs: &Ciphertext,
+        rhs: &Ciphertext,
+    ) -> Result<Ciphertext, GadgetError> {
+        let encoding = BOOLEAN_ENCODINGS.get(gate_str).unwrap();
+        let (lhs, rhs) = (canonical(lhs), canonical(rhs));
+        let (lhs, rhs) = (lhs.as_ref(), rhs.as_ref());
+
+        for (pin, ct) in [lhs, rhs].into_iter().enumerate() {
+            if let Ciphertext::Encrypted(_, plaintext_modulus, _) = ct {
+                if *plaintext_modulus != BOOLEAN_PLAINTEXT_MODULUS {
+                    return Err(GadgetError::PlaintextModulusMismatch {
+                        pin,
+                        expected: BOOLEAN_PLAINTEXT_MODULUS,
+                        actual: *plaintext_modulus,
+                    });
+                }
+            }
+        }
+
+        match (lhs, rhs) {
+            (
+                Ciphertext::Encrypted(lwe_lhs, _, noise_lhs),
+                Ciphertext::Encrypted(lwe_rhs, _, noise_rhs),
+            ) => {
+                let mut bootstrap_lwe_ciphertext =
+                    LweCiphertext::new(0u32, self.ciphertext_lwe_size(), self.ciphertext_modulus());
+                lwe_ciphertext_add(&mut bootstrap_lwe_ciphertext, lwe_lhs, lwe_rhs);
+                self.stats.record_linear_op();
+                self.bootstrap(
+                    Ciphertext::Encrypted(
+                        bootstrap_lwe_ciphertext,
+                        BOOLEAN_PLAINTEXT_MODULUS,
+                        NoiseLevel(noise_lhs.0 + noise_rhs.0),
+                    ),
+                    encoding,
+                )
+            }
+            (Ciphertext::Encrypted(lwe_lhs, _, noise_lhs), Ciphertext::Trivial(trivial_rhs)) => {
+                let mut bootstrap_lwe_ciphertext = lwe_lhs.clone();
+
+                let plaintext_rhs = self.boolean_plaintext(*trivial_rhs);
+                lwe_ciphertext_plaintext_add_assign(&mut bootstrap_lwe_ciphertext, plaintext_rhs);
+                self.stats.record_linear_op();
+                self.bootstrap(
+                    Ciphertext::Encrypted(
+                        bootstrap_lwe_ciphertext,
+                        BOOLEAN_PLAINTEXT_MODULUS,
+                        *noise_lhs,
+                    ),
+                    encoding,
+                )
+            }
+            (Ciphertext::Trivial(trivial_lhs), Ciphertext::Encrypted(lwe_rhs, _, noise_rhs)) => {
+                let mut bootstrap_lwe_ciphertext = lwe_rhs.clone();
+
+                let plaintext_rhs = self.boolean_plaintext(*trivial_lhs);
+                lwe_ciphertext_plaintext_add_assign(&mut bootstrap_lwe_ciphertext, plaintext_rhs);
+                self.stats.record_linear_op();
+                self.bootstrap(
+                    Ciphertext::Encrypted(
+                        bootstrap_lwe_ciphertext,
+                        BOOLEAN_PLAINTEXT_MODULUS,
+                        *noise_rhs,
+                    ),
+                    encoding,
+                )
+            }
+            (Ciphertext::Trivial(lhs), Ciphertext::Trivial(rhs)) => {
+                Ok(Ciphertext::Trivial(gate_fn(*lhs, *rhs)))
+            }
+            (Ciphertext::Placeholder, _) | (_, Ciphertext::Placeholder) => {
+                Err(GadgetError::PlaceholderCiphertext)
+            }
+        }
+    }
+
+    /// Same as [`Self::boolean_gate`] for gates whose encoding takes some pins negated, `lhs`
+    /// and `rhs` being paired with `true` when negated.
+    fn signed_boolean_gate(
+        &self,
+        gate_str: &str,
+        gate_fn: fn(lhs: bool, rhs: bool) -> bool,
+        lhs: (&Ciphertext, bool),
+        rhs: (&Ciphertext, bool),
+    ) -> Result<Ciphertext, GadgetError> {
+        let (lhs_ct, rhs_ct) = (canonical(lhs.0), canonical(rhs.0));
+        let (lhs, rhs) = ((lhs_ct.as_ref(), lhs.1), (rhs_ct.as_ref(), rhs.1));
+        if let (Ciphertext::Trivial(lhs), Ciphertext::Trivial(rhs)) = (lhs.0, rhs.0) {
+            return Ok(Ciphertext::Trivial(gate_fn(*lhs, *rhs)));
+        }
+
+        let sum = self.signed_sum(&[lhs, rhs])?;
+        self.bootstrap(sum, &BOOLEAN_ENCODINGS[gate_str])
+    }
+
+    /// Sums `inputs`, each one negated if paired with `true`, into a ciphertext modulo
+    /// [`BOOLEAN_PLAINTEXT_MODULUS`] to be bootstrapped.
+    fn signed_sum(&self, inputs: &[(&Ciphertext, bool)]) -> Result<Ciphertext, GadgetError> {
+        let mut sum =
+            LweCiphertext::new(0u32, self.ciphertext_lwe_size(), self.ciphertext_modulus());
+        let mut noise_level = NoiseLevel::ZERO;
+
+        for (pin, (ct, negate)) in inputs.iter().enumerate() {
+            match ct {
+                Ciphertext::Encrypted(lwe, plaintext_modulus, noise) => {
+                    if *plaintext_modulus != BOOLEAN_PLAINTEXT_MODULUS {
+                        return Err(GadgetError::PlaintextModulusMismatch {
+                            pin,
+                            expected: BOOLEAN_PLAINTEXT_MODULUS,
+                            actual: *plaintext_modulus,
+                        });
+                    }
+                    if *negate {
+                        lwe_ciphertext_sub_assign(&mut sum, lwe);
+                    } else {
+                        lwe_ciphertext_add_assign(&mut sum, lwe);
+                    }
+                    noise_level.0 += noise.0;
+                }
+                Ciphertext::Trivial(value) => {
+                    let plaintext = self.boolean_plaintext(*value);
+                    if *negate {
+                        lwe_ciphertext_plaintext_sub_assign(&mut sum, plaintext);
+                    } else {
+                        lwe_ciphertext_plaintext_add_assign(&mut sum, plaintext);
+                    }
+                }
+                Ciphertext::Placeholder => return Err(GadgetError::PlaceholderCiphertext),
+            }
+        }
+
+        self.stats.record_linear_op();
+        Ok(Ciphertext::Encrypted(
+            sum,
+            BOOLEAN_PLAINTEXT_MODULUS,
+            noise_level,
+        ))
+    }
+
+    /// Evaluates `select ? then_ct : else_ct`.
+    ///
+    /// The boolean encoding cannot fit the MUX in a single bootstrap modulo 3, it costs two
+    /// bootstraps instead of the three of `(select & then) | (!select & else)`. A trivial
+    /// `select` costs none.
+    pub fn mux(
+        &self,
+        select: &Ciphertext,
+        then_ct: &Ciphertext,
+        else_ct: &Ciphertext,
+    ) -> Result<Ciphertext, GadgetError> {
+        if let Ciphertext::Trivial(select) = canonical(select).as_ref() {
+            return Ok(if *select {
+                then_ct.clone()
+            } else {
+                else_ct.clone()
+            });
+        }
+
+        let sum = self.signed_sum(&[(select, false), (then_ct, false), (else_ct, true)])?;
+        let selected = self.bootstrap(sum, &BOOLEAN_ENCODINGS["mux_select"])?;
+
+        let sum = self.signed_sum(&[(&selected, false), (select, true), (else_ct, true)])?;
+        self.bootstrap(sum, &BOOLEAN_ENCODINGS["mux"])
+    }
+
+    pub fn and(&self, lhs: &Ciphertext, rhs: &Ciphertext) -> Result<Ciphertext, GadgetError> {
+        self.boolean_gate("and", |lhs, rhs| lhs && rhs, lhs, rhs)
+    }
+
+    pub fn nand(&self, lhs: &Ciphertext, rhs: &Ciphertext) -> Result<Ciphertext, GadgetError> {
+        self.boolean_gate("nand", |lhs, rhs| !(lhs && rhs), lhs, rhs)
+    }
+
+    pub fn or(&self, lhs: &Ciphertext, rhs: &Ciphertext) -> Result<Ciphertext, GadgetError> {
+        self.boolean_gate("or", |lhs, rhs| (lhs || rhs), lhs, rhs)
+    }
+
+    pub fn nor(&self, lhs: &Ciphertext, rhs: &Ciphertext) -> Result<Ciphertext, GadgetError> {
+        self.boolean_gate("nor", |lhs, rhs| !(lhs || rhs), lhs, rhs)
+    }
+
+    pub fn xor(&self, lhs: &Ciphertext, rhs: &Ciphertext) -> Result<Ciphertext, GadgetError> {
+        self.boolean_gate("xor", |lhs, rhs| (lhs ^ rhs), lhs, rhs)
+    }
+
+    pub fn xnor(&self, lhs: &Ciphertext, rhs: &Ciphertext) -> Result<Ciphertext, GadgetError> {
+        self.boolean_gate("xnor", |lhs, rhs| !(lhs ^ rhs), lhs, rhs)
+    }
+
+    /// Evaluates `!lhs | rhs`.
+    pub fn imply(&self, lhs: &Ciphertext, rhs: &Ciphertext) -> Result<Ciphertext, GadgetError> {
+        self.signed_boolean_gate("imply", |lhs, rhs| !lhs || rhs, (lhs, true), (rhs, false))
+    }
+
+    /// Evaluates `lhs & !rhs`, i.e. the negation of [`Self::imply`]. Same as [`Self::andnot`].
+    pub fn nimply(&self, lhs: &Ciphertext, rhs: &Ciphertext) -> Result<Ciphertext, GadgetError> {
+        self.andnot(lhs, rhs)
+    }
+
+    /// Evaluates `lhs & !rhs`.
+    pub fn andnot(&self, lhs: &Ciphertext, rhs: &Ciphertext) -> Result<Ciphertext, GadgetError> {
+        self.signed_boolean_gate("andnot", |lhs, rhs| lhs && !rhs, (lhs, false), (rhs, true))
+    }
+
+    /// Evaluates `lhs | !rhs`.
+    pub fn ornot(&self, lhs: &Ciphertext, rhs: &Ciphertext) -> Result<Ciphertext, GadgetError> {
+        self.signed_boolean_gate("ornot", |lhs, rhs| lhs || !rhs, (lhs, false), (rhs, true))
+    }
+
+    /// Evaluates `lhs & rhs` for a public `rhs`, without bootstrap: `false` if `rhs` is unset,
+    /// `lhs` otherwise.
+    pub fn and_scalar(&self, lhs: &Ciphertext, rhs: bool) -> Result<Ciphertext, GadgetError> {
+        check_boolean(lhs, 0)?;
+        Ok(if rhs {
+            lhs.clone()
+        } else {
+            Ciphertext::Trivial(false)
+        })
+    }
+
+    /// Evaluates `lhs | rhs` for a public `rhs`, without bootstrap: `true` if `rhs` is set,
+    /// `lhs` otherwise.
+    pub fn or_scalar(&self, lhs: &Ciphertext, rhs: bool) -> Result<Ciphertext, GadgetError> {
+        check_boolean(lhs, 0)?;
+        Ok(if rhs {
+            Ciphertext::Trivial(true)
+        } else {
+            lhs.clone()
+        })
+    }
+
+    /// Evaluates `lhs ^ rhs` for a public `rhs`, without bootstrap: the negation of `lhs` if
+    /// `rhs` is set, see [`Self::not`], `lhs` otherwise.
+    pub fn xor_scalar(&self, lhs: &Ciphertext, rhs: bool) -> Result<Ciphertext, GadgetError> {
+        check_boolean(lhs, 0)?;
+        if rhs {
+            self.not(lhs)
+        } else {
+            Ok(lhs.clone())
+        }
+    }
+
+    /// Evaluates `select ? then_ct : else_ct` for a public `select`, without bootstrap.
+    pub fn mux_scalar_select(
+        &self,
+        select: bool,
+        then_ct: &Ciphertext,
+        else_ct: &Ciphertext,
+    ) -> Result<Ciphertext, GadgetError> {
+        // pin 0 is the select input of `mux`
+        check_boolean(then_ct, 1)?;
+        check_boolean(else_ct, 2)?;
+        Ok(if select {
+            then_ct.clone()
+        } else {
+            else_ct.clone()
+        })
+    }
+
+    /// Evaluates the 3-input gate `cascade` on encrypted inputs, in two bootstraps of depth two.
+    /// A trivial input instead reduces the gate to the 2-input gate `reduce` returns, evaluated
+    /// in a single bootstrap.
+    fn three_input_gate(
+        &self,
+        reduce: fn(&Self, bool, &Ciphertext, &Ciphertext) -> Result<Ciphertext, GadgetError>,
+        cascade: fn(
+            &Self,
+            &Ciphertext,
+            &Ciphertext,
+            &Ciphertext,
+        ) -> Result<Ciphertext, GadgetError>,
+        inputs: [&Ciphertext; 3],
+    ) -> Result<Ciphertext, GadgetError> {
+        for (pin, ct) in inputs.iter().enumerate() {
+            check_boolean(ct, pin)?;
+        }
+        let [a, b, c] = inputs.map(canonical);
+        let (a, b, c) = (a.as_ref(), b.as_ref(), c.as_ref());
+
+        // the gates are symmetric, the trivial input can be any of them
+        for (trivial, lhs, rhs) in [(a, b, c), (b, a, c), (c, a, b)] {
+            if let Ciphertext::Trivial(trivial) = trivial {
+                return reduce(self, *trivial, lhs, rhs);
+            }
+        }
+
+        cascade(self, a, b, c)
+    }
+
+    /// Returns `(lhs < rhs, lhs <= rhs)`, both bootstrapped from the sum `rhs - lhs` modulo 3,
+    /// in parallel. The outputs are booleans if `to_boolean` is set, canonical pins modulo
+    /// [`THREE_INPUT_PLAINTEXT_MODULUS`] otherwise, e.g. for [`Self::maj3_pins`].
+    pub(crate) fn lt_le(
+        &self,
+        lhs: &Ciphertext,
+        rhs: &Ciphertext,
+        to_boolean: bool,
+    ) -> Result<(Ciphertext, Ciphertext), GadgetError> {
+        let (lhs, rhs) = (canonical(lhs), canonical(rhs));
+        let (lhs, rhs) = (lhs.as_ref(), rhs.as_ref());
+        if let (Ciphertext::Trivial(lhs), Ciphertext::Trivial(rhs)) = (lhs, rhs) {
+            return Ok((
+                Ciphertext::Trivial(!lhs & rhs),
+                Ciphertext::Trivial(!lhs | rhs),
+            ));
+        }
+
+        let sum = self.signed_sum(&[(lhs, true), (rhs, false)])?;
+        let (lt, le) = rayon::join(
+            || {
+                let encoding = three_input_output(&BOOLEAN_ENCODINGS["lt"], to_boolean);
+                self.bootstrap(sum.clone(), &encoding)
+            },
+            || {
+                let encoding = three_input_output(&BOOLEAN_ENCODINGS["imply"], to_boolean);
+                self.bootstrap(sum.clone(), &encoding)
+            },
+        );
+        Ok((lt?, le?))
+    }
+
+    /// Evaluates `lhs ^ rhs` into a canonical pin modulo `p` in a single bootstrap, e.g. for
+    /// [`Self::reduce_pins`].
+    pub(crate) fn xor_pin(
+        &self,
+        lhs: &Ciphertext,
+        rhs: &Ciphertext,
+        p: u32,
+    ) -> Result<Ciphertext, GadgetError> {
+        let (lhs, rhs) = (canonical(lhs), canonical(rhs));
+        let (lhs, rhs) = (lhs.as_ref(), rhs.as_ref());
+        if let (Ciphertext::Trivial(lhs), Ciphertext::Trivial(rhs)) = (lhs, rhs) {
+            return Ok(Ciphertext::Trivial(lhs ^ rhs));
+        }
+
+        let mut encoding = BOOLEAN_ENCODINGS["xor"].clone();
+        encoding.new_0 = 0;
+        encoding.new_1 = 1;
+        encoding.new_p = p;
+        let sum = self.signed_sum(&[(lhs, false), (rhs, false)])?;
+        self.bootstrap(sum, &encoding)
+    }
+
+    /// Evaluates the majority of the canonical pins modulo [`THREE_INPUT_PLAINTEXT_MODULUS`]
+    /// `pins` in a single bootstrap, into a boolean if `to_boolean` is set and into a canonical
+    /// pin otherwise.
+    pub(crate) fn maj3_pins(
+        &self,
+        pins: [&Ciphertext; 3],
+        to_boolean: bool,
+    ) -> Result<Ciphertext, GadgetError> {
+        let encoding = three_input_output(&BOOLEAN_ENCODINGS["maj3_pins"], to_boolean);
+        self.evaluate_gate(pins.map(Ciphertext::clone).to_vec(), &encoding)
+    }
+
+    /// Switches the encrypted booleans of `inputs` to canonical pins modulo `p`, false being 0
+    /// and true 1, in one bootstrap each evaluated in parallel. Trivial inputs are kept as is.
+    fn canonical_pins(
+        &self,
+        inputs: &[&Ciphertext],
+        p: u32,
+    ) -> Result<Vec<Ciphertext>, GadgetError> {
+        let encoding = Encoding::new(
+            2,
+            1,
+            vec![BOOLEAN_MESSAGE_FALSE],
+            vec![BOOLEAN_MESSAGE_TRUE],
+            vec![BOOLEAN_MESSAGE_FALSE],
+            vec![0, BOOLEAN_MESSAGE_TRUE],
+            0,
+            1,
+            BOOLEAN_PLAINTEXT_MODULUS,
+            p,
+        );
+        inputs
+            .par_iter()
+            .map(|ct| match canonical(ct).as_ref() {
+                Ciphertext::Trivial(value) => Ok(Ciphertext::Trivial(*value)),
+                ct => self.bootstrap(ct.clone(), &encoding),
+            })
+            .collect()
+    }
+
+    /// Evaluates whether at least two of `a`, `b` and `c` are set.
+    ///
+    /// Costs two bootstraps of depth two, see `BOOLEAN_ENCODINGS`, or a single one when an input
+    /// is trivial.
+    pub fn maj3(
+        &self,
+        a: &Ciphertext,
+        b: &Ciphertext,
+        c: &Ciphertext,
+    ) -> Result<Ciphertext, GadgetError> {
+        // the majority of a trivial input with two others is either their and or their or
+        self.three_input_gate(
+            |server_key, trivial, lhs, rhs| {
+                if trivial {
+                    server_key.or(lhs, rhs)
+                } else {
+                    server_key.and(lhs, rhs)
+                }
+            },
+            |server_key, a, b, c| {
+                let sum = server_key.signed_sum(&[(a, false), (b, false), (c, true)])?;
+                let partial = server_key.bootstrap(sum, &BOOLEAN_ENCODINGS["maj3_partial"])?;
+
+                let sum = server_key.signed_sum(&[(&partial, false), (c, false)])?;
+                server_key.bootstrap(sum, &BOOLEAN_ENCODINGS["maj3"])
+            },
+            [a, b, c],
+        )
+    }
+
+    /// Evaluates `a & b & c`, see [`Self::maj3`] for its cost.
+    ///
+    /// No single bootstrap modulo 3 evaluates a 3-input and, so this is the two gate cascade.
+    pub fn and3(
+        &self,
+        a: &Ciphertext,
+        b: &Ciphertext,
+        c: &Ciphertext,
+    ) -> Result<Ciphertext, GadgetError> {
+        self.three_input_gate(
+            |server_key, trivial, lhs, rhs| {
+                if trivial {
+                    server_key.and(lhs, rhs)
+                } else {
+                    Ok(Ciphertext::Trivial(false))
+                }
+            },
+            |server_key, a, b, c| server_key.and(&server_key.and(a, b)?, c),
+            [a, b, c],
+        )
+    }
+
+    /// Evaluates `a | b | c`, see [`Self::maj3`] for its cost.
+    ///
+    /// No single bootstrap modulo 3 evaluates a 3-input or, so this is the two gate cascade.
+    pub fn or3(
+        &self,
+        a: &Ciphertext,
+        b: &Ciphertext,
+        c: &Ciphertext,
+    ) -> Result<Ciphertext, GadgetError> {
+        self.three_input_gate(
+            |server_key, trivial, lhs, rhs| {
+                if trivial {
+                    Ok(Ciphertext::Trivial(true))
+                } else {
+                    server_key.or(lhs, rhs)
+                }
+            },
+            |server_key, a, b, c| server_key.or(&server_key.or(a, b)?, c),
+            [a, b, c],
+        )
+    }
+
+    /// Evaluates `a ^ b ^ c`, see [`Self::maj3`] for its cost.
+    ///
+    /// No single bootstrap modulo 3 evaluates a 3-input xor, so this is the two gate cascade.
+    pub fn xor3(
+        &self,
+        a: &Ciphertext,
+        b: &Ciphertext,
+        c: &Ciphertext,
+    ) -> Result<Ciphertext, GadgetError> {
+        self.three_input_gate(
+            |server_key, trivial, lhs, rhs| {
+                if trivial {
+                    server_key.xnor(lhs, rhs)
+                } else {
+                    server_key.xor(lhs, rhs)
+                }
+            },
+            |server_key, a, b, c| server_key.xor(&server_key.xor(a, b)?, c),
+            [a, b, c],
+        )
+    }
+
+    /// Returns the sum `a ^ b` and the carry `a & b` of a half adder.
+    ///
+    /// Both outputs bootstrap the same linear combination `a + b`, which is only computed once.
+    pub fn half_adder(
+        &self,
+        a: &Ciphertext,
+        b: &Ciphertext,
+    ) -> Result<(Ciphertext, Ciphertext), GadgetError> {
+        let (a, b) = (canonical(a), canonical(b));
+        let (a, b) = (a.as_ref(), b.as_ref());
+        if let (Ciphertext::Trivial(a), Ciphertext::Trivial(b)) = (a, b) {
+            return Ok((Ciphertext::Trivial(a ^ b), Ciphertext::Trivial(a & b)));
+        }
+
+        let sum = self.signed_sum(&[(a, false), (b, false)])?;
+        let carry = self.bootstrap(sum.clone(), &BOOLEAN_ENCODINGS["and"])?;
+        let sum = self.bootstrap(sum, &BOOLEAN_ENCODINGS["xor"])?;
+        Ok((sum, carry))
+    }
+
+    /// Returns the sum `a ^ b ^ cin` and the carry out of a full adder.
+    ///
+    /// Neither output fits in a single bootstrap modulo 3 and no linear combination of the
+    /// inputs can be shared between them, so the sum is the cascade of two xor and the carry is
+    /// bootstrapped from the sum and the inputs, three bootstraps in total.
+    pub fn full_adder(
+        &self,
+        a: &Ciphertext,
+        b: &Ciphertext,
+        cin: &Ciphertext,
+    ) -> Result<(Ciphertext, Ciphertext), GadgetError> {
+        let (a, b, cin) = (canonical(a), canonical(b), canonical(cin));
+        let (a, b, cin) = (a.as_ref(), b.as_ref(), cin.as_ref());
+        if let (Ciphertext::Trivial(a), Ciphertext::Trivial(b), Ciphertext::Trivial(cin)) =
+            (a, b, cin)
+        {
+            return Ok((
+                Ciphertext::Trivial(a ^ b ^ cin),
+                Ciphertext::Trivial((a & b) | (cin & (a ^ b))),
+            ));
+        }
+
+        let sum = self.xor(&self.xor(a, b)?, cin)?;
+
+        let carry = self.signed_sum(&[(a, true), (b, true), (cin, true), (&sum, false)])?;
+        let carry = self.bootstrap(carry, &BOOLEAN_ENCODINGS["full_adder_carry"])?;
+        Ok((sum, carry))
+    }
+
+    /// Returns the largest number of canonical pins, up to [`MAX_WIDE_PIN_COUNT`], whose sum a
+    /// single bootstrap of this key reads, together with the plaintext modulus of the sum. Each
+    /// pin adds the nominal noise level. Returns `None` if no bootstrap reads more than two pins.
+    pub(crate) fn wide_capacity(&self) -> Option<(usize, u32)> {
+        let parameters = self.parameters();
+        (3..=MAX_WIDE_PIN_COUNT).rev().find_map(|pin_count| {
+            // the sum of the pins counts from 0 to pin_count
+            let p = pin_count as u32 + 1;
+            (p <= parameters.max_supported_p()
+                && parameters.max_noise_level(p) >= NoiseLevel(pin_count))
+            .then_some((pin_count, p))
+        })
+    }
+
+    /// Reduces `inputs` with the symmetric gate `output`, whose output only depends on the
+    /// number of set pins out of a number of pins, returning `Ciphertext::Trivial(identity)` if
+    /// `inputs` is empty.
+    ///
+    /// `inputs` are reduced with the 2-input `gate` as a balanced tree, the gates of each level
+    /// being evaluated in parallel, `n - 1` bootstraps at a depth of `ceil(log2(n))` for `n`
+    /// inputs.
+    ///
+    /// The boolean encoding cannot fit more than two pins in a bootstrap modulo 3, the sum of
+    /// the pins only depends on the number of set pins modulo 3. The wide path instead switches
+    /// the inputs to canonical pins modulo the `p` of [`Self::wide_capacity`], then reduces them
+    /// with [`Self::reduce_pins`], about `n + ceil((n - 1) / (c - 1))` bootstraps at a depth of
+    /// `1 + ceil(log_c(n))` for chunks of `c` pins. It is only taken when it costs both fewer
+    /// bootstraps and a smaller depth than the tree, see [`reduce_pins_cost`]. As each input
+    /// costs a bootstrap to switch to a pin, the tree wins for every `n` and `c`.
+    fn reduce_many(
+        &self,
+        inputs: &[Ciphertext],
+        output: fn(set_pins: usize, pin_count: usize) -> bool,
+        gate: fn(&Self, &Ciphertext, &Ciphertext) -> Result<Ciphertext, GadgetError>,
+        identity: bool,
+    ) -> Result<Ciphertext, GadgetError> {
+        let (capacity, p) = match (inputs, self.wide_capacity()) {
+            ([], _) => return Ok(Ciphertext::Trivial(identity)),
+            ([input], _) => return Ok(input.clone()),
+            ([_, _], _) | (_, None) => return self.reduce_tree(inputs, gate),
+            (_, Some(capacity)) => capacity,
+        };
+
+        let n = inputs.len();
+        let (pins_bootstraps, pins_depth) = reduce_pins_cost(n, capacity);
+        let tree_depth = n.next_power_of_two().trailing_zeros() as usize;
+        // the switch to canonical pins costs a bootstrap per input, at a depth of one
+        if n + pins_bootstraps >= n - 1 || 1 + pins_depth >= tree_depth {
+            return self.reduce_tree(inputs, gate);
+        }
+
+        for (pin, ct) in inputs.iter().enumerate() {
+            check_boolean(ct, pin)?;
+        }
+
+        let pins = self.canonical_pins(&inputs.iter().collect::<Vec<_>>(), p)?;
+        self.reduce_pins(pins, (capacity, p), output)
+    }
+
+    /// Reduces the canonical pins modulo `p` of `pins` with the symmetric gate `output` into a
+    /// boolean, in chunks of `capacity` pins, see [`Self::reduce_many`] and
+    /// [`Self::wide_capacity`].
+    pub(crate) fn reduce_pins(
+        &self,
+        pins: Vec<Ciphertext>,
+        (capacity, p): (usize, u32),
+        output: fn(set_pins: usize, pin_count: usize) -> bool,
+    ) -> Result<Ciphertext, GadgetError> {
+        let mut level = pins;
+        loop {
+            let last = level.len() <= capacity;
+            let (new_p, new_0, new_1) = if last {
+                (
+                    BOOLEAN_PLAINTEXT_MODULUS,
+                    BOOLEAN_MESSAGE_FALSE,
+                    BOOLEAN_MESSAGE_TRUE,
+                )
+            } else {
+                (p, 0, 1)
+            };
+
+            level = level
+                .par_chunks(capacity)
+                .map(|chunk| {
+                    if let ([pin], false) = (chunk, last) {
+                        // a single pin left over by the chunks is already canonical
+                        return Ok(pin.clone());
+                    }
+
+                    let pin_count = chunk.len();
+                    let tt_value = (0..1u128 << pin_count)
+                        .filter(|row| output(row.count_ones() as usize, pin_count))
+                        .fold(0, |tt_value, row| tt_value | 1 << row);
+                    let encoding = EncodingBuilder::new(tt_value, pin_count, p)
+                        .output_modulus(new_p, new_0, new_1)
+                        .build()?;
+                    self.evaluate_gate(chunk.to_vec(), &encoding)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if last {
+                return Ok(level.pop().unwrap());
+            }
+        }
+    }
+
+    /// Reduces `inputs` with the 2-input `gate` as a balanced tree, the gates of each level
+    /// being evaluated in parallel, see [`Self::reduce_many`].
+    fn reduce_tree(
+        &self,
+        inputs: &[Ciphertext],
+        gate: fn(&Self, &Ciphertext, &Ciphertext) -> Result<Ciphertext, GadgetError>,
+    ) -> Result<Ciphertext, GadgetError> {
+        let mut level = inputs.to_vec();
+        while level.len() > 1 {
+            level = level
+                .par_chunks(2)
+                .map(|pair| self.reduce_pair(pair, gate))
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+
+        Ok(level.pop().unwrap())
+    }
+
+    fn reduce_pair(
+        &self,
+        pair: &[Ciphertext],
+        gate: fn(&Self, &Ciphertext, &Ciphertext) -> Result<Ciphertext, GadgetError>,
+    ) -> Result<Ciphertext, GadgetError> {
+        match pair {
+            [lhs, rhs] => gate(self, lhs, rhs),
+            [input] => Ok(input.clone()),
+            _ => unreachable!("chunks hold one or two ciphertexts"),
+        }
+    }
+
+    /// Evaluates the and of all `inputs`, `true` if `inputs` is empty. See
+    /// [`Self::reduce_many`] for the cost.
+    pub fn and_many(&self, inputs: &[Ciphertext]) -> Result<Ciphertext, GadgetError> {
+        self.reduce_many(
+            inputs,
+            |set_pins, pin_count| set_pins == pin_count,
+            Self::and,
+            true,
+        )
+    }
+
+    /// Evaluates the or of all `inputs`, `false` if `inputs` is empty. See
+    /// [`Self::reduce_many`] for the cost.
+    pub fn or_many(&self, inputs: &[Ciphertext]) -> Result<Ciphertext, GadgetError> {
+        self.reduce_many(inputs, |set_pins, _| set_pins > 0, Self::or, false)
+    }
+
+    /// Evaluates the xor of all `inputs`, `false` if `inputs` is empty. See
+    /// [`Self::reduce_many`] for the cost.
+    pub fn xor_many(&self, inputs: &[Ciphertext]) -> Result<Ciphertext, GadgetError> {
+        self.reduce_many(inputs, |set_pins, _| set_pins % 2 == 1, Self::xor, false)
+    }
+
+    pub fn not(&self, input: &Ciphertext) -> Result<Ciphertext, GadgetError> {
+        match input {
+            Ciphertext::Encrypted(lwe_input, plaintext_modulus, noise_level) => {
+                let mut lwe_input_clone = lwe_input.clone();
+                lwe_ciphertext_opposite_assign(&mut lwe_input_clone);
+                self.stats.record_linear_op();
+                Ok(Ciphertext::Encrypted(
+                    lwe_input_clone,
+                    *plaintext_modulus,
+                    *noise_level,
+                ))
+            }
+            Ciphertext::Trivial(input) => Ok(Ciphertext::Trivial(!input)),
+            Ciphertext::Placeholder => Err(GadgetError::PlaceholderCiphertext),
+        }
+    }
+}
+
+impl ClientKey {
+    pub fn encrypt(&self, message: bool) -> Ciphertext {
+        GadgetEngine::with_thread_local_mut(|engine| self.encrypt_with_engine(engine, message))
+    }
+
+    /// Same as [`ClientKey::encrypt`], drawing the randomness from `engine` instead of the
+    /// thread-local engine.
+    pub fn encrypt_with_engine(&self, engine: &mut GadgetEngine, message: bool) -> Ciphertext {
+        let message = {
+            if message {
+                BOOLEAN_MESSAGE_TRUE
+            } else {
+                BOOLEAN_MESSAGE_FALSE
+            }
+        };
+        engine.encrypt(message, self, BOOLEAN_PLAINTEXT_MODULUS)
+    }
+
+    pub fn encrypt_compressed(&self, message: bool) -> CompressedCiphertext {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            let message = {
+                if message {
+                    BOOLEAN_MESSAGE_TRUE
+                } else {
+                    BOOLEAN_MESSAGE_FALSE
+                }
+            };
+            engine.encrypt_compressed(message, self, BOOLEAN_PLAINTEXT_MODULUS)
+        })
+    }
+
+    /// Decrypts `ct`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the decryption fails, see [`Self::try_decrypt`].
+    pub fn decrypt(&self, ct: &Ciphertext) -> bool {
+        self.try_decrypt(ct)
+            .unwrap_or_else(|error| panic!("P-encoding boolean decryption failed: {error}"))
+    }
+
+    /// Decrypts `ct`, returning an error if it is not a boolean ciphertext or if its decrypted
+    /// message encodes neither true nor false, e.g. because its noise exceeded its bound.
+    pub fn try_decrypt(&self, ct: &Ciphertext) -> Result<bool, DecryptionError> {
+        let plaintext_modulus = match ct {
+            // trivial ciphertexts hold the boolean itself rather than its encoding
+            Ciphertext::Trivial(value) => return Ok(*value),
+            Ciphertext::Encrypted(_, plaintext_modulus, _) => *plaintext_modulus,
+            Ciphertext::Placeholder => return Err(DecryptionError::PlaceholderCiphertext),
+        };
+        if plaintext_modulus != BOOLEAN_PLAINTEXT_MODULUS {
+            return Err(DecryptionError::PlaintextModulusMismatch {
+                expected: BOOLEAN_PLAINTEXT_MODULUS,
+                actual: plaintext_modulus,
+            });
+        }
+
+        let message = GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(ct, self));
+        if message == BOOLEAN_MESSAGE_FALSE {
+            Ok(false)
+        } else if message == BOOLEAN_MESSAGE_TRUE {
+            Ok(true)
+        } else {
+            Err(DecryptionError::InvalidBooleanMessage(message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::gadget::gen_keys;
+    use std::error::Error;
+
+    static REPEAT: usize = 1000;
+
+    fn random_boolean() -> bool {
+        (rand::thread_rng().gen::<u32>() % 2) != 0
+    }
+
+    #[test]
+    fn test_and_gate() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for _ in 0..REPEAT {
+            let lhs = random_boolean();
+            let rhs = random_boolean();
+            let expected_out_bool = lhs && rhs;
+
+            let lhs_ct = client_key.encrypt(lhs);
+            let rhs_ct = client_key.encrypt(rhs);
+            // let now = std::time::Instant::now();
+            let out_ct = server_key.and(&lhs_ct, &rhs_ct)?;
+            // println!("Time: {}", now.elapsed().as_millis());
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Ciphertext lhs, Trivial rhs
+            let lhs_ct = client_key.encrypt(lhs);
+            let out_ct = server_key.and(&lhs_ct, &Ciphertext::Trivial(rhs))?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Ciphertext rhs
+            let rhs_ct = client_key.encrypt(rhs);
+            let out_ct = server_key.and(&Ciphertext::Trivial(lhs), &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nand_gate() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for _ in 0..REPEAT {
+            let lhs = random_boolean();
+            let rhs = random_boolean();
+            let expected_out_bool = !(lhs && rhs);
+
+            let lhs_ct = client_key.encrypt(lhs);
+            let rhs_ct = client_key.encrypt(rhs);
+            let out_ct = server_key.nand(&lhs_ct, &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Ciphertext lhs, Trivial rhs
+            let lhs_ct = client_key.encrypt(lhs);
+            let out_ct = server_key.nand(&lhs_ct, &Ciphertext::Trivial(rhs))?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Ciphertext rhs
+            let rhs_ct = client_key.encrypt(rhs);
+            let out_ct = server_key.nand(&Ciphertext::Trivial(lhs), &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_or_gate() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for _ in 0..REPEAT {
+            let lhs = random_boolean();
+            let rhs = random_boolean();
+            let expected_out_bool = lhs || rhs;
+
+            let lhs_ct = client_key.encrypt(lhs);
+            let rhs_ct = client_key.encrypt(rhs);
+            let out_ct = server_key.or(&lhs_ct, &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Ciphertext lhs, Trivial rhs
+            let lhs_ct = client_key.encrypt(lhs);
+            let out_ct = server_key.or(&lhs_ct, &Ciphertext::Trivial(rhs))?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Ciphertext rhs
+            let rhs_ct = client_key.encrypt(rhs);
+            let out_ct = server_key.or(&Ciphertext::Trivial(lhs), &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nor_gate() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for _ in 0..REPEAT {
+            let lhs = random_boolean();
+            let rhs = random_boolean();
+            let expected_out_bool = !(lhs || rhs);
+
+            let lhs_ct = client_key.encrypt(lhs);
+            let rhs_ct = client_key.encrypt(rhs);
+            let out_ct = server_key.nor(&lhs_ct, &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Ciphertext lhs, Trivial rhs
+            let lhs_ct = client_key.encrypt(lhs);
+            let out_ct = server_key.nor(&lhs_ct, &Ciphertext::Trivial(rhs))?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Ciphertext rhs
+            let rhs_ct = client_key.encrypt(rhs);
+            let out_ct = server_key.nor(&Ciphertext::Trivial(lhs), &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_xor_gate() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for _ in 0..REPEAT {
+            let lhs = random_boolean();
+            let rhs = random_boolean();
+            let expected_out_bool = lhs ^ rhs;
+
+            let lhs_ct = client_key.encrypt(lhs);
+            let rhs_ct = client_key.encrypt(rhs);
+            let out_ct = server_key.xor(&lhs_ct, &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Ciphertext lhs, Trivial rhs
+            let lhs_ct = client_key.encrypt(lhs);
+            let out_ct = server_key.xor(&lhs_ct, &Ciphertext::Trivial(rhs))?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Ciphertext rhs
+            let rhs_ct = client_key.encrypt(rhs);
+            let out_ct = server_key.xor(&Ciphertext::Trivial(lhs), &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_not_gate() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for _ in 0..REPEAT {
+            let input = random_boolean();
+            let input_ct = client_key.encrypt(input);
+            let out_ct = server_key.not(&input_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, !input, "input: {input}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compressed_encryption() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for _ in 0..REPEAT {
+            let lhs = random_boolean();
+            let rhs = random_boolean();
+
+            let lhs_ct = client_key.encrypt_compressed(lhs).decompress();
+            let rhs_ct: Ciphertext = client_key.encrypt_compressed(rhs).into();
+            assert_eq!(client_key.decrypt(&lhs_ct), lhs);
+
+            let out_ct = server_key.and(&lhs_ct, &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, lhs && rhs, "left: {lhs}, right: {rhs}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_and_then_or_gate() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        // Helps test parameters are correct for repeated bootstrapping. Even a single
+        // loop should suffice.
+        let mut main_wire = random_boolean();
+        let mut main_wire_ct = client_key.encrypt(main_wire);
+        for _ in 0..REPEAT {
+            // and
+            let rhs = random_boolean();
+            let rhs_ct = client_key.encrypt(rhs);
+            main_wire &= rhs;
+            main_wire_ct = server_key.and(&main_wire_ct, &rhs_ct)?;
+
+            // or
+            let rhs = random_boolean();
+            let rhs_ct = client_key.encrypt(rhs);
+            main_wire |= rhs;
+            main_wire_ct = server_key.or(&main_wire_ct, &rhs_ct)?;
+        }
+
+        let out = client_key.decrypt(&main_wire_ct);
+        assert_eq!(out, main_wire);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_not_with_another_gate() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        let mut main_wire = random_boolean();
+        let mut main_wire_ct = client_key.encrypt(main_wire);
+
+        // and
+        {
+            let rhs = random_boolean();
+            let rhs_ct = client_key.encrypt(rhs);
+            main_wire &= rhs;
+            main_wire_ct = server_key.and(&main_wire_ct, &rhs_ct)?;
+        }
+
+        // not
+        main_wire = !main_wire;
+        main_wire_ct = server_key.not(&main_wire_ct)?;
+
+        // xor
+        {
+            let rhs = random_boolean();
+            let rhs_ct = client_key.encrypt(rhs);
+            main_wire ^= rhs;
+            main_wire_ct = server_key.xor(&main_wire_ct, &rhs_ct)?;
+        }
+
+        let out = client_key.decrypt(&main_wire_ct);
+        assert_eq!(out, main_wire);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_xnor_gate() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for _ in 0..REPEAT {
+            let lhs = random_boolean();
+            let rhs = random_boolean();
+            let expected_out_bool = !(lhs ^ rhs);
+
+            let lhs_ct = client_key.encrypt(lhs);
+            let rhs_ct = client_key.encrypt(rhs);
+            let out_ct = server_key.xnor(&lhs_ct, &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Ciphertext lhs, Trivial rhs
+            let out_ct = server_key.xnor(&lhs_ct, &Ciphertext::Trivial(rhs))?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Ciphertext rhs
+            let out_ct = server_key.xnor(&Ciphertext::Trivial(lhs), &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Trivial rhs
+            let out_ct = server_key.xnor(&Ciphertext::Trivial(lhs), &Ciphertext::Trivial(rhs))?;
+            assert!(matches!(out_ct, Ciphertext::Trivial(out) if out == expected_out_bool));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_imply_gate() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for _ in 0..REPEAT {
+            let lhs = random_boolean();
+            let rhs = random_boolean();
+            let expected_out_bool = !lhs || rhs;
+
+            let lhs_ct = client_key.encrypt(lhs);
+            let rhs_ct = client_key.encrypt(rhs);
+            let out_ct = server_key.imply(&lhs_ct, &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Ciphertext lhs, Trivial rhs
+            let out_ct = server_key.imply(&lhs_ct, &Ciphertext::Trivial(rhs))?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Ciphertext rhs
+            let out_ct = server_key.imply(&Ciphertext::Trivial(lhs), &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Trivial rhs
+            let out_ct = server_key.imply(&Ciphertext::Trivial(lhs), &Ciphertext::Trivial(rhs))?;
+            assert!(matches!(out_ct, Ciphertext::Trivial(out) if out == expected_out_bool));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nimply_gate() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for _ in 0..REPEAT {
+            let lhs = random_boolean();
+            let rhs = random_boolean();
+            let expected_out_bool = lhs && !rhs;
+
+            let lhs_ct = client_key.encrypt(lhs);
+            let rhs_ct = client_key.encrypt(rhs);
+            let out_ct = server_key.nimply(&lhs_ct, &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Ciphertext lhs, Trivial rhs
+            let out_ct = server_key.nimply(&lhs_ct, &Ciphertext::Trivial(rhs))?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Ciphertext rhs
+            let out_ct = server_key.nimply(&Ciphertext::Trivial(lhs), &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Trivial rhs
+            let out_ct = server_key.nimply(&Ciphertext::Trivial(lhs), &Ciphertext::Trivial(rhs))?;
+            assert!(matches!(out_ct, Ciphertext::Trivial(out) if out == expected_out_bool));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_andnot_gate() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for _ in 0..REPEAT {
+            let lhs = random_boolean();
+            let rhs = random_boolean();
+            let expected_out_bool = lhs && !rhs;
+
+            let lhs_ct = client_key.encrypt(lhs);
+            let rhs_ct = client_key.encrypt(rhs);
+            let out_ct = server_key.andnot(&lhs_ct, &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Ciphertext lhs, Trivial rhs
+            let out_ct = server_key.andnot(&lhs_ct, &Ciphertext::Trivial(rhs))?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Ciphertext rhs
+            let out_ct = server_key.andnot(&Ciphertext::Trivial(lhs), &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Trivial rhs
+            let out_ct = server_key.andnot(&Ciphertext::Trivial(lhs), &Ciphertext::Trivial(rhs))?;
+            assert!(matches!(out_ct, Ciphertext::Trivial(out) if out == expected_out_bool));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ornot_gate() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for _ in 0..REPEAT {
+            let lhs = random_boolean();
+            let rhs = random_boolean();
+            let expected_out_bool = lhs || !rhs;
+
+            let lhs_ct = client_key.encrypt(lhs);
+            let rhs_ct = client_key.encrypt(rhs);
+            let out_ct = server_key.ornot(&lhs_ct, &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Ciphertext lhs, Trivial rhs
+            let out_ct = server_key.ornot(&lhs_ct, &Ciphertext::Trivial(rhs))?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Ciphertext rhs
+            let out_ct = server_key.ornot(&Ciphertext::Trivial(lhs), &rhs_ct)?;
+            let out_bool = client_key.decrypt(&out_ct);
+            assert_eq!(out_bool, expected_out_bool, "left: {lhs}, right: {rhs}");
+
+            // Trivial lhs, Trivial rhs
+            let out_ct = server_key.ornot(&Ciphertext::Trivial(lhs), &Ciphertext::Trivial(rhs))?;
+            assert!(matches!(out_ct, Ciphertext::Trivial(out) if out == expected_out_bool));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_three_input_gates() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        server_key.enable_stats(true);
+
+        type Gate = fn(
+            &ServerKey,
+            &Ciphertext,
+            &Ciphertext,
+            &Ciphertext,
+        ) -> Result<Ciphertext, GadgetError>;
+        type Reference = fn(bool, bool, bool) -> bool;
+        let gates: [(&str, Gate, Reference); 4] = [
+            ("maj3", ServerKey::maj3, |a, b, c| {
+                (a & b) | (a & c) | (b & c)
+            }),
+            ("and3", ServerKey::and3, |a, b, c| a & b & c),
+            ("or3", ServerKey::or3, |a, b, c| a | b | c),
+            ("xor3", ServerKey::xor3, |a, b, c| a ^ b ^ c),
+        ];
+
+        for (name, gate, gate_fn) in gates {
+            for row in 0..8 {
+                let (a, b, c) = (row & 1 == 1, row & 2 == 2, row & 4 == 4);
+                let expected_out_bool = gate_fn(a, b, c);
+
+                let a_ct = client_key.encrypt(a);
+                let b_ct = client_key.encrypt(b);
+                let c_ct = client_key.encrypt(c);
+                server_key.reset_stats();
+                let out_ct = gate(&server_key, &a_ct, &b_ct, &c_ct)?;
+                assert_eq!(
+                    client_key.decrypt(&out_ct),
+                    expected_out_bool,
+                    "{name} row: {row}"
+                );
+                assert_eq!(server_key.stats().pbs_count, 2, "{name} row: {row}");
+
+                // One trivial input in each position, a single bootstrap unless the trivial
+                // input decides the output
+                let trivial_inputs = [
+                    (Ciphertext::Trivial(a), b_ct.clone(), c_ct.clone()),
+                    (a_ct.clone(), Ciphertext::Trivial(b), c_ct.clone()),
+                    (a_ct.clone(), b_ct.clone(), Ciphertext::Trivial(c)),
+                ];
+                for (a_ct, b_ct, c_ct) in &trivial_inputs {
+                    server_key.reset_stats();
+                    let out_ct = gate(&server_key, a_ct, b_ct, c_ct)?;
+                    assert_eq!(
+                        client_key.decrypt(&out_ct),
+                        expected_out_bool,
+                        "{name} row: {row}"
+                    );
+                    let expected_pbs_count = u64::from(!matches!(out_ct, Ciphertext::Trivial(_)));
+                    assert_eq!(
+                        server_key.stats().pbs_count,
+                        expected_pbs_count,
+                        "{name} row: {row}"
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_adders() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for row in 0..8 {
+            let (a, b, cin) = (row & 1 == 1, row & 2 == 2, row & 4 == 4);
+            let a_ct = client_key.encrypt(a);
+            let b_ct = client_key.encrypt(b);
+            let cin_ct = client_key.encrypt(cin);
+
+            let (sum_ct, carry_ct) = server_key.half_adder(&a_ct, &b_ct)?;
+            assert_eq!(client_key.decrypt(&sum_ct), a ^ b, "row: {row}");
+            assert_eq!(client_key.decrypt(&carry_ct), a & b, "row: {row}");
+
+            let expected_sum = a ^ b ^ cin;
+            let expected_carry = (a as u8 + b as u8 + cin as u8) >= 2;
+
+            let (sum_ct, carry_ct) = server_key.full_adder(&a_ct, &b_ct, &cin_ct)?;
+            assert_eq!(client_key.decrypt(&sum_ct), expected_sum, "row: {row}");
+            assert_eq!(client_key.decrypt(&carry_ct), expected_carry, "row: {row}");
+
+            // Trivial carry in
+            let (sum_ct, carry_ct) =
+                server_key.full_adder(&a_ct, &b_ct, &Ciphertext::Trivial(cin))?;
+            assert_eq!(client_key.decrypt(&sum_ct), expected_sum, "row: {row}");
+            assert_eq!(client_key.decrypt(&carry_ct), expected_carry, "row: {row}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reductions() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        server_key.enable_stats(true);
+
+        for len in [0usize, 1, 2, 5, 8] {
+            // the balanced tree, cheaper than the wide path, e.g. 7 bootstraps for 8 inputs
+            let tree_pbs_count = len.saturating_sub(1) as u64;
+            for _ in 0..10 {
+                let values: Vec<bool> = (0..len).map(|_| random_boolean()).collect();
+                let cts: Vec<Ciphertext> = values
+                    .iter()
+                    .map(|value| client_key.encrypt(*value))
+                    .collect();
+
+                server_key.reset_stats();
+                let out_ct = server_key.and_many(&cts)?;
+                assert_eq!(server_key.stats().pbs_count, tree_pbs_count, "{values:?}");
+                assert_eq!(
+                    client_key.decrypt(&out_ct),
+                    values.iter().all(|v| *v),
+                    "{values:?}"
+                );
+
+                server_key.reset_stats();
+                let out_ct = server_key.or_many(&cts)?;
+                assert_eq!(server_key.stats().pbs_count, tree_pbs_count, "{values:?}");
+                assert_eq!(
+                    client_key.decrypt(&out_ct),
+                    values.iter().any(|v| *v),
+                    "{values:?}"
+                );
+
+                server_key.reset_stats();
+                let out_ct = server_key.xor_many(&cts)?;
+                assert_eq!(server_key.stats().pbs_count, tree_pbs_count, "{values:?}");
+                let expected = values.iter().fold(false, |acc, v| acc ^ v);
+                assert_eq!(client_key.decrypt(&out_ct), expected, "{values:?}");
+            }
+        }
+
+        // trivial inputs are folded into the gates of the tree
+        let values = [true, false, true, true, true];
+        let cts = [
+            client_key.encrypt(true),
+            Ciphertext::Trivial(false),
+            client_key.encrypt(true),
+            Ciphertext::Trivial(true),
+            client_key.encrypt(true),
+        ];
+        assert!(!client_key.decrypt(&server_key.and_many(&cts)?));
+        assert!(client_key.decrypt(&server_key.or_many(&cts)?));
+        assert_eq!(
+            client_key.decrypt(&server_key.xor_many(&cts)?),
+            values.iter().fold(false, |acc, v| acc ^ v)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_boolean_encodings_are_valid() {
+        for (name, encoding) in BOOLEAN_ENCODINGS.iter() {
+            encoding
+                .check_truth_table()
+                .unwrap_or_else(|err| panic!("invalid boolean encoding {name}: {err}"));
+        }
+    }
+
+    #[test]
+    fn test_mux_gate() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for row in 0..8 {
+            let select = row & 1 == 1;
+            let then_value = row & 2 == 2;
+            let else_value = row & 4 == 4;
+            let expected_out_bool = if select { then_value } else { else_value };
+
+            let select_ct = client_key.encrypt(select);
+            let then_ct = client_key.encrypt(then_value);
+            let else_ct = client_key.encrypt(else_value);
+            let out_ct = server_key.mux(&select_ct, &then_ct, &else_ct)?;
+            assert_eq!(client_key.decrypt(&out_ct), expected_out_bool, "row: {row}");
+
+            // Trivial data inputs
+            let out_ct = server_key.mux(
+                &select_ct,
+                &Ciphertext::Trivial(then_value),
+                &Ciphertext::Trivial(else_value),
+            )?;
+            assert_eq!(client_key.decrypt(&out_ct), expected_out_bool, "row: {row}");
+
+            // Trivial select
+            let out_ct = server_key.mux(&Ciphertext::Trivial(select), &then_ct, &else_ct)?;
+            assert_eq!(client_key.decrypt(&out_ct), expected_out_bool, "row: {row}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scalar_gates() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        server_key.enable_stats(true);
+
+        for row in 0..4 {
+            let (lhs, rhs) = (row & 1 == 1, row & 2 == 2);
+            let lhs_ct = client_key.encrypt(lhs);
+            let rhs_ct = client_key.encrypt(rhs);
+
+            let out_ct = server_key.and_scalar(&lhs_ct, rhs)?;
+            assert_eq!(client_key.decrypt(&out_ct), lhs && rhs, "row: {row}");
+            let out_ct = server_key.or_scalar(&lhs_ct, rhs)?;
+            assert_eq!(client_key.decrypt(&out_ct), lhs || rhs, "row: {row}");
+            let out_ct = server_key.xor_scalar(&lhs_ct, rhs)?;
+            assert_eq!(client_key.decrypt(&out_ct), lhs ^ rhs, "row: {row}");
+            let out_ct = server_key.mux_scalar_select(lhs, &rhs_ct, &lhs_ct)?;
+            assert_eq!(client_key.decrypt(&out_ct), lhs && rhs, "row: {row}");
+        }
+        assert_eq!(server_key.stats().pbs_count, 0);
+
+        assert!(matches!(
+            server_key.and_scalar(&client_key.encrypt(true), false)?,
+            Ciphertext::Trivial(false)
+        ));
+        assert_eq!(
+            server_key
+                .or_scalar(&Ciphertext::Placeholder, true)
+                .unwrap_err(),
+            GadgetError::PlaceholderCiphertext
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trivial_lwe_constants() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let trivial_lwe = |value: bool| {
+            let message = if value {
+                BOOLEAN_MESSAGE_TRUE
+            } else {
+                BOOLEAN_MESSAGE_FALSE
+            };
+            server_key.trivial_encrypt_modular(message, BOOLEAN_PLAINTEXT_MODULUS)
+        };
+
+        for (lhs, rhs) in [(false, false), (false, true), (true, false), (true, true)] {
+            let lhs_ct = trivial_lwe(lhs);
+            assert_eq!(client_key.decrypt(&lhs_ct), lhs);
+
+            let out_ct = server_key.and(&lhs_ct, &client_key.encrypt(rhs))?;
+            assert_eq!(client_key.decrypt(&out_ct), lhs && rhs);
+
+            // constants fold into trivial ciphertexts whichever way they were encrypted
+            let out_ct = server_key.xor(&lhs_ct, &server_key.trivial_encrypt(rhs))?;
+            assert!(matches!(out_ct, Ciphertext::Trivial(out) if out == lhs ^ rhs));
+            let out_ct = server_key.ornot(&lhs_ct, &trivial_lwe(rhs))?;
+            assert!(matches!(out_ct, Ciphertext::Trivial(out) if out == lhs || !rhs));
+            let out_ct = server_key.maj3(&lhs_ct, &trivial_lwe(rhs), &client_key.encrypt(rhs))?;
+            assert_eq!(client_key.decrypt(&out_ct), rhs);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_decrypt() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for value in [false, true] {
+            let ct = client_key.encrypt(value);
+            assert_eq!(client_key.try_decrypt(&ct), Ok(value));
+
+            // a fresh ciphertext lies well within the window of its message
+            let (message, distance) = client_key.try_decrypt_modular(&ct).unwrap();
+            assert_eq!(message, value as u32 + 1);
+            assert!(distance < 1. / (4. * BOOLEAN_PLAINTEXT_MODULUS as f64));
+        }
+
+        assert_eq!(
+            client_key
+                .try_decrypt(&server_key.trivial_encrypt_modular(0, BOOLEAN_PLAINTEXT_MODULUS)),
+            Err(DecryptionError::InvalidBooleanMessage(0))
+        );
+        assert_eq!(
+            client_key.try_decrypt(&server_key.trivial_encrypt_modular(1, 7)),
+            Err(DecryptionError::PlaintextModulusMismatch {
+                expected: BOOLEAN_PLAINTEXT_MODULUS,
+                actual: 7
+            })
+        );
+        assert_eq!(
+            client_key.try_decrypt(&Ciphertext::Placeholder),
+            Err(DecryptionError::PlaceholderCiphertext)
+        );
+    }
+
+    #[test]
+    fn test_decrypt_with_margin() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let max_margin = 1. / (2. * BOOLEAN_PLAINTEXT_MODULUS as f64);
+
+        for _ in 0..REPEAT {
+            let b1 = random_boolean();
+            let b2 = random_boolean();
+            let ct = server_key.and(&client_key.encrypt(b1), &client_key.encrypt(b2))?;
+
+            let (message, margin) = client_key.decrypt_with_margin(&ct, BOOLEAN_PLAINTEXT_MODULUS);
+            assert_eq!(message, (b1 && b2) as u32 + 1);
+            assert!(margin > 0. && margin <= max_margin);
+        }
+
+        assert_eq!(
+            client_key.decrypt_with_margin(&Ciphertext::Trivial(true), BOOLEAN_PLAINTEXT_MODULUS),
+            (1, max_margin)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+//! Operator overloading for boolean ciphertexts.
+//!
+//! A [`KeyedCiphertext`] bundles a boolean [`Ciphertext`] with the [`ServerKey`] evaluating its
+//! gates, so that `&a & &b` reads as `server_key.and(&a, &b)`.
+
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::server_key::ServerKey;
+
+/// A boolean [`Ciphertext`] bound to the [`ServerKey`] evaluating the operators applied to it.
+///
+/// # Panics
+///
+/// The operators panic if the gate evaluation fails or if the operands are bound to different
+/// server keys. Use the [`ServerKey`] gates directly to handle errors.
+#[derive(Clone)]
+pub struct KeyedCiphertext<'a> {
+    ciphertext: Ciphertext,
+    server_key: &'a ServerKey,
+}
+
+impl<'a> KeyedCiphertext<'a> {
+    pub fn new(ciphertext: Ciphertext, server_key: &'a ServerKey) -> Self {
+        Self {
+            ciphertext,
+            server_key,
+        }
+    }
+
+    pub fn ciphertext(&self) -> &Ciphertext {
+        &self.ciphertext
+    }
+
+    pub fn server_key(&self) -> &'a ServerKey {
+        self.server_key
+    }
+
+    pub fn into_inner(self) -> Ciphertext {
+        self.ciphertext
+    }
+}
+
+macro_rules! impl_binary_operator {
+    ($trait:ident, $method:ident, $gate:ident) => {
+        impl<'a, 'b> $trait<&'b KeyedCiphertext<'a>> for &'b KeyedCiphertext<'a> {
+            type Output = KeyedCiphertext<'a>;
+
+            fn $method(self, rhs: &'b KeyedCiphertext<'a>) -> KeyedCiphertext<'a> {
+                assert!(
+                    std::ptr::eq(self.server_key, rhs.server_key),
+                    "operands are bound to different server keys"
+                );
+                let ciphertext = self
+                    .server_key
+                    .$gate(&self.ciphertext, &rhs.ciphertext)
+                    .unwrap_or_else(|err| panic!("{} gate failed: {err}", stringify!($gate)));
+                KeyedCiphertext::new(ciphertext, self.server_key)
+            }
+        }
+
+        impl<'a> $trait<KeyedCiphertext<'a>> for KeyedCiphertext<'a> {
+            type Output = KeyedCiphertext<'a>;
+
+            fn $method(self, rhs: KeyedCiphertext<'a>) -> KeyedCiphertext<'a> {
+                (&self).$method(&rhs)
+            }
+        }
+    };
+}
+
+impl_binary_operator!(BitAnd, bitand, and);
+impl_binary_operator!(BitOr, bitor, or);
+impl_binary_operator!(BitXor, bitxor, xor);
+
+impl<'a> Not for &KeyedCiphertext<'a> {
+    type Output = KeyedCiphertext<'a>;
+
+    fn not(self) -> KeyedCiphertext<'a> {
+        let ciphertext = self
+            .server_key
+            .not(&self.ciphertext)
+            .unwrap_or_else(|err| panic!("not gate failed: {err}"));
+        KeyedCiphertext::new(ciphertext, self.server_key)
+    }
+}
+
+impl<'a> Not for KeyedCiphertext<'a> {
+    type Output = KeyedCiphertext<'a>;
+
+    fn not(self) -> KeyedCiphertext<'a> {
+        !&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadget::boolean::BOOLEAN_PARAMETERS;
+    use crate::gadget::gen_keys;
+
+    #[test]
+    fn operators_match_gates() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        for row in 0..4 {
+            let (lhs, rhs) = (row & 1 == 1, row & 2 == 2);
+            let a = KeyedCiphertext::new(client_key.encrypt(lhs), &server_key);
+            let b = KeyedCiphertext::new(client_key.encrypt(rhs), &server_key);
+
+            assert_eq!(client_key.decrypt((&a & &b).ciphertext()), lhs & rhs);
+            assert_eq!(client_key.decrypt((&a | &b).ciphertext()), lhs | rhs);
+            assert_eq!(client_key.decrypt((&a ^ &b).ciphertext()), lhs ^ rhs);
+            assert_eq!(client_key.decrypt((!&a).ciphertext()), !lhs);
+
+            let out = !(a & b.clone()) ^ b;
+            assert_eq!(client_key.decrypt(&out.into_inner()), !(lhs & rhs) ^ rhs);
+        }
+    }
+}
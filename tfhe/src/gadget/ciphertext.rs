@@ -1,14 +1,414 @@
+use crate::conformance::ParameterSetConformant;
+use crate::core_crypto::commons::parameters::{
+    CiphertextModulus, CiphertextModulusLog, LweDimension, LweSize,
+};
 use crate::core_crypto::entities::*;
+use crate::core_crypto::prelude::misc::check_content_respects_mod;
+use crate::core_crypto::prelude::ContiguousEntityContainer;
+use crate::gadget::engine::{decode, modulus_bits, GadgetScalar};
+use crate::gadget::error::GadgetError;
+use crate::gadget::parameters::GadgetParameters;
+use crate::named::Named;
 use serde::{Deserialize, Serialize};
 
+/// The noise of a ciphertext, as a multiple of the variance of a fresh or freshly bootstrapped
+/// ciphertext.
+///
+/// A linear combination with weights `w_i` of ciphertexts with levels `l_i` has level
+/// `sum(w_i^2 * l_i)`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Serialize, Deserialize)]
+pub struct NoiseLevel(pub usize);
+
+impl NoiseLevel {
+    pub const ZERO: Self = Self(0);
+    pub const NOMINAL: Self = Self(1);
+}
+
 /// A structure containing a ciphertext, meant to encrypt a Boolean message.
 ///
 /// It is used to evaluate a Boolean circuits homomorphically.
+///
+/// An encrypted ciphertext carries the plaintext modulus `p` of the p-encoding its message lives
+/// in, so that it can be decrypted without extra information and checked against the encoding
+/// of the gate it is fed to, as well as its [`NoiseLevel`].
+///
+/// Ciphertexts live on a 32 bits torus by default, see [`GadgetScalar`].
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub enum Ciphertext {
-    Encrypted(LweCiphertextOwned<u32>),
+pub enum Ciphertext<Scalar: GadgetScalar = u32> {
+    Encrypted(LweCiphertextOwned<Scalar>, u32, NoiseLevel),
     Trivial(bool),
     Placeholder,
 }
 
-//TODO: add seeded ciphertext
+impl<Scalar: GadgetScalar> Ciphertext<Scalar> {
+    /// Returns the plaintext modulus of an encrypted ciphertext.
+    pub fn plaintext_modulus(&self) -> Option<u32> {
+        match self {
+            Ciphertext::Encrypted(_, plaintext_modulus, _) => Some(*plaintext_modulus),
+            Ciphertext::Trivial(_) | Ciphertext::Placeholder => None,
+        }
+    }
+
+    /// Returns the noise level of the ciphertext, trivial ciphertexts have no noise.
+    pub fn noise_level(&self) -> NoiseLevel {
+        match self {
+            Ciphertext::Encrypted(_, _, noise_level) => *noise_level,
+            Ciphertext::Trivial(_) | Ciphertext::Placeholder => NoiseLevel::ZERO,
+        }
+    }
+
+    /// Returns the message of a trivial ciphertext: the value of a [`Ciphertext::Trivial`] as 0
+    /// or 1, or the message of an LWE ciphertext with a zero mask such as the ones of
+    /// [`ServerKey::trivial_encrypt_modular`](super::server_key::ServerKey::trivial_encrypt_modular).
+    pub fn trivial_message(&self) -> Option<u32> {
+        match self {
+            Ciphertext::Encrypted(lwe, plaintext_modulus, _) => {
+                trivial_lwe_message(lwe, *plaintext_modulus)
+            }
+            Ciphertext::Trivial(value) => Some(*value as u32),
+            Ciphertext::Placeholder => None,
+        }
+    }
+
+    /// Switches the coefficients of an encrypted ciphertext to the modulus `2^log_modulus`, e.g.
+    /// `2^16` or `2^12`, and packs them on `log_modulus` bits each to shrink the outputs of a
+    /// computation before sending them to the client, see [`TransportCiphertext`].
+    ///
+    /// The switch rounds every coefficient, which adds a noise of variance about
+    /// `(1 + n / 2) / (12 * 2^(2 * log_modulus))` on the torus: `log_modulus` must leave room
+    /// for it above the bits of the plaintext modulus. The result can only be decrypted, with
+    /// [`ClientKey::decrypt_transport`](super::client_key::ClientKey::decrypt_transport).
+    ///
+    /// Returns [`GadgetError::UnsupportedTransportModulus`] if `2^log_modulus` is not smaller
+    /// than the ciphertext modulus of an encrypted ciphertext.
+    pub fn compress_for_transport(
+        &self,
+        log_modulus: CiphertextModulusLog,
+    ) -> Result<TransportCiphertext, GadgetError> {
+        match self {
+            Ciphertext::Encrypted(lwe, plaintext_modulus, _) => {
+                if log_modulus.0 == 0 || log_modulus.0 >= modulus_bits(lwe.ciphertext_modulus()) {
+                    return Err(GadgetError::UnsupportedTransportModulus(log_modulus.0));
+                }
+
+                // coefficients modulo a power of two are stored in the most significant bits of
+                // the scalar, the switch keeps the top `log_modulus` bits with rounding
+                let shift = Scalar::BITS - log_modulus.0;
+                let switched = lwe.as_ref().iter().map(|coefficient| {
+                    let coefficient: u128 = (*coefficient).cast_into();
+                    (((coefficient + (1 << (shift - 1))) >> shift) as u64)
+                        & ((1 << log_modulus.0) - 1)
+                });
+
+                Ok(TransportCiphertext::Encrypted(
+                    ModulusSwitchedLweCiphertext {
+                        packed_coefficients: pack_bits(switched, log_modulus.0),
+                        lwe_dimension: lwe.lwe_size().to_lwe_dimension(),
+                        log_modulus,
+                    },
+                    *plaintext_modulus,
+                ))
+            }
+            Ciphertext::Trivial(value) => Ok(TransportCiphertext::Trivial(*value)),
+            Ciphertext::Placeholder => Err(GadgetError::PlaceholderCiphertext),
+        }
+    }
+}
+
+/// Returns the message modulo `plaintext_modulus` of `lwe` if its mask is zero.
+pub(crate) fn trivial_lwe_message<Scalar: GadgetScalar>(
+    lwe: &LweCiphertextOwned<Scalar>,
+    plaintext_modulus: u32,
+) -> Option<u32> {
+    if lwe
+        .get_mask()
+        .as_ref()
+        .iter()
+        .any(|value| *value != Scalar::ZERO)
+    {
+        return None;
+    }
+
+    let ciphertext_modulus = lwe.ciphertext_modulus();
+    let mut body = *lwe.get_body().data;
+    if !ciphertext_modulus.is_native_modulus() {
+        body = body.wrapping_div(ciphertext_modulus.get_power_of_two_scaling_to_native_torus());
+    }
+    Some(decode(body, plaintext_modulus, ciphertext_modulus))
+}
+
+/// A structure containing a compressed ciphertext.
+///
+/// Only the body and the 128 bits seed of the mask are stored, i.e. a single torus element and
+/// a few tens of bytes of metadata instead of the `n + 1` torus elements of a [`Ciphertext`] of
+/// LWE dimension `n`, e.g. 2892 bytes for `n = 722` on a 32 bits torus. It has to be decompressed
+/// before being used in a homomorphic computation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CompressedCiphertext<Scalar: GadgetScalar = u32> {
+    pub(crate) ciphertext: SeededLweCiphertext<Scalar>,
+    pub(crate) plaintext_modulus: u32,
+}
+
+impl<Scalar: GadgetScalar> CompressedCiphertext<Scalar> {
+    pub fn decompress(self) -> Ciphertext<Scalar> {
+        Ciphertext::Encrypted(
+            self.ciphertext.decompress_into_lwe_ciphertext(),
+            self.plaintext_modulus,
+            NoiseLevel::NOMINAL,
+        )
+    }
+}
+
+impl<Scalar: GadgetScalar> From<CompressedCiphertext<Scalar>> for Ciphertext<Scalar> {
+    fn from(value: CompressedCiphertext<Scalar>) -> Self {
+        value.decompress()
+    }
+}
+
+/// Ciphertexts encrypting messages modulo a common plaintext modulus, stored contiguously in a
+/// single LWE ciphertext list, see
+/// [`ClientKey::encrypt_many`](super::client_key::ClientKey::encrypt_many).
+///
+/// The `n + 1` scalars of the ciphertexts are laid out one after the other in one flat vector,
+/// which is read in place by [`CiphertextList::view`] and by the batch APIs of the server key,
+/// e.g. [`ServerKey::evaluate_gate_list`](super::server_key::ServerKey::evaluate_gate_list),
+/// and (de)serialized in a single allocation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CiphertextList<Scalar: GadgetScalar = u32> {
+    pub(crate) lwe_list: LweCiphertextListOwned<Scalar>,
+    pub(crate) plaintext_modulus: u32,
+    pub(crate) noise_level: NoiseLevel,
+}
+
+impl<Scalar: GadgetScalar> CiphertextList<Scalar> {
+    /// Returns the number of ciphertexts in the list.
+    pub fn len(&self) -> usize {
+        self.lwe_list.lwe_ciphertext_count().0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the plaintext modulus shared by the ciphertexts of the list.
+    pub fn plaintext_modulus(&self) -> u32 {
+        self.plaintext_modulus
+    }
+
+    /// Returns the noise level of the ciphertexts of the list.
+    pub fn noise_level(&self) -> NoiseLevel {
+        self.noise_level
+    }
+
+    /// Returns the LWE ciphertexts of the list.
+    pub fn lwe_list(&self) -> &LweCiphertextListOwned<Scalar> {
+        &self.lwe_list
+    }
+
+    /// Returns the LWE ciphertext of the `index`-th ciphertext without copying it, `None` if it
+    /// is out of bounds.
+    pub fn view(&self, index: usize) -> Option<LweCiphertextView<'_, Scalar>> {
+        (index < self.len()).then(|| self.lwe_list.get(index))
+    }
+
+    /// Iterates over the LWE ciphertexts of the list without copying them.
+    pub fn views(&self) -> impl Iterator<Item = LweCiphertextView<'_, Scalar>> + '_ {
+        self.lwe_list.iter()
+    }
+
+    /// Returns a copy of the `index`-th ciphertext, `None` if it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<Ciphertext<Scalar>> {
+        (index < self.len()).then(|| self.ciphertext(self.lwe_list.get(index)))
+    }
+
+    /// Iterates over copies of the ciphertexts of the list.
+    pub fn iter(&self) -> impl Iterator<Item = Ciphertext<Scalar>> + '_ {
+        self.lwe_list.iter().map(|lwe| self.ciphertext(lwe))
+    }
+
+    /// Gathers `lwes`, ciphertexts of `lwe_size` scalars, in a list.
+    pub(crate) fn from_lwe_ciphertexts<'a>(
+        lwes: impl ExactSizeIterator<Item = &'a LweCiphertextOwned<Scalar>>,
+        lwe_size: LweSize,
+        ciphertext_modulus: CiphertextModulus<Scalar>,
+        plaintext_modulus: u32,
+        noise_level: NoiseLevel,
+    ) -> Self {
+        let mut data = Vec::with_capacity(lwes.len() * lwe_size.0);
+        for lwe in lwes {
+            data.extend_from_slice(lwe.as_ref());
+        }
+
+        Self {
+            lwe_list: LweCiphertextListOwned::from_container(data, lwe_size, ciphertext_modulus),
+            plaintext_modulus,
+            noise_level,
+        }
+    }
+
+    fn ciphertext(&self, lwe: LweCiphertextView<'_, Scalar>) -> Ciphertext<Scalar> {
+        Ciphertext::Encrypted(
+            LweCiphertextOwned::from_container(lwe.as_ref().to_vec(), lwe.ciphertext_modulus()),
+            self.plaintext_modulus,
+            self.noise_level,
+        )
+    }
+}
+
+impl<Scalar: GadgetScalar> From<CiphertextList<Scalar>> for Vec<Ciphertext<Scalar>> {
+    fn from(list: CiphertextList<Scalar>) -> Self {
+        list.iter().collect()
+    }
+}
+
+/// An LWE ciphertext whose mask and body were switched to the modulus `2^log_modulus` and
+/// packed on `log_modulus` bits each, see [`Ciphertext::compress_for_transport`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModulusSwitchedLweCiphertext {
+    pub(crate) packed_coefficients: Vec<u64>,
+    pub(crate) lwe_dimension: LweDimension,
+    pub(crate) log_modulus: CiphertextModulusLog,
+}
+
+impl ModulusSwitchedLweCiphertext {
+    pub fn lwe_dimension(&self) -> LweDimension {
+        self.lwe_dimension
+    }
+
+    pub fn log_modulus(&self) -> CiphertextModulusLog {
+        self.log_modulus
+    }
+
+    /// Returns the mask followed by the body, modulo `2^log_modulus`.
+    pub(crate) fn coefficients(&self) -> Vec<u64> {
+        unpack_bits(
+            &self.packed_coefficients,
+            self.log_modulus.0,
+            self.lwe_dimension.to_lwe_size().0,
+        )
+    }
+}
+
+/// A ciphertext shrunk for transmission to the client, which can only be decrypted, see
+/// [`Ciphertext::compress_for_transport`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransportCiphertext {
+    Encrypted(ModulusSwitchedLweCiphertext, u32),
+    Trivial(bool),
+}
+
+/// Concatenates the `bits` least significant bits of every value.
+fn pack_bits(values: impl Iterator<Item = u64>, bits: usize) -> Vec<u64> {
+    let mut packed = vec![];
+    for (index, value) in values.enumerate() {
+        let (word, offset) = (index * bits / 64, index * bits % 64);
+        if word == packed.len() {
+            packed.push(0);
+        }
+        packed[word] |= value << offset;
+        if offset + bits > 64 {
+            packed.push(value >> (64 - offset));
+        }
+    }
+    packed
+}
+
+/// Splits `packed` back in `count` values of `bits` bits, see [`pack_bits`].
+fn unpack_bits(packed: &[u64], bits: usize, count: usize) -> Vec<u64> {
+    let mask = (1u64 << bits) - 1;
+    (0..count)
+        .map(|index| {
+            let (word, offset) = (index * bits / 64, index * bits % 64);
+            let mut value = packed[word] >> offset;
+            if offset + bits > 64 {
+                value |= packed[word + 1] << (64 - offset);
+            }
+            value & mask
+        })
+        .collect()
+}
+
+/// Returns the expected shape of the LWE ciphertexts under `parameters`, `None` if their
+/// ciphertext modulus is not supported over `Scalar`.
+fn lwe_ciphertext_parameters<Scalar: GadgetScalar>(
+    parameters: &GadgetParameters,
+) -> Option<LweCiphertextParameters<Scalar>> {
+    Some(LweCiphertextParameters {
+        lwe_dim: parameters.encryption_lwe_dimension(),
+        ct_modulus: parameters.scalar_ciphertext_modulus::<Scalar>().ok()?,
+    })
+}
+
+/// Checks that a ciphertext received from an untrusted party, e.g. after deserialization, has
+/// the LWE dimension and ciphertext modulus of `parameters` and a valid plaintext modulus.
+/// Placeholders are never conformant.
+impl<Scalar: GadgetScalar> ParameterSetConformant for Ciphertext<Scalar> {
+    type ParameterSet = GadgetParameters;
+
+    fn is_conformant(&self, parameters: &GadgetParameters) -> bool {
+        match self {
+            Ciphertext::Encrypted(lwe, plaintext_modulus, _) => {
+                *plaintext_modulus >= 2
+                    && lwe_ciphertext_parameters(parameters)
+                        .is_some_and(|lwe_parameters| lwe.is_conformant(&lwe_parameters))
+            }
+            Ciphertext::Trivial(_) => true,
+            Ciphertext::Placeholder => false,
+        }
+    }
+}
+
+impl<Scalar: GadgetScalar> ParameterSetConformant for CompressedCiphertext<Scalar> {
+    type ParameterSet = GadgetParameters;
+
+    fn is_conformant(&self, parameters: &GadgetParameters) -> bool {
+        self.plaintext_modulus >= 2
+            && lwe_ciphertext_parameters(parameters)
+                .is_some_and(|lwe_parameters| self.ciphertext.is_conformant(&lwe_parameters))
+    }
+}
+
+/// Checks that a list received from an untrusted party holds whole LWE ciphertexts with the LWE
+/// dimension and ciphertext modulus of `parameters`, and has a valid plaintext modulus.
+impl<Scalar: GadgetScalar> ParameterSetConformant for CiphertextList<Scalar> {
+    type ParameterSet = GadgetParameters;
+
+    fn is_conformant(&self, parameters: &GadgetParameters) -> bool {
+        let lwe_size = self.lwe_list.lwe_size();
+        self.plaintext_modulus >= 2
+            && lwe_ciphertext_parameters::<Scalar>(parameters).is_some_and(|lwe_parameters| {
+                lwe_size == lwe_parameters.lwe_dim.to_lwe_size()
+                    && self.lwe_list.ciphertext_modulus() == lwe_parameters.ct_modulus
+            })
+            && self.lwe_list.as_ref().len() % lwe_size.0 == 0
+            && check_content_respects_mod(&self.lwe_list, self.lwe_list.ciphertext_modulus())
+    }
+}
+
+impl Named for Ciphertext<u32> {
+    const NAME: &'static str = "gadget::Ciphertext";
+}
+
+impl Named for Ciphertext<u64> {
+    const NAME: &'static str = "gadget::Ciphertext64";
+}
+
+impl Named for CompressedCiphertext<u32> {
+    const NAME: &'static str = "gadget::CompressedCiphertext";
+}
+
+impl Named for CompressedCiphertext<u64> {
+    const NAME: &'static str = "gadget::CompressedCiphertext64";
+}
+
+impl Named for CiphertextList<u32> {
+    const NAME: &'static str = "gadget::CiphertextList";
+}
+
+impl Named for CiphertextList<u64> {
+    const NAME: &'static str = "gadget::CiphertextList64";
+}
+
+impl Named for TransportCiphertext {
+    const NAME: &'static str = "gadget::TransportCiphertext";
+}
@@ -0,0 +1,244 @@
+//! Parser for combinational netlists in the Berkeley Logic Interchange Format (BLIF).
+//!
+//! Only the first `.model` of the file is read. It may use the `.inputs`, `.outputs` and `.names`
+//! constructs, every `.names` table becoming a [`Gate`]. Sequential elements (`.latch`) and
+//! hierarchical or library constructs (`.subckt`, `.gate`) are rejected.
+
+use super::{Circuit, Gate};
+use crate::gadget::error::GadgetError;
+
+/// Largest number of inputs of a `.names` table, its truth table must fit in a `u128`.
+const MAX_NAMES_INPUTS: usize = 7;
+
+/// A `.names` table being parsed.
+struct Names {
+    line: usize,
+    signals: Vec<String>,
+    cubes: Vec<(String, char)>,
+}
+
+/// Parses the BLIF netlist `source` into a [`Circuit`].
+pub fn parse(source: &str) -> Result<Circuit, GadgetError> {
+    let mut name = String::new();
+    let mut inputs = vec![];
+    let mut outputs = vec![];
+    let mut gates = vec![];
+    let mut names: Option<Names> = None;
+
+    for (line, tokens) in logical_lines(source) {
+        let error = |reason: String| GadgetError::CircuitParse { line, reason };
+
+        if !tokens[0].starts_with('.') {
+            // a row of the cover of the current .names table
+            let names = names
+                .as_mut()
+                .ok_or_else(|| error(format!("unexpected token {}", tokens[0])))?;
+            let cube = match (names.signals.len(), tokens.as_slice()) {
+                (1, [output]) => (String::new(), output.as_str()),
+                (_, [input, output]) => (input.clone(), output.as_str()),
+                _ => return Err(error("malformed cover row".to_string())),
+            };
+            let output = match cube.1 {
+                "0" => '0',
+                "1" => '1',
+                _ => return Err(error(format!("invalid cover output {}", cube.1))),
+            };
+            names.cubes.push((cube.0, output));
+            continue;
+        }
+
+        if let Some(names) = names.take() {
+            gates.push(names_to_gate(names)?);
+        }
+
+        match tokens[0].as_str() {
+            ".model" => {
+                if !name.is_empty() {
+                    // only the first model is read
+                    break;
+                }
+                name = tokens.get(1).cloned().unwrap_or_default();
+            }
+            ".inputs" => inputs.extend(tokens[1..].iter().cloned()),
+            ".outputs" => outputs.extend(tokens[1..].iter().cloned()),
+            ".names" => {
+                if tokens.len() < 2 {
+                    return Err(error(".names without output signal".to_string()));
+                }
+                if tokens.len() - 2 > MAX_NAMES_INPUTS {
+                    return Err(error(format!(
+                        ".names with more than {MAX_NAMES_INPUTS} inputs"
+                    )));
+                }
+                names = Some(Names {
+                    line,
+                    signals: tokens[1..].to_vec(),
+                    cubes: vec![],
+                });
+            }
+            ".end" => break,
+            directive => {
+                return Err(error(format!("unsupported construct {directive}")));
+            }
+        }
+    }
+
+    if let Some(names) = names.take() {
+        gates.push(names_to_gate(names)?);
+    }
+
+    Circuit::new(name, inputs, outputs, gates)
+}
+
+/// Computes the truth table of a `.names` table from its cover.
+fn names_to_gate(names: Names) -> Result<Gate, GadgetError> {
+    let Names {
+        line,
+        mut signals,
+        cubes,
+    } = names;
+    let output = signals.pop().unwrap();
+    let input_count = signals.len();
+
+    // the cover lists either the on-set or the off-set of the function
+    let polarity = cubes.first().map(|(_, output)| *output).unwrap_or('1');
+    let mut tt_value = 0u128;
+    for (cube, cube_output) in cubes.iter() {
+        if *cube_output != polarity {
+            return Err(GadgetError::CircuitParse {
+                line,
+                reason: format!("cover of {output} mixes on-set and off-set rows"),
+            });
+        }
+        if cube.len() != input_count {
+            return Err(GadgetError::CircuitParse {
+                line,
+                reason: format!("cover row {cube} does not match {input_count} inputs"),
+            });
+        }
+
+        for row in 0..(1u128 << input_count) {
+            let mut matches = true;
+            for (pin, literal) in cube.chars().enumerate() {
+                let bit = (row >> pin) & 1;
+                matches &= match literal {
+                    '0' => bit == 0,
+                    '1' => bit == 1,
+                    '-' => true,
+                    _ => {
+                        return Err(GadgetError::CircuitParse {
+                            line,
+                            reason: format!("invalid literal {literal} in cover of {output}"),
+                        })
+                    }
+                };
+            }
+            if matches {
+                tt_value |= 1 << row;
+            }
+        }
+    }
+
+    if polarity == '0' {
+        let row_mask = if input_count == MAX_NAMES_INPUTS {
+            u128::MAX
+        } else {
+            (1u128 << (1 << input_count)) - 1
+        };
+        tt_value = !tt_value & row_mask;
+    }
+
+    Ok(Gate::new(signals, output, tt_value))
+}
+
+/// Splits `source` into tokenized logical lines, with their 1-based line number, dropping
+/// comments and joining lines continued with a trailing `\`.
+fn logical_lines(source: &str) -> Vec<(usize, Vec<String>)> {
+    let mut lines = vec![];
+    let mut pending: Option<(usize, Vec<String>)> = None;
+
+    for (index, raw_line) in source.lines().enumerate() {
+        let content = raw_line.split('#').next().unwrap();
+        let (content, continued) = match content.trim_end().strip_suffix('\\') {
+            Some(content) => (content, true),
+            None => (content, false),
+        };
+
+        let (line, mut tokens) = pending.take().unwrap_or((index + 1, vec![]));
+        tokens.extend(content.split_whitespace().map(str::to_string));
+
+        if continued {
+            pending = Some((line, tokens));
+        } else if !tokens.is_empty() {
+            lines.push((line, tokens));
+        }
+    }
+
+    if let Some((line, tokens)) = pending {
+        if !tokens.is_empty() {
+            lines.push((line, tokens));
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_names_tables() {
+        let source = "\
+.model gates
+.inputs a b \\
+    c
+.outputs and nor one
+.names a b c and # 3 input and
+111 1
+.names a b nor
+00 1
+.names one
+1
+.end
+";
+        let circuit = parse(source).unwrap();
+        assert_eq!(circuit.name(), "gates");
+        assert_eq!(circuit.inputs(), &["a", "b", "c"]);
+
+        let tt_values: Vec<(&str, u128)> = circuit
+            .gates()
+            .iter()
+            .map(|gate| (gate.output(), gate.tt_value()))
+            .collect();
+        assert_eq!(tt_values, vec![("and", 0x80), ("nor", 0b0001), ("one", 1)]);
+
+        // off-set cover of a nand
+        let circuit = parse(".model nand\n.inputs a b\n.outputs y\n.names a b y\n11 0\n").unwrap();
+        assert_eq!(circuit.gates()[0].tt_value(), 0b0111);
+    }
+
+    #[test]
+    fn parse_reports_errors() {
+        let latch = ".model m\n.inputs a\n.outputs q\n.latch a q re clk 0\n.end\n";
+        assert_eq!(
+            parse(latch).unwrap_err(),
+            GadgetError::CircuitParse {
+                line: 4,
+                reason: "unsupported construct .latch".to_string()
+            }
+        );
+
+        let malformed = ".model m\n.inputs a b\n.outputs y\n.names a b y\n1 1\n";
+        assert!(matches!(
+            parse(malformed),
+            Err(GadgetError::CircuitParse { line: 4, .. })
+        ));
+
+        let undriven = ".model m\n.inputs a\n.outputs y\n.names a b y\n11 1\n";
+        assert!(matches!(
+            parse(undriven),
+            Err(GadgetError::InvalidCircuit(_))
+        ));
+    }
+}
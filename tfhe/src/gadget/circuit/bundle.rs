@@ -0,0 +1,321 @@
+//! Named inputs and outputs of circuit evaluations.
+//!
+//! Netlists name the bit `i` of a multi-bit bus `x` as the signal `x[i]`, see
+//! [`yosys`](super::yosys). An [`InputBundle`] binds ciphertexts to single wires or to whole
+//! buses, and is checked against the inputs of a circuit before evaluating it: every input must
+//! be bound, and every bound bit must be an input. The outputs are returned as an
+//! [`OutputBundle`], indexed by wire or bus name.
+//!
+//! ```rust,ignore
+//! let mut inputs = InputBundle::new();
+//! inputs.bind_bus("x", x_bits).bind_bus("y", y_bits).bind_constant("cin", false);
+//! let outputs = server_key.evaluate_circuit_bundle(&circuit, &inputs)?;
+//! let sum = &outputs["sum"][0..8];
+//! ```
+
+use super::Circuit;
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::error::GadgetError;
+use crate::gadget::server_key::ServerKey;
+use std::collections::{HashMap, HashSet};
+use std::ops::Index;
+
+/// Ciphertexts bound to the named input wires and buses of a circuit.
+#[derive(Clone, Default)]
+pub struct InputBundle {
+    /// Bits of each bound bus, a single wire being a bus of width 1
+    buses: HashMap<String, Vec<Ciphertext>>,
+}
+
+impl InputBundle {
+    pub fn new() -> InputBundle {
+        InputBundle::default()
+    }
+
+    /// Binds `ct` to the input wire `name`, replacing any previous binding.
+    pub fn bind(&mut self, name: &str, ct: Ciphertext) -> &mut Self {
+        self.bind_bus(name, vec![ct])
+    }
+
+    /// Binds the trivial encryption of `value` to the input wire `name`.
+    pub fn bind_constant(&mut self, name: &str, value: bool) -> &mut Self {
+        self.bind(name, Ciphertext::Trivial(value))
+    }
+
+    /// Binds `bits` to the input bus `name`, bit `i` driving the wire `name[i]`.
+    pub fn bind_bus(&mut self, name: &str, bits: Vec<Ciphertext>) -> &mut Self {
+        self.buses.insert(name.to_string(), bits);
+        self
+    }
+
+    /// Binds the trivial encryptions of the `width` least significant bits of `value` to the
+    /// input bus `name`.
+    pub fn bind_bus_constant(&mut self, name: &str, value: u128, width: usize) -> &mut Self {
+        let bits = (0..width)
+            .map(|bit| Ciphertext::Trivial(bit < 128 && (value >> bit) & 1 == 1))
+            .collect();
+        self.bind_bus(name, bits)
+    }
+
+    /// Returns the bits bound to the bus or wire `name`.
+    pub fn get(&self, name: &str) -> Option<&[Ciphertext]> {
+        self.buses.get(name).map(Vec::as_slice)
+    }
+
+    /// Returns the ciphertext of each input wire of `circuit`.
+    ///
+    /// Fails with [`GadgetError::MissingCircuitInput`] if an input wire is not bound, and with
+    /// [`GadgetError::UnexpectedCircuitInput`] if a bound wire or bus bit is not an input of
+    /// `circuit`, e.g. because the bus is wider than the one of the circuit.
+    pub fn resolve(&self, circuit: &Circuit) -> Result<HashMap<String, Ciphertext>, GadgetError> {
+        let mut used = HashSet::new();
+        let mut inputs = HashMap::with_capacity(circuit.inputs.len());
+        for input in circuit.inputs.iter() {
+            let (bus, index) = match self.buses.get(input.as_str()) {
+                Some(bits) if bits.len() == 1 => (input.as_str(), 0),
+                _ => split_bus_bit(input)
+                    .ok_or_else(|| GadgetError::MissingCircuitInput(input.clone()))?,
+            };
+            let ct = self
+                .buses
+                .get(bus)
+                .and_then(|bits| bits.get(index))
+                .ok_or_else(|| GadgetError::MissingCircuitInput(input.clone()))?;
+            used.insert((bus, index));
+            inputs.insert(input.clone(), ct.clone());
+        }
+
+        for (bus, bits) in self.buses.iter() {
+            if let Some(index) =
+                (0..bits.len()).find(|index| !used.contains(&(bus.as_str(), *index)))
+            {
+                let name = if bits.len() == 1 {
+                    bus.clone()
+                } else {
+                    format!("{bus}[{index}]")
+                };
+                return Err(GadgetError::UnexpectedCircuitInput(name));
+            }
+        }
+
+        Ok(inputs)
+    }
+}
+
+impl Index<&str> for InputBundle {
+    type Output = [Ciphertext];
+
+    /// # Panics
+    ///
+    /// Panics if nothing is bound to `name`.
+    fn index(&self, name: &str) -> &[Ciphertext] {
+        self.get(name)
+            .unwrap_or_else(|| panic!("no input bound to {name}"))
+    }
+}
+
+/// Ciphertexts of the named output wires and buses of an evaluated circuit.
+#[derive(Clone, Default)]
+pub struct OutputBundle {
+    wires: HashMap<String, Ciphertext>,
+    /// Bits of each output bus whose wires `name[0]`, `name[1]`, ... are all outputs
+    buses: HashMap<String, Vec<Ciphertext>>,
+}
+
+impl OutputBundle {
+    /// Groups the named `outputs` of a circuit into buses.
+    pub fn new(outputs: HashMap<String, Ciphertext>) -> OutputBundle {
+        let mut bus_bits: HashMap<&str, Vec<Option<&Ciphertext>>> = HashMap::new();
+        for (wire, ct) in outputs.iter() {
+            if let Some((bus, index)) = split_bus_bit(wire) {
+                let bits = bus_bits.entry(bus).or_default();
+                if bits.len() <= index {
+                    bits.resize(index + 1, None);
+                }
+                bits[index] = Some(ct);
+            }
+        }
+
+        // buses with missing bits are only reachable through their wires
+        let buses = bus_bits
+            .into_iter()
+            .filter_map(|(bus, bits)| {
+                let bits = bits.into_iter().collect::<Option<Vec<_>>>()?;
+                Some((bus.to_string(), bits.into_iter().cloned().collect()))
+            })
+            .collect();
+
+        OutputBundle {
+            wires: outputs,
+            buses,
+        }
+    }
+
+    /// Returns the ciphertext of the output wire `name`, e.g. `sum` or `sum[3]`.
+    pub fn wire(&self, name: &str) -> Option<&Ciphertext> {
+        self.wires.get(name)
+    }
+
+    /// Returns the bits of the output bus `name`, a single wire being a bus of width 1.
+    pub fn get(&self, name: &str) -> Option<&[Ciphertext]> {
+        self.buses
+            .get(name)
+            .map(Vec::as_slice)
+            .or_else(|| self.wires.get(name).map(std::slice::from_ref))
+    }
+
+    /// Returns the ciphertexts of every output wire.
+    pub fn into_wires(self) -> HashMap<String, Ciphertext> {
+        self.wires
+    }
+}
+
+impl Index<&str> for OutputBundle {
+    type Output = [Ciphertext];
+
+    /// # Panics
+    ///
+    /// Panics if the circuit has no output wire or bus `name`.
+    fn index(&self, name: &str) -> &[Ciphertext] {
+        self.get(name)
+            .unwrap_or_else(|| panic!("no output named {name}"))
+    }
+}
+
+/// Splits the wire `bus[index]` into its bus name and bit index.
+fn split_bus_bit(wire: &str) -> Option<(&str, usize)> {
+    let (bus, index) = wire.strip_suffix(']')?.rsplit_once('[')?;
+    Some((bus, index.parse().ok()?))
+}
+
+impl ServerKey {
+    /// Evaluates `circuit` on the ciphertexts bound by `inputs`, see
+    /// [`ServerKey::evaluate_circuit`] and [`InputBundle::resolve`].
+    pub fn evaluate_circuit_bundle(
+        &self,
+        circuit: &Circuit,
+        inputs: &InputBundle,
+    ) -> Result<OutputBundle, GadgetError> {
+        let outputs = self.evaluate_circuit(circuit, &inputs.resolve(circuit)?)?;
+        Ok(OutputBundle::new(outputs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean::engine::WithThreadLocalEngine;
+    use crate::gadget::circuit::Gate;
+    use crate::gadget::client_key::ClientKey;
+    use crate::gadget::engine::GadgetEngine;
+    use crate::gadget::gen_keys;
+    use crate::gadget::parameters::PLAINTEXT_3_BITS_PARAMETERS;
+
+    /// Adder of two 2 bits buses `x` and `y` with a carry in, driving the 3 bits bus `s`.
+    fn adder() -> Circuit {
+        let sum = 0b1001_0110;
+        let carry = 0b1110_1000;
+        let gates = vec![
+            Gate::new(
+                vec!["x[0]".into(), "y[0]".into(), "cin".into()],
+                "s[0]".into(),
+                sum,
+            ),
+            Gate::new(
+                vec!["x[0]".into(), "y[0]".into(), "cin".into()],
+                "c".into(),
+                carry,
+            ),
+            Gate::new(
+                vec!["x[1]".into(), "y[1]".into(), "c".into()],
+                "s[1]".into(),
+                sum,
+            ),
+            Gate::new(
+                vec!["x[1]".into(), "y[1]".into(), "c".into()],
+                "s[2]".into(),
+                carry,
+            ),
+        ];
+        Circuit::new(
+            "adder".to_string(),
+            ["x[0]", "x[1]", "y[0]", "y[1]", "cin"]
+                .map(String::from)
+                .to_vec(),
+            ["s[0]", "s[1]", "s[2]"].map(String::from).to_vec(),
+            gates,
+        )
+        .unwrap()
+    }
+
+    fn encrypt_bus(client_key: &ClientKey, value: u32, width: usize, p: u32) -> Vec<Ciphertext> {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            (0..width)
+                .map(|bit| engine.encrypt((value >> bit) & 1, client_key, p))
+                .collect()
+        })
+    }
+
+    #[test]
+    fn resolve_binds_buses() {
+        let circuit = adder();
+        let mut inputs = InputBundle::new();
+        inputs
+            .bind_bus_constant("x", 0b01, 2)
+            .bind_bus_constant("y", 0b11, 2);
+        assert_eq!(
+            inputs.resolve(&circuit).unwrap_err(),
+            GadgetError::MissingCircuitInput("cin".to_string())
+        );
+
+        inputs.bind_constant("cin", true);
+        let resolved = inputs.resolve(&circuit).unwrap();
+        assert_eq!(resolved.len(), 5);
+        assert!(matches!(resolved["x[0]"], Ciphertext::Trivial(true)));
+        assert!(matches!(resolved["x[1]"], Ciphertext::Trivial(false)));
+        assert_eq!(inputs["y"].len(), 2);
+
+        // the bus is wider than the one of the circuit
+        inputs.bind_bus_constant("y", 0b011, 3);
+        assert_eq!(
+            inputs.resolve(&circuit).unwrap_err(),
+            GadgetError::UnexpectedCircuitInput("y[2]".to_string())
+        );
+
+        inputs
+            .bind_bus_constant("y", 0b11, 2)
+            .bind_constant("z", false);
+        assert_eq!(
+            inputs.resolve(&circuit).unwrap_err(),
+            GadgetError::UnexpectedCircuitInput("z".to_string())
+        );
+    }
+
+    #[test]
+    fn evaluate_circuit_bundle() {
+        let circuit = adder();
+        let p = circuit.plaintext_modulus();
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+
+        for (x, y, cin) in [(0, 0, false), (1, 2, true), (3, 3, true), (2, 3, false)] {
+            let mut inputs = InputBundle::new();
+            inputs
+                .bind_bus("x", encrypt_bus(&client_key, x, 2, p))
+                .bind_bus("y", encrypt_bus(&client_key, y, 2, p))
+                .bind_constant("cin", cin);
+            let outputs = server_key
+                .evaluate_circuit_bundle(&circuit, &inputs)
+                .unwrap();
+
+            let s = GadgetEngine::with_thread_local_mut(|engine| {
+                outputs["s"][0..3]
+                    .iter()
+                    .enumerate()
+                    .fold(0, |s, (bit, ct)| s | engine.decrypt(ct, &client_key) << bit)
+            });
+            assert_eq!(s, x + y + cin as u32, "{x} + {y} + {cin}");
+            assert!(outputs.wire("s[2]").is_some());
+            assert!(outputs.get("c").is_none());
+        }
+    }
+}
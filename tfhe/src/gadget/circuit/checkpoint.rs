@@ -0,0 +1,112 @@
+//! Checkpoints of partially evaluated circuits.
+
+use super::Circuit;
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::error::GadgetError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// State of a circuit evaluation after a number of completed levels.
+///
+/// A checkpoint holds the ciphertexts of every signal evaluated so far. It can be serialized,
+/// e.g. with bincode, and evaluation resumed later with [`ServerKey::resume_circuit`], possibly
+/// from another process.
+///
+/// [`ServerKey::resume_circuit`]: crate::gadget::server_key::ServerKey::resume_circuit
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EvaluationCheckpoint {
+    pub(crate) circuit_name: String,
+    pub(crate) gate_count: usize,
+    pub(crate) completed_levels: usize,
+    /// Time spent evaluating the completed levels
+    pub(crate) elapsed: Duration,
+    pub(crate) signals: HashMap<String, Ciphertext>,
+}
+
+impl EvaluationCheckpoint {
+    /// Starts the evaluation of `circuit` on the ciphertexts of its named `inputs`.
+    pub fn new(
+        circuit: &Circuit,
+        inputs: &HashMap<String, Ciphertext>,
+    ) -> Result<EvaluationCheckpoint, GadgetError> {
+        let mut signals = HashMap::new();
+        for input in circuit.inputs.iter() {
+            let ct = inputs
+                .get(input)
+                .ok_or_else(|| GadgetError::MissingCircuitInput(input.clone()))?;
+            signals.insert(input.clone(), ct.clone());
+        }
+
+        Ok(EvaluationCheckpoint {
+            circuit_name: circuit.name.clone(),
+            gate_count: circuit.gates.len(),
+            completed_levels: 0,
+            elapsed: Duration::ZERO,
+            signals,
+        })
+    }
+
+    /// Returns the number of levels of the circuit already evaluated.
+    pub fn completed_levels(&self) -> usize {
+        self.completed_levels
+    }
+
+    pub fn is_complete(&self, circuit: &Circuit) -> bool {
+        self.completed_levels == circuit.depth()
+    }
+
+    /// Returns the ciphertexts of the named outputs of a completely evaluated `circuit`.
+    pub fn outputs(&self, circuit: &Circuit) -> Result<HashMap<String, Ciphertext>, GadgetError> {
+        self.check_circuit(circuit)?;
+        if !self.is_complete(circuit) {
+            return Err(GadgetError::InvalidCircuit(format!(
+                "evaluation of {} stopped after {} of {} levels",
+                circuit.name,
+                self.completed_levels,
+                circuit.depth()
+            )));
+        }
+
+        Ok(circuit
+            .outputs
+            .iter()
+            .map(|output| (output.clone(), self.signals[output].clone()))
+            .collect())
+    }
+
+    /// Checks that the checkpoint was created for `circuit` and holds the ciphertexts of its
+    /// inputs and of the outputs of the gates of the completed levels, which a deserialized
+    /// checkpoint may lack.
+    pub(crate) fn check_circuit(&self, circuit: &Circuit) -> Result<(), GadgetError> {
+        if self.circuit_name != circuit.name
+            || self.gate_count != circuit.gates.len()
+            || self.completed_levels > circuit.depth()
+        {
+            return Err(GadgetError::InvalidCircuit(format!(
+                "checkpoint of circuit {} does not match circuit {}",
+                self.circuit_name, circuit.name
+            )));
+        }
+
+        let evaluated_gates = match self.completed_levels {
+            0 => 0,
+            completed_levels => circuit.level_ends[completed_levels - 1],
+        };
+        let evaluated_signals = circuit.inputs.iter().chain(
+            circuit.gates[..evaluated_gates]
+                .iter()
+                .map(|gate| &gate.output),
+        );
+        for signal in evaluated_signals {
+            if !self.signals.contains_key(signal) {
+                return Err(GadgetError::InvalidCircuit(format!(
+                    "checkpoint of circuit {} lacks signal {signal}",
+                    circuit.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
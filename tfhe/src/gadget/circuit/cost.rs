@@ -0,0 +1,201 @@
+//! Cost estimation of circuit evaluations.
+//!
+//! [`Circuit::cost_estimate`] counts the operations an evaluation performs without any key, and
+//! [`CostReport::estimated_time`] turns them into a wall time from [`OperationTimings`] measured
+//! on the evaluating machine, e.g. to compare synthesis strategies before a long evaluation.
+
+use super::Circuit;
+use crate::boolean::engine::WithThreadLocalEngine;
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::encoding::Encoding;
+use crate::gadget::engine::GadgetEngine;
+use crate::gadget::parameters::GadgetParameters;
+use crate::gadget::server_key::ServerKey;
+use std::time::{Duration, Instant};
+
+/// Duration of the operations of a gate evaluation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OperationTimings {
+    /// Duration of a bootstrap, including its keyswitch
+    pub bootstrap: Duration,
+    /// Duration of the weighted sum of the inputs of a gate
+    pub linear_op: Duration,
+}
+
+impl OperationTimings {
+    /// Measures the average duration of `samples` bootstraps and weighted sums of 2 inputs
+    /// with `server_key`, on the current thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `samples` is 0.
+    pub fn measure(client_key: &ClientKey, server_key: &ServerKey, samples: usize) -> Self {
+        assert!(samples > 0, "at least one sample is needed");
+
+        let and = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let ct = GadgetEngine::with_thread_local_mut(|engine| engine.encrypt(1, client_key, 3));
+        let inputs = [&ct, &ct];
+
+        // computes the lookup table out of the measurements
+        let (sum, noise_level) =
+            GadgetEngine::linear_combination(server_key, &and, &inputs).unwrap();
+        let sum = Ciphertext::Encrypted(sum, 3, noise_level);
+        server_key.bootstrap(sum.clone(), &and).unwrap();
+
+        let start = Instant::now();
+        for _ in 0..samples {
+            GadgetEngine::linear_combination(server_key, &and, &inputs).unwrap();
+        }
+        let linear_op = start.elapsed() / samples as u32;
+
+        let start = Instant::now();
+        for _ in 0..samples {
+            server_key.bootstrap(sum.clone(), &and).unwrap();
+        }
+        let bootstrap = start.elapsed() / samples as u32;
+
+        Self {
+            bootstrap,
+            linear_op,
+        }
+    }
+}
+
+/// Operations performed by the evaluation of a circuit, see [`Circuit::cost_estimate`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CostReport {
+    pub bootstraps: usize,
+    pub keyswitches: usize,
+    pub linear_ops: usize,
+    /// Number of levels, evaluated one after another
+    pub depth: usize,
+    /// Number of bootstraps of each level, evaluated in parallel
+    pub level_bootstraps: Vec<usize>,
+    /// Whether the parameters support the plaintext modulus of the circuit, see
+    /// [`GadgetParameters::max_supported_p`]
+    pub supported: bool,
+    /// Upper bound on the probability that one of the bootstraps outputs a wrong value
+    pub failure_probability: f64,
+}
+
+impl CostReport {
+    /// Estimates the wall time of the evaluation with `threads` threads, the bootstraps of each
+    /// level being split evenly between the threads.
+    pub fn estimated_time(&self, timings: &OperationTimings, threads: usize) -> Duration {
+        let threads = threads.max(1);
+        let gate_time = timings.bootstrap + timings.linear_op;
+        self.level_bootstraps
+            .iter()
+            .map(|bootstraps| gate_time * ((bootstraps + threads - 1) / threads) as u32)
+            .sum()
+    }
+}
+
+impl Circuit {
+    /// Counts the operations performed by the evaluation of the circuit under `parameters`.
+    ///
+    /// Every bootstrapped gate performs a weighted sum of its inputs, a blind rotation and a
+    /// keyswitch, constants and duplicated gates perform none.
+    pub fn cost_estimate(&self, parameters: &GadgetParameters) -> CostReport {
+        let starts = std::iter::once(0).chain(self.level_ends.iter().copied());
+        let level_bootstraps: Vec<usize> = starts
+            .zip(self.level_ends.iter().copied())
+            .map(|(start, end)| {
+                self.encodings[start..end]
+                    .iter()
+                    .zip(self.duplicates[start..end].iter())
+                    .filter(|(encoding, duplicate)| encoding.is_some() && duplicate.is_none())
+                    .count()
+            })
+            .collect();
+        let failure_probability = self
+            .encodings
+            .iter()
+            .zip(self.duplicates.iter())
+            .filter_map(|(encoding, duplicate)| encoding.as_ref().filter(|_| duplicate.is_none()))
+            .map(|encoding| encoding.estimate_failure_probability(parameters))
+            .sum::<f64>()
+            .min(1.);
+
+        let bootstraps = self.bootstrap_count();
+        CostReport {
+            bootstraps,
+            keyswitches: bootstraps,
+            linear_ops: bootstraps,
+            depth: self.depth(),
+            level_bootstraps,
+            supported: self.plaintext_modulus <= parameters.max_supported_p(),
+            failure_probability,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadget::gen_keys;
+    use crate::gadget::parameters::PLAINTEXT_3_BITS_PARAMETERS;
+
+    const ADDER_2_BITS: &str = "\
+.model adder2
+.inputs a0 b0 a1 b1
+.outputs s0 s1 c1
+.names a0 b0 s0
+10 1
+01 1
+.names a1 b1 c0 s1
+100 1
+010 1
+001 1
+111 1
+.names a0 b0 c0
+11 1
+.names a1 b1 c0 c1
+11- 1
+1-1 1
+-11 1
+.end
+";
+
+    #[test]
+    fn cost_of_ripple_carry_adder() {
+        let circuit = Circuit::from_blif(ADDER_2_BITS).unwrap();
+        let report = circuit.cost_estimate(&PLAINTEXT_3_BITS_PARAMETERS);
+        assert_eq!(report.bootstraps, 4);
+        assert_eq!(report.keyswitches, 4);
+        assert_eq!(report.linear_ops, 4);
+        assert_eq!(report.depth, 2);
+        assert_eq!(report.level_bootstraps, vec![2, 2]);
+        assert!(report.supported);
+        assert!(report.failure_probability > 0. && report.failure_probability < 1e-3);
+
+        let timings = OperationTimings {
+            bootstrap: Duration::from_millis(10),
+            linear_op: Duration::from_millis(1),
+        };
+        assert_eq!(
+            report.estimated_time(&timings, 1),
+            Duration::from_millis(44)
+        );
+        assert_eq!(
+            report.estimated_time(&timings, 2),
+            Duration::from_millis(22)
+        );
+        assert_eq!(
+            report.estimated_time(&timings, 8),
+            Duration::from_millis(22)
+        );
+    }
+
+    #[test]
+    fn measured_timings() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let timings = OperationTimings::measure(&client_key, &server_key, 4);
+        assert!(timings.bootstrap > timings.linear_op);
+
+        let circuit = Circuit::from_blif(ADDER_2_BITS).unwrap();
+        let report = circuit.cost_estimate(&PLAINTEXT_3_BITS_PARAMETERS);
+        assert!(report.estimated_time(&timings, 1) >= timings.bootstrap * 4);
+    }
+}
@@ -0,0 +1,971 @@
+//! Boolean circuits evaluated gate by gate with p-encodings.
+//!
+//! A [`Circuit`] is a combinational netlist of [`Gate`]s, each described by the truth table of
+//! its output over its named input signals. When the circuit is built, every gate is mapped to
+//! an [`Encoding`] under a plaintext modulus shared by the whole circuit, so the output of any
+//! gate can be fed to any other gate without switching modulus.
+//!
+//! Circuits are usually parsed from a netlist, see [`Circuit::from_blif`] and
+//! [`Circuit::from_yosys_json`].
+
+pub mod blif;
+pub mod bundle;
+pub mod checkpoint;
+pub mod cost;
+pub mod optimize;
+pub mod progress;
+pub mod yosys;
+
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::encoding::decompose::decompose;
+use crate::gadget::encoding::{synthesize, synthesize_with_modulus, Encoding};
+use crate::gadget::error::GadgetError;
+use crate::gadget::server_key::ServerKey;
+use checkpoint::EvaluationCheckpoint;
+use progress::{CancellationToken, EvaluationObserver, EvaluationProgress};
+use rayon::prelude::*;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Largest plaintext modulus considered when mapping the gates of a circuit to encodings.
+pub const MAX_CIRCUIT_PLAINTEXT_MODULUS: u32 = 15;
+
+/// A gate of a [`Circuit`], driving `output` with the truth table `tt_value` of its `inputs`.
+///
+/// The first input is pin 0, i.e. the LSB of a truth table row. A gate without inputs is a
+/// constant driving bit 0 of `tt_value`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Gate {
+    pub(crate) inputs: Vec<String>,
+    pub(crate) output: String,
+    pub(crate) tt_value: u128,
+}
+
+impl Gate {
+    pub fn new(inputs: Vec<String>, output: String, tt_value: u128) -> Gate {
+        Gate {
+            inputs,
+            output,
+            tt_value,
+        }
+    }
+
+    pub fn inputs(&self) -> &[String] {
+        &self.inputs
+    }
+
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    pub fn tt_value(&self) -> u128 {
+        self.tt_value
+    }
+}
+
+/// A combinational circuit whose gates are mapped to encodings sharing one plaintext modulus.
+///
+/// Inputs of the circuit must be canonical encryptions (of 0 or 1) modulo
+/// [`Circuit::plaintext_modulus`], or trivial ciphertexts.
+#[derive(Clone, Debug)]
+pub struct Circuit {
+    pub(crate) name: String,
+    pub(crate) inputs: Vec<String>,
+    pub(crate) outputs: Vec<String>,
+    /// Gates sorted by topological level
+    pub(crate) gates: Vec<Gate>,
+    /// End index in `gates` of each level, gates of a level only depend on previous levels
+    pub(crate) level_ends: Vec<usize>,
+    /// Encoding of each gate in `gates`, `None` for constants
+    pub(crate) encodings: Vec<Option<Encoding>>,
+    /// For each gate in `gates`, the index of an earlier gate of the same level computing the
+    /// same output, whose output is reused instead of evaluating the gate again
+    pub(crate) duplicates: Vec<Option<usize>>,
+    pub(crate) plaintext_modulus: u32,
+}
+
+impl Circuit {
+    /// Builds a circuit, scheduling its gates in topological order and mapping them to
+    /// encodings under the smallest plaintext modulus realizing every gate.
+    ///
+    /// A gate no encoding realizes up to [`MAX_CIRCUIT_PLAINTEXT_MODULUS`] is replaced by the
+    /// gates of its [`Decomposition`](crate::gadget::encoding::Decomposition): cofactor `j` of
+    /// the gate driving `output` drives the internal signal `output#j`, which the multiplexer
+    /// driving `output` selects.
+    pub fn new(
+        name: String,
+        inputs: Vec<String>,
+        outputs: Vec<String>,
+        gates: Vec<Gate>,
+    ) -> Result<Circuit, GadgetError> {
+        let gates = decompose_gates(gates)?;
+        let (gates, level_ends) = schedule(&inputs, &outputs, gates)?;
+
+        // every gate needs at least its own minimal modulus
+        let mut min_p = 3;
+        for gate in gates.iter().filter(|gate| !gate.inputs.is_empty()) {
+            let encoding = synthesize(
+                gate.tt_value,
+                gate.inputs.len(),
+                MAX_CIRCUIT_PLAINTEXT_MODULUS,
+            )
+            .ok_or_else(|| {
+                GadgetError::InvalidCircuit(format!(
+                    "no encoding with p <= {MAX_CIRCUIT_PLAINTEXT_MODULUS} realizes gate {}",
+                    gate.output
+                ))
+            })?;
+            min_p = min_p.max(encoding.p());
+        }
+
+        for plaintext_modulus in (min_p..=MAX_CIRCUIT_PLAINTEXT_MODULUS).step_by(2) {
+            let encodings = gates
+                .iter()
+                .map(|gate| {
+                    if gate.inputs.is_empty() {
+                        Some(None)
+                    } else {
+                        synthesize_with_modulus(gate.tt_value, gate.inputs.len(), plaintext_modulus)
+                            .map(Some)
+                    }
+                })
+                .collect::<Option<Vec<_>>>();
+
+            if let Some(encodings) = encodings {
+                let duplicates = find_duplicates(&gates, &encodings);
+                return Ok(Circuit {
+                    name,
+                    inputs,
+                    outputs,
+                    gates,
+                    level_ends,
+                    encodings,
+                    duplicates,
+                    plaintext_modulus,
+                });
+            }
+        }
+
+        Err(GadgetError::InvalidCircuit(format!(
+            "no plaintext modulus <= {MAX_CIRCUIT_PLAINTEXT_MODULUS} realizes every gate"
+        )))
+    }
+
+    /// Parses a circuit from the BLIF netlist `source`, see [`blif`].
+    pub fn from_blif(source: &str) -> Result<Circuit, GadgetError> {
+        blif::parse(source)
+    }
+
+    /// Parses `module` of the Yosys JSON netlist `source`, mapping its cells with the default
+    /// [`yosys::CellLibrary`]. See [`yosys::parse`] to provide other cell types.
+    pub fn from_yosys_json(source: &str, module: &str) -> Result<Circuit, GadgetError> {
+        yosys::parse(source, module, &yosys::CellLibrary::default())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn inputs(&self) -> &[String] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[String] {
+        &self.outputs
+    }
+
+    /// Returns the gates of the circuit in evaluation order.
+    pub fn gates(&self) -> &[Gate] {
+        &self.gates
+    }
+
+    /// Returns the gates of each topological level, gates of a level only depend on the
+    /// circuit inputs and on gates of previous levels.
+    pub fn levels(&self) -> impl Iterator<Item = &[Gate]> {
+        let starts = std::iter::once(0).chain(self.level_ends.iter().copied());
+        starts
+            .zip(self.level_ends.iter().copied())
+            .map(|(start, end)| &self.gates[start..end])
+    }
+
+    /// Returns the number of bootstraps needed to evaluate the circuit, gates duplicating
+    /// another gate being evaluated once.
+    pub fn bootstrap_count(&self) -> usize {
+        self.bootstraps_before(self.gates.len())
+    }
+
+    /// Returns the number of bootstraps needed to evaluate the first `gate_count` gates.
+    fn bootstraps_before(&self, gate_count: usize) -> usize {
+        self.encodings[..gate_count]
+            .iter()
+            .zip(self.duplicates.iter())
+            .filter(|(encoding, duplicate)| encoding.is_some() && duplicate.is_none())
+            .count()
+    }
+
+    /// Returns the number of topological levels of the circuit.
+    pub fn depth(&self) -> usize {
+        self.level_ends.len()
+    }
+
+    /// Returns the plaintext modulus inputs must be encrypted under.
+    pub fn plaintext_modulus(&self) -> u32 {
+        self.plaintext_modulus
+    }
+}
+
+/// Replaces the gates without an encoding up to [`MAX_CIRCUIT_PLAINTEXT_MODULUS`] by their
+/// cofactors and multiplexer, see [`Circuit::new`].
+fn decompose_gates(gates: Vec<Gate>) -> Result<Vec<Gate>, GadgetError> {
+    let mut decomposed_gates = Vec::with_capacity(gates.len());
+    for gate in gates {
+        let pin_count = gate.inputs.len();
+        if pin_count == 0
+            || synthesize(gate.tt_value, pin_count, MAX_CIRCUIT_PLAINTEXT_MODULUS).is_some()
+        {
+            decomposed_gates.push(gate);
+            continue;
+        }
+
+        let decomposition = decompose(gate.tt_value, pin_count, MAX_CIRCUIT_PLAINTEXT_MODULUS)
+            .ok_or_else(|| {
+                GadgetError::InvalidCircuit(format!(
+                    "no encoding or decomposition with p <= {MAX_CIRCUIT_PLAINTEXT_MODULUS} \
+                    realizes gate {}",
+                    gate.output
+                ))
+            })?;
+        let cofactor_inputs: Vec<String> = decomposition
+            .cofactor_pins()
+            .iter()
+            .map(|pin| gate.inputs[*pin].clone())
+            .collect();
+        let mut mux_inputs = vec![];
+        for (j, cofactor) in decomposition.cofactors().iter().enumerate() {
+            let cofactor_output = format!("{}#{j}", gate.output);
+            decomposed_gates.push(Gate::new(
+                cofactor_inputs.clone(),
+                cofactor_output.clone(),
+                cofactor.tt_value(),
+            ));
+            mux_inputs.push(cofactor_output);
+        }
+        mux_inputs.push(gate.inputs[decomposition.select_pin()].clone());
+        decomposed_gates.push(Gate::new(
+            mux_inputs,
+            gate.output,
+            decomposition.combiner().tt_value(),
+        ));
+    }
+
+    Ok(decomposed_gates)
+}
+
+/// Checks that every signal is driven exactly once and sorts `gates` by topological level,
+/// returning the end index of each level.
+fn schedule(
+    inputs: &[String],
+    outputs: &[String],
+    gates: Vec<Gate>,
+) -> Result<(Vec<Gate>, Vec<usize>), GadgetError> {
+    let mut drivers: HashMap<&str, Option<usize>> = HashMap::new();
+    for input in inputs.iter() {
+        if drivers.insert(input, None).is_some() {
+            return Err(GadgetError::InvalidCircuit(format!(
+                "input {input} is declared twice"
+            )));
+        }
+    }
+    for (index, gate) in gates.iter().enumerate() {
+        if drivers.insert(&gate.output, Some(index)).is_some() {
+            return Err(GadgetError::InvalidCircuit(format!(
+                "signal {} has multiple drivers",
+                gate.output
+            )));
+        }
+    }
+
+    for signal in gates
+        .iter()
+        .flat_map(|gate| gate.inputs.iter())
+        .chain(outputs.iter())
+    {
+        if !drivers.contains_key(signal.as_str()) {
+            return Err(GadgetError::InvalidCircuit(format!(
+                "signal {signal} is not driven"
+            )));
+        }
+    }
+
+    // depth first post-order traversal from every gate, `visiting` detects loops
+    let mut order = Vec::with_capacity(gates.len());
+    let mut visited = HashSet::new();
+    let mut visiting = HashSet::new();
+    for index in 0..gates.len() {
+        visit(
+            index,
+            &gates,
+            &drivers,
+            &mut visited,
+            &mut visiting,
+            &mut order,
+        )?;
+    }
+
+    // drivers come first in the post-order, so their level is known when reaching a gate
+    let mut levels = vec![0; gates.len()];
+    for index in order.iter() {
+        levels[*index] = gates[*index]
+            .inputs
+            .iter()
+            .filter_map(|input| drivers[input.as_str()])
+            .map(|driver| levels[driver] + 1)
+            .max()
+            .unwrap_or(0);
+    }
+    order.sort_by_key(|index| levels[*index]);
+
+    let mut level_ends = vec![];
+    for (position, index) in order.iter().enumerate() {
+        if levels[*index] == level_ends.len() {
+            level_ends.push(position);
+        }
+        *level_ends.last_mut().unwrap() = position + 1;
+    }
+
+    let mut gates: Vec<Option<Gate>> = gates.into_iter().map(Some).collect();
+    let gates = order
+        .into_iter()
+        .map(|index| gates[index].take().unwrap())
+        .collect();
+    Ok((gates, level_ends))
+}
+
+/// Finds the gates computing the same output as an earlier gate, see [`Circuit::duplicates`].
+///
+/// Gates are hashed by their encoding and the signals of their inputs, the output of a
+/// duplicate standing for the output of the gate it duplicates: the gates reading duplicated
+/// signals are found as well. Duplicates thus have the same level as the gate they duplicate.
+fn find_duplicates(gates: &[Gate], encodings: &[Option<Encoding>]) -> Vec<Option<usize>> {
+    let mut originals: HashMap<&str, &str> = HashMap::new();
+    let mut evaluated: HashMap<(&Encoding, Vec<&str>), usize> = HashMap::new();
+    gates
+        .iter()
+        .zip(encodings.iter())
+        .enumerate()
+        .map(|(index, (gate, encoding))| {
+            let encoding = encoding.as_ref()?;
+            let inputs = gate
+                .inputs
+                .iter()
+                .map(|input| {
+                    originals
+                        .get(input.as_str())
+                        .copied()
+                        .unwrap_or(input.as_str())
+                })
+                .collect();
+            match evaluated.entry((encoding, inputs)) {
+                Entry::Occupied(original) => {
+                    originals.insert(&gate.output, &gates[*original.get()].output);
+                    Some(*original.get())
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(index);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Appends the gates driving the inputs of gate `index`, then the gate itself, to `order`.
+///
+/// The traversal keeps its own stack of the gates being visited, each with the position of its
+/// next input to visit, so that deep circuits, e.g. long ripple carry chains, cannot overflow the
+/// call stack.
+fn visit(
+    index: usize,
+    gates: &[Gate],
+    drivers: &HashMap<&str, Option<usize>>,
+    visited: &mut HashSet<usize>,
+    visiting: &mut HashSet<usize>,
+    order: &mut Vec<usize>,
+) -> Result<(), GadgetError> {
+    if visited.contains(&index) {
+        return Ok(());
+    }
+    visiting.insert(index);
+    let mut stack = vec![(index, 0)];
+
+    while let Some(&(index, next_input)) = stack.last() {
+        let Some(input) = gates[index].inputs.get(next_input) else {
+            stack.pop();
+            visiting.remove(&index);
+            visited.insert(index);
+            order.push(index);
+            continue;
+        };
+        stack.last_mut().unwrap().1 += 1;
+
+        if let Some(Some(driver)) = drivers.get(input.as_str()) {
+            if visited.contains(driver) {
+                continue;
+            }
+            if !visiting.insert(*driver) {
+                return Err(GadgetError::InvalidCircuit(format!(
+                    "combinational loop through signal {}",
+                    gates[*driver].output
+                )));
+            }
+            stack.push((*driver, 0));
+        }
+    }
+
+    Ok(())
+}
+
+impl ServerKey {
+    /// Evaluates `circuit` on the ciphertexts of its named `inputs`, returning the ciphertexts
+    /// of its named outputs.
+    ///
+    /// Gates are evaluated level by level, each with one bootstrap. The gates of a level are
+    /// independent and evaluated in parallel. A gate with the same encoding and inputs as
+    /// another gate is not evaluated, nor notified to observers: it reuses the output of the
+    /// other gate.
+    pub fn evaluate_circuit(
+        &self,
+        circuit: &Circuit,
+        inputs: &HashMap<String, Ciphertext>,
+    ) -> Result<HashMap<String, Ciphertext>, GadgetError> {
+        self.evaluate_circuit_with_observer(circuit, inputs, &(), &CancellationToken::new())
+    }
+
+    /// Evaluates `circuit` as [`ServerKey::evaluate_circuit`], notifying `observer` of the
+    /// progress of the evaluation.
+    ///
+    /// `cancellation` is checked before each gate, once cancelled the evaluation returns
+    /// [`GadgetError::EvaluationCancelled`].
+    pub fn evaluate_circuit_with_observer(
+        &self,
+        circuit: &Circuit,
+        inputs: &HashMap<String, Ciphertext>,
+        observer: &dyn EvaluationObserver,
+        cancellation: &CancellationToken,
+    ) -> Result<HashMap<String, Ciphertext>, GadgetError> {
+        let mut checkpoint = EvaluationCheckpoint::new(circuit, inputs)?;
+        self.resume_circuit(
+            circuit,
+            &mut checkpoint,
+            circuit.depth(),
+            observer,
+            cancellation,
+        )?;
+        checkpoint.outputs(circuit)
+    }
+
+    /// Continues the evaluation of `circuit` from `checkpoint` for at most `max_levels` levels.
+    ///
+    /// `checkpoint` is updated after each completed level. If the evaluation fails or is
+    /// cancelled, the gates of the current level are discarded and `checkpoint` still holds the
+    /// last completed level, from which the evaluation can be resumed.
+    pub fn resume_circuit(
+        &self,
+        circuit: &Circuit,
+        checkpoint: &mut EvaluationCheckpoint,
+        max_levels: usize,
+        observer: &dyn EvaluationObserver,
+        cancellation: &CancellationToken,
+    ) -> Result<(), GadgetError> {
+        checkpoint.check_circuit(circuit)?;
+
+        let start_time = Instant::now();
+        let previous_elapsed = checkpoint.elapsed;
+        let total_bootstraps = circuit.bootstrap_count();
+
+        let mut start = match checkpoint.completed_levels {
+            0 => 0,
+            completed_levels => circuit.level_ends[completed_levels - 1],
+        };
+        let bootstraps = AtomicUsize::new(circuit.bootstraps_before(start));
+
+        let remaining_levels = circuit.level_ends[checkpoint.completed_levels..]
+            .iter()
+            .copied()
+            .take(max_levels);
+        for end in remaining_levels {
+            let signals = &checkpoint.signals;
+            let level_outputs = circuit.gates[start..end]
+                .par_iter()
+                .zip(circuit.encodings[start..end].par_iter())
+                .zip(circuit.duplicates[start..end].par_iter())
+                .map(|((gate, encoding), duplicate)| {
+                    if duplicate.is_some() {
+                        return Ok(None);
+                    }
+                    if cancellation.is_cancelled() {
+                        return Err(GadgetError::EvaluationCancelled);
+                    }
+                    observer.gate_started(gate);
+
+                    let output = match encoding {
+                        Some(encoding) => {
+                            let input_ciphertexts: Vec<_> =
+                                gate.inputs.iter().map(|input| &signals[input]).collect();
+                            let output = self.evaluate_gate_ref(&input_ciphertexts, encoding)?;
+                            bootstraps.fetch_add(1, Ordering::Relaxed);
+                            output
+                        }
+                        None => Ciphertext::Trivial(gate.tt_value & 1 == 1),
+                    };
+
+                    let progress = EvaluationProgress {
+                        bootstraps: bootstraps.load(Ordering::Relaxed),
+                        total_bootstraps,
+                        elapsed: previous_elapsed + start_time.elapsed(),
+                    };
+                    observer.gate_finished(gate, &progress);
+                    Ok(Some(output))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            for (index, output) in (start..end).zip(level_outputs) {
+                // duplicates come after the gate they duplicate in their level
+                let output = match output {
+                    Some(output) => output,
+                    None => {
+                        let original = circuit.duplicates[index].unwrap();
+                        checkpoint.signals[&circuit.gates[original].output].clone()
+                    }
+                };
+                checkpoint
+                    .signals
+                    .insert(circuit.gates[index].output.clone(), output);
+            }
+            checkpoint.completed_levels += 1;
+            checkpoint.elapsed = previous_elapsed + start_time.elapsed();
+            start = end;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean::engine::WithThreadLocalEngine;
+    use crate::gadget::engine::GadgetEngine;
+    use crate::gadget::gen_keys;
+    use crate::gadget::parameters::PLAINTEXT_3_BITS_PARAMETERS;
+
+    const FULL_ADDER: &str = "\
+# 1 bit full adder
+.model full_adder
+.inputs a b cin
+.outputs sum cout
+.names a b cin sum
+100 1
+010 1
+001 1
+111 1
+.names a b cin cout
+11- 1
+1-1 1
+-11 1
+.end
+";
+
+    #[test]
+    fn circuit_schedules_gates() {
+        let gates = vec![
+            Gate::new(vec!["t".to_string()], "y".to_string(), 0b01),
+            Gate::new(
+                vec!["a".to_string(), "b".to_string()],
+                "t".to_string(),
+                0b1000,
+            ),
+        ];
+        let circuit = Circuit::new(
+            "nand".to_string(),
+            vec!["a".to_string(), "b".to_string()],
+            vec!["y".to_string()],
+            gates,
+        )
+        .unwrap();
+
+        let order: Vec<&str> = circuit.gates().iter().map(Gate::output).collect();
+        assert_eq!(order, vec!["t", "y"]);
+        assert_eq!(circuit.depth(), 2);
+        assert_eq!(circuit.plaintext_modulus(), 3);
+
+        let looped = vec![
+            Gate::new(
+                vec!["a".to_string(), "y".to_string()],
+                "t".to_string(),
+                0b1000,
+            ),
+            Gate::new(vec!["t".to_string()], "y".to_string(), 0b01),
+        ];
+        assert!(matches!(
+            Circuit::new(
+                "loop".to_string(),
+                vec!["a".to_string()],
+                vec!["y".to_string()],
+                looped
+            ),
+            Err(GadgetError::InvalidCircuit(_))
+        ));
+    }
+
+    #[test]
+    fn circuit_levels() {
+        let circuit = Circuit::from_blif(FULL_ADDER).unwrap();
+        assert_eq!(circuit.depth(), 1);
+
+        // ripple carry adder of 2 bits, the second sum depends on the first carry
+        let circuit = Circuit::from_blif(
+            "\
+.model adder2
+.inputs a0 b0 a1 b1
+.outputs s0 s1 c1
+.names a0 b0 s0
+10 1
+01 1
+.names a1 b1 c0 s1
+100 1
+010 1
+001 1
+111 1
+.names a0 b0 c0
+11 1
+.names a1 b1 c0 c1
+11- 1
+1-1 1
+-11 1
+.end
+",
+        )
+        .unwrap();
+
+        let levels: Vec<Vec<&str>> = circuit
+            .levels()
+            .map(|level| level.iter().map(Gate::output).collect())
+            .collect();
+        assert_eq!(levels, vec![vec!["s0", "c0"], vec!["s1", "c1"]]);
+    }
+
+    #[derive(Default)]
+    struct CancelAfterFirstGate {
+        token: CancellationToken,
+        finished: AtomicUsize,
+    }
+
+    impl EvaluationObserver for CancelAfterFirstGate {
+        fn gate_finished(&self, _gate: &Gate, progress: &EvaluationProgress) {
+            assert!(progress.bootstraps <= progress.total_bootstraps);
+            self.finished.fetch_add(1, Ordering::Relaxed);
+            self.token.cancel();
+        }
+    }
+
+    #[test]
+    fn schedule_deep_circuit() {
+        // a chain of inverters deeper than a recursive traversal could follow
+        let depth = 100_000;
+        let gates = (0..depth)
+            .map(|i| Gate::new(vec![format!("t{i}")], format!("t{}", i + 1), 0b01))
+            .collect();
+        let circuit = Circuit::new(
+            "chain".to_string(),
+            vec!["t0".to_string()],
+            vec![format!("t{depth}")],
+            gates,
+        )
+        .unwrap();
+        assert_eq!(circuit.depth(), depth);
+        assert_eq!(circuit.gates()[depth - 1].output(), format!("t{depth}"));
+    }
+
+    #[test]
+    fn evaluate_circuit_cancellation() {
+        // chain of 3 inverters, each on its own level
+        let gates = vec![
+            Gate::new(vec!["a".to_string()], "t0".to_string(), 0b01),
+            Gate::new(vec!["t0".to_string()], "t1".to_string(), 0b01),
+            Gate::new(vec!["t1".to_string()], "y".to_string(), 0b01),
+        ];
+        let circuit = Circuit::new(
+            "chain".to_string(),
+            vec!["a".to_string()],
+            vec!["y".to_string()],
+            gates,
+        )
+        .unwrap();
+        assert_eq!(circuit.bootstrap_count(), 3);
+
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let mut inputs = HashMap::new();
+        inputs.insert(
+            "a".to_string(),
+            GadgetEngine::with_thread_local_mut(|engine| engine.encrypt(1, &client_key, 3)),
+        );
+
+        let observer = CancelAfterFirstGate::default();
+        let result = server_key.evaluate_circuit_with_observer(
+            &circuit,
+            &inputs,
+            &observer,
+            &observer.token,
+        );
+        assert_eq!(result.unwrap_err(), GadgetError::EvaluationCancelled);
+        assert_eq!(observer.finished.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn resume_circuit_from_checkpoint() {
+        let gates = vec![
+            Gate::new(vec!["a".to_string()], "t".to_string(), 0b01),
+            Gate::new(
+                vec!["t".to_string(), "b".to_string()],
+                "y".to_string(),
+                0b1000,
+            ),
+        ];
+        let circuit = Circuit::new(
+            "and_not".to_string(),
+            vec!["a".to_string(), "b".to_string()],
+            vec!["y".to_string()],
+            gates,
+        )
+        .unwrap();
+
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+            [("a", 0), ("b", 1)]
+                .into_iter()
+                .map(|(name, bit)| (name.to_string(), engine.encrypt(bit, &client_key, 3)))
+                .collect::<HashMap<_, _>>()
+        });
+
+        let mut checkpoint = EvaluationCheckpoint::new(&circuit, &inputs).unwrap();
+        let cancellation = CancellationToken::new();
+        server_key
+            .resume_circuit(&circuit, &mut checkpoint, 1, &(), &cancellation)
+            .unwrap();
+        assert_eq!(checkpoint.completed_levels(), 1);
+        assert!(checkpoint.outputs(&circuit).is_err());
+
+        // the checkpoint survives a serialization round trip
+        let serialized = bincode::serialize(&checkpoint).unwrap();
+        let mut checkpoint: EvaluationCheckpoint = bincode::deserialize(&serialized).unwrap();
+
+        // a checkpoint lacking the signal of a completed level is rejected
+        let mut truncated = checkpoint.clone();
+        truncated.signals.remove("t");
+        assert!(matches!(
+            server_key.resume_circuit(&circuit, &mut truncated, usize::MAX, &(), &cancellation),
+            Err(GadgetError::InvalidCircuit(_))
+        ));
+
+        server_key
+            .resume_circuit(&circuit, &mut checkpoint, usize::MAX, &(), &cancellation)
+            .unwrap();
+        assert!(checkpoint.is_complete(&circuit));
+
+        let outputs = checkpoint.outputs(&circuit).unwrap();
+        let y = GadgetEngine::with_thread_local_mut(|engine| {
+            engine.decrypt(&outputs["y"], &client_key)
+        });
+        assert_eq!(y, 1);
+    }
+
+    #[derive(Default)]
+    struct CountGates {
+        started: AtomicUsize,
+    }
+
+    impl EvaluationObserver for CountGates {
+        fn gate_started(&self, _gate: &Gate) {
+            self.started.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn evaluate_circuit_deduplicates_gates() {
+        // t1 duplicates t0, and so does z of y once t1 stands for t0
+        let gates = vec![
+            Gate::new(
+                vec!["a".to_string(), "b".to_string()],
+                "t0".to_string(),
+                0b1000,
+            ),
+            Gate::new(
+                vec!["a".to_string(), "b".to_string()],
+                "t1".to_string(),
+                0b1000,
+            ),
+            Gate::new(
+                vec!["t0".to_string(), "c".to_string()],
+                "y".to_string(),
+                0b0110,
+            ),
+            Gate::new(
+                vec!["t1".to_string(), "c".to_string()],
+                "z".to_string(),
+                0b0110,
+            ),
+            Gate::new(
+                vec!["b".to_string(), "a".to_string()],
+                "u".to_string(),
+                0b1000,
+            ),
+        ];
+        let circuit = Circuit::new(
+            "duplicates".to_string(),
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            vec![
+                "t1".to_string(),
+                "y".to_string(),
+                "z".to_string(),
+                "u".to_string(),
+            ],
+            gates,
+        )
+        .unwrap();
+        // the inputs of u are swapped, it is evaluated on its own
+        assert_eq!(circuit.bootstrap_count(), 3);
+
+        let p = circuit.plaintext_modulus();
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        for row in 0..8u32 {
+            let bits = [row & 1, (row >> 1) & 1, (row >> 2) & 1];
+            let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                ["a", "b", "c"]
+                    .into_iter()
+                    .zip(bits)
+                    .map(|(name, bit)| (name.to_string(), engine.encrypt(bit, &client_key, p)))
+                    .collect::<HashMap<_, _>>()
+            });
+
+            let observer = CountGates::default();
+            let outputs = server_key
+                .evaluate_circuit_with_observer(
+                    &circuit,
+                    &inputs,
+                    &observer,
+                    &CancellationToken::new(),
+                )
+                .unwrap();
+            assert_eq!(observer.started.load(Ordering::Relaxed), 3);
+
+            let decrypted = GadgetEngine::with_thread_local_mut(|engine| {
+                ["t1", "y", "z", "u"].map(|name| engine.decrypt(&outputs[name], &client_key))
+            });
+            let and = bits[0] & bits[1];
+            assert_eq!(
+                decrypted,
+                [and, and ^ bits[2], and ^ bits[2], and],
+                "row {row}"
+            );
+        }
+    }
+
+    #[test]
+    fn evaluate_full_adder() {
+        let circuit = Circuit::from_blif(FULL_ADDER).unwrap();
+        let p = circuit.plaintext_modulus();
+        assert_eq!(p, 5);
+
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+
+        for row in 0..8u32 {
+            let bits = [row & 1, (row >> 1) & 1, (row >> 2) & 1];
+            let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                ["a", "b", "cin"]
+                    .into_iter()
+                    .zip(bits)
+                    .map(|(name, bit)| (name.to_string(), engine.encrypt(bit, &client_key, p)))
+                    .collect::<HashMap<_, _>>()
+            });
+
+            let outputs = server_key.evaluate_circuit(&circuit, &inputs).unwrap();
+            let (sum, cout) = GadgetEngine::with_thread_local_mut(|engine| {
+                (
+                    engine.decrypt(&outputs["sum"], &client_key),
+                    engine.decrypt(&outputs["cout"], &client_key),
+                )
+            });
+
+            let total = bits.iter().sum::<u32>();
+            assert_eq!(sum, total & 1, "row {row}");
+            assert_eq!(cout, total >> 1, "row {row}");
+        }
+
+        let mut inputs = HashMap::new();
+        inputs.insert("a".to_string(), Ciphertext::Trivial(true));
+        assert_eq!(
+            server_key.evaluate_circuit(&circuit, &inputs).unwrap_err(),
+            GadgetError::MissingCircuitInput("b".to_string())
+        );
+    }
+    #[test]
+    fn circuit_decomposes_gates_without_encoding() {
+        // s ? a ^ b ^ c : d & e & f has no encoding up to MAX_CIRCUIT_PLAINTEXT_MODULUS
+        let output = |row: u32| {
+            let bit = |pin: u32| (row >> pin) & 1;
+            if bit(0) == 1 {
+                bit(1) ^ bit(2) ^ bit(3)
+            } else {
+                bit(4) & bit(5) & bit(6)
+            }
+        };
+        let tt_value = (0..128u32).fold(0u128, |tt, row| tt | (output(row) as u128) << row);
+        let inputs: Vec<String> = ["s", "a", "b", "c", "d", "e", "f"]
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let circuit = Circuit::new(
+            "mux".to_string(),
+            inputs.clone(),
+            vec!["y".to_string()],
+            vec![Gate::new(inputs.clone(), "y".to_string(), tt_value)],
+        )
+        .unwrap();
+
+        let order: Vec<&str> = circuit.gates().iter().map(Gate::output).collect();
+        assert_eq!(order, vec!["y#0", "y#1", "y"]);
+        assert_eq!(circuit.depth(), 2);
+        assert_eq!(circuit.bootstrap_count(), 3);
+
+        let p = circuit.plaintext_modulus();
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        for row in [
+            0b000_0000u32,
+            0b111_0000,
+            0b101_0000,
+            0b000_0011,
+            0b000_1111,
+        ] {
+            let encrypted = GadgetEngine::with_thread_local_mut(|engine| {
+                inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(pin, name)| {
+                        (
+                            name.clone(),
+                            engine.encrypt((row >> pin) & 1, &client_key, p),
+                        )
+                    })
+                    .collect::<HashMap<_, _>>()
+            });
+            let outputs = server_key.evaluate_circuit(&circuit, &encrypted).unwrap();
+            let y = GadgetEngine::with_thread_local_mut(|engine| {
+                engine.decrypt(&outputs["y"], &client_key)
+            });
+            assert_eq!(y, output(row), "row {row:#09b}");
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! Encoding-aware optimization of circuits.
+//!
+//! XOR, XNOR, NOT and buffers are affine over GF(2): under p-encodings, the gates reading their
+//! output can often read their inputs instead, the parity being computed by the linear
+//! combination of the downstream encoding. [`Circuit::optimize`] folds such gates into the gates
+//! they drive whenever the merged truth table still has an encoding, saving their bootstraps.
+
+use super::{Circuit, Gate};
+use crate::gadget::encoding::synthesize_with_modulus;
+use std::collections::HashSet;
+
+/// Bootstrap counts of a circuit before and after [`Circuit::optimize`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OptimizationReport {
+    pub bootstraps_before: usize,
+    pub bootstraps_after: usize,
+    pub plaintext_modulus_before: u32,
+    pub plaintext_modulus_after: u32,
+}
+
+impl OptimizationReport {
+    pub fn saved_bootstraps(&self) -> usize {
+        self.bootstraps_before - self.bootstraps_after
+    }
+}
+
+impl Circuit {
+    /// Folds the affine gates (XOR, XNOR, NOT, buffers) of the circuit into the gates reading
+    /// their output, returning the optimized circuit and its bootstrap counts.
+    ///
+    /// A gate is folded if it does not drive an output of the circuit and every gate reading it
+    /// still has an encoding once merged, under a plaintext modulus shared by the whole circuit
+    /// of at most `max_plaintext_modulus`. Folding chains of XORs usually needs a larger modulus
+    /// than the one of the circuit: each odd modulus up to `max_plaintext_modulus` is tried and
+    /// the circuit with the fewest bootstraps is kept, the smallest modulus breaking ties. Pass
+    /// [`Circuit::plaintext_modulus`] to keep the modulus of the circuit.
+    pub fn optimize(&self, max_plaintext_modulus: u32) -> (Circuit, OptimizationReport) {
+        let mut optimized = self.clone();
+        for plaintext_modulus in (self.plaintext_modulus..=max_plaintext_modulus).step_by(2) {
+            // the gates left as is may have no encoding under the larger moduli
+            let candidate = Circuit::new(
+                self.name.clone(),
+                self.inputs.clone(),
+                self.outputs.clone(),
+                fold_affine_gates(self, plaintext_modulus),
+            );
+            if let Ok(candidate) = candidate {
+                if candidate.plaintext_modulus <= max_plaintext_modulus
+                    && candidate.bootstrap_count() < optimized.bootstrap_count()
+                {
+                    optimized = candidate;
+                }
+            }
+        }
+
+        let report = OptimizationReport {
+            bootstraps_before: self.bootstrap_count(),
+            bootstraps_after: optimized.bootstrap_count(),
+            plaintext_modulus_before: self.plaintext_modulus,
+            plaintext_modulus_after: optimized.plaintext_modulus,
+        };
+        (optimized, report)
+    }
+}
+
+/// Folds the affine gates of `circuit` into the gates reading them until none can be folded
+/// while keeping an encoding modulo `plaintext_modulus` for every gate.
+fn fold_affine_gates(circuit: &Circuit, plaintext_modulus: u32) -> Vec<Gate> {
+    let outputs: HashSet<&str> = circuit.outputs.iter().map(String::as_str).collect();
+    let mut gates: Vec<Option<Gate>> = circuit.gates.iter().cloned().map(Some).collect();
+
+    // folding a gate may make the gates reading it affine, e.g. along chains of XORs
+    let mut folded = true;
+    while folded {
+        folded = false;
+        for index in 0..gates.len() {
+            let driver = match &gates[index] {
+                Some(gate)
+                    if !gate.inputs.is_empty()
+                        && !outputs.contains(gate.output.as_str())
+                        && is_affine(gate.tt_value, gate.inputs.len()) =>
+                {
+                    gate.clone()
+                }
+                _ => continue,
+            };
+
+            let readers: Vec<usize> = gates
+                .iter()
+                .enumerate()
+                .filter(|(_, gate)| {
+                    gate.as_ref()
+                        .is_some_and(|gate| gate.inputs.contains(&driver.output))
+                })
+                .map(|(reader, _)| reader)
+                .collect();
+            if readers.is_empty() {
+                continue;
+            }
+
+            let merged = readers
+                .iter()
+                .map(|reader| {
+                    compose(gates[*reader].as_ref().unwrap(), &driver).filter(|gate| {
+                        synthesize_with_modulus(gate.tt_value, gate.inputs.len(), plaintext_modulus)
+                            .is_some()
+                    })
+                })
+                .collect::<Option<Vec<_>>>();
+            if let Some(merged) = merged {
+                for (reader, gate) in readers.into_iter().zip(merged) {
+                    gates[reader] = Some(gate);
+                }
+                gates[index] = None;
+                folded = true;
+            }
+        }
+    }
+
+    gates.into_iter().flatten().collect()
+}
+
+/// Returns whether the truth table `tt_value` of `pin_count` pins is the XOR of a constant and
+/// of some of its pins.
+fn is_affine(tt_value: u128, pin_count: usize) -> bool {
+    let constant = tt_value & 1;
+    let parity_pins = (0..pin_count)
+        .filter(|pin| (tt_value >> (1usize << pin)) & 1 != constant)
+        .fold(0usize, |pins, pin| pins | 1 << pin);
+
+    (0..1usize << pin_count).all(|row| {
+        (tt_value >> row) & 1 == constant ^ ((row & parity_pins).count_ones() & 1) as u128
+    })
+}
+
+/// Returns the gate computing `reader` with the output of `driver` replaced by its definition,
+/// or `None` if the merged gate has more pins than a truth table holds.
+fn compose(reader: &Gate, driver: &Gate) -> Option<Gate> {
+    let mut inputs: Vec<String> = reader
+        .inputs
+        .iter()
+        .filter(|input| **input != driver.output)
+        .cloned()
+        .collect();
+    for input in driver.inputs.iter() {
+        if !inputs.contains(input) {
+            inputs.push(input.clone());
+        }
+    }
+    // truth tables of up to 7 pins fit in a u128
+    if inputs.len() > 7 {
+        return None;
+    }
+
+    let pin = |signal: &String| inputs.iter().position(|input| input == signal).unwrap();
+    let tt_value = (0..1usize << inputs.len()).fold(0u128, |tt_value, row| {
+        let bit = |signal: &String| (row >> pin(signal)) & 1;
+        let driver_row = driver
+            .inputs
+            .iter()
+            .enumerate()
+            .fold(0, |driver_row, (driver_pin, input)| {
+                driver_row | bit(input) << driver_pin
+            });
+        let driver_value = (driver.tt_value >> driver_row) as usize & 1;
+        let reader_row =
+            reader
+                .inputs
+                .iter()
+                .enumerate()
+                .fold(0, |reader_row, (reader_pin, input)| {
+                    let value = if *input == driver.output {
+                        driver_value
+                    } else {
+                        bit(input)
+                    };
+                    reader_row | value << reader_pin
+                });
+        tt_value | ((reader.tt_value >> reader_row) & 1) << row
+    });
+
+    Some(Gate::new(inputs, reader.output.clone(), tt_value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean::engine::WithThreadLocalEngine;
+    use crate::gadget::circuit::MAX_CIRCUIT_PLAINTEXT_MODULUS;
+    use crate::gadget::engine::GadgetEngine;
+    use crate::gadget::gen_keys;
+    use crate::gadget::parameters::PLAINTEXT_3_BITS_PARAMETERS;
+    use std::collections::HashMap;
+
+    fn signals(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn affine_truth_tables() {
+        assert!(is_affine(0b01, 1));
+        assert!(is_affine(0b10, 1));
+        assert!(is_affine(0b0110, 2));
+        assert!(is_affine(0b1001, 2));
+        assert!(is_affine(0b1001_0110, 3));
+        assert!(!is_affine(0b1000, 2));
+        assert!(!is_affine(0b1110, 2));
+    }
+
+    #[test]
+    fn optimize_folds_xor_chain() {
+        // y = (a ^ b ^ c) & d
+        let gates = vec![
+            Gate::new(signals(&["a", "b"]), "t0".to_string(), 0b0110),
+            Gate::new(signals(&["t0", "c"]), "t1".to_string(), 0b0110),
+            Gate::new(signals(&["t1", "d"]), "y".to_string(), 0b1000),
+        ];
+        let circuit = Circuit::new(
+            "xor_and".to_string(),
+            signals(&["a", "b", "c", "d"]),
+            signals(&["y"]),
+            gates,
+        )
+        .unwrap();
+
+        // the modulus of the circuit is too small for the parity of 3 inputs
+        let (same_modulus, report) = circuit.optimize(circuit.plaintext_modulus());
+        assert_eq!(report.saved_bootstraps(), 0);
+        assert_eq!(same_modulus.bootstrap_count(), 3);
+
+        let (optimized, report) = circuit.optimize(MAX_CIRCUIT_PLAINTEXT_MODULUS);
+        assert_eq!(report.bootstraps_before, 3);
+        assert_eq!(report.bootstraps_after, 1);
+        assert_eq!(optimized.bootstrap_count(), 1);
+        assert_eq!(optimized.depth(), 1);
+
+        let p = optimized.plaintext_modulus();
+        assert_eq!(report.plaintext_modulus_after, p);
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        for row in 0..16u32 {
+            let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                ["a", "b", "c", "d"]
+                    .into_iter()
+                    .enumerate()
+                    .map(|(pin, name)| {
+                        let bit = (row >> pin) & 1;
+                        (name.to_string(), engine.encrypt(bit, &client_key, p))
+                    })
+                    .collect::<HashMap<_, _>>()
+            });
+            let outputs = server_key.evaluate_circuit(&optimized, &inputs).unwrap();
+            let y = GadgetEngine::with_thread_local_mut(|engine| {
+                engine.decrypt(&outputs["y"], &client_key)
+            });
+            let expected = ((row ^ (row >> 1) ^ (row >> 2)) & (row >> 3)) & 1;
+            assert_eq!(y, expected, "row {row:#06b}");
+        }
+    }
+
+    #[test]
+    fn optimize_keeps_circuit_outputs() {
+        // y = !a & b, the inverter is folded unless it drives an output
+        let gates = vec![
+            Gate::new(signals(&["a"]), "t".to_string(), 0b01),
+            Gate::new(signals(&["t", "b"]), "y".to_string(), 0b1000),
+        ];
+        let circuit = Circuit::new(
+            "and_not".to_string(),
+            signals(&["a", "b"]),
+            signals(&["y"]),
+            gates.clone(),
+        )
+        .unwrap();
+        let (optimized, report) = circuit.optimize(circuit.plaintext_modulus());
+        assert_eq!(report.bootstraps_after, 1);
+        assert_eq!(optimized.plaintext_modulus(), circuit.plaintext_modulus());
+        assert_eq!(optimized.gates()[0].inputs(), signals(&["b", "a"]));
+        assert_eq!(optimized.gates()[0].tt_value(), 0b0010);
+
+        let circuit = Circuit::new(
+            "and_not".to_string(),
+            signals(&["a", "b"]),
+            signals(&["y", "t"]),
+            gates,
+        )
+        .unwrap();
+        let (_, report) = circuit.optimize(MAX_CIRCUIT_PLAINTEXT_MODULUS);
+        assert_eq!(report.bootstraps_after, 2);
+    }
+}
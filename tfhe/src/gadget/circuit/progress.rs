@@ -0,0 +1,69 @@
+//! Progress notifications and cancellation of circuit evaluations.
+
+use super::Gate;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Progress of a circuit evaluation when a gate finishes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EvaluationProgress {
+    /// Number of bootstraps performed so far
+    pub bootstraps: usize,
+    /// Number of bootstraps needed to evaluate the whole circuit
+    pub total_bootstraps: usize,
+    /// Time elapsed since the evaluation started
+    pub elapsed: Duration,
+}
+
+impl EvaluationProgress {
+    /// Estimates the remaining evaluation time from the average time of the bootstraps
+    /// performed so far. Returns `None` before the first bootstrap finishes.
+    pub fn estimated_remaining(&self) -> Option<Duration> {
+        if self.bootstraps == 0 {
+            return None;
+        }
+
+        let remaining = self.total_bootstraps.saturating_sub(self.bootstraps);
+        Some(
+            self.elapsed
+                .mul_f64(remaining as f64 / self.bootstraps as f64),
+        )
+    }
+}
+
+/// Receives notifications while a circuit is evaluated.
+///
+/// Gates of a level are evaluated in parallel, hence methods may be called concurrently from
+/// several threads. All methods do nothing by default.
+pub trait EvaluationObserver: Sync {
+    fn gate_started(&self, _gate: &Gate) {}
+
+    fn gate_finished(&self, _gate: &Gate, _progress: &EvaluationProgress) {}
+}
+
+/// Observer ignoring every notification.
+impl EvaluationObserver for () {}
+
+/// Cancels a circuit evaluation from another thread.
+///
+/// The token is checked before starting each gate, gates already being evaluated are not
+/// interrupted. Clones share the same cancellation state.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
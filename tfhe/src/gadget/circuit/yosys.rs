@@ -0,0 +1,386 @@
+//! Parser for netlists written by the Yosys `write_json` command.
+//!
+//! Every cell of the selected module is mapped to a [`Gate`] through a [`CellLibrary`], which
+//! gives the input ports, output port and truth table of each cell type. The default library
+//! covers the Yosys internal gate cells (`$_AND_`, `$_MUX_`, ...), as produced by e.g.
+//! `synth; abc -g AND,NAND,OR,NOR,XOR,XNOR,ANDNOT,ORNOT,MUX,AOI3,OAI3,AOI4,OAI4`, as well as
+//! `$lut` cells produced by `abc -lut`. Other cell types, e.g. of a liberty file, can be added with
+//! [`CellLibrary::register`].
+//!
+//! Signals driven by a single bit port `name` are named `name`, bits of wider ports are named
+//! `name[i]`.
+
+use super::{Circuit, Gate};
+use crate::gadget::error::GadgetError;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Input ports, output port and truth table of a cell type, the first input port being pin 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CellDefinition {
+    pub(crate) inputs: Vec<String>,
+    pub(crate) output: String,
+    pub(crate) tt_value: u128,
+}
+
+/// Maps cell types of a Yosys netlist to their truth tables.
+#[derive(Clone, Debug)]
+pub struct CellLibrary {
+    cells: HashMap<String, CellDefinition>,
+}
+
+impl CellLibrary {
+    /// Returns a library without any cell type.
+    pub fn empty() -> CellLibrary {
+        CellLibrary {
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Registers the single output `cell_type`, replacing any previous definition.
+    pub fn register(
+        &mut self,
+        cell_type: &str,
+        inputs: &[&str],
+        output: &str,
+        tt_value: u128,
+    ) -> &mut CellLibrary {
+        self.cells.insert(
+            cell_type.to_string(),
+            CellDefinition {
+                inputs: inputs.iter().map(|input| input.to_string()).collect(),
+                output: output.to_string(),
+                tt_value,
+            },
+        );
+        self
+    }
+
+    pub fn get(&self, cell_type: &str) -> Option<&CellDefinition> {
+        self.cells.get(cell_type)
+    }
+}
+
+impl Default for CellLibrary {
+    /// Library of the Yosys internal gate cells.
+    fn default() -> CellLibrary {
+        let mut library = CellLibrary::empty();
+        library
+            .register("$_BUF_", &["A"], "Y", 0b10)
+            .register("$_NOT_", &["A"], "Y", 0b01)
+            .register("$_AND_", &["A", "B"], "Y", 0b1000)
+            .register("$_NAND_", &["A", "B"], "Y", 0b0111)
+            .register("$_OR_", &["A", "B"], "Y", 0b1110)
+            .register("$_NOR_", &["A", "B"], "Y", 0b0001)
+            .register("$_XOR_", &["A", "B"], "Y", 0b0110)
+            .register("$_XNOR_", &["A", "B"], "Y", 0b1001)
+            // A & !B
+            .register("$_ANDNOT_", &["A", "B"], "Y", 0b0010)
+            // A | !B
+            .register("$_ORNOT_", &["A", "B"], "Y", 0b1011)
+            // S ? B : A
+            .register("$_MUX_", &["A", "B", "S"], "Y", 0xca)
+            .register("$_NMUX_", &["A", "B", "S"], "Y", 0x35)
+            // !((A & B) | C)
+            .register("$_AOI3_", &["A", "B", "C"], "Y", 0x07)
+            // !((A | B) & C)
+            .register("$_OAI3_", &["A", "B", "C"], "Y", 0x1f)
+            // !((A & B) | (C & D))
+            .register("$_AOI4_", &["A", "B", "C", "D"], "Y", 0x0777)
+            // !((A | B) & (C | D))
+            .register("$_OAI4_", &["A", "B", "C", "D"], "Y", 0x111f);
+        library
+    }
+}
+
+#[derive(Deserialize)]
+struct Netlist {
+    modules: BTreeMap<String, Module>,
+}
+
+#[derive(Deserialize)]
+struct Module {
+    #[serde(default)]
+    ports: BTreeMap<String, Port>,
+    #[serde(default)]
+    cells: BTreeMap<String, Cell>,
+}
+
+#[derive(Deserialize)]
+struct Port {
+    direction: String,
+    bits: Vec<Bit>,
+}
+
+#[derive(Deserialize)]
+struct Cell {
+    #[serde(rename = "type")]
+    cell_type: String,
+    #[serde(default)]
+    parameters: BTreeMap<String, serde_json::Value>,
+    connections: BTreeMap<String, Vec<Bit>>,
+}
+
+/// A bit of a port, either a net index or a constant
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Bit {
+    Net(u64),
+    Constant(String),
+}
+
+/// Parses `module` of the Yosys JSON netlist `source`, mapping cells through `library`.
+pub fn parse(source: &str, module: &str, library: &CellLibrary) -> Result<Circuit, GadgetError> {
+    let netlist: Netlist =
+        serde_json::from_str(source).map_err(|err| GadgetError::CircuitParse {
+            line: err.line(),
+            reason: err.to_string(),
+        })?;
+    let module_name = module;
+    let module = netlist.modules.get(module_name).ok_or_else(|| {
+        GadgetError::InvalidCircuit(format!("module {module_name} not found in netlist"))
+    })?;
+
+    let mut builder = NetlistBuilder::default();
+
+    let mut inputs = vec![];
+    let mut output_ports = vec![];
+    for (port_name, port) in module.ports.iter() {
+        match port.direction.as_str() {
+            "input" => {
+                for (index, bit) in port.bits.iter().enumerate() {
+                    let name = bit_name(port_name, index, port.bits.len());
+                    match bit {
+                        Bit::Net(net) => {
+                            builder.net_names.insert(*net, name.clone());
+                        }
+                        Bit::Constant(_) => {
+                            return Err(GadgetError::InvalidCircuit(format!(
+                                "input {name} is tied to a constant"
+                            )))
+                        }
+                    }
+                    inputs.push(name);
+                }
+            }
+            "output" => output_ports.push((port_name, port)),
+            direction => {
+                return Err(GadgetError::InvalidCircuit(format!(
+                    "port {port_name} has unsupported direction {direction}"
+                )))
+            }
+        }
+    }
+
+    // nets driving output ports are named after the port, unless already named
+    let mut outputs = vec![];
+    let mut aliases = vec![];
+    for (port_name, port) in output_ports {
+        for (index, bit) in port.bits.iter().enumerate() {
+            let name = bit_name(port_name, index, port.bits.len());
+            match bit {
+                Bit::Net(net) => match builder.net_names.get(net) {
+                    Some(driver) => aliases.push((driver.clone(), name.clone())),
+                    None => {
+                        builder.net_names.insert(*net, name.clone());
+                    }
+                },
+                Bit::Constant(_) => {
+                    let value = builder.constant_value(bit)?;
+                    builder
+                        .gates
+                        .push(Gate::new(vec![], name.clone(), value as u128));
+                }
+            }
+            outputs.push(name);
+        }
+    }
+
+    for (cell_name, cell) in module.cells.iter() {
+        let gate = if cell.cell_type == "$lut" {
+            builder.lut_gate(cell_name, cell)?
+        } else {
+            let definition = library.get(&cell.cell_type).ok_or_else(|| {
+                GadgetError::InvalidCircuit(format!(
+                    "cell {cell_name} has unknown type {}",
+                    cell.cell_type
+                ))
+            })?;
+            let inputs = definition
+                .inputs
+                .iter()
+                .map(|port| builder.port_signal(cell_name, cell, port))
+                .collect::<Result<Vec<_>, _>>()?;
+            let output = builder.port_signal(cell_name, cell, &definition.output)?;
+            Gate::new(inputs, output, definition.tt_value)
+        };
+        builder.gates.push(gate);
+    }
+
+    // output ports sharing their net with an input or another output are buffered
+    for (driver, name) in aliases {
+        builder.gates.push(Gate::new(vec![driver], name, 0b10));
+    }
+
+    Circuit::new(module_name.to_string(), inputs, outputs, builder.gates)
+}
+
+#[derive(Default)]
+struct NetlistBuilder {
+    net_names: HashMap<u64, String>,
+    gates: Vec<Gate>,
+    constants: [bool; 2],
+}
+
+impl NetlistBuilder {
+    fn constant_value(&self, bit: &Bit) -> Result<bool, GadgetError> {
+        match bit {
+            // undefined bits are free to take any value
+            Bit::Constant(value) if value == "0" || value == "x" => Ok(false),
+            Bit::Constant(value) if value == "1" => Ok(true),
+            Bit::Constant(value) => Err(GadgetError::InvalidCircuit(format!(
+                "unsupported constant bit {value}"
+            ))),
+            Bit::Net(_) => unreachable!(),
+        }
+    }
+
+    fn bit_signal(&mut self, bit: &Bit) -> Result<String, GadgetError> {
+        match bit {
+            Bit::Net(net) => Ok(self
+                .net_names
+                .entry(*net)
+                .or_insert_with(|| format!("$n{net}"))
+                .clone()),
+            Bit::Constant(_) => {
+                let value = self.constant_value(bit)?;
+                let name = format!("$const{}", value as u8);
+                if !self.constants[value as usize] {
+                    self.constants[value as usize] = true;
+                    self.gates
+                        .push(Gate::new(vec![], name.clone(), value as u128));
+                }
+                Ok(name)
+            }
+        }
+    }
+
+    fn port_signal(
+        &mut self,
+        cell_name: &str,
+        cell: &Cell,
+        port: &str,
+    ) -> Result<String, GadgetError> {
+        match cell.connections.get(port).map(Vec::as_slice) {
+            Some([bit]) => self.bit_signal(bit),
+            _ => Err(GadgetError::InvalidCircuit(format!(
+                "port {port} of cell {cell_name} is not connected to a single bit"
+            ))),
+        }
+    }
+
+    /// Maps a `$lut` cell, whose `LUT` parameter is the truth table of its `A` input bits
+    fn lut_gate(&mut self, cell_name: &str, cell: &Cell) -> Result<Gate, GadgetError> {
+        let inputs = cell
+            .connections
+            .get("A")
+            .map(|bits| {
+                bits.iter()
+                    .map(|bit| self.bit_signal(bit))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let output = self.port_signal(cell_name, cell, "Y")?;
+
+        let lut = match cell.parameters.get("LUT") {
+            Some(serde_json::Value::String(lut)) => lut,
+            _ => {
+                return Err(GadgetError::InvalidCircuit(format!(
+                    "cell {cell_name} has no LUT parameter"
+                )))
+            }
+        };
+        // parameters are written as binary strings, MSB first
+        let tt_value = u128::from_str_radix(lut, 2).map_err(|_| {
+            GadgetError::InvalidCircuit(format!("cell {cell_name} has invalid LUT {lut}"))
+        })?;
+
+        Ok(Gate::new(inputs, output, tt_value))
+    }
+}
+
+fn bit_name(port_name: &str, index: usize, width: usize) -> String {
+    if width == 1 {
+        port_name.to_string()
+    } else {
+        format!("{port_name}[{index}]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HALF_ADDER: &str = r#"{
+  "creator": "Yosys",
+  "modules": {
+    "half_adder": {
+      "ports": {
+        "a": { "direction": "input", "bits": [ 2 ] },
+        "b": { "direction": "input", "bits": [ 3 ] },
+        "s": { "direction": "output", "bits": [ 4, 5 ] }
+      },
+      "cells": {
+        "$abc$1": {
+          "type": "$_XOR_",
+          "port_directions": { "A": "input", "B": "input", "Y": "output" },
+          "connections": { "A": [ 2 ], "B": [ 3 ], "Y": [ 4 ] }
+        },
+        "$abc$2": {
+          "type": "$lut",
+          "parameters": { "WIDTH": "00000000000000000000000000000010", "LUT": "1000" },
+          "connections": { "A": [ 2, 3 ], "Y": [ 5 ] }
+        }
+      }
+    }
+  }
+}"#;
+
+    #[test]
+    fn parse_cells() {
+        let circuit = parse(HALF_ADDER, "half_adder", &CellLibrary::default()).unwrap();
+        assert_eq!(circuit.inputs(), &["a", "b"]);
+        assert_eq!(circuit.outputs(), &["s[0]", "s[1]"]);
+
+        let gates: Vec<(&str, &[String], u128)> = circuit
+            .gates()
+            .iter()
+            .map(|gate| (gate.output(), gate.inputs(), gate.tt_value()))
+            .collect();
+        let inputs = ["a".to_string(), "b".to_string()];
+        assert_eq!(
+            gates,
+            vec![("s[0]", &inputs[..], 0b0110), ("s[1]", &inputs[..], 0b1000)]
+        );
+    }
+
+    #[test]
+    fn parse_with_custom_cells() {
+        let source = HALF_ADDER.replace("$_XOR_", "XOR2X1");
+        assert!(matches!(
+            parse(&source, "half_adder", &CellLibrary::default()),
+            Err(GadgetError::InvalidCircuit(_))
+        ));
+
+        let mut library = CellLibrary::default();
+        library.register("XOR2X1", &["A", "B"], "Y", 0b0110);
+        let circuit = parse(&source, "half_adder", &library).unwrap();
+        assert_eq!(circuit.gates().len(), 2);
+
+        assert!(matches!(
+            parse(&source, "full_adder", &library),
+            Err(GadgetError::InvalidCircuit(_))
+        ));
+    }
+}
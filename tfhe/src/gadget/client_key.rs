@@ -4,10 +4,21 @@
 //! encryption and decryption methods.
 
 use crate::boolean::engine::WithThreadLocalEngine;
+use crate::conformance::ParameterSetConformant;
+use crate::core_crypto::algorithms::{decrypt_lwe_ciphertext, decrypt_lwe_ciphertext_list};
+use crate::core_crypto::commons::dispersion::StandardDev;
+use crate::core_crypto::commons::parameters::{
+    CiphertextModulus, EncryptionKeyChoice, LweDimension, PlaintextCount,
+};
 use crate::core_crypto::entities::*;
-use crate::gadget::ciphertext::Ciphertext;
-use crate::gadget::engine::GadgetEngine;
+use crate::gadget::ciphertext::{Ciphertext, CiphertextList, TransportCiphertext};
+use crate::gadget::constant_time::decode_secret;
+use crate::gadget::engine::{
+    decode_with_distance, torus_modulus, zeroize, GadgetEngine, GadgetScalar,
+};
+use crate::gadget::error::{DecryptionError, GadgetError};
 use crate::gadget::parameters::GadgetParameters;
+use crate::named::Named;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Formatter};
 
@@ -17,18 +28,22 @@ use super::encoding::{self, Encoding};
 ///
 /// In more details, it contains:
 /// * `lwe_secret_key` - an LWE secret key, used to encrypt the inputs and decrypt the outputs.
-/// This secret key is also used in the generation of bootstrapping and key switching keys.
+///   This secret key is also used in the generation of bootstrapping and key switching keys.
 /// * `glwe_secret_key` - a GLWE secret key, used to generate the bootstrapping keys and key
-/// switching keys.
+///   switching keys. Its big LWE key encrypts the inputs and decrypts the outputs instead of the
+///   small one with the `Big` [`GadgetParameters::encryption_key_choice`].
 /// * `parameters` - the cryptographic parameter set.
+///
+/// The keys live on a 32 bits torus by default, see [`GadgetScalar`]. They are overwritten with
+/// zeros when the client key is dropped, clones included.
 #[derive(Clone, Serialize, Deserialize)]
-pub struct ClientKey {
-    pub(crate) lwe_secret_key: LweSecretKeyOwned<u32>,
-    pub(crate) glwe_secret_key: GlweSecretKeyOwned<u32>,
+pub struct ClientKey<Scalar: GadgetScalar = u32> {
+    pub(crate) lwe_secret_key: LweSecretKeyOwned<Scalar>,
+    pub(crate) glwe_secret_key: GlweSecretKeyOwned<Scalar>,
     pub(crate) parameters: GadgetParameters,
 }
 
-impl PartialEq for ClientKey {
+impl<Scalar: GadgetScalar> PartialEq for ClientKey<Scalar> {
     fn eq(&self, other: &Self) -> bool {
         self.parameters == other.parameters
             && self.lwe_secret_key == other.lwe_secret_key
@@ -36,20 +51,269 @@ impl PartialEq for ClientKey {
     }
 }
 
-impl Debug for ClientKey {
+impl<Scalar: GadgetScalar> Drop for ClientKey<Scalar> {
+    fn drop(&mut self) {
+        zeroize(self.lwe_secret_key.as_mut());
+        zeroize(self.glwe_secret_key.as_mut());
+    }
+}
+
+/// Prints the parameters of the key only, see [`ClientKey::debug_secrets`] to print the secret
+/// keys too.
+impl<Scalar: GadgetScalar> Debug for ClientKey<Scalar> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "ClientKey {{ ")?;
-        write!(f, "lwe_secret_key: {:?}, ", self.lwe_secret_key)?;
-        write!(f, "glwe_secret_key: {:?}, ", self.glwe_secret_key)?;
-        write!(f, "parameters: {:?}, ", self.parameters)?;
-        write!(f, "engine: CoreEngine, ")?;
-        write!(f, "}}")?;
-        Ok(())
+        f.debug_struct("ClientKey")
+            .field("lwe_secret_key", &format_args!("<redacted>"))
+            .field("glwe_secret_key", &format_args!("<redacted>"))
+            .field("parameters", &self.parameters)
+            .finish()
     }
 }
 
-impl ClientKey {
-    pub fn new(parameter_set: &GadgetParameters) -> ClientKey {
-        GadgetEngine::with_thread_local_mut(|engine| engine.create_client_key(parameter_set))
+/// Checks that the secret keys are binary and have the dimensions of `parameters`, the
+/// parameters of the key being `parameters` themselves.
+impl<Scalar: GadgetScalar> ParameterSetConformant for ClientKey<Scalar> {
+    type ParameterSet = GadgetParameters;
+
+    fn is_conformant(&self, parameters: &GadgetParameters) -> bool {
+        let is_binary = |key: &[Scalar]| key.iter().all(|bit| *bit <= Scalar::ONE);
+
+        self.parameters == *parameters
+            && self.lwe_secret_key.lwe_dimension() == parameters.lwe_dimension
+            && self.glwe_secret_key.glwe_dimension() == parameters.glwe_dimension
+            && self.glwe_secret_key.polynomial_size() == parameters.polynomial_size
+            && is_binary(self.lwe_secret_key.as_ref())
+            && is_binary(self.glwe_secret_key.as_ref())
+    }
+}
+
+impl Named for ClientKey<u32> {
+    const NAME: &'static str = "gadget::ClientKey";
+}
+
+impl Named for ClientKey<u64> {
+    const NAME: &'static str = "gadget::ClientKey64";
+}
+
+impl<Scalar: GadgetScalar> ClientKey<Scalar> {
+    /// Generates a new client key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parameter_set` is not supported by keys over `Scalar`, see
+    /// [`ClientKey::try_new`].
+    pub fn new(parameter_set: &GadgetParameters) -> Self {
+        GadgetEngine::with_thread_local_mut(|engine| Self::new_with_engine(engine, parameter_set))
+    }
+
+    /// Generates a new client key, or returns an error if the ciphertext modulus of
+    /// `parameter_set` is not a power of two fitting `Scalar`, if its grouping factor (see
+    /// [`GadgetParameters::grouping_factor`]) or its bootstrapping backend options are not
+    /// supported, or if its noise is below the precision of `Scalar`.
+    pub fn try_new(parameter_set: &GadgetParameters) -> Result<Self, GadgetError> {
+        GadgetEngine::with_thread_local_mut(|engine| engine.try_create_client_key(parameter_set))
+    }
+
+    /// Same as [`ClientKey::new`], drawing the randomness from `engine` instead of the
+    /// thread-local engine, see [`GadgetEngine::new_from_seeder`].
+    pub fn new_with_engine(engine: &mut GadgetEngine, parameter_set: &GadgetParameters) -> Self {
+        engine.create_client_key(parameter_set)
+    }
+
+    /// Returns a value whose [`Debug`] output includes the secret keys, which the [`Debug`]
+    /// output of the client key itself redacts. Meant for tests, the output must not be logged.
+    pub fn debug_secrets(&self) -> impl Debug + '_ {
+        struct SecretDebug<'a, Scalar: GadgetScalar>(&'a ClientKey<Scalar>);
+
+        impl<Scalar: GadgetScalar> Debug for SecretDebug<'_, Scalar> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct("ClientKey")
+                    .field("lwe_secret_key", &self.0.lwe_secret_key)
+                    .field("glwe_secret_key", &self.0.glwe_secret_key)
+                    .field("parameters", &self.0.parameters)
+                    .finish()
+            }
+        }
+
+        SecretDebug(self)
+    }
+
+    /// Returns the modulus of the ciphertexts encrypted under this key.
+    pub fn ciphertext_modulus(&self) -> CiphertextModulus<Scalar> {
+        self.parameters
+            .scalar_ciphertext_modulus()
+            .expect("ciphertext modulus checked at key generation")
+    }
+
+    /// Encrypts `messages` modulo `plaintext_modulus` into a contiguous list, borrowing the
+    /// thread-local engine and allocating once for the whole batch instead of once per message.
+    pub fn encrypt_many(&self, messages: &[u32], plaintext_modulus: u32) -> CiphertextList<Scalar> {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            engine.encrypt_many(messages, self, plaintext_modulus)
+        })
+    }
+
+    /// Decrypts `cts` into their messages modulo `plaintext_modulus`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of `cts` is a [`Ciphertext::Placeholder`].
+    pub fn decrypt_many(&self, cts: &[Ciphertext<Scalar>], plaintext_modulus: u32) -> Vec<u32> {
+        cts.iter()
+            .map(|ct| match ct {
+                Ciphertext::Encrypted(lwe_ct, _, _) => {
+                    let lwe_secret_key = self.decryption_key(lwe_ct.lwe_size().to_lwe_dimension());
+                    let decrypted = decrypt_lwe_ciphertext(&lwe_secret_key, lwe_ct);
+                    decode_secret(decrypted.0, plaintext_modulus, lwe_ct.ciphertext_modulus())
+                }
+                Ciphertext::Trivial(b) => *b as u32,
+                Ciphertext::Placeholder => {
+                    panic!("Ciphertext placeholder reached in gadget engine!")
+                }
+            })
+            .collect()
+    }
+
+    /// Decrypts the ciphertexts of `list` into their messages modulo its plaintext modulus.
+    pub fn decrypt_list(&self, list: &CiphertextList<Scalar>) -> Vec<u32> {
+        let lwe_secret_key = self.decryption_key(list.lwe_list.lwe_size().to_lwe_dimension());
+        let mut plaintexts = PlaintextList::new(Scalar::ZERO, PlaintextCount(list.len()));
+        decrypt_lwe_ciphertext_list(&lwe_secret_key, &list.lwe_list, &mut plaintexts);
+
+        let ciphertext_modulus = list.lwe_list.ciphertext_modulus();
+        plaintexts
+            .as_ref()
+            .iter()
+            .map(|plaintext| decode_secret(*plaintext, list.plaintext_modulus, ciphertext_modulus))
+            .collect()
+    }
+
+    /// Returns the LWE key fresh ciphertexts are encrypted under, see
+    /// [`GadgetParameters::encryption_key_choice`], along with the standard deviation of their
+    /// noise.
+    pub(crate) fn encryption_key(&self) -> (LweSecretKey<&[Scalar]>, StandardDev) {
+        match self.parameters.encryption_key_choice {
+            EncryptionKeyChoice::Big => (
+                self.glwe_secret_key.as_lwe_secret_key(),
+                self.parameters.glwe_modular_std_dev,
+            ),
+            EncryptionKeyChoice::Small => (
+                LweSecretKey::from_container(self.lwe_secret_key.as_ref()),
+                self.parameters.lwe_modular_std_dev,
+            ),
+        }
+    }
+
+    /// Returns the LWE key decrypting ciphertexts of dimension `lwe_dimension`: the small key if
+    /// it has this dimension, the big key derived from the GLWE key otherwise.
+    pub(crate) fn decryption_key(&self, lwe_dimension: LweDimension) -> LweSecretKey<&[Scalar]> {
+        if lwe_dimension == self.lwe_secret_key.lwe_dimension() {
+            LweSecretKey::from_container(self.lwe_secret_key.as_ref())
+        } else {
+            self.glwe_secret_key.as_lwe_secret_key()
+        }
+    }
+
+    /// Decrypts `ct` into its message modulo its plaintext modulus, along with the distance on
+    /// the torus between its decrypted value and the center of the window of the message: the
+    /// noise of the ciphertext as long as it stays below `1 / 2p`, or `1 / 4p` for an even `p`.
+    pub fn try_decrypt_modular(
+        &self,
+        ct: &Ciphertext<Scalar>,
+    ) -> Result<(u32, f64), DecryptionError> {
+        GadgetEngine::with_thread_local_mut(|engine| engine.try_decrypt(ct, self))
+    }
+
+    /// Decrypts `ct` into its message modulo `plaintext_modulus`, along with the margin on the
+    /// torus between its decrypted value and the closest decision boundary, at most `1 / 2p`
+    /// (`1 / 4p` for an even `p`, whose messages keep a padding bit).
+    ///
+    /// The margin is the noise the ciphertext can still take before decrypting to a neighbouring
+    /// message, which helps tuning parameter sets. Trivial ciphertexts have the largest margin.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ct` is a [`Ciphertext::Placeholder`].
+    pub fn decrypt_with_margin(
+        &self,
+        ct: &Ciphertext<Scalar>,
+        plaintext_modulus: u32,
+    ) -> (u32, f64) {
+        let max_margin = 1. / (2. * torus_modulus(plaintext_modulus) as f64);
+        match ct {
+            Ciphertext::Encrypted(lwe_ct, _, _) => {
+                let lwe_secret_key = self.decryption_key(lwe_ct.lwe_size().to_lwe_dimension());
+                let decrypted = decrypt_lwe_ciphertext(&lwe_secret_key, lwe_ct);
+                let (message, distance) = decode_with_distance(
+                    decrypted.0,
+                    plaintext_modulus,
+                    lwe_ct.ciphertext_modulus(),
+                );
+                (message, max_margin - distance)
+            }
+            Ciphertext::Trivial(b) => (*b as u32, max_margin),
+            Ciphertext::Placeholder => panic!("Ciphertext placeholder reached in gadget engine!"),
+        }
+    }
+
+    /// Decrypts `ct` with the small LWE key into its message modulo its plaintext modulus, e.g.
+    /// an output shrunk by
+    /// [`ServerKey::switch_to_small_key`](super::server_key::ServerKey::switch_to_small_key).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ct` is a [`Ciphertext::Placeholder`] or is not encrypted under the small key.
+    pub fn decrypt_small(&self, ct: &Ciphertext<Scalar>) -> u32 {
+        match ct {
+            Ciphertext::Encrypted(lwe_ct, plaintext_modulus, _) => {
+                assert_eq!(
+                    lwe_ct.lwe_size().to_lwe_dimension(),
+                    self.lwe_secret_key.lwe_dimension(),
+                    "the ciphertext is not encrypted under the small LWE key"
+                );
+                let decrypted = decrypt_lwe_ciphertext(&self.lwe_secret_key, lwe_ct);
+                decode_secret(decrypted.0, *plaintext_modulus, lwe_ct.ciphertext_modulus())
+            }
+            Ciphertext::Trivial(b) => *b as u32,
+            Ciphertext::Placeholder => panic!("Ciphertext placeholder reached in gadget engine!"),
+        }
+    }
+
+    /// Decrypts a ciphertext switched to a smaller modulus for transmission into its message
+    /// modulo its plaintext modulus, see [`Ciphertext::compress_for_transport`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the LWE dimension of `ct` is neither the small nor the big one of this key.
+    pub fn decrypt_transport(&self, ct: &TransportCiphertext) -> u32 {
+        match ct {
+            TransportCiphertext::Encrypted(lwe, plaintext_modulus) => {
+                let lwe_secret_key = self.decryption_key(lwe.lwe_dimension());
+                assert_eq!(
+                    lwe.lwe_dimension(),
+                    lwe_secret_key.lwe_dimension(),
+                    "the ciphertext was not encrypted under this key"
+                );
+
+                let log_modulus = lwe.log_modulus().0;
+                let coefficients = lwe.coefficients();
+                let (mask, body) = coefficients.split_at(coefficients.len() - 1);
+                let phase =
+                    mask.iter()
+                        .zip(lwe_secret_key.as_ref())
+                        .fold(body[0], |phase, (mask, key)| {
+                            let key: u64 = (*key).cast_into();
+                            phase.wrapping_sub(mask.wrapping_mul(key))
+                        });
+
+                // lifts the phase to the most significant bits of the native 64 bits torus
+                decode_secret(
+                    phase << (64 - log_modulus),
+                    *plaintext_modulus,
+                    CiphertextModulus::<u64>::new_native(),
+                )
+            }
+            TransportCiphertext::Trivial(value) => *value as u32,
+        }
     }
 }
@@ -0,0 +1,132 @@
+//! Constant-time encoding and decoding of secret messages on the client.
+//!
+//! The plain encoding divides by the number of slots of the torus and the plain decoding reduces
+//! modulo it, both on values depending on the message. Integer divisions take a data-dependent
+//! time on many CPUs, which a side channel on the client can observe. With the
+//! `p-encoding-constant-time` feature, the client encryptions and decryptions go through the
+//! [`subtle`] based implementations of this module instead, whose running time depends on the
+//! plaintext modulus only: encoding selects the plaintext of the message among the encodings of
+//! every slot of the torus, decoding rounds with a multiplication and a shift and reduces with
+//! conditional subtractions.
+//!
+//! [`ENABLED`] reports whether the feature is enabled, for applications to assert it. Audit of
+//! the gadget code paths handling secret values:
+//! * the LWE encryptions and decryptions of `core_crypto` only use wrapping additions and
+//!   multiplications, whose time does not depend on the key nor on the message;
+//! * the encodings and decodings of [`ClientKey`](super::client_key::ClientKey) encryptions and
+//!   decryptions, of [`ClientKey::decrypt_packed`](super::client_key::ClientKey::decrypt_packed),
+//!   of the threshold decryptions and of the multiparty public key encryptions go through this
+//!   module;
+//! * [`ClientKey::try_decrypt_modular`](super::client_key::ClientKey::try_decrypt_modular) and
+//!   [`ClientKey::decrypt_with_margin`](super::client_key::ClientKey::decrypt_with_margin) also
+//!   measure the noise with floating point arithmetic and are not constant time, they are meant
+//!   for diagnostics;
+//! * ciphertexts under a non native modulus are scaled down by a division before decoding, only
+//!   native moduli are constant time;
+//! * the server branches on whether ciphertexts are
+//!   [`Ciphertext::Trivial`](super::ciphertext::Ciphertext::Trivial) and on their value, which
+//!   are public by definition, never on encrypted values.
+
+use crate::core_crypto::commons::parameters::CiphertextModulus;
+use crate::gadget::engine::GadgetScalar;
+#[cfg(not(feature = "p-encoding-constant-time"))]
+use crate::gadget::engine::{decode, encode};
+#[cfg(feature = "p-encoding-constant-time")]
+use crate::gadget::engine::{encode_slot, modulus_bits, torus_modulus};
+#[cfg(feature = "p-encoding-constant-time")]
+use subtle::{ConditionallySelectable, ConstantTimeEq, ConstantTimeGreater};
+
+/// Whether the client encodes and decodes secret messages in constant time, i.e. whether the
+/// `p-encoding-constant-time` feature is enabled.
+pub const ENABLED: bool = cfg!(feature = "p-encoding-constant-time");
+
+/// Same as [`encode`](super::engine::encode) for a secret `message` lower than
+/// `plaintext_modulus`, in constant time if [`ENABLED`].
+pub(crate) fn encode_secret<Scalar: GadgetScalar>(
+    message: u32,
+    plaintext_modulus: u32,
+    ciphertext_modulus: CiphertextModulus<Scalar>,
+) -> Scalar {
+    #[cfg(feature = "p-encoding-constant-time")]
+    {
+        let message = message as u64;
+        let torus_modulus = torus_modulus(plaintext_modulus);
+        let mut encoded = 0u64;
+        for slot in 0..torus_modulus {
+            let candidate: u128 =
+                encode_slot::<Scalar>(slot, torus_modulus, ciphertext_modulus).cast_into();
+            encoded.conditional_assign(&(candidate as u64), slot.ct_eq(&message));
+        }
+        Scalar::cast_from(encoded as u128)
+    }
+    #[cfg(not(feature = "p-encoding-constant-time"))]
+    encode(message, plaintext_modulus, ciphertext_modulus)
+}
+
+/// Same as [`decode`](super::engine::decode) for a secret `value`, in constant time if [`ENABLED`].
+pub(crate) fn decode_secret<Scalar: GadgetScalar>(
+    value: Scalar,
+    plaintext_modulus: u32,
+    ciphertext_modulus: CiphertextModulus<Scalar>,
+) -> u32 {
+    #[cfg(feature = "p-encoding-constant-time")]
+    {
+        let bits = modulus_bits(ciphertext_modulus);
+        let value: u128 = value.cast_into();
+        let torus_modulus = torus_modulus(plaintext_modulus);
+
+        // ((t * d) + (q/2)) / q, in [0, t] as d < q
+        let rounded = ((value * torus_modulus as u128 + (1 << (bits - 1))) >> bits) as u64;
+        let mut slot = rounded;
+        slot.conditional_assign(&0, rounded.ct_eq(&torus_modulus));
+
+        // the slots of even moduli cover the torus twice
+        let plaintext_modulus = plaintext_modulus as u64;
+        let wrapped = slot.wrapping_sub(plaintext_modulus);
+        slot.conditional_assign(&wrapped, !plaintext_modulus.ct_gt(&slot));
+        slot as u32
+    }
+    #[cfg(not(feature = "p-encoding-constant-time"))]
+    decode(value, plaintext_modulus, ciphertext_modulus)
+}
+
+#[cfg(all(test, feature = "p-encoding-constant-time"))]
+mod tests {
+    use super::*;
+    use crate::gadget::engine::{decode, encode};
+
+    fn matches_plain_encoding<Scalar: GadgetScalar>(ciphertext_modulus: CiphertextModulus<Scalar>) {
+        let bits = modulus_bits(ciphertext_modulus);
+        for plaintext_modulus in 2..=16 {
+            for message in 0..plaintext_modulus {
+                let encoded = encode_secret(message, plaintext_modulus, ciphertext_modulus);
+                assert_eq!(
+                    encoded,
+                    encode(message, plaintext_modulus, ciphertext_modulus)
+                );
+                assert_eq!(
+                    decode_secret(encoded, plaintext_modulus, ciphertext_modulus),
+                    message
+                );
+            }
+
+            // values around the slot boundaries, including the wrap around the torus
+            let step = (1u128 << bits) / (4 * torus_modulus(plaintext_modulus) as u128);
+            for i in 0..(4 * torus_modulus(plaintext_modulus) as u128) {
+                for value in [i * step, (i * step).saturating_sub(1), i * step + 1] {
+                    let value = Scalar::cast_from(value % (1 << bits));
+                    assert_eq!(
+                        decode_secret(value, plaintext_modulus, ciphertext_modulus),
+                        decode(value, plaintext_modulus, ciphertext_modulus)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn constant_time_encoding_matches_plain_encoding() {
+        matches_plain_encoding(CiphertextModulus::<u32>::new_native());
+        matches_plain_encoding(CiphertextModulus::<u64>::new_native());
+    }
+}
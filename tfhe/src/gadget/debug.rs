@@ -0,0 +1,222 @@
+//! Shadow evaluation of circuits for debugging.
+//!
+//! A [`ShadowServerKey`] pairs the server key with the client key. Every gate is evaluated on
+//! ciphertexts and its inputs and output are decrypted, so that the first gate failing because
+//! of a bad encoding or of too much noise is reported, instead of a wrong circuit output.
+//!
+//! Holding the client key next to the server key defeats the purpose of FHE, this module is only
+//! available with the `p-encoding-debug` feature and must only be used to diagnose parameters
+//! and encodings.
+
+use crate::boolean::engine::WithThreadLocalEngine;
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::circuit::Circuit;
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::encoding::Encoding;
+use crate::gadget::engine::GadgetEngine;
+use crate::gadget::error::GadgetError;
+use crate::gadget::server_key::ServerKey;
+use crate::gadget::simulator::SimulatorKey;
+use std::collections::HashMap;
+
+/// Evaluates circuits on ciphertexts while checking every gate against its decrypted inputs.
+pub struct ShadowServerKey<'a> {
+    server_key: &'a ServerKey,
+    client_key: &'a ClientKey,
+}
+
+impl<'a> ShadowServerKey<'a> {
+    pub fn new(server_key: &'a ServerKey, client_key: &'a ClientKey) -> ShadowServerKey<'a> {
+        ShadowServerKey {
+            server_key,
+            client_key,
+        }
+    }
+
+    /// Evaluates the gate described by `encoding`, checking that its decrypted output matches
+    /// the simulation of `encoding` on the decrypted inputs.
+    ///
+    /// `signal` names the gate output in the returned errors.
+    pub fn evaluate_gate(
+        &self,
+        signal: &str,
+        input_ciphertexts: Vec<Ciphertext>,
+        encoding: &Encoding,
+    ) -> Result<Ciphertext, GadgetError> {
+        let input_ciphertexts: Vec<&Ciphertext> = input_ciphertexts.iter().collect();
+        self.evaluate_gate_ref(signal, &input_ciphertexts, encoding)
+    }
+
+    /// Same as [`Self::evaluate_gate`] on borrowed inputs, see [`ServerKey::evaluate_gate_ref`].
+    pub fn evaluate_gate_ref(
+        &self,
+        signal: &str,
+        input_ciphertexts: &[&Ciphertext],
+        encoding: &Encoding,
+    ) -> Result<Ciphertext, GadgetError> {
+        let expected = self.simulate(input_ciphertexts, encoding)?;
+
+        let output = self
+            .server_key
+            .evaluate_gate_ref(input_ciphertexts, encoding)?;
+        let actual = self.decrypt(&output);
+        if actual != expected {
+            return Err(GadgetError::ShadowNoiseMismatch {
+                signal: signal.to_string(),
+                expected,
+                actual,
+            });
+        }
+
+        Ok(output)
+    }
+
+    /// Evaluates `circuit` gate by gate in topological order, returning the first gate whose
+    /// encoding or decrypted output is wrong as an error.
+    pub fn evaluate_circuit(
+        &self,
+        circuit: &Circuit,
+        inputs: &HashMap<String, Ciphertext>,
+    ) -> Result<HashMap<String, Ciphertext>, GadgetError> {
+        let mut signals: HashMap<&str, Ciphertext> = HashMap::new();
+        for input in circuit.inputs.iter() {
+            let ct = inputs
+                .get(input)
+                .ok_or_else(|| GadgetError::MissingCircuitInput(input.clone()))?;
+            signals.insert(input, ct.clone());
+        }
+
+        for (gate, encoding) in circuit.gates.iter().zip(circuit.encodings.iter()) {
+            let output = match encoding {
+                Some(encoding) => {
+                    let input_ciphertexts: Vec<&Ciphertext> = gate
+                        .inputs
+                        .iter()
+                        .map(|input| &signals[input.as_str()])
+                        .collect();
+
+                    // the encoding must realize the truth table on the decrypted inputs
+                    let row = input_ciphertexts
+                        .iter()
+                        .enumerate()
+                        .fold(0u128, |row, (pin, ct)| {
+                            row | ((self.decrypt(ct) as u128 & 1) << pin)
+                        });
+                    let tt_output = ((gate.tt_value >> row) & 1) as u32;
+                    let simulated_output = self.simulate(&input_ciphertexts, encoding)?;
+                    if simulated_output != tt_output {
+                        return Err(GadgetError::ShadowEncodingMismatch {
+                            signal: gate.output.clone(),
+                            expected: tt_output,
+                            actual: simulated_output,
+                        });
+                    }
+
+                    self.evaluate_gate_ref(&gate.output, &input_ciphertexts, encoding)?
+                }
+                None => Ciphertext::Trivial(gate.tt_value & 1 == 1),
+            };
+            signals.insert(&gate.output, output);
+        }
+
+        Ok(circuit
+            .outputs
+            .iter()
+            .map(|output| (output.clone(), signals[output.as_str()].clone()))
+            .collect())
+    }
+
+    /// Simulates `encoding` on the decrypted `input_ciphertexts`.
+    fn simulate(
+        &self,
+        input_ciphertexts: &[&Ciphertext],
+        encoding: &Encoding,
+    ) -> Result<u32, GadgetError> {
+        let simulator = SimulatorKey::new();
+        let simulated_inputs = input_ciphertexts
+            .iter()
+            .map(|ct| {
+                // trivial ciphertexts are canonical and fit any plaintext modulus
+                let p = ct.plaintext_modulus().unwrap_or(encoding.p());
+                simulator.encrypt(self.decrypt(ct), p)
+            })
+            .collect();
+
+        simulator
+            .evaluate_gate(simulated_inputs, encoding)
+            .map(|ct| simulator.decrypt(&ct))
+    }
+
+    fn decrypt(&self, ct: &Ciphertext) -> u32 {
+        GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(ct, self.client_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadget::circuit::Gate;
+    use crate::gadget::gen_keys;
+    use crate::gadget::parameters::PLAINTEXT_3_BITS_PARAMETERS;
+
+    #[test]
+    fn shadow_evaluation_reports_bad_encoding() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let shadow_key = ShadowServerKey::new(&server_key, &client_key);
+
+        let circuit = Circuit::new(
+            "and".to_string(),
+            vec!["a".to_string(), "b".to_string()],
+            vec!["y".to_string()],
+            vec![Gate::new(
+                vec!["a".to_string(), "b".to_string()],
+                "y".to_string(),
+                0b1000,
+            )],
+        )
+        .unwrap();
+
+        let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+            [("a", 1), ("b", 1)]
+                .into_iter()
+                .map(|(name, bit)| (name.to_string(), engine.encrypt(bit, &client_key, 3)))
+                .collect::<HashMap<_, _>>()
+        });
+        let outputs = shadow_key.evaluate_circuit(&circuit, &inputs).unwrap();
+        assert_eq!(shadow_key.decrypt(&outputs["y"]), 1);
+
+        // a signal fanning out to both pins is only borrowed
+        let encoding = circuit.encodings[0].as_ref().unwrap();
+        let output = shadow_key
+            .evaluate_gate_ref("y", &[&inputs["a"], &inputs["a"]], encoding)
+            .unwrap();
+        assert_eq!(shadow_key.decrypt(&output), 1);
+
+        // an or encoding standing for the and gate
+        let mut bad_circuit = circuit.clone();
+        bad_circuit.encodings[0] = Some(Encoding::new_canonical(
+            14,
+            2,
+            vec![1, 1],
+            vec![0],
+            vec![1, 2],
+            3,
+        ));
+        let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+            [("a", 1), ("b", 0)]
+                .into_iter()
+                .map(|(name, bit)| (name.to_string(), engine.encrypt(bit, &client_key, 3)))
+                .collect::<HashMap<_, _>>()
+        });
+        assert_eq!(
+            shadow_key
+                .evaluate_circuit(&bad_circuit, &inputs)
+                .unwrap_err(),
+            GadgetError::ShadowEncodingMismatch {
+                signal: "y".to_string(),
+                expected: 0,
+                actual: 1,
+            }
+        );
+    }
+}
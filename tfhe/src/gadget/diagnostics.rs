@@ -0,0 +1,135 @@
+//! Empirical measurement of the noise of ciphertexts.
+//!
+//! The noise estimates of [`GadgetParameters`](super::parameters::GadgetParameters), e.g.
+//! [`bootstrapped_noise_variance`](super::parameters::GadgetParameters::bootstrapped_noise_variance),
+//! drive the choice of parameters. [`measure_noise`] computes the actual noise of a ciphertext
+//! with the client key and [`noise_distribution`] summarizes many such samples, so that custom
+//! parameter sets can be validated against the estimates.
+
+use crate::core_crypto::algorithms::decrypt_lwe_ciphertext;
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::engine::{decode_with_noise, GadgetScalar};
+use crate::gadget::error::DecryptionError;
+
+/// Returns the signed noise on the torus of `ct`, the difference between its decrypted value and
+/// the encoding of its message.
+///
+/// The message is the closest one to the decrypted value, i.e. the noise is exact as long as it
+/// stays within `1 / 2p`, or `1 / 4p` for an even `p`. Trivial ciphertexts have no noise.
+pub fn measure_noise<Scalar: GadgetScalar>(
+    client_key: &ClientKey<Scalar>,
+    ct: &Ciphertext<Scalar>,
+) -> Result<f64, DecryptionError> {
+    match ct {
+        Ciphertext::Encrypted(lwe_ct, plaintext_modulus, _) => {
+            let lwe_secret_key = client_key.decryption_key(lwe_ct.lwe_size().to_lwe_dimension());
+            let decrypted = decrypt_lwe_ciphertext(&lwe_secret_key, lwe_ct);
+            let (_, noise) =
+                decode_with_noise(decrypted.0, *plaintext_modulus, lwe_ct.ciphertext_modulus());
+            Ok(noise)
+        }
+        Ciphertext::Trivial(_) => Ok(0.),
+        Ciphertext::Placeholder => Err(DecryptionError::PlaceholderCiphertext),
+    }
+}
+
+/// Empirical distribution of noise samples on the torus, see [`noise_distribution`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NoiseDistribution {
+    pub sample_count: usize,
+    pub mean: f64,
+    /// Unbiased sample variance, to compare with the variance estimates of the parameters
+    pub variance: f64,
+    /// Largest absolute noise sampled
+    pub max_abs: f64,
+}
+
+impl NoiseDistribution {
+    pub fn std_dev(&self) -> f64 {
+        self.variance.sqrt()
+    }
+}
+
+/// Draws `sample_count` noise samples from `sample`, e.g. the [`measure_noise`] of freshly
+/// bootstrapped ciphertexts, and returns their distribution.
+///
+/// # Panics
+///
+/// Panics if `sample_count` is less than 2.
+pub fn noise_distribution(sample_count: usize, sample: &dyn Fn() -> f64) -> NoiseDistribution {
+    assert!(
+        sample_count >= 2,
+        "At least 2 samples are needed to estimate a variance, got {sample_count}"
+    );
+
+    let samples: Vec<f64> = (0..sample_count).map(|_| sample()).collect();
+
+    let mean = samples.iter().sum::<f64>() / sample_count as f64;
+    let variance = samples
+        .iter()
+        .map(|noise| (noise - mean) * (noise - mean))
+        .sum::<f64>()
+        / (sample_count - 1) as f64;
+    let max_abs = samples
+        .iter()
+        .fold(0., |max: f64, noise| max.max(noise.abs()));
+
+    NoiseDistribution {
+        sample_count,
+        mean,
+        variance,
+        max_abs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean::engine::WithThreadLocalEngine;
+    use crate::gadget::encoding::Encoding;
+    use crate::gadget::engine::GadgetEngine;
+    use crate::gadget::gen_keys;
+    use crate::gadget::parameters::PLAINTEXT_2_BITS_PARAMETERS;
+
+    const SAMPLE_COUNT: usize = 1000;
+
+    #[test]
+    fn measured_noise_matches_estimates() {
+        let parameters = PLAINTEXT_2_BITS_PARAMETERS;
+        let (client_key, server_key) = gen_keys(&parameters);
+        let p = 3;
+        let refresh_encoding = Encoding::new_refresh(p);
+
+        let encrypt =
+            || GadgetEngine::with_thread_local_mut(|engine| engine.encrypt(1, &client_key, p));
+
+        let fresh = noise_distribution(SAMPLE_COUNT, &|| {
+            measure_noise(&client_key, &encrypt()).unwrap()
+        });
+        assert_eq!(fresh.sample_count, SAMPLE_COUNT);
+        let ratio = fresh.variance / parameters.fresh_noise_variance();
+        assert!((0.8..1.2).contains(&ratio), "{fresh:?}");
+        assert!(fresh.mean.abs() < 4. * fresh.std_dev() / (SAMPLE_COUNT as f64).sqrt());
+
+        // the estimate of the bootstrapped noise bounds the actual noise
+        let bootstrapped = noise_distribution(SAMPLE_COUNT / 10, &|| {
+            let ct = server_key.bootstrap(encrypt(), &refresh_encoding).unwrap();
+            measure_noise(&client_key, &ct).unwrap()
+        });
+        assert!(
+            bootstrapped.variance < 2. * parameters.bootstrapped_noise_variance(),
+            "{bootstrapped:?}"
+        );
+        assert!(bootstrapped.max_abs < 1. / (4. * p as f64));
+
+        assert_eq!(
+            measure_noise(&client_key, &Ciphertext::Trivial(true)),
+            Ok(0.)
+        );
+        assert_eq!(
+            measure_noise(&client_key, &Ciphertext::Placeholder),
+            Err(DecryptionError::PlaceholderCiphertext)
+        );
+    }
+}
@@ -0,0 +1,256 @@
+//! Normal form of encodings and deduplication of the encodings of a netlist.
+//!
+//! Netlists instantiate the same few cells thousands of times, often with their pins wired in a
+//! different order, each order giving a different truth table and different input mappings.
+//! Reordering the pins of every encoding in a fixed way, see [`Encoding::canonicalize`], lets an
+//! [`EncodingCache`] map all of them to one normal form, whose lookup table the server key then
+//! builds and caches once.
+
+use super::{Encoding, EncodingSet};
+use std::collections::HashMap;
+
+impl Encoding {
+    /// Returns the normal form of the encoding together with the pin order of its inputs.
+    ///
+    /// The output sets of the normal form are sorted and deduplicated and its pins are ordered
+    /// by increasing `(input_mapping_0, input_mapping_1)`, the truth table being permuted
+    /// accordingly. Pin `i` of the normal form is pin `pins[i]` of `self`: evaluating the normal
+    /// form on `inputs[pins[0]], inputs[pins[1]], ...` is evaluating `self` on `inputs`, see
+    /// [`CachedEncoding::reorder_inputs`].
+    ///
+    /// Valid encodings (see [`Encoding::check_truth_table`]) of a gate up to a permutation of
+    /// its pins share their normal form. The pins of a malformed encoding are left in place.
+    ///
+    /// ```rust
+    /// use tfhe::gadget::encoding::Encoding;
+    ///
+    /// // a & !b and !a & b, the negated input being mapped to 2
+    /// let and_not_b = Encoding::from_mappings(0b0010, vec![2, 1], 3).unwrap();
+    /// let and_not_a = Encoding::from_mappings(0b0100, vec![1, 2], 3).unwrap();
+    ///
+    /// let (normal_form, pins) = and_not_b.canonicalize();
+    /// assert_eq!(pins, [0, 1]);
+    /// assert_eq!(and_not_a.canonicalize(), (normal_form, vec![1, 0]));
+    /// ```
+    pub fn canonicalize(&self) -> (Encoding, Vec<usize>) {
+        let mut canonical = self.clone();
+        for output_encodings in [
+            &mut canonical.output_encodings_0,
+            &mut canonical.output_encodings_1,
+        ] {
+            output_encodings.sort_unstable();
+            output_encodings.dedup();
+        }
+
+        let pin_count = self.pin_count;
+        let mut pins: Vec<usize> = (0..pin_count).collect();
+        if super::check_truth_table_shape(self.tt_value, pin_count).is_err()
+            || super::check_mappings(self).is_err()
+        {
+            return (canonical, pins);
+        }
+
+        // mappings are stored in reverse pin order
+        let mappings = |pin: usize| {
+            (
+                self.input_mappings_0[pin_count - 1 - pin],
+                self.input_mappings_1[pin_count - 1 - pin],
+            )
+        };
+        pins.sort_by_key(|pin| mappings(*pin));
+
+        canonical.tt_value = 0;
+        for row in 0..(1u128 << pin_count) {
+            let original_row = pins
+                .iter()
+                .enumerate()
+                .filter(|(pin, _)| (row >> pin) & 1 == 1)
+                .fold(0usize, |original_row, (_, original_pin)| {
+                    original_row | (1 << original_pin)
+                });
+            canonical.tt_value |= ((self.tt_value >> original_row) & 1) << row;
+        }
+
+        for (pin, original_pin) in pins.iter().enumerate() {
+            let (mapping_0, mapping_1) = mappings(*original_pin);
+            canonical.input_mappings_0[pin_count - 1 - pin] = mapping_0;
+            canonical.input_mappings_1[pin_count - 1 - pin] = mapping_1;
+        }
+
+        (canonical, pins)
+    }
+}
+
+/// The normal form of an encoding in an [`EncodingCache`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CachedEncoding {
+    /// Index of the normal form in [`EncodingCache::encodings`]
+    pub index: usize,
+    /// Pin order of the inputs of the normal form, see [`Encoding::canonicalize`]
+    pub pins: Vec<usize>,
+}
+
+impl CachedEncoding {
+    /// Reorders the inputs of a gate, in the pin order of its encoding, to the pin order of the
+    /// normal form.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of inputs differs from the pin count of the encoding.
+    pub fn reorder_inputs<T>(&self, inputs: Vec<T>) -> Vec<T> {
+        assert_eq!(
+            inputs.len(),
+            self.pins.len(),
+            "Expected {} inputs, got {}",
+            self.pins.len(),
+            inputs.len()
+        );
+
+        let mut inputs: Vec<Option<T>> = inputs.into_iter().map(Some).collect();
+        self.pins
+            .iter()
+            .map(|pin| inputs[*pin].take().unwrap())
+            .collect()
+    }
+}
+
+/// Deduplicates the encodings of many gates up to a permutation of their pins.
+///
+/// Each distinct encoding is canonicalized once, gates whose encodings share a normal form share
+/// its index, so that evaluating the normal forms instead of the original encodings builds a
+/// single lookup table per normal form.
+///
+/// ```rust
+/// use tfhe::gadget::encoding::{Encoding, EncodingCache};
+///
+/// let and_not_b = Encoding::from_mappings(0b0010, vec![2, 1], 3).unwrap();
+/// let and_not_a = Encoding::from_mappings(0b0100, vec![1, 2], 3).unwrap();
+///
+/// let mut cache = EncodingCache::new();
+/// let first = cache.insert(&and_not_b).clone();
+/// let second = cache.insert(&and_not_a).clone();
+/// assert_eq!(first.index, second.index);
+/// assert_eq!(second.reorder_inputs(vec!["a", "b"]), ["b", "a"]);
+/// assert_eq!(cache.encodings().len(), 1);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct EncodingCache {
+    normal_forms: EncodingSet,
+    entries: HashMap<Encoding, CachedEncoding>,
+}
+
+impl EncodingCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the normal form of `encoding`, canonicalizing it on its first insertion.
+    pub fn insert(&mut self, encoding: &Encoding) -> &CachedEncoding {
+        if !self.entries.contains_key(encoding) {
+            let (normal_form, pins) = encoding.canonicalize();
+            let index = self.normal_forms.insert(normal_form);
+            self.entries
+                .insert(encoding.clone(), CachedEncoding { index, pins });
+        }
+        &self.entries[encoding]
+    }
+
+    /// Returns the normal form of `encoding` if it has been inserted.
+    pub fn get(&self, encoding: &Encoding) -> Option<&CachedEncoding> {
+        self.entries.get(encoding)
+    }
+
+    /// Returns the distinct normal forms, indexed by [`CachedEncoding::index`].
+    pub fn encodings(&self) -> &EncodingSet {
+        &self.normal_forms
+    }
+
+    /// Returns the number of distinct encodings inserted, before canonicalization.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean::engine::WithThreadLocalEngine;
+    use crate::gadget::encoding::library;
+    use crate::gadget::engine::GadgetEngine;
+    use crate::gadget::gen_keys;
+    use crate::gadget::parameters::PLAINTEXT_3_BITS_PARAMETERS;
+
+    /// Returns the encoding of `encoding` with its pins wired in the order `pins`.
+    fn permute_pins(encoding: &Encoding, pins: &[usize]) -> Encoding {
+        let pin_count = encoding.pin_count();
+        let tt_value = (0..(1u128 << pin_count)).fold(0, |tt_value, row| {
+            let original_row = (0..pin_count)
+                .filter(|pin| (row >> pins[*pin]) & 1 == 1)
+                .fold(0usize, |original_row, pin| original_row | (1 << pin));
+            tt_value | (((encoding.tt_value() >> original_row) & 1) << row)
+        });
+        let mut input_mappings_1 = vec![0; pin_count];
+        for (pin, new_pin) in pins.iter().enumerate() {
+            input_mappings_1[pin_count - 1 - new_pin] =
+                encoding.input_mappings_1()[pin_count - 1 - pin];
+        }
+        Encoding::from_mappings(tt_value, input_mappings_1, encoding.p()).unwrap()
+    }
+
+    #[test]
+    fn permuted_encodings_share_normal_form() {
+        let mut cache = EncodingCache::new();
+        for name in ["aoi21", "mux2", "oai22", "maj3"] {
+            let encoding = library::get(name).unwrap();
+            let (normal_form, _) = encoding.canonicalize();
+            normal_form.check_truth_table().unwrap();
+            assert_eq!(normal_form.canonicalize().0, normal_form);
+
+            let pin_count = encoding.pin_count();
+            let reversed: Vec<usize> = (0..pin_count).rev().collect();
+            let rotated: Vec<usize> = (0..pin_count).map(|pin| (pin + 1) % pin_count).collect();
+            for pins in [reversed, rotated] {
+                let permuted = permute_pins(encoding, &pins);
+                permuted.check_truth_table().unwrap();
+                assert_eq!(permuted.canonicalize().0, normal_form);
+                let index = cache.insert(encoding).index;
+                assert_eq!(cache.insert(&permuted).index, index);
+            }
+        }
+        assert_eq!(cache.encodings().len(), 4);
+
+        // output sets are sorted
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![1, 0, 1], vec![2], 3);
+        assert_eq!(encoding.canonicalize().0.output_encodings_0(), &[0, 1]);
+    }
+
+    #[test]
+    fn evaluate_normal_form() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let mux = library::get("mux2").unwrap();
+        let mut cache = EncodingCache::new();
+
+        // mux with the select signal as pin 0
+        let permuted = permute_pins(mux, &[1, 2, 0]);
+        let cached = cache.insert(&permuted).clone();
+        let normal_form = cache.encodings().get(cached.index).unwrap().clone();
+
+        for row in 0..8u32 {
+            let inputs: Vec<_> = GadgetEngine::with_thread_local_mut(|engine| {
+                (0..3)
+                    .map(|pin| engine.encrypt((row >> pin) & 1, &client_key, permuted.p()))
+                    .collect()
+            });
+            let out = server_key
+                .evaluate_gate(cached.reorder_inputs(inputs), &normal_form)
+                .unwrap();
+            let decrypted =
+                GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&out, &client_key));
+            assert_eq!(decrypted as u128, (permuted.tt_value() >> row) & 1);
+        }
+    }
+}
@@ -0,0 +1,363 @@
+//! Compact binary format of encodings.
+//!
+//! Encodings are shipped by the thousands alongside netlists, their JSON form spends most of its
+//! bytes on field names and decimal digits. The compact format stores every integer as an
+//! LEB128 varint, which takes a single byte for the small values of the mappings and the output
+//! sets, and the number of input mappings is implied by the pin count:
+//!
+//! `version | tt_value | pin_count | p | new_p | new_0 | new_1 | input_mappings_0 |
+//! input_mappings_1 | len | output_encodings_0 | len | output_encodings_1`
+//!
+//! An [`EncodingSet`] stores each distinct encoding once, gates refer to their encoding by its
+//! index in the set.
+
+use super::Encoding;
+use crate::gadget::error::GadgetError;
+use crate::named::Named;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Version of the compact format, the first byte of the serialized data.
+const COMPACT_FORMAT_VERSION: u8 = 0;
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u128) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn write_list(bytes: &mut Vec<u8>, values: &[u32]) {
+    values
+        .iter()
+        .for_each(|value| write_varint(bytes, *value as u128));
+}
+
+fn invalid_bytes(reason: &str) -> GadgetError {
+    GadgetError::InvalidEncoding(format!("invalid compact encoding: {reason}"))
+}
+
+/// Reads the compact format, failing on truncated data and on values overflowing their field.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn read_varint(&mut self) -> Result<u128, GadgetError> {
+        let mut value = 0u128;
+        for shift in (0..128).step_by(7) {
+            let (byte, rest) = self
+                .bytes
+                .split_first()
+                .ok_or_else(|| invalid_bytes("unexpected end of data"))?;
+            self.bytes = rest;
+
+            let bits = (*byte & 0x7f) as u128;
+            if shift > 0 && bits >> (128 - shift) != 0 {
+                return Err(invalid_bytes("varint overflows 128 bits"));
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid_bytes("varint overflows 128 bits"))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, GadgetError> {
+        u32::try_from(self.read_varint()?).map_err(|_| invalid_bytes("value overflows 32 bits"))
+    }
+
+    fn read_len(&mut self) -> Result<usize, GadgetError> {
+        let len = usize::try_from(self.read_varint()?)
+            .map_err(|_| invalid_bytes("length overflows usize"))?;
+        // every value takes at least one byte, this bounds the allocation of malicious data
+        if len > self.bytes.len() {
+            return Err(invalid_bytes("length exceeds the data"));
+        }
+        Ok(len)
+    }
+
+    fn read_list(&mut self, len: usize) -> Result<Vec<u32>, GadgetError> {
+        (0..len).map(|_| self.read_u32()).collect()
+    }
+
+    fn read_version(&mut self) -> Result<(), GadgetError> {
+        match self.bytes.split_first() {
+            Some((&COMPACT_FORMAT_VERSION, rest)) => {
+                self.bytes = rest;
+                Ok(())
+            }
+            Some((version, _)) => Err(invalid_bytes(&format!("unknown version {version}"))),
+            None => Err(invalid_bytes("unexpected end of data")),
+        }
+    }
+
+    fn finish(self) -> Result<(), GadgetError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid_bytes("trailing bytes"))
+        }
+    }
+}
+
+impl Encoding {
+    fn write_compact(&self, bytes: &mut Vec<u8>) {
+        write_varint(bytes, self.tt_value);
+        write_varint(bytes, self.pin_count as u128);
+        for value in [self.p, self.new_p, self.new_0, self.new_1] {
+            write_varint(bytes, value as u128);
+        }
+        write_list(bytes, &self.input_mappings_0);
+        write_list(bytes, &self.input_mappings_1);
+        for output_encodings in [&self.output_encodings_0, &self.output_encodings_1] {
+            write_varint(bytes, output_encodings.len() as u128);
+            write_list(bytes, output_encodings);
+        }
+    }
+
+    fn read_compact(reader: &mut Reader<'_>) -> Result<Self, GadgetError> {
+        let tt_value = reader.read_varint()?;
+        let pin_count = reader.read_len()?;
+        let p = reader.read_u32()?;
+        let new_p = reader.read_u32()?;
+        let new_0 = reader.read_u32()?;
+        let new_1 = reader.read_u32()?;
+        let input_mappings_0 = reader.read_list(pin_count)?;
+        let input_mappings_1 = reader.read_list(pin_count)?;
+        let len = reader.read_len()?;
+        let output_encodings_0 = reader.read_list(len)?;
+        let len = reader.read_len()?;
+        let output_encodings_1 = reader.read_list(len)?;
+
+        Ok(Self::new(
+            tt_value,
+            pin_count,
+            input_mappings_0,
+            input_mappings_1,
+            output_encodings_0,
+            output_encodings_1,
+            new_0,
+            new_1,
+            p,
+            new_p,
+        ))
+    }
+
+    /// Serializes the encoding to the compact binary format, see the
+    /// [module](self) documentation.
+    ///
+    /// The format must not be mistaken with the serde representation of the encoding, which
+    /// remains available for bincode and JSON.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![COMPACT_FORMAT_VERSION];
+        self.write_compact(&mut bytes);
+        bytes
+    }
+
+    /// Deserializes an encoding serialized by [`Encoding::to_bytes`].
+    ///
+    /// The encoding is not validated, see [`Encoding::check_truth_table`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GadgetError> {
+        let mut reader = Reader { bytes };
+        reader.read_version()?;
+        let encoding = Self::read_compact(&mut reader)?;
+        reader.finish()?;
+        Ok(encoding)
+    }
+}
+
+/// A set of distinct encodings, e.g. the encodings of the gates of a netlist, each one being
+/// identified by its index.
+///
+/// Inserting an encoding already in the set returns its index instead of storing it again. The
+/// set is serialized as the list of its encodings, with serde or in the compact binary format
+/// of [`EncodingSet::to_bytes`].
+///
+/// ```rust
+/// use tfhe::gadget::encoding::{Encoding, EncodingSet};
+///
+/// let and = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+/// let or = Encoding::new_canonical(14, 2, vec![1, 1], vec![0], vec![1, 2], 3);
+///
+/// let mut set = EncodingSet::new();
+/// let gate_encodings: Vec<usize> = [&and, &or, &and]
+///     .into_iter()
+///     .map(|encoding| set.insert(encoding.clone()))
+///     .collect();
+/// assert_eq!(gate_encodings, [0, 1, 0]);
+///
+/// let set = EncodingSet::from_bytes(&set.to_bytes()).unwrap();
+/// assert_eq!(set.get(gate_encodings[1]), Some(&or));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<Encoding>", into = "Vec<Encoding>")]
+pub struct EncodingSet {
+    encodings: Vec<Encoding>,
+    indices: HashMap<Encoding, usize>,
+}
+
+impl EncodingSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts `encoding` if it is not in the set yet and returns its index.
+    pub fn insert(&mut self, encoding: Encoding) -> usize {
+        if let Some(index) = self.indices.get(&encoding) {
+            return *index;
+        }
+
+        let index = self.encodings.len();
+        self.indices.insert(encoding.clone(), index);
+        self.encodings.push(encoding);
+        index
+    }
+
+    pub fn index_of(&self, encoding: &Encoding) -> Option<usize> {
+        self.indices.get(encoding).copied()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Encoding> {
+        self.encodings.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.encodings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.encodings.is_empty()
+    }
+
+    /// Iterates over the encodings in the order of their indices.
+    pub fn iter(&self) -> impl Iterator<Item = &Encoding> {
+        self.encodings.iter()
+    }
+
+    /// Serializes the set to the compact binary format: the version, the number of encodings
+    /// and each encoding in the format of [`Encoding::to_bytes`], without its version.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![COMPACT_FORMAT_VERSION];
+        write_varint(&mut bytes, self.encodings.len() as u128);
+        for encoding in &self.encodings {
+            encoding.write_compact(&mut bytes);
+        }
+        bytes
+    }
+
+    /// Deserializes a set serialized by [`EncodingSet::to_bytes`].
+    ///
+    /// Fails if the data holds the same encoding twice, which would shift the indices of the
+    /// following encodings.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GadgetError> {
+        let mut reader = Reader { bytes };
+        reader.read_version()?;
+        let len = reader.read_len()?;
+
+        let mut set = Self::new();
+        for index in 0..len {
+            let encoding = Encoding::read_compact(&mut reader)?;
+            if set.insert(encoding) != index {
+                return Err(invalid_bytes(&format!("encoding {index} is a duplicate")));
+            }
+        }
+        reader.finish()?;
+        Ok(set)
+    }
+}
+
+impl From<Vec<Encoding>> for EncodingSet {
+    /// Collects the distinct encodings of `encodings`, in the order of their first occurrence.
+    fn from(encodings: Vec<Encoding>) -> Self {
+        encodings.into_iter().collect()
+    }
+}
+
+impl From<EncodingSet> for Vec<Encoding> {
+    fn from(set: EncodingSet) -> Self {
+        set.encodings
+    }
+}
+
+impl FromIterator<Encoding> for EncodingSet {
+    fn from_iter<I: IntoIterator<Item = Encoding>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for encoding in iter {
+            set.insert(encoding);
+        }
+        set
+    }
+}
+
+impl Named for EncodingSet {
+    const NAME: &'static str = "gadget::EncodingSet";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadget::encoding::library;
+
+    #[test]
+    fn compact_round_trip() {
+        let encodings: Vec<Encoding> = ["and2", "xor3", "mux2", "fa_carry", "oai22"]
+            .into_iter()
+            .map(|name| library::get(name).unwrap().clone())
+            .chain([Encoding::new(
+                u128::MAX,
+                7,
+                vec![u32::MAX; 7],
+                vec![300; 7],
+                vec![],
+                vec![0, 128, 16384],
+                5,
+                6,
+                u32::MAX,
+                1,
+            )])
+            .collect();
+
+        for encoding in &encodings {
+            let bytes = encoding.to_bytes();
+            assert_eq!(Encoding::from_bytes(&bytes).unwrap(), *encoding);
+            assert!(bytes.len() < serde_json::to_string(encoding).unwrap().len());
+        }
+
+        let set: EncodingSet = encodings.iter().chain(&encodings).cloned().collect();
+        assert_eq!(set.len(), encodings.len());
+        let bytes = set.to_bytes();
+        assert_eq!(EncodingSet::from_bytes(&bytes).unwrap(), set);
+
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(json, serde_json::to_string(&encodings).unwrap());
+        let deserialized: EncodingSet = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.index_of(&encodings[3]), Some(3));
+        assert_eq!(deserialized, set);
+    }
+
+    #[test]
+    fn compact_rejects_invalid_bytes() {
+        let bytes = library::get("and2").unwrap().to_bytes();
+
+        assert!(Encoding::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Encoding::from_bytes(&[bytes.clone(), vec![0]].concat()).is_err());
+        assert!(Encoding::from_bytes(&[vec![1], bytes[1..].to_vec()].concat()).is_err());
+        assert!(Encoding::from_bytes(&[]).is_err());
+        // a pin count larger than the data must not be allocated
+        assert!(Encoding::from_bytes(&[0, 8, 0xff, 0xff, 0xff, 0xff, 0x0f]).is_err());
+        // 2^32 does not fit the plaintext modulus
+        assert!(Encoding::from_bytes(&[0, 8, 2, 0x80, 0x80, 0x80, 0x80, 0x10]).is_err());
+
+        let mut duplicated = vec![COMPACT_FORMAT_VERSION, 2];
+        duplicated.extend_from_slice(&bytes[1..]);
+        duplicated.extend_from_slice(&bytes[1..]);
+        assert!(EncodingSet::from_bytes(&duplicated).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! Shannon decomposition of gates whose truth table no p-encoding realizes.
+//!
+//! The linear combination of the inputs of a gate holds at most `p` distinct values, so wide or
+//! irregular gates often have no encoding under the plaintext modulus of a circuit. Fixing a
+//! select pin `s` of such a gate splits it into two cofactors over the remaining pins, each
+//! evaluated by its own bootstrap, and a multiplexer combining them with one more bootstrap:
+//!
+//! `f(x) = s ? f(x | s = 1) : f(x | s = 0)`
+//!
+//! The multiplexer needs `p >= 7`. [`plan`] only decomposes the gates
+//! [`synthesize_with_modulus`] cannot realize.
+
+use super::{synthesize_with_modulus, Encoding};
+
+/// Truth table of the multiplexer of pins 0 and 1 selected by pin 2.
+const MUX_TRUTH_TABLE: u128 = 0xca;
+
+/// A two-level cascade of encodings evaluating a gate, see the [module](self) documentation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Decomposition {
+    pub(crate) pin_count: usize,
+    pub(crate) select_pin: usize,
+    pub(crate) cofactor_pins: Vec<usize>,
+    pub(crate) cofactors: Vec<Encoding>,
+    pub(crate) combiner: Encoding,
+}
+
+impl Decomposition {
+    /// Returns the number of pins of the decomposed gate.
+    pub fn pin_count(&self) -> usize {
+        self.pin_count
+    }
+
+    /// Returns the pin of the gate selecting the cofactor.
+    pub fn select_pin(&self) -> usize {
+        self.select_pin
+    }
+
+    /// Returns the pins of the gate that are the inputs of every cofactor, in increasing order.
+    pub fn cofactor_pins(&self) -> &[usize] {
+        &self.cofactor_pins
+    }
+
+    /// Returns the encodings of the cofactors, cofactor `j` being the gate with the select pin
+    /// set to `j`.
+    pub fn cofactors(&self) -> &[Encoding] {
+        &self.cofactors
+    }
+
+    /// Returns the encoding of the combiner, whose pins are the outputs of the two cofactors
+    /// followed by the select pin.
+    pub fn combiner(&self) -> &Encoding {
+        &self.combiner
+    }
+
+    /// Returns the number of bootstraps needed to evaluate the gate.
+    pub fn bootstrap_count(&self) -> usize {
+        self.cofactors.len() + 1
+    }
+}
+
+/// How a gate is evaluated under a plaintext modulus, see [`plan`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GatePlan {
+    /// A single bootstrap
+    Single(Encoding),
+    /// A cascade of bootstraps, see [`Decomposition`]
+    Decomposed(Decomposition),
+}
+
+impl GatePlan {
+    /// Returns the number of bootstraps needed to evaluate the gate.
+    pub fn bootstrap_count(&self) -> usize {
+        match self {
+            GatePlan::Single(_) => 1,
+            GatePlan::Decomposed(decomposition) => decomposition.bootstrap_count(),
+        }
+    }
+}
+
+/// Plans the evaluation of the gate of truth table `tt_value` with `pin_count` inputs under the
+/// plaintext modulus `p`, decomposing it (see [`decompose`]) only if no single encoding realizes
+/// it.
+///
+/// Returns `None` if the truth table is malformed or neither a single encoding nor a
+/// decomposition exists.
+pub fn plan(tt_value: u128, pin_count: usize, p: u32) -> Option<GatePlan> {
+    if let Some(encoding) = synthesize_with_modulus(tt_value, pin_count, p) {
+        return Some(GatePlan::Single(encoding));
+    }
+
+    decompose(tt_value, pin_count, p).map(GatePlan::Decomposed)
+}
+
+/// Searches for a decomposition of the gate of truth table `tt_value` with `pin_count` inputs
+/// whose cofactors and combiner are all realized under the plaintext modulus `p`.
+///
+/// The select pins are tried in increasing order. Returns `None` if the truth table is malformed
+/// or has a single pin, or if no select pin has cofactors realized modulo `p`.
+pub fn decompose(tt_value: u128, pin_count: usize, p: u32) -> Option<Decomposition> {
+    if pin_count < 2 {
+        return None;
+    }
+
+    // the cofactors only differ in their truth table, the combiner is shared by every select pin
+    let combiner = synthesize_with_modulus(MUX_TRUTH_TABLE, 3, p)?;
+    (0..pin_count).find_map(|select_pin| {
+        let cofactor_pins: Vec<usize> = (0..pin_count).filter(|pin| *pin != select_pin).collect();
+        let cofactors = (0..2)
+            .map(|selection| {
+                let cofactor_tt = (0..1usize << cofactor_pins.len()).fold(0u128, |tt, row| {
+                    let gate_row = spread_bits(row, &cofactor_pins) | selection << select_pin;
+                    tt | ((tt_value >> gate_row) & 1) << row
+                });
+                synthesize_with_modulus(cofactor_tt, cofactor_pins.len(), p)
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Decomposition {
+            pin_count,
+            select_pin,
+            cofactor_pins,
+            cofactors,
+            combiner: combiner.clone(),
+        })
+    })
+}
+
+/// Moves bit `i` of `value` to bit `pins[i]`.
+fn spread_bits(value: usize, pins: &[usize]) -> usize {
+    pins.iter()
+        .enumerate()
+        .fold(0, |row, (bit, pin)| row | ((value >> bit) & 1) << pin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decompose_gate_beyond_modulus() {
+        // the parity of 7 inputs needs p >= 8, its cofactors on pin 0 are parities of 6 inputs
+        let xor7 = (0..128u32).fold(0u128, |tt, row| {
+            tt | ((row.count_ones() % 2) as u128) << row
+        });
+        assert!(synthesize_with_modulus(xor7, 7, 7).is_none());
+
+        let GatePlan::Decomposed(decomposition) = plan(xor7, 7, 7).unwrap() else {
+            panic!("xor7 has no encoding modulo 7");
+        };
+        assert_eq!(decomposition.select_pin(), 0);
+        assert_eq!(decomposition.cofactor_pins(), &[1, 2, 3, 4, 5, 6]);
+        assert_eq!(decomposition.bootstrap_count(), 3);
+        for cofactor in decomposition.cofactors() {
+            cofactor.check_truth_table().unwrap();
+        }
+        decomposition.combiner().check_truth_table().unwrap();
+
+        // evaluating the cascade in the clear gives back the truth table
+        let output = |encoding: &Encoding, row: usize| ((encoding.tt_value() >> row) & 1) as usize;
+        for row in 0..128usize {
+            let cofactor_row = (row >> 1) & 0x3f;
+            let combiner_row = output(&decomposition.cofactors()[0], cofactor_row)
+                | output(&decomposition.cofactors()[1], cofactor_row) << 1
+                | (row & 1) << 2;
+            assert_eq!(
+                output(decomposition.combiner(), combiner_row) as u128,
+                (xor7 >> row) & 1
+            );
+        }
+
+        // gates with an encoding are not decomposed
+        assert!(matches!(plan(0x6, 2, 3), Some(GatePlan::Single(_))));
+    }
+}
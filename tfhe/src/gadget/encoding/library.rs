@@ -0,0 +1,87 @@
+//! Pre-searched encodings of common standard cells.
+//!
+//! Cells are keyed by their lowercase name. Inputs of a cell map to pins in the order they are
+//! listed below, the first input being pin 0 (i.e. the LSB of a truth table row):
+//!
+//! - `and2` to `and6`, `or2` to `or6`, `xor2` to `xor4`: inputs `A, B, ...`
+//! - `mux2`: inputs `A, B, S`, outputs `B` if `S` is set and `A` otherwise
+//! - `maj3`: inputs `A, B, C`, outputs whether at least 2 inputs are set
+//! - `aoi21`: inputs `A1, A2, B`, outputs `!((A1 & A2) | B)`
+//! - `oai22`: inputs `A1, A2, B1, B2`, outputs `!((A1 | A2) & (B1 | B2))`
+//! - `fa_sum`, `fa_carry`: inputs `A, B, CI`, the sum and carry out of a full adder
+//!
+//! All encodings take canonical inputs (false is 0 and true is 1) and have the smallest odd
+//! plaintext modulus found by [`synthesize`](super::synthesize).
+
+use super::{Encoding, EncodingBuilder};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+lazy_static! {
+    static ref LIBRARY: HashMap<&'static str, Encoding> = {
+        // (name, truth table, input mappings of pins set to 1 in reverse pin order, p)
+        let cells: [(&'static str, u128, Vec<u32>, u32); 19] = [
+            ("and2", 0x8, vec![1; 2], 3),
+            ("and3", 0x80, vec![1; 3], 5),
+            ("and4", 0x8000, vec![1; 4], 5),
+            ("and5", 0x8000_0000, vec![1; 5], 7),
+            ("and6", 0x8000_0000_0000_0000, vec![1; 6], 7),
+            ("or2", 0xe, vec![1; 2], 3),
+            ("or3", 0xfe, vec![1; 3], 5),
+            ("or4", 0xfffe, vec![1; 4], 5),
+            ("or5", 0xffff_fffe, vec![1; 5], 7),
+            ("or6", 0xffff_ffff_ffff_fffe, vec![1; 6], 7),
+            ("xor2", 0x6, vec![1; 2], 3),
+            ("xor3", 0x96, vec![1; 3], 5),
+            ("xor4", 0x6996, vec![1; 4], 5),
+            ("mux2", 0xca, vec![2, 3, 1], 7),
+            ("maj3", 0xe8, vec![1; 3], 5),
+            ("aoi21", 0x07, vec![2, 1, 1], 5),
+            ("oai22", 0x111f, vec![3, 3, 1, 1], 9),
+            ("fa_sum", 0x96, vec![1; 3], 5),
+            ("fa_carry", 0xe8, vec![1; 3], 5),
+        ];
+
+        cells
+            .into_iter()
+            .map(|(name, tt_value, input_mappings_1, p)| {
+                let encoding = EncodingBuilder::new(tt_value, input_mappings_1.len(), p)
+                    .input_mappings_1(input_mappings_1)
+                    .build()
+                    .unwrap_or_else(|err| panic!("invalid library encoding {name}: {err}"));
+                (name, encoding)
+            })
+            .collect()
+    };
+}
+
+/// Returns the encoding of the standard cell `name`, if it is part of the library.
+pub fn get(name: &str) -> Option<&'static Encoding> {
+    LIBRARY.get(name)
+}
+
+/// Returns the names of all cells in the library.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    LIBRARY.keys().copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadget::encoding::synthesize;
+
+    #[test]
+    fn library_encodings_are_valid_and_minimal() {
+        assert_eq!(names().count(), 19);
+
+        for name in names() {
+            let encoding = get(name).unwrap();
+            encoding.check_truth_table().unwrap();
+
+            let synthesized = synthesize(encoding.tt_value(), encoding.pin_count, 9).unwrap();
+            assert_eq!(synthesized.p(), encoding.p(), "{name}");
+        }
+
+        assert!(get("nand7").is_none());
+    }
+}
@@ -0,0 +1,765 @@
+pub mod canonical;
+pub mod compact;
+pub mod decompose;
+pub mod library;
+pub mod multi_output;
+
+pub use canonical::{CachedEncoding, EncodingCache};
+pub use compact::EncodingSet;
+pub use decompose::{Decomposition, GatePlan};
+pub use multi_output::MultiOutputEncoding;
+
+use crate::gadget::ciphertext::NoiseLevel;
+use crate::gadget::engine::torus_modulus;
+use crate::gadget::error::GadgetError;
+use crate::gadget::parameters::GadgetParameters;
+use crate::named::Named;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Encoding {
+    // we actually don't use this value anywhere in rust
+    pub(crate) tt_value: u128,
+    pub(crate) pin_count: usize,
+    /// Input pin mappings when pin is set to 1. Mapping when pin is set to 0 defaults to 0.
+    /// Given a gate with input pins a, b, c (i.e. starting with LSB), pins are mapped to:
+    /// a, b, c => c, b, a (i.e. in reverse) as a row in truth table.
+    pub(crate) input_mappings_0: Vec<u32>,
+    pub(crate) input_mappings_1: Vec<u32>,
+    pub(crate) output_encodings_0: Vec<u32>,
+    pub(crate) output_encodings_1: Vec<u32>,
+    pub(crate) new_0: u32,
+    pub(crate) new_1: u32,
+    pub(crate) p: u32,
+    pub(crate) new_p: u32,
+}
+
+impl Named for Encoding {
+    const NAME: &'static str = "gadget::Encoding";
+}
+
+impl Encoding {
+    pub fn new(
+        tt_value: u128,
+        pin_count: usize,
+        input_mappings_0: Vec<u32>,
+        input_mappings_1: Vec<u32>,
+        output_encodings_0: Vec<u32>,
+        output_encodings_1: Vec<u32>,
+        new_0: u32,
+        new_1: u32,
+        p: u32,
+        new_p: u32,
+    ) -> Encoding {
+        Encoding {
+            tt_value,
+            pin_count,
+            input_mappings_0,
+            input_mappings_1,
+            output_encodings_0,
+            output_encodings_1,
+            new_0,
+            new_1,
+            p,
+            new_p,
+        }
+    }
+
+    pub fn new_canonical(
+        tt_value: u128,
+        pin_count: usize,
+        input_mappings_1: Vec<u32>,
+        output_encodings_0: Vec<u32>,
+        output_encodings_1: Vec<u32>,
+        p: u32,
+    ) -> Encoding {
+        let input_mappings_0 = vec![0u32; pin_count];
+        Self::new(
+            tt_value,
+            pin_count,
+            input_mappings_0,
+            input_mappings_1,
+            output_encodings_0,
+            output_encodings_1,
+            0,
+            1,
+            p,
+            p,
+        )
+    }
+
+    /// Encoding of a single pin mapping a canonical input (0 or 1) modulo `p` to itself, used
+    /// to reset the noise of a ciphertext.
+    pub fn new_refresh(p: u32) -> Encoding {
+        Self::new_canonical(2, 1, vec![1], vec![0], (1..p).collect(), p)
+    }
+
+    /// Returns whether bootstrapping with this encoding is the identity on every plaintext, in
+    /// which case the linear combination of the inputs already is the gate output.
+    pub fn is_linear(&self) -> bool {
+        self.new_p == self.p
+            && self
+                .output_encodings_0
+                .iter()
+                .all(|value| *value == self.new_0)
+            && self
+                .output_encodings_1
+                .iter()
+                .all(|value| *value == self.new_1)
+    }
+
+    /// Returns the unscaled values of the `p` windows of the test polynomial, followed by the
+    /// value of the upper half of the window of 0, which wraps around to the end of the
+    /// polynomial and is negated by the negacyclic rotation.
+    ///
+    /// Messages modulo an odd `p` fill the whole torus, those of the second half landing
+    /// negated between the ones of the first half: window `2i` maps message `i` and window
+    /// `2i + 1` message `i + (p + 1) / 2`. Messages modulo an even `p` keep a padding bit and
+    /// only fill the first half of the torus, window `i` mapping message `i`.
+    pub fn create_accumulator(&self) -> Vec<u32> {
+        accumulator(self.p, self.new_p, |message| {
+            if self.output_encodings_0.contains(&message) {
+                self.new_0
+            } else {
+                self.new_1
+            }
+        })
+    }
+
+    /// Returns the value, in the p-encoding space, of the linear combination of the inputs for
+    /// the truth table `row`, bit `i` of `row` being the input of pin `i`.
+    pub(crate) fn row_sum(&self, row: u128) -> u32 {
+        (0..self.pin_count).fold(0, |sum, pin| {
+            // mappings are stored in reverse pin order
+            let mapping_index = self.pin_count - 1 - pin;
+            let mapping = if (row >> pin) & 1 == 1 {
+                self.input_mappings_1[mapping_index]
+            } else {
+                self.input_mappings_0[mapping_index]
+            };
+            (sum + mapping) % self.p
+        })
+    }
+
+    /// Checks that the output sets of the encoding agree with `tt_value` on every row of the
+    /// truth table.
+    pub fn check_truth_table(&self) -> Result<(), GadgetError> {
+        check_truth_table_shape(self.tt_value, self.pin_count)?;
+        check_mappings(self)?;
+
+        for row in 0..(1u128 << self.pin_count) {
+            let sum = self.row_sum(row);
+            let expected = (self.tt_value >> row) & 1 == 1;
+            let (output_set, other_set) = if expected {
+                (&self.output_encodings_1, &self.output_encodings_0)
+            } else {
+                (&self.output_encodings_0, &self.output_encodings_1)
+            };
+
+            if !output_set.contains(&sum) || other_set.contains(&sum) {
+                return Err(GadgetError::InvalidEncoding(format!(
+                    "truth table row {row} evaluates to {expected} but its sum {sum} is not \
+                    exclusively in the corresponding output set"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the noise level of the linear combination of the inputs when each input is at
+    /// [`NoiseLevel::NOMINAL`], i.e. the sum of the squared input mappings.
+    pub fn noise_level(&self) -> NoiseLevel {
+        NoiseLevel(
+            self.input_mappings_1
+                .iter()
+                .map(|mapping| (*mapping as usize) * (*mapping as usize))
+                .sum(),
+        )
+    }
+
+    /// Estimates the probability that evaluating this encoding under `parameters` outputs a
+    /// wrong value, when every input is a fresh or freshly bootstrapped ciphertext.
+    pub fn estimate_failure_probability(&self, parameters: &GadgetParameters) -> f64 {
+        parameters.failure_probability(self.p, self.noise_level())
+    }
+
+    /// Builds the encoding of the gate with truth table `tt_value` whose inputs set to 1 are
+    /// mapped to `input_mappings_1` modulo `p`, in reverse pin order, deriving the pin count and
+    /// the output sets. See [`EncodingBuilder`] for the other settings.
+    ///
+    /// ```rust
+    /// use tfhe::gadget::encoding::Encoding;
+    ///
+    /// // 2 input AND gate
+    /// let encoding = Encoding::from_mappings(8, vec![1, 1], 3).unwrap();
+    /// assert_eq!(encoding.pin_count(), 2);
+    /// assert_eq!(encoding.output_encodings_1(), &[2]);
+    /// ```
+    pub fn from_mappings(
+        tt_value: u128,
+        input_mappings_1: Vec<u32>,
+        p: u32,
+    ) -> Result<Encoding, GadgetError> {
+        Self::builder(tt_value, input_mappings_1.len(), p)
+            .input_mappings_1(input_mappings_1)
+            .build()
+    }
+
+    /// Starts building an encoding, see [`EncodingBuilder::new`].
+    pub fn builder(tt_value: u128, pin_count: usize, p: u32) -> EncodingBuilder {
+        EncodingBuilder::new(tt_value, pin_count, p)
+    }
+
+    pub fn tt_value(&self) -> u128 {
+        self.tt_value
+    }
+
+    pub fn pin_count(&self) -> usize {
+        self.pin_count
+    }
+
+    /// Returns the values the input pins set to 0 are mapped to, in reverse pin order: the last
+    /// mapping is the one of pin 0. See [`Encoding::pin_mappings`].
+    pub fn input_mappings_0(&self) -> &[u32] {
+        &self.input_mappings_0
+    }
+
+    /// Returns the values the input pins set to 1 are mapped to, in reverse pin order: the last
+    /// mapping is the one of pin 0. See [`Encoding::pin_mappings`].
+    pub fn input_mappings_1(&self) -> &[u32] {
+        &self.input_mappings_1
+    }
+
+    /// Returns the values `pin` is mapped to when set to 0 and to 1, `None` if the gate has no
+    /// such pin.
+    pub fn pin_mappings(&self, pin: usize) -> Option<(u32, u32)> {
+        // mappings are stored in reverse pin order
+        let mapping_index = self.pin_count.checked_sub(pin + 1)?;
+        Some((
+            *self.input_mappings_0.get(mapping_index)?,
+            *self.input_mappings_1.get(mapping_index)?,
+        ))
+    }
+
+    pub fn p(&self) -> u32 {
+        self.p
+    }
+
+    pub fn new_p(&self) -> u32 {
+        self.new_p
+    }
+
+    pub fn new_0(&self) -> u32 {
+        self.new_0
+    }
+
+    pub fn new_1(&self) -> u32 {
+        self.new_1
+    }
+
+    pub fn output_encodings_0(&self) -> &[u32] {
+        &self.output_encodings_0
+    }
+
+    pub fn output_encodings_1(&self) -> &[u32] {
+        &self.output_encodings_1
+    }
+}
+
+/// Returns the unscaled values of the `p` windows of the test polynomial mapping each message
+/// modulo `p` to `output(message)` modulo `new_p`, see [`Encoding::create_accumulator`].
+pub(crate) fn accumulator(p: u32, new_p: u32, output: impl Fn(u32) -> u32) -> Vec<u32> {
+    let p = p as usize;
+    // output values are negated on the torus of the output space
+    let output_torus_modulus = torus_modulus(new_p);
+    let negate = |value: u32| {
+        ((output_torus_modulus - value as u64 % output_torus_modulus) % output_torus_modulus) as u32
+    };
+
+    if p % 2 == 0 {
+        let mut acc: Vec<u32> = (0..p as u32).map(output).collect();
+        acc.push(negate(acc[0]));
+        return acc;
+    }
+
+    // p+1 to accomodate other half window corresponding to 0
+    let mut acc = vec![0; p + 1];
+    for i in 0..((p + 1) / 2) {
+        // first half
+        acc[2 * i] = output(i as u32);
+
+        let beta = (i + ((p + 1) / 2)) % p;
+        acc[2 * i + 1] = negate(output(beta as u32));
+    }
+
+    acc
+}
+
+/// Builds an [`Encoding`] from a truth table and input mappings, deriving the output sets.
+///
+/// Every row of the truth table is enumerated to compute the value of the linear combination of
+/// the inputs in the p-encoding space, the output sets are then derived from the expected output
+/// bit of each row. Building fails if two rows with different outputs collide on the same value.
+///
+/// ```rust
+/// use tfhe::gadget::encoding::EncodingBuilder;
+///
+/// // 2 input AND gate
+/// let encoding = EncodingBuilder::new(8, 2, 3)
+///     .input_mappings_1(vec![1, 1])
+///     .build()
+///     .unwrap();
+/// assert_eq!(encoding.output_encodings_0(), &[0, 1]);
+/// assert_eq!(encoding.output_encodings_1(), &[2]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct EncodingBuilder {
+    tt_value: u128,
+    pin_count: usize,
+    p: u32,
+    input_mappings_0: Option<Vec<u32>>,
+    input_mappings_1: Vec<u32>,
+    new_0: u32,
+    new_1: u32,
+    new_p: Option<u32>,
+}
+
+impl EncodingBuilder {
+    /// Starts building the encoding of a gate with `pin_count` inputs over the plaintext
+    /// modulus `p`. Input pins mapped to 1 default to 1 and pins mapped to 0 default to 0.
+    pub fn new(tt_value: u128, pin_count: usize, p: u32) -> Self {
+        Self {
+            tt_value,
+            pin_count,
+            p,
+            input_mappings_0: None,
+            input_mappings_1: vec![1; pin_count],
+            new_0: 0,
+            new_1: 1,
+            new_p: None,
+        }
+    }
+
+    pub fn input_mappings_0(mut self, input_mappings_0: Vec<u32>) -> Self {
+        self.input_mappings_0 = Some(input_mappings_0);
+        self
+    }
+
+    pub fn input_mappings_1(mut self, input_mappings_1: Vec<u32>) -> Self {
+        self.input_mappings_1 = input_mappings_1;
+        self
+    }
+
+    /// Sets the plaintext modulus of the output together with the values false and true are
+    /// mapped to. By default the output is canonical: false is 0 and true is 1 modulo `p`.
+    pub fn output_modulus(mut self, new_p: u32, new_0: u32, new_1: u32) -> Self {
+        self.new_p = Some(new_p);
+        self.new_0 = new_0;
+        self.new_1 = new_1;
+        self
+    }
+
+    pub fn build(self) -> Result<Encoding, GadgetError> {
+        let mut encoding = Encoding::new(
+            self.tt_value,
+            self.pin_count,
+            self.input_mappings_0
+                .unwrap_or_else(|| vec![0; self.pin_count]),
+            self.input_mappings_1,
+            vec![],
+            vec![],
+            self.new_0,
+            self.new_1,
+            self.p,
+            self.new_p.unwrap_or(self.p),
+        );
+
+        check_truth_table_shape(encoding.tt_value, encoding.pin_count)?;
+        check_mappings(&encoding)?;
+
+        let mut output_encodings_0 = vec![];
+        let mut output_encodings_1 = vec![];
+        for row in 0..(1u128 << encoding.pin_count) {
+            let sum = encoding.row_sum(row);
+            let (output_set, other_set) = if (encoding.tt_value >> row) & 1 == 1 {
+                (&mut output_encodings_1, &output_encodings_0)
+            } else {
+                (&mut output_encodings_0, &output_encodings_1)
+            };
+
+            if other_set.contains(&sum) {
+                return Err(GadgetError::InvalidEncoding(format!(
+                    "truth table row {row} collides on value {sum} with a row of different output"
+                )));
+            }
+            if !output_set.contains(&sum) {
+                output_set.push(sum);
+            }
+        }
+
+        output_encodings_0.sort_unstable();
+        output_encodings_1.sort_unstable();
+        encoding.output_encodings_0 = output_encodings_0;
+        encoding.output_encodings_1 = output_encodings_1;
+
+        Ok(encoding)
+    }
+}
+
+/// Searches for a p-encoding realizing the truth table `tt_value` of a gate with `pin_count`
+/// inputs, with the smallest plaintext modulus `p <= max_p`.
+///
+/// Pins mapped to 0 are encoded as 0 and the input mappings of pins set to 1 are found by a
+/// depth first search, assigning pins in order and pruning as soon as two rows of the truth table
+/// that only involve assigned pins collide on the same value with different outputs. Only odd
+/// plaintext moduli are considered: an even one keeps a padding bit, which the sum of the inputs
+/// of every row would additionally have to leave clear to be bootstrapped.
+///
+/// Returns `None` if the truth table is malformed or no encoding exists up to `max_p`.
+pub fn synthesize(tt_value: u128, pin_count: usize, max_p: u32) -> Option<Encoding> {
+    check_truth_table_shape(tt_value, pin_count).ok()?;
+
+    (3..=max_p)
+        .step_by(2)
+        .find_map(|p| synthesize_with_modulus(tt_value, pin_count, p))
+}
+
+/// Searches for a p-encoding realizing the truth table `tt_value` of a gate with `pin_count`
+/// inputs under the plaintext modulus `p`. See [`synthesize`].
+pub fn synthesize_with_modulus(tt_value: u128, pin_count: usize, p: u32) -> Option<Encoding> {
+    check_truth_table_shape(tt_value, pin_count).ok()?;
+
+    let mut mappings = Vec::with_capacity(pin_count);
+    if !synthesize_mappings(tt_value, pin_count, p, &mut mappings) {
+        return None;
+    }
+
+    // mappings are stored in reverse pin order
+    mappings.reverse();
+    EncodingBuilder::new(tt_value, pin_count, p)
+        .input_mappings_1(mappings)
+        .build()
+        .ok()
+}
+
+/// Extends `mappings`, indexed by pin, until every pin is assigned. Returns false if no
+/// assignment extending `mappings` realizes `tt_value` modulo `p`.
+fn synthesize_mappings(tt_value: u128, pin_count: usize, p: u32, mappings: &mut Vec<u32>) -> bool {
+    let assigned = mappings.len();
+    if assigned == pin_count {
+        return true;
+    }
+
+    for mapping in 0..p {
+        mappings.push(mapping);
+
+        // rows whose bits only cover assigned pins have a known sum, check the new ones
+        let mut outputs = vec![None; p as usize];
+        let consistent = (0..(1u128 << (assigned + 1))).all(|row| {
+            let sum = mappings
+                .iter()
+                .enumerate()
+                .filter(|(pin, _)| (row >> pin) & 1 == 1)
+                .fold(0, |sum, (_, mapping)| (sum + mapping) % p);
+            let output = (tt_value >> row) & 1 == 1;
+            *outputs[sum as usize].get_or_insert(output) == output
+        });
+
+        if consistent && synthesize_mappings(tt_value, pin_count, p, mappings) {
+            return true;
+        }
+        mappings.pop();
+    }
+
+    false
+}
+
+fn check_truth_table_shape(tt_value: u128, pin_count: usize) -> Result<(), GadgetError> {
+    // the truth table has 2^pin_count rows stored in a u128
+    if pin_count == 0 || pin_count > 7 {
+        return Err(GadgetError::InvalidEncoding(format!(
+            "pin count {pin_count} is not in [1, 7]"
+        )));
+    }
+
+    let row_count = 1u32 << pin_count;
+    if row_count < 128 && tt_value >> row_count != 0 {
+        return Err(GadgetError::InvalidEncoding(format!(
+            "truth table {tt_value} has more than {row_count} rows"
+        )));
+    }
+
+    Ok(())
+}
+
+fn check_mappings(encoding: &Encoding) -> Result<(), GadgetError> {
+    if encoding.p == 0 {
+        return Err(GadgetError::UnsupportedPlaintextModulus(encoding.p));
+    }
+
+    for mappings in [&encoding.input_mappings_0, &encoding.input_mappings_1] {
+        if mappings.len() != encoding.pin_count {
+            return Err(GadgetError::InvalidEncoding(format!(
+                "{} input mappings for {} pins",
+                mappings.len(),
+                encoding.pin_count
+            )));
+        }
+        if let Some(mapping) = mappings.iter().find(|mapping| **mapping >= encoding.p) {
+            return Err(GadgetError::InvalidEncoding(format!(
+                "input mapping {mapping} does not fit in p = {}",
+                encoding.p
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadget::parameters::{PLAINTEXT_2_BITS_PARAMETERS, PLAINTEXT_3_BITS_PARAMETERS};
+    use std::error::Error;
+
+    #[test]
+    fn print_accumulator() {
+        let encoding = Encoding::new_canonical(
+            4294836226,
+            6,
+            vec![1, 2, 3, 3, 3, 11],
+            vec![0, 1, 2, 3, 4, 6, 7, 9, 10, 12, 14, 15, 17, 18, 20, 21],
+            vec![5, 8, 11, 13, 16, 19, 22],
+            23,
+        );
+        let acc = encoding.create_accumulator();
+        println!("Acc: {:?}", acc);
+    }
+
+    #[test]
+    fn builder_derives_output_sets() {
+        // xor
+        let encoding = EncodingBuilder::new(6, 2, 3)
+            .input_mappings_1(vec![1, 1])
+            .build()
+            .unwrap();
+        assert_eq!(encoding.output_encodings_0(), &[0, 2]);
+        assert_eq!(encoding.output_encodings_1(), &[1]);
+        encoding.check_truth_table().unwrap();
+
+        // and, with the pin mappings in reverse order
+        let encoding = EncodingBuilder::new(0b1000, 2, 5)
+            .input_mappings_1(vec![1, 3])
+            .build()
+            .unwrap();
+        assert_eq!(encoding.output_encodings_0(), &[0, 1, 3]);
+        assert_eq!(encoding.output_encodings_1(), &[4]);
+        encoding.check_truth_table().unwrap();
+    }
+
+    #[test]
+    fn accessors_and_constructors() {
+        // !a & b, pin 0 (a) being mapped to 2 and pin 1 (b) to 1
+        let encoding = Encoding::from_mappings(0b0100, vec![1, 2], 3).unwrap();
+        assert_eq!(
+            encoding,
+            Encoding::builder(0b0100, 2, 3)
+                .input_mappings_1(vec![1, 2])
+                .build()
+                .unwrap()
+        );
+        assert_eq!(encoding.pin_count(), 2);
+        assert_eq!(encoding.input_mappings_0(), &[0, 0]);
+        assert_eq!(encoding.input_mappings_1(), &[1, 2]);
+        assert_eq!(encoding.pin_mappings(0), Some((0, 2)));
+        assert_eq!(encoding.pin_mappings(1), Some((0, 1)));
+        assert_eq!(encoding.pin_mappings(2), None);
+        assert_eq!(encoding.output_encodings_1(), &[1]);
+        encoding.check_truth_table().unwrap();
+
+        assert!(Encoding::from_mappings(8, vec![1, 1], 2).is_err());
+        assert!(Encoding::from_mappings(8, vec![], 3).is_err());
+    }
+
+    #[test]
+    fn builder_detects_inconsistent_truth_table() {
+        // and cannot be evaluated from a plain sum modulo 2
+        let result = EncodingBuilder::new(8, 2, 2)
+            .input_mappings_1(vec![1, 1])
+            .build();
+        assert!(matches!(result, Err(GadgetError::InvalidEncoding(_))));
+
+        // declared output sets disagree with the truth table of and
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 2], vec![1], 3);
+        assert!(encoding.check_truth_table().is_err());
+    }
+
+    #[test]
+    fn synthesize_finds_minimal_modulus() {
+        // xor of 2 inputs only needs the sum modulo 3
+        let encoding = synthesize(6, 2, 7).unwrap();
+        assert_eq!(encoding.p(), 3);
+        encoding.check_truth_table().unwrap();
+
+        // majority of 3 inputs
+        let encoding = synthesize(0b1110_1000, 3, 7).unwrap();
+        assert_eq!(encoding.p(), 5);
+        encoding.check_truth_table().unwrap();
+
+        // 3 input xor needs the sum up to 3 to be distinguished from 0
+        assert!(synthesize(0b1001_0110, 3, 3).is_none());
+        let encoding = synthesize(0b1001_0110, 3, 7).unwrap();
+        encoding.check_truth_table().unwrap();
+    }
+
+    #[test]
+    fn failure_probability_grows_with_p_and_pins() {
+        let and2 = library::get("and2").unwrap();
+        let and6 = library::get("and6").unwrap();
+        assert_eq!(and6.noise_level(), NoiseLevel(6));
+
+        let and2_failure = and2.estimate_failure_probability(&PLAINTEXT_3_BITS_PARAMETERS);
+        let and6_failure = and6.estimate_failure_probability(&PLAINTEXT_3_BITS_PARAMETERS);
+        assert!(and2_failure < 2f64.powi(-40));
+        assert!(and6_failure > and2_failure);
+
+        // 6 pins modulo 7 are not safe under the 2 bits parameters
+        assert!(and6.estimate_failure_probability(&PLAINTEXT_2_BITS_PARAMETERS) > 2f64.powi(-40));
+    }
+
+    #[test]
+    fn deserialization_works() -> Result<(), Box<dyn Error>> {
+        let json_data = r#"
+        [{
+            "input_mappings_1": [
+                1,
+                1,
+                1,
+                1,
+                5,
+                6
+            ],
+            "output_encodings_0": [
+                0,
+                1,
+                2,
+                3,
+                4,
+                6,
+                7,
+                8,
+                9,
+                10,
+                11,
+                12,
+                13,
+                14,
+                15
+            ],
+            "output_encodings_1": [
+                5
+            ],
+            "p": 23,
+            "pin_count": 6,
+            "tt_value": 4
+        },{
+            "input_mappings_1": [
+                1,
+                1,
+                1,
+                1,
+                5,
+                5
+            ],
+            "output_encodings_0": [
+                0,
+                1,
+                2,
+                3,
+                4,
+                5,
+                6,
+                7,
+                8,
+                9,
+                11,
+                12,
+                13,
+                14
+            ],
+            "output_encodings_1": [
+                10
+            ],
+            "p": 23,
+            "pin_count": 6,
+            "tt_value": 8
+        },{
+            "input_mappings_1": [
+                1,
+                2,
+                2,
+                2,
+                2,
+                2
+            ],
+            "output_encodings_0": [
+                0,
+                1,
+                2,
+                3,
+                4,
+                5,
+                6,
+                7,
+                8,
+                9,
+                11
+            ],
+            "output_encodings_1": [
+                10
+            ],
+            "p": 23,
+            "pin_count": 6,
+            "tt_value": 2147483648
+        },{
+            "input_mappings_1": [
+                1,
+                2,
+                2,
+                2,
+                2,
+                9
+            ],
+            "output_encodings_0": [
+                0,
+                1,
+                2,
+                4,
+                6,
+                8,
+                11,
+                13,
+                15,
+                17
+            ],
+            "output_encodings_1": [
+                3,
+                5,
+                7,
+                9,
+                10,
+                12,
+                14,
+                16,
+                18
+            ],
+            "p": 23,
+            "pin_count": 6,
+            "tt_value": 18446744065119617026
+        }]
+        "#;
+        let encodings: Vec<Encoding> = serde_json::from_str(json_data)?;
+        Ok(())
+    }
+}
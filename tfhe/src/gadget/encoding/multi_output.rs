@@ -0,0 +1,275 @@
+//! Encodings of lookup tables with more than two output values.
+//!
+//! An [`Encoding`] splits the messages of the linear combination of its inputs in two output
+//! sets, mapped to `new_0` and `new_1`. A [`MultiOutputEncoding`] maps each message modulo `p`
+//! to its own output value modulo `new_p`, so that a single bootstrap evaluates a function with
+//! a small integer output, e.g. a 4-bit S-box, see
+//! [`ServerKey::evaluate_lut`](crate::gadget::server_key::ServerKey::evaluate_lut).
+
+use super::{accumulator, Encoding};
+use crate::gadget::ciphertext::NoiseLevel;
+use crate::gadget::error::GadgetError;
+use crate::gadget::parameters::GadgetParameters;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MultiOutputEncoding {
+    /// Input pin mappings, in reverse pin order as for [`Encoding::input_mappings_1`]. Each
+    /// input is multiplied by the mapping of its pin.
+    pub(crate) input_mappings_1: Vec<u32>,
+    /// Output value of each message modulo `p` of the linear combination of the inputs
+    pub(crate) outputs: Vec<u32>,
+    pub(crate) p: u32,
+    pub(crate) new_p: u32,
+}
+
+impl MultiOutputEncoding {
+    /// Builds the encoding mapping the message `m` modulo `p` of the linear combination of the
+    /// inputs to `outputs[m]` modulo `new_p`.
+    ///
+    /// Messages modulo an even `p` keep a padding bit: the linear combination of the inputs
+    /// must stay below `p`, which is up to the caller.
+    pub fn new(
+        input_mappings_1: Vec<u32>,
+        outputs: Vec<u32>,
+        p: u32,
+        new_p: u32,
+    ) -> Result<MultiOutputEncoding, GadgetError> {
+        let encoding = MultiOutputEncoding {
+            input_mappings_1,
+            outputs,
+            p,
+            new_p,
+        };
+        encoding.check()?;
+        Ok(encoding)
+    }
+
+    /// Builds the encoding of the function `f` of a single input encrypting a message modulo
+    /// `p`, whose outputs are messages modulo `new_p`.
+    pub fn from_function(
+        p: u32,
+        new_p: u32,
+        f: impl Fn(u32) -> u32,
+    ) -> Result<MultiOutputEncoding, GadgetError> {
+        Self::new(vec![1], (0..p).map(f).collect(), p, new_p)
+    }
+
+    /// Builds the encoding of the gate whose boolean inputs select the output `table[row]`,
+    /// bit `i` of `row` being the input of pin `i`, the inputs set to 1 being mapped to
+    /// `input_mappings_1` modulo `p` in reverse pin order.
+    ///
+    /// Messages reached by no row are mapped to 0. Fails with [`GadgetError::InvalidEncoding`]
+    /// if `table` does not have one entry per row, or if two rows with different outputs
+    /// collide on the same message.
+    ///
+    /// ```rust
+    /// use tfhe::gadget::encoding::MultiOutputEncoding;
+    ///
+    /// // 2 bits input x = x0 + 2 * x1, output x * x modulo 5
+    /// let encoding = MultiOutputEncoding::from_table(&[0, 1, 4, 4], vec![2, 1], 5, 5).unwrap();
+    /// assert_eq!(encoding.outputs(), &[0, 1, 4, 4, 0]);
+    /// ```
+    pub fn from_table(
+        table: &[u32],
+        input_mappings_1: Vec<u32>,
+        p: u32,
+        new_p: u32,
+    ) -> Result<MultiOutputEncoding, GadgetError> {
+        let pin_count = input_mappings_1.len();
+        if pin_count > 7 || table.len() != 1 << pin_count {
+            return Err(GadgetError::InvalidEncoding(format!(
+                "table of {} rows for {pin_count} pins",
+                table.len()
+            )));
+        }
+        if p == 0 {
+            return Err(GadgetError::UnsupportedPlaintextModulus(p));
+        }
+
+        let mut outputs = vec![None; p as usize];
+        for (row, output) in table.iter().enumerate() {
+            // mappings are stored in reverse pin order
+            let sum = (0..pin_count)
+                .filter(|pin| (row >> pin) & 1 == 1)
+                .map(|pin| input_mappings_1[pin_count - 1 - pin] as u64)
+                .sum::<u64>();
+            if p % 2 == 0 && sum >= p as u64 {
+                return Err(GadgetError::InvalidEncoding(format!(
+                    "the inputs of row {row} sum to {sum}, which sets the padding bit of the \
+                    plaintext modulus {p}"
+                )));
+            }
+
+            let message = (sum % p as u64) as usize;
+            if *outputs[message].get_or_insert(*output) != *output {
+                return Err(GadgetError::InvalidEncoding(format!(
+                    "table row {row} collides on value {message} with a row of different output"
+                )));
+            }
+        }
+
+        Self::new(
+            input_mappings_1,
+            outputs
+                .into_iter()
+                .map(|output| output.unwrap_or(0))
+                .collect(),
+            p,
+            new_p,
+        )
+    }
+
+    /// Checks that the mappings and outputs fit in their plaintext moduli.
+    pub fn check(&self) -> Result<(), GadgetError> {
+        if self.p == 0 || self.new_p == 0 {
+            return Err(GadgetError::UnsupportedPlaintextModulus(
+                self.p.min(self.new_p),
+            ));
+        }
+        if self.outputs.len() != self.p as usize {
+            return Err(GadgetError::InvalidEncoding(format!(
+                "{} outputs for p = {}",
+                self.outputs.len(),
+                self.p
+            )));
+        }
+        if let Some(mapping) = self.input_mappings_1.iter().find(|m| **m >= self.p) {
+            return Err(GadgetError::InvalidEncoding(format!(
+                "input mapping {mapping} does not fit in p = {}",
+                self.p
+            )));
+        }
+        if let Some(output) = self.outputs.iter().find(|output| **output >= self.new_p) {
+            return Err(GadgetError::InvalidEncoding(format!(
+                "output {output} does not fit in new_p = {}",
+                self.new_p
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the unscaled values of the windows of the test polynomial, see
+    /// [`Encoding::create_accumulator`].
+    pub fn create_accumulator(&self) -> Vec<u32> {
+        accumulator(self.p, self.new_p, |message| self.outputs[message as usize])
+    }
+
+    /// Returns the output value of `message` modulo `p`.
+    pub fn output(&self, message: u32) -> u32 {
+        self.outputs[(message % self.p) as usize]
+    }
+
+    /// Returns the noise level of the linear combination of the inputs when each input is at
+    /// [`NoiseLevel::NOMINAL`], see [`Encoding::noise_level`].
+    pub fn noise_level(&self) -> NoiseLevel {
+        NoiseLevel(
+            self.input_mappings_1
+                .iter()
+                .map(|mapping| (*mapping as usize) * (*mapping as usize))
+                .sum(),
+        )
+    }
+
+    /// Estimates the probability that evaluating this encoding under `parameters` outputs a
+    /// wrong value, when every input is a fresh or freshly bootstrapped ciphertext.
+    pub fn estimate_failure_probability(&self, parameters: &GadgetParameters) -> f64 {
+        parameters.failure_probability(self.p, self.noise_level())
+    }
+
+    pub fn pin_count(&self) -> usize {
+        self.input_mappings_1.len()
+    }
+
+    /// Returns the values the input pins are multiplied by, in reverse pin order.
+    pub fn input_mappings_1(&self) -> &[u32] {
+        &self.input_mappings_1
+    }
+
+    pub fn outputs(&self) -> &[u32] {
+        &self.outputs
+    }
+
+    pub fn p(&self) -> u32 {
+        self.p
+    }
+
+    pub fn new_p(&self) -> u32 {
+        self.new_p
+    }
+}
+
+impl TryFrom<&Encoding> for MultiOutputEncoding {
+    type Error = GadgetError;
+
+    /// Converts an encoding whose pins set to 0 are mapped to 0, its output sets mapping to
+    /// `new_0` and `new_1`.
+    fn try_from(encoding: &Encoding) -> Result<MultiOutputEncoding, GadgetError> {
+        if encoding
+            .input_mappings_0
+            .iter()
+            .any(|mapping| *mapping != 0)
+        {
+            return Err(GadgetError::InvalidEncoding(
+                "pins set to 0 must be mapped to 0".to_string(),
+            ));
+        }
+
+        let outputs = (0..encoding.p)
+            .map(|message| {
+                if encoding.output_encodings_0.contains(&message) {
+                    encoding.new_0
+                } else {
+                    encoding.new_1
+                }
+            })
+            .collect();
+        Self::new(
+            encoding.input_mappings_1.clone(),
+            outputs,
+            encoding.p,
+            encoding.new_p,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_table_derives_outputs() {
+        // 2 bits input x = x0 + 2 * x1 modulo 5, output x + 1
+        let encoding = MultiOutputEncoding::from_table(&[1, 2, 3, 4], vec![2, 1], 5, 5).unwrap();
+        assert_eq!(encoding.pin_count(), 2);
+        assert_eq!(encoding.outputs(), &[1, 2, 3, 4, 0]);
+        assert_eq!(encoding.output(7), 3);
+
+        // x0 + x1 merges the rows 1 and 2
+        assert!(matches!(
+            MultiOutputEncoding::from_table(&[1, 2, 3, 4], vec![1, 1], 5, 5),
+            Err(GadgetError::InvalidEncoding(_))
+        ));
+        // the sum of row 3 sets the padding bit
+        assert!(matches!(
+            MultiOutputEncoding::from_table(&[1, 2, 3, 4], vec![2, 1], 2, 5),
+            Err(GadgetError::InvalidEncoding(_))
+        ));
+        assert!(matches!(
+            MultiOutputEncoding::from_table(&[1, 2, 3, 7], vec![2, 1], 5, 5),
+            Err(GadgetError::InvalidEncoding(_))
+        ));
+    }
+
+    #[test]
+    fn accumulator_matches_encoding() {
+        let encoding = Encoding::from_mappings(8, vec![1, 1], 3).unwrap();
+        let multi_output = MultiOutputEncoding::try_from(&encoding).unwrap();
+        assert_eq!(multi_output.outputs(), &[0, 0, 1]);
+        assert_eq!(
+            multi_output.create_accumulator(),
+            encoding.create_accumulator()
+        );
+    }
+}
@@ -1,228 +1,992 @@
 use crate::boolean::engine::WithThreadLocalEngine;
+use crate::core_crypto::commons::dispersion::StandardDev;
 use crate::core_crypto::commons::generators::DeterministicSeeder;
-use crate::core_crypto::commons::parameters::CiphertextModulus;
+use crate::core_crypto::commons::math::torus::UnsignedTorus;
+use crate::core_crypto::commons::numeric::{CastFrom, CastInto, Numeric};
+use crate::core_crypto::commons::parameters::{
+    CiphertextModulus, DecompositionBaseLog, DecompositionLevelCount, LweCiphertextCount, PBSOrder,
+};
 use crate::core_crypto::entities::*;
+use crate::core_crypto::fft_impl::fft64::math::fft::FftView;
 use crate::core_crypto::prelude::{
-    allocate_and_encrypt_new_lwe_ciphertext, allocate_and_generate_new_binary_glwe_secret_key,
-    allocate_and_generate_new_binary_lwe_secret_key, allocate_and_generate_new_lwe_keyswitch_key,
+    allocate_and_encrypt_new_lwe_ciphertext, allocate_and_encrypt_new_seeded_lwe_ciphertext,
+    allocate_and_generate_new_binary_glwe_secret_key,
+    allocate_and_generate_new_binary_lwe_secret_key,
+    allocate_and_generate_new_lwe_packing_keyswitch_key, blind_rotate_assign_mem_optimized,
+    blind_rotate_assign_mem_optimized_requirement,
     convert_standard_lwe_bootstrap_key_to_fourier_mem_optimized_requirement,
-    decrypt_lwe_ciphertext, keyswitch_lwe_ciphertext, lwe_ciphertext_add_assign,
-    lwe_ciphertext_cleartext_mul_assign, lwe_ciphertext_plaintext_add_assign, new_seeder,
+    decrypt_lwe_ciphertext, extract_lwe_sample_from_glwe_ciphertext, keyswitch_lwe_ciphertext,
+    lwe_ciphertext_cleartext_mul_add_assign, lwe_ciphertext_plaintext_add_assign,
+    multi_bit_deterministic_blind_rotate_assign,
+    multi_bit_deterministic_programmable_bootstrap_lwe_ciphertext, new_seeder,
+    par_allocate_and_generate_new_circuit_bootstrap_lwe_pfpksk_list,
     par_allocate_and_generate_new_lwe_bootstrap_key,
+    par_allocate_and_generate_new_lwe_keyswitch_key,
+    par_allocate_and_generate_new_lwe_multi_bit_bootstrap_key,
     par_convert_standard_lwe_bootstrap_key_to_fourier,
+    par_convert_standard_lwe_multi_bit_bootstrap_key_to_fourier, par_encrypt_lwe_ciphertext_list,
     programmable_bootstrap_lwe_ciphertext_mem_optimized,
     programmable_bootstrap_lwe_ciphertext_mem_optimized_requirement, ActivatedRandomGenerator,
-    ComputationBuffers, EncryptionRandomGenerator, Fft, FourierLweBootstrapKey, GlweCiphertext,
-    LweCiphertextMutView, SecretRandomGenerator,
+    ComputationBuffers, Container, ContainerMut, ContiguousEntityContainer,
+    ContiguousEntityContainerMut, EncryptionRandomGenerator, Fft, FourierLweBootstrapKey,
+    FourierLweMultiBitBootstrapKey, LweBskGroupingFactor, LweCiphertextMutView, MonomialDegree,
+    PolynomialSize, SecretRandomGenerator, ThreadCount,
+};
+#[cfg(feature = "__wasm_api")]
+use crate::core_crypto::prelude::{
+    allocate_and_generate_new_seeded_lwe_bootstrap_key,
+    allocate_and_generate_new_seeded_lwe_keyswitch_key,
+    allocate_and_generate_new_seeded_lwe_multi_bit_bootstrap_key,
+};
+#[cfg(not(feature = "__wasm_api"))]
+use crate::core_crypto::prelude::{
+    par_allocate_and_generate_new_seeded_lwe_bootstrap_key,
+    par_allocate_and_generate_new_seeded_lwe_keyswitch_key,
+    par_allocate_and_generate_new_seeded_lwe_multi_bit_bootstrap_key,
+};
+use crate::gadget::ciphertext::{
+    trivial_lwe_message, Ciphertext, CiphertextList, CompressedCiphertext, NoiseLevel,
 };
-use crate::gadget::ciphertext::Ciphertext;
 use crate::gadget::client_key::ClientKey;
-use crate::gadget::encoding::Encoding;
-use crate::gadget::parameters::GadgetParameters;
-use crate::gadget::server_key::ServerKey;
-use concrete_csprng::seeders::Seeder;
+use crate::gadget::constant_time::{decode_secret, encode_secret};
+use crate::gadget::encoding::{Encoding, MultiOutputEncoding};
+use crate::gadget::error::{DecryptionError, GadgetError};
+use crate::gadget::ntt::NttBootstrapKey;
+use crate::gadget::parameters::{
+    GadgetPackingParameters, GadgetParameters, GadgetWopbsParameters, PbsBackend,
+};
+use crate::gadget::pipeline::PipelineConfig;
+use crate::gadget::server_key::{
+    lookup_table_messages, CompressedBootstrappingKey, CompressedServerKey, GadgetBootstrappingKey,
+    LookupTable, ServerKey,
+};
+use crate::gadget::telemetry::span;
+use concrete_csprng::seeders::{Seed, Seeder};
 use itertools::izip;
+use serde::Serialize;
+use std::borrow::Borrow;
 use std::cell::RefCell;
-use std::error::Error;
 use std::thread_local;
+use std::time::Instant;
+
+/// Torus scalar of the gadget ciphertexts and keys, `u32` (the default) or `u64`.
+///
+/// A 64 bits torus holds noise distributions much smaller than `2^-32`, which the parameter sets
+/// of large plaintext moduli require (see
+/// [`PLAINTEXT_6_BITS_PARAMETERS`](crate::gadget::parameters::PLAINTEXT_6_BITS_PARAMETERS)), at
+/// the price of ciphertexts and keyswitching keys twice as large.
+pub trait GadgetScalar:
+    UnsignedTorus
+    + CastFrom<u32>
+    + CastFrom<usize>
+    + CastInto<usize>
+    + CastInto<u64>
+    + Sync
+    + Send
+    + Serialize
+{
+}
+
+impl GadgetScalar for u32 {}
+
+impl GadgetScalar for u64 {}
+
+/// Number of bits of the modulus of ciphertexts over `Scalar`.
+pub(crate) fn modulus_bits<Scalar: GadgetScalar>(
+    ciphertext_modulus: CiphertextModulus<Scalar>,
+) -> usize {
+    if ciphertext_modulus.is_native_modulus() {
+        Scalar::BITS
+    } else {
+        ciphertext_modulus.get_custom_modulus().ilog2() as usize
+    }
+}
+
+/// Returns the number of slots the torus is split in for messages modulo `plaintext_modulus`.
+///
+/// Odd moduli use the whole torus. Messages modulo an even modulus `p` keep a padding bit: they
+/// are encoded as `m / 2p` on the first half of the torus, the negacyclic lookup tables mapping
+/// that half freely (see [`Encoding::create_accumulator`]) as long as the linear combinations of
+/// the inputs do not overflow into the padding bit.
+pub(crate) fn torus_modulus(plaintext_modulus: u32) -> u64 {
+    if plaintext_modulus % 2 == 0 {
+        2 * plaintext_modulus as u64
+    } else {
+        plaintext_modulus as u64
+    }
+}
+
+/// Encodes the slot `slot` of a torus split in `torus_modulus` slots as a plaintext modulo
+/// `ciphertext_modulus`.
+pub(crate) fn encode_slot<Scalar: GadgetScalar>(
+    slot: u64,
+    torus_modulus: u64,
+    ciphertext_modulus: CiphertextModulus<Scalar>,
+) -> Scalar {
+    let bits = modulus_bits(ciphertext_modulus);
+    Scalar::cast_from(((slot as u128) << bits) / torus_modulus as u128)
+}
+
+/// Encodes `message` modulo `plaintext_modulus` as a plaintext modulo `ciphertext_modulus`.
+pub(crate) fn encode<Scalar: GadgetScalar>(
+    message: u32,
+    plaintext_modulus: u32,
+    ciphertext_modulus: CiphertextModulus<Scalar>,
+) -> Scalar {
+    encode_slot(
+        message as u64,
+        torus_modulus(plaintext_modulus),
+        ciphertext_modulus,
+    )
+}
+
+/// Encodes `message` modulo `plaintext_modulus` as a ciphertext coefficient, non native moduli
+/// being stored in the most significant bits of the scalar.
+pub(crate) fn encode_coefficient<Scalar: GadgetScalar>(
+    message: u32,
+    plaintext_modulus: u32,
+    ciphertext_modulus: CiphertextModulus<Scalar>,
+) -> Scalar {
+    encode(message, plaintext_modulus, ciphertext_modulus)
+        .wrapping_mul(ciphertext_modulus.get_power_of_two_scaling_to_native_torus())
+}
+
+/// Decodes the plaintext `value` modulo `ciphertext_modulus` to the closest slot of a torus split
+/// in `torus_modulus` slots.
+fn decode_slot<Scalar: GadgetScalar>(
+    value: Scalar,
+    torus_modulus: u64,
+    ciphertext_modulus: CiphertextModulus<Scalar>,
+) -> u64 {
+    let bits = modulus_bits(ciphertext_modulus);
+    let value: u128 = value.cast_into();
+    let torus_modulus = torus_modulus as u128;
+
+    // ((t * d) + (q/2)) / q; to round
+    (((value * torus_modulus + (1 << (bits - 1))) >> bits) % torus_modulus) as u64
+}
+
+/// Decodes the plaintext `value` modulo `ciphertext_modulus` to the closest message modulo
+/// `plaintext_modulus`.
+///
+/// A message modulo an even modulus whose padding bit is set, e.g. after a linear combination
+/// overflowed, still decodes to the sum modulo `plaintext_modulus`.
+pub(crate) fn decode<Scalar: GadgetScalar>(
+    value: Scalar,
+    plaintext_modulus: u32,
+    ciphertext_modulus: CiphertextModulus<Scalar>,
+) -> u32 {
+    let slot = decode_slot(value, torus_modulus(plaintext_modulus), ciphertext_modulus);
+    (slot % plaintext_modulus as u64) as u32
+}
+
+/// Same as [`decode`], also returning the signed difference on the torus between `value` and the
+/// center of the slot of the decoded message, within `[-1 / 2t, 1 / 2t]` for the
+/// [`torus_modulus`] `t` of `plaintext_modulus`.
+pub(crate) fn decode_with_noise<Scalar: GadgetScalar>(
+    value: Scalar,
+    plaintext_modulus: u32,
+    ciphertext_modulus: CiphertextModulus<Scalar>,
+) -> (u32, f64) {
+    let torus_modulus = torus_modulus(plaintext_modulus);
+    let slot = decode_slot(value, torus_modulus, ciphertext_modulus);
+    let bits = modulus_bits(ciphertext_modulus);
+    let value: u128 = value.cast_into();
+
+    let mut noise = value as f64 / 2f64.powi(bits as i32) - slot as f64 / torus_modulus as f64;
+    // the slot of 0 wraps around the torus
+    noise -= noise.round();
+    ((slot % plaintext_modulus as u64) as u32, noise)
+}
+
+/// Same as [`decode`], also returning the distance on the torus between `value` and the center
+/// of the slot of the decoded message, at most `1 / 2p` for odd moduli and `1 / 4p` for even
+/// ones.
+pub(crate) fn decode_with_distance<Scalar: GadgetScalar>(
+    value: Scalar,
+    plaintext_modulus: u32,
+    ciphertext_modulus: CiphertextModulus<Scalar>,
+) -> (u32, f64) {
+    let (message, noise) = decode_with_noise(value, plaintext_modulus, ciphertext_modulus);
+    (message, noise.abs())
+}
+
+/// Overwrites the secret `data` with zeros before its memory is freed, the volatile writes
+/// preventing the compiler from eliding the stores to memory that is not read afterwards.
+pub(crate) fn zeroize<T: Numeric>(data: &mut [T]) {
+    for value in data.iter_mut() {
+        // SAFETY: value is a valid and aligned pointer, borrowed mutably from the slice
+        unsafe { std::ptr::write_volatile(value, T::ZERO) };
+    }
+    std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+}
+
+/// Number of threads of the multi-bit blind rotation, the ones found best by shortint on many
+/// core machines for the grouping factors 2 and 3.
+fn multi_bit_thread_count(grouping_factor: LweBskGroupingFactor) -> ThreadCount {
+    match grouping_factor.0 {
+        2 => ThreadCount(5),
+        3 => ThreadCount(7),
+        factor => ThreadCount((1 << factor) - 1),
+    }
+}
+
+/// Converts a standard bootstrapping key to the Fourier domain.
+pub(crate) fn classic_fourier_bootstrapping_key<Scalar: GadgetScalar>(
+    bootstrapping_key: &LweBootstrapKeyOwned<Scalar>,
+) -> GadgetBootstrappingKey {
+    let mut fourier_bsk = FourierLweBootstrapKey::new(
+        bootstrapping_key.input_lwe_dimension(),
+        bootstrapping_key.glwe_size(),
+        bootstrapping_key.polynomial_size(),
+        bootstrapping_key.decomposition_base_log(),
+        bootstrapping_key.decomposition_level_count(),
+    );
+
+    par_convert_standard_lwe_bootstrap_key_to_fourier(bootstrapping_key, &mut fourier_bsk);
+
+    GadgetBootstrappingKey::Classic(fourier_bsk)
+}
+
+/// Converts a standard multi-bit bootstrapping key to the Fourier domain.
+pub(crate) fn multi_bit_fourier_bootstrapping_key<Scalar: GadgetScalar>(
+    bootstrapping_key: &LweMultiBitBootstrapKeyOwned<Scalar>,
+) -> GadgetBootstrappingKey {
+    let mut fourier_bsk = FourierLweMultiBitBootstrapKey::new(
+        bootstrapping_key.input_lwe_dimension(),
+        bootstrapping_key.glwe_size(),
+        bootstrapping_key.polynomial_size(),
+        bootstrapping_key.decomposition_base_log(),
+        bootstrapping_key.decomposition_level_count(),
+        bootstrapping_key.grouping_factor(),
+    );
+
+    par_convert_standard_lwe_multi_bit_bootstrap_key_to_fourier(
+        bootstrapping_key,
+        &mut fourier_bsk,
+    );
+
+    GadgetBootstrappingKey::MultiBit {
+        fourier_bsk,
+        thread_count: multi_bit_thread_count(bootstrapping_key.grouping_factor()),
+    }
+}
+
+/// Sizes `computation_buffers` for the bootstraps of [`programmable_bootstrap`] with
+/// `bootstrapping_key`.
+fn reserve_bootstrap_buffers<Scalar: GadgetScalar>(
+    bootstrapping_key: &GadgetBootstrappingKey,
+    fft: FftView<'_>,
+    computation_buffers: &mut ComputationBuffers,
+) {
+    if let GadgetBootstrappingKey::Classic(fourier_bsk) = bootstrapping_key {
+        computation_buffers.resize(
+            programmable_bootstrap_lwe_ciphertext_mem_optimized_requirement::<Scalar>(
+                fourier_bsk.glwe_size(),
+                fourier_bsk.polynomial_size(),
+                fft,
+            )
+            .unwrap()
+            .unaligned_bytes_required(),
+        );
+    }
+}
+
+/// Keyswitches `input` into `output` with the keyswitching key of `server_key`.
+fn keyswitch<Scalar, InputCont, OutputCont>(
+    server_key: &ServerKey<Scalar>,
+    input: &LweCiphertext<InputCont>,
+    output: &mut LweCiphertext<OutputCont>,
+) where
+    Scalar: GadgetScalar,
+    InputCont: Container<Element = Scalar>,
+    OutputCont: ContainerMut<Element = Scalar>,
+{
+    let _span = span!(
+        "keyswitch",
+        input_lwe_dimension = input.lwe_size().to_lwe_dimension().0,
+        output_lwe_dimension = output.lwe_size().to_lwe_dimension().0,
+    );
+    keyswitch_lwe_ciphertext(&server_key.key_switching_key, input, output);
+}
 
-pub struct BuffersRef<'a> {
-    pub(crate) lookup_table: GlweCiphertextMutView<'a, u32>,
-    // For the intermediate keyswitch result in the case of a big ciphertext
-    pub(crate) buffer_lwe_after_ks: LweCiphertextMutView<'a, u32>,
-    // For the intermediate PBS result in the case of a smallciphertext
-    pub(crate) buffer_lwe_after_pbs: LweCiphertextMutView<'a, u32>,
+/// Bootstraps `input`, under the small LWE key, with the accumulator `acc` into `output`, under
+/// the big LWE key. `computation_buffers` must be sized by [`reserve_bootstrap_buffers`].
+fn programmable_bootstrap<Scalar, InputCont, OutputCont>(
+    bootstrapping_key: &GadgetBootstrappingKey,
+    input: &LweCiphertext<InputCont>,
+    output: &mut LweCiphertext<OutputCont>,
+    acc: &GlweCiphertextOwned<Scalar>,
+    fft: FftView<'_>,
+    computation_buffers: &mut ComputationBuffers,
+) where
+    Scalar: GadgetScalar,
+    InputCont: Container<Element = Scalar>,
+    OutputCont: ContainerMut<Element = Scalar>,
+{
+    let _span = span!(
+        "pbs",
+        input_lwe_dimension = input.lwe_size().to_lwe_dimension().0,
+        polynomial_size = bootstrapping_key.polynomial_size().0,
+    );
+    match bootstrapping_key {
+        GadgetBootstrappingKey::Classic(fourier_bsk) => {
+            programmable_bootstrap_lwe_ciphertext_mem_optimized(
+                input,
+                output,
+                acc,
+                fourier_bsk,
+                fft,
+                computation_buffers.stack(),
+            );
+        }
+        GadgetBootstrappingKey::MultiBit {
+            fourier_bsk,
+            thread_count,
+        } => {
+            multi_bit_deterministic_programmable_bootstrap_lwe_ciphertext(
+                input,
+                output,
+                acc,
+                fourier_bsk,
+                *thread_count,
+            );
+        }
+        GadgetBootstrappingKey::Ntt(ntt_bsk) => {
+            let mut rotated_acc = acc.clone();
+            ntt_bsk.blind_rotate_assign(input, &mut rotated_acc);
+            extract_lwe_sample_from_glwe_ciphertext(&rotated_acc, output, MonomialDegree(0));
+        }
+    }
+}
+
+pub struct BuffersRef<'a, Scalar: GadgetScalar> {
+    // For the intermediate result between the bootstrap and the keyswitch, under the small LWE
+    // key in the keyswitch-bootstrap order and under the big one in the bootstrap-keyswitch order
+    pub(crate) buffer_lwe_intermediate: LweCiphertextMutView<'a, Scalar>,
 }
 
 #[derive(Default)]
 struct Memory {
-    buffer: Vec<u32>,
+    // Backing storage shared by the u32 and u64 buffers
+    buffer: Vec<u64>,
 }
 
 impl Memory {
-    fn as_buffers(&mut self, server_key: &ServerKey, encoding: &Encoding) -> BuffersRef<'_> {
-        let num_elem_in_accumulator = server_key.bootstrapping_key.glwe_size().0
-            * server_key.bootstrapping_key.polynomial_size().0;
-        let num_of_elem_lwe_after_ksk = server_key.key_switching_key.output_lwe_size().0;
-        let num_of_elem_lwe_after_pbs = server_key
-            .bootstrapping_key
-            .output_lwe_dimension()
-            .to_lwe_size()
-            .0;
-
-        let total_elem_needed =
-            num_elem_in_accumulator + num_of_elem_lwe_after_ksk + num_of_elem_lwe_after_pbs;
-
-        let all_elements = if self.buffer.len() < total_elem_needed {
-            self.buffer.resize(total_elem_needed, 0u32);
-            self.buffer.as_mut_slice()
-        } else {
-            &mut self.buffer[..total_elem_needed]
+    fn as_buffers<Scalar: GadgetScalar>(
+        &mut self,
+        server_key: &ServerKey<Scalar>,
+    ) -> BuffersRef<'_, Scalar> {
+        let total_elem_needed = match server_key.parameters.pbs_order() {
+            PBSOrder::KeyswitchBootstrap => server_key.key_switching_key.output_lwe_size().0,
+            PBSOrder::BootstrapKeyswitch => {
+                server_key
+                    .bootstrapping_key
+                    .output_lwe_dimension()
+                    .to_lwe_size()
+                    .0
+            }
         };
 
-        let (accumulator_elements, other_elements) =
-            all_elements.split_at_mut(num_elem_in_accumulator);
+        let word_size = std::mem::size_of::<u64>();
+        let total_words_needed =
+            (total_elem_needed * std::mem::size_of::<Scalar>() + word_size - 1) / word_size;
+        if self.buffer.len() < total_words_needed {
+            self.buffer.resize(total_words_needed, 0u64);
+        }
+        let all_elements: &mut [Scalar] =
+            &mut bytemuck::cast_slice_mut(&mut self.buffer[..total_words_needed])
+                [..total_elem_needed];
 
-        let mut acc = GlweCiphertext::from_container(
-            accumulator_elements,
-            server_key.bootstrapping_key.polynomial_size(),
-            CiphertextModulus::new_native(),
-        );
+        let buffer_lwe_intermediate =
+            LweCiphertextMutView::from_container(all_elements, server_key.ciphertext_modulus());
 
-        // accumulator is a trivial ciphertext of test vector polynomial
-        acc.get_mut_mask().as_mut().fill(0u32);
+        BuffersRef {
+            buffer_lwe_intermediate,
+        }
+    }
+}
 
-        {
-            let p = encoding.p as usize;
-            let n = server_key.bootstrapping_key.polynomial_size().0;
-            let half_window = (n / (2 * p));
-            let encoding_acc = encoding.create_accumulator();
-
-            // handle first half of 0^th window
-            let v = ((1u64 << 32) / p as u64) * encoding_acc[0] as u64;
-            acc.get_mut_body().as_mut()[..half_window].fill(v as u32);
-
-            for i in 1..(p as usize) {
-                let v = ((1u64 << 32) / p as u64) * encoding_acc[i] as u64;
-                acc.get_mut_body().as_mut()
-                    [((i - 1) * n / p) + half_window..i * n / p + half_window]
-                    .fill(v as u32);
-            }
+/// Scratch buffers of the bootstraps, grown to the sizes required by the server keys they are
+/// used with and kept for the next evaluations.
+///
+/// Every thread evaluating gates through the thread-local [`GadgetEngine`] keeps such buffers
+/// until it exits. Servers creating their own contexts, and passing them to
+/// [`ServerKey::evaluate_gate_with_context`], decide how many exist and when they are freed.
+#[derive(Default)]
+pub struct EvaluationContext {
+    memory: Memory,
+    computation_buffers: ComputationBuffers,
+}
 
-            // handle second half of 0^th window
-            let v = ((1u64 << 32) / p as u64) * encoding_acc[p] as u64;
-            acc.get_mut_body().as_mut()[n - half_window..].fill(v as u32);
-        }
+impl EvaluationContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        let (after_ks_elements, after_pbs_elements) =
-            other_elements.split_at_mut(num_of_elem_lwe_after_ksk);
+    /// Sizes the buffers of this context for the bootstraps of `server_key`, see
+    /// [`GadgetEngine::preallocate`].
+    pub fn preallocate<Scalar: GadgetScalar>(&mut self, server_key: &ServerKey<Scalar>) {
+        self.memory.as_buffers(server_key);
 
-        let buffer_lwe_after_ks = LweCiphertextMutView::from_container(
-            after_ks_elements,
-            CiphertextModulus::new_native(),
-        );
-        let buffer_lwe_after_pbs = LweCiphertextMutView::from_container(
-            after_pbs_elements,
-            CiphertextModulus::new_native(),
+        // also caches the FFT plan of the polynomial size
+        let bootstrapping_key = &server_key.bootstrapping_key;
+        let fft = Fft::new(bootstrapping_key.polynomial_size());
+        reserve_bootstrap_buffers::<Scalar>(
+            bootstrapping_key,
+            fft.as_view(),
+            &mut self.computation_buffers,
         );
+    }
 
-        BuffersRef {
-            lookup_table: acc,
-            buffer_lwe_after_ks,
-            buffer_lwe_after_pbs,
-        }
+    /// Returns the size in bytes of the buffers held by this context.
+    pub fn size_in_bytes(&self) -> usize {
+        self.memory.buffer.len() * std::mem::size_of::<u64>()
+            + self.computation_buffers.size_in_bytes()
     }
 }
 
 pub struct Bootstrapper {
-    memory: Memory,
+    context: EvaluationContext,
 
     encryption_generator: EncryptionRandomGenerator<ActivatedRandomGenerator>,
-    computation_buffers: ComputationBuffers,
+    pub(crate) seeder: DeterministicSeeder<ActivatedRandomGenerator>,
 }
 
 impl Bootstrapper {
     pub fn new(seeder: &mut dyn Seeder) -> Self {
-        let memory = Default::default();
-
         Bootstrapper {
-            memory,
+            context: EvaluationContext::default(),
             encryption_generator: EncryptionRandomGenerator::<_>::new(seeder.seed(), seeder),
-            computation_buffers: ComputationBuffers::default(),
+            seeder: DeterministicSeeder::<_>::new(seeder.seed()),
         }
     }
 
-    pub fn bootstrap_keyswitch(
+    pub fn bootstrap_keyswitch<Scalar: GadgetScalar>(
         &mut self,
-        mut ciphertext: LweCiphertextOwned<u32>,
-        server_key: &ServerKey,
-        encoding: &Encoding,
-    ) -> Result<Ciphertext, Box<dyn Error>> {
-        let BuffersRef {
-            lookup_table: accumulator,
-            mut buffer_lwe_after_ks,
-            mut buffer_lwe_after_pbs,
-        } = self.memory.as_buffers(server_key, encoding);
+        mut ciphertext: LweCiphertextOwned<Scalar>,
+        server_key: &ServerKey<Scalar>,
+        lookup_table: &LookupTable<Scalar>,
+        output_plaintext_modulus: u32,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        self.bootstrap_keyswitch_assign(&mut ciphertext, server_key, lookup_table);
 
-        let fourier_bsk = &server_key.bootstrapping_key;
+        Ok(Ciphertext::Encrypted(
+            ciphertext,
+            output_plaintext_modulus,
+            NoiseLevel::NOMINAL,
+        ))
+    }
 
-        let fft = Fft::new(fourier_bsk.polynomial_size());
-        let fft = fft.as_view();
+    /// Bootstraps `ciphertext` with `lookup_table` and keyswitches the result back into
+    /// `ciphertext`, in the [`PBSOrder`] of `server_key`, without allocating.
+    pub fn bootstrap_keyswitch_assign<Scalar: GadgetScalar>(
+        &mut self,
+        ciphertext: &mut LweCiphertextOwned<Scalar>,
+        server_key: &ServerKey<Scalar>,
+        lookup_table: &LookupTable<Scalar>,
+    ) {
+        let BuffersRef {
+            mut buffer_lwe_intermediate,
+        } = self.context.memory.as_buffers(server_key);
 
-        self.computation_buffers.resize(
-            programmable_bootstrap_lwe_ciphertext_mem_optimized_requirement::<u64>(
-                fourier_bsk.glwe_size(),
-                fourier_bsk.polynomial_size(),
-                fft,
-            )
-            .unwrap()
-            .unaligned_bytes_required(),
+        let bootstrapping_key = &server_key.bootstrapping_key;
+        let fft = Fft::new(bootstrapping_key.polynomial_size());
+        let fft = fft.as_view();
+        reserve_bootstrap_buffers::<Scalar>(
+            bootstrapping_key,
+            fft,
+            &mut self.context.computation_buffers,
         );
-        let stack = self.computation_buffers.stack();
 
-        programmable_bootstrap_lwe_ciphertext_mem_optimized(
-            &ciphertext,
-            &mut buffer_lwe_after_pbs,
-            &accumulator,
-            fourier_bsk,
+        match server_key.parameters.pbs_order() {
+            PBSOrder::BootstrapKeyswitch => {
+                programmable_bootstrap(
+                    bootstrapping_key,
+                    ciphertext,
+                    &mut buffer_lwe_intermediate,
+                    &lookup_table.acc,
+                    fft,
+                    &mut self.context.computation_buffers,
+                );
+                keyswitch(server_key, &buffer_lwe_intermediate, ciphertext);
+            }
+            PBSOrder::KeyswitchBootstrap => {
+                keyswitch(server_key, ciphertext, &mut buffer_lwe_intermediate);
+                programmable_bootstrap(
+                    bootstrapping_key,
+                    &buffer_lwe_intermediate,
+                    ciphertext,
+                    &lookup_table.acc,
+                    fft,
+                    &mut self.context.computation_buffers,
+                );
+            }
+        }
+    }
+
+    /// Bootstraps `input`, under the small LWE key, with `lookup_table` into `output`, under the
+    /// big LWE key, leaving the keyswitch to the caller.
+    pub fn programmable_bootstrap<Scalar, InputCont, OutputCont>(
+        &mut self,
+        input: &LweCiphertext<InputCont>,
+        output: &mut LweCiphertext<OutputCont>,
+        server_key: &ServerKey<Scalar>,
+        lookup_table: &LookupTable<Scalar>,
+    ) where
+        Scalar: GadgetScalar,
+        InputCont: Container<Element = Scalar>,
+        OutputCont: ContainerMut<Element = Scalar>,
+    {
+        let bootstrapping_key = &server_key.bootstrapping_key;
+        let fft = Fft::new(bootstrapping_key.polynomial_size());
+        let fft = fft.as_view();
+        reserve_bootstrap_buffers::<Scalar>(
+            bootstrapping_key,
             fft,
-            stack,
+            &mut self.context.computation_buffers,
         );
+        programmable_bootstrap(
+            bootstrapping_key,
+            input,
+            output,
+            &lookup_table.acc,
+            fft,
+            &mut self.context.computation_buffers,
+        );
+    }
 
-        keyswitch_lwe_ciphertext(
-            &server_key.key_switching_key,
-            &buffer_lwe_after_pbs,
-            &mut ciphertext,
+    /// Bootstraps and keyswitches a batch of `ciphertexts` with the lookup table of `encoding`.
+    ///
+    /// The lookup table, the FFT plan and the computation buffers are set up once for the whole
+    /// batch instead of once per ciphertext.
+    pub fn bootstrap_many<Scalar: GadgetScalar>(
+        &mut self,
+        ciphertexts: &[LweCiphertextOwned<Scalar>],
+        server_key: &ServerKey<Scalar>,
+        encoding: &Encoding,
+    ) -> Result<Vec<Ciphertext<Scalar>>, GadgetError> {
+        self.bootstrap_stream(
+            ciphertexts
+                .iter()
+                .map(|ciphertext| Ok(ciphertext.as_view())),
+            server_key,
+            encoding,
+        )
+    }
+
+    /// Batched bootstrap of the ciphertexts yielded by `ciphertexts`, which may still be computed
+    /// elsewhere while the previous ones are bootstrapped. Stops at the first error.
+    fn bootstrap_stream<Scalar, InputCont>(
+        &mut self,
+        ciphertexts: impl Iterator<Item = Result<LweCiphertext<InputCont>, GadgetError>>,
+        server_key: &ServerKey<Scalar>,
+        encoding: &Encoding,
+    ) -> Result<Vec<Ciphertext<Scalar>>, GadgetError>
+    where
+        Scalar: GadgetScalar,
+        InputCont: Container<Element = Scalar>,
+    {
+        server_key.parameters.check_plaintext_modulus(encoding.p)?;
+
+        let lookup_table = server_key.lookup_table(encoding);
+        let BuffersRef {
+            mut buffer_lwe_intermediate,
+        } = self.context.memory.as_buffers(server_key);
+
+        let bootstrapping_key = &server_key.bootstrapping_key;
+        let fft = Fft::new(bootstrapping_key.polynomial_size());
+        let fft = fft.as_view();
+        reserve_bootstrap_buffers::<Scalar>(
+            bootstrapping_key,
+            fft,
+            &mut self.context.computation_buffers,
         );
 
-        Ok(Ciphertext::Encrypted(ciphertext))
+        let mut outputs = Vec::with_capacity(ciphertexts.size_hint().0);
+        for ciphertext in ciphertexts {
+            let ciphertext = ciphertext?;
+            let start = Instant::now();
+
+            let mut output = LweCiphertextOwned::new(
+                Scalar::ZERO,
+                server_key.ciphertext_lwe_size(),
+                server_key.ciphertext_modulus(),
+            );
+            match server_key.parameters.pbs_order() {
+                PBSOrder::BootstrapKeyswitch => {
+                    programmable_bootstrap(
+                        bootstrapping_key,
+                        &ciphertext,
+                        &mut buffer_lwe_intermediate,
+                        &lookup_table.acc,
+                        fft,
+                        &mut self.context.computation_buffers,
+                    );
+                    keyswitch(server_key, &buffer_lwe_intermediate, &mut output);
+                }
+                PBSOrder::KeyswitchBootstrap => {
+                    keyswitch(server_key, &ciphertext, &mut buffer_lwe_intermediate);
+                    programmable_bootstrap(
+                        bootstrapping_key,
+                        &buffer_lwe_intermediate,
+                        &mut output,
+                        &lookup_table.acc,
+                        fft,
+                        &mut self.context.computation_buffers,
+                    );
+                }
+            }
+
+            server_key
+                .stats
+                .record_bootstrap(std::slice::from_ref(encoding), start.elapsed());
+            outputs.push(Ciphertext::Encrypted(
+                output,
+                encoding.new_p,
+                NoiseLevel::NOMINAL,
+            ));
+        }
+
+        Ok(outputs)
     }
 
-    pub fn new_server_key(&mut self, client_key: &ClientKey) -> ServerKey {
-        let bootstrapping_key = par_allocate_and_generate_new_lwe_bootstrap_key(
-            &client_key.lwe_secret_key,
-            &client_key.glwe_secret_key,
-            client_key.parameters.pbs_base_log,
-            client_key.parameters.pbs_level,
-            client_key.parameters.glwe_modular_std_dev,
-            CiphertextModulus::new_native(),
-            &mut self.encryption_generator,
+    /// Multi-value bootstrap: one blind rotation shared by all `encodings`.
+    ///
+    /// The redundant test polynomial `(delta / 2) * (1 + X + ... + X^{N-1})` is blind rotated
+    /// once. As `(1 + X + ... + X^{N-1}) * (1 - X) = 2` modulo `X^N + 1`, multiplying the rotated
+    /// accumulator by the small factor `(1 - X) * t(X)` yields the rotation of `delta * t(X)`
+    /// for the test polynomial `t(X)` of each encoding.
+    ///
+    /// Returns [`GadgetError::InvalidEncoding`] if `encodings` is empty.
+    pub fn multi_bootstrap_keyswitch<Scalar: GadgetScalar>(
+        &mut self,
+        ciphertext: LweCiphertextOwned<Scalar>,
+        server_key: &ServerKey<Scalar>,
+        encodings: &[Encoding],
+    ) -> Result<Vec<Ciphertext<Scalar>>, GadgetError> {
+        let bootstrapping_key = &server_key.bootstrapping_key;
+        let polynomial_size = bootstrapping_key.polynomial_size();
+        let ciphertext_modulus = server_key.ciphertext_modulus();
+        let new_p = encodings
+            .first()
+            .ok_or_else(|| {
+                GadgetError::InvalidEncoding("multi-value evaluation needs an encoding".to_string())
+            })?
+            .new_p;
+
+        let mut rotated_acc = GlweCiphertextOwned::new(
+            Scalar::ZERO,
+            bootstrapping_key.glwe_size(),
+            polynomial_size,
+            ciphertext_modulus,
+        );
+        rotated_acc.get_mut_body().as_mut().fill(
+            encode_slot(1, 2 * torus_modulus(new_p), ciphertext_modulus)
+                .wrapping_mul(ciphertext_modulus.get_power_of_two_scaling_to_native_torus()),
         );
+        self.blind_rotate_assign(ciphertext, server_key, &mut rotated_acc);
 
-        // convert to fourier domain
-        let mut fourier_bsk = FourierLweBootstrapKey::new(
-            bootstrapping_key.input_lwe_dimension(),
+        let mut output_acc = GlweCiphertextOwned::new(
+            Scalar::ZERO,
             bootstrapping_key.glwe_size(),
-            bootstrapping_key.polynomial_size(),
-            bootstrapping_key.decomposition_base_log(),
-            bootstrapping_key.decomposition_level_count(),
+            polynomial_size,
+            ciphertext_modulus,
         );
+        let outputs = encodings
+            .iter()
+            .map(|encoding| {
+                let factor = multi_value_factor(encoding, polynomial_size);
 
-        let fft = Fft::new(bootstrapping_key.polynomial_size());
-        let fft = fft.as_view();
-        self.computation_buffers.resize(
-            convert_standard_lwe_bootstrap_key_to_fourier_mem_optimized_requirement(fft)
-                .unwrap()
-                .unaligned_bytes_required(),
+                for (mut output_poly, rotated_poly) in izip!(
+                    output_acc.as_mut_polynomial_list().iter_mut(),
+                    rotated_acc.as_polynomial_list().iter()
+                ) {
+                    polynomial_sparse_wrapping_mul(
+                        output_poly.as_mut(),
+                        rotated_poly.as_ref(),
+                        &factor,
+                    );
+                }
+
+                let output = extract_keyswitch(server_key, &output_acc, MonomialDegree(0));
+                Ciphertext::Encrypted(output, new_p, NoiseLevel::NOMINAL)
+            })
+            .collect();
+
+        Ok(outputs)
+    }
+
+    /// Many-LUT bootstrap: one blind rotation of `lookup_table`, which interleaves the test
+    /// polynomials of `output_count` encodings, see [`ServerKey::generate_many_lookup_table`].
+    ///
+    /// The test polynomial of output `j` is read at the degree `j * s` of the rotated
+    /// accumulator, `s` being the width of the sub-windows of the lookup table. Each output
+    /// only costs a sample extraction and a keyswitch.
+    pub fn many_lut_bootstrap_keyswitch<Scalar: GadgetScalar>(
+        &mut self,
+        ciphertext: LweCiphertextOwned<Scalar>,
+        server_key: &ServerKey<Scalar>,
+        lookup_table: &LookupTable<Scalar>,
+        p: u32,
+        new_p: u32,
+        output_count: usize,
+    ) -> Vec<Ciphertext<Scalar>> {
+        let mut rotated_acc = lookup_table.acc.clone();
+        self.blind_rotate_assign(ciphertext, server_key, &mut rotated_acc);
+
+        let sub_window = rotated_acc.polynomial_size().0 / (p as usize * output_count);
+        (0..output_count)
+            .map(|output| {
+                let degree = MonomialDegree(output * sub_window);
+                let output = extract_keyswitch(server_key, &rotated_acc, degree);
+                Ciphertext::Encrypted(output, new_p, NoiseLevel::NOMINAL)
+            })
+            .collect()
+    }
+
+    /// Blind rotates `acc` by the message of `ciphertext`, keyswitching it to the small LWE key
+    /// first in the [`PBSOrder::KeyswitchBootstrap`] order.
+    fn blind_rotate_assign<Scalar: GadgetScalar>(
+        &mut self,
+        ciphertext: LweCiphertextOwned<Scalar>,
+        server_key: &ServerKey<Scalar>,
+        acc: &mut GlweCiphertextOwned<Scalar>,
+    ) {
+        let bootstrapping_key = &server_key.bootstrapping_key;
+        let polynomial_size = bootstrapping_key.polynomial_size();
+
+        // the blind rotation takes a ciphertext under the small key
+        let ciphertext = match server_key.parameters.pbs_order() {
+            PBSOrder::BootstrapKeyswitch => ciphertext,
+            PBSOrder::KeyswitchBootstrap => {
+                let mut small_ciphertext = LweCiphertextOwned::new(
+                    Scalar::ZERO,
+                    server_key.key_switching_key.output_lwe_size(),
+                    server_key.ciphertext_modulus(),
+                );
+                keyswitch(server_key, &ciphertext, &mut small_ciphertext);
+                small_ciphertext
+            }
+        };
+
+        let _span = span!(
+            "pbs",
+            input_lwe_dimension = ciphertext.lwe_size().to_lwe_dimension().0,
+            polynomial_size = polynomial_size.0,
         );
+        match bootstrapping_key {
+            GadgetBootstrappingKey::Classic(fourier_bsk) => {
+                let fft = Fft::new(polynomial_size);
+                let fft = fft.as_view();
 
-        par_convert_standard_lwe_bootstrap_key_to_fourier(&bootstrapping_key, &mut fourier_bsk);
+                self.context.computation_buffers.resize(
+                    blind_rotate_assign_mem_optimized_requirement::<Scalar>(
+                        fourier_bsk.glwe_size(),
+                        polynomial_size,
+                        fft,
+                    )
+                    .unwrap()
+                    .unaligned_bytes_required(),
+                );
+                let stack = self.context.computation_buffers.stack();
 
-        let big_lwe_secret_key = client_key.glwe_secret_key.clone().into_lwe_secret_key();
+                blind_rotate_assign_mem_optimized(&ciphertext, acc, fourier_bsk, fft, stack);
+            }
+            GadgetBootstrappingKey::MultiBit {
+                fourier_bsk,
+                thread_count,
+            } => {
+                multi_bit_deterministic_blind_rotate_assign(
+                    &ciphertext,
+                    acc,
+                    fourier_bsk,
+                    *thread_count,
+                );
+            }
+            GadgetBootstrappingKey::Ntt(ntt_bsk) => {
+                ntt_bsk.blind_rotate_assign(&ciphertext, acc);
+            }
+        }
+    }
 
-        let ksk = allocate_and_generate_new_lwe_keyswitch_key(
+    pub fn new_server_key<Scalar: GadgetScalar>(
+        &mut self,
+        client_key: &ClientKey<Scalar>,
+    ) -> ServerKey<Scalar> {
+        let parameters = &client_key.parameters;
+        let _span = span!(
+            "keygen",
+            key = "server",
+            lwe_dimension = parameters.lwe_dimension.0,
+            glwe_dimension = parameters.glwe_dimension.0,
+            polynomial_size = parameters.polynomial_size.0,
+        );
+        let bootstrapping_key = match parameters.grouping_factor {
+            None if parameters.pbs_backend == PbsBackend::Ntt => {
+                GadgetBootstrappingKey::Ntt(NttBootstrapKey::generate(
+                    &client_key.lwe_secret_key,
+                    &client_key.glwe_secret_key,
+                    parameters.pbs_base_log,
+                    parameters.pbs_level,
+                    parameters.glwe_modular_std_dev,
+                    &mut self.encryption_generator,
+                ))
+            }
+            None => {
+                let bootstrapping_key = par_allocate_and_generate_new_lwe_bootstrap_key(
+                    &client_key.lwe_secret_key,
+                    &client_key.glwe_secret_key,
+                    parameters.pbs_base_log,
+                    parameters.pbs_level,
+                    parameters.glwe_modular_std_dev,
+                    client_key.ciphertext_modulus(),
+                    &mut self.encryption_generator,
+                );
+
+                let fft = Fft::new(bootstrapping_key.polynomial_size());
+                let fft = fft.as_view();
+                self.context.computation_buffers.resize(
+                    convert_standard_lwe_bootstrap_key_to_fourier_mem_optimized_requirement(fft)
+                        .unwrap()
+                        .unaligned_bytes_required(),
+                );
+
+                classic_fourier_bootstrapping_key(&bootstrapping_key)
+            }
+            Some(grouping_factor) => {
+                let bootstrapping_key = par_allocate_and_generate_new_lwe_multi_bit_bootstrap_key(
+                    &client_key.lwe_secret_key,
+                    &client_key.glwe_secret_key,
+                    parameters.pbs_base_log,
+                    parameters.pbs_level,
+                    grouping_factor,
+                    parameters.glwe_modular_std_dev,
+                    client_key.ciphertext_modulus(),
+                    &mut self.encryption_generator,
+                );
+
+                multi_bit_fourier_bootstrapping_key(&bootstrapping_key)
+            }
+        };
+
+        let big_lwe_secret_key = client_key.glwe_secret_key.as_lwe_secret_key();
+
+        let ksk = par_allocate_and_generate_new_lwe_keyswitch_key(
             &big_lwe_secret_key,
             &client_key.lwe_secret_key,
             client_key.parameters.ks_base_log,
             client_key.parameters.ks_level,
             client_key.parameters.lwe_modular_std_dev,
-            CiphertextModulus::new_native(),
+            client_key.ciphertext_modulus(),
             &mut self.encryption_generator,
         );
 
         ServerKey {
-            bootstrapping_key: fourier_bsk,
+            bootstrapping_key,
             key_switching_key: ksk,
+            parameters: client_key.parameters,
+            packing_key: None,
+            lookup_tables: Default::default(),
+            stats: Default::default(),
+        }
+    }
+
+    pub fn new_compressed_server_key<Scalar: GadgetScalar>(
+        &mut self,
+        client_key: &ClientKey<Scalar>,
+    ) -> CompressedServerKey<Scalar> {
+        let parameters = &client_key.parameters;
+        assert_eq!(
+            parameters.pbs_backend,
+            PbsBackend::Fft,
+            "NTT bootstrapping keys cannot be compressed"
+        );
+        let bootstrapping_key = match parameters.grouping_factor {
+            None => {
+                #[cfg(not(feature = "__wasm_api"))]
+                let bootstrapping_key = par_allocate_and_generate_new_seeded_lwe_bootstrap_key(
+                    &client_key.lwe_secret_key,
+                    &client_key.glwe_secret_key,
+                    parameters.pbs_base_log,
+                    parameters.pbs_level,
+                    parameters.glwe_modular_std_dev,
+                    client_key.ciphertext_modulus(),
+                    &mut self.seeder,
+                );
+
+                #[cfg(feature = "__wasm_api")]
+                let bootstrapping_key = allocate_and_generate_new_seeded_lwe_bootstrap_key(
+                    &client_key.lwe_secret_key,
+                    &client_key.glwe_secret_key,
+                    parameters.pbs_base_log,
+                    parameters.pbs_level,
+                    parameters.glwe_modular_std_dev,
+                    client_key.ciphertext_modulus(),
+                    &mut self.seeder,
+                );
+
+                CompressedBootstrappingKey::Classic(bootstrapping_key)
+            }
+            Some(grouping_factor) => {
+                #[cfg(not(feature = "__wasm_api"))]
+                let bootstrapping_key =
+                    par_allocate_and_generate_new_seeded_lwe_multi_bit_bootstrap_key(
+                        &client_key.lwe_secret_key,
+                        &client_key.glwe_secret_key,
+                        parameters.pbs_base_log,
+                        parameters.pbs_level,
+                        parameters.glwe_modular_std_dev,
+                        grouping_factor,
+                        client_key.ciphertext_modulus(),
+                        &mut self.seeder,
+                    );
+
+                #[cfg(feature = "__wasm_api")]
+                let bootstrapping_key =
+                    allocate_and_generate_new_seeded_lwe_multi_bit_bootstrap_key(
+                        &client_key.lwe_secret_key,
+                        &client_key.glwe_secret_key,
+                        parameters.pbs_base_log,
+                        parameters.pbs_level,
+                        parameters.glwe_modular_std_dev,
+                        grouping_factor,
+                        client_key.ciphertext_modulus(),
+                        &mut self.seeder,
+                    );
+
+                CompressedBootstrappingKey::MultiBit(bootstrapping_key)
+            }
+        };
+
+        let big_lwe_secret_key = client_key.glwe_secret_key.as_lwe_secret_key();
+
+        #[cfg(not(feature = "__wasm_api"))]
+        let key_switching_key = par_allocate_and_generate_new_seeded_lwe_keyswitch_key(
+            &big_lwe_secret_key,
+            &client_key.lwe_secret_key,
+            parameters.ks_base_log,
+            parameters.ks_level,
+            parameters.lwe_modular_std_dev,
+            client_key.ciphertext_modulus(),
+            &mut self.seeder,
+        );
+
+        #[cfg(feature = "__wasm_api")]
+        let key_switching_key = allocate_and_generate_new_seeded_lwe_keyswitch_key(
+            &big_lwe_secret_key,
+            &client_key.lwe_secret_key,
+            parameters.ks_base_log,
+            parameters.ks_level,
+            parameters.lwe_modular_std_dev,
+            client_key.ciphertext_modulus(),
+            &mut self.seeder,
+        );
+
+        CompressedServerKey {
+            bootstrapping_key,
+            key_switching_key,
+            parameters: client_key.parameters,
         }
     }
 }
@@ -247,11 +1011,30 @@ impl WithThreadLocalEngine for GadgetEngine {
 }
 
 impl GadgetEngine {
+    /// Replaces the thread-local engine used by the gadget keys of the current thread with
+    /// `new_engine`, e.g. one created by [`GadgetEngine::new_from_seeder`] to draw the randomness
+    /// from a custom [`Seeder`] instead of the one of the platform.
+    ///
+    /// Engines of other threads are not affected.
+    pub fn replace_thread_local(new_engine: Self) {
+        Self::with_thread_local_mut(|local_engine| {
+            *local_engine = new_engine;
+        })
+    }
+
     pub fn new() -> Self {
         let mut root_seeder = new_seeder();
         Self::new_from_seeder(root_seeder.as_mut())
     }
 
+    /// Creates an engine whose randomness is derived from `seed` only, so that the keys and
+    /// ciphertexts it generates are reproducible, see
+    /// [`gen_keys_from_seed`](super::gen_keys_from_seed).
+    pub fn new_from_seed(seed: Seed) -> Self {
+        let mut seeder = DeterministicSeeder::<ActivatedRandomGenerator>::new(seed);
+        Self::new_from_seeder(&mut seeder)
+    }
+
     pub fn new_from_seeder(root_seeder: &mut dyn Seeder) -> Self {
         let mut deterministic_seeder =
             DeterministicSeeder::<ActivatedRandomGenerator>::new(root_seeder.seed());
@@ -268,71 +1051,302 @@ impl GadgetEngine {
         }
     }
 
-    pub fn encrypt(
+    pub fn encrypt<Scalar: GadgetScalar>(
         &mut self,
         message: u32,
-        client_key: &ClientKey,
+        client_key: &ClientKey<Scalar>,
         plaintext_modulus: u32,
-    ) -> Ciphertext {
-        let plaintext = Plaintext((((message as u64) << 32) / plaintext_modulus as u64) as u32);
+    ) -> Ciphertext<Scalar> {
+        let plaintext = Plaintext(encode_secret(
+            message,
+            plaintext_modulus,
+            client_key.ciphertext_modulus(),
+        ));
 
-        // default to small LWE secret
-        let lwe_secret = LweSecretKey::from_container(client_key.lwe_secret_key.as_ref());
+        let (lwe_secret, noise) = client_key.encryption_key();
+        let _span = span!(
+            "encrypt",
+            plaintext_modulus = plaintext_modulus,
+            lwe_dimension = lwe_secret.lwe_dimension().0,
+        );
 
         let ct = allocate_and_encrypt_new_lwe_ciphertext(
             &lwe_secret,
             plaintext,
-            client_key.parameters.lwe_modular_std_dev,
-            CiphertextModulus::new_native(),
+            noise,
+            client_key.ciphertext_modulus(),
             &mut self.encryption_generator,
         );
 
-        Ciphertext::Encrypted(ct)
+        Ciphertext::Encrypted(ct, plaintext_modulus, NoiseLevel::NOMINAL)
     }
 
-    pub fn decrypt(&self, ct: &Ciphertext, client_key: &ClientKey, plaintext_modulus: u32) -> u32 {
+    /// Encrypts `messages` modulo `plaintext_modulus` into a single list, the encryption
+    /// generator being forked once for the whole batch.
+    pub fn encrypt_many<Scalar: GadgetScalar>(
+        &mut self,
+        messages: &[u32],
+        client_key: &ClientKey<Scalar>,
+        plaintext_modulus: u32,
+    ) -> CiphertextList<Scalar> {
+        let ciphertext_modulus = client_key.ciphertext_modulus();
+        let plaintexts = PlaintextList::from_container(
+            messages
+                .iter()
+                .map(|message| encode_secret(*message, plaintext_modulus, ciphertext_modulus))
+                .collect::<Vec<_>>(),
+        );
+
+        let (lwe_secret, noise) = client_key.encryption_key();
+
+        let mut lwe_list = LweCiphertextListOwned::new(
+            Scalar::ZERO,
+            lwe_secret.lwe_dimension().to_lwe_size(),
+            LweCiphertextCount(messages.len()),
+            ciphertext_modulus,
+        );
+        // the generator cannot be forked for an empty list
+        if !messages.is_empty() {
+            par_encrypt_lwe_ciphertext_list(
+                &lwe_secret,
+                &mut lwe_list,
+                &plaintexts,
+                noise,
+                &mut self.encryption_generator,
+            );
+        }
+
+        CiphertextList {
+            lwe_list,
+            plaintext_modulus,
+            noise_level: NoiseLevel::NOMINAL,
+        }
+    }
+
+    pub fn encrypt_compressed<Scalar: GadgetScalar>(
+        &mut self,
+        message: u32,
+        client_key: &ClientKey<Scalar>,
+        plaintext_modulus: u32,
+    ) -> CompressedCiphertext<Scalar> {
+        let plaintext = Plaintext(encode_secret(
+            message,
+            plaintext_modulus,
+            client_key.ciphertext_modulus(),
+        ));
+
+        let (lwe_secret, noise) = client_key.encryption_key();
+
+        let ct = allocate_and_encrypt_new_seeded_lwe_ciphertext(
+            &lwe_secret,
+            plaintext,
+            noise,
+            client_key.ciphertext_modulus(),
+            &mut self.bootstrapper.seeder,
+        );
+
+        CompressedCiphertext {
+            ciphertext: ct,
+            plaintext_modulus,
+        }
+    }
+
+    pub fn decrypt<Scalar: GadgetScalar>(
+        &self,
+        ct: &Ciphertext<Scalar>,
+        client_key: &ClientKey<Scalar>,
+    ) -> u32 {
         match ct {
-            Ciphertext::Encrypted(lwe_ct) => {
-                // default to small LWE secret
-                let lwe_secret = LweSecretKey::from_container(client_key.lwe_secret_key.as_ref());
-
-                let decrypted_u32 = decrypt_lwe_ciphertext(&lwe_secret, &lwe_ct);
-
-                // {
-                //     // print noise
-                //     let p_over_q = plaintext_modulus as f64 / (1u64 << 32) as f64;
-                //     let scaled_down_decrypted =
-                //         (decrypted_u32.0 as f64 * p_over_q).round() as u32 % plaintext_modulus;
-
-                //     let diff = ((decrypted_u32.0 as i64)
-                //         - (((scaled_down_decrypted as u64) << 32) / plaintext_modulus as u64) as
-                //           i64)
-                //         .abs();
-
-                //     println!("Noise: {}", (diff as f64).log2())
-                // }
-
-                // ((p * d) + (q/2)) / q; to round
-                //
-                // let p_over_q = plaintext_modulus as f64 / (1u64 << 32) as f64;
-                // let scaled_down_decrypted =
-                //     (decrypted_u32.0 as f64 * p_over_q).round() as u32 % plaintext_modulus;
-                // scaled_down_decrypted
-                ((((decrypted_u32.0 as u64 * plaintext_modulus as u64) + (1 << 31)) >> 32) as u32)
-                    % plaintext_modulus
+            Ciphertext::Encrypted(lwe_ct, plaintext_modulus, _) => {
+                let lwe_secret = client_key.decryption_key(lwe_ct.lwe_size().to_lwe_dimension());
+                let decrypted = decrypt_lwe_ciphertext(&lwe_secret, lwe_ct);
+                decode_secret(decrypted.0, *plaintext_modulus, lwe_ct.ciphertext_modulus())
             }
             Ciphertext::Trivial(b) => *b as u32,
-            _ => {
-                panic!("Ciphertext placeholder reached in gadget engine!")
+            Ciphertext::Placeholder => panic!("Ciphertext placeholder reached in gadget engine!"),
+        }
+    }
+
+    /// Decrypts `ct` into its message along with the distance on the torus between its
+    /// decrypted value and the center of the window of the message, i.e. its noise if the
+    /// message is the one encrypted. Trivial ciphertexts have no noise.
+    pub fn try_decrypt<Scalar: GadgetScalar>(
+        &self,
+        ct: &Ciphertext<Scalar>,
+        client_key: &ClientKey<Scalar>,
+    ) -> Result<(u32, f64), DecryptionError> {
+        match ct {
+            Ciphertext::Encrypted(lwe_ct, plaintext_modulus, _) => {
+                let plaintext_modulus = *plaintext_modulus;
+
+                let lwe_secret = client_key.decryption_key(lwe_ct.lwe_size().to_lwe_dimension());
+
+                let decrypted = decrypt_lwe_ciphertext(&lwe_secret, lwe_ct);
+
+                Ok(decode_with_distance(
+                    decrypted.0,
+                    plaintext_modulus,
+                    lwe_ct.ciphertext_modulus(),
+                ))
             }
+            Ciphertext::Trivial(b) => Ok((*b as u32, 0.)),
+            Ciphertext::Placeholder => Err(DecryptionError::PlaceholderCiphertext),
         }
     }
 
-    pub fn create_server_key(&mut self, client_key: &ClientKey) -> ServerKey {
+    /// Sizes the buffers of this engine for the bootstraps of `server_key` ahead of the first
+    /// evaluation, for a predictable latency of every gate.
+    ///
+    /// [`GadgetEngine::evaluate_gate_into`] and [`GadgetEngine::bootstrap_assign`] then do not
+    /// allocate, provided that:
+    /// - the bootstrapping key is classic, multi-bit and NTT bootstraps allocating their own
+    ///   buffers,
+    /// - the lookup table of the encoding is already cached, see [`ServerKey::lookup_table`],
+    /// - the statistics of `server_key` are disabled,
+    /// - the output of [`GadgetEngine::evaluate_gate_into`] already holds a ciphertext under
+    ///   `server_key`.
+    pub fn preallocate<Scalar: GadgetScalar>(&mut self, server_key: &ServerKey<Scalar>) {
+        self.bootstrapper.context.preallocate(server_key);
+    }
+
+    /// Runs `func` on this engine with the buffers of `context` in place of its own, which are
+    /// left untouched.
+    pub fn with_context<R>(
+        &mut self,
+        context: &mut EvaluationContext,
+        func: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        std::mem::swap(&mut self.bootstrapper.context, context);
+        let output = func(self);
+        std::mem::swap(&mut self.bootstrapper.context, context);
+        output
+    }
+
+    /// The bootstrapper holding the buffers of the bootstraps of this engine.
+    pub(crate) fn bootstrapper(&mut self) -> &mut Bootstrapper {
+        &mut self.bootstrapper
+    }
+
+    pub fn create_server_key<Scalar: GadgetScalar>(
+        &mut self,
+        client_key: &ClientKey<Scalar>,
+    ) -> ServerKey<Scalar> {
         self.bootstrapper.new_server_key(client_key)
     }
 
-    pub fn create_client_key(&mut self, parameters: &GadgetParameters) -> ClientKey {
+    pub fn create_compressed_server_key<Scalar: GadgetScalar>(
+        &mut self,
+        client_key: &ClientKey<Scalar>,
+    ) -> CompressedServerKey<Scalar> {
+        self.bootstrapper.new_compressed_server_key(client_key)
+    }
+
+    /// Fills `output` with uniformly random values on the native torus.
+    pub(crate) fn fill_with_random_uniform<Scalar: GadgetScalar>(&mut self, output: &mut [Scalar]) {
+        self.encryption_generator
+            .fill_slice_with_random_mask(output);
+    }
+
+    /// Samples a Gaussian noise value on the native torus.
+    pub(crate) fn random_noise<Scalar: GadgetScalar>(&mut self, std: StandardDev) -> Scalar {
+        self.encryption_generator.random_noise(std)
+    }
+
+    /// Fills `output` with Gaussian noise values on the native torus.
+    pub(crate) fn fill_with_random_noise<Scalar: GadgetScalar>(
+        &mut self,
+        output: &mut [Scalar],
+        std: StandardDev,
+    ) {
+        self.encryption_generator
+            .fill_slice_with_random_noise(output, std);
+    }
+
+    /// Generates a keyswitching key from `input_key` to `output_key`, e.g. to cast ciphertexts
+    /// between the gadget keys and the keys of another module.
+    pub(crate) fn create_keyswitch_key<Scalar: GadgetScalar>(
+        &mut self,
+        input_key: &LweSecretKey<&[Scalar]>,
+        output_key: &LweSecretKey<&[Scalar]>,
+        decomp_base_log: DecompositionBaseLog,
+        decomp_level_count: DecompositionLevelCount,
+        noise_parameters: StandardDev,
+        ciphertext_modulus: CiphertextModulus<Scalar>,
+    ) -> LweKeyswitchKeyOwned<Scalar> {
+        par_allocate_and_generate_new_lwe_keyswitch_key(
+            input_key,
+            output_key,
+            decomp_base_log,
+            decomp_level_count,
+            noise_parameters,
+            ciphertext_modulus,
+            &mut self.encryption_generator,
+        )
+    }
+
+    /// Generates the packing keyswitching key from the LWE key of `client_key` to its GLWE key,
+    /// with the noise of the bootstrapping key.
+    pub(crate) fn create_packing_keyswitch_key<Scalar: GadgetScalar>(
+        &mut self,
+        client_key: &ClientKey<Scalar>,
+        parameters: &GadgetPackingParameters,
+    ) -> LwePackingKeyswitchKeyOwned<Scalar> {
+        let (input_lwe_secret_key, _) = client_key.encryption_key();
+        allocate_and_generate_new_lwe_packing_keyswitch_key(
+            &input_lwe_secret_key,
+            &client_key.glwe_secret_key,
+            parameters.pks_base_log,
+            parameters.pks_level,
+            client_key.parameters.glwe_modular_std_dev,
+            client_key.ciphertext_modulus(),
+            &mut self.encryption_generator,
+        )
+    }
+
+    /// Generates the private functional packing keyswitching keys of the circuit bootstrapping,
+    /// from the big LWE key of `client_key` to its GLWE key, see
+    /// [`WopbsKey`](super::wopbs::WopbsKey).
+    pub(crate) fn create_circuit_bootstrap_pfpksk_list<Scalar: GadgetScalar>(
+        &mut self,
+        client_key: &ClientKey<Scalar>,
+        parameters: &GadgetWopbsParameters,
+    ) -> LwePrivateFunctionalPackingKeyswitchKeyListOwned<Scalar> {
+        let big_lwe_secret_key = client_key.glwe_secret_key.as_lwe_secret_key();
+
+        par_allocate_and_generate_new_circuit_bootstrap_lwe_pfpksk_list(
+            &big_lwe_secret_key,
+            &client_key.glwe_secret_key,
+            parameters.pfks_base_log,
+            parameters.pfks_level,
+            parameters.pfks_modular_std_dev,
+            client_key.ciphertext_modulus(),
+            &mut self.encryption_generator,
+        )
+    }
+
+    pub fn create_client_key<Scalar: GadgetScalar>(
+        &mut self,
+        parameters: &GadgetParameters,
+    ) -> ClientKey<Scalar> {
+        self.try_create_client_key(parameters)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Same as [`GadgetEngine::create_client_key`], returning an error instead of panicking
+    /// when `parameters` are not supported by keys over `Scalar`.
+    pub fn try_create_client_key<Scalar: GadgetScalar>(
+        &mut self,
+        parameters: &GadgetParameters,
+    ) -> Result<ClientKey<Scalar>, GadgetError> {
+        parameters.check_key_parameters::<Scalar>()?;
+        let _span = span!(
+            "keygen",
+            key = "client",
+            lwe_dimension = parameters.lwe_dimension.0,
+            glwe_dimension = parameters.glwe_dimension.0,
+            polynomial_size = parameters.polynomial_size.0,
+        );
+
         let lwe_secret_key = allocate_and_generate_new_binary_lwe_secret_key(
             parameters.lwe_dimension,
             &mut self.secret_generator,
@@ -344,82 +1358,933 @@ impl GadgetEngine {
             &mut self.secret_generator,
         );
 
-        ClientKey {
+        Ok(ClientKey {
             lwe_secret_key,
             glwe_secret_key,
             parameters: parameters.clone(),
-        }
+        })
+    }
+
+    pub fn bootstrap<Scalar: GadgetScalar>(
+        &mut self,
+        ct: Ciphertext<Scalar>,
+        server_key: &ServerKey<Scalar>,
+        encoding: &Encoding,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        let mut ct = ct;
+        self.bootstrap_assign(&mut ct, server_key, encoding)?;
+        Ok(ct)
     }
 
-    pub fn bootstrap(
+    /// Bootstraps `ct` in place, reusing its LWE ciphertext for the output.
+    pub fn bootstrap_assign<Scalar: GadgetScalar>(
         &mut self,
-        ct: Ciphertext,
-        server_key: &ServerKey,
+        ct: &mut Ciphertext<Scalar>,
+        server_key: &ServerKey<Scalar>,
         encoding: &Encoding,
-    ) -> Result<Ciphertext, Box<dyn Error>> {
+    ) -> Result<(), GadgetError> {
+        check_encoding(encoding)?;
+        check_bootstrappable(encoding)?;
+        server_key.parameters.check_plaintext_modulus(encoding.p)?;
+
         match ct {
-            Ciphertext::Encrypted(lwe_ct) => {
+            Ciphertext::Encrypted(lwe_ct, plaintext_modulus, noise_level) => {
+                if *plaintext_modulus != encoding.p {
+                    return Err(GadgetError::PlaintextModulusMismatch {
+                        pin: 0,
+                        expected: encoding.p,
+                        actual: *plaintext_modulus,
+                    });
+                }
+
+                // a trivial encryption is evaluated in the clear and stays trivial
+                if let Some(message) = trivial_lwe_message(lwe_ct, encoding.p) {
+                    let output = if encoding.output_encodings_0.contains(&message) {
+                        encoding.new_0
+                    } else {
+                        encoding.new_1
+                    };
+                    let ciphertext_modulus = lwe_ct.ciphertext_modulus();
+                    *lwe_ct.get_mut_body().data =
+                        encode_coefficient(output, encoding.new_p, ciphertext_modulus);
+                    *plaintext_modulus = encoding.new_p;
+                    *noise_level = NoiseLevel::ZERO;
+                    return Ok(());
+                }
+
+                let start = Instant::now();
+                let lookup_table = server_key.lookup_table(encoding);
                 self.bootstrapper
-                    .bootstrap_keyswitch(lwe_ct, &server_key, encoding)
+                    .bootstrap_keyswitch_assign(lwe_ct, server_key, &lookup_table);
+                *plaintext_modulus = encoding.new_p;
+                *noise_level = NoiseLevel::NOMINAL;
+                server_key
+                    .stats
+                    .record_bootstrap(std::slice::from_ref(encoding), start.elapsed());
+                Ok(())
             }
-            Ciphertext::Trivial(c) => Ok(Ciphertext::Trivial(c)),
-            _ => {
-                panic!("Ciphertext placeholder reached in gadget engine!")
+            Ciphertext::Trivial(_) => Ok(()),
+            Ciphertext::Placeholder => Err(GadgetError::PlaceholderCiphertext),
+        }
+    }
+
+    pub fn bootstrap_with_modulus_switch<Scalar: GadgetScalar>(
+        &mut self,
+        ct: Ciphertext<Scalar>,
+        server_key: &ServerKey<Scalar>,
+        encoding: &Encoding,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        if encoding.new_p == 0
+            || encoding.new_0 >= encoding.new_p
+            || encoding.new_1 >= encoding.new_p
+        {
+            return Err(GadgetError::InvalidEncoding(format!(
+                "output messages {} and {} do not fit in new_p = {}",
+                encoding.new_0, encoding.new_1, encoding.new_p
+            )));
+        }
+
+        // The lookup table already maps to the new_p space
+        self.bootstrap(ct, server_key, encoding)
+    }
+
+    pub fn evaluate_gate<Scalar: GadgetScalar>(
+        &mut self,
+        server_key: &ServerKey<Scalar>,
+        encoding: &Encoding,
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        if let Some(output) = fold_trivial_inputs(server_key, encoding, &input_ciphertexts) {
+            return Ok(output);
+        }
+
+        let (sum_ct, noise_level) =
+            Self::linear_combination(server_key, encoding, &input_ciphertexts)?;
+
+        self.bootstrap(
+            Ciphertext::Encrypted(sum_ct, encoding.p, noise_level),
+            server_key,
+            encoding,
+        )
+    }
+
+    /// Evaluates the gate described by `encoding` on borrowed inputs, e.g. signals of a circuit
+    /// feeding several gates, which spares cloning them.
+    pub fn evaluate_gate_ref<Scalar: GadgetScalar>(
+        &mut self,
+        server_key: &ServerKey<Scalar>,
+        encoding: &Encoding,
+        input_ciphertexts: &[&Ciphertext<Scalar>],
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        if let Some(output) = fold_trivial_inputs(server_key, encoding, input_ciphertexts) {
+            return Ok(output);
+        }
+
+        let (sum_ct, noise_level) =
+            Self::linear_combination(server_key, encoding, input_ciphertexts)?;
+
+        self.bootstrap(
+            Ciphertext::Encrypted(sum_ct, encoding.p, noise_level),
+            server_key,
+            encoding,
+        )
+    }
+
+    /// Evaluates the gate described by `encoding` into `output`, reusing the LWE ciphertext of
+    /// `output` if it is encrypted under `server_key`. `output` is left as a placeholder on error.
+    pub fn evaluate_gate_into<Scalar: GadgetScalar>(
+        &mut self,
+        server_key: &ServerKey<Scalar>,
+        encoding: &Encoding,
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+        output: &mut Ciphertext<Scalar>,
+    ) -> Result<(), GadgetError> {
+        if let Some(folded) = fold_trivial_inputs(server_key, encoding, &input_ciphertexts) {
+            *output = folded;
+            return Ok(());
+        }
+
+        let lwe_size = server_key.ciphertext_lwe_size();
+        let mut sum_ct = match std::mem::replace(output, Ciphertext::Placeholder) {
+            Ciphertext::Encrypted(mut ct, _, _)
+                if ct.lwe_size() == lwe_size
+                    && ct.ciphertext_modulus() == server_key.ciphertext_modulus() =>
+            {
+                ct.as_mut().fill(Scalar::ZERO);
+                ct
             }
+            _ => LweCiphertext::new(Scalar::ZERO, lwe_size, server_key.ciphertext_modulus()),
+        };
+
+        let noise_level =
+            Self::linear_combination_into(server_key, encoding, &input_ciphertexts, &mut sum_ct)?;
+
+        *output = Ciphertext::Encrypted(sum_ct, encoding.p, noise_level);
+        let result = self.bootstrap_assign(output, server_key, encoding);
+        if result.is_err() {
+            *output = Ciphertext::Placeholder;
         }
+        result
     }
 
-    pub fn evaluate_gate(
+    pub fn smart_evaluate_gate<Scalar: GadgetScalar>(
         &mut self,
-        server_key: &ServerKey,
+        server_key: &ServerKey<Scalar>,
         encoding: &Encoding,
-        input_ciphertexts: Vec<Ciphertext>,
-    ) -> Result<Ciphertext, Box<dyn Error>> {
-        assert_eq!(encoding.pin_count, input_ciphertexts.len());
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        check_encoding(encoding)?;
+        let max_noise_level = server_key.max_noise_level(encoding.p);
+
+        let mut input_ciphertexts = input_ciphertexts;
+        if linear_combination_noise_level(encoding, &input_ciphertexts) > max_noise_level {
+            // Bring every input back to the nominal noise level before combining them. The
+            // inputs may hold any message modulo p, e.g. the outputs of a linear encoding whose
+            // new_0 and new_1 are not 0 and 1, which the identity lookup table keeps as is.
+            for ct in input_ciphertexts.iter_mut() {
+                if let Ciphertext::Encrypted(_, plaintext_modulus, noise_level) = ct {
+                    if *noise_level > NoiseLevel::NOMINAL {
+                        let p = *plaintext_modulus;
+                        let identity = MultiOutputEncoding::from_function(p, p, |message| message)?;
+                        let refreshed =
+                            self.evaluate_lut(server_key, &identity, std::slice::from_ref(ct))?;
+                        *ct = refreshed;
+                    }
+                }
+            }
+        }
+
+        let (sum_ct, noise_level) =
+            Self::linear_combination(server_key, encoding, &input_ciphertexts)?;
+
+        if encoding.is_linear() && noise_level <= max_noise_level {
+            return Ok(Ciphertext::Encrypted(sum_ct, encoding.new_p, noise_level));
+        }
+
+        self.bootstrap(
+            Ciphertext::Encrypted(sum_ct, encoding.p, noise_level),
+            server_key,
+            encoding,
+        )
+    }
+
+    pub fn evaluate_gate_multi<Scalar: GadgetScalar>(
+        &mut self,
+        server_key: &ServerKey<Scalar>,
+        encodings: &[Encoding],
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+    ) -> Result<Vec<Ciphertext<Scalar>>, GadgetError> {
+        let first_encoding = check_shared_linear_combination(server_key, encodings)?;
+        let (sum_ct, _) = Self::linear_combination(server_key, first_encoding, &input_ciphertexts)?;
+
+        let start = Instant::now();
+        let outputs = self
+            .bootstrapper
+            .multi_bootstrap_keyswitch(sum_ct, server_key, encodings);
+        server_key
+            .stats
+            .record_bootstrap(encodings, start.elapsed());
+        outputs
+    }
+
+    /// Evaluates `encodings`, sharing the same linear combination of `input_ciphertexts`, with
+    /// one blind rotation of their interleaved test polynomials, see
+    /// [`Bootstrapper::many_lut_bootstrap_keyswitch`].
+    ///
+    /// Each window of the test polynomial is split in one sub-window per encoding, the
+    /// combination must stay within a sub-window: its noise is bounded as for the plaintext
+    /// modulus `p * encodings.len()`, which must be supported by the parameters.
+    pub fn evaluate_gate_outputs<Scalar: GadgetScalar>(
+        &mut self,
+        server_key: &ServerKey<Scalar>,
+        encodings: &[Encoding],
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+    ) -> Result<Vec<Ciphertext<Scalar>>, GadgetError> {
+        let first_encoding = check_shared_linear_combination(server_key, encodings)?;
+        server_key
+            .parameters
+            .check_plaintext_modulus(first_encoding.p * encodings.len() as u32)?;
+        let (sum_ct, _) = Self::linear_combination(server_key, first_encoding, &input_ciphertexts)?;
+
+        let start = Instant::now();
+        let lookup_table = server_key.generate_many_lookup_table(encodings);
+        let outputs = self.bootstrapper.many_lut_bootstrap_keyswitch(
+            sum_ct,
+            server_key,
+            &lookup_table,
+            first_encoding.p,
+            first_encoding.new_p,
+            encodings.len(),
+        );
+        server_key
+            .stats
+            .record_bootstrap(encodings, start.elapsed());
+        Ok(outputs)
+    }
+
+    /// Evaluates the lookup table described by `encoding` on borrowed inputs, the output
+    /// encrypting a message modulo `encoding.new_p`.
+    pub fn evaluate_lut<Scalar: GadgetScalar>(
+        &mut self,
+        server_key: &ServerKey<Scalar>,
+        encoding: &MultiOutputEncoding,
+        input_ciphertexts: &[impl Borrow<Ciphertext<Scalar>>],
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        encoding.check()?;
+        server_key.parameters.check_plaintext_modulus(encoding.p)?;
+        if encoding.pin_count() != input_ciphertexts.len() {
+            return Err(GadgetError::PinCountMismatch {
+                expected: encoding.pin_count(),
+                actual: input_ciphertexts.len(),
+            });
+        }
 
         let mut sum_ct = LweCiphertext::new(
-            0u32,
-            server_key
-                .bootstrapping_key
-                .input_lwe_dimension()
-                .to_lwe_size(),
-            CiphertextModulus::new_native(),
+            Scalar::ZERO,
+            server_key.ciphertext_lwe_size(),
+            server_key.ciphertext_modulus(),
+        );
+        // mappings are stored in reverse pin order
+        Self::weighted_sum_into(
+            server_key,
+            encoding.input_mappings_1.iter().rev().copied(),
+            encoding.p,
+            input_ciphertexts,
+            &mut sum_ct,
+        )?;
+
+        // a trivial encryption is evaluated in the clear and stays trivial
+        if let Some(message) = trivial_lwe_message(&sum_ct, encoding.p) {
+            *sum_ct.get_mut_body().data = encode_coefficient(
+                encoding.output(message),
+                encoding.new_p,
+                server_key.ciphertext_modulus(),
+            );
+            return Ok(Ciphertext::Encrypted(
+                sum_ct,
+                encoding.new_p,
+                NoiseLevel::ZERO,
+            ));
+        }
+
+        let lookup_table = server_key.lookup_table_from_accumulator(
+            &encoding.create_accumulator(),
+            encoding.p,
+            encoding.new_p,
+        );
+        self.bootstrapper
+            .bootstrap_keyswitch_assign(&mut sum_ct, server_key, &lookup_table);
+        server_key.stats.record_lut_bootstrap();
+
+        Ok(Ciphertext::Encrypted(
+            sum_ct,
+            encoding.new_p,
+            NoiseLevel::NOMINAL,
+        ))
+    }
+
+    /// Computes `constant + sum(weights[i] * input_ciphertexts[i])` modulo `p` without
+    /// bootstrapping, see [`ServerKey::linear_combination`].
+    pub fn weighted_sum<Scalar: GadgetScalar>(
+        &mut self,
+        server_key: &ServerKey<Scalar>,
+        input_ciphertexts: &[Ciphertext<Scalar>],
+        weights: &[u32],
+        constant: u32,
+        p: u32,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        if p == 0 {
+            return Err(GadgetError::UnsupportedPlaintextModulus(p));
+        }
+        if weights.len() != input_ciphertexts.len() {
+            return Err(GadgetError::PinCountMismatch {
+                expected: weights.len(),
+                actual: input_ciphertexts.len(),
+            });
+        }
+
+        let mut sum_ct = LweCiphertext::new(
+            Scalar::ZERO,
+            server_key.ciphertext_lwe_size(),
+            server_key.ciphertext_modulus(),
+        );
+        let noise_level = Self::weighted_sum_into(
+            server_key,
+            weights.iter().map(|weight| weight % p),
+            p,
+            input_ciphertexts,
+            &mut sum_ct,
+        )?;
+        lwe_ciphertext_plaintext_add_assign(
+            &mut sum_ct,
+            Plaintext(encode(constant % p, p, server_key.ciphertext_modulus())),
         );
 
+        Ok(Ciphertext::Encrypted(sum_ct, p, noise_level))
+    }
+
+    /// Evaluates the gate described by `encoding` on each set of inputs of `batch`.
+    ///
+    /// The bootstraps go through [`Bootstrapper::bootstrap_many`]. If `pipelined` is set, they
+    /// go through the pipeline of [`ServerKey::evaluate_gate_pipelined`] instead, with the
+    /// default [`PipelineConfig`]. Sets of inputs which fold to a constant, or only hold trivial
+    /// ciphertexts, are evaluated by [`GadgetEngine::evaluate_gate`] and keep its noise level.
+    pub fn evaluate_gate_many<Scalar: GadgetScalar>(
+        &mut self,
+        server_key: &ServerKey<Scalar>,
+        encoding: &Encoding,
+        batch: Vec<Vec<Ciphertext<Scalar>>>,
+        pipelined: bool,
+    ) -> Result<Vec<Ciphertext<Scalar>>, GadgetError> {
+        self.evaluate_gate_batch(
+            server_key,
+            encoding,
+            batch,
+            pipelined.then(PipelineConfig::default),
+        )
+    }
+
+    /// Same as [`GadgetEngine::evaluate_gate_many`], the bootstraps going through the pipeline of
+    /// `pipeline` if any.
+    pub(crate) fn evaluate_gate_batch<Scalar: GadgetScalar>(
+        &mut self,
+        server_key: &ServerKey<Scalar>,
+        encoding: &Encoding,
+        batch: Vec<Vec<Ciphertext<Scalar>>>,
+        pipeline: Option<PipelineConfig>,
+    ) -> Result<Vec<Ciphertext<Scalar>>, GadgetError> {
+        check_encoding(encoding)?;
+        check_bootstrappable(encoding)?;
+
+        // `None` marks the outputs of the sets of inputs left to bootstrap
+        let mut outputs = Vec::with_capacity(batch.len());
+        let mut encrypted_batch = Vec::with_capacity(batch.len());
+        for input_ciphertexts in batch {
+            let is_trivial = fold_trivial_inputs(server_key, encoding, &input_ciphertexts)
+                .is_some()
+                || !input_ciphertexts
+                    .iter()
+                    .any(|ct| matches!(ct, Ciphertext::Encrypted(..)));
+            if is_trivial {
+                outputs.push(Some(self.evaluate_gate(
+                    server_key,
+                    encoding,
+                    input_ciphertexts,
+                )?));
+            } else {
+                outputs.push(None);
+                encrypted_batch.push(input_ciphertexts);
+            }
+        }
+
+        let mut bootstrapped = self
+            .bootstrap_batch(server_key, encoding, encrypted_batch, pipeline)?
+            .into_iter();
+        Ok(outputs
+            .into_iter()
+            .map(|output| output.or_else(|| bootstrapped.next()))
+            .collect::<Option<_>>()
+            .expect("one bootstrapped output per set of encrypted inputs"))
+    }
+
+    /// Bootstraps the linear combinations of each set of inputs of `batch`, see
+    /// [`GadgetEngine::evaluate_gate_many`].
+    fn bootstrap_batch<Scalar: GadgetScalar>(
+        &mut self,
+        server_key: &ServerKey<Scalar>,
+        encoding: &Encoding,
+        batch: Vec<Vec<Ciphertext<Scalar>>>,
+        pipeline: Option<PipelineConfig>,
+    ) -> Result<Vec<Ciphertext<Scalar>>, GadgetError> {
+        if let Some(config) = pipeline {
+            return server_key.bootstrap_pipelined(batch, encoding, config);
+        }
+
+        self.bootstrapper.bootstrap_stream(
+            batch.into_iter().map(|input_ciphertexts| {
+                Self::linear_combination(server_key, encoding, &input_ciphertexts)
+                    .map(|(sum_ct, _)| sum_ct)
+            }),
+            server_key,
+            encoding,
+        )
+    }
+
+    /// Bootstraps each ciphertext of `list` with the lookup table of `encoding`, reading them in
+    /// place, into a list of the same length.
+    pub fn bootstrap_list<Scalar: GadgetScalar>(
+        &mut self,
+        server_key: &ServerKey<Scalar>,
+        encoding: &Encoding,
+        list: &CiphertextList<Scalar>,
+    ) -> Result<CiphertextList<Scalar>, GadgetError> {
+        check_encoding(encoding)?;
+        check_bootstrappable(encoding)?;
+        if list.plaintext_modulus != encoding.p {
+            return Err(GadgetError::PlaintextModulusMismatch {
+                pin: 0,
+                expected: encoding.p,
+                actual: list.plaintext_modulus,
+            });
+        }
+
+        let outputs =
+            self.bootstrapper
+                .bootstrap_stream(list.views().map(Ok), server_key, encoding)?;
+        Ok(bootstrapped_list(server_key, &outputs, encoding.new_p))
+    }
+
+    /// Evaluates the gate described by `encoding` on the rows of `inputs`, the `i`-th list
+    /// holding the ciphertexts of the `i`-th pin, into a list of one output per row. The input
+    /// ciphertexts are read in place.
+    pub fn evaluate_gate_list<Scalar: GadgetScalar>(
+        &mut self,
+        server_key: &ServerKey<Scalar>,
+        encoding: &Encoding,
+        inputs: &[&CiphertextList<Scalar>],
+    ) -> Result<CiphertextList<Scalar>, GadgetError> {
+        check_encoding(encoding)?;
+        check_bootstrappable(encoding)?;
+        if encoding.pin_count != inputs.len() {
+            return Err(GadgetError::PinCountMismatch {
+                expected: encoding.pin_count,
+                actual: inputs.len(),
+            });
+        }
+        let row_count = inputs.first().map_or(0, |list| list.len());
+        for (pin, list) in inputs.iter().enumerate() {
+            if list.plaintext_modulus != encoding.p {
+                return Err(GadgetError::PlaintextModulusMismatch {
+                    pin,
+                    expected: encoding.p,
+                    actual: list.plaintext_modulus,
+                });
+            }
+            if list.len() != row_count {
+                return Err(GadgetError::ListLengthMismatch {
+                    pin,
+                    expected: row_count,
+                    actual: list.len(),
+                });
+            }
+        }
+
+        // pins are weighted in reverse order of the input mappings, see linear_combination_into
+        let weights: Vec<_> = encoding
+            .input_mappings_1
+            .iter()
+            .rev()
+            .map(|weight| Cleartext(Scalar::cast_from(*weight)))
+            .collect();
+        let sums = (0..row_count).map(|row| {
+            let mut sum_ct = LweCiphertext::new(
+                Scalar::ZERO,
+                server_key.ciphertext_lwe_size(),
+                server_key.ciphertext_modulus(),
+            );
+            for (list, weight) in inputs.iter().zip(weights.iter()) {
+                lwe_ciphertext_cleartext_mul_add_assign(
+                    &mut sum_ct,
+                    &list.lwe_list.get(row),
+                    *weight,
+                );
+            }
+            server_key.stats.record_linear_op();
+            Ok(sum_ct)
+        });
+
+        let outputs = self
+            .bootstrapper
+            .bootstrap_stream(sums, server_key, encoding)?;
+        Ok(bootstrapped_list(server_key, &outputs, encoding.new_p))
+    }
+
+    /// Computes the sum of `input_ciphertexts` weighted by the input mappings of `encoding`,
+    /// together with its noise level.
+    pub(crate) fn linear_combination<Scalar: GadgetScalar>(
+        server_key: &ServerKey<Scalar>,
+        encoding: &Encoding,
+        input_ciphertexts: &[impl Borrow<Ciphertext<Scalar>>],
+    ) -> Result<(LweCiphertextOwned<Scalar>, NoiseLevel), GadgetError> {
+        let mut sum_ct = LweCiphertext::new(
+            Scalar::ZERO,
+            server_key.ciphertext_lwe_size(),
+            server_key.ciphertext_modulus(),
+        );
+        let noise_level =
+            Self::linear_combination_into(server_key, encoding, input_ciphertexts, &mut sum_ct)?;
+
+        Ok((sum_ct, noise_level))
+    }
+
+    /// Adds the sum of `input_ciphertexts` weighted by the input mappings of `encoding` to
+    /// `sum_ct`, returning the noise level of the sum. The inputs are only borrowed, each pin
+    /// being multiplied and added to the sum in a single pass.
+    fn linear_combination_into<Scalar: GadgetScalar>(
+        server_key: &ServerKey<Scalar>,
+        encoding: &Encoding,
+        input_ciphertexts: &[impl Borrow<Ciphertext<Scalar>>],
+        sum_ct: &mut LweCiphertextOwned<Scalar>,
+    ) -> Result<NoiseLevel, GadgetError> {
+        check_encoding(encoding)?;
+        if encoding.pin_count != input_ciphertexts.len() {
+            return Err(GadgetError::PinCountMismatch {
+                expected: encoding.pin_count,
+                actual: input_ciphertexts.len(),
+            });
+        }
+
+        let _span = span!(
+            "linear_combination",
+            encoding = crate::gadget::telemetry::encoding_hash(encoding),
+            pin_count = encoding.pin_count,
+            lwe_dimension = sum_ct.lwe_size().to_lwe_dimension().0,
+        );
+        check_input_moduli(
+            encoding.p,
+            input_ciphertexts
+                .iter()
+                .map(|ct| ct.borrow().plaintext_modulus()),
+        )?;
+
         // Input pins p0, p1, ..., pn starting with LSB is mapped to a truth table row
         // as pn, ..., p1, p0 (i.e. starting with MSB). Thus, input_mappings_1 stores
         // pin mapping in reverse order of corresponding input ciphertexts
-        izip!(
-            encoding.input_mappings_1.iter().rev(),
-            input_ciphertexts.into_iter()
+        Self::weighted_sum_into(
+            server_key,
+            encoding.input_mappings_1.iter().rev().copied(),
+            encoding.p,
+            input_ciphertexts,
+            sum_ct,
         )
-        .for_each(|(scalar_val, pin_ct)| {
-            match pin_ct {
-                Ciphertext::Encrypted(mut ct) => {
+    }
+
+    /// Adds the sum of `input_ciphertexts`, encrypting messages modulo `p`, weighted by `weights`
+    /// to `sum_ct`, returning the noise level of the sum.
+    fn weighted_sum_into<Scalar: GadgetScalar>(
+        server_key: &ServerKey<Scalar>,
+        weights: impl Iterator<Item = u32> + Clone,
+        p: u32,
+        input_ciphertexts: &[impl Borrow<Ciphertext<Scalar>>],
+        sum_ct: &mut LweCiphertextOwned<Scalar>,
+    ) -> Result<NoiseLevel, GadgetError> {
+        let noise_level = weighted_noise_level(weights.clone(), input_ciphertexts);
+        server_key.stats.record_linear_op();
+
+        for (pin, (weight, pin_ct)) in izip!(weights, input_ciphertexts.iter()).enumerate() {
+            match pin_ct.borrow() {
+                Ciphertext::Encrypted(ct, plaintext_modulus, _) => {
+                    if *plaintext_modulus != p {
+                        return Err(GadgetError::PlaintextModulusMismatch {
+                            pin,
+                            expected: p,
+                            actual: *plaintext_modulus,
+                        });
+                    }
+
                     // FIXME: For now assume each input ciphertext is in canonical form (i.e. either
                     // encrypts 1 or 0)
-                    lwe_ciphertext_cleartext_mul_assign(&mut ct, Cleartext(*scalar_val));
-
                     // add casted input ciphertext to total sum
-                    lwe_ciphertext_add_assign(&mut sum_ct, &ct);
+                    lwe_ciphertext_cleartext_mul_add_assign(
+                        sum_ct,
+                        ct,
+                        Cleartext(Scalar::cast_from(weight)),
+                    );
                 }
                 Ciphertext::Trivial(bool_constant) => {
                     // 1
-                    if bool_constant {
+                    if *bool_constant {
                         // cast true to expected encoding and add to total sum
                         let plaintext_1 =
-                            Plaintext((((*scalar_val as u64) << 32) / encoding.p as u64) as u32);
-                        lwe_ciphertext_plaintext_add_assign(&mut sum_ct, plaintext_1);
+                            Plaintext(encode(weight, p, server_key.ciphertext_modulus()));
+                        lwe_ciphertext_plaintext_add_assign(sum_ct, plaintext_1);
                     }
                 }
-                _ => {
-                    panic!("Ciphertext placeholder reached in gadget engine!")
-                }
+                Ciphertext::Placeholder => return Err(GadgetError::PlaceholderCiphertext),
             }
-        });
+        }
+
+        Ok(noise_level)
+    }
+}
+
+/// Gathers the bootstrapped `outputs`, encrypted modulo `new_p`, in a list.
+fn bootstrapped_list<Scalar: GadgetScalar>(
+    server_key: &ServerKey<Scalar>,
+    outputs: &[Ciphertext<Scalar>],
+    new_p: u32,
+) -> CiphertextList<Scalar> {
+    CiphertextList::from_lwe_ciphertexts(
+        outputs.iter().map(|ct| match ct {
+            Ciphertext::Encrypted(lwe, _, _) => lwe,
+            _ => unreachable!("bootstrapping outputs an encrypted ciphertext"),
+        }),
+        server_key.ciphertext_lwe_size(),
+        server_key.ciphertext_modulus(),
+        new_p,
+        NoiseLevel::NOMINAL,
+    )
+}
+
+/// Noise level of the sum of `input_ciphertexts` weighted by the input mappings of `encoding`.
+fn linear_combination_noise_level<Scalar: GadgetScalar>(
+    encoding: &Encoding,
+    input_ciphertexts: &[impl Borrow<Ciphertext<Scalar>>],
+) -> NoiseLevel {
+    weighted_noise_level(
+        encoding.input_mappings_1.iter().rev().copied(),
+        input_ciphertexts,
+    )
+}
+
+/// Noise level of the sum of `input_ciphertexts` weighted by `weights`.
+fn weighted_noise_level<Scalar: GadgetScalar>(
+    weights: impl Iterator<Item = u32>,
+    input_ciphertexts: &[impl Borrow<Ciphertext<Scalar>>],
+) -> NoiseLevel {
+    let level = izip!(weights, input_ciphertexts.iter())
+        .map(|(weight, pin_ct)| {
+            let weight = weight as usize;
+            weight * weight * pin_ct.borrow().noise_level().0
+        })
+        .sum();
+
+    NoiseLevel(level)
+}
+
+/// Returns the non zero coefficients, as (degree, coefficient) pairs, of `(1 - X) * t(X)` modulo
+/// `X^N + 1` where `t(X)` is the unscaled test polynomial of `encoding`.
+fn multi_value_factor<Scalar: GadgetScalar>(
+    encoding: &Encoding,
+    polynomial_size: PolynomialSize,
+) -> Vec<(usize, Scalar)> {
+    let messages: Vec<Scalar> = lookup_table_messages(encoding, polynomial_size)
+        .into_iter()
+        .map(Scalar::cast_from)
+        .collect();
+    let n = messages.len();
+
+    // X * X^{N-1} = -1, hence the wrapped last coefficient is added to the constant term
+    let constant_term = messages[0].wrapping_add(messages[n - 1]);
+    let mut factor = vec![(0, constant_term)];
+    factor
+        .extend((1..n).map(|degree| (degree, messages[degree].wrapping_sub(messages[degree - 1]))));
+    factor.retain(|(_, coefficient)| *coefficient != Scalar::ZERO);
+    factor
+}
+
+/// Extracts the coefficient of `degree` of the blind rotated `acc` as an LWE ciphertext,
+/// keyswitched back to the small key in the [`PBSOrder::BootstrapKeyswitch`] order.
+fn extract_keyswitch<Scalar: GadgetScalar>(
+    server_key: &ServerKey<Scalar>,
+    acc: &GlweCiphertextOwned<Scalar>,
+    degree: MonomialDegree,
+) -> LweCiphertextOwned<Scalar> {
+    let ciphertext_modulus = server_key.ciphertext_modulus();
+    let mut output = LweCiphertextOwned::new(
+        Scalar::ZERO,
+        server_key.ciphertext_lwe_size(),
+        ciphertext_modulus,
+    );
+    match server_key.parameters.pbs_order() {
+        PBSOrder::BootstrapKeyswitch => {
+            let mut buffer_lwe_intermediate = LweCiphertextOwned::new(
+                Scalar::ZERO,
+                server_key
+                    .bootstrapping_key
+                    .output_lwe_dimension()
+                    .to_lwe_size(),
+                ciphertext_modulus,
+            );
+            extract_lwe_sample_from_glwe_ciphertext(acc, &mut buffer_lwe_intermediate, degree);
+            keyswitch(server_key, &buffer_lwe_intermediate, &mut output);
+        }
+        PBSOrder::KeyswitchBootstrap => {
+            extract_lwe_sample_from_glwe_ciphertext(acc, &mut output, degree);
+        }
+    }
+    output
+}
+
+/// Computes `output = input * factor` modulo `X^N + 1` for a sparse `factor` given as
+/// (degree, coefficient) pairs.
+fn polynomial_sparse_wrapping_mul<Scalar: GadgetScalar>(
+    output: &mut [Scalar],
+    input: &[Scalar],
+    factor: &[(usize, Scalar)],
+) {
+    let n = input.len();
+    output.fill(Scalar::ZERO);
+
+    for (degree, coefficient) in factor.iter() {
+        for (j, value) in input.iter().enumerate() {
+            let product = value.wrapping_mul(*coefficient);
+            let index = j + degree;
+            if index < n {
+                output[index] = output[index].wrapping_add(product);
+            } else {
+                output[index - n] = output[index - n].wrapping_sub(product);
+            }
+        }
+    }
+}
+
+/// Returns the output of the gate described by `encoding` if its [`Ciphertext::Trivial`]
+/// inputs alone determine it, whatever the other inputs encrypt, e.g. an AND gate with a false
+/// input or a gate without encrypted input. The other trivial inputs are added as plaintexts
+/// by the linear combination of the evaluation.
+///
+/// The output is read from `tt_value`, which is only trusted when the pins set to 0 are mapped
+/// to 0 and the output sets agree with it, see [`Encoding::check_truth_table`]. A canonical
+/// output is returned as a [`Ciphertext::Trivial`], any other as the trivial encryption of
+/// `new_0` or `new_1` modulo `new_p`. Returns `None` if the gate has to be evaluated, including
+/// on inputs the evaluation rejects, so that it reports the error.
+fn fold_trivial_inputs<Scalar: GadgetScalar>(
+    server_key: &ServerKey<Scalar>,
+    encoding: &Encoding,
+    input_ciphertexts: &[impl Borrow<Ciphertext<Scalar>>],
+) -> Option<Ciphertext<Scalar>> {
+    if input_ciphertexts.len() != encoding.pin_count {
+        return None;
+    }
+
+    // bit `i` is set in `known_pins` if pin `i` is trivial, with the value of bit `i` of `known`
+    let (mut known_pins, mut known) = (0u128, 0u128);
+    for (pin, ct) in input_ciphertexts.iter().enumerate() {
+        match ct.borrow() {
+            Ciphertext::Trivial(value) => {
+                known_pins |= 1 << pin;
+                known |= (*value as u128) << pin;
+            }
+            Ciphertext::Encrypted(_, plaintext_modulus, _) if *plaintext_modulus == encoding.p => {}
+            _ => return None,
+        }
+    }
+    if known_pins == 0
+        || encoding
+            .input_mappings_0
+            .iter()
+            .any(|mapping| *mapping != 0)
+        || encoding.check_truth_table().is_err()
+        || check_bootstrappable(encoding).is_err()
+    {
+        return None;
+    }
+
+    let mut outputs = (0..1u128 << encoding.pin_count)
+        .filter(|row| row & known_pins == known)
+        .map(|row| (encoding.tt_value >> row) & 1 == 1);
+    let output = outputs.next()?;
+    if outputs.any(|other| other != output) {
+        return None;
+    }
+
+    if encoding.new_0 == 0 && encoding.new_1 == 1 {
+        return Some(Ciphertext::Trivial(output));
+    }
+    let message = if output {
+        encoding.new_1
+    } else {
+        encoding.new_0
+    };
+    Some(server_key.trivial_encrypt_modular(message, encoding.new_p))
+}
+
+/// Checks that `encodings` can share a blind rotation: they must have the same `p`, `new_p`,
+/// `pin_count` and input mappings. Returns the first encoding.
+fn check_shared_linear_combination<'a, Scalar: GadgetScalar>(
+    server_key: &ServerKey<Scalar>,
+    encodings: &'a [Encoding],
+) -> Result<&'a Encoding, GadgetError> {
+    let first_encoding = encodings.first().ok_or_else(|| {
+        GadgetError::InvalidEncoding("multi-value evaluation needs an encoding".to_string())
+    })?;
+    for encoding in encodings.iter() {
+        check_encoding(encoding)?;
+        check_bootstrappable(encoding)?;
+        server_key.parameters.check_plaintext_modulus(encoding.p)?;
+        if encoding.p != first_encoding.p
+            || encoding.new_p != first_encoding.new_p
+            || encoding.pin_count != first_encoding.pin_count
+            || encoding.input_mappings_1 != first_encoding.input_mappings_1
+        {
+            return Err(GadgetError::InvalidEncoding(
+                "multi-value encodings must share the same linear combination".to_string(),
+            ));
+        }
+    }
+
+    Ok(first_encoding)
+}
+
+/// Checks that `encoding` is well formed.
+pub(crate) fn check_encoding(encoding: &Encoding) -> Result<(), GadgetError> {
+    if encoding.p == 0 {
+        return Err(GadgetError::UnsupportedPlaintextModulus(encoding.p));
+    }
+
+    if encoding.input_mappings_1.len() != encoding.pin_count {
+        return Err(GadgetError::InvalidEncoding(format!(
+            "{} input mappings for {} pins",
+            encoding.input_mappings_1.len(),
+            encoding.pin_count
+        )));
+    }
 
-        // Ok(Ciphertext::Encrypted(sum_ct))
+    Ok(())
+}
 
-        self.bootstrap(Ciphertext::Encrypted(sum_ct), server_key, encoding)
+/// Checks that the encrypted inputs of a gate, whose plaintext moduli are given in pin order,
+/// are encrypted modulo `p`, the plaintext modulus of its encoding. Trivial inputs, without
+/// plaintext modulus, fit any encoding.
+///
+/// Fails with [`GadgetError::EncodingMismatch`] listing every offending pin, so that a gate
+/// mixing ciphertexts of several encodings is reported at once rather than pin by pin.
+pub(crate) fn check_input_moduli(
+    p: u32,
+    input_moduli: impl Iterator<Item = Option<u32>>,
+) -> Result<(), GadgetError> {
+    let pins: Vec<usize> = input_moduli
+        .enumerate()
+        .filter(|(_, plaintext_modulus)| plaintext_modulus.is_some_and(|modulus| modulus != p))
+        .map(|(pin, _)| pin)
+        .collect();
+    if !pins.is_empty() {
+        return Err(GadgetError::EncodingMismatch { expected: p, pins });
     }
+
+    Ok(())
+}
+
+/// Checks that `encoding` can be evaluated by the bootstrapper.
+///
+/// Messages modulo an even `p` keep a padding bit, which the sum of the inputs must not reach:
+/// the negacyclic rotation would negate the output of the bootstrap.
+pub(crate) fn check_bootstrappable(encoding: &Encoding) -> Result<(), GadgetError> {
+    if encoding.p % 2 == 1 {
+        return Ok(());
+    }
+
+    let pin_count = encoding.pin_count;
+    let modulus = torus_modulus(encoding.p);
+    for row in 0..(1usize << pin_count) {
+        // mappings are stored in reverse pin order
+        let sum = (0..pin_count).fold(0u64, |sum, pin| {
+            let mappings = if (row >> pin) & 1 == 1 {
+                &encoding.input_mappings_1
+            } else {
+                &encoding.input_mappings_0
+            };
+            sum + mappings[pin_count - 1 - pin] as u64
+        });
+        if sum % modulus >= encoding.p as u64 {
+            return Err(GadgetError::InvalidEncoding(format!(
+                "the inputs of row {row} sum to {sum}, which sets the padding bit of the \
+                plaintext modulus {}",
+                encoding.p
+            )));
+        }
+    }
+
+    Ok(())
 }
@@ -0,0 +1,331 @@
+//! Errors returned by the gadget module.
+
+use std::fmt::{Display, Formatter};
+
+/// Error returned by the fallible gadget operations.
+///
+/// Some variants only exist with the feature enabling the operation returning them, hence the
+/// enum is not exhaustive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GadgetError {
+    /// The number of input ciphertexts does not match the `pin_count` of the encoding.
+    PinCountMismatch { expected: usize, actual: usize },
+    /// The encoding is malformed, e.g. its input mappings do not cover every pin.
+    InvalidEncoding(String),
+    /// The input ciphertext of `pin` is not encrypted under the plaintext modulus of the encoding.
+    PlaintextModulusMismatch {
+        pin: usize,
+        expected: u32,
+        actual: u32,
+    },
+    /// The input ciphertexts of `pins` of a gate are not encrypted under the plaintext modulus
+    /// of its encoding, e.g. because they are outputs of gates of another encoding.
+    EncodingMismatch { expected: u32, pins: Vec<usize> },
+    /// The plaintext modulus of the encoding cannot be handled by the bootstrapper.
+    UnsupportedPlaintextModulus(u32),
+    /// A [`Ciphertext::Placeholder`](`super::ciphertext::Ciphertext::Placeholder`) was given as
+    /// an operand.
+    PlaceholderCiphertext,
+    /// A netlist could not be parsed, `line` is 1-based.
+    CircuitParse { line: usize, reason: String },
+    /// The circuit is malformed, e.g. a signal is undriven or the gates form a loop.
+    InvalidCircuit(String),
+    /// No ciphertext was provided for the circuit input.
+    MissingCircuitInput(String),
+    /// A ciphertext was bound to a wire that is not an input of the circuit.
+    UnexpectedCircuitInput(String),
+    /// The circuit evaluation was cancelled through its
+    /// [`CancellationToken`](`super::circuit::progress::CancellationToken`).
+    EvaluationCancelled,
+    /// No parameter set meets the requested plaintext modulus, noise and failure probability.
+    UnsatisfiableParameters(String),
+    /// The ciphertext modulus is not a power of two fitting the torus scalar of the keys.
+    UnsupportedCiphertextModulus(u128),
+    /// The grouping factor of the multi-bit bootstrapping key is not 2, 3 or 4, or does not
+    /// divide the LWE dimension.
+    UnsupportedGroupingFactor(usize),
+    /// The operation needs a classic bootstrapping key, e.g. the circuit bootstrapping of
+    /// [`wopbs`](super::wopbs), while the server key holds a multi-bit one.
+    UnsupportedMultiBitKey,
+    /// The operation or the parameters are not supported by the NTT blind rotation, see
+    /// [`PbsBackend::Ntt`](super::parameters::PbsBackend::Ntt).
+    UnsupportedNttBackend(String),
+    /// A standard deviation of the parameters is below the precision of the torus scalar of the
+    /// keys, the noise would be rounded to zero.
+    NoiseBelowTorusPrecision(String),
+    /// The operation needs fresh ciphertexts under the small LWE key, e.g. the circuit
+    /// bootstrapping of [`wopbs`](super::wopbs), while the parameters encrypt under the big one.
+    UnsupportedBigEncryptionKey,
+    /// The server key holds no packing keyswitching key, see
+    /// [`ServerKey::enable_packing`](super::server_key::ServerKey::enable_packing).
+    MissingPackingKey,
+    /// The modulus `2^log_modulus` a ciphertext is switched to before transmission is not
+    /// smaller than its ciphertext modulus, see
+    /// [`Ciphertext::compress_for_transport`](super::ciphertext::Ciphertext::compress_for_transport).
+    UnsupportedTransportModulus(usize),
+    /// No server key is registered under this id in the
+    /// [`ServerKeyStore`](super::key_store::ServerKeyStore).
+    UnknownServerKey(String),
+    /// The server key of `size` bytes does not fit in the memory budget of the
+    /// [`ServerKeyStore`](super::key_store::ServerKeyStore) on its own.
+    ServerKeyOverBudget { size: usize, budget: usize },
+    /// The bit-vector operands of a comparison hold a different number of bits.
+    BitWidthMismatch { lhs: usize, rhs: usize },
+    /// The table of an oblivious selection does not hold one entry per value of the index.
+    TableSizeMismatch { expected: usize, actual: usize },
+    /// The ciphertext list of `pin` does not hold as many ciphertexts as the list of the first
+    /// pin, see
+    /// [`ServerKey::evaluate_gate_list`](super::server_key::ServerKey::evaluate_gate_list).
+    ListLengthMismatch {
+        pin: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// The decryption shares do not hold exactly one share of every party.
+    InvalidDecryptionShares(String),
+    /// The flooding noise of a [`threshold`](super::threshold) key share is below the noise of
+    /// the ciphertexts, or overflows the decryption window of a ciphertext.
+    InvalidFloodingNoise(String),
+    /// The parameters or the messages of a distributed key generation are inconsistent, see
+    /// [`multiparty`](super::multiparty).
+    InvalidKeyGeneration(String),
+    /// An encrypted client key could not be restored, because the passphrase is wrong or the
+    /// bytes are not the output of
+    /// [`ClientKey::serialize_encrypted`](super::client_key::ClientKey::serialize_encrypted).
+    #[cfg(feature = "p-encoding-key-encryption")]
+    InvalidEncryptedKey(String),
+    /// The server key or the ciphertext is not under the parameters of the
+    /// [`ReparamKey`](super::reparam::ReparamKey).
+    ReparamKeyMismatch(String),
+    /// The symmetric key of a [`transcipher`](super::transcipher) cipher does not hold
+    /// `expected` bits.
+    SymmetricKeyLength { expected: usize, actual: usize },
+    /// In shadow evaluation, the encoding of the gate driving `signal` does not compute the truth
+    /// table of the gate on the decrypted inputs.
+    #[cfg(feature = "p-encoding-debug")]
+    ShadowEncodingMismatch {
+        signal: String,
+        expected: u32,
+        actual: u32,
+    },
+    /// In shadow evaluation, the decrypted output of the gate driving `signal` differs from the
+    /// output of its encoding on the decrypted inputs, e.g. because of noise.
+    #[cfg(feature = "p-encoding-debug")]
+    ShadowNoiseMismatch {
+        signal: String,
+        expected: u32,
+        actual: u32,
+    },
+}
+
+impl Display for GadgetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GadgetError::PinCountMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Encoding expects {expected} input ciphertexts, got {actual}"
+                )
+            }
+            GadgetError::InvalidEncoding(reason) => {
+                write!(f, "Invalid encoding: {reason}")
+            }
+            GadgetError::PlaintextModulusMismatch {
+                pin,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "Input ciphertext of pin {pin} is encrypted modulo {actual}, expected modulo \
+                    {expected}"
+                )
+            }
+            GadgetError::EncodingMismatch { expected, pins } => {
+                write!(
+                    f,
+                    "Input ciphertexts of pins {pins:?} are not encrypted modulo {expected}"
+                )
+            }
+            GadgetError::UnsupportedPlaintextModulus(p) => {
+                write!(f, "Plaintext modulus {p} is not supported")
+            }
+            GadgetError::PlaceholderCiphertext => {
+                write!(f, "Ciphertext placeholder reached in gadget engine")
+            }
+            GadgetError::CircuitParse { line, reason } => {
+                write!(f, "Failed to parse netlist at line {line}: {reason}")
+            }
+            GadgetError::InvalidCircuit(reason) => {
+                write!(f, "Invalid circuit: {reason}")
+            }
+            GadgetError::MissingCircuitInput(name) => {
+                write!(f, "No ciphertext provided for circuit input {name}")
+            }
+            GadgetError::UnexpectedCircuitInput(name) => {
+                write!(f, "Circuit has no input {name}")
+            }
+            GadgetError::EvaluationCancelled => {
+                write!(f, "Circuit evaluation was cancelled")
+            }
+            GadgetError::UnsatisfiableParameters(reason) => {
+                write!(f, "Unsatisfiable parameters: {reason}")
+            }
+            GadgetError::UnsupportedCiphertextModulus(modulus) => {
+                write!(
+                    f,
+                    "Unsupported ciphertext modulus {modulus}, only powers of two fitting the \
+                    torus scalar are supported"
+                )
+            }
+            GadgetError::UnsupportedGroupingFactor(factor) => {
+                write!(
+                    f,
+                    "Unsupported grouping factor {factor}, it must be 2, 3 or 4 and divide the \
+                    LWE dimension"
+                )
+            }
+            GadgetError::UnsupportedMultiBitKey => {
+                write!(
+                    f,
+                    "Operation not supported with a multi-bit bootstrapping key"
+                )
+            }
+            GadgetError::UnsupportedNttBackend(reason) => {
+                write!(f, "The NTT bootstrapping backend does not support {reason}")
+            }
+            GadgetError::NoiseBelowTorusPrecision(reason) => {
+                write!(f, "Noise below the precision of the torus: {reason}")
+            }
+            GadgetError::UnsupportedBigEncryptionKey => {
+                write!(
+                    f,
+                    "Operation not supported when encrypting under the big LWE key"
+                )
+            }
+            GadgetError::MissingPackingKey => {
+                write!(f, "Server key holds no packing keyswitching key")
+            }
+            GadgetError::UnsupportedTransportModulus(log_modulus) => {
+                write!(
+                    f,
+                    "Unsupported transport modulus 2^{log_modulus}, it must be smaller than the \
+                    ciphertext modulus"
+                )
+            }
+            GadgetError::UnknownServerKey(key_id) => {
+                write!(f, "No server key registered under id {key_id:?}")
+            }
+            GadgetError::ServerKeyOverBudget { size, budget } => {
+                write!(
+                    f,
+                    "Server key of {size} bytes exceeds the memory budget of {budget} bytes"
+                )
+            }
+            GadgetError::BitWidthMismatch { lhs, rhs } => {
+                write!(
+                    f,
+                    "Operands hold {lhs} and {rhs} bits, expected the same width"
+                )
+            }
+            GadgetError::TableSizeMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Table holds {actual} entries, expected one entry per index value, {expected}"
+                )
+            }
+            GadgetError::ListLengthMismatch {
+                pin,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "Ciphertext list of pin {pin} holds {actual} ciphertexts, expected {expected}"
+                )
+            }
+            GadgetError::InvalidDecryptionShares(reason) => {
+                write!(f, "Invalid decryption shares: {reason}")
+            }
+            GadgetError::InvalidFloodingNoise(reason) => {
+                write!(f, "Invalid flooding noise: {reason}")
+            }
+            GadgetError::InvalidKeyGeneration(reason) => {
+                write!(f, "Invalid distributed key generation: {reason}")
+            }
+            #[cfg(feature = "p-encoding-key-encryption")]
+            GadgetError::InvalidEncryptedKey(reason) => {
+                write!(f, "Invalid encrypted client key: {reason}")
+            }
+            GadgetError::ReparamKeyMismatch(reason) => {
+                write!(f, "The re-parameterization key does not match {reason}")
+            }
+            GadgetError::SymmetricKeyLength { expected, actual } => {
+                write!(f, "Symmetric key of {actual} bits instead of {expected}")
+            }
+            #[cfg(feature = "p-encoding-debug")]
+            GadgetError::ShadowEncodingMismatch {
+                signal,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "Encoding of the gate driving {signal} outputs {actual}, its truth table \
+                    outputs {expected}"
+                )
+            }
+            #[cfg(feature = "p-encoding-debug")]
+            GadgetError::ShadowNoiseMismatch {
+                signal,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "Gate driving {signal} decrypts to {actual}, expected {expected}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for GadgetError {}
+
+/// Error returned by the fallible decryptions, e.g.
+/// [`ClientKey::try_decrypt`](super::client_key::ClientKey::try_decrypt).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecryptionError {
+    /// A [`Ciphertext::Placeholder`](`super::ciphertext::Ciphertext::Placeholder`) was given.
+    PlaceholderCiphertext,
+    /// The ciphertext is not encrypted under the plaintext modulus of the expected encoding.
+    PlaintextModulusMismatch { expected: u32, actual: u32 },
+    /// The decrypted message is neither the encoding of true nor the one of false, e.g. because
+    /// the noise of the ciphertext exceeded its bound.
+    InvalidBooleanMessage(u32),
+}
+
+impl Display for DecryptionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecryptionError::PlaceholderCiphertext => {
+                write!(f, "Ciphertext placeholder cannot be decrypted")
+            }
+            DecryptionError::PlaintextModulusMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Ciphertext is encrypted modulo {actual}, expected modulo {expected}"
+                )
+            }
+            DecryptionError::InvalidBooleanMessage(message) => {
+                write!(
+                    f,
+                    "Decrypted message {message} encodes neither true nor false"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecryptionError {}
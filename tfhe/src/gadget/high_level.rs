@@ -0,0 +1,216 @@
+//! High level boolean API over the gadget backend.
+//!
+//! The server key is stored thread locally by [`set_server_key`], as in the crate's high level
+//! API, so that [`FheBool`] operators read like plain boolean expressions and application code
+//! never touches encodings.
+//!
+//! ```
+//! use tfhe::gadget::prelude::*;
+//!
+//! let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+//! set_server_key(server_key);
+//!
+//! let a = FheBool::encrypt(true, &client_key);
+//! let b = FheBool::encrypt(false, &client_key);
+//! let c = !(&a & &b) ^ &b;
+//! assert!(c.decrypt(&client_key));
+//! ```
+
+use std::cell::RefCell;
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+use serde::{Deserialize, Serialize};
+
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::server_key::ServerKey;
+
+thread_local! {
+    static SERVER_KEY: RefCell<Option<ServerKey>> = const { RefCell::new(None) };
+}
+
+/// Sets the server key evaluating the [`FheBool`] operators of the current thread.
+///
+/// Each thread has its own server key, this must be called on every thread operating on
+/// [`FheBool`] values.
+pub fn set_server_key(server_key: ServerKey) {
+    SERVER_KEY.with(|key| key.replace(Some(server_key)));
+}
+
+/// Removes the server key of the current thread, returning it if one was set.
+pub fn unset_server_key() -> Option<ServerKey> {
+    SERVER_KEY.with(|key| key.take())
+}
+
+/// # Panics
+///
+/// Panics if no server key is set on the current thread.
+fn with_server_key<T>(f: impl FnOnce(&ServerKey) -> T) -> T {
+    SERVER_KEY.with(|key| {
+        let key = key.borrow();
+        let server_key = key
+            .as_ref()
+            .expect("no gadget server key is set on this thread, call set_server_key first");
+        f(server_key)
+    })
+}
+
+/// An encrypted boolean whose operators are evaluated with the server key of the current
+/// thread, see [`set_server_key`].
+///
+/// # Panics
+///
+/// The operators panic if no server key is set or if the gate evaluation fails.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FheBool {
+    ciphertext: Ciphertext,
+}
+
+impl FheBool {
+    pub fn encrypt(value: bool, client_key: &ClientKey) -> Self {
+        Self {
+            ciphertext: client_key.encrypt(value),
+        }
+    }
+
+    /// Creates a trivial, i.e. not encrypted, boolean which costs nothing in gates.
+    pub fn encrypt_trivial(value: bool) -> Self {
+        Self {
+            ciphertext: Ciphertext::Trivial(value),
+        }
+    }
+
+    pub fn decrypt(&self, client_key: &ClientKey) -> bool {
+        client_key.decrypt(&self.ciphertext)
+    }
+
+    /// Evaluates `self ? then_value : else_value`.
+    pub fn if_then_else(&self, then_value: &FheBool, else_value: &FheBool) -> FheBool {
+        let ciphertext = with_server_key(|server_key| {
+            server_key.mux(
+                &self.ciphertext,
+                &then_value.ciphertext,
+                &else_value.ciphertext,
+            )
+        })
+        .unwrap_or_else(|err| panic!("mux gate failed: {err}"));
+        FheBool { ciphertext }
+    }
+
+    pub fn ciphertext(&self) -> &Ciphertext {
+        &self.ciphertext
+    }
+
+    pub fn into_inner(self) -> Ciphertext {
+        self.ciphertext
+    }
+}
+
+impl From<Ciphertext> for FheBool {
+    fn from(ciphertext: Ciphertext) -> Self {
+        Self { ciphertext }
+    }
+}
+
+macro_rules! impl_binary_operator {
+    ($trait:ident, $method:ident, $gate:ident) => {
+        impl $trait<&FheBool> for &FheBool {
+            type Output = FheBool;
+
+            fn $method(self, rhs: &FheBool) -> FheBool {
+                let ciphertext = with_server_key(|server_key| {
+                    server_key.$gate(&self.ciphertext, &rhs.ciphertext)
+                })
+                .unwrap_or_else(|err| panic!("{} gate failed: {err}", stringify!($gate)));
+                FheBool { ciphertext }
+            }
+        }
+
+        impl $trait<FheBool> for FheBool {
+            type Output = FheBool;
+
+            fn $method(self, rhs: FheBool) -> FheBool {
+                (&self).$method(&rhs)
+            }
+        }
+
+        impl $trait<&FheBool> for FheBool {
+            type Output = FheBool;
+
+            fn $method(self, rhs: &FheBool) -> FheBool {
+                (&self).$method(rhs)
+            }
+        }
+
+        impl $trait<FheBool> for &FheBool {
+            type Output = FheBool;
+
+            fn $method(self, rhs: FheBool) -> FheBool {
+                self.$method(&rhs)
+            }
+        }
+    };
+}
+
+impl_binary_operator!(BitAnd, bitand, and);
+impl_binary_operator!(BitOr, bitor, or);
+impl_binary_operator!(BitXor, bitxor, xor);
+
+impl Not for &FheBool {
+    type Output = FheBool;
+
+    fn not(self) -> FheBool {
+        let ciphertext = with_server_key(|server_key| server_key.not(&self.ciphertext))
+            .unwrap_or_else(|err| panic!("not gate failed: {err}"));
+        FheBool { ciphertext }
+    }
+}
+
+impl Not for FheBool {
+    type Output = FheBool;
+
+    fn not(self) -> FheBool {
+        !&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadget::boolean::BOOLEAN_PARAMETERS;
+    use crate::gadget::gen_keys;
+
+    #[test]
+    fn fhe_bool_operators() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        set_server_key(server_key);
+
+        for row in 0..8 {
+            let (lhs, rhs, select) = (row & 1 == 1, row & 2 == 2, row & 4 == 4);
+            let a = FheBool::encrypt(lhs, &client_key);
+            let b = FheBool::encrypt(rhs, &client_key);
+            let s = FheBool::encrypt(select, &client_key);
+
+            assert_eq!((&a & &b).decrypt(&client_key), lhs & rhs);
+            assert_eq!((&a | &b).decrypt(&client_key), lhs | rhs);
+            assert_eq!(
+                (&a ^ FheBool::encrypt_trivial(rhs)).decrypt(&client_key),
+                lhs ^ rhs
+            );
+            assert_eq!((!&a).decrypt(&client_key), !lhs);
+            assert_eq!(
+                s.if_then_else(&a, &b).decrypt(&client_key),
+                if select { lhs } else { rhs }
+            );
+        }
+
+        assert!(unset_server_key().is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "no gadget server key is set")]
+    fn operators_require_a_server_key() {
+        let a = FheBool::encrypt_trivial(true);
+        let _ = !&a;
+    }
+}
@@ -0,0 +1,506 @@
+//! Unsigned integers represented as vectors of encrypted bits, evaluated with the
+//! [`boolean`](super::boolean) gates.
+//!
+//! Arithmetic wraps around modulo `2^N` and comparisons return an encrypted boolean
+//! [`Ciphertext`].
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::error::GadgetError;
+use crate::gadget::server_key::ServerKey;
+
+pub mod sort;
+
+pub use sort::{sorting_network, SortingNetwork};
+
+/// An unsigned integer of `N` bits, each bit being a boolean [`Ciphertext`] and the least
+/// significant bit first.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GadgetUint<const N: usize> {
+    bits: Vec<Ciphertext>,
+}
+
+pub type GadgetUint8 = GadgetUint<8>;
+pub type GadgetUint16 = GadgetUint<16>;
+pub type GadgetUint32 = GadgetUint<32>;
+
+impl<const N: usize> GadgetUint<N> {
+    /// Encrypts the `N` least significant bits of `value`.
+    pub fn encrypt(value: u64, client_key: &ClientKey) -> Self {
+        Self {
+            bits: (0..N)
+                .map(|bit| client_key.encrypt((value >> bit) & 1 == 1))
+                .collect(),
+        }
+    }
+
+    /// Creates a trivial, i.e. not encrypted, integer from the `N` least significant bits of
+    /// `value`.
+    pub fn encrypt_trivial(value: u64) -> Self {
+        Self {
+            bits: (0..N)
+                .map(|bit| Ciphertext::Trivial((value >> bit) & 1 == 1))
+                .collect(),
+        }
+    }
+
+    pub fn decrypt(&self, client_key: &ClientKey) -> u64 {
+        self.bits
+            .iter()
+            .enumerate()
+            .map(|(bit, ct)| (client_key.decrypt(ct) as u64) << bit)
+            .sum()
+    }
+
+    /// Returns the bits of the integer, the least significant bit first.
+    pub fn bits(&self) -> &[Ciphertext] {
+        &self.bits
+    }
+
+    /// Creates an integer from its bits, the least significant bit first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` does not hold `N` ciphertexts.
+    pub fn from_bits(bits: Vec<Ciphertext>) -> Self {
+        assert_eq!(bits.len(), N, "a GadgetUint<{N}> has {N} bits");
+        Self { bits }
+    }
+}
+
+impl ServerKey {
+    /// Returns `lhs + rhs` modulo `2^N`, with a ripple carry adder.
+    pub fn add_uint<const N: usize>(
+        &self,
+        lhs: &GadgetUint<N>,
+        rhs: &GadgetUint<N>,
+    ) -> Result<GadgetUint<N>, GadgetError> {
+        self.ripple_carry_add(&lhs.bits, &rhs.bits, Ciphertext::Trivial(false))
+    }
+
+    /// Returns `lhs - rhs` modulo `2^N`, computed as `lhs + !rhs + 1`.
+    pub fn sub_uint<const N: usize>(
+        &self,
+        lhs: &GadgetUint<N>,
+        rhs: &GadgetUint<N>,
+    ) -> Result<GadgetUint<N>, GadgetError> {
+        let not_rhs = rhs
+            .bits
+            .iter()
+            .map(|bit| self.not(bit))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.ripple_carry_add(&lhs.bits, &not_rhs, Ciphertext::Trivial(true))
+    }
+
+    fn ripple_carry_add<const N: usize>(
+        &self,
+        lhs: &[Ciphertext],
+        rhs: &[Ciphertext],
+        carry_in: Ciphertext,
+    ) -> Result<GadgetUint<N>, GadgetError> {
+        let mut carry = carry_in;
+        let mut bits = Vec::with_capacity(N);
+        for (index, (lhs, rhs)) in lhs.iter().zip(rhs).enumerate() {
+            if index + 1 == N {
+                // the carry out of the last bit is dropped
+                bits.push(self.xor3(lhs, rhs, &carry)?);
+            } else {
+                let (sum, carry_out) = self.full_adder(lhs, rhs, &carry)?;
+                bits.push(sum);
+                carry = carry_out;
+            }
+        }
+        Ok(GadgetUint { bits })
+    }
+
+    /// Returns whether `lhs == rhs`.
+    pub fn eq_uint<const N: usize>(
+        &self,
+        lhs: &GadgetUint<N>,
+        rhs: &GadgetUint<N>,
+    ) -> Result<Ciphertext, GadgetError> {
+        self.eq_bits(&lhs.bits, &rhs.bits)
+    }
+
+    /// Returns whether `lhs != rhs`.
+    pub fn ne_uint<const N: usize>(
+        &self,
+        lhs: &GadgetUint<N>,
+        rhs: &GadgetUint<N>,
+    ) -> Result<Ciphertext, GadgetError> {
+        self.not(&self.eq_uint(lhs, rhs)?)
+    }
+
+    /// Returns whether `lhs < rhs`.
+    pub fn lt_uint<const N: usize>(
+        &self,
+        lhs: &GadgetUint<N>,
+        rhs: &GadgetUint<N>,
+    ) -> Result<Ciphertext, GadgetError> {
+        self.borrows(&lhs.bits, &rhs.bits)
+    }
+
+    /// Returns whether `lhs <= rhs`.
+    pub fn le_uint<const N: usize>(
+        &self,
+        lhs: &GadgetUint<N>,
+        rhs: &GadgetUint<N>,
+    ) -> Result<Ciphertext, GadgetError> {
+        self.not(&self.lt_uint(rhs, lhs)?)
+    }
+
+    /// Returns whether `lhs > rhs`.
+    pub fn gt_uint<const N: usize>(
+        &self,
+        lhs: &GadgetUint<N>,
+        rhs: &GadgetUint<N>,
+    ) -> Result<Ciphertext, GadgetError> {
+        self.lt_uint(rhs, lhs)
+    }
+
+    /// Returns whether `lhs >= rhs`.
+    pub fn ge_uint<const N: usize>(
+        &self,
+        lhs: &GadgetUint<N>,
+        rhs: &GadgetUint<N>,
+    ) -> Result<Ciphertext, GadgetError> {
+        self.not(&self.lt_uint(lhs, rhs)?)
+    }
+
+    /// Compares the unsigned integers `lhs` and `rhs`, given as bits with the least significant
+    /// bit first, returning the encrypted booleans `(lhs < rhs, lhs == rhs, lhs > rhs)`.
+    ///
+    /// For `n` bits this costs `4n - 1` bootstraps at a depth of `2 + ceil(log2(n))`, see
+    /// [`Self::lt_le_bits`]: `lhs == rhs` is `lhs <= rhs` and not `lhs < rhs`, one more
+    /// bootstrap, and `lhs > rhs` is the negation of `lhs <= rhs`, which is free.
+    pub fn compare(
+        &self,
+        lhs: &[Ciphertext],
+        rhs: &[Ciphertext],
+    ) -> Result<(Ciphertext, Ciphertext, Ciphertext), GadgetError> {
+        let (lt, le) = self.lt_le_bits(lhs, rhs)?;
+        let eq = self.andnot(&le, &lt)?;
+        let gt = self.not(&le)?;
+        Ok((lt, eq, gt))
+    }
+
+    /// Returns whether computing `lhs - rhs` borrows, i.e. whether `lhs < rhs`, see
+    /// [`Self::lt_le_bits`].
+    fn borrows(&self, lhs: &[Ciphertext], rhs: &[Ciphertext]) -> Result<Ciphertext, GadgetError> {
+        self.lt_le_bits(lhs, rhs).map(|(lt, _)| lt)
+    }
+
+    /// Returns `(lhs < rhs, lhs <= rhs)` for the unsigned integers `lhs` and `rhs`, given as bits
+    /// with the least significant bit first.
+    ///
+    /// Each bit position is compared from the difference of its bits, see [`ServerKey::lt_le`],
+    /// then adjacent groups of bits are merged as a balanced tree, the groups of each level in
+    /// parallel: the higher group decides unless its bits are equal, i.e.
+    /// `lt = maj3(lt_hi, le_hi, lt_lo)` and `le = maj3(lt_hi, le_hi, le_lo)`, as `lt_hi`
+    /// implies `le_hi`. The majorities read canonical pins modulo 4 in a single bootstrap, see
+    /// [`ServerKey::maj3_pins`]. For `n` bits this costs `4n - 2` bootstraps at a depth of
+    /// `1 + ceil(log2(n))`.
+    fn lt_le_bits(
+        &self,
+        lhs: &[Ciphertext],
+        rhs: &[Ciphertext],
+    ) -> Result<(Ciphertext, Ciphertext), GadgetError> {
+        if lhs.len() != rhs.len() {
+            return Err(GadgetError::BitWidthMismatch {
+                lhs: lhs.len(),
+                rhs: rhs.len(),
+            });
+        }
+        if lhs.is_empty() {
+            return Ok((Ciphertext::Trivial(false), Ciphertext::Trivial(true)));
+        }
+
+        // the groups are ordered from the least significant one
+        let to_boolean = lhs.len() == 1;
+        let mut level = lhs
+            .par_iter()
+            .zip(rhs.par_iter())
+            .map(|(lhs, rhs)| self.lt_le(lhs, rhs, to_boolean))
+            .collect::<Result<Vec<_>, _>>()?;
+        while level.len() > 1 {
+            let to_boolean = level.len() == 2;
+            level = level
+                .par_chunks(2)
+                .map(|groups| match groups {
+                    [(lt_lo, le_lo), (lt_hi, le_hi)] => {
+                        let (lt, le) = rayon::join(
+                            || self.maj3_pins([lt_hi, le_hi, lt_lo], to_boolean),
+                            || self.maj3_pins([lt_hi, le_hi, le_lo], to_boolean),
+                        );
+                        Ok((lt?, le?))
+                    }
+                    [group] => Ok(group.clone()),
+                    _ => unreachable!("chunks hold one or two groups"),
+                })
+                .collect::<Result<Vec<_>, GadgetError>>()?;
+        }
+
+        Ok(level.pop().unwrap())
+    }
+
+    /// Returns whether the bit-vectors `lhs` and `rhs` are equal.
+    ///
+    /// A xor bootstrap per bit outputs the difference of the bits as a canonical pin of the
+    /// wide modulus of [`ServerKey::wide_capacity`], the differences are summed linearly and a
+    /// single bootstrap tests the sum against zero, or one per chunk of as many pins as a
+    /// bootstrap reads, see [`ServerKey::reduce_pins`]. For `n` bits and chunks of `c` pins
+    /// this costs `n + ceil((n - 1) / (c - 1))` bootstraps, `n + 1` up to `c` bits. If the key
+    /// reads no more than two pins, the xnor of the bits are reduced by [`Self::and_many`]
+    /// instead. An empty bit-vector is equal to an empty bit-vector and costs nothing.
+    pub fn eq_bits(
+        &self,
+        lhs: &[Ciphertext],
+        rhs: &[Ciphertext],
+    ) -> Result<Ciphertext, GadgetError> {
+        if lhs.len() != rhs.len() {
+            return Err(GadgetError::BitWidthMismatch {
+                lhs: lhs.len(),
+                rhs: rhs.len(),
+            });
+        }
+        if lhs.is_empty() {
+            return Ok(Ciphertext::Trivial(true));
+        }
+
+        let Some((capacity, p)) = self.wide_capacity() else {
+            let equal_bits = lhs
+                .par_iter()
+                .zip(rhs.par_iter())
+                .map(|(lhs, rhs)| self.xnor(lhs, rhs))
+                .collect::<Result<Vec<_>, _>>()?;
+            return self.and_many(&equal_bits);
+        };
+
+        let differences = lhs
+            .par_iter()
+            .zip(rhs.par_iter())
+            .map(|(lhs, rhs)| self.xor_pin(lhs, rhs, p))
+            .collect::<Result<Vec<_>, _>>()?;
+        // the bit-vectors differ if any difference is set
+        let differ = self.reduce_pins(differences, (capacity, p), |set_pins, _| set_pins > 0)?;
+        self.not(&differ)
+    }
+
+    /// Returns `select ? then_value : else_value`, the bits being selected in parallel.
+    pub fn mux_uint<const N: usize>(
+        &self,
+        select: &Ciphertext,
+        then_value: &GadgetUint<N>,
+        else_value: &GadgetUint<N>,
+    ) -> Result<GadgetUint<N>, GadgetError> {
+        let bits = then_value
+            .bits
+            .par_iter()
+            .zip(else_value.bits.par_iter())
+            .map(|(then_bit, else_bit)| self.mux(select, then_bit, else_bit))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(GadgetUint { bits })
+    }
+
+    /// Obliviously selects the entry of `table` at the encrypted `index_bits`, given with the
+    /// least significant bit first, as a tree of muxes.
+    ///
+    /// `table` must hold `2^k` entries of the same width `w` for `k` index bits. Level `i` of
+    /// the tree merges pairs of entries under the index bit `i`, the whole selection costing
+    /// `2w(2^k - 1)` bootstraps at a depth of `2k`.
+    pub fn select(
+        &self,
+        table: &[Vec<Ciphertext>],
+        index_bits: &[Ciphertext],
+    ) -> Result<Vec<Ciphertext>, GadgetError> {
+        let expected = 1usize
+            .checked_shl(index_bits.len() as u32)
+            .unwrap_or(usize::MAX);
+        if table.len() != expected {
+            return Err(GadgetError::TableSizeMismatch {
+                expected,
+                actual: table.len(),
+            });
+        }
+        let width = table[0].len();
+        if let Some(entry) = table.iter().find(|entry| entry.len() != width) {
+            return Err(GadgetError::BitWidthMismatch {
+                lhs: width,
+                rhs: entry.len(),
+            });
+        }
+
+        let mut level = table.to_vec();
+        for index_bit in index_bits {
+            level = level
+                .par_chunks(2)
+                .map(|pair| {
+                    pair[1]
+                        .par_iter()
+                        .zip(pair[0].par_iter())
+                        .map(|(then_bit, else_bit)| self.mux(index_bit, then_bit, else_bit))
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+
+        Ok(level.pop().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::gadget::boolean::BOOLEAN_PARAMETERS;
+    use crate::gadget::gen_keys;
+    use std::error::Error;
+
+    static REPEAT: usize = 5;
+
+    #[test]
+    fn uint8_arithmetic() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..REPEAT {
+            let lhs = rng.gen::<u8>();
+            let rhs = rng.gen::<u8>();
+            let lhs_ct = GadgetUint8::encrypt(lhs as u64, &client_key);
+            let rhs_ct = GadgetUint8::encrypt(rhs as u64, &client_key);
+
+            let sum = server_key.add_uint(&lhs_ct, &rhs_ct)?;
+            assert_eq!(sum.decrypt(&client_key), lhs.wrapping_add(rhs) as u64);
+
+            let difference = server_key.sub_uint(&lhs_ct, &rhs_ct)?;
+            assert_eq!(
+                difference.decrypt(&client_key),
+                lhs.wrapping_sub(rhs) as u64
+            );
+
+            // Trivial operand
+            let sum = server_key.add_uint(&lhs_ct, &GadgetUint8::encrypt_trivial(rhs as u64))?;
+            assert_eq!(sum.decrypt(&client_key), lhs.wrapping_add(rhs) as u64);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn uint8_comparisons_and_mux() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let mut rng = rand::thread_rng();
+
+        for index in 0..REPEAT {
+            let lhs = rng.gen::<u8>();
+            // also compare equal values
+            let rhs = if index == 0 { lhs } else { rng.gen::<u8>() };
+            let lhs_ct = GadgetUint8::encrypt(lhs as u64, &client_key);
+            let rhs_ct = GadgetUint8::encrypt(rhs as u64, &client_key);
+
+            let eq = server_key.eq_uint(&lhs_ct, &rhs_ct)?;
+            assert_eq!(client_key.decrypt(&eq), lhs == rhs, "{lhs} == {rhs}");
+            let lt = server_key.lt_uint(&lhs_ct, &rhs_ct)?;
+            assert_eq!(client_key.decrypt(&lt), lhs < rhs, "{lhs} < {rhs}");
+            let ge = server_key.ge_uint(&lhs_ct, &rhs_ct)?;
+            assert_eq!(client_key.decrypt(&ge), lhs >= rhs, "{lhs} >= {rhs}");
+
+            let max = server_key.mux_uint(&ge, &lhs_ct, &rhs_ct)?;
+            assert_eq!(max.decrypt(&client_key), lhs.max(rhs) as u64);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn oblivious_selection() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        let values = [3u64, 14, 7, 0];
+        let table: Vec<Vec<Ciphertext>> = values
+            .iter()
+            .map(|value| {
+                GadgetUint::<4>::encrypt(*value, &client_key)
+                    .bits()
+                    .to_vec()
+            })
+            .collect();
+
+        for (index, value) in values.iter().enumerate() {
+            let index_ct = GadgetUint::<2>::encrypt(index as u64, &client_key);
+            let selected = server_key.select(&table, index_ct.bits())?;
+            let selected = GadgetUint::<4>::from_bits(selected);
+            assert_eq!(selected.decrypt(&client_key), *value, "index: {index}");
+        }
+
+        assert_eq!(
+            server_key
+                .select(&table[..3], GadgetUint::<2>::encrypt_trivial(0).bits())
+                .unwrap_err(),
+            GadgetError::TableSizeMismatch {
+                expected: 4,
+                actual: 3
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn compare_bit_vectors() -> Result<(), Box<dyn Error>> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let mut rng = rand::thread_rng();
+
+        for index in 0..REPEAT {
+            let lhs = rng.gen::<u8>() as u64 & 0x3f;
+            let rhs = if index == 0 {
+                lhs
+            } else {
+                rng.gen::<u8>() as u64 & 0x3f
+            };
+            let lhs_ct = GadgetUint::<6>::encrypt(lhs, &client_key);
+            let rhs_ct = GadgetUint::<6>::encrypt(rhs, &client_key);
+
+            let (lt, eq, gt) = server_key.compare(lhs_ct.bits(), rhs_ct.bits())?;
+            assert_eq!(client_key.decrypt(&lt), lhs < rhs, "{lhs} < {rhs}");
+            assert_eq!(client_key.decrypt(&eq), lhs == rhs, "{lhs} == {rhs}");
+            assert_eq!(client_key.decrypt(&gt), lhs > rhs, "{lhs} > {rhs}");
+        }
+
+        let lhs_ct = GadgetUint8::encrypt(0xa5, &client_key);
+        server_key.enable_stats(true);
+        let eq = server_key.eq_bits(lhs_ct.bits(), lhs_ct.bits())?;
+        assert!(client_key.decrypt(&eq));
+        // 8 xor into canonical pins modulo 4 and 4 gates of 3 pins
+        assert_eq!(server_key.stats().pbs_count, 12);
+
+        server_key.reset_stats();
+        let rhs_ct = GadgetUint8::encrypt(0xa6, &client_key);
+        let (lt, eq, gt) = server_key.compare(lhs_ct.bits(), rhs_ct.bits())?;
+        assert!(client_key.decrypt(&lt));
+        assert!(!client_key.decrypt(&eq));
+        assert!(!client_key.decrypt(&gt));
+        assert_eq!(server_key.stats().pbs_count, 4 * 8 - 1);
+
+        // up to 3 bits, the sum of the differences is tested against zero in a single bootstrap
+        server_key.reset_stats();
+        let eq = server_key.eq_bits(&lhs_ct.bits()[..3], &rhs_ct.bits()[..3])?;
+        assert!(!client_key.decrypt(&eq));
+        assert_eq!(server_key.stats().pbs_count, 3 + 1);
+        server_key.enable_stats(false);
+
+        let bits = GadgetUint8::encrypt_trivial(0);
+        assert_eq!(
+            server_key
+                .compare(bits.bits(), &bits.bits()[1..])
+                .unwrap_err(),
+            GadgetError::BitWidthMismatch { lhs: 8, rhs: 7 }
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+//! Oblivious sorting of encrypted bit-vectors.
+//!
+//! [`ServerKey::sort2`] orders two unsigned integers with a compare-and-swap, and
+//! [`sorting_network`] lays such comparators out as a data-independent network, so that
+//! [`ServerKey::sort`] sorts without learning anything about the values. Top-k or median
+//! queries read the first entries, or the middle one, of the sorted values.
+
+use rayon::prelude::*;
+
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::error::GadgetError;
+use crate::gadget::server_key::ServerKey;
+
+/// A sorting network on `size` wires: layers of comparators on distinct wires, each comparator
+/// `(i, j)` with `i < j` moving the smaller value to wire `i`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SortingNetwork {
+    size: usize,
+    layers: Vec<Vec<(usize, usize)>>,
+}
+
+impl SortingNetwork {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the layers of the network, the comparators of a layer being independent.
+    pub fn layers(&self) -> &[Vec<(usize, usize)>] {
+        &self.layers
+    }
+
+    pub fn depth(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn comparator_count(&self) -> usize {
+        self.layers.iter().map(Vec::len).sum()
+    }
+
+    /// Applies the network to cleartext `values`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` does not hold one value per wire.
+    pub fn sort<T: Ord>(&self, values: &mut [T]) {
+        assert_eq!(
+            values.len(),
+            self.size,
+            "the network sorts {} values",
+            self.size
+        );
+        for (i, j) in self.layers.iter().flatten() {
+            if values[*j] < values[*i] {
+                values.swap(*i, *j);
+            }
+        }
+    }
+}
+
+/// Builds Batcher's odd-even merge sorting network on `size` wires.
+///
+/// The network has a depth of at most `log2(n)(log2(n) + 1) / 2` for `n` the next power of two
+/// of `size`, the comparators reaching past `size` being dropped: they would only compare against
+/// padding values larger than any input.
+pub fn sorting_network(size: usize) -> SortingNetwork {
+    let mut layers = Vec::new();
+    let mut p = 1;
+    while p < size {
+        let mut k = p;
+        while k > 0 {
+            let mut layer = Vec::new();
+            let mut j = k % p;
+            while j + k < size {
+                for i in 0..k.min(size - j - k) {
+                    if (i + j) / (2 * p) == (i + j + k) / (2 * p) {
+                        layer.push((i + j, i + j + k));
+                    }
+                }
+                j += 2 * k;
+            }
+            if !layer.is_empty() {
+                layers.push(layer);
+            }
+            k /= 2;
+        }
+        p *= 2;
+    }
+
+    SortingNetwork { size, layers }
+}
+
+impl ServerKey {
+    /// Returns `(min(lhs, rhs), max(lhs, rhs))` for the unsigned integers `lhs` and `rhs`,
+    /// given as bits with the least significant bit first.
+    ///
+    /// For `n` bits this costs `8n - 2` bootstraps: `4n - 2` for the comparison of `lhs` and
+    /// `rhs`, see [`ServerKey::compare`], and two muxes per bit.
+    pub fn sort2(
+        &self,
+        lhs: &[Ciphertext],
+        rhs: &[Ciphertext],
+    ) -> Result<(Vec<Ciphertext>, Vec<Ciphertext>), GadgetError> {
+        if lhs.len() != rhs.len() {
+            return Err(GadgetError::BitWidthMismatch {
+                lhs: lhs.len(),
+                rhs: rhs.len(),
+            });
+        }
+
+        let lt = self.borrows(lhs, rhs)?;
+        lhs.par_iter()
+            .zip(rhs.par_iter())
+            .map(|(lhs, rhs)| Ok((self.mux(&lt, lhs, rhs)?, self.mux(&lt, rhs, lhs)?)))
+            .collect::<Result<Vec<_>, GadgetError>>()
+            .map(|bits| bits.into_iter().unzip())
+    }
+
+    /// Sorts the unsigned integers `values` in ascending order, with the comparators of
+    /// [`sorting_network`] evaluated one layer after another, in parallel within a layer.
+    ///
+    /// Fails with [`GadgetError::BitWidthMismatch`] if the values do not all have the same
+    /// width.
+    pub fn sort(&self, values: &[Vec<Ciphertext>]) -> Result<Vec<Vec<Ciphertext>>, GadgetError> {
+        let network = sorting_network(values.len());
+        let mut values = values.to_vec();
+        for layer in network.layers() {
+            let sorted = layer
+                .par_iter()
+                .map(|(i, j)| self.sort2(&values[*i], &values[*j]))
+                .collect::<Result<Vec<_>, _>>()?;
+            for ((i, j), (min, max)) in layer.iter().zip(sorted) {
+                values[*i] = min;
+                values[*j] = max;
+            }
+        }
+
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::gadget::boolean::BOOLEAN_PARAMETERS;
+    use crate::gadget::gen_keys;
+    use crate::gadget::integer::GadgetUint;
+
+    #[test]
+    fn sorting_networks_sort_every_boolean_vector() {
+        // a network sorting every 0-1 input sorts every input
+        for size in 0..=10 {
+            let network = sorting_network(size);
+            for row in 0..1u32 << size {
+                let mut values: Vec<u32> = (0..size).map(|bit| (row >> bit) & 1).collect();
+                network.sort(&mut values);
+                assert!(values.windows(2).all(|pair| pair[0] <= pair[1]), "{row:#b}");
+            }
+        }
+
+        let network = sorting_network(8);
+        assert_eq!(network.depth(), 6);
+        assert_eq!(network.comparator_count(), 19);
+        for layer in network.layers() {
+            let mut wires: Vec<usize> = layer.iter().flat_map(|(i, j)| [*i, *j]).collect();
+            wires.sort_unstable();
+            wires.dedup();
+            assert_eq!(wires.len(), 2 * layer.len());
+        }
+    }
+
+    #[test]
+    fn sort_encrypted_values() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let mut rng = rand::thread_rng();
+
+        let lhs = GadgetUint::<3>::encrypt(5, &client_key);
+        let rhs = GadgetUint::<3>::encrypt(2, &client_key);
+        let (min, max) = server_key.sort2(lhs.bits(), rhs.bits()).unwrap();
+        assert_eq!(GadgetUint::<3>::from_bits(min).decrypt(&client_key), 2);
+        assert_eq!(GadgetUint::<3>::from_bits(max).decrypt(&client_key), 5);
+
+        let mut values: Vec<u64> = (0..5).map(|_| rng.gen_range(0..8)).collect();
+        let cts: Vec<Vec<Ciphertext>> = values
+            .iter()
+            .map(|value| {
+                GadgetUint::<3>::encrypt(*value, &client_key)
+                    .bits()
+                    .to_vec()
+            })
+            .collect();
+        let sorted = server_key.sort(&cts).unwrap();
+        values.sort_unstable();
+        let decrypted: Vec<u64> = sorted
+            .into_iter()
+            .map(|bits| GadgetUint::<3>::from_bits(bits).decrypt(&client_key))
+            .collect();
+        assert_eq!(decrypted, values);
+
+        assert_eq!(
+            server_key.sort2(lhs.bits(), &rhs.bits()[1..]).unwrap_err(),
+            GadgetError::BitWidthMismatch { lhs: 3, rhs: 2 }
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Passphrase protected serialization of client keys, for applications persisting them to disk.
+//!
+//! [`ClientKey::serialize_encrypted`] derives a 256 bits key from the passphrase and a random
+//! salt with Argon2id, using its default cost parameters, and encrypts the bincode serialization
+//! of the client key with ChaCha20-Poly1305 under a random nonce. The output is laid out as
+//!
+//! ```text
+//! version (1 byte) | salt (16 bytes) | nonce (12 bytes) | ciphertext | tag (16 bytes)
+//! ```
+//!
+//! the header and the name of the key type being authenticated along with the ciphertext, so that
+//! a key of a different [`GadgetScalar`] is rejected as well. The serialization of the key is
+//! zeroed once encrypted or deserialized.
+//!
+//! This module is only available with the `p-encoding-key-encryption` feature.
+
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{AeadCore, AeadInPlace, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, Tag};
+
+use crate::conformance::ParameterSetConformant;
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::engine::{zeroize, GadgetScalar};
+use crate::gadget::error::GadgetError;
+use crate::named::Named;
+use serde::de::DeserializeOwned;
+
+/// Version of the layout of the encrypted keys, bumped whenever the layout or the key derivation
+/// changes.
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const HEADER_LEN: usize = 1 + SALT_LEN + NONCE_LEN;
+
+/// Derives the cipher of `passphrase` and `salt`, zeroing the derived key once expanded.
+fn cipher(passphrase: &[u8], salt: &[u8]) -> ChaCha20Poly1305 {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase, salt, &mut key)
+        .expect("salt and key lengths supported by Argon2");
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    zeroize(&mut key);
+    cipher
+}
+
+fn associated_data<T: Named>(header: &[u8]) -> Vec<u8> {
+    [header, T::NAME.as_bytes()].concat()
+}
+
+impl<Scalar: GadgetScalar + DeserializeOwned> ClientKey<Scalar>
+where
+    Self: Named,
+{
+    /// Serializes the key encrypted under `passphrase`, see the [module](self) documentation.
+    ///
+    /// The derivation of the encryption key is deliberately slow, in the order of tens of
+    /// milliseconds, to make guessing the passphrase costly.
+    pub fn serialize_encrypted(&self, passphrase: &[u8]) -> Vec<u8> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        // allocating the whole output upfront, no copy of the serialized key is left behind by a
+        // reallocation
+        let serialized_size =
+            bincode::serialized_size(self).expect("client keys are serializable") as usize;
+        let mut bytes = Vec::with_capacity(HEADER_LEN + serialized_size + TAG_LEN);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&salt);
+        bytes.extend_from_slice(&nonce);
+        bincode::serialize_into(&mut bytes, self).expect("client keys are serializable");
+
+        let (header, payload) = bytes.split_at_mut(HEADER_LEN);
+        let tag = cipher(passphrase, &salt)
+            .encrypt_in_place_detached(&nonce, &associated_data::<Self>(header), payload)
+            .expect("client keys fit in a single ChaCha20-Poly1305 message");
+        bytes.extend_from_slice(&tag);
+        bytes
+    }
+
+    /// Decrypts and deserializes a key serialized with [`ClientKey::serialize_encrypted`].
+    ///
+    /// Fails with [`GadgetError::InvalidEncryptedKey`] if `passphrase` is wrong, if `bytes` were
+    /// corrupted or were not encrypted by [`ClientKey::serialize_encrypted`] for this `Scalar`, or
+    /// if the decrypted key does not conform to its own parameters.
+    pub fn deserialize_encrypted(bytes: &[u8], passphrase: &[u8]) -> Result<Self, GadgetError> {
+        if bytes.len() < HEADER_LEN + TAG_LEN {
+            return Err(GadgetError::InvalidEncryptedKey(format!(
+                "{} bytes are too short to hold an encrypted key",
+                bytes.len()
+            )));
+        }
+        let (header, rest) = bytes.split_at(HEADER_LEN);
+        if header[0] != FORMAT_VERSION {
+            return Err(GadgetError::InvalidEncryptedKey(format!(
+                "unsupported format version {}, expected {FORMAT_VERSION}",
+                header[0]
+            )));
+        }
+        let (salt, nonce) = header[1..].split_at(SALT_LEN);
+        let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+
+        let mut payload = ciphertext.to_vec();
+        let client_key = cipher(passphrase, salt)
+            .decrypt_in_place_detached(
+                Nonce::from_slice(nonce),
+                &associated_data::<Self>(header),
+                &mut payload,
+                Tag::from_slice(tag),
+            )
+            .map_err(|_| {
+                GadgetError::InvalidEncryptedKey(
+                    "wrong passphrase or corrupted encrypted key".to_string(),
+                )
+            })
+            .and_then(|()| {
+                bincode::deserialize::<Self>(&payload)
+                    .map_err(|err| GadgetError::InvalidEncryptedKey(err.to_string()))
+            });
+        zeroize(&mut payload);
+
+        let client_key = client_key?;
+        if !client_key.is_conformant(&client_key.parameters) {
+            return Err(GadgetError::InvalidEncryptedKey(
+                "the secret keys do not match the parameters of the client key".to_string(),
+            ));
+        }
+        Ok(client_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadget::gen_keys;
+    use crate::gadget::parameters::PLAINTEXT_3_BITS_PARAMETERS;
+
+    #[test]
+    fn encrypted_client_key_round_trip() {
+        let (client_key, _) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let encrypted = client_key.serialize_encrypted(b"correct horse battery staple");
+        assert_eq!(encrypted[0], FORMAT_VERSION);
+
+        let decrypted =
+            ClientKey::deserialize_encrypted(&encrypted, b"correct horse battery staple").unwrap();
+        assert_eq!(decrypted, client_key);
+
+        // a fresh salt and nonce on every encryption
+        assert_ne!(
+            client_key.serialize_encrypted(b"correct horse battery staple"),
+            encrypted
+        );
+
+        assert!(matches!(
+            ClientKey::<u32>::deserialize_encrypted(&encrypted, b"wrong passphrase"),
+            Err(GadgetError::InvalidEncryptedKey(_))
+        ));
+
+        let mut tampered = encrypted.clone();
+        tampered[HEADER_LEN] ^= 1;
+        assert!(matches!(
+            ClientKey::<u32>::deserialize_encrypted(&tampered, b"correct horse battery staple"),
+            Err(GadgetError::InvalidEncryptedKey(_))
+        ));
+
+        // the key type is authenticated
+        assert!(matches!(
+            ClientKey::<u64>::deserialize_encrypted(&encrypted, b"correct horse battery staple"),
+            Err(GadgetError::InvalidEncryptedKey(_))
+        ));
+        assert!(matches!(
+            ClientKey::<u32>::deserialize_encrypted(&encrypted[..HEADER_LEN], b""),
+            Err(GadgetError::InvalidEncryptedKey(_))
+        ));
+    }
+}
@@ -0,0 +1,313 @@
+//! Registry of the server keys of many clients, for services evaluating circuits on behalf of
+//! several users.
+//!
+//! A [`ServerKeyStore`] maps the id of each client key to the [`ServerKey`] generated from it
+//! and keeps the keys within a memory budget: registering a key that does not fit evicts the
+//! least recently used ones, which the service then has to reload, e.g. from its own storage or
+//! by asking the client again.
+//!
+//! Keys are handed out behind an [`Arc`], so that an evaluation never holds the lock of the
+//! store and a key evicted while in use stays alive until its evaluations complete.
+
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::encoding::Encoding;
+use crate::gadget::engine::GadgetScalar;
+use crate::gadget::error::GadgetError;
+use crate::gadget::server_key::ServerKey;
+use crate::named::Named;
+use serde::de::Deserializer;
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+struct StoreEntry<Scalar: GadgetScalar> {
+    server_key: Arc<ServerKey<Scalar>>,
+    size: usize,
+    /// Value of the store clock at the last access, the smallest is the least recently used.
+    last_used: u64,
+}
+
+struct StoreEntries<Scalar: GadgetScalar> {
+    entries: HashMap<String, StoreEntry<Scalar>>,
+    clock: u64,
+    memory_usage: usize,
+}
+
+impl<Scalar: GadgetScalar> StoreEntries<Scalar> {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn remove(&mut self, key_id: &str) -> Option<StoreEntry<Scalar>> {
+        let entry = self.entries.remove(key_id)?;
+        self.memory_usage -= entry.size;
+        Some(entry)
+    }
+
+    /// Evicts the least recently used keys until `size` more bytes fit in `budget`, returning
+    /// the ids of the evicted keys.
+    fn make_room(&mut self, size: usize, budget: usize) -> Vec<String> {
+        let mut evicted = vec![];
+        while self.memory_usage + size > budget {
+            let Some(key_id) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key_id, _)| key_id.clone())
+            else {
+                break;
+            };
+            self.remove(&key_id);
+            evicted.push(key_id);
+        }
+        evicted
+    }
+}
+
+/// Server keys of many clients indexed by key id, see the [module](self) documentation.
+///
+/// The store is `Send + Sync` and meant to be shared between the workers of a service, every
+/// method taking `&self`.
+pub struct ServerKeyStore<Scalar: GadgetScalar = u32> {
+    memory_budget: usize,
+    entries: Mutex<StoreEntries<Scalar>>,
+}
+
+impl<Scalar: GadgetScalar> ServerKeyStore<Scalar> {
+    /// Creates an empty store holding at most `memory_budget` bytes of keys, as measured by
+    /// [`ServerKey::size_in_bytes`].
+    pub fn new(memory_budget: usize) -> Self {
+        Self {
+            memory_budget,
+            entries: Mutex::new(StoreEntries {
+                entries: HashMap::new(),
+                clock: 0,
+                memory_usage: 0,
+            }),
+        }
+    }
+
+    pub fn memory_budget(&self) -> usize {
+        self.memory_budget
+    }
+
+    /// Returns the memory held by the registered keys, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.entries.lock().unwrap().memory_usage
+    }
+
+    /// Returns the number of registered keys.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, key_id: &str) -> bool {
+        self.entries.lock().unwrap().entries.contains_key(key_id)
+    }
+
+    /// Registers `server_key` under `key_id`, replacing the key previously registered under it,
+    /// and returns the ids of the keys evicted to make room for it, least recently used first.
+    ///
+    /// Fails with [`GadgetError::ServerKeyOverBudget`] if the key alone exceeds the memory
+    /// budget, in which case the store is left untouched.
+    pub fn insert(
+        &self,
+        key_id: impl Into<String>,
+        server_key: ServerKey<Scalar>,
+    ) -> Result<Vec<String>, GadgetError> {
+        let size = server_key.size_in_bytes();
+        if size > self.memory_budget {
+            return Err(GadgetError::ServerKeyOverBudget {
+                size,
+                budget: self.memory_budget,
+            });
+        }
+
+        let key_id = key_id.into();
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove(&key_id);
+        let evicted = entries.make_room(size, self.memory_budget);
+
+        let last_used = entries.tick();
+        entries.memory_usage += size;
+        entries.entries.insert(
+            key_id,
+            StoreEntry {
+                server_key: Arc::new(server_key),
+                size,
+                last_used,
+            },
+        );
+        Ok(evicted)
+    }
+
+    /// Returns the key registered under `key_id`, marking it as the most recently used.
+    pub fn get(&self, key_id: &str) -> Option<Arc<ServerKey<Scalar>>> {
+        let mut entries = self.entries.lock().unwrap();
+        let last_used = entries.tick();
+        let entry = entries.entries.get_mut(key_id)?;
+        entry.last_used = last_used;
+        Some(entry.server_key.clone())
+    }
+
+    /// Unregisters the key of `key_id`, returning it.
+    pub fn remove(&self, key_id: &str) -> Option<Arc<ServerKey<Scalar>>> {
+        self.entries
+            .lock()
+            .unwrap()
+            .remove(key_id)
+            .map(|entry| entry.server_key)
+    }
+
+    /// Evaluates the gate described by `encoding` with the key registered under `key_id`, see
+    /// [`ServerKey::evaluate_gate`].
+    ///
+    /// Fails with [`GadgetError::UnknownServerKey`] if no key is registered under `key_id`, e.g.
+    /// because it was evicted.
+    pub fn evaluate_gate_for(
+        &self,
+        key_id: &str,
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+        encoding: &Encoding,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        let server_key = self
+            .get(key_id)
+            .ok_or_else(|| GadgetError::UnknownServerKey(key_id.to_string()))?;
+        server_key.evaluate_gate(input_ciphertexts, encoding)
+    }
+}
+
+/// Serialized form of a [`ServerKeyStore`], the keys being listed from the least to the most
+/// recently used so that deserialization restores the eviction order.
+#[derive(Serialize)]
+struct SerializedStore<'a, Scalar: GadgetScalar> {
+    memory_budget: usize,
+    keys: Vec<(&'a str, &'a ServerKey<Scalar>)>,
+}
+
+#[derive(Deserialize)]
+struct DeserializedStore<Scalar: GadgetScalar> {
+    memory_budget: usize,
+    keys: Vec<(String, ServerKey<Scalar>)>,
+}
+
+impl<Scalar: GadgetScalar> Serialize for ServerKeyStore<Scalar> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let entries = self.entries.lock().unwrap();
+        let mut keys: Vec<_> = entries.entries.iter().collect();
+        keys.sort_by_key(|(_, entry)| entry.last_used);
+
+        SerializedStore {
+            memory_budget: self.memory_budget,
+            keys: keys
+                .into_iter()
+                .map(|(key_id, entry)| (key_id.as_str(), entry.server_key.as_ref()))
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, Scalar: GadgetScalar + Deserialize<'de>> Deserialize<'de> for ServerKeyStore<Scalar> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let DeserializedStore {
+            memory_budget,
+            keys,
+        } = DeserializedStore::deserialize(deserializer)?;
+
+        let store = Self::new(memory_budget);
+        for (key_id, server_key) in keys {
+            store
+                .insert(key_id, server_key)
+                .map_err(serde::de::Error::custom)?;
+        }
+        Ok(store)
+    }
+}
+
+impl Named for ServerKeyStore<u32> {
+    const NAME: &'static str = "gadget::ServerKeyStore";
+}
+
+impl Named for ServerKeyStore<u64> {
+    const NAME: &'static str = "gadget::ServerKeyStore64";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean::engine::WithThreadLocalEngine;
+    use crate::gadget::engine::GadgetEngine;
+    use crate::gadget::gen_keys;
+    use crate::gadget::parameters::PLAINTEXT_3_BITS_PARAMETERS;
+
+    #[test]
+    fn least_recently_used_keys_are_evicted() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let size = server_key.size_in_bytes();
+        assert!(size > 0);
+
+        let store = ServerKeyStore::new(2 * size);
+        assert_eq!(store.insert("alice", server_key.clone()), Ok(vec![]));
+        assert_eq!(store.insert("bob", server_key.clone()), Ok(vec![]));
+        assert_eq!(store.memory_usage(), 2 * size);
+
+        // alice was used last, bob is evicted
+        assert!(store.get("alice").is_some());
+        assert_eq!(
+            store.insert("carol", server_key.clone()),
+            Ok(vec!["bob".to_string()])
+        );
+        assert!(!store.contains("bob"));
+        assert_eq!(store.len(), 2);
+
+        // replacing a key does not evict another one
+        assert_eq!(store.insert("carol", server_key.clone()), Ok(vec![]));
+        assert_eq!(
+            ServerKeyStore::new(size - 1).insert("dave", server_key),
+            Err(GadgetError::ServerKeyOverBudget {
+                size,
+                budget: size - 1,
+            })
+        );
+
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+            vec![
+                engine.encrypt(1, &client_key, 3),
+                engine.encrypt(1, &client_key, 3),
+            ]
+        });
+        let out = store
+            .evaluate_gate_for("alice", inputs.clone(), &encoding)
+            .unwrap();
+        let decrypted =
+            GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&out, &client_key));
+        assert_eq!(decrypted, 1);
+        assert_eq!(
+            store
+                .evaluate_gate_for("bob", inputs, &encoding)
+                .unwrap_err(),
+            GadgetError::UnknownServerKey("bob".to_string())
+        );
+
+        // the eviction order goes through serialization: alice was used after carol
+        let serialized = bincode::serialize(&store).unwrap();
+        let store: ServerKeyStore = bincode::deserialize(&serialized).unwrap();
+        assert_eq!(store.memory_budget(), 2 * size);
+        assert_eq!(store.memory_usage(), 2 * size);
+        let server_key = store.remove("alice").unwrap();
+        assert_eq!(store.memory_usage(), size);
+        assert_eq!(store.insert("alice", (*server_key).clone()), Ok(vec![]));
+        assert_eq!(
+            store.insert("erin", (*server_key).clone()),
+            Ok(vec!["carol".to_string()])
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Keyswitching keys between two gadget client keys.
+//!
+//! A [`KeySwitchingKey`] re-encrypts ciphertexts of one client key under another one without
+//! decrypting them, e.g. for a server to deliver results to a different user than the one who
+//! encrypted the inputs.
+
+use serde::{Deserialize, Serialize};
+
+use crate::boolean::engine::WithThreadLocalEngine;
+use crate::core_crypto::prelude::{keyswitch_lwe_ciphertext, LweCiphertext, LweKeyswitchKeyOwned};
+use crate::gadget::ciphertext::{Ciphertext, NoiseLevel};
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::engine::{GadgetEngine, GadgetScalar};
+use crate::gadget::error::GadgetError;
+use crate::gadget::parameters::GadgetKeySwitchingParameters;
+use crate::gadget::server_key::ServerKey;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeySwitchingKey<Scalar: GadgetScalar = u32> {
+    pub(crate) key_switching_key: LweKeyswitchKeyOwned<Scalar>,
+}
+
+impl<Scalar: GadgetScalar> KeySwitchingKey<Scalar> {
+    /// Generates the keyswitching key from the encryption key of `client_key_from` to the one of
+    /// `client_key_to`, with the noise of fresh encryptions under `client_key_to`.
+    ///
+    /// Returns [`GadgetError::UnsupportedCiphertextModulus`] with the modulus of
+    /// `client_key_to` if the ciphertext moduli of the two client keys differ.
+    pub fn new(
+        client_key_from: &ClientKey<Scalar>,
+        client_key_to: &ClientKey<Scalar>,
+        parameters: GadgetKeySwitchingParameters,
+    ) -> Result<Self, GadgetError> {
+        let ciphertext_modulus = client_key_to.ciphertext_modulus();
+        if client_key_from.ciphertext_modulus() != ciphertext_modulus {
+            return Err(GadgetError::UnsupportedCiphertextModulus(
+                ciphertext_modulus.get_custom_modulus(),
+            ));
+        }
+
+        let (input_key, _) = client_key_from.encryption_key();
+        let (output_key, output_noise) = client_key_to.encryption_key();
+        let key_switching_key = GadgetEngine::with_thread_local_mut(|engine| {
+            engine.create_keyswitch_key(
+                &input_key,
+                &output_key,
+                parameters.ks_base_log,
+                parameters.ks_level,
+                output_noise,
+                ciphertext_modulus,
+            )
+        });
+
+        Ok(Self { key_switching_key })
+    }
+}
+
+impl<Scalar: GadgetScalar> ServerKey<Scalar> {
+    /// Re-encrypts `ct` under the destination key of `key_switching_key`, keeping its plaintext
+    /// modulus. Trivial ciphertexts are returned as is.
+    ///
+    /// The keyswitch adds the noise of a fresh encryption to `ct`, raising its noise level by
+    /// [`NoiseLevel::NOMINAL`], so `ct` should be bootstrapped before being keyswitched if it
+    /// went through linear operations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ct` is not encrypted under the source key of `key_switching_key`.
+    pub fn keyswitch_to(
+        &self,
+        ct: &Ciphertext<Scalar>,
+        key_switching_key: &KeySwitchingKey<Scalar>,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        match ct {
+            Ciphertext::Encrypted(lwe, plaintext_modulus, noise_level) => {
+                let mut output = LweCiphertext::new(
+                    Scalar::ZERO,
+                    key_switching_key.key_switching_key.output_lwe_size(),
+                    lwe.ciphertext_modulus(),
+                );
+                keyswitch_lwe_ciphertext(&key_switching_key.key_switching_key, lwe, &mut output);
+                self.stats.record_keyswitch();
+                Ok(Ciphertext::Encrypted(
+                    output,
+                    *plaintext_modulus,
+                    NoiseLevel(noise_level.0 + NoiseLevel::NOMINAL.0),
+                ))
+            }
+            Ciphertext::Trivial(value) => Ok(Ciphertext::Trivial(*value)),
+            Ciphertext::Placeholder => Err(GadgetError::PlaceholderCiphertext),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadget::boolean::BOOLEAN_PARAMETERS;
+    use crate::gadget::gen_keys;
+
+    #[test]
+    fn keyswitch_between_client_keys() -> Result<(), GadgetError> {
+        let (client_key_from, server_key_from) = gen_keys(&BOOLEAN_PARAMETERS);
+        let (client_key_to, server_key_to) = gen_keys(&BOOLEAN_PARAMETERS);
+        let key_switching_key = KeySwitchingKey::new(
+            &client_key_from,
+            &client_key_to,
+            GadgetKeySwitchingParameters::new(
+                BOOLEAN_PARAMETERS.ks_base_log,
+                BOOLEAN_PARAMETERS.ks_level,
+            ),
+        )?;
+
+        for (lhs, rhs) in [(false, false), (false, true), (true, false), (true, true)] {
+            let out = server_key_from
+                .and(&client_key_from.encrypt(lhs), &client_key_from.encrypt(rhs))?;
+            let out = server_key_from.keyswitch_to(&out, &key_switching_key)?;
+            assert_eq!(out.noise_level(), NoiseLevel(2));
+            assert_eq!(client_key_to.decrypt(&out), lhs && rhs);
+
+            // the re-encrypted ciphertext goes through the gates of the destination key
+            let out = server_key_to.not(&out)?;
+            let out = server_key_to.or(&out, &client_key_to.encrypt(false))?;
+            assert_eq!(client_key_to.decrypt(&out), !(lhs && rhs));
+        }
+
+        Ok(())
+    }
+}
@@ -1,21 +1,99 @@
+use crate::core_crypto::commons::generators::DeterministicSeeder;
+use crate::core_crypto::prelude::{new_seeder, ActivatedRandomGenerator};
 use client_key::ClientKey;
+use concrete_csprng::seeders::{Seed, Seeder};
+use engine::GadgetEngine;
+use error::GadgetError;
 use parameters::{GadgetParameters, PLAINTEXT_2_BITS_PARAMETERS, PLAINTEXT_3_BITS_PARAMETERS};
 use server_key::ServerKey;
 
+#[cfg(feature = "p-encoding-async")]
+pub mod asynchronous;
 pub mod boolean;
 pub mod ciphertext;
+pub mod circuit;
 pub mod client_key;
+pub mod constant_time;
+#[cfg(feature = "p-encoding-debug")]
+pub mod debug;
+pub mod diagnostics;
 pub mod encoding;
 pub mod engine;
+pub mod error;
+pub mod high_level;
+pub mod integer;
+#[cfg(feature = "p-encoding-key-encryption")]
+pub mod key_encryption;
+pub mod key_store;
+pub mod key_switching_key;
+pub mod multiparty;
+pub mod ntt;
+pub mod packing;
 pub mod parameters;
+pub mod pipeline;
+pub mod pir;
+pub mod prelude;
+pub mod reparam;
 pub mod server_key;
+pub mod simulator;
+pub mod stats;
+pub mod telemetry;
+pub mod threshold;
+pub mod transcipher;
+pub mod versioning;
+pub mod wopbs;
 
 pub fn gen_keys(parameter_set: &GadgetParameters) -> (ClientKey, ServerKey) {
-    let client_key = ClientKey::new(parameter_set);
+    try_gen_keys(parameter_set).unwrap_or_else(|error| panic!("{error}"))
+}
+
+/// Same as [`gen_keys`], returning an error instead of panicking when `parameter_set` is not
+/// supported, see [`ClientKey::try_new`].
+pub fn try_gen_keys(
+    parameter_set: &GadgetParameters,
+) -> Result<(ClientKey, ServerKey), GadgetError> {
+    let client_key = ClientKey::try_new(parameter_set)?;
     let server_key = ServerKey::new(&client_key);
+    Ok((client_key, server_key))
+}
+
+/// Generates the keys of `parameter_set` deterministically from `seed`, e.g. to reproduce keys in
+/// tests or to derive them from a key derivation function.
+///
+/// The same seed and parameters always give the same keys, the seed must thus be kept as secret
+/// as the client key.
+pub fn gen_keys_from_seed(parameter_set: &GadgetParameters, seed: Seed) -> (ClientKey, ServerKey) {
+    let mut engine = GadgetEngine::new_from_seed(seed);
+    let client_key = engine.create_client_key(parameter_set);
+    let server_key = engine.create_server_key(&client_key);
     (client_key, server_key)
 }
 
+/// Generates one pair of keys per parameter set of `parameter_sets`, all derived from a single
+/// master seed, e.g. for circuits evaluating some gates at 2 bits and others at 3 bits.
+///
+/// See [`gen_keys_multi_from_seed`] to derive the keys from a known master seed.
+pub fn gen_keys_multi(parameter_sets: &[GadgetParameters]) -> Vec<(ClientKey, ServerKey)> {
+    let mut seeder = new_seeder();
+    gen_keys_multi_from_seed(parameter_sets, seeder.seed())
+}
+
+/// Generates one pair of keys per parameter set of `parameter_sets` deterministically from the
+/// master `seed`, see [`gen_keys_from_seed`].
+///
+/// The keys of the `i`-th parameter set only depend on `seed`, `i` and that parameter set: one
+/// master seed is enough to store, or to regenerate, the keys of every parameter set.
+pub fn gen_keys_multi_from_seed(
+    parameter_sets: &[GadgetParameters],
+    seed: Seed,
+) -> Vec<(ClientKey, ServerKey)> {
+    let mut master_seeder = DeterministicSeeder::<ActivatedRandomGenerator>::new(seed);
+    parameter_sets
+        .iter()
+        .map(|parameter_set| gen_keys_from_seed(parameter_set, master_seeder.seed()))
+        .collect()
+}
+
 // #[cfg(test)]
 // mod tests {
 
@@ -0,0 +1,771 @@
+//! Distributed generation of the gadget keys.
+//!
+//! `n` parties jointly generate a [`ServerKey`] whose secret keys no party knows. The LWE secret
+//! key `s` of the server key is the concatenation `s_0 | ... | s_(n-1)` of binary key blocks, one
+//! per party, and so is its GLWE secret key `S`. The generation takes two rounds, every party
+//! broadcasting one serializable message per round:
+//!
+//! 1. [`PartySecretKey::round1`]: each party publishes its share of a GLWE public key under `S`
+//!    and its share of the keyswitching key from `S` to `s`. A share of an encryption under a
+//!    joint key is the mask block `a_i` of the key block of the party along with the partial body
+//!    `<a_i, s_i> + e_i`, to which the party adds the message when it owns the encrypted key
+//!    element. The bodies add up to an encryption under the joint key.
+//! 2. [`PartySecretKey::round2`]: each party encrypts the bits of its LWE key block into GGSW
+//!    ciphertexts under `S`, each GGSW row being a public key encryption of zero to which the
+//!    party adds the gadget matrix scaled by the bit.
+//!
+//! Anyone holding the messages of the first round assembles the joint [`PublicKey`] with
+//! [`public_key`], which encrypts the inputs of the circuits, and the [`ServerKey`] with
+//! [`server_key`] once the messages of the second round are in. The outputs are decrypted with
+//! the [threshold decryption](super::threshold), the shares of the parties being given by
+//! [`PartySecretKey::client_key_share`].
+//!
+//! The parameters describe the joint keys: their LWE and GLWE dimensions must be multiples of the
+//! number of parties, the keys must live on the native torus and use the classic bootstrapping.
+//! The generated keys are noisier than keys generated by a single party: the keyswitching key
+//! sums the noise of every party, and the bootstrapping key is made of public key encryptions,
+//! about `sqrt((n + K) N / 2)` times noisier than secret key encryptions for a GLWE dimension
+//! `K` and a polynomial size `N`, see [`public_key_noise_variance`]. The assembled [`ServerKey`]
+//! carries the [`joint_noise_parameters`], whose noise estimates account for both, so that gates
+//! bootstrap within the noise budget of the generated keys. [`MULTIPARTY_2_PARTIES_PARAMETERS`]
+//! is a parameter set for booleans between two parties.
+//!
+//! # Threat model
+//!
+//! The joint keys are not additively shared: every party knows its own block of the joint key in
+//! the clear. The keys therefore stay secret as long as at least one party keeps its blocks
+//! secret, and a coalition of parties learns the blocks of all its members, leaving an LWE
+//! problem of the dimension of the blocks of the other parties. The dimensions of a single block
+//! must therefore be secure on their own, as for [`MULTIPARTY_2_PARTIES_PARAMETERS`]. The
+//! parties are assumed honest but curious: the messages carry no proof of being well-formed, so
+//! a malicious party can bias the keys, e.g. to make the bootstrap fail or to learn the
+//! decryption of chosen ciphertexts.
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::boolean::engine::WithThreadLocalEngine;
+use crate::core_crypto::algorithms::polynomial_algorithms::polynomial_wrapping_add_mul_assign;
+use crate::core_crypto::algorithms::slice_algorithms::slice_wrapping_dot_product;
+use crate::core_crypto::commons::dispersion::DispersionParameter;
+use crate::core_crypto::prelude::{
+    extract_lwe_sample_from_glwe_ciphertext, keyswitch_lwe_ciphertext,
+    par_convert_standard_lwe_bootstrap_key_to_fourier, CiphertextModulus,
+    ContiguousEntityContainer, DecompositionBaseLog, DecompositionLevelCount, EncryptionKeyChoice,
+    FourierLweBootstrapKey, GlweCiphertext, GlweCiphertextOwned, GlweDimension, LweBootstrapKey,
+    LweCiphertextOwned, LweDimension, LweKeyswitchKey, MonomialDegree, Polynomial, PolynomialSize,
+};
+use crate::gadget::ciphertext::{Ciphertext, NoiseLevel};
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::constant_time::encode_secret;
+use crate::gadget::engine::{GadgetEngine, GadgetScalar};
+use crate::gadget::error::GadgetError;
+use crate::gadget::parameters::{GadgetParameters, PbsBackend, StandardDev};
+use crate::gadget::server_key::{GadgetBootstrappingKey, ServerKey};
+use crate::gadget::threshold::{check_flooding_noise, ClientKeyShare};
+
+/// Parameter set for booleans, i.e. plaintext moduli up to 4, between two parties over a 64 bits
+/// torus.
+///
+/// The key blocks of each party are the LWE key of dimension 742 and the GLWE key of polynomial
+/// size 2048 of the 128 bits shortint parameters for 2 bits messages and carries, estimated at
+/// 133 and 129 bits by [`estimate_lwe_security_bits`]. The generated keys bootstrap linear
+/// combinations of noise level up to 12 modulo 3 and 6 modulo 4 according to the
+/// [`joint_noise_parameters`].
+///
+/// [`estimate_lwe_security_bits`]: crate::gadget::parameters::estimate_lwe_security_bits
+pub const MULTIPARTY_2_PARTIES_PARAMETERS: GadgetParameters = GadgetParameters {
+    lwe_dimension: LweDimension(1484),
+    glwe_dimension: GlweDimension(2),
+    polynomial_size: PolynomialSize(2048),
+    lwe_modular_std_dev: StandardDev(0.000007069849454709433),
+    glwe_modular_std_dev: StandardDev(0.00000000000000029403601535432533),
+    pbs_base_log: DecompositionBaseLog(23),
+    pbs_level: DecompositionLevelCount(1),
+    ks_base_log: DecompositionBaseLog(3),
+    ks_level: DecompositionLevelCount(5),
+    ciphertext_modulus: CiphertextModulus::new_native(),
+    grouping_factor: None,
+    encryption_key_choice: EncryptionKeyChoice::Small,
+    pbs_backend: PbsBackend::Fft,
+};
+
+/// The secret key blocks of party `party` in a distributed key generation.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PartySecretKey<Scalar: GadgetScalar = u32> {
+    party: usize,
+    party_count: usize,
+    parameters: GadgetParameters,
+    /// Client key of the dimensions of a block, holding the blocks of the LWE and GLWE secret
+    /// keys of the party.
+    key_block: ClientKey<Scalar>,
+}
+
+/// The message broadcast by party `party` in the first round, see [`PartySecretKey::round1`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Round1Message<Scalar: GadgetScalar = u32> {
+    party: usize,
+    party_count: usize,
+    /// Mask polynomials of the public key matching the GLWE key block of the party.
+    public_key_mask: Vec<Scalar>,
+    public_key_body: Vec<Scalar>,
+    /// Mask blocks of every keyswitching key ciphertext, matching the LWE key block of the party.
+    keyswitching_key_mask: Vec<Scalar>,
+    keyswitching_key_body: Vec<Scalar>,
+}
+
+/// The message broadcast by party `party` in the second round, see [`PartySecretKey::round2`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Round2Message<Scalar: GadgetScalar = u32> {
+    party: usize,
+    party_count: usize,
+    /// GGSW ciphertexts of the bits of the LWE key block of the party.
+    bootstrapping_key: Vec<Scalar>,
+}
+
+/// GLWE public key under the joint GLWE secret key, see [`public_key`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PublicKey<Scalar: GadgetScalar = u32> {
+    public_key: GlweCiphertextOwned<Scalar>,
+    parameters: GadgetParameters,
+    party_count: usize,
+}
+
+impl<Scalar: GadgetScalar> PartySecretKey<Scalar> {
+    /// Samples the key blocks of party `party` out of `party_count` parties generating the keys
+    /// of `parameters`.
+    pub fn new(
+        party: usize,
+        party_count: usize,
+        parameters: &GadgetParameters,
+    ) -> Result<Self, GadgetError> {
+        check_parameters(party_count, parameters)?;
+        parameters.check_key_parameters::<Scalar>()?;
+        if party >= party_count {
+            return Err(GadgetError::InvalidKeyGeneration(format!(
+                "party {party} out of {party_count} parties"
+            )));
+        }
+
+        let block_parameters = GadgetParameters {
+            lwe_dimension: LweDimension(parameters.lwe_dimension.0 / party_count),
+            glwe_dimension: GlweDimension(parameters.glwe_dimension.0 / party_count),
+            ..*parameters
+        };
+
+        Ok(Self {
+            party,
+            party_count,
+            parameters: *parameters,
+            key_block: ClientKey::new(&block_parameters),
+        })
+    }
+
+    pub fn party(&self) -> usize {
+        self.party
+    }
+
+    pub fn party_count(&self) -> usize {
+        self.party_count
+    }
+
+    /// Returns the message of the party in the first round: its shares of the public key and of
+    /// the keyswitching key.
+    pub fn round1(&self) -> Round1Message<Scalar> {
+        let polynomial_size = self.parameters.polynomial_size;
+        let lwe_block = self.key_block.lwe_secret_key.as_ref();
+        let glwe_block = self.key_block.glwe_secret_key.as_ref();
+
+        // elements of the GLWE key, seen as an LWE key, owned by the party
+        let owned_elements = self.party * glwe_block.len()..(self.party + 1) * glwe_block.len();
+        let input_dimension = self.parameters.glwe_dimension.0 * polynomial_size.0;
+        let ks_base_log = self.parameters.ks_base_log.0;
+        let ks_level = self.parameters.ks_level.0;
+
+        GadgetEngine::with_thread_local_mut(|engine| {
+            let mut public_key_mask = vec![Scalar::ZERO; glwe_block.len()];
+            engine.fill_with_random_uniform(&mut public_key_mask);
+            let mut public_key_body = Polynomial::new(Scalar::ZERO, polynomial_size);
+            engine.fill_with_random_noise(
+                public_key_body.as_mut(),
+                self.parameters.glwe_modular_std_dev,
+            );
+            for (mask, key) in public_key_mask
+                .chunks_exact(polynomial_size.0)
+                .zip(self.key_block.glwe_secret_key.as_polynomial_list().iter())
+            {
+                polynomial_wrapping_add_mul_assign(
+                    &mut public_key_body,
+                    &Polynomial::from_container(mask),
+                    &key,
+                );
+            }
+
+            let mut keyswitching_key_mask =
+                vec![Scalar::ZERO; input_dimension * ks_level * lwe_block.len()];
+            engine.fill_with_random_uniform(&mut keyswitching_key_mask);
+            let mut keyswitching_key_body = vec![Scalar::ZERO; input_dimension * ks_level];
+            engine.fill_with_random_noise(
+                &mut keyswitching_key_body,
+                self.parameters.lwe_modular_std_dev,
+            );
+            for (element, (masks, bodies)) in keyswitching_key_mask
+                .chunks_exact(ks_level * lwe_block.len())
+                .zip(keyswitching_key_body.chunks_exact_mut(ks_level))
+                .enumerate()
+            {
+                let key_element = if owned_elements.contains(&element) {
+                    glwe_block[element - owned_elements.start]
+                } else {
+                    Scalar::ZERO
+                };
+                // the keyswitching key lays the levels out from the last to the first
+                for ((mask, body), level) in masks
+                    .chunks_exact(lwe_block.len())
+                    .zip(bodies.iter_mut())
+                    .zip((1..=ks_level).rev())
+                {
+                    *body = body
+                        .wrapping_add(slice_wrapping_dot_product(mask, lwe_block))
+                        .wrapping_add(
+                            key_element.wrapping_mul(decomposition_factor(ks_base_log, level)),
+                        );
+                }
+            }
+
+            Round1Message {
+                party: self.party,
+                party_count: self.party_count,
+                public_key_mask,
+                public_key_body: public_key_body.into_container(),
+                keyswitching_key_mask,
+                keyswitching_key_body,
+            }
+        })
+    }
+
+    /// Returns the message of the party in the second round: the GGSW encryptions of the bits of
+    /// its LWE key block under the joint public key.
+    pub fn round2(
+        &self,
+        public_key: &PublicKey<Scalar>,
+    ) -> Result<Round2Message<Scalar>, GadgetError> {
+        if public_key.parameters != self.parameters {
+            return Err(GadgetError::InvalidKeyGeneration(
+                "the public key was generated for other parameters".to_string(),
+            ));
+        }
+
+        let polynomial_size = self.parameters.polynomial_size.0;
+        let glwe_size = self.parameters.glwe_dimension.to_glwe_size().0;
+        let pbs_base_log = self.parameters.pbs_base_log.0;
+        let pbs_level = self.parameters.pbs_level.0;
+        let glwe_len = glwe_size * polynomial_size;
+        let level_matrix_len = glwe_size * glwe_len;
+        let lwe_block = self.key_block.lwe_secret_key.as_ref();
+
+        let mut bootstrapping_key =
+            vec![Scalar::ZERO; lwe_block.len() * pbs_level * level_matrix_len];
+        bootstrapping_key
+            .par_chunks_exact_mut(pbs_level * level_matrix_len)
+            .zip(lwe_block.par_iter())
+            .for_each(|(ggsw, key_bit)| {
+                GadgetEngine::with_thread_local_mut(|engine| {
+                    // GGSW(s_j) = Z + s_j G: row `r` of level `l` encrypts zero, with the factor
+                    // s_j q / B^l added to the constant coefficient of its polynomial `r`
+                    for (level_matrix, level) in
+                        ggsw.chunks_exact_mut(level_matrix_len).zip(1..=pbs_level)
+                    {
+                        let factor =
+                            key_bit.wrapping_mul(decomposition_factor(pbs_base_log, level));
+                        for (row, glwe) in level_matrix.chunks_exact_mut(glwe_len).enumerate() {
+                            public_key.encrypt_zero(engine, glwe);
+                            let coefficient = &mut glwe[row * polynomial_size];
+                            *coefficient = coefficient.wrapping_add(factor);
+                        }
+                    }
+                });
+            });
+
+        Ok(Round2Message {
+            party: self.party,
+            party_count: self.party_count,
+            bootstrapping_key,
+        })
+    }
+
+    /// Returns the share of the joint LWE secret key held by the party, for the threshold
+    /// decryption of the ciphertexts under the generated keys.
+    ///
+    /// Returns [`GadgetError::InvalidFloodingNoise`] if `flooding_noise` is below the nominal
+    /// noise of the ciphertexts, see [`threshold`](super::threshold).
+    pub fn client_key_share(
+        &self,
+        flooding_noise: StandardDev,
+    ) -> Result<ClientKeyShare<Scalar>, GadgetError> {
+        let parameters = joint_noise_parameters(&self.parameters, self.party_count);
+        check_flooding_noise(&parameters, self.party_count, flooding_noise)?;
+
+        let lwe_block = self.key_block.lwe_secret_key.as_ref();
+        let mut lwe_key_share = vec![Scalar::ZERO; self.parameters.lwe_dimension.0];
+        lwe_key_share[self.party * lwe_block.len()..(self.party + 1) * lwe_block.len()]
+            .copy_from_slice(lwe_block);
+
+        Ok(ClientKeyShare {
+            party: self.party,
+            party_count: self.party_count,
+            lwe_key_share,
+            flooding_noise,
+            parameters,
+        })
+    }
+}
+
+impl<Scalar: GadgetScalar> PublicKey<Scalar> {
+    /// Encrypts `message` modulo `plaintext_modulus` under the joint LWE secret key, the public
+    /// key encryption under the GLWE key being keyswitched by `server_key`.
+    ///
+    /// The noise of the encryption is within the nominal noise of the
+    /// [`joint_noise_parameters`]. Returns [`GadgetError::UnsupportedPlaintextModulus`] if the
+    /// generated keys cannot bootstrap a nominal ciphertext modulo `plaintext_modulus`, and
+    /// [`GadgetError::InvalidKeyGeneration`] if `server_key` was not generated for the
+    /// parameters of the public key.
+    pub fn encrypt(
+        &self,
+        message: u32,
+        plaintext_modulus: u32,
+        server_key: &ServerKey<Scalar>,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        if server_key.parameters != joint_noise_parameters(&self.parameters, self.party_count) {
+            return Err(GadgetError::InvalidKeyGeneration(
+                "the server key was generated for other parameters than the public key".to_string(),
+            ));
+        }
+        server_key
+            .parameters
+            .check_plaintext_modulus(plaintext_modulus)?;
+        if server_key.max_noise_level(plaintext_modulus) < NoiseLevel::NOMINAL {
+            return Err(GadgetError::UnsupportedPlaintextModulus(plaintext_modulus));
+        }
+
+        let ciphertext_modulus = self.public_key.ciphertext_modulus();
+        let mut glwe = GlweCiphertext::new(
+            Scalar::ZERO,
+            self.parameters.glwe_dimension.to_glwe_size(),
+            self.parameters.polynomial_size,
+            ciphertext_modulus,
+        );
+        GadgetEngine::with_thread_local_mut(|engine| self.encrypt_zero(engine, glwe.as_mut()));
+        let mut body = glwe.get_mut_body();
+        body.as_mut()[0] = body.as_ref()[0].wrapping_add(encode_secret(
+            message,
+            plaintext_modulus,
+            ciphertext_modulus,
+        ));
+
+        let mut big_lwe = LweCiphertextOwned::new(
+            Scalar::ZERO,
+            server_key
+                .key_switching_key
+                .input_key_lwe_dimension()
+                .to_lwe_size(),
+            ciphertext_modulus,
+        );
+        extract_lwe_sample_from_glwe_ciphertext(&glwe, &mut big_lwe, MonomialDegree(0));
+        let mut lwe = LweCiphertextOwned::new(
+            Scalar::ZERO,
+            server_key.key_switching_key.output_lwe_size(),
+            ciphertext_modulus,
+        );
+        keyswitch_lwe_ciphertext(&server_key.key_switching_key, &big_lwe, &mut lwe);
+
+        Ok(Ciphertext::Encrypted(
+            lwe,
+            plaintext_modulus,
+            NoiseLevel::NOMINAL,
+        ))
+    }
+
+    /// Writes a fresh encryption of zero `(u a_r + e_r, u b + e)` into `output`, for a random
+    /// binary polynomial `u` and the public key `(a_r, b)`.
+    fn encrypt_zero(&self, engine: &mut GadgetEngine, output: &mut [Scalar]) {
+        let polynomial_size = self.parameters.polynomial_size;
+
+        let mut u = Polynomial::new(Scalar::ZERO, polynomial_size);
+        engine.fill_with_random_uniform(u.as_mut());
+        for coefficient in u.as_mut().iter_mut() {
+            *coefficient &= Scalar::ONE;
+        }
+
+        engine.fill_with_random_noise(output, self.parameters.glwe_modular_std_dev);
+        for (output, key) in output
+            .chunks_exact_mut(polynomial_size.0)
+            .zip(self.public_key.as_polynomial_list().iter())
+        {
+            polynomial_wrapping_add_mul_assign(&mut Polynomial::from_container(output), &u, &key);
+        }
+    }
+}
+
+/// Assembles the public key under the joint GLWE secret key from the messages of the first
+/// round of every party.
+pub fn public_key<Scalar: GadgetScalar>(
+    parameters: &GadgetParameters,
+    round1: &[Round1Message<Scalar>],
+) -> Result<PublicKey<Scalar>, GadgetError> {
+    let round1 = check_round1(parameters, round1)?;
+    let polynomial_size = parameters.polynomial_size;
+
+    let mut container: Vec<Scalar> = round1
+        .iter()
+        .flat_map(|message| message.public_key_mask.iter().copied())
+        .collect();
+    let mut body = vec![Scalar::ZERO; polynomial_size.0];
+    for message in &round1 {
+        for (body, share) in body.iter_mut().zip(&message.public_key_body) {
+            *body = body.wrapping_add(*share);
+        }
+    }
+    container.extend(body);
+
+    Ok(PublicKey {
+        public_key: GlweCiphertext::from_container(
+            container,
+            polynomial_size,
+            CiphertextModulus::new_native(),
+        ),
+        parameters: *parameters,
+        party_count: round1.len(),
+    })
+}
+
+/// Variance, on the torus, of a public key encryption of zero under the joint GLWE key of
+/// `party_count` parties.
+///
+/// The encryption `(u a_r + e_r, u b + e)` decrypts to `u e_b + e - <e_r, S>`, where the body
+/// noise `e_b` of the public key sums the noise of every party and is multiplied by the random
+/// binary polynomial `u`, and the mask noise `e_r` is multiplied by the binary GLWE key `S`.
+pub fn public_key_noise_variance(parameters: &GadgetParameters, party_count: usize) -> f64 {
+    let big_n = parameters.polynomial_size.0 as f64;
+    let k = parameters.glwe_dimension.0 as f64;
+    (party_count as f64 + k) * big_n / 2. * parameters.glwe_modular_std_dev.get_variance()
+        + parameters.glwe_modular_std_dev.get_variance()
+}
+
+/// Returns `parameters` with the noise of the keys generated by `party_count` parties, for the
+/// noise estimates of [`GadgetParameters`] to hold for these keys: the keyswitching key sums the
+/// LWE noise of every party, and the bootstrapping key is made of public key encryptions whose
+/// noise is given by [`public_key_noise_variance`].
+pub fn joint_noise_parameters(
+    parameters: &GadgetParameters,
+    party_count: usize,
+) -> GadgetParameters {
+    GadgetParameters {
+        lwe_modular_std_dev: StandardDev(
+            parameters.lwe_modular_std_dev.0 * (party_count as f64).sqrt(),
+        ),
+        glwe_modular_std_dev: StandardDev(
+            public_key_noise_variance(parameters, party_count).sqrt(),
+        ),
+        ..*parameters
+    }
+}
+
+/// Assembles the server key of the joint secret keys from the messages of both rounds of every
+/// party. The server key carries the [`joint_noise_parameters`] of `parameters`.
+pub fn server_key<Scalar: GadgetScalar>(
+    parameters: &GadgetParameters,
+    round1: &[Round1Message<Scalar>],
+    round2: &[Round2Message<Scalar>],
+) -> Result<ServerKey<Scalar>, GadgetError> {
+    let round1 = check_round1(parameters, round1)?;
+    let round2 = check_round2(parameters, round2)?;
+    let party_count = round1.len();
+    let ciphertext_modulus = CiphertextModulus::new_native();
+
+    // each keyswitching key ciphertext is the concatenation of the mask blocks of the parties
+    // followed by the sum of their bodies
+    let block_dimension = parameters.lwe_dimension.0 / party_count;
+    let ciphertext_count = round1[0].keyswitching_key_body.len();
+    let output_lwe_size = parameters.lwe_dimension.to_lwe_size();
+    let mut keyswitching_key = Vec::with_capacity(ciphertext_count * output_lwe_size.0);
+    for ciphertext in 0..ciphertext_count {
+        let mut body = Scalar::ZERO;
+        for message in &round1 {
+            keyswitching_key.extend_from_slice(
+                &message.keyswitching_key_mask
+                    [ciphertext * block_dimension..(ciphertext + 1) * block_dimension],
+            );
+            body = body.wrapping_add(message.keyswitching_key_body[ciphertext]);
+        }
+        keyswitching_key.push(body);
+    }
+    let key_switching_key = LweKeyswitchKey::from_container(
+        keyswitching_key,
+        parameters.ks_base_log,
+        parameters.ks_level,
+        output_lwe_size,
+        ciphertext_modulus,
+    );
+
+    let bootstrapping_key = LweBootstrapKey::from_container(
+        round2
+            .iter()
+            .flat_map(|message| message.bootstrapping_key.iter().copied())
+            .collect::<Vec<_>>(),
+        parameters.glwe_dimension.to_glwe_size(),
+        parameters.polynomial_size,
+        parameters.pbs_base_log,
+        parameters.pbs_level,
+        ciphertext_modulus,
+    );
+    let mut fourier_bsk = FourierLweBootstrapKey::new(
+        bootstrapping_key.input_lwe_dimension(),
+        bootstrapping_key.glwe_size(),
+        bootstrapping_key.polynomial_size(),
+        bootstrapping_key.decomposition_base_log(),
+        bootstrapping_key.decomposition_level_count(),
+    );
+    par_convert_standard_lwe_bootstrap_key_to_fourier(&bootstrapping_key, &mut fourier_bsk);
+
+    Ok(ServerKey {
+        bootstrapping_key: GadgetBootstrappingKey::Classic(fourier_bsk),
+        key_switching_key,
+        parameters: joint_noise_parameters(parameters, party_count),
+        packing_key: None,
+        lookup_tables: Default::default(),
+        stats: Default::default(),
+    })
+}
+
+/// Returns the factor `q / B^level` of the gadget decomposition of base `B = 2^base_log`.
+fn decomposition_factor<Scalar: GadgetScalar>(base_log: usize, level: usize) -> Scalar {
+    Scalar::ONE << (Scalar::BITS - base_log * level)
+}
+
+fn check_parameters(party_count: usize, parameters: &GadgetParameters) -> Result<(), GadgetError> {
+    if !parameters.ciphertext_modulus.is_native_modulus() {
+        return Err(GadgetError::InvalidKeyGeneration(
+            "the keys must live on the native torus".to_string(),
+        ));
+    }
+    if parameters.encryption_key_choice == EncryptionKeyChoice::Big {
+        return Err(GadgetError::InvalidKeyGeneration(
+            "encryption under the big LWE key is not supported".to_string(),
+        ));
+    }
+    if parameters.grouping_factor.is_some() {
+        return Err(GadgetError::InvalidKeyGeneration(
+            "multi-bit bootstrapping keys are not supported".to_string(),
+        ));
+    }
+    if parameters.pbs_backend == PbsBackend::Ntt {
+        return Err(GadgetError::InvalidKeyGeneration(
+            "NTT bootstrapping keys are not supported".to_string(),
+        ));
+    }
+    if party_count == 0
+        || parameters.lwe_dimension.0 % party_count != 0
+        || parameters.glwe_dimension.0 % party_count != 0
+    {
+        return Err(GadgetError::InvalidKeyGeneration(format!(
+            "LWE dimension {} and GLWE dimension {} cannot be split across {party_count} parties",
+            parameters.lwe_dimension.0, parameters.glwe_dimension.0
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that `parties` holds the `(party, party_count)` pair of exactly one message of each
+/// party, returning the positions of the messages in party order.
+fn order_parties(
+    parties: impl ExactSizeIterator<Item = (usize, usize)>,
+) -> Result<Vec<usize>, GadgetError> {
+    let message_count = parties.len();
+    let mut positions = vec![None; message_count];
+    for (position, (party, party_count)) in parties.enumerate() {
+        if party_count != message_count {
+            return Err(GadgetError::InvalidKeyGeneration(format!(
+                "{message_count} messages given, {party_count} parties expected"
+            )));
+        }
+        if party >= party_count || positions[party].replace(position).is_some() {
+            return Err(GadgetError::InvalidKeyGeneration(format!(
+                "message of party {party} out of {party_count} parties does not fit the other \
+                messages"
+            )));
+        }
+    }
+    Ok(positions.into_iter().flatten().collect())
+}
+
+fn check_round1<'a, Scalar: GadgetScalar>(
+    parameters: &GadgetParameters,
+    round1: &'a [Round1Message<Scalar>],
+) -> Result<Vec<&'a Round1Message<Scalar>>, GadgetError> {
+    let positions = order_parties(
+        round1
+            .iter()
+            .map(|message| (message.party, message.party_count)),
+    )?;
+    check_parameters(positions.len(), parameters)?;
+
+    let party_count = positions.len();
+    let polynomial_size = parameters.polynomial_size.0;
+    let ks_len = parameters.glwe_dimension.0 * polynomial_size * parameters.ks_level.0;
+    let block_dimension = parameters.lwe_dimension.0 / party_count;
+    let glwe_block_len = parameters.glwe_dimension.0 / party_count * polynomial_size;
+    positions
+        .into_iter()
+        .map(|position| {
+            let message = &round1[position];
+            if message.public_key_mask.len() != glwe_block_len
+                || message.public_key_body.len() != polynomial_size
+                || message.keyswitching_key_mask.len() != ks_len * block_dimension
+                || message.keyswitching_key_body.len() != ks_len
+            {
+                return Err(GadgetError::InvalidKeyGeneration(format!(
+                    "first round message of party {} does not fit the parameters",
+                    message.party
+                )));
+            }
+            Ok(message)
+        })
+        .collect()
+}
+
+fn check_round2<'a, Scalar: GadgetScalar>(
+    parameters: &GadgetParameters,
+    round2: &'a [Round2Message<Scalar>],
+) -> Result<Vec<&'a Round2Message<Scalar>>, GadgetError> {
+    let positions = order_parties(
+        round2
+            .iter()
+            .map(|message| (message.party, message.party_count)),
+    )?;
+    check_parameters(positions.len(), parameters)?;
+
+    let glwe_size = parameters.glwe_dimension.to_glwe_size().0;
+    let ggsw_len = parameters.pbs_level.0 * glwe_size * glwe_size * parameters.polynomial_size.0;
+    let bootstrapping_key_len = parameters.lwe_dimension.0 / positions.len() * ggsw_len;
+    positions
+        .into_iter()
+        .map(|position| {
+            let message = &round2[position];
+            if message.bootstrapping_key.len() != bootstrapping_key_len {
+                return Err(GadgetError::InvalidKeyGeneration(format!(
+                    "second round message of party {} does not fit the parameters",
+                    message.party
+                )));
+            }
+            Ok(message)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadget::parameters::{
+        estimate_lwe_security_bits, PLAINTEXT_2_BITS_PARAMETERS, TARGET_SECURITY_BITS,
+    };
+    use crate::gadget::threshold::combine_shares;
+
+    const PARTY_COUNT: usize = 2;
+
+    // Insecure parameters keeping the test fast, each party holding an LWE key block of
+    // dimension 128 and one GLWE key polynomial. The GLWE noise of 2^-30 keeps the public key
+    // encryptions of the bootstrapping key within the decomposition precision.
+    const TEST_PARAMETERS: GadgetParameters = GadgetParameters {
+        lwe_dimension: LweDimension(256),
+        lwe_modular_std_dev: StandardDev(0.000001),
+        glwe_modular_std_dev: StandardDev(0.0000000009313225746154785),
+        ..PLAINTEXT_2_BITS_PARAMETERS
+    };
+
+    #[test]
+    fn distributed_key_generation() -> Result<(), GadgetError> {
+        let parties = (0..PARTY_COUNT)
+            .map(|party| PartySecretKey::<u32>::new(party, PARTY_COUNT, &TEST_PARAMETERS))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // the messages go through serialization as when broadcast
+        let round1: Vec<Round1Message> = parties
+            .iter()
+            .map(|party| bincode::deserialize(&bincode::serialize(&party.round1()).unwrap()))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let public_key = public_key(&TEST_PARAMETERS, &round1)?;
+        let round2 = parties
+            .iter()
+            .map(|party| party.round2(&public_key))
+            .collect::<Result<Vec<_>, _>>()?;
+        let server_key = server_key(&TEST_PARAMETERS, &round1, &round2)?;
+
+        let shares = parties
+            .iter()
+            .map(|party| party.client_key_share(StandardDev(2f64.powi(-8))))
+            .collect::<Result<Vec<_>, _>>()?;
+        let decrypt = |ct: &Ciphertext| {
+            let decryption_shares = shares
+                .iter()
+                .map(|share| share.partial_decrypt(ct))
+                .collect::<Result<Vec<_>, _>>()?;
+            combine_shares(ct, &decryption_shares)
+        };
+
+        // the boolean layer encodes false as 1 and true as 2
+        for (lhs, rhs) in [(false, false), (false, true), (true, false), (true, true)] {
+            let lhs_ct = public_key.encrypt(lhs as u32 + 1, 3, &server_key)?;
+            let rhs_ct = public_key.encrypt(rhs as u32 + 1, 3, &server_key)?;
+            assert_eq!(decrypt(&lhs_ct)?, lhs as u32 + 1);
+
+            let out = server_key.and(&lhs_ct, &rhs_ct)?;
+            assert_eq!(decrypt(&out)?, (lhs && rhs) as u32 + 1);
+        }
+
+        assert!(matches!(
+            super::server_key(&TEST_PARAMETERS, &round1[1..], &round2),
+            Err(GadgetError::InvalidKeyGeneration(_))
+        ));
+        assert!(matches!(
+            PartySecretKey::<u32>::new(0, 3, &TEST_PARAMETERS),
+            Err(GadgetError::InvalidKeyGeneration(_))
+        ));
+        assert!(matches!(
+            public_key.encrypt(1, 64, &server_key),
+            Err(GadgetError::UnsupportedPlaintextModulus(64))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn two_parties_parameters() -> Result<(), GadgetError> {
+        let parameters = MULTIPARTY_2_PARTIES_PARAMETERS;
+
+        // the blocks of a single party must be secure on their own
+        let lwe_block = parameters.lwe_dimension.0 / 2;
+        let glwe_block = parameters.glwe_dimension.0 / 2 * parameters.polynomial_size.0;
+        assert!(
+            estimate_lwe_security_bits(lwe_block, parameters.lwe_modular_std_dev, 64)
+                >= TARGET_SECURITY_BITS
+        );
+        assert!(
+            estimate_lwe_security_bits(glwe_block, parameters.glwe_modular_std_dev, 64)
+                >= TARGET_SECURITY_BITS
+        );
+
+        let joint_parameters = joint_noise_parameters(&parameters, 2);
+        assert_eq!(joint_parameters.max_noise_level(3), NoiseLevel(12));
+        assert_eq!(joint_parameters.max_noise_level(4), NoiseLevel(6));
+
+        PartySecretKey::<u64>::new(0, 2, &parameters)?;
+        assert!(matches!(
+            PartySecretKey::<u32>::new(0, 2, &parameters),
+            Err(GadgetError::NoiseBelowTorusPrecision(_))
+        ));
+
+        Ok(())
+    }
+}
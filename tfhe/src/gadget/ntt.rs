@@ -0,0 +1,653 @@
+//! Blind rotation over a prime modulus with a number theoretic transform, the bootstrapping
+//! backend of [`PbsBackend::Ntt`](super::parameters::PbsBackend::Ntt).
+//!
+//! The GGSW ciphertexts of an [`NttBootstrapKey`] are encrypted modulo the prime
+//! [`NTT_MODULUS`] `P = 2^64 - 2^32 + 1`, whose multiplicative group holds the `2N`-th roots of
+//! unity of every polynomial size up to `2^31`: the negacyclic products of the external products
+//! are computed exactly in the NTT domain, where the key is stored. The accumulator is lifted from
+//! the torus of the ciphertexts to `Z/PZ` before the blind rotation and rounded back after it,
+//! the modulus switch of the input, the sample extraction and the keyswitch being the ones of the
+//! FFT backend.
+
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core_crypto::commons::dispersion::StandardDev;
+use crate::core_crypto::commons::parameters::{
+    DecompositionBaseLog, DecompositionLevelCount, GlweSize, LutCountLog, LweDimension,
+    ModulusSwitchOffset, PolynomialSize,
+};
+use crate::core_crypto::entities::{GlweCiphertext, GlweSecretKey, LweCiphertext, LweSecretKey};
+use crate::core_crypto::fft_impl::common::fast_pbs_modulus_switch;
+use crate::core_crypto::prelude::{
+    ActivatedRandomGenerator, Container, ContainerMut, EncryptionRandomGenerator,
+};
+use crate::gadget::engine::{modulus_bits, GadgetScalar};
+
+/// The prime modulus of the NTT bootstrapping keys, `2^64 - 2^32 + 1`.
+pub const NTT_MODULUS: u64 = 0xFFFF_FFFF_0000_0001;
+
+/// `2^64` modulo [`NTT_MODULUS`].
+const EPSILON: u64 = 0xFFFF_FFFF;
+
+/// Generator of the multiplicative group modulo [`NTT_MODULUS`].
+const GENERATOR: u64 = 7;
+
+fn add(lhs: u64, rhs: u64) -> u64 {
+    let (sum, carry) = lhs.overflowing_add(rhs);
+    if carry || sum >= NTT_MODULUS {
+        sum.wrapping_sub(NTT_MODULUS)
+    } else {
+        sum
+    }
+}
+
+fn sub(lhs: u64, rhs: u64) -> u64 {
+    let (difference, borrow) = lhs.overflowing_sub(rhs);
+    if borrow {
+        difference.wrapping_add(NTT_MODULUS)
+    } else {
+        difference
+    }
+}
+
+fn neg(value: u64) -> u64 {
+    sub(0, value)
+}
+
+/// Reduces `value` modulo [`NTT_MODULUS`], using `2^64 = 2^32 - 1` and `2^96 = -1`.
+fn reduce(value: u128) -> u64 {
+    let low = value as u64;
+    let high = (value >> 64) as u64;
+    let (high_high, high_low) = (high >> 32, high & EPSILON);
+
+    let (mut result, borrow) = low.overflowing_sub(high_high);
+    if borrow {
+        result = result.wrapping_sub(EPSILON);
+    }
+    let (result, carry) = result.overflowing_add(high_low * EPSILON);
+    let result = if carry {
+        result.wrapping_add(EPSILON)
+    } else {
+        result
+    };
+    if result >= NTT_MODULUS {
+        result - NTT_MODULUS
+    } else {
+        result
+    }
+}
+
+fn mul(lhs: u64, rhs: u64) -> u64 {
+    reduce(lhs as u128 * rhs as u128)
+}
+
+fn pow(mut base: u64, mut exponent: u64) -> u64 {
+    let mut result = 1;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul(result, base);
+        }
+        base = mul(base, base);
+        exponent >>= 1;
+    }
+    result
+}
+
+fn inv(value: u64) -> u64 {
+    pow(value, NTT_MODULUS - 2)
+}
+
+/// Maps a signed value to its representative modulo [`NTT_MODULUS`].
+fn from_signed(value: i64) -> u64 {
+    (value as i128).rem_euclid(NTT_MODULUS as i128) as u64
+}
+
+/// Twiddle factors of the negacyclic NTT of one polynomial size.
+///
+/// The negacyclic transform of `a` is the cyclic transform of `a_i * psi^i`, `psi` being a
+/// primitive `2N`-th root of unity, with the roots `omega = psi^2`.
+#[derive(Clone, Debug)]
+struct NttPlan {
+    twists: Vec<u64>,
+    /// `psi^-i / N`, undoing the twist and scaling the inverse cyclic transform.
+    inverse_twists: Vec<u64>,
+    /// `omega^i` for `i < N / 2`.
+    roots: Vec<u64>,
+    inverse_roots: Vec<u64>,
+}
+
+impl NttPlan {
+    fn new(polynomial_size: PolynomialSize) -> Self {
+        let n = polynomial_size.0;
+        assert!(
+            n.is_power_of_two() && (2..=1 << 31).contains(&n),
+            "the NTT supports polynomial sizes from 2 to 2^31, got {n}"
+        );
+
+        let psi = pow(GENERATOR, (NTT_MODULUS - 1) / (2 * n as u64));
+        let omega = mul(psi, psi);
+        let powers = |root: u64, count: usize| {
+            std::iter::successors(Some(1), move |&power| Some(mul(power, root)))
+                .take(count)
+                .collect::<Vec<_>>()
+        };
+
+        let inverse_n = inv(n as u64);
+        Self {
+            twists: powers(psi, n),
+            inverse_twists: powers(inv(psi), n)
+                .into_iter()
+                .map(|twist| mul(twist, inverse_n))
+                .collect(),
+            roots: powers(omega, n / 2),
+            inverse_roots: powers(inv(omega), n / 2),
+        }
+    }
+
+    fn forward(&self, data: &mut [u64]) {
+        for (value, &twist) in data.iter_mut().zip(&self.twists) {
+            *value = mul(*value, twist);
+        }
+        cyclic_transform(data, &self.roots);
+    }
+
+    fn backward(&self, data: &mut [u64]) {
+        cyclic_transform(data, &self.inverse_roots);
+        for (value, &twist) in data.iter_mut().zip(&self.inverse_twists) {
+            *value = mul(*value, twist);
+        }
+    }
+}
+
+/// In place iterative radix-2 cyclic transform, `roots` holding the first `N / 2` powers of the
+/// root of unity of order `N`.
+fn cyclic_transform(data: &mut [u64], roots: &[u64]) {
+    let n = data.len();
+    let log_n = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - log_n);
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let half = len / 2;
+        let stride = n / len;
+        for chunk in data.chunks_exact_mut(len) {
+            let (low, high) = chunk.split_at_mut(half);
+            for (k, (u, v)) in low.iter_mut().zip(high.iter_mut()).enumerate() {
+                let t = mul(*v, roots[k * stride]);
+                *v = sub(*u, t);
+                *u = add(*u, t);
+            }
+        }
+        len *= 2;
+    }
+}
+
+/// Writes `input * X^degree` modulo `X^N + 1` to `output`, for `degree < 2N`.
+fn monomial_mul(output: &mut [u64], input: &[u64], degree: usize) {
+    let n = input.len();
+    for (i, &value) in input.iter().enumerate() {
+        let position = i + degree;
+        let (position, negate) = (position % n, (position / n) % 2 == 1);
+        output[position] = if negate { neg(value) } else { value };
+    }
+}
+
+/// A bootstrapping key whose GGSW ciphertexts are encrypted modulo [`NTT_MODULUS`], in the NTT
+/// domain, see the [module](self) documentation.
+///
+/// The GGSW ciphertext of each LWE secret key bit holds, for each decomposition level from the
+/// most significant one, `k + 1` GLWE ciphertexts of `k + 1` polynomials.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NttBootstrapKey {
+    data: Vec<u64>,
+    input_lwe_dimension: LweDimension,
+    glwe_size: GlweSize,
+    polynomial_size: PolynomialSize,
+    decomposition_base_log: DecompositionBaseLog,
+    decomposition_level_count: DecompositionLevelCount,
+    #[serde(skip)]
+    plan: OnceLock<NttPlan>,
+}
+
+/// Number of `u64` of the NTT GGSW ciphertexts of the given shape.
+pub(crate) fn ntt_ggsw_ciphertext_size(
+    glwe_size: GlweSize,
+    polynomial_size: PolynomialSize,
+    decomposition_level_count: DecompositionLevelCount,
+) -> usize {
+    decomposition_level_count.0 * glwe_size.0 * glwe_size.0 * polynomial_size.0
+}
+
+impl NttBootstrapKey {
+    /// Encrypts the bits of `lwe_secret_key` under `glwe_secret_key` modulo [`NTT_MODULUS`].
+    ///
+    /// The noise is drawn from the distribution of standard deviation `noise_std_dev` on the
+    /// native torus of `u64` and mapped to the same fraction of `NTT_MODULUS`, which differs from
+    /// `2^64` by a factor `1 - 2^-32`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the decomposition does not fit in 63 bits.
+    pub(crate) fn generate<Scalar, LweKeyCont, GlweKeyCont>(
+        lwe_secret_key: &LweSecretKey<LweKeyCont>,
+        glwe_secret_key: &GlweSecretKey<GlweKeyCont>,
+        decomposition_base_log: DecompositionBaseLog,
+        decomposition_level_count: DecompositionLevelCount,
+        noise_std_dev: StandardDev,
+        generator: &mut EncryptionRandomGenerator<ActivatedRandomGenerator>,
+    ) -> Self
+    where
+        Scalar: GadgetScalar,
+        LweKeyCont: Container<Element = Scalar>,
+        GlweKeyCont: Container<Element = Scalar>,
+    {
+        assert!(decomposition_base_log.0 * decomposition_level_count.0 < 64);
+
+        let polynomial_size = glwe_secret_key.polynomial_size();
+        let glwe_size = glwe_secret_key.glwe_dimension().to_glwe_size();
+        let n = polynomial_size.0;
+        let plan = NttPlan::new(polynomial_size);
+
+        let key_polynomials: Vec<Vec<u64>> = glwe_secret_key
+            .as_ref()
+            .chunks_exact(n)
+            .map(|polynomial| {
+                let mut polynomial: Vec<u64> = polynomial
+                    .iter()
+                    .map(|&bit| if bit == Scalar::ZERO { 0 } else { 1 })
+                    .collect();
+                plan.forward(&mut polynomial);
+                polynomial
+            })
+            .collect();
+
+        let mut data = Vec::with_capacity(
+            lwe_secret_key.as_ref().len()
+                * ntt_ggsw_ciphertext_size(glwe_size, polynomial_size, decomposition_level_count),
+        );
+        let mut glwe = vec![0u64; glwe_size.0 * n];
+        let mut body = vec![0u64; n];
+        let mut noise = vec![0u64; n];
+        for &bit in lwe_secret_key.as_ref() {
+            let bit = bit != Scalar::ZERO;
+            for level in 1..=decomposition_level_count.0 {
+                let base_power = 1u128 << (decomposition_base_log.0 * level);
+                let gadget = ((NTT_MODULUS as u128 + base_power / 2) / base_power) as u64;
+                for row in 0..glwe_size.0 {
+                    let (mask, body_ntt) = glwe.split_at_mut((glwe_size.0 - 1) * n);
+                    fill_uniform(mask, generator);
+
+                    // body = sum of mask_i * s_i + noise
+                    body_ntt.fill(0);
+                    for (mask_polynomial, key_polynomial) in
+                        mask.chunks_exact_mut(n).zip(&key_polynomials)
+                    {
+                        plan.forward(mask_polynomial);
+                        for (body, (&mask, &key)) in body_ntt
+                            .iter_mut()
+                            .zip(mask_polynomial.iter().zip(key_polynomial))
+                        {
+                            *body = add(*body, mul(mask, key));
+                        }
+                    }
+                    body.copy_from_slice(body_ntt);
+                    plan.backward(&mut body);
+                    generator.fill_slice_with_random_noise(&mut noise, noise_std_dev);
+                    for (body, &noise) in body.iter_mut().zip(&noise) {
+                        *body = add(*body, from_signed(noise as i64));
+                    }
+
+                    // the constant bit * g is added to the polynomial of the row, the NTT of the
+                    // constant polynomial c being c everywhere
+                    if bit && row + 1 < glwe_size.0 {
+                        mask[row * n..(row + 1) * n]
+                            .iter_mut()
+                            .for_each(|value| *value = add(*value, gadget));
+                    } else if bit {
+                        body[0] = add(body[0], gadget);
+                    }
+                    plan.forward(&mut body);
+                    body_ntt.copy_from_slice(&body);
+
+                    data.extend_from_slice(&glwe);
+                }
+            }
+        }
+
+        let key = Self {
+            data,
+            input_lwe_dimension: lwe_secret_key.lwe_dimension(),
+            glwe_size,
+            polynomial_size,
+            decomposition_base_log,
+            decomposition_level_count,
+            plan: OnceLock::new(),
+        };
+        let _ = key.plan.set(plan);
+        key
+    }
+
+    pub fn input_lwe_dimension(&self) -> LweDimension {
+        self.input_lwe_dimension
+    }
+
+    pub fn output_lwe_dimension(&self) -> LweDimension {
+        LweDimension((self.glwe_size.0 - 1) * self.polynomial_size.0)
+    }
+
+    pub fn glwe_size(&self) -> GlweSize {
+        self.glwe_size
+    }
+
+    pub fn polynomial_size(&self) -> PolynomialSize {
+        self.polynomial_size
+    }
+
+    pub fn decomposition_base_log(&self) -> DecompositionBaseLog {
+        self.decomposition_base_log
+    }
+
+    pub fn decomposition_level_count(&self) -> DecompositionLevelCount {
+        self.decomposition_level_count
+    }
+
+    /// The GGSW ciphertexts of the key, in the NTT domain.
+    pub fn data(&self) -> &[u64] {
+        &self.data
+    }
+
+    fn plan(&self) -> &NttPlan {
+        self.plan.get_or_init(|| NttPlan::new(self.polynomial_size))
+    }
+
+    /// Blind rotates `acc` by the phase of `input`, modulus switched to `2N`, as
+    /// [`blind_rotate_assign_mem_optimized`](crate::core_crypto::prelude::blind_rotate_assign_mem_optimized)
+    /// does with a Fourier key.
+    pub(crate) fn blind_rotate_assign<Scalar, InputCont, AccCont>(
+        &self,
+        input: &LweCiphertext<InputCont>,
+        acc: &mut GlweCiphertext<AccCont>,
+    ) where
+        Scalar: GadgetScalar,
+        InputCont: Container<Element = Scalar>,
+        AccCont: ContainerMut<Element = Scalar>,
+    {
+        let plan = self.plan();
+        let n = self.polynomial_size.0;
+        let glwe_len = self.glwe_size.0 * n;
+        let ggsw_len = ntt_ggsw_ciphertext_size(
+            self.glwe_size,
+            self.polynomial_size,
+            self.decomposition_level_count,
+        );
+        let modulus_switch = |value: Scalar| {
+            fast_pbs_modulus_switch(
+                value,
+                self.polynomial_size,
+                ModulusSwitchOffset(0),
+                LutCountLog(0),
+            ) % (2 * n)
+        };
+
+        let lifted: Vec<u64> = acc.as_ref().iter().map(|&value| lift(value)).collect();
+        let (body, mask) = input.as_ref().split_last().unwrap();
+        let mut rotated = vec![0u64; glwe_len];
+        let body_degree = (2 * n - modulus_switch(*body)) % (2 * n);
+        for (rotated, lifted) in rotated.chunks_exact_mut(n).zip(lifted.chunks_exact(n)) {
+            monomial_mul(rotated, lifted, body_degree);
+        }
+
+        let mut difference = vec![0u64; glwe_len];
+        let mut scratch = ExternalProductScratch::new(
+            self.glwe_size,
+            self.polynomial_size,
+            self.decomposition_level_count,
+        );
+        for (&mask_element, ggsw) in mask.iter().zip(self.data.chunks_exact(ggsw_len)) {
+            if mask_element == Scalar::ZERO {
+                continue;
+            }
+
+            // cmux: rotated += ggsw x (rotated * X^a - rotated)
+            let degree = modulus_switch(mask_element);
+            for (difference, rotated) in difference.chunks_exact_mut(n).zip(rotated.chunks_exact(n))
+            {
+                monomial_mul(difference, rotated, degree);
+                for (difference, &rotated) in difference.iter_mut().zip(rotated) {
+                    *difference = sub(*difference, rotated);
+                }
+            }
+            self.add_external_product_assign(plan, &mut rotated, ggsw, &difference, &mut scratch);
+        }
+
+        let ciphertext_modulus = acc.ciphertext_modulus();
+        let bits = modulus_bits(ciphertext_modulus);
+        let scaling = ciphertext_modulus.get_power_of_two_scaling_to_native_torus();
+        for (value, &rotated) in acc.as_mut().iter_mut().zip(&rotated) {
+            *value = lower::<Scalar>(rotated, bits).wrapping_mul(scaling);
+        }
+    }
+
+    /// Adds the external product of `ggsw` and `glwe` to `output`.
+    fn add_external_product_assign(
+        &self,
+        plan: &NttPlan,
+        output: &mut [u64],
+        ggsw: &[u64],
+        glwe: &[u64],
+        scratch: &mut ExternalProductScratch,
+    ) {
+        let n = self.polynomial_size.0;
+        let glwe_size = self.glwe_size.0;
+        let base_log = self.decomposition_base_log.0;
+        let level_count = self.decomposition_level_count.0;
+
+        scratch.accumulator.fill(0);
+        for (row, polynomial) in glwe.chunks_exact(n).enumerate() {
+            for (i, &coefficient) in polynomial.iter().enumerate() {
+                decompose(coefficient, base_log, level_count, |level, digit| {
+                    scratch.digits[level * n + i] = digit;
+                });
+            }
+
+            for (level, digits) in scratch.digits.chunks_exact_mut(n).enumerate() {
+                plan.forward(digits);
+                let glwe_offset = (level * glwe_size + row) * glwe_size * n;
+                let key_glwe = &ggsw[glwe_offset..glwe_offset + glwe_size * n];
+                for (accumulator, key_polynomial) in scratch
+                    .accumulator
+                    .chunks_exact_mut(n)
+                    .zip(key_glwe.chunks_exact(n))
+                {
+                    for (accumulator, (&digit, &key)) in accumulator
+                        .iter_mut()
+                        .zip(digits.iter().zip(key_polynomial))
+                    {
+                        *accumulator = add(*accumulator, mul(digit, key));
+                    }
+                }
+            }
+        }
+
+        for (output, accumulator) in output
+            .chunks_exact_mut(n)
+            .zip(scratch.accumulator.chunks_exact_mut(n))
+        {
+            plan.backward(accumulator);
+            for (output, &accumulator) in output.iter_mut().zip(accumulator.iter()) {
+                *output = add(*output, accumulator);
+            }
+        }
+    }
+}
+
+/// Buffers of the external products of a blind rotation.
+struct ExternalProductScratch {
+    /// The decomposition of one polynomial, level after level.
+    digits: Vec<u64>,
+    /// The output GLWE ciphertext, in the NTT domain.
+    accumulator: Vec<u64>,
+}
+
+impl ExternalProductScratch {
+    fn new(
+        glwe_size: GlweSize,
+        polynomial_size: PolynomialSize,
+        decomposition_level_count: DecompositionLevelCount,
+    ) -> Self {
+        Self {
+            digits: vec![0; decomposition_level_count.0 * polynomial_size.0],
+            accumulator: vec![0; glwe_size.0 * polynomial_size.0],
+        }
+    }
+}
+
+/// Calls `digit` with the signed digits, modulo [`NTT_MODULUS`], of the closest approximation of
+/// `value` as `sum of d_j * P / B^j` for `j` from 1 to `level_count`, the level `j - 1` being
+/// passed with `d_j`.
+fn decompose(value: u64, base_log: usize, level_count: usize, mut digit: impl FnMut(usize, u64)) {
+    let total_bits = base_log * level_count;
+    let modulus = NTT_MODULUS as u128;
+    // round(value * B^level_count / P), a final carry standing for P itself
+    let mut scaled = (((value as u128) << total_bits) + modulus / 2) / modulus;
+
+    let base = 1u128 << base_log;
+    let mut carry = 0;
+    for level in (0..level_count).rev() {
+        let mut signed = (scaled & (base - 1)) as i64 + carry;
+        scaled >>= base_log;
+        carry = 0;
+        if signed >= (base / 2) as i64 {
+            signed -= base as i64;
+            carry = 1;
+        }
+        digit(level, from_signed(signed));
+    }
+}
+
+/// Lifts a torus value to the closest element of `Z/PZ`.
+fn lift<Scalar: GadgetScalar>(value: Scalar) -> u64 {
+    let value: u128 = value.cast_into();
+    ((value * NTT_MODULUS as u128 + (1 << (Scalar::BITS - 1))) >> Scalar::BITS) as u64
+}
+
+/// Rounds an element of `Z/PZ` to the closest torus value modulo `2^bits`, in the low bits of
+/// `Scalar`.
+fn lower<Scalar: GadgetScalar>(value: u64, bits: usize) -> Scalar {
+    let modulus = NTT_MODULUS as u128;
+    let rounded = (((value as u128) << bits) + modulus / 2) / modulus;
+    Scalar::cast_from(rounded % (1 << bits))
+}
+
+/// Fills `output` with uniform values modulo [`NTT_MODULUS`], rejecting the values above it.
+fn fill_uniform(
+    output: &mut [u64],
+    generator: &mut EncryptionRandomGenerator<ActivatedRandomGenerator>,
+) {
+    generator.fill_slice_with_random_mask(output);
+    for value in output.iter_mut() {
+        while *value >= NTT_MODULUS {
+            let mut resampled = [0u64];
+            generator.fill_slice_with_random_mask(&mut resampled);
+            *value = resampled[0];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn field_operations_match_integer_arithmetic() {
+        let mut rng = rand::thread_rng();
+        let modulus = NTT_MODULUS as u128;
+        let edges = [
+            0,
+            1,
+            2,
+            EPSILON,
+            EPSILON + 1,
+            NTT_MODULUS - 2,
+            NTT_MODULUS - 1,
+        ];
+        let values = edges
+            .into_iter()
+            .chain((0..1000).map(|_| rng.gen_range(0..NTT_MODULUS)))
+            .collect::<Vec<_>>();
+        for (&lhs, &rhs) in values.iter().zip(values.iter().rev()) {
+            let (wide_lhs, wide_rhs) = (lhs as u128, rhs as u128);
+            assert_eq!(add(lhs, rhs) as u128, (wide_lhs + wide_rhs) % modulus);
+            assert_eq!(
+                sub(lhs, rhs) as u128,
+                (wide_lhs + modulus - wide_rhs) % modulus
+            );
+            assert_eq!(mul(lhs, rhs) as u128, wide_lhs * wide_rhs % modulus);
+        }
+        for value in [u128::MAX, rng.gen(), modulus * modulus - 1] {
+            assert_eq!(reduce(value) as u128, value % modulus);
+        }
+        assert_eq!(mul(inv(12345), 12345), 1);
+    }
+
+    #[test]
+    fn ntt_computes_negacyclic_products() {
+        let mut rng = rand::thread_rng();
+        for n in [2, 16, 1024] {
+            let plan = NttPlan::new(PolynomialSize(n));
+            let lhs: Vec<u64> = (0..n).map(|_| rng.gen_range(0..NTT_MODULUS)).collect();
+            let rhs: Vec<u64> = (0..n).map(|_| rng.gen_range(0..NTT_MODULUS)).collect();
+
+            let mut expected = vec![0; n];
+            for i in 0..n {
+                for j in 0..n {
+                    let product = mul(lhs[i], rhs[j]);
+                    if i + j < n {
+                        expected[i + j] = add(expected[i + j], product);
+                    } else {
+                        expected[i + j - n] = sub(expected[i + j - n], product);
+                    }
+                }
+            }
+
+            let (mut lhs_ntt, mut rhs_ntt) = (lhs.clone(), rhs.clone());
+            plan.forward(&mut lhs_ntt);
+            plan.forward(&mut rhs_ntt);
+            let mut product: Vec<u64> = lhs_ntt
+                .iter()
+                .zip(&rhs_ntt)
+                .map(|(&l, &r)| mul(l, r))
+                .collect();
+            plan.backward(&mut product);
+            assert_eq!(product, expected);
+
+            plan.backward(&mut lhs_ntt);
+            assert_eq!(lhs_ntt, lhs);
+        }
+    }
+
+    #[test]
+    fn decomposition_approximates_coefficients() {
+        let mut rng = rand::thread_rng();
+        let (base_log, level_count) = (7, 3);
+        for _ in 0..1000 {
+            let value = rng.gen_range(0..NTT_MODULUS);
+            let mut recomposed = 0;
+            decompose(value, base_log, level_count, |level, digit| {
+                let base_power = 1u128 << (base_log * (level + 1));
+                let gadget = ((NTT_MODULUS as u128 + base_power / 2) / base_power) as u64;
+                recomposed = add(recomposed, mul(digit, gadget));
+            });
+            let error = sub(recomposed, value).min(sub(value, recomposed));
+            assert!(error < NTT_MODULUS >> (base_log * level_count - 1));
+        }
+    }
+}
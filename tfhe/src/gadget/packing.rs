@@ -0,0 +1,214 @@
+//! Packing of gate outputs in GLWE ciphertexts.
+//!
+//! Every gate output is an LWE ciphertext of `n + 1` scalars carrying a single message, which
+//! makes returning many outputs to the client costly. [`ServerKey::pack`] keyswitches up to `N`
+//! of them into the coefficients of a single GLWE ciphertext of `(k + 1) * N` scalars with the
+//! packing keyswitching key of [`ServerKey::enable_packing`], and
+//! [`ClientKey::decrypt_packed`] decrypts all of them at once.
+//!
+//! The packing adds the noise of one keyswitch to the outputs, it is meant to be the last step
+//! of a computation: the packed outputs are not bootstrapped anymore.
+
+use crate::core_crypto::prelude::{
+    decrypt_glwe_ciphertext, keyswitch_lwe_ciphertext_list_and_pack_in_glwe_ciphertext,
+    ContiguousEntityContainer, ContiguousEntityContainerMut, GlweCiphertextCount,
+    GlweCiphertextListOwned, LweCiphertextCount, LweCiphertextListOwned, PlaintextCount,
+    PlaintextList,
+};
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::constant_time::decode_secret;
+use crate::gadget::engine::GadgetScalar;
+use crate::gadget::error::GadgetError;
+use crate::gadget::server_key::ServerKey;
+use serde::{Deserialize, Serialize};
+
+/// Messages modulo a common plaintext modulus packed in the coefficients of GLWE ciphertexts,
+/// see [`ServerKey::pack`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PackedCiphertext<Scalar: GadgetScalar = u32> {
+    pub(crate) glwe_list: GlweCiphertextListOwned<Scalar>,
+    pub(crate) plaintext_modulus: u32,
+    pub(crate) count: usize,
+}
+
+impl<Scalar: GadgetScalar> PackedCiphertext<Scalar> {
+    /// Returns the GLWE ciphertexts, the `i`-th message being the coefficient `i % N` of the
+    /// ciphertext `i / N`.
+    pub fn glwe_list(&self) -> &GlweCiphertextListOwned<Scalar> {
+        &self.glwe_list
+    }
+
+    /// Returns the plaintext modulus of the packed messages.
+    pub fn plaintext_modulus(&self) -> u32 {
+        self.plaintext_modulus
+    }
+
+    /// Returns the number of packed messages.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<Scalar: GadgetScalar> ServerKey<Scalar> {
+    /// Packs `cts` in GLWE ciphertexts of `N` messages each, see the [module](self)
+    /// documentation.
+    ///
+    /// The encrypted ciphertexts must share their plaintext modulus, [`Ciphertext::Trivial`]
+    /// inputs are encrypted trivially under it (modulo 2 if every input is trivial).
+    ///
+    /// Returns [`GadgetError::MissingPackingKey`] if [`ServerKey::enable_packing`] was not
+    /// called on this key.
+    pub fn pack(
+        &self,
+        cts: &[Ciphertext<Scalar>],
+    ) -> Result<PackedCiphertext<Scalar>, GadgetError> {
+        let packing_key = self
+            .packing_key
+            .as_ref()
+            .ok_or(GadgetError::MissingPackingKey)?;
+
+        let mut plaintext_modulus = None;
+        for (pin, ct) in cts.iter().enumerate() {
+            match ct {
+                Ciphertext::Encrypted(_, p, _) => match plaintext_modulus {
+                    None => plaintext_modulus = Some(*p),
+                    Some(expected) if expected != *p => {
+                        return Err(GadgetError::PlaintextModulusMismatch {
+                            pin,
+                            expected,
+                            actual: *p,
+                        });
+                    }
+                    Some(_) => {}
+                },
+                Ciphertext::Trivial(_) => {}
+                Ciphertext::Placeholder => return Err(GadgetError::PlaceholderCiphertext),
+            }
+        }
+        let plaintext_modulus = plaintext_modulus.unwrap_or(2);
+
+        let ciphertext_modulus = self.ciphertext_modulus();
+        let polynomial_size = packing_key.output_polynomial_size();
+        let mut glwe_list = GlweCiphertextListOwned::new(
+            Scalar::ZERO,
+            packing_key.output_glwe_size(),
+            polynomial_size,
+            GlweCiphertextCount((cts.len() + polynomial_size.0 - 1) / polynomial_size.0),
+            ciphertext_modulus,
+        );
+
+        for (chunk, mut glwe) in cts.chunks(polynomial_size.0).zip(glwe_list.iter_mut()) {
+            let mut lwe_list = LweCiphertextListOwned::new(
+                Scalar::ZERO,
+                packing_key.input_key_lwe_dimension().to_lwe_size(),
+                LweCiphertextCount(chunk.len()),
+                ciphertext_modulus,
+            );
+            for (ct, mut lwe) in chunk.iter().zip(lwe_list.iter_mut()) {
+                match ct {
+                    Ciphertext::Encrypted(ct, _, _) => lwe.as_mut().copy_from_slice(ct.as_ref()),
+                    Ciphertext::Trivial(bit) => {
+                        let Ciphertext::Encrypted(ct, _, _) =
+                            self.trivial_encrypt_modular(*bit as u32, plaintext_modulus)
+                        else {
+                            unreachable!("trivial encryptions are LWE ciphertexts")
+                        };
+                        lwe.as_mut().copy_from_slice(ct.as_ref());
+                    }
+                    Ciphertext::Placeholder => unreachable!("placeholders rejected above"),
+                }
+            }
+
+            keyswitch_lwe_ciphertext_list_and_pack_in_glwe_ciphertext(
+                packing_key,
+                &lwe_list,
+                &mut glwe,
+            );
+        }
+
+        Ok(PackedCiphertext {
+            glwe_list,
+            plaintext_modulus,
+            count: cts.len(),
+        })
+    }
+}
+
+impl<Scalar: GadgetScalar> ClientKey<Scalar> {
+    /// Decrypts the messages of `packed` modulo its plaintext modulus, in the order they were
+    /// packed in.
+    pub fn decrypt_packed(&self, packed: &PackedCiphertext<Scalar>) -> Vec<u32> {
+        let ciphertext_modulus = packed.glwe_list.ciphertext_modulus();
+        let polynomial_size = packed.glwe_list.polynomial_size();
+        let mut plaintext_list =
+            PlaintextList::new(Scalar::ZERO, PlaintextCount(polynomial_size.0));
+
+        let mut messages = Vec::with_capacity(packed.count);
+        for glwe in packed.glwe_list.iter() {
+            decrypt_glwe_ciphertext(&self.glwe_secret_key, &glwe, &mut plaintext_list);
+            messages.extend(plaintext_list.iter().map(|plaintext| {
+                decode_secret(*plaintext.0, packed.plaintext_modulus, ciphertext_modulus)
+            }));
+        }
+        messages.truncate(packed.count);
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean::engine::WithThreadLocalEngine;
+    use crate::core_crypto::prelude::{DecompositionBaseLog, DecompositionLevelCount};
+    use crate::gadget::encoding::synthesize_with_modulus;
+    use crate::gadget::engine::GadgetEngine;
+    use crate::gadget::gen_keys;
+    use crate::gadget::parameters::{GadgetPackingParameters, PLAINTEXT_3_BITS_PARAMETERS};
+
+    #[test]
+    fn pack_gate_outputs() {
+        let (client_key, mut server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let inputs: Vec<_> = GadgetEngine::with_thread_local_mut(|engine| {
+            [0, 1, 1]
+                .iter()
+                .map(|bit| engine.encrypt(*bit, &client_key, 3))
+                .collect()
+        });
+        assert!(matches!(
+            server_key.pack(&inputs),
+            Err(GadgetError::MissingPackingKey)
+        ));
+
+        server_key.enable_packing(
+            &client_key,
+            GadgetPackingParameters::new(DecompositionBaseLog(4), DecompositionLevelCount(6)),
+        );
+
+        // the XOR of every pair of inputs, followed by a trivial output
+        let xor = synthesize_with_modulus(0x6, 2, 3).unwrap();
+        let mut outputs = vec![];
+        for (a, b) in [(0, 1), (0, 2), (1, 2)] {
+            outputs.push(
+                server_key
+                    .evaluate_gate(vec![inputs[a].clone(), inputs[b].clone()], &xor)
+                    .unwrap(),
+            );
+        }
+        outputs.push(Ciphertext::Trivial(true));
+
+        let packed = server_key.pack(&outputs).unwrap();
+        assert_eq!(packed.count(), 4);
+        assert_eq!(packed.plaintext_modulus(), 3);
+        assert_eq!(
+            packed.glwe_list().glwe_ciphertext_count(),
+            GlweCiphertextCount(1)
+        );
+        assert_eq!(client_key.decrypt_packed(&packed), vec![1, 1, 0, 1]);
+
+        assert!(matches!(
+            server_key.pack(&[outputs[0].clone(), Ciphertext::Placeholder]),
+            Err(GadgetError::PlaceholderCiphertext)
+        ));
+    }
+}
@@ -1,44 +1,57 @@
 //! The cryptographic parameter set.
 //!
 //! This module provides the structure containing the cryptographic parameters required for the
-//! homomorphic evaluation of Boolean circuit as well as a list of secure cryptographic parameter
+//! homomorphic evaluation of p-encoded gates as well as a list of secure cryptographic parameter
 //! sets.
 //!
-//! Two parameter sets are provided:
-//!  * `tfhe::boolean::parameters::DEFAULT_PARAMETERS`
-//!  * `tfhe::boolean::parameters::TFHE_LIB_PARAMETERS`
+//! Five parameter sets are provided for the bootstraps of the lookup tables:
+//!  * `tfhe::gadget::parameters::PLAINTEXT_2_BITS_PARAMETERS`, for plaintext moduli up to 4
+//!  * `tfhe::gadget::parameters::PLAINTEXT_3_BITS_PARAMETERS`, for plaintext moduli up to 8
+//!  * `tfhe::gadget::parameters::PLAINTEXT_4_BITS_PARAMETERS`, for plaintext moduli up to 16
+//!  * `tfhe::gadget::parameters::PLAINTEXT_5_BITS_PARAMETERS`, for plaintext moduli up to 32
+//!  * `tfhe::gadget::parameters::PLAINTEXT_6_BITS_PARAMETERS`, for plaintext moduli up to 64,
+//!    with keys on a 64 bits torus only
 //!
-//! They ensure the correctness of the Boolean circuit evaluation result (up to a certain
-//! probability) along with 128-bits of security.
+//! They ensure the correctness of a bootstrap of a freshly encrypted or bootstrapped ciphertext
+//! within [`CORRECTNESS_STANDARD_SCORE`] standard deviations, along with 128-bits of security.
+//! The dimensions and noise distributions of the 2 and 3 bits sets are the ones of the boolean
+//! parameter sets, with the polynomial size large enough for the modulus switch to fit the
+//! accumulator windows of p. The 4 and 5 bits sets keep the LWE noise to dimension ratio of the
+//! boolean sets and use a GLWE noise of 2^-30, the smallest a 32 bits torus can hold, which is
+//! secure for GLWE dimensions of 2048 and more.
+//! The 6 bits set needs a keyswitching noise below what a 32 bits torus can hold, it uses the GLWE
+//! noise and decompositions of the shortint parameter sets of the same polynomial size.
 //!
-//! The two parameter sets offer a trade-off in terms of execution time versus error probability.
-//! The `DEFAULT_PARAMETERS` set offers better performances on homomorphic circuit evaluation
-//! with an higher probability error in comparison with the `TFHE_LIB_PARAMETERS`.
+//! The WoP-PBS of [`wopbs`](super::wopbs) has its own pair of sets, with keys on a 64 bits torus
+//! only: [`WOPBS_5_BITS_PARAMETERS`] for the client and server keys along with
+//! [`WOPBS_5_BITS_CBS_PARAMETERS`] for the [`WopbsKey`](super::wopbs::WopbsKey), for plaintext
+//! moduli up to 32.
 //! Note that if you desire, you can also create your own set of parameters.
 //! Failing to properly fix the parameters will potentially result with an incorrect and/or insecure
 //! computation.
 
+use crate::core_crypto::commons::dispersion::DispersionParameter;
 pub use crate::core_crypto::commons::dispersion::StandardDev;
 pub use crate::core_crypto::commons::parameters::{
-    DecompositionBaseLog, DecompositionLevelCount, EncryptionKeyChoice, GlweDimension,
-    LweDimension, PolynomialSize,
+    CiphertextModulus as CoreCiphertextModulus, DecompositionBaseLog, DecompositionLevelCount,
+    EncryptionKeyChoice, GlweDimension, LweBskGroupingFactor, LweDimension, PBSOrder,
+    PolynomialSize,
 };
 
+use crate::gadget::ciphertext::NoiseLevel;
+use crate::gadget::engine::GadgetScalar;
+use crate::gadget::error::GadgetError;
 use serde::{Deserialize, Serialize};
 
+/// Number of standard deviations the noise must stay within for a bootstrap to be considered
+/// correct, which corresponds to a failure probability of about 2^-40.
+pub const CORRECTNESS_STANDARD_SCORE: f64 = 7.0;
+
+/// Modulus of the ciphertexts, the native modulus stands for the modulus of the torus scalar of
+/// the keys (`2^32` or `2^64`, see [`GadgetScalar`]). Custom moduli must be powers of two.
+pub type CiphertextModulus = CoreCiphertextModulus<u64>;
+
 /// A set of cryptographic parameters for homomorphic Boolean circuit evaluation.
-/// The choice of encryption key for (`boolean ciphertext`)[`super::ciphertext::Ciphertext`].
-///
-/// * The `Big` choice means the big LWE key derived from the GLWE key is used to encrypt the input
-///   ciphertext. This offers better performance but the (`public
-///   key`)[`super::public_key::PublicKey`] can be extremely large and in some cases may not fit in
-///   memory. When refreshing a ciphertext and/or evaluating a table lookup the PBS is computed
-///   first followed by a keyswitch.
-/// * The `Small` choice means the small LWE key is used to encrypt the input ciphertext.
-///   Performance is not as good as in the `Big` case but (`public
-///   key`)[`super::public_key::PublicKey`] sizes are much more manageable and should always fit in
-///   memory. When refreshing a ciphertext and/or evaluating a table lookup the keyswitch is
-///   computed first followed by a PBS.
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct GadgetParameters {
     pub lwe_dimension: LweDimension,
@@ -50,6 +63,48 @@ pub struct GadgetParameters {
     pub pbs_level: DecompositionLevelCount,
     pub ks_base_log: DecompositionBaseLog,
     pub ks_level: DecompositionLevelCount,
+    pub ciphertext_modulus: CiphertextModulus,
+    /// Number of LWE secret key bits processed together by the multi-bit blind rotation, `None`
+    /// for the classic bootstrapping key.
+    ///
+    /// A grouping factor `g` divides the number of sequential external products by `g`, the
+    /// `2^g - 1` GGSW ciphertexts of each group being combined in parallel, at the cost of a
+    /// bootstrapping key `(2^g - 1) / g` times larger. It must be 2, 3 or 4 and divide the LWE
+    /// dimension.
+    pub grouping_factor: Option<LweBskGroupingFactor>,
+    /// The key ciphertexts are encrypted under, which sets the order of the bootstrap and the
+    /// keyswitch, see [`PBSOrder`].
+    ///
+    /// * With `Small`, ciphertexts are encrypted under the small LWE key: gates bootstrap the
+    ///   linear combination of their inputs then keyswitch the result back to the small key.
+    /// * With `Big`, ciphertexts are encrypted under the big LWE key derived from the GLWE key:
+    ///   gates keyswitch the linear combination to the small key then bootstrap it back to the
+    ///   big key. Fresh encryptions carry the smaller GLWE noise and the keyswitch noise is not
+    ///   scaled by the linear combination, at the cost of ciphertexts of `k * N + 1` scalars.
+    pub encryption_key_choice: EncryptionKeyChoice,
+    /// Arithmetic of the blind rotations of the bootstraps, see [`PbsBackend`].
+    pub pbs_backend: PbsBackend,
+}
+
+/// Arithmetic of the blind rotations, the part of a bootstrap multiplying polynomials.
+///
+/// With [`PbsBackend::Ntt`], the bootstrapping key is encrypted modulo the prime
+/// [`NTT_MODULUS`](super::ntt::NTT_MODULUS) and the products of polynomials are computed exactly
+/// by a number theoretic transform, where the FFT rounds them to the 53 bits of a `f64`: the
+/// rounding error of the FFT grows with the polynomial size and the decomposition, and is the
+/// largest with the 64 bits torus. Only the blind rotation runs modulo the prime, the ciphertexts
+/// and the keyswitch stay modulo [`GadgetParameters::ciphertext_modulus`].
+///
+/// The NTT backend runs portable scalar code and does not support multi-bit bootstrapping keys,
+/// compressed server keys, the circuit bootstrapping of [`wopbs`](super::wopbs) nor the
+/// distributed key generation of [`multiparty`](super::multiparty).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PbsBackend {
+    /// Products with the negacyclic FFT over `f64`, on the torus of the ciphertexts.
+    #[default]
+    Fft,
+    /// Exact products with the negacyclic NTT modulo [`NTT_MODULUS`](super::ntt::NTT_MODULUS).
+    Ntt,
 }
 
 impl GadgetParameters {
@@ -72,6 +127,10 @@ impl GadgetParameters {
         pbs_level: DecompositionLevelCount,
         ks_base_log: DecompositionBaseLog,
         ks_level: DecompositionLevelCount,
+        ciphertext_modulus: CiphertextModulus,
+        grouping_factor: Option<LweBskGroupingFactor>,
+        encryption_key_choice: EncryptionKeyChoice,
+        pbs_backend: PbsBackend,
     ) -> GadgetParameters {
         GadgetParameters {
             lwe_dimension,
@@ -83,30 +142,948 @@ impl GadgetParameters {
             pbs_level,
             ks_level,
             ks_base_log,
+            ciphertext_modulus,
+            grouping_factor,
+            encryption_key_choice,
+            pbs_backend,
+        }
+    }
+
+    /// Returns the order of the bootstrap and the keyswitch of a gate, see
+    /// [`GadgetParameters::encryption_key_choice`].
+    pub fn pbs_order(&self) -> PBSOrder {
+        self.encryption_key_choice.into()
+    }
+
+    /// Returns the dimension of the LWE key ciphertexts are encrypted under.
+    pub fn encryption_lwe_dimension(&self) -> LweDimension {
+        match self.encryption_key_choice {
+            EncryptionKeyChoice::Big => {
+                LweDimension(self.glwe_dimension.0 * self.polynomial_size.0)
+            }
+            EncryptionKeyChoice::Small => self.lwe_dimension,
+        }
+    }
+
+    /// Returns the ciphertext modulus of keys and ciphertexts over `Scalar`.
+    pub(crate) fn scalar_ciphertext_modulus<Scalar: GadgetScalar>(
+        &self,
+    ) -> Result<CoreCiphertextModulus<Scalar>, GadgetError> {
+        if self.ciphertext_modulus.is_native_modulus() {
+            return Ok(CoreCiphertextModulus::new_native());
+        }
+
+        let modulus = self.ciphertext_modulus.get_custom_modulus();
+        if !modulus.is_power_of_two() {
+            return Err(GadgetError::UnsupportedCiphertextModulus(modulus));
+        }
+        CoreCiphertextModulus::try_new(modulus)
+            .map_err(|_| GadgetError::UnsupportedCiphertextModulus(modulus))
+    }
+
+    /// Checks the grouping factor of the multi-bit bootstrapping key, if any.
+    pub(crate) fn check_grouping_factor(&self) -> Result<(), GadgetError> {
+        match self.grouping_factor {
+            Some(LweBskGroupingFactor(factor))
+                if !(2..=4).contains(&factor) || self.lwe_dimension.0 % factor != 0 =>
+            {
+                Err(GadgetError::UnsupportedGroupingFactor(factor))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that keys over `Scalar` can be generated for the parameters, see
+    /// [`ClientKey::try_new`](super::client_key::ClientKey::try_new).
+    pub(crate) fn check_key_parameters<Scalar: GadgetScalar>(&self) -> Result<(), GadgetError> {
+        self.scalar_ciphertext_modulus::<Scalar>()?;
+        self.check_grouping_factor()?;
+        self.check_pbs_backend()?;
+        self.check_noise_precision::<Scalar>()
+    }
+
+    /// Checks that the LWE and GLWE noise of keys over `Scalar` is not rounded away, i.e. that
+    /// their standard deviations are at least `2^-torus_bits`.
+    pub(crate) fn check_noise_precision<Scalar: GadgetScalar>(&self) -> Result<(), GadgetError> {
+        let torus_bits = if self.ciphertext_modulus.is_native_modulus() {
+            Scalar::BITS as i32
+        } else {
+            self.ciphertext_modulus.get_custom_modulus().ilog2() as i32
+        };
+        let precision = 2f64.powi(-torus_bits);
+        for (name, std_dev) in [
+            ("LWE", self.lwe_modular_std_dev),
+            ("GLWE", self.glwe_modular_std_dev),
+        ] {
+            if std_dev.0 < precision {
+                return Err(GadgetError::NoiseBelowTorusPrecision(format!(
+                    "{name} standard deviation {} on a torus of {torus_bits} bits",
+                    std_dev.0
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the bootstrapping key options are supported by the blind rotation backend.
+    pub(crate) fn check_pbs_backend(&self) -> Result<(), GadgetError> {
+        match self.pbs_backend {
+            PbsBackend::Ntt if self.grouping_factor.is_some() => Err(
+                GadgetError::UnsupportedNttBackend("multi-bit bootstrapping keys".to_string()),
+            ),
+            // the decomposed coefficients are scaled to base^level within a u64
+            PbsBackend::Ntt if self.pbs_base_log.0 * self.pbs_level.0 >= 64 => {
+                Err(GadgetError::UnsupportedNttBackend(format!(
+                    "decompositions of {} bits",
+                    self.pbs_base_log.0 * self.pbs_level.0
+                )))
+            }
+            _ => Ok(()),
         }
     }
 }
 
-pub const PLAINTEXT_2_BITS_PARAMETERS: GadgetParameters = GadgetParameters {
-    lwe_dimension: LweDimension(694),
-    glwe_dimension: GlweDimension(5),
-    polynomial_size: PolynomialSize(256),
-    lwe_modular_std_dev: StandardDev(0.000022810107419132102),
-    glwe_modular_std_dev: StandardDev(0.00000000037411618952047216),
-    pbs_base_log: DecompositionBaseLog(14),
-    pbs_level: DecompositionLevelCount(1),
-    ks_base_log: DecompositionBaseLog(4),
-    ks_level: DecompositionLevelCount(3),
-};
+/// Decomposition parameters of a keyswitching key between two gadget client keys, see
+/// [`KeySwitchingKey`](super::key_switching_key::KeySwitchingKey).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GadgetKeySwitchingParameters {
+    pub ks_base_log: DecompositionBaseLog,
+    pub ks_level: DecompositionLevelCount,
+}
 
-pub const PLAINTEXT_3_BITS_PARAMETERS: GadgetParameters = GadgetParameters {
-    lwe_dimension: LweDimension(672),
-    glwe_dimension: GlweDimension(3),
+impl GadgetKeySwitchingParameters {
+    pub fn new(ks_base_log: DecompositionBaseLog, ks_level: DecompositionLevelCount) -> Self {
+        Self {
+            ks_base_log,
+            ks_level,
+        }
+    }
+}
+
+/// Decomposition parameters of the packing keyswitching key of a server key, see
+/// [`ServerKey::enable_packing`](super::server_key::ServerKey::enable_packing).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GadgetPackingParameters {
+    pub pks_base_log: DecompositionBaseLog,
+    pub pks_level: DecompositionLevelCount,
+}
+
+impl GadgetPackingParameters {
+    pub fn new(pks_base_log: DecompositionBaseLog, pks_level: DecompositionLevelCount) -> Self {
+        Self {
+            pks_base_log,
+            pks_level,
+        }
+    }
+}
+
+/// Parameters of the circuit bootstrapping of a [`WopbsKey`](super::wopbs::WopbsKey).
+///
+/// The private functional packing keyswitching keys use `pfks_base_log`, `pfks_level` and
+/// `pfks_modular_std_dev`, the GGSW ciphertexts output by the circuit bootstrapping are
+/// decomposed with `cbs_base_log` and `cbs_level`.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GadgetWopbsParameters {
+    pub pfks_base_log: DecompositionBaseLog,
+    pub pfks_level: DecompositionLevelCount,
+    pub pfks_modular_std_dev: StandardDev,
+    pub cbs_base_log: DecompositionBaseLog,
+    pub cbs_level: DecompositionLevelCount,
+}
+
+impl GadgetWopbsParameters {
+    pub fn new(
+        pfks_base_log: DecompositionBaseLog,
+        pfks_level: DecompositionLevelCount,
+        pfks_modular_std_dev: StandardDev,
+        cbs_base_log: DecompositionBaseLog,
+        cbs_level: DecompositionLevelCount,
+    ) -> Self {
+        Self {
+            pfks_base_log,
+            pfks_level,
+            pfks_modular_std_dev,
+            cbs_base_log,
+            cbs_level,
+        }
+    }
+}
+
+impl GadgetParameters {
+    /// Variance, on the torus, of a fresh encryption under the key of
+    /// [`GadgetParameters::encryption_key_choice`].
+    pub fn fresh_noise_variance(&self) -> f64 {
+        match self.encryption_key_choice {
+            EncryptionKeyChoice::Big => self.glwe_modular_std_dev.get_variance(),
+            EncryptionKeyChoice::Small => self.lwe_modular_std_dev.get_variance(),
+        }
+    }
+
+    /// Variance, on the torus, of a freshly bootstrapped ciphertext: after a bootstrap followed
+    /// by a keyswitch, or after the bootstrap alone with the `Big` encryption key choice.
+    pub fn bootstrapped_noise_variance(&self) -> f64 {
+        match self.pbs_order() {
+            PBSOrder::BootstrapKeyswitch => {
+                self.blind_rotation_noise_variance() + self.keyswitch_noise_variance()
+            }
+            PBSOrder::KeyswitchBootstrap => self.blind_rotation_noise_variance(),
+        }
+    }
+
+    /// Variance, on the torus, of the output of a blind rotation.
+    fn blind_rotation_noise_variance(&self) -> f64 {
+        let n = self.lwe_dimension.0 as f64;
+        let big_n = self.polynomial_size.0 as f64;
+        let k = self.glwe_dimension.0 as f64;
+
+        // Blind rotation: n external products with the decomposed accumulator. The multi-bit
+        // blind rotation performs n / g external products with GGSW ciphertexts summing the
+        // noise of 2^g - 1 key GGSW ciphertexts each.
+        let (key_factor, rounding_factor) = match self.grouping_factor {
+            None => (1., 1.),
+            Some(LweBskGroupingFactor(factor)) => {
+                let factor = factor as f64;
+                ((2f64.powf(factor) - 1.) / factor, 1. / factor)
+            }
+        };
+        let pbs_base = 2f64.powi(self.pbs_base_log.0 as i32);
+        let pbs_level = self.pbs_level.0 as f64;
+        key_factor * n * (k + 1.) * pbs_level * big_n * (pbs_base * pbs_base + 2.) / 12.
+            * self.glwe_modular_std_dev.get_variance()
+            + rounding_factor * n * (1. + k * big_n / 2.) / (24. * pbs_base.powf(2. * pbs_level))
+    }
+
+    /// Variance, on the torus, added by the keyswitch from the big LWE key of dimension `k * N`
+    /// to the small key.
+    pub fn keyswitch_noise_variance(&self) -> f64 {
+        let big_n = self.polynomial_size.0 as f64;
+        let k = self.glwe_dimension.0 as f64;
+
+        // Each of the k * N * ks_level key ciphertexts is scaled by a decomposed digit of
+        // variance (B^2 + 2) / 12 for the base B, as for the external products of the blind
+        // rotation, so the noise of the key grows with the base.
+        let ks_base = 2f64.powi(self.ks_base_log.0 as i32);
+        let ks_level = self.ks_level.0 as f64;
+        k * big_n * ks_level * (ks_base * ks_base + 2.) / 12.
+            * self.lwe_modular_std_dev.get_variance()
+            + k * big_n / (24. * ks_base.powf(2. * ks_level))
+    }
+
+    /// Variance, on the torus, added by the modulus switch to `2N` at the start of a bootstrap.
+    pub fn modulus_switch_noise_variance(&self) -> f64 {
+        let n = self.lwe_dimension.0 as f64;
+        let big_n = self.polynomial_size.0 as f64;
+        (1. + n / 2.) / (48. * big_n * big_n)
+    }
+
+    /// Variance, on the torus, added to a linear combination before its blind rotation: the
+    /// modulus switch, preceded by the keyswitch with the `Big` encryption key choice.
+    fn pre_bootstrap_noise_variance(&self) -> f64 {
+        match self.pbs_order() {
+            PBSOrder::BootstrapKeyswitch => self.modulus_switch_noise_variance(),
+            PBSOrder::KeyswitchBootstrap => {
+                self.keyswitch_noise_variance() + self.modulus_switch_noise_variance()
+            }
+        }
+    }
+
+    /// Variance corresponding to [`NoiseLevel::NOMINAL`], i.e. the noise of a fresh or
+    /// freshly bootstrapped ciphertext.
+    pub fn nominal_noise_variance(&self) -> f64 {
+        self.fresh_noise_variance()
+            .max(self.bootstrapped_noise_variance())
+    }
+
+    /// Returns the largest plaintext modulus whose messages can be bootstrapped.
+    ///
+    /// Each message owns a window of `N / p` coefficients of the accumulator, the window of 0
+    /// being split in two halves at both ends of the polynomial: above `N / 2`, the halves hold
+    /// no coefficient and the windows no longer match the messages.
+    pub fn max_supported_p(&self) -> u32 {
+        (self.polynomial_size.0 / 2) as u32
+    }
+
+    /// Checks that messages modulo `p` can be bootstrapped, see
+    /// [`GadgetParameters::max_supported_p`].
+    pub(crate) fn check_plaintext_modulus(&self, p: u32) -> Result<(), GadgetError> {
+        if p > self.max_supported_p() {
+            return Err(GadgetError::UnsupportedPlaintextModulus(p));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the largest noise level a ciphertext encrypting a message modulo `p` can reach
+    /// and still be bootstrapped correctly.
+    ///
+    /// Each message owns a window of `N / p` coefficients of the accumulator, i.e. the noise
+    /// must stay within `1 / 4p` on the torus.
+    pub fn max_noise_level(&self, p: u32) -> NoiseLevel {
+        self.max_noise_level_with_score(p, CORRECTNESS_STANDARD_SCORE)
+    }
+
+    /// Returns the largest noise level a ciphertext encrypting a message modulo `p` can reach
+    /// with its noise staying within `standard_score` standard deviations of the bound.
+    pub fn max_noise_level_with_score(&self, p: u32, standard_score: f64) -> NoiseLevel {
+        let bound = 1. / (4. * p as f64) / standard_score;
+        let available_variance = bound * bound - self.pre_bootstrap_noise_variance();
+        if available_variance <= 0. {
+            return NoiseLevel::ZERO;
+        }
+
+        NoiseLevel((available_variance / self.nominal_noise_variance()).floor() as usize)
+    }
+
+    /// Estimates the security level of the parameters in bits on a 32 bits torus, or on the
+    /// custom ciphertext modulus if any, the minimum of the security of the LWE and GLWE secret
+    /// keys. See [`estimate_lwe_security_bits`], this is a coarse screen which does not certify
+    /// the security of the parameters.
+    pub fn security_level_bits(&self) -> f64 {
+        if self.ciphertext_modulus.is_native_modulus() {
+            self.security_level_bits_for_torus(u32::BITS as usize)
+        } else {
+            let modulus = self.ciphertext_modulus.get_custom_modulus();
+            self.security_level_bits_for_torus(modulus.ilog2() as usize)
+        }
+    }
+
+    /// Estimates the security level of the parameters in bits on a torus of `torus_bits` bits,
+    /// e.g. 64 for keys over a [`GadgetScalar`](crate::gadget::engine::GadgetScalar) of `u64`.
+    pub fn security_level_bits_for_torus(&self, torus_bits: usize) -> f64 {
+        let lwe_security =
+            estimate_lwe_security_bits(self.lwe_dimension.0, self.lwe_modular_std_dev, torus_bits);
+        let glwe_security = estimate_lwe_security_bits(
+            self.glwe_dimension.0 * self.polynomial_size.0,
+            self.glwe_modular_std_dev,
+            torus_bits,
+        );
+        lwe_security.min(glwe_security)
+    }
+
+    /// Returns the probability that bootstrapping a ciphertext encrypting a message modulo `p`
+    /// with noise level `noise_level` lands in the window of another message.
+    pub fn failure_probability(&self, p: u32, noise_level: NoiseLevel) -> f64 {
+        let bound = 1. / (4. * p as f64);
+        let variance = noise_level.0 as f64 * self.nominal_noise_variance()
+            + self.pre_bootstrap_noise_variance();
+        erfc(bound / (2. * variance).sqrt())
+    }
+}
+
+/// Security level targeted by the provided parameter sets and the [`GadgetParametersBuilder`].
+pub const TARGET_SECURITY_BITS: f64 = 128.;
+
+/// Estimates the security in bits of LWE instances with a uniform binary secret of dimension
+/// `dimension`, a torus of `torus_bits` bits and a gaussian noise of standard deviation `std_dev`
+/// on the torus.
+///
+/// The estimate is the cost of the primal uSVP attack: the smallest BKZ block size `b` for which
+/// the embedding lattice of dimension `d` (with `m` samples) reveals the error, following the
+/// success condition of [ADPS16] with the secret rescaled to the error size [BG14], costed with
+/// the classical sieving model `0.292 b + 16.4 + log2(8d)`.
+///
+/// Only the primal uSVP attack is costed, so this is an upper bound on the security and no
+/// substitute for the lattice estimator: it screens out clearly insecure parameters, e.g. in the
+/// [`GadgetParametersBuilder`], but does not certify 128 bits. It gives 127.6 bits for the LWE
+/// dimension of 630 of the boolean and 3 bits sets, whose security rests on the lattice estimator
+/// run for the boolean sets.
+///
+/// [ADPS16]: https://eprint.iacr.org/2015/1092
+/// [BG14]: https://eprint.iacr.org/2014/375
+pub fn estimate_lwe_security_bits(
+    dimension: usize,
+    std_dev: StandardDev,
+    torus_bits: usize,
+) -> f64 {
+    let log2_q = torus_bits as f64;
+    let n = dimension as f64;
+    let error_std_dev = std_dev.0 * 2f64.powf(log2_q);
+    // uniform binary secret has a standard deviation of 1/2
+    let log2_scaling = (error_std_dev / 0.5).log2();
+
+    for block_size in 40..4000usize {
+        let b = block_size as f64;
+        let log2_delta = ((std::f64::consts::PI * b).powf(1. / b) * b
+            / (2. * std::f64::consts::PI * std::f64::consts::E))
+            .log2()
+            / (2. * (b - 1.));
+        let lhs = (error_std_dev * b.sqrt()).log2();
+
+        for samples in (dimension / 4..3 * dimension).step_by(8) {
+            let m = samples as f64;
+            let d = n + m + 1.;
+            let log2_volume = m * log2_q + n * log2_scaling;
+            if lhs <= (2. * b - d - 1.) * log2_delta + log2_volume / d {
+                return 0.292 * b + 16.4 + (8. * d).log2();
+            }
+        }
+    }
+
+    f64::INFINITY
+}
+
+/// Number of LWE dimensions per bit of noise standard deviation below 1, i.e. a std dev of
+/// `2^-(n / 44.5)` for a dimension `n`. This is the ratio of the boolean parameter sets.
+const DIMENSIONS_PER_NOISE_BIT: f64 = 44.5;
+
+/// Smallest noise standard deviation a 32 bits torus can meaningfully hold.
+const MIN_STD_DEV_LOG2: f64 = -30.;
+
+/// Builds parameters for gates with a given plaintext modulus, linear combination norm and
+/// failure probability.
+///
+/// The builder searches the LWE dimension, the GLWE dimension and polynomial size, and the
+/// decomposition parameters of the bootstrap and keyswitch with the smallest estimated
+/// bootstrapping cost such that a linear combination of nominal ciphertexts with squared norm
+/// `max_norm2` (the sum of the squared input mappings) is bootstrapped correctly with the target
+/// probability. Noise distributions follow the noise to dimension ratio of the boolean parameter
+/// sets and candidates below [`TARGET_SECURITY_BITS`] according to
+/// [`estimate_lwe_security_bits`] are discarded, security should still be confirmed with the
+/// lattice estimator before production use.
+///
+/// ```rust
+/// use tfhe::gadget::parameters::GadgetParameters;
+///
+/// // 2 input gates over p = 3 with mappings 1 and 1
+/// let parameters = GadgetParameters::builder()
+///     .plaintext_modulus(3)
+///     .max_norm2(2)
+///     .failure_probability(2f64.powi(-40))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GadgetParametersBuilder {
+    plaintext_modulus: u32,
+    max_norm2: usize,
+    failure_probability: f64,
+    pbs_backend: PbsBackend,
+}
+
+impl Default for GadgetParametersBuilder {
+    fn default() -> Self {
+        Self {
+            plaintext_modulus: 3,
+            max_norm2: 1,
+            failure_probability: 2f64.powi(-40),
+            pbs_backend: PbsBackend::Fft,
+        }
+    }
+}
+
+impl GadgetParametersBuilder {
+    pub fn plaintext_modulus(mut self, plaintext_modulus: u32) -> Self {
+        self.plaintext_modulus = plaintext_modulus;
+        self
+    }
+
+    /// Sets the largest squared norm of the linear combinations bootstrapped, i.e. the largest
+    /// sum of squared input mappings of the encodings evaluated.
+    pub fn max_norm2(mut self, max_norm2: usize) -> Self {
+        self.max_norm2 = max_norm2;
+        self
+    }
+
+    /// Sets the largest acceptable probability of a bootstrap failure.
+    pub fn failure_probability(mut self, failure_probability: f64) -> Self {
+        self.failure_probability = failure_probability;
+        self
+    }
+
+    /// Sets the blind rotation backend of the parameters built, [`PbsBackend::Fft`] by default.
+    pub fn pbs_backend(mut self, pbs_backend: PbsBackend) -> Self {
+        self.pbs_backend = pbs_backend;
+        self
+    }
+
+    /// Returns the standard score matching the failure probability, i.e. `z` such that a
+    /// centered gaussian exceeds `z` standard deviations in absolute value with the failure
+    /// probability.
+    pub fn standard_score(&self) -> f64 {
+        let (mut low, mut high) = (0f64, 40f64);
+        for _ in 0..100 {
+            let z = (low + high) / 2.;
+            if erfc(z / std::f64::consts::SQRT_2) > self.failure_probability {
+                low = z;
+            } else {
+                high = z;
+            }
+        }
+        high
+    }
+
+    /// Checks that `parameters` meet the requirements of the builder.
+    pub fn check(&self, parameters: &GadgetParameters) -> Result<(), GadgetError> {
+        self.check_requirements()?;
+
+        if self.plaintext_modulus > parameters.max_supported_p() {
+            return Err(GadgetError::UnsatisfiableParameters(format!(
+                "polynomial size {} supports plaintext moduli up to {}, {} is needed",
+                parameters.polynomial_size.0,
+                parameters.max_supported_p(),
+                self.plaintext_modulus
+            )));
+        }
+
+        let max_noise_level =
+            parameters.max_noise_level_with_score(self.plaintext_modulus, self.standard_score());
+        if max_noise_level.0 < self.max_norm2 {
+            return Err(GadgetError::UnsatisfiableParameters(format!(
+                "linear combinations of squared norm up to {} modulo {} are correct with \
+                probability {}, noise level {} is needed",
+                max_noise_level.0, self.plaintext_modulus, self.failure_probability, self.max_norm2
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn build(&self) -> Result<GadgetParameters, GadgetError> {
+        self.check_requirements()?;
+        let standard_score = self.standard_score();
+
+        let is_secure = |dimension: usize| {
+            estimate_lwe_security_bits(dimension, secure_std_dev(dimension), u32::BITS as usize)
+                >= TARGET_SECURITY_BITS
+        };
+
+        let mut best: Option<(f64, GadgetParameters)> = None;
+        for (glwe_dimension, polynomial_size) in [
+            (2, 512),
+            (1, 1024),
+            (2, 1024),
+            (1, 2048),
+            (1, 4096),
+            (1, 8192),
+        ] {
+            if !is_secure(glwe_dimension * polynomial_size)
+                || self.plaintext_modulus as usize > polynomial_size / 2
+            {
+                continue;
+            }
+
+            for lwe_dimension in (600..=1200).step_by(10) {
+                if !is_secure(lwe_dimension) {
+                    continue;
+                }
+
+                let mut parameters = GadgetParameters {
+                    lwe_dimension: LweDimension(lwe_dimension),
+                    glwe_dimension: GlweDimension(glwe_dimension),
+                    polynomial_size: PolynomialSize(polynomial_size),
+                    lwe_modular_std_dev: secure_std_dev(lwe_dimension),
+                    glwe_modular_std_dev: secure_std_dev(glwe_dimension * polynomial_size),
+                    pbs_base_log: DecompositionBaseLog(1),
+                    pbs_level: DecompositionLevelCount(1),
+                    ks_base_log: DecompositionBaseLog(1),
+                    ks_level: DecompositionLevelCount(1),
+                    ciphertext_modulus: CiphertextModulus::new_native(),
+                    grouping_factor: None,
+                    encryption_key_choice: EncryptionKeyChoice::Small,
+                    pbs_backend: self.pbs_backend,
+                };
+
+                for pbs_level in 1..=8 {
+                    for ks_level in 1..=16 {
+                        // the base minimizing the noise for each level, the decomposition must
+                        // fit the 32 bits torus
+                        parameters.pbs_level = DecompositionLevelCount(pbs_level);
+                        parameters.pbs_base_log = (1..=32 / pbs_level)
+                            .map(DecompositionBaseLog)
+                            .min_by(|lhs, rhs| {
+                                let mut lhs_parameters = parameters;
+                                lhs_parameters.pbs_base_log = *lhs;
+                                let mut rhs_parameters = parameters;
+                                rhs_parameters.pbs_base_log = *rhs;
+                                lhs_parameters
+                                    .bootstrapped_noise_variance()
+                                    .total_cmp(&rhs_parameters.bootstrapped_noise_variance())
+                            })
+                            .unwrap();
+                        parameters.ks_level = DecompositionLevelCount(ks_level);
+                        parameters.ks_base_log = (1..=32 / ks_level)
+                            .map(DecompositionBaseLog)
+                            .min_by(|lhs, rhs| {
+                                let mut lhs_parameters = parameters;
+                                lhs_parameters.ks_base_log = *lhs;
+                                let mut rhs_parameters = parameters;
+                                rhs_parameters.ks_base_log = *rhs;
+                                lhs_parameters
+                                    .bootstrapped_noise_variance()
+                                    .total_cmp(&rhs_parameters.bootstrapped_noise_variance())
+                            })
+                            .unwrap();
+
+                        let max_noise_level = parameters
+                            .max_noise_level_with_score(self.plaintext_modulus, standard_score);
+                        if max_noise_level.0 < self.max_norm2 {
+                            continue;
+                        }
+
+                        let cost = bootstrapping_cost(&parameters);
+                        if best.map_or(true, |(best_cost, _)| cost < best_cost) {
+                            best = Some((cost, parameters));
+                        }
+                    }
+                }
+            }
+        }
+
+        best.map(|(_, parameters)| parameters).ok_or_else(|| {
+            GadgetError::UnsatisfiableParameters(format!(
+                "no parameters bootstrap linear combinations of squared norm {} modulo {} with \
+                failure probability {}",
+                self.max_norm2, self.plaintext_modulus, self.failure_probability
+            ))
+        })
+    }
+
+    fn check_requirements(&self) -> Result<(), GadgetError> {
+        if self.plaintext_modulus < 2
+            || self.max_norm2 == 0
+            || !(self.failure_probability > 0. && self.failure_probability < 1.)
+        {
+            return Err(GadgetError::UnsatisfiableParameters(format!(
+                "invalid requirements {self:?}"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl GadgetParameters {
+    pub fn builder() -> GadgetParametersBuilder {
+        GadgetParametersBuilder::default()
+    }
+}
+
+/// Noise standard deviation of an LWE instance of dimension `dimension`, following the ratio of
+/// the boolean parameter sets.
+fn secure_std_dev(dimension: usize) -> StandardDev {
+    let log2_std_dev = (-(dimension as f64) / DIMENSIONS_PER_NOISE_BIT).max(MIN_STD_DEV_LOG2);
+    StandardDev(2f64.powf(log2_std_dev))
+}
+
+/// Estimated number of elementary operations of a bootstrap followed by a keyswitch.
+fn bootstrapping_cost(parameters: &GadgetParameters) -> f64 {
+    let n = parameters.lwe_dimension.0 as f64;
+    let k = parameters.glwe_dimension.0 as f64;
+    let big_n = parameters.polynomial_size.0 as f64;
+
+    // each external product computes (k + 1) * level forward FFTs and k + 1 backward FFTs
+    let pbs_cost = n * (k + 1.) * (parameters.pbs_level.0 as f64 + 1.) * big_n * big_n.log2();
+    let ks_cost = n * k * big_n * parameters.ks_level.0 as f64;
+    pbs_cost + ks_cost
+}
+
+/// Complementary error function, with a relative error below 1.2e-7 (Numerical Recipes).
+pub(crate) fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1. / (1. + 0.5 * z);
+    let polynomial = -z * z - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398
+                                + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    let result = t * polynomial.exp();
+    if x >= 0. {
+        result
+    } else {
+        2. - result
+    }
+}
+
+/// Parameter set for plaintext moduli up to 4, a ciphertext can be bootstrapped modulo 3 after a
+/// linear combination of noise level up to 11, and modulo 4 up to 5.
+pub const PLAINTEXT_2_BITS_PARAMETERS: GadgetParameters = GadgetParameters {
+    lwe_dimension: LweDimension(722),
+    glwe_dimension: GlweDimension(2),
     polynomial_size: PolynomialSize(512),
     lwe_modular_std_dev: StandardDev(0.000013071021089943935),
     glwe_modular_std_dev: StandardDev(0.00000004990272175010415),
-    pbs_base_log: DecompositionBaseLog(4),
-    pbs_level: DecompositionLevelCount(6),
+    pbs_base_log: DecompositionBaseLog(6),
+    pbs_level: DecompositionLevelCount(3),
+    ks_base_log: DecompositionBaseLog(3),
+    ks_level: DecompositionLevelCount(4),
+    ciphertext_modulus: CiphertextModulus::new_native(),
+    grouping_factor: None,
+    encryption_key_choice: EncryptionKeyChoice::Small,
+    pbs_backend: PbsBackend::Fft,
+};
+
+/// Parameter set for plaintext moduli up to 8, the polynomial size of 1024 keeps the modulus
+/// switching noise within the windows of the accumulator for p = 7 and p = 8. Nominal ciphertexts
+/// only can be bootstrapped modulo 7 and 8, linear combinations of noise level up to 11 modulo 3.
+///
+/// The keyswitch decomposes in 15 levels of 1 bit, base 2^2 with 8 levels misses the
+/// correctness bound for p = 8 by the
+/// [`bootstrapped_noise_variance`](GadgetParameters::bootstrapped_noise_variance) estimate.
+pub const PLAINTEXT_3_BITS_PARAMETERS: GadgetParameters = GadgetParameters {
+    lwe_dimension: LweDimension(630),
+    glwe_dimension: GlweDimension(1),
+    polynomial_size: PolynomialSize(1024),
+    lwe_modular_std_dev: StandardDev(0.000030517578125),
+    glwe_modular_std_dev: StandardDev(0.00000002980232238769531),
+    pbs_base_log: DecompositionBaseLog(7),
+    pbs_level: DecompositionLevelCount(3),
+    ks_base_log: DecompositionBaseLog(1),
+    ks_level: DecompositionLevelCount(15),
+    ciphertext_modulus: CiphertextModulus::new_native(),
+    grouping_factor: None,
+    encryption_key_choice: EncryptionKeyChoice::Small,
+    pbs_backend: PbsBackend::Fft,
+};
+
+/// Parameter set for plaintext moduli up to 16, e.g. gates with up to 5 pins. Linear
+/// combinations of noise level up to 2 can be bootstrapped modulo 16, up to 12 modulo 8.
+///
+/// The LWE dimension of 800 lowers the LWE noise enough for a keyswitch in base 2^3 to fit
+/// p = 16, see [`GadgetParameters::bootstrapped_noise_variance`].
+pub const PLAINTEXT_4_BITS_PARAMETERS: GadgetParameters = GadgetParameters {
+    lwe_dimension: LweDimension(800),
+    glwe_dimension: GlweDimension(1),
+    polynomial_size: PolynomialSize(2048),
+    lwe_modular_std_dev: StandardDev(0.000003874581469191443),
+    glwe_modular_std_dev: StandardDev(0.0000000009313225746154785),
+    pbs_base_log: DecompositionBaseLog(10),
+    pbs_level: DecompositionLevelCount(2),
+    ks_base_log: DecompositionBaseLog(3),
+    ks_level: DecompositionLevelCount(5),
+    ciphertext_modulus: CiphertextModulus::new_native(),
+    grouping_factor: None,
+    encryption_key_choice: EncryptionKeyChoice::Small,
+    pbs_backend: PbsBackend::Fft,
+};
+
+/// Parameter set for plaintext moduli up to 32, e.g. gates with up to 6 pins. Nominal
+/// ciphertexts only can be bootstrapped modulo 32, linear combinations of noise level up to 7
+/// modulo 16.
+///
+/// The LWE dimension of 810 lowers the LWE noise enough for a keyswitch in base 2^2 to fit
+/// p = 32, see [`GadgetParameters::bootstrapped_noise_variance`].
+pub const PLAINTEXT_5_BITS_PARAMETERS: GadgetParameters = GadgetParameters {
+    lwe_dimension: LweDimension(810),
+    glwe_dimension: GlweDimension(1),
+    polynomial_size: PolynomialSize(4096),
+    lwe_modular_std_dev: StandardDev(0.0000033157181532605795),
+    glwe_modular_std_dev: StandardDev(0.0000000009313225746154785),
+    pbs_base_log: DecompositionBaseLog(7),
+    pbs_level: DecompositionLevelCount(3),
     ks_base_log: DecompositionBaseLog(2),
+    ks_level: DecompositionLevelCount(8),
+    ciphertext_modulus: CiphertextModulus::new_native(),
+    grouping_factor: None,
+    encryption_key_choice: EncryptionKeyChoice::Small,
+    pbs_backend: PbsBackend::Fft,
+};
+
+/// Parameters for plaintext moduli up to 64, which must be used with keys over a
+/// [`GadgetScalar`](crate::gadget::engine::GadgetScalar) of `u64`.
+pub const PLAINTEXT_6_BITS_PARAMETERS: GadgetParameters = GadgetParameters {
+    lwe_dimension: LweDimension(930),
+    glwe_dimension: GlweDimension(1),
+    polynomial_size: PolynomialSize(8192),
+    lwe_modular_std_dev: StandardDev(0.0000005114595904920907),
+    glwe_modular_std_dev: StandardDev(0.0000000000000000002168404344971009),
+    pbs_base_log: DecompositionBaseLog(15),
+    pbs_level: DecompositionLevelCount(2),
+    ks_base_log: DecompositionBaseLog(3),
     ks_level: DecompositionLevelCount(6),
+    ciphertext_modulus: CiphertextModulus::new_native(),
+    grouping_factor: None,
+    encryption_key_choice: EncryptionKeyChoice::Small,
+    pbs_backend: PbsBackend::Fft,
+};
+
+/// Parameters of the client and server keys for the WoP-PBS of [`wopbs`](super::wopbs), to be
+/// paired with [`WOPBS_5_BITS_CBS_PARAMETERS`] for the [`WopbsKey`](super::wopbs::WopbsKey) and
+/// used with keys over a [`GadgetScalar`](crate::gadget::engine::GadgetScalar) of `u64`.
+///
+/// The dimensions, noise distributions and bootstrapping decomposition are the ones of the
+/// shortint WoP-PBS set for 2 bits messages and carries, with the keyswitch decomposed in base
+/// 2^2 to lower its noise. The bit extraction keeps the modulus switching noise out of the
+/// windows of the messages, so that linear combinations of noise level up to 2 are evaluated by
+/// [`ServerKey::evaluate_gate_wopbs`](super::server_key::ServerKey::evaluate_gate_wopbs) modulo
+/// 32, while the lookup tables of this set stop at 16.
+pub const WOPBS_5_BITS_PARAMETERS: GadgetParameters = GadgetParameters {
+    lwe_dimension: LweDimension(769),
+    glwe_dimension: GlweDimension(1),
+    polynomial_size: PolynomialSize(2048),
+    lwe_modular_std_dev: StandardDev(0.0000043131554647504185),
+    glwe_modular_std_dev: StandardDev(0.00000000000000029403601535432533),
+    pbs_base_log: DecompositionBaseLog(15),
+    pbs_level: DecompositionLevelCount(2),
+    ks_base_log: DecompositionBaseLog(2),
+    ks_level: DecompositionLevelCount(8),
+    ciphertext_modulus: CiphertextModulus::new_native(),
+    grouping_factor: None,
+    encryption_key_choice: EncryptionKeyChoice::Small,
+    pbs_backend: PbsBackend::Fft,
+};
+
+/// Parameters of the circuit bootstrapping of the keys of [`WOPBS_5_BITS_PARAMETERS`], the
+/// ones of the shortint WoP-PBS set for 2 bits messages and carries.
+pub const WOPBS_5_BITS_CBS_PARAMETERS: GadgetWopbsParameters = GadgetWopbsParameters {
+    pfks_base_log: DecompositionBaseLog(15),
+    pfks_level: DecompositionLevelCount(2),
+    pfks_modular_std_dev: StandardDev(0.00000000000000029403601535432533),
+    cbs_base_log: DecompositionBaseLog(5),
+    cbs_level: DecompositionLevelCount(3),
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean::engine::WithThreadLocalEngine;
+    use crate::gadget::encoding::Encoding;
+    use crate::gadget::engine::GadgetEngine;
+    use crate::gadget::gen_keys;
+
+    const BOOTSTRAP_CHAIN_LENGTH: usize = 50;
+
+    #[test]
+    fn parameter_sets_pass_the_security_screen() {
+        // the coarse estimate puts the LWE dimension of 630 of the boolean sets at 127.6 bits
+        for parameters in [
+            PLAINTEXT_2_BITS_PARAMETERS,
+            PLAINTEXT_3_BITS_PARAMETERS,
+            PLAINTEXT_4_BITS_PARAMETERS,
+            PLAINTEXT_5_BITS_PARAMETERS,
+        ] {
+            assert!(
+                parameters.security_level_bits() >= TARGET_SECURITY_BITS - 1.,
+                "{parameters:?}"
+            );
+        }
+        for parameters in [PLAINTEXT_6_BITS_PARAMETERS, WOPBS_5_BITS_PARAMETERS] {
+            assert!(
+                parameters.security_level_bits_for_torus(64) >= TARGET_SECURITY_BITS,
+                "{parameters:?}"
+            );
+        }
+
+        // a tiny noise breaks security
+        let mut insecure = PLAINTEXT_2_BITS_PARAMETERS;
+        insecure.lwe_modular_std_dev = StandardDev(2f64.powi(-30));
+        assert!(insecure.security_level_bits() < 80.);
+    }
+
+    #[test]
+    fn noise_below_torus_precision_is_rejected() {
+        // the GLWE noise of the 6 bits set is about 2^-62, lost on a 32 bits torus
+        assert!(matches!(
+            PLAINTEXT_6_BITS_PARAMETERS.check_noise_precision::<u32>(),
+            Err(GadgetError::NoiseBelowTorusPrecision(_))
+        ));
+        for parameters in [PLAINTEXT_6_BITS_PARAMETERS, WOPBS_5_BITS_PARAMETERS] {
+            assert!(matches!(
+                parameters.check_noise_precision::<u32>(),
+                Err(GadgetError::NoiseBelowTorusPrecision(_))
+            ));
+            assert_eq!(parameters.check_noise_precision::<u64>(), Ok(()));
+        }
+        for parameters in [
+            PLAINTEXT_2_BITS_PARAMETERS,
+            PLAINTEXT_3_BITS_PARAMETERS,
+            PLAINTEXT_4_BITS_PARAMETERS,
+            PLAINTEXT_5_BITS_PARAMETERS,
+        ] {
+            assert_eq!(parameters.check_noise_precision::<u32>(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn standard_score_matches_failure_probability() {
+        // 7 standard deviations correspond to a failure probability of about 2^-38.6
+        let builder = GadgetParameters::builder().failure_probability(2f64.powf(-38.6));
+        assert!((builder.standard_score() - 7.).abs() < 0.01);
+    }
+
+    #[test]
+    fn builder_finds_and_validates_parameters() {
+        let builder = GadgetParameters::builder()
+            .plaintext_modulus(7)
+            .max_norm2(3)
+            .failure_probability(2f64.powi(-40));
+        let parameters = builder.build().unwrap();
+        builder.check(&parameters).unwrap();
+        assert!(parameters.security_level_bits() >= TARGET_SECURITY_BITS);
+
+        // the 3 bits set handles p = 7 for nominal ciphertexts only
+        let builder = builder.max_norm2(1).failure_probability(2f64.powf(-38.6));
+        builder.check(&PLAINTEXT_3_BITS_PARAMETERS).unwrap();
+        assert!(builder
+            .plaintext_modulus(9)
+            .check(&PLAINTEXT_3_BITS_PARAMETERS)
+            .is_err());
+
+        assert!(matches!(
+            GadgetParameters::builder()
+                .plaintext_modulus(1 << 12)
+                .build(),
+            Err(GadgetError::UnsatisfiableParameters(_))
+        ));
+    }
+
+    #[test]
+    fn parameter_sets_fit_their_plaintext_moduli() {
+        for (parameters, max_p) in [
+            (PLAINTEXT_2_BITS_PARAMETERS, 4),
+            (PLAINTEXT_3_BITS_PARAMETERS, 8),
+            (PLAINTEXT_4_BITS_PARAMETERS, 16),
+            (PLAINTEXT_5_BITS_PARAMETERS, 32),
+            (PLAINTEXT_6_BITS_PARAMETERS, 64),
+        ] {
+            assert!(parameters.max_supported_p() >= max_p);
+            for p in 2..=max_p {
+                assert!(
+                    parameters.max_noise_level(p) >= NoiseLevel::NOMINAL,
+                    "p = {p} under {parameters:?}"
+                );
+            }
+        }
+    }
+
+    fn check_repeated_bootstrapping(parameters: &GadgetParameters, p: u32) {
+        let (client_key, server_key) = gen_keys(parameters);
+        let refresh_encoding = Encoding::new_refresh(p);
+
+        for message in 0..2 {
+            let mut ct = GadgetEngine::with_thread_local_mut(|engine| {
+                engine.encrypt(message, &client_key, p)
+            });
+            for _ in 0..BOOTSTRAP_CHAIN_LENGTH {
+                ct = server_key.bootstrap(ct, &refresh_encoding).unwrap();
+                let decrypted =
+                    GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&ct, &client_key));
+                assert_eq!(decrypted, message, "p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn repeated_bootstrapping_2_bits() {
+        check_repeated_bootstrapping(&PLAINTEXT_2_BITS_PARAMETERS, 3);
+    }
+
+    #[test]
+    fn repeated_bootstrapping_3_bits() {
+        for p in [3, 5, 7] {
+            check_repeated_bootstrapping(&PLAINTEXT_3_BITS_PARAMETERS, p);
+        }
+    }
+
+    #[test]
+    fn repeated_bootstrapping_4_bits() {
+        check_repeated_bootstrapping(&PLAINTEXT_4_BITS_PARAMETERS, 15);
+    }
+
+    #[test]
+    fn repeated_bootstrapping_5_bits() {
+        check_repeated_bootstrapping(&PLAINTEXT_5_BITS_PARAMETERS, 31);
+    }
+}
@@ -0,0 +1,371 @@
+//! Two-stage pipelined evaluation of large batches of gates.
+//!
+//! The evaluation of a gate alternates between two very different kinds of work: the linear
+//! combination of its inputs and the keyswitch stream through large vectors and keys, bound by
+//! the memory bandwidth, while the blind rotation of the bootstrap is bound by the FFTs. Running
+//! them on separate pools of threads, connected by bounded channels, keeps both busy.
+//!
+//! With [`PBSOrder::KeyswitchBootstrap`] the keyswitch workers compute the linear combination and
+//! the keyswitch of each gate before handing it to the bootstrap workers. With
+//! [`PBSOrder::BootstrapKeyswitch`] the bootstrapped ciphertexts are handed back to the keyswitch
+//! workers, which keyswitch them in between the linear combinations of the next gates.
+
+use crate::boolean::engine::WithThreadLocalEngine;
+use crate::core_crypto::commons::parameters::PBSOrder;
+use crate::core_crypto::entities::*;
+use crate::core_crypto::prelude::keyswitch_lwe_ciphertext;
+use crate::gadget::ciphertext::{Ciphertext, NoiseLevel};
+use crate::gadget::encoding::Encoding;
+use crate::gadget::engine::{GadgetEngine, GadgetScalar};
+use crate::gadget::error::GadgetError;
+use crate::gadget::server_key::ServerKey;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Receiver};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Sizes of the stages of [`ServerKey::evaluate_gate_pipelined`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PipelineConfig {
+    /// Number of threads computing the linear combinations and the keyswitches.
+    pub keyswitch_threads: usize,
+    /// Number of threads computing the blind rotations.
+    pub bootstrap_threads: usize,
+    /// Number of keyswitched ciphertexts waiting for a bootstrap thread before the keyswitch
+    /// threads block.
+    pub channel_capacity: usize,
+}
+
+impl PipelineConfig {
+    pub fn new(
+        keyswitch_threads: usize,
+        bootstrap_threads: usize,
+        channel_capacity: usize,
+    ) -> Self {
+        Self {
+            keyswitch_threads,
+            bootstrap_threads,
+            channel_capacity,
+        }
+    }
+}
+
+impl Default for PipelineConfig {
+    /// One keyswitch thread and a bootstrap thread on each of the other available cores, with
+    /// room for two ciphertexts per bootstrap thread in between.
+    fn default() -> Self {
+        let bootstrap_threads = std::thread::available_parallelism()
+            .map_or(1, |threads| threads.get().saturating_sub(1))
+            .max(1);
+
+        Self::new(1, bootstrap_threads, 2 * bootstrap_threads)
+    }
+}
+
+/// The first failure of a pipelined evaluation, by index of the set of inputs.
+#[derive(Default)]
+struct Failure {
+    aborted: AtomicBool,
+    error: Mutex<Option<(usize, GadgetError)>>,
+}
+
+impl Failure {
+    fn record(&self, index: usize, error: GadgetError) {
+        self.aborted.store(true, Ordering::Relaxed);
+        let mut first = self.error.lock().unwrap();
+        if first
+            .as_ref()
+            .map_or(true, |(first_index, _)| index < *first_index)
+        {
+            *first = Some((index, error));
+        }
+    }
+
+    fn aborted(&self) -> bool {
+        self.aborted.load(Ordering::Relaxed)
+    }
+}
+
+/// Receives from a receiver shared by several workers, `None` once all its senders hung up.
+fn receive<T>(receiver: &Mutex<Receiver<T>>) -> Option<T> {
+    receiver.lock().unwrap().recv().ok()
+}
+
+impl<Scalar: GadgetScalar> ServerKey<Scalar> {
+    /// Evaluates the gate described by `encoding` on each set of inputs of `batch`, the outputs
+    /// being returned in the order of `batch`, on a two-stage pipeline of threads: see the
+    /// [module documentation](crate::gadget::pipeline).
+    ///
+    /// [`ServerKey::evaluate_gate_many`] with `pipelined` set is this function with the default
+    /// [`PipelineConfig`]. On failure, the error of the first failing set of trivial inputs is
+    /// returned, else the one of the first failing set of inputs of the pipeline.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config` has no keyswitch or no bootstrap thread.
+    pub fn evaluate_gate_pipelined(
+        &self,
+        batch: Vec<Vec<Ciphertext<Scalar>>>,
+        encoding: &Encoding,
+        config: PipelineConfig,
+    ) -> Result<Vec<Ciphertext<Scalar>>, GadgetError> {
+        assert!(
+            config.keyswitch_threads > 0,
+            "the pipeline needs a keyswitch thread"
+        );
+        assert!(
+            config.bootstrap_threads > 0,
+            "the pipeline needs a bootstrap thread"
+        );
+
+        GadgetEngine::with_thread_local_mut(|engine| {
+            engine.evaluate_gate_batch(self, encoding, batch, Some(config))
+        })
+    }
+
+    /// Bootstraps the linear combinations of each set of inputs of `batch` on the pipeline of
+    /// `config`, see [`ServerKey::evaluate_gate_pipelined`].
+    pub(crate) fn bootstrap_pipelined(
+        &self,
+        batch: Vec<Vec<Ciphertext<Scalar>>>,
+        encoding: &Encoding,
+        config: PipelineConfig,
+    ) -> Result<Vec<Ciphertext<Scalar>>, GadgetError> {
+        self.parameters.check_plaintext_modulus(encoding.p)?;
+
+        let pbs_order = self.parameters.pbs_order();
+        let lookup_table = self.lookup_table(encoding);
+        let output_count = batch.len();
+        let inputs = Mutex::new(batch.into_iter().enumerate());
+        let failure = Failure::default();
+
+        let (bootstrap_sender, bootstrap_receiver) =
+            sync_channel::<(usize, LweCiphertextOwned<Scalar>)>(config.channel_capacity);
+        let bootstrap_receiver = Mutex::new(bootstrap_receiver);
+        // only used with PBSOrder::BootstrapKeyswitch, bounded by the bootstrap channel
+        let (keyswitch_sender, keyswitch_receiver) =
+            channel::<(usize, LweCiphertextOwned<Scalar>)>();
+        let keyswitch_receiver = Mutex::new(keyswitch_receiver);
+        let (output_sender, output_receiver) = channel();
+
+        let keyswitch_after_bootstrap = |big_lwe: &LweCiphertextOwned<Scalar>| {
+            let mut output = LweCiphertext::new(
+                Scalar::ZERO,
+                self.ciphertext_lwe_size(),
+                self.ciphertext_modulus(),
+            );
+            keyswitch_lwe_ciphertext(&self.key_switching_key, big_lwe, &mut output);
+            output
+        };
+
+        std::thread::scope(|scope| {
+            for _ in 0..config.keyswitch_threads {
+                let bootstrap_sender = bootstrap_sender.clone();
+                let output_sender = output_sender.clone();
+                let (inputs, failure, keyswitch_receiver) =
+                    (&inputs, &failure, &keyswitch_receiver);
+                let keyswitch_after_bootstrap = &keyswitch_after_bootstrap;
+                scope.spawn(move || {
+                    let keyswitch_output =
+                        |(index, big_lwe): (usize, LweCiphertextOwned<Scalar>)| {
+                            let output = keyswitch_after_bootstrap(&big_lwe);
+                            let _ = output_sender.send((index, output));
+                        };
+
+                    while !failure.aborted() {
+                        // keyswitch the pending bootstrapped ciphertexts first, unless another
+                        // worker is already waiting on them
+                        while let Some(bootstrapped) = keyswitch_receiver
+                            .try_lock()
+                            .ok()
+                            .and_then(|receiver| receiver.try_recv().ok())
+                        {
+                            keyswitch_output(bootstrapped);
+                        }
+
+                        let next = inputs.lock().unwrap().next();
+                        let Some((index, input_ciphertexts)) = next else {
+                            break;
+                        };
+                        let sum_ct = match GadgetEngine::linear_combination(
+                            self,
+                            encoding,
+                            &input_ciphertexts,
+                        ) {
+                            Ok((sum_ct, _)) => sum_ct,
+                            Err(error) => {
+                                failure.record(index, error);
+                                break;
+                            }
+                        };
+                        let sum_ct = match pbs_order {
+                            PBSOrder::KeyswitchBootstrap => {
+                                let mut small_lwe = LweCiphertext::new(
+                                    Scalar::ZERO,
+                                    self.key_switching_key
+                                        .output_key_lwe_dimension()
+                                        .to_lwe_size(),
+                                    self.ciphertext_modulus(),
+                                );
+                                keyswitch_lwe_ciphertext(
+                                    &self.key_switching_key,
+                                    &sum_ct,
+                                    &mut small_lwe,
+                                );
+                                small_lwe
+                            }
+                            PBSOrder::BootstrapKeyswitch => sum_ct,
+                        };
+                        if bootstrap_sender.send((index, sum_ct)).is_err() {
+                            break;
+                        }
+                    }
+
+                    // the bootstrap workers stop once every keyswitch worker hung up, closing
+                    // the channel of the bootstrapped ciphertexts
+                    drop(bootstrap_sender);
+                    while let Some(bootstrapped) = receive(keyswitch_receiver) {
+                        keyswitch_output(bootstrapped);
+                    }
+                });
+            }
+
+            for _ in 0..config.bootstrap_threads {
+                let keyswitch_sender = keyswitch_sender.clone();
+                let output_sender = output_sender.clone();
+                let (bootstrap_receiver, failure, lookup_table) =
+                    (&bootstrap_receiver, &failure, &lookup_table);
+                scope.spawn(move || {
+                    while let Some((index, small_lwe)) = receive(bootstrap_receiver) {
+                        if failure.aborted() {
+                            continue;
+                        }
+
+                        let start = Instant::now();
+                        let mut big_lwe = LweCiphertext::new(
+                            Scalar::ZERO,
+                            self.bootstrapping_key.output_lwe_dimension().to_lwe_size(),
+                            self.ciphertext_modulus(),
+                        );
+                        GadgetEngine::with_thread_local_mut(|engine| {
+                            engine.bootstrapper().programmable_bootstrap(
+                                &small_lwe,
+                                &mut big_lwe,
+                                self,
+                                lookup_table,
+                            );
+                        });
+                        self.stats
+                            .record_bootstrap(std::slice::from_ref(encoding), start.elapsed());
+
+                        let _ = match pbs_order {
+                            PBSOrder::KeyswitchBootstrap => output_sender.send((index, big_lwe)),
+                            PBSOrder::BootstrapKeyswitch => keyswitch_sender.send((index, big_lwe)),
+                        };
+                    }
+                });
+            }
+
+            // the workers hold the only senders left, the loops below end once they all stopped
+            drop((bootstrap_sender, keyswitch_sender, output_sender));
+
+            let mut outputs: Vec<_> = (0..output_count).map(|_| None).collect();
+            for (index, output) in output_receiver {
+                outputs[index] = Some(Ciphertext::Encrypted(
+                    output,
+                    encoding.new_p,
+                    NoiseLevel::NOMINAL,
+                ));
+            }
+
+            if let Some((_, error)) = failure.error.lock().unwrap().take() {
+                return Err(error);
+            }
+
+            Ok(outputs
+                .into_iter()
+                .map(|output| output.expect("every set of inputs is evaluated"))
+                .collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conformance::ParameterSetConformant;
+    use crate::core_crypto::commons::parameters::EncryptionKeyChoice;
+    use crate::gadget::client_key::ClientKey;
+    use crate::gadget::gen_keys;
+    use crate::gadget::parameters::{GadgetParameters, PLAINTEXT_3_BITS_PARAMETERS};
+
+    fn and_batch(client_key: &ClientKey, inputs: &[(u32, u32)]) -> Vec<Vec<Ciphertext>> {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            inputs
+                .iter()
+                .map(|(lhs, rhs)| {
+                    vec![
+                        engine.encrypt(*lhs, client_key, 3),
+                        engine.encrypt(*rhs, client_key, 3),
+                    ]
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn pipelined_evaluation_matches_truth_table() {
+        let and_encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let inputs: Vec<_> = (0..12).map(|i| (i & 1, (i >> 1) & 1)).collect();
+
+        for encryption_key_choice in [EncryptionKeyChoice::Small, EncryptionKeyChoice::Big] {
+            let parameters = GadgetParameters {
+                encryption_key_choice,
+                ..PLAINTEXT_3_BITS_PARAMETERS
+            };
+            let (client_key, server_key) = gen_keys(&parameters);
+            server_key.enable_stats(true);
+
+            for config in [PipelineConfig::new(1, 1, 1), PipelineConfig::new(2, 3, 4)] {
+                server_key.reset_stats();
+                let outputs = server_key
+                    .evaluate_gate_pipelined(and_batch(&client_key, &inputs), &and_encoding, config)
+                    .unwrap();
+
+                assert_eq!(outputs.len(), inputs.len());
+                assert_eq!(server_key.stats().pbs_count, inputs.len() as u64);
+                GadgetEngine::with_thread_local_mut(|engine| {
+                    for ((lhs, rhs), output) in inputs.iter().zip(outputs.iter()) {
+                        assert!(output.is_conformant(&parameters));
+                        assert_eq!(engine.decrypt(output, &client_key), lhs & rhs);
+                    }
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn pipelined_evaluation_reports_first_error() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let and_encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+
+        let mut batch = and_batch(&client_key, &[(0, 1); 8]);
+        batch[3].pop();
+        batch[6][0] =
+            GadgetEngine::with_thread_local_mut(|engine| engine.encrypt(0, &client_key, 5));
+        assert_eq!(
+            server_key
+                .evaluate_gate_pipelined(batch, &and_encoding, PipelineConfig::new(2, 2, 1))
+                .unwrap_err(),
+            GadgetError::PinCountMismatch {
+                expected: 2,
+                actual: 1
+            }
+        );
+
+        assert!(server_key
+            .evaluate_gate_pipelined(vec![], &and_encoding, PipelineConfig::default())
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,229 @@
+//! Private information retrieval from a cleartext database.
+//!
+//! The client encrypts the bits of the index of the record it wants to read, the server returns
+//! the encryption of that record without learning which one it is. The database is public to
+//! the server, which selects the record with a tree of muxes over trivial encryptions, see
+//! [`ServerKey::pir_retrieve`].
+
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::error::GadgetError;
+use crate::gadget::server_key::ServerKey;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Number of index bits resolved by the leaves of the selection tree, whose functions of the
+/// index bits are shared between every record and every column.
+const LEAF_INDEX_BITS: usize = 2;
+
+/// A cleartext database of records of the same width.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PirDatabase {
+    width: usize,
+    /// Bits of each record, the least significant bit first
+    records: Vec<Vec<bool>>,
+}
+
+impl PirDatabase {
+    /// Builds a database holding the `width` least significant bits of each value of
+    /// `values`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is larger than 64.
+    pub fn from_values(values: &[u64], width: usize) -> PirDatabase {
+        assert!(width <= 64, "records of {width} bits do not fit in a u64");
+        PirDatabase {
+            width,
+            records: values
+                .iter()
+                .map(|value| (0..width).map(|bit| (value >> bit) & 1 == 1).collect())
+                .collect(),
+        }
+    }
+
+    /// Builds a database from the bits of its records, the least significant bit first.
+    ///
+    /// Fails with [`GadgetError::BitWidthMismatch`] if the records have different widths.
+    pub fn from_records(records: Vec<Vec<bool>>) -> Result<PirDatabase, GadgetError> {
+        let width = records.first().map_or(0, Vec::len);
+        if let Some(record) = records.iter().find(|record| record.len() != width) {
+            return Err(GadgetError::BitWidthMismatch {
+                lhs: width,
+                rhs: record.len(),
+            });
+        }
+
+        Ok(PirDatabase { width, records })
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns the number of bits of each record.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of index bits addressing every record.
+    pub fn index_bits(&self) -> usize {
+        self.records.len().next_power_of_two().trailing_zeros() as usize
+    }
+
+    /// Returns bit `column` of record `index`, records past the end of the database being 0.
+    fn bit(&self, index: usize, column: usize) -> bool {
+        self.records.get(index).is_some_and(|record| record[column])
+    }
+}
+
+impl ServerKey {
+    /// Returns the encryption of the record of `database` at the encrypted `index_bits`, given
+    /// with the least significant bit first. Indices past the end of the database read a
+    /// record of zeros.
+    ///
+    /// Every bit of the selected record is a boolean function of the index bits. The functions
+    /// of the 2 least significant index bits are computed first, once for every record and
+    /// column they appear in: there are at most 16 of them, costing at most 10 bootstraps in
+    /// total. The remaining `k - 2` index bits select among the groups of 4 records with
+    /// [`ServerKey::select`], for `2w(2^(k - 2) - 1)` bootstraps on records of `w` bits.
+    ///
+    /// Fails with [`GadgetError::TableSizeMismatch`] if `index_bits` cannot address every
+    /// record of `database`.
+    pub fn pir_retrieve(
+        &self,
+        database: &PirDatabase,
+        index_bits: &[Ciphertext],
+    ) -> Result<Vec<Ciphertext>, GadgetError> {
+        let capacity = 1usize
+            .checked_shl(index_bits.len() as u32)
+            .unwrap_or(usize::MAX);
+        if database.len() > capacity {
+            return Err(GadgetError::TableSizeMismatch {
+                expected: capacity,
+                actual: database.len(),
+            });
+        }
+
+        let (leaf_index_bits, tree_index_bits) =
+            index_bits.split_at(index_bits.len().min(LEAF_INDEX_BITS));
+        let leaf_size = 1 << leaf_index_bits.len();
+        let group_count = capacity / leaf_size;
+
+        // truth table of bit `column` of the records of `group` over the leaf index bits
+        let truth_table = |group: usize, column: usize| {
+            (0..leaf_size)
+                .filter(|row| database.bit(group * leaf_size + row, column))
+                .fold(0u8, |truth_table, row| truth_table | 1 << row)
+        };
+
+        let truth_tables: HashSet<u8> = (0..group_count)
+            .flat_map(|group| (0..database.width).map(move |column| truth_table(group, column)))
+            .collect();
+        let leaves = truth_tables
+            .into_par_iter()
+            .map(|table| Ok((table, self.truth_table_gate(table, leaf_index_bits)?)))
+            .collect::<Result<HashMap<_, _>, GadgetError>>()?;
+
+        let table: Vec<Vec<Ciphertext>> = (0..group_count)
+            .map(|group| {
+                (0..database.width)
+                    .map(|column| leaves[&truth_table(group, column)].clone())
+                    .collect()
+            })
+            .collect();
+        self.select(&table, tree_index_bits)
+    }
+
+    /// Evaluates the function of at most 2 `bits` whose output on row `bits[0] + 2 * bits[1]`
+    /// is bit `row` of `truth_table`, with at most one bootstrap.
+    fn truth_table_gate(
+        &self,
+        truth_table: u8,
+        bits: &[Ciphertext],
+    ) -> Result<Ciphertext, GadgetError> {
+        match (bits, truth_table) {
+            ([], _) => Ok(Ciphertext::Trivial(truth_table & 1 == 1)),
+            ([_], 0b00) | ([_, _], 0b0000) => Ok(Ciphertext::Trivial(false)),
+            ([_], 0b11) | ([_, _], 0b1111) => Ok(Ciphertext::Trivial(true)),
+            ([b0], 0b10) | ([b0, _], 0b1010) => Ok(b0.clone()),
+            ([b0], 0b01) | ([b0, _], 0b0101) => self.not(b0),
+            ([_, b1], 0b1100) => Ok(b1.clone()),
+            ([_, b1], 0b0011) => self.not(b1),
+            ([b0, b1], 0b1000) => self.and(b0, b1),
+            ([b0, b1], 0b0111) => self.nand(b0, b1),
+            ([b0, b1], 0b1110) => self.or(b0, b1),
+            ([b0, b1], 0b0001) => self.nor(b0, b1),
+            ([b0, b1], 0b0110) => self.xor(b0, b1),
+            ([b0, b1], 0b1001) => self.xnor(b0, b1),
+            ([b0, b1], 0b0010) => self.andnot(b0, b1),
+            ([b0, b1], 0b0100) => self.andnot(b1, b0),
+            ([b0, b1], 0b1011) => self.ornot(b0, b1),
+            ([b0, b1], 0b1101) => self.imply(b0, b1),
+            _ => unreachable!("truth table {truth_table:#b} of {} bits", bits.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadget::boolean::BOOLEAN_PARAMETERS;
+    use crate::gadget::gen_keys;
+    use crate::gadget::integer::GadgetUint;
+    use rand::Rng;
+
+    #[test]
+    fn truth_table_gates() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let rows = [(false, false), (true, false), (false, true), (true, true)];
+        for truth_table in 0..16u8 {
+            for (row, (b0, b1)) in rows.into_iter().enumerate() {
+                let bits = [client_key.encrypt(b0), client_key.encrypt(b1)];
+                let out = server_key.truth_table_gate(truth_table, &bits).unwrap();
+                assert_eq!(
+                    client_key.decrypt(&out),
+                    (truth_table >> row) & 1 == 1,
+                    "truth table {truth_table:#06b}, row {row}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pir_retrieves_records() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let mut rng = rand::thread_rng();
+        let values: Vec<u64> = (0..6).map(|_| rng.gen::<u64>() & 0xf).collect();
+        let database = PirDatabase::from_values(&values, 4);
+        assert_eq!(database.index_bits(), 3);
+
+        server_key.enable_stats(true);
+        for index in 0..8 {
+            let index_ct = GadgetUint::<3>::encrypt(index as u64, &client_key);
+            let record = server_key.pir_retrieve(&database, index_ct.bits()).unwrap();
+            let record = GadgetUint::<4>::from_bits(record).decrypt(&client_key);
+            assert_eq!(
+                record,
+                values.get(index).copied().unwrap_or(0),
+                "index {index}"
+            );
+        }
+        // the leaves cost at most 10 bootstraps, a single level of muxes 8 on 4 bits
+        assert!(server_key.stats().pbs_count <= 8 * (10 + 8));
+
+        let index_ct = GadgetUint::<2>::encrypt(0, &client_key);
+        assert_eq!(
+            server_key
+                .pir_retrieve(&database, index_ct.bits())
+                .unwrap_err(),
+            GadgetError::TableSizeMismatch {
+                expected: 4,
+                actual: 6
+            }
+        );
+    }
+}
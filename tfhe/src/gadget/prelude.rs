@@ -0,0 +1,9 @@
+//! Items needed to write boolean circuits with the high level [`FheBool`] API.
+
+pub use super::boolean::BOOLEAN_PARAMETERS;
+pub use super::client_key::ClientKey;
+pub use super::high_level::{set_server_key, unset_server_key, FheBool};
+pub use super::server_key::ServerKey;
+pub use super::{
+    gen_keys, gen_keys_from_seed, gen_keys_multi, gen_keys_multi_from_seed, try_gen_keys,
+};
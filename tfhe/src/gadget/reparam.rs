@@ -0,0 +1,202 @@
+//! Re-parameterization of ciphertexts between two parameter sets.
+//!
+//! A [`ReparamKey`] moves ciphertexts of the client key of one parameter set, e.g.
+//! [`PLAINTEXT_2_BITS_PARAMETERS`](super::parameters::PLAINTEXT_2_BITS_PARAMETERS), to the client
+//! key of another one, e.g.
+//! [`PLAINTEXT_3_BITS_PARAMETERS`](super::parameters::PLAINTEXT_3_BITS_PARAMETERS), so that a
+//! circuit can evaluate its cheap gates at a small plaintext modulus and its wide gates at a
+//! larger one without decrypting in between. The keys of both sets are typically generated with
+//! [`gen_keys_multi`](super::gen_keys_multi).
+
+use serde::{Deserialize, Serialize};
+
+use crate::boolean::engine::WithThreadLocalEngine;
+use crate::core_crypto::prelude::{keyswitch_lwe_ciphertext, LweCiphertext, LweKeyswitchKeyOwned};
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::encoding::Encoding;
+use crate::gadget::engine::{GadgetEngine, GadgetScalar};
+use crate::gadget::error::GadgetError;
+use crate::gadget::parameters::GadgetParameters;
+use crate::gadget::server_key::ServerKey;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReparamKey<Scalar: GadgetScalar = u32> {
+    pub(crate) key_switching_key: LweKeyswitchKeyOwned<Scalar>,
+    pub(crate) parameters_from: GadgetParameters,
+    pub(crate) parameters_to: GadgetParameters,
+}
+
+impl<Scalar: GadgetScalar> ReparamKey<Scalar> {
+    /// Generates the re-parameterization key from the encryption key of `client_key_from` to the
+    /// one of `client_key_to`, with the keyswitching parameters and the noise of the parameter
+    /// set of `client_key_to`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ciphertext moduli of the two client keys differ.
+    pub fn new(client_key_from: &ClientKey<Scalar>, client_key_to: &ClientKey<Scalar>) -> Self {
+        assert_eq!(
+            client_key_from.ciphertext_modulus(),
+            client_key_to.ciphertext_modulus(),
+            "client keys must share the same ciphertext modulus"
+        );
+
+        let parameters_to = client_key_to.parameters;
+        let (input_key, _) = client_key_from.encryption_key();
+        let (output_key, output_noise) = client_key_to.encryption_key();
+        let key_switching_key = GadgetEngine::with_thread_local_mut(|engine| {
+            engine.create_keyswitch_key(
+                &input_key,
+                &output_key,
+                parameters_to.ks_base_log,
+                parameters_to.ks_level,
+                output_noise,
+                client_key_to.ciphertext_modulus(),
+            )
+        });
+
+        Self {
+            key_switching_key,
+            parameters_from: client_key_from.parameters,
+            parameters_to,
+        }
+    }
+
+    /// Returns the parameter set of the ciphertexts the key takes.
+    pub fn parameters_from(&self) -> &GadgetParameters {
+        &self.parameters_from
+    }
+
+    /// Returns the parameter set of the ciphertexts the key returns.
+    pub fn parameters_to(&self) -> &GadgetParameters {
+        &self.parameters_to
+    }
+}
+
+impl<Scalar: GadgetScalar> ServerKey<Scalar> {
+    /// Moves `ct`, encrypted under the source parameter set of `reparam_key`, to the parameter
+    /// set of this server key: `ct` is keyswitched to the client key of this server key then
+    /// bootstrapped with `encoding`, whose `p` is the plaintext modulus of `ct` and whose
+    /// `new_p` is the plaintext modulus of the output, e.g. to widen the messages of `ct` to
+    /// the larger moduli this parameter set supports. Trivial ciphertexts are returned as is.
+    ///
+    /// Fails with [`GadgetError::ReparamKeyMismatch`] if `reparam_key` does not go to the
+    /// parameters of this server key or if `ct` is not a ciphertext of its source parameters.
+    pub fn reparameterize(
+        &self,
+        ct: &Ciphertext<Scalar>,
+        reparam_key: &ReparamKey<Scalar>,
+        encoding: &Encoding,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        if reparam_key.parameters_to != self.parameters {
+            return Err(GadgetError::ReparamKeyMismatch(
+                "the parameters of the server key".to_string(),
+            ));
+        }
+
+        match ct {
+            Ciphertext::Encrypted(lwe, plaintext_modulus, noise_level) => {
+                let input_lwe_size = reparam_key
+                    .key_switching_key
+                    .input_key_lwe_dimension()
+                    .to_lwe_size();
+                if lwe.lwe_size() != input_lwe_size {
+                    return Err(GadgetError::ReparamKeyMismatch(format!(
+                        "the ciphertext: LWE size {} instead of {}",
+                        lwe.lwe_size().0,
+                        input_lwe_size.0
+                    )));
+                }
+
+                let mut output = LweCiphertext::new(
+                    Scalar::ZERO,
+                    reparam_key.key_switching_key.output_lwe_size(),
+                    lwe.ciphertext_modulus(),
+                );
+                keyswitch_lwe_ciphertext(&reparam_key.key_switching_key, lwe, &mut output);
+                self.stats.record_keyswitch();
+
+                self.bootstrap(
+                    Ciphertext::Encrypted(output, *plaintext_modulus, *noise_level),
+                    encoding,
+                )
+            }
+            Ciphertext::Trivial(value) => Ok(Ciphertext::Trivial(*value)),
+            Ciphertext::Placeholder => Err(GadgetError::PlaceholderCiphertext),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use concrete_csprng::seeders::Seed;
+
+    use super::*;
+    use crate::gadget::gen_keys_multi_from_seed;
+    use crate::gadget::parameters::{PLAINTEXT_2_BITS_PARAMETERS, PLAINTEXT_3_BITS_PARAMETERS};
+
+    #[test]
+    fn reparameterize_from_2_to_3_bits() -> Result<(), GadgetError> {
+        let mut keys = gen_keys_multi_from_seed(
+            &[PLAINTEXT_2_BITS_PARAMETERS, PLAINTEXT_3_BITS_PARAMETERS],
+            Seed(96),
+        );
+        let (client_key_to, server_key_to) = keys.pop().unwrap();
+        let (client_key_from, server_key_from) = keys.pop().unwrap();
+        let reparam_key = ReparamKey::new(&client_key_from, &client_key_to);
+
+        // booleans are encoded as 1 (false) and 2 (true) modulo 3
+        let boolean_refresh =
+            Encoding::new(2, 1, vec![1], vec![2], vec![0, 1], vec![2], 1, 2, 3, 3);
+        for (lhs, rhs) in [(false, false), (false, true), (true, false), (true, true)] {
+            let out = server_key_from
+                .and(&client_key_from.encrypt(lhs), &client_key_from.encrypt(rhs))?;
+            let out = server_key_to.reparameterize(&out, &reparam_key, &boolean_refresh)?;
+            assert_eq!(client_key_to.decrypt(&out), lhs && rhs);
+
+            // the moved ciphertext goes through the gates of the destination parameters
+            let out = server_key_to.xor(&out, &client_key_to.encrypt(true))?;
+            assert_eq!(client_key_to.decrypt(&out), !(lhs && rhs));
+        }
+
+        // widens the booleans to messages 0 and 4 modulo 5
+        let widen = Encoding::new(2, 1, vec![1], vec![2], vec![0, 1], vec![2], 0, 4, 3, 5);
+        for value in [false, true] {
+            let out = server_key_to.reparameterize(
+                &client_key_from.encrypt(value),
+                &reparam_key,
+                &widen,
+            )?;
+            assert_eq!(client_key_to.decrypt_many(&[out], 5), [4 * value as u32]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reparameterize_with_mismatching_keys() {
+        let mut keys = gen_keys_multi_from_seed(
+            &[PLAINTEXT_2_BITS_PARAMETERS, PLAINTEXT_3_BITS_PARAMETERS],
+            Seed(97),
+        );
+        let (client_key_to, server_key_to) = keys.pop().unwrap();
+        let (client_key_from, server_key_from) = keys.pop().unwrap();
+        let reparam_key = ReparamKey::new(&client_key_from, &client_key_to);
+        let refresh = Encoding::new_refresh(3);
+
+        // the key goes to the 3 bits parameters, not to the 2 bits ones
+        let ct = client_key_from.encrypt(true);
+        assert!(matches!(
+            server_key_from.reparameterize(&ct, &reparam_key, &refresh),
+            Err(GadgetError::ReparamKeyMismatch(_))
+        ));
+
+        // the ciphertext already is under the 3 bits parameters
+        let ct = client_key_to.encrypt(true);
+        assert!(matches!(
+            server_key_to.reparameterize(&ct, &reparam_key, &refresh),
+            Err(GadgetError::ReparamKeyMismatch(_))
+        ));
+    }
+}
@@ -1,37 +1,2880 @@
 use crate::boolean::engine::WithThreadLocalEngine;
+use crate::conformance::ParameterSetConformant;
+use crate::core_crypto::commons::parameters::{
+    CiphertextModulus, DecompositionBaseLog, DecompositionLevelCount, GlweSize, LweDimension,
+    LweSize, PBSOrder, PolynomialSize, ThreadCount,
+};
 use crate::core_crypto::entities::*;
-use crate::gadget::ciphertext::Ciphertext;
+use crate::core_crypto::prelude::misc::check_content_respects_mod;
+use crate::core_crypto::prelude::{
+    allocate_and_trivially_encrypt_new_lwe_ciphertext, keyswitch_lwe_ciphertext,
+};
+use crate::gadget::ciphertext::{Ciphertext, CiphertextList, NoiseLevel};
 use crate::gadget::client_key::ClientKey;
-use crate::gadget::engine::GadgetEngine;
-use std::error::Error;
+use crate::gadget::engine::{
+    classic_fourier_bootstrapping_key, encode, encode_coefficient,
+    multi_bit_fourier_bootstrapping_key, EvaluationContext, GadgetEngine, GadgetScalar,
+};
+use crate::gadget::error::GadgetError;
+use crate::gadget::ntt::{ntt_ggsw_ciphertext_size, NttBootstrapKey, NTT_MODULUS};
+use crate::gadget::parameters::{GadgetPackingParameters, GadgetParameters, PbsBackend};
+use crate::gadget::stats::{ServerKeyStats, StatsCollector};
+use crate::named::Named;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 
-use super::encoding::Encoding;
+use super::encoding::{Encoding, GatePlan, MultiOutputEncoding};
 
-pub struct ServerKey {
-    pub(crate) bootstrapping_key: FourierLweBootstrapKeyOwned,
-    pub(crate) key_switching_key: LweKeyswitchKeyOwned<u32>,
+/// A precomputed accumulator (test vector) for an [`Encoding`].
+///
+/// The accumulator is a trivial GLWE encryption of the test polynomial mapping every window of
+/// the p-encoding space to its output value. Building it is O(N) in the polynomial size, so it
+/// is cached inside the [`ServerKey`] and reused across bootstraps sharing the same encoding.
+pub struct LookupTable<Scalar: GadgetScalar = u32> {
+    pub(crate) acc: GlweCiphertextOwned<Scalar>,
 }
 
-impl ServerKey {
-    pub fn new(client_key: &ClientKey) -> ServerKey {
-        GadgetEngine::with_thread_local_mut(|engine| engine.create_server_key(client_key))
+impl<Scalar: GadgetScalar> LookupTable<Scalar> {
+    pub fn acc(&self) -> &GlweCiphertextOwned<Scalar> {
+        &self.acc
+    }
+}
+
+/// Maximum number of lookup tables cached by a [`ServerKey`].
+///
+/// A lookup table holds a GLWE ciphertext of `(k + 1) * N` torus elements, at most a few tens of
+/// KiB for the provided parameter sets, so the cache of a server evaluating circuits sent by its
+/// clients stays below a few tens of MiB however many encodings they use.
+pub const LOOKUP_TABLE_CACHE_CAPACITY: usize = 1024;
+
+/// A cached lookup table and the stamp of its last use.
+type CacheEntry<Scalar> = (Arc<LookupTable<Scalar>>, AtomicU64);
+
+/// Lookup tables of the encodings recently seen by a [`ServerKey`], evicting the least recently
+/// used one when full.
+pub(crate) struct LookupTableCache<Scalar: GadgetScalar> {
+    capacity: usize,
+    /// Source of the last use stamps of the entries
+    clock: AtomicU64,
+    entries: RwLock<HashMap<Encoding, CacheEntry<Scalar>>>,
+}
+
+impl<Scalar: GadgetScalar> Default for LookupTableCache<Scalar> {
+    fn default() -> Self {
+        Self::new(LOOKUP_TABLE_CACHE_CAPACITY)
+    }
+}
+
+impl<Scalar: GadgetScalar> LookupTableCache<Scalar> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "a lookup table cache must hold at least one table"
+        );
+        Self {
+            capacity,
+            clock: AtomicU64::new(0),
+            entries: RwLock::default(),
+        }
+    }
+
+    fn get(&self, encoding: &Encoding) -> Option<Arc<LookupTable<Scalar>>> {
+        let entries = self.entries.read().unwrap();
+        let (lookup_table, last_use) = entries.get(encoding)?;
+        last_use.store(
+            self.clock.fetch_add(1, Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
+        Some(lookup_table.clone())
+    }
+
+    /// Caches `lookup_table` unless another thread cached one for `encoding` in the meantime,
+    /// returning the cached one.
+    fn insert(
+        &self,
+        encoding: &Encoding,
+        lookup_table: Arc<LookupTable<Scalar>>,
+    ) -> Arc<LookupTable<Scalar>> {
+        let mut entries = self.entries.write().unwrap();
+        let now = self.clock.fetch_add(1, Ordering::Relaxed);
+        if let Some((cached, last_use)) = entries.get(encoding) {
+            last_use.store(now, Ordering::Relaxed);
+            return cached.clone();
+        }
+
+        if entries.len() >= self.capacity {
+            let least_recently_used = entries
+                .iter()
+                .min_by_key(|(_, (_, last_use))| last_use.load(Ordering::Relaxed))
+                .map(|(encoding, _)| encoding.clone());
+            if let Some(least_recently_used) = least_recently_used {
+                entries.remove(&least_recently_used);
+            }
+        }
+        entries.insert(
+            encoding.clone(),
+            (lookup_table.clone(), AtomicU64::new(now)),
+        );
+        lookup_table
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    fn clear(&self) {
+        self.entries.write().unwrap().clear();
+    }
+}
+
+/// The bootstrapping key of a [`ServerKey`], in the Fourier domain or in the NTT domain.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum GadgetBootstrappingKey {
+    Classic(FourierLweBootstrapKeyOwned),
+    /// Multi-bit key, see [`GadgetParameters::grouping_factor`]. The blind rotation combines the
+    /// GGSW ciphertexts of each group on `thread_count` threads.
+    MultiBit {
+        fourier_bsk: FourierLweMultiBitBootstrapKeyOwned,
+        thread_count: ThreadCount,
+    },
+    /// Key encrypted modulo a prime, see [`PbsBackend::Ntt`].
+    Ntt(NttBootstrapKey),
+}
+
+impl GadgetBootstrappingKey {
+    pub fn input_lwe_dimension(&self) -> LweDimension {
+        match self {
+            Self::Classic(fourier_bsk) => fourier_bsk.input_lwe_dimension(),
+            Self::MultiBit { fourier_bsk, .. } => fourier_bsk.input_lwe_dimension(),
+            Self::Ntt(ntt_bsk) => ntt_bsk.input_lwe_dimension(),
+        }
+    }
+
+    pub fn output_lwe_dimension(&self) -> LweDimension {
+        match self {
+            Self::Classic(fourier_bsk) => fourier_bsk.output_lwe_dimension(),
+            Self::MultiBit { fourier_bsk, .. } => fourier_bsk.output_lwe_dimension(),
+            Self::Ntt(ntt_bsk) => ntt_bsk.output_lwe_dimension(),
+        }
+    }
+
+    pub fn glwe_size(&self) -> GlweSize {
+        match self {
+            Self::Classic(fourier_bsk) => fourier_bsk.glwe_size(),
+            Self::MultiBit { fourier_bsk, .. } => fourier_bsk.glwe_size(),
+            Self::Ntt(ntt_bsk) => ntt_bsk.glwe_size(),
+        }
+    }
+
+    pub fn polynomial_size(&self) -> PolynomialSize {
+        match self {
+            Self::Classic(fourier_bsk) => fourier_bsk.polynomial_size(),
+            Self::MultiBit { fourier_bsk, .. } => fourier_bsk.polynomial_size(),
+            Self::Ntt(ntt_bsk) => ntt_bsk.polynomial_size(),
+        }
+    }
+
+    pub fn decomposition_base_log(&self) -> DecompositionBaseLog {
+        match self {
+            Self::Classic(fourier_bsk) => fourier_bsk.decomposition_base_log(),
+            Self::MultiBit { fourier_bsk, .. } => fourier_bsk.decomposition_base_log(),
+            Self::Ntt(ntt_bsk) => ntt_bsk.decomposition_base_log(),
+        }
+    }
+
+    pub fn decomposition_level_count(&self) -> DecompositionLevelCount {
+        match self {
+            Self::Classic(fourier_bsk) => fourier_bsk.decomposition_level_count(),
+            Self::MultiBit { fourier_bsk, .. } => fourier_bsk.decomposition_level_count(),
+            Self::Ntt(ntt_bsk) => ntt_bsk.decomposition_level_count(),
+        }
+    }
+}
+
+/// Instruction set of the negacyclic FFT and of the torus conversions of the bootstraps, as
+/// reported by [`ServerKey::diagnose_fft_kernels`].
+///
+/// This is a diagnostic only, nothing selects kernels through it: the gadget bootstraps run on
+/// the FFT of [`core_crypto`](crate::core_crypto), whose plans and torus conversions dispatch to
+/// these kernels at runtime. The AVX-512 kernels are compiled in at build time with the
+/// `nightly-avx512` feature, then picked when the CPU supports them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FftKernels {
+    /// AVX-512F and AVX-512DQ kernels, selected only when tfhe is built with the `nightly-avx512`
+    /// feature (which requires a nightly compiler).
+    Avx512,
+    /// AVX2 and FMA kernels.
+    Avx2Fma,
+    /// Portable scalar code, on CPUs without AVX2 and FMA and on non x86 targets.
+    Scalar,
+}
+
+impl FftKernels {
+    /// Detects the kernels selected on this CPU, the same way the FFT dispatches at runtime.
+    pub fn detect() -> Self {
+        #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+        {
+            #[cfg(feature = "nightly-avx512")]
+            if pulp::x86::V4::try_new().is_some() {
+                return Self::Avx512;
+            }
+            if pulp::x86::V3::try_new().is_some() {
+                return Self::Avx2Fma;
+            }
+        }
+        Self::Scalar
+    }
+}
+
+/// Returns the number of GGSW ciphertexts of a bootstrapping key generated with `parameters`,
+/// `None` if the grouping factor does not divide the LWE dimension.
+fn bootstrapping_key_ggsw_count(parameters: &GadgetParameters) -> Option<usize> {
+    let lwe_dimension = parameters.lwe_dimension.0;
+    match parameters.grouping_factor {
+        None => Some(lwe_dimension),
+        Some(grouping_factor)
+            if grouping_factor.0 > 0 && lwe_dimension % grouping_factor.0 == 0 =>
+        {
+            Some(lwe_dimension / grouping_factor.0 * grouping_factor.ggsw_per_multi_bit_element().0)
+        }
+        Some(_) => None,
+    }
+}
+
+/// Returns the LWE dimension of the ciphertexts extracted from the bootstrap accumulators, the
+/// input dimension of the key switching key.
+fn big_lwe_dimension(parameters: &GadgetParameters) -> LweDimension {
+    parameters
+        .glwe_dimension
+        .to_equivalent_lwe_dimension(parameters.polynomial_size)
+}
+
+/// Checks the variant, dimensions, decomposition and size of the key against `parameters`.
+impl ParameterSetConformant for GadgetBootstrappingKey {
+    type ParameterSet = GadgetParameters;
+
+    fn is_conformant(&self, parameters: &GadgetParameters) -> bool {
+        let Some(ggsw_count) = bootstrapping_key_ggsw_count(parameters) else {
+            return false;
+        };
+
+        let glwe_size = parameters.glwe_dimension.to_glwe_size();
+        let fourier_ggsw_size = || {
+            fourier_ggsw_ciphertext_size(
+                glwe_size,
+                parameters.polynomial_size.to_fourier_polynomial_size(),
+                parameters.pbs_level,
+            )
+        };
+        let (data_len, ggsw_size) = match (self, parameters.grouping_factor, parameters.pbs_backend)
+        {
+            (Self::Classic(fourier_bsk), None, PbsBackend::Fft) => {
+                (fourier_bsk.as_view().data().len(), fourier_ggsw_size())
+            }
+            (
+                Self::MultiBit {
+                    fourier_bsk,
+                    thread_count,
+                },
+                Some(grouping_factor),
+                PbsBackend::Fft,
+            ) if fourier_bsk.grouping_factor() == grouping_factor && thread_count.0 > 0 => {
+                (fourier_bsk.as_view().data().len(), fourier_ggsw_size())
+            }
+            (Self::Ntt(ntt_bsk), None, PbsBackend::Ntt)
+                if ntt_bsk.data().iter().all(|&value| value < NTT_MODULUS) =>
+            {
+                (
+                    ntt_bsk.data().len(),
+                    ntt_ggsw_ciphertext_size(
+                        glwe_size,
+                        parameters.polynomial_size,
+                        parameters.pbs_level,
+                    ),
+                )
+            }
+            _ => return false,
+        };
+
+        self.input_lwe_dimension() == parameters.lwe_dimension
+            && self.glwe_size() == glwe_size
+            && self.polynomial_size() == parameters.polynomial_size
+            && self.decomposition_base_log() == parameters.pbs_base_log
+            && self.decomposition_level_count() == parameters.pbs_level
+            && data_len == ggsw_count * ggsw_size
+    }
+}
+
+/// A structure containing the server public key.
+///
+/// The server key is generated by the client and is meant to be published: the client
+/// sends it to the server so it can evaluate gates homomorphically.
+///
+/// In more details, it contains:
+/// * `bootstrapping_key` - a public key, used to perform the bootstrapping operation, classic or
+///   multi-bit.
+/// * `key_switching_key` - a public key, used to perform the key-switching operation.
+/// * `parameters` - the cryptographic parameter set the keys were generated with.
+/// * `packing_key` - an optional public key packing LWE ciphertexts in GLWE ciphertexts, see
+///   [`ServerKey::enable_packing`].
+///
+/// The lookup table cache and the statistics are not serialized, the lookup tables are rebuilt
+/// lazily on the receiving side.
+///
+/// The keys live on a 32 bits torus by default, see [`GadgetScalar`].
+///
+/// A server key is `Send + Sync`: gates can be evaluated concurrently from several threads
+/// sharing one key, e.g. behind an [`Arc`]. The key itself is never mutated by an evaluation,
+/// the buffers of the bootstraps belong to the engine of each thread and the lookup table cache
+/// and the statistics are synchronized.
+#[derive(Serialize, Deserialize)]
+// the skipped lookup table cache would otherwise require `Scalar: Default`
+#[serde(bound(deserialize = "Scalar: Deserialize<'de>"))]
+pub struct ServerKey<Scalar: GadgetScalar = u32> {
+    pub(crate) bootstrapping_key: GadgetBootstrappingKey,
+    pub(crate) key_switching_key: LweKeyswitchKeyOwned<Scalar>,
+    pub(crate) parameters: GadgetParameters,
+    pub(crate) packing_key: Option<LwePackingKeyswitchKeyOwned<Scalar>>,
+    /// Lookup tables already generated for the encodings recently seen by this key.
+    #[serde(skip)]
+    pub(crate) lookup_tables: LookupTableCache<Scalar>,
+    #[serde(skip)]
+    pub(crate) stats: StatsCollector,
+}
+
+// Sharing keys and ciphertexts between threads is part of the API, see [`ServerKey`].
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ServerKey<u32>>();
+    assert_send_sync::<ServerKey<u64>>();
+    assert_send_sync::<Ciphertext<u32>>();
+    assert_send_sync::<Ciphertext<u64>>();
+};
+
+impl<Scalar: GadgetScalar> Clone for ServerKey<Scalar> {
+    fn clone(&self) -> Self {
+        Self {
+            bootstrapping_key: self.bootstrapping_key.clone(),
+            key_switching_key: self.key_switching_key.clone(),
+            parameters: self.parameters,
+            packing_key: self.packing_key.clone(),
+            lookup_tables: Default::default(),
+            stats: Default::default(),
+        }
+    }
+}
+
+/// Checks that a server key received from a client, e.g. after deserialization, was generated
+/// with `parameters`: the parameters of the key, the shape of its bootstrapping, key switching
+/// and packing keys and the ciphertext modulus of the latter two must all match.
+impl<Scalar: GadgetScalar> ParameterSetConformant for ServerKey<Scalar> {
+    type ParameterSet = GadgetParameters;
+
+    fn is_conformant(&self, parameters: &GadgetParameters) -> bool {
+        let Ok(ciphertext_modulus) = parameters.scalar_ciphertext_modulus::<Scalar>() else {
+            return false;
+        };
+        let ksk = &self.key_switching_key;
+        let input_lwe_dimension = big_lwe_dimension(parameters);
+        let encryption_lwe_dimension = parameters.encryption_lwe_dimension();
+
+        self.parameters == *parameters
+            && self.bootstrapping_key.is_conformant(parameters)
+            && ksk.input_key_lwe_dimension() == input_lwe_dimension
+            && ksk.output_key_lwe_dimension() == parameters.lwe_dimension
+            && ksk.decomposition_base_log() == parameters.ks_base_log
+            && ksk.decomposition_level_count() == parameters.ks_level
+            && ksk.ciphertext_modulus() == ciphertext_modulus
+            && ksk.as_ref().len()
+                == input_lwe_dimension.0
+                    * lwe_keyswitch_key_input_key_element_encrypted_size(
+                        parameters.ks_level,
+                        parameters.lwe_dimension.to_lwe_size(),
+                    )
+            && check_content_respects_mod(ksk, ciphertext_modulus)
+            && self.packing_key.as_ref().map_or(true, |pksk| {
+                let glwe_size = parameters.glwe_dimension.to_glwe_size();
+                pksk.input_key_lwe_dimension() == encryption_lwe_dimension
+                    && pksk.output_glwe_size() == glwe_size
+                    && pksk.output_polynomial_size() == parameters.polynomial_size
+                    && pksk.ciphertext_modulus() == ciphertext_modulus
+                    && pksk.as_ref().len()
+                        == encryption_lwe_dimension.0
+                            * lwe_packing_keyswitch_key_input_key_element_encrypted_size(
+                                pksk.decomposition_level_count(),
+                                glwe_size,
+                                parameters.polynomial_size,
+                            )
+                    && check_content_respects_mod(pksk, ciphertext_modulus)
+            })
+    }
+}
+
+impl Named for ServerKey<u32> {
+    const NAME: &'static str = "gadget::ServerKey";
+}
+
+impl Named for ServerKey<u64> {
+    const NAME: &'static str = "gadget::ServerKey64";
+}
+
+impl<Scalar: GadgetScalar> ServerKey<Scalar> {
+    pub fn new(client_key: &ClientKey<Scalar>) -> Self {
+        GadgetEngine::with_thread_local_mut(|engine| Self::new_with_engine(engine, client_key))
+    }
+
+    /// Same as [`ServerKey::new`], drawing the randomness from `engine` instead of the
+    /// thread-local engine.
+    pub fn new_with_engine(engine: &mut GadgetEngine, client_key: &ClientKey<Scalar>) -> Self {
+        engine.create_server_key(client_key)
+    }
+
+    pub fn parameters(&self) -> &GadgetParameters {
+        &self.parameters
+    }
+
+    /// Returns the memory held by the bootstrapping, key switching and packing keys, in bytes.
+    /// The lookup table cache is not accounted for.
+    pub fn size_in_bytes(&self) -> usize {
+        let bootstrapping_key_size = match &self.bootstrapping_key {
+            GadgetBootstrappingKey::Classic(fourier_bsk) => {
+                std::mem::size_of_val(fourier_bsk.as_view().data())
+            }
+            GadgetBootstrappingKey::MultiBit { fourier_bsk, .. } => {
+                std::mem::size_of_val(fourier_bsk.as_view().data())
+            }
+            GadgetBootstrappingKey::Ntt(ntt_bsk) => std::mem::size_of_val(ntt_bsk.data()),
+        };
+        let packing_key_len = self
+            .packing_key
+            .as_ref()
+            .map_or(0, |pksk| pksk.as_ref().len());
+
+        bootstrapping_key_size
+            + (self.key_switching_key.as_ref().len() + packing_key_len)
+                * std::mem::size_of::<Scalar>()
+    }
+
+    /// Returns the modulus of the ciphertexts handled by this key.
+    pub fn ciphertext_modulus(&self) -> CiphertextModulus<Scalar> {
+        self.key_switching_key.ciphertext_modulus()
+    }
+
+    /// Diagnoses the instruction set the bootstraps of this key run on, without changing it.
+    ///
+    /// The FFT picks the widest kernels the CPU supports at runtime, the AVX-512 ones requiring
+    /// the `nightly-avx512` feature on top, see [`FftKernels`]: the per-gate latency being
+    /// dominated by the FFT, this tells whether a server runs on its fastest path. The NTT of
+    /// [`PbsBackend::Ntt`] keys always runs portable scalar code.
+    pub fn diagnose_fft_kernels(&self) -> FftKernels {
+        match self.bootstrapping_key {
+            GadgetBootstrappingKey::Ntt(_) => FftKernels::Scalar,
+            _ => FftKernels::detect(),
+        }
+    }
+
+    /// Returns the size of the LWE ciphertexts the gates take and return, encrypted under the
+    /// key of [`GadgetParameters::encryption_key_choice`].
+    pub(crate) fn ciphertext_lwe_size(&self) -> LweSize {
+        match self.parameters.pbs_order() {
+            PBSOrder::BootstrapKeyswitch => self.key_switching_key.output_lwe_size(),
+            PBSOrder::KeyswitchBootstrap => self
+                .key_switching_key
+                .input_key_lwe_dimension()
+                .to_lwe_size(),
+        }
+    }
+
+    /// Returns the trivial encryption of the public constant `value`, which the gates combine
+    /// with the other inputs without bootstrapping it.
+    pub fn trivial_encrypt(&self, value: bool) -> Ciphertext<Scalar> {
+        Ciphertext::Trivial(value)
+    }
+
+    /// Returns the trivial encryption of the public constant `message` modulo
+    /// `plaintext_modulus`, an LWE ciphertext with a zero mask and no noise.
+    ///
+    /// Gates take it like any encrypted input. A bootstrap of a trivial LWE ciphertext is
+    /// evaluated in the clear and returns a trivial LWE ciphertext, see
+    /// [`Ciphertext::trivial_message`].
+    pub fn trivial_encrypt_modular(
+        &self,
+        message: u32,
+        plaintext_modulus: u32,
+    ) -> Ciphertext<Scalar> {
+        let lwe = allocate_and_trivially_encrypt_new_lwe_ciphertext(
+            self.ciphertext_lwe_size(),
+            Plaintext(encode(
+                message,
+                plaintext_modulus,
+                self.ciphertext_modulus(),
+            )),
+            self.ciphertext_modulus(),
+        );
+        Ciphertext::Encrypted(lwe, plaintext_modulus, NoiseLevel::ZERO)
+    }
+
+    /// Keyswitches an output ciphertext encrypted under the big LWE key, of dimension `k * N`,
+    /// down to the small LWE key to shrink it before it is sent to the client, see
+    /// [`ClientKey::decrypt_small`].
+    ///
+    /// With [`EncryptionKeyChoice::Small`] gates bootstrap then keyswitch, so their outputs are
+    /// already under the small key and are returned as is, as are [`Ciphertext::Trivial`] ones.
+    /// With [`EncryptionKeyChoice::Big`] every output is under the big key. The noise level is
+    /// kept: the noise of the keyswitch is part of the one of a bootstrap.
+    ///
+    /// [`EncryptionKeyChoice::Small`]: crate::gadget::parameters::EncryptionKeyChoice::Small
+    /// [`EncryptionKeyChoice::Big`]: crate::gadget::parameters::EncryptionKeyChoice::Big
+    ///
+    /// # Panics
+    ///
+    /// Panics if the LWE dimension of `ct` is neither the small nor the big one of this key.
+    pub fn switch_to_small_key(
+        &self,
+        ct: &Ciphertext<Scalar>,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        match ct {
+            Ciphertext::Encrypted(lwe, plaintext_modulus, noise_level) => {
+                let ksk = &self.key_switching_key;
+                if lwe.lwe_size() == ksk.output_lwe_size() {
+                    return Ok(ct.clone());
+                }
+                assert_eq!(
+                    lwe.lwe_size().to_lwe_dimension(),
+                    ksk.input_key_lwe_dimension(),
+                    "the ciphertext is encrypted under neither LWE key of this server key"
+                );
+
+                let mut output = LweCiphertextOwned::new(
+                    Scalar::ZERO,
+                    ksk.output_lwe_size(),
+                    ksk.ciphertext_modulus(),
+                );
+                keyswitch_lwe_ciphertext(ksk, lwe, &mut output);
+                self.stats.record_keyswitch();
+                Ok(Ciphertext::Encrypted(
+                    output,
+                    *plaintext_modulus,
+                    *noise_level,
+                ))
+            }
+            Ciphertext::Trivial(_) => Ok(ct.clone()),
+            Ciphertext::Placeholder => Err(GadgetError::PlaceholderCiphertext),
+        }
+    }
+
+    /// Returns the largest [`NoiseLevel`] a ciphertext encrypting a message modulo `p` can
+    /// reach and still be bootstrapped correctly under the parameters of this key.
+    pub fn max_noise_level(&self, p: u32) -> NoiseLevel {
+        self.parameters.max_noise_level(p)
+    }
+
+    /// Enables or disables the collection of runtime statistics, disabled by default.
+    ///
+    /// Disabling keeps the statistics collected so far.
+    pub fn enable_stats(&self, enabled: bool) {
+        self.stats.set_enabled(enabled);
+    }
+
+    /// Returns the statistics collected since they were enabled or last reset.
+    pub fn stats(&self) -> ServerKeyStats {
+        self.stats.snapshot()
+    }
+
+    /// Clears the statistics collected so far, collection stays enabled or disabled.
+    pub fn reset_stats(&self) {
+        self.stats.reset();
+    }
+
+    /// Generates the packing keyswitching key of [`ServerKey::pack`] from the encryption key of
+    /// `client_key` to its GLWE key. Server keys are generated without one, as it is only needed
+    /// to return compact results.
+    pub fn enable_packing(
+        &mut self,
+        client_key: &ClientKey<Scalar>,
+        parameters: GadgetPackingParameters,
+    ) {
+        let packing_key = GadgetEngine::with_thread_local_mut(|engine| {
+            engine.create_packing_keyswitch_key(client_key, &parameters)
+        });
+        self.packing_key = Some(packing_key);
+    }
+
+    /// Builds the lookup table evaluating `encoding` during a bootstrap.
+    ///
+    /// The result is not cached, see [`ServerKey::lookup_table`] for the cached variant used by
+    /// gate evaluation.
+    pub fn generate_lookup_table(&self, encoding: &Encoding) -> LookupTable<Scalar> {
+        self.lookup_table_from_accumulator(
+            &encoding.create_accumulator(),
+            encoding.p,
+            encoding.new_p,
+        )
+    }
+
+    /// Builds the lookup table interleaving the test polynomials of `encodings`, which must
+    /// share their `p` and `new_p`, see [`ServerKey::evaluate_gate_outputs`].
+    pub fn generate_many_lookup_table(&self, encodings: &[Encoding]) -> LookupTable<Scalar> {
+        let polynomial_size = self.bootstrapping_key.polynomial_size();
+        let new_p = encodings[0].new_p;
+
+        let mut acc = GlweCiphertextOwned::new(
+            Scalar::ZERO,
+            self.bootstrapping_key.glwe_size(),
+            polynomial_size,
+            self.ciphertext_modulus(),
+        );
+        let delta = encode_coefficient(1, new_p, self.ciphertext_modulus());
+        for (coefficient, message) in acc
+            .get_mut_body()
+            .as_mut()
+            .iter_mut()
+            .zip(many_lookup_table_messages(encodings, polynomial_size))
+        {
+            *coefficient = delta.wrapping_mul(Scalar::cast_from(message));
+        }
+
+        LookupTable { acc }
+    }
+
+    /// Builds the lookup table of `accumulator`, the values of the windows of the messages
+    /// modulo `p` scaled as messages modulo `new_p`.
+    pub(crate) fn lookup_table_from_accumulator(
+        &self,
+        accumulator: &[u32],
+        p: u32,
+        new_p: u32,
+    ) -> LookupTable<Scalar> {
+        let polynomial_size = self.bootstrapping_key.polynomial_size();
+
+        // accumulator is a trivial ciphertext of test vector polynomial
+        let mut acc = GlweCiphertextOwned::new(
+            Scalar::ZERO,
+            self.bootstrapping_key.glwe_size(),
+            polynomial_size,
+            self.ciphertext_modulus(),
+        );
+
+        // Output values live in the new_p space, they are scaled accordingly
+        let delta = encode_coefficient(1, new_p, self.ciphertext_modulus());
+        for (coefficient, message) in acc
+            .get_mut_body()
+            .as_mut()
+            .iter_mut()
+            .zip(accumulator_messages(accumulator, p, polynomial_size))
+        {
+            *coefficient = delta.wrapping_mul(Scalar::cast_from(message));
+        }
+
+        LookupTable { acc }
+    }
+
+    /// Returns the lookup table of `encoding`, generating and caching it on first use.
+    ///
+    /// At most [`LOOKUP_TABLE_CACHE_CAPACITY`] tables are cached, the least recently used one
+    /// being dropped to make room for a new one.
+    pub fn lookup_table(&self, encoding: &Encoding) -> Arc<LookupTable<Scalar>> {
+        if let Some(lookup_table) = self.lookup_tables.get(encoding) {
+            return lookup_table;
+        }
+
+        let lookup_table = Arc::new(self.generate_lookup_table(encoding));
+        self.lookup_tables.insert(encoding, lookup_table)
+    }
+
+    /// Drops every cached lookup table.
+    pub fn clear_lookup_table_cache(&self) {
+        self.lookup_tables.clear();
     }
 
+    /// Bootstraps `ct` with the lookup table of `encoding`.
+    ///
+    /// Fails with [`GadgetError::UnsupportedPlaintextModulus`] if the plaintext modulus of
+    /// `encoding` is above [`GadgetParameters::max_supported_p`].
     pub fn bootstrap(
         &self,
-        ct: Ciphertext,
+        ct: Ciphertext<Scalar>,
         encoding: &Encoding,
-    ) -> Result<Ciphertext, Box<dyn Error>> {
-        GadgetEngine::with_thread_local_mut(|engine| engine.bootstrap(ct, &self, encoding))
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            self.bootstrap_with_engine(engine, ct, encoding)
+        })
+    }
+
+    /// Same as [`ServerKey::bootstrap`], using the buffers of `engine` instead of the ones of
+    /// the thread-local engine.
+    pub fn bootstrap_with_engine(
+        &self,
+        engine: &mut GadgetEngine,
+        ct: Ciphertext<Scalar>,
+        encoding: &Encoding,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        engine.bootstrap(ct, self, encoding)
     }
 
     pub fn evaluate_gate(
         &self,
-        input_ciphertexts: Vec<Ciphertext>,
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+        encoding: &Encoding,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            self.evaluate_gate_with_engine(engine, input_ciphertexts, encoding)
+        })
+    }
+
+    /// Evaluates the gate described by `encoding` without taking ownership of the inputs.
+    pub fn evaluate_gate_ref(
+        &self,
+        input_ciphertexts: &[&Ciphertext<Scalar>],
+        encoding: &Encoding,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            engine.evaluate_gate_ref(self, encoding, input_ciphertexts)
+        })
+    }
+
+    /// Same as [`ServerKey::evaluate_gate`], using the buffers of `engine` instead of the ones
+    /// of the thread-local engine, e.g. to own one engine per worker of a server.
+    pub fn evaluate_gate_with_engine(
+        &self,
+        engine: &mut GadgetEngine,
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+        encoding: &Encoding,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        engine.evaluate_gate(self, encoding, input_ciphertexts)
+    }
+
+    /// Same as [`ServerKey::evaluate_gate`], bootstrapping with the buffers of `context` instead
+    /// of the ones of the thread-local engine, which do not grow.
+    pub fn evaluate_gate_with_context(
+        &self,
+        context: &mut EvaluationContext,
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+        encoding: &Encoding,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            engine.with_context(context, |engine| {
+                self.evaluate_gate_with_engine(engine, input_ciphertexts, encoding)
+            })
+        })
+    }
+
+    /// Bootstraps `ct` in place, see [`ServerKey::bootstrap`].
+    pub fn bootstrap_assign(
+        &self,
+        ct: &mut Ciphertext<Scalar>,
+        encoding: &Encoding,
+    ) -> Result<(), GadgetError> {
+        GadgetEngine::with_thread_local_mut(|engine| engine.bootstrap_assign(ct, self, encoding))
+    }
+
+    /// Evaluates the gate described by `encoding` into `output`.
+    ///
+    /// The LWE ciphertext of `output` is reused for the linear combination and the bootstrap
+    /// when `output` is already encrypted under this key, which avoids allocating in loops over
+    /// large circuits. `output` is left as a [`Ciphertext::Placeholder`] on error.
+    pub fn evaluate_gate_into(
+        &self,
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+        encoding: &Encoding,
+        output: &mut Ciphertext<Scalar>,
+    ) -> Result<(), GadgetError> {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            engine.evaluate_gate_into(self, encoding, input_ciphertexts, output)
+        })
+    }
+
+    /// Evaluates the gate described by `encoding` on each set of inputs of `batch`, the outputs
+    /// being returned in the order of `batch`.
+    ///
+    /// The bootstraps of the batch share their lookup table and buffers. If `pipelined` is set,
+    /// the batch is evaluated by [`ServerKey::evaluate_gate_pipelined`] with the default
+    /// [`PipelineConfig`](crate::gadget::pipeline::PipelineConfig) instead.
+    pub fn evaluate_gate_many(
+        &self,
+        batch: Vec<Vec<Ciphertext<Scalar>>>,
+        encoding: &Encoding,
+        pipelined: bool,
+    ) -> Result<Vec<Ciphertext<Scalar>>, GadgetError> {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            self.evaluate_gate_many_with_engine(engine, batch, encoding, pipelined)
+        })
+    }
+
+    /// Same as [`ServerKey::evaluate_gate_many`], using the buffers of `engine` instead of the
+    /// ones of the thread-local engine.
+    pub fn evaluate_gate_many_with_engine(
+        &self,
+        engine: &mut GadgetEngine,
+        batch: Vec<Vec<Ciphertext<Scalar>>>,
+        encoding: &Encoding,
+        pipelined: bool,
+    ) -> Result<Vec<Ciphertext<Scalar>>, GadgetError> {
+        engine.evaluate_gate_many(self, encoding, batch, pipelined)
+    }
+
+    /// Evaluates the gate described by `encoding` on the rows of `inputs`, the `i`-th list
+    /// holding the ciphertexts of the `i`-th pin, the output of row `j` being the `j`-th
+    /// ciphertext of the returned list.
+    ///
+    /// The ciphertexts are read in place from the lists and the bootstraps share their lookup
+    /// table and buffers, as with [`ServerKey::evaluate_gate_many`]. Fails with
+    /// [`GadgetError::ListLengthMismatch`] if the lists do not have the same length.
+    pub fn evaluate_gate_list(
+        &self,
+        inputs: &[&CiphertextList<Scalar>],
         encoding: &Encoding,
-    ) -> Result<Ciphertext, Box<dyn Error>> {
+    ) -> Result<CiphertextList<Scalar>, GadgetError> {
         GadgetEngine::with_thread_local_mut(|engine| {
-            engine.evaluate_gate(&self, encoding, input_ciphertexts)
+            engine.evaluate_gate_list(self, encoding, inputs)
         })
     }
+
+    /// Bootstraps every ciphertext of `list` with the lookup table of `encoding`, see
+    /// [`ServerKey::bootstrap`].
+    pub fn bootstrap_list(
+        &self,
+        list: &CiphertextList<Scalar>,
+        encoding: &Encoding,
+    ) -> Result<CiphertextList<Scalar>, GadgetError> {
+        GadgetEngine::with_thread_local_mut(|engine| engine.bootstrap_list(self, encoding, list))
+    }
+
+    /// Gathers `cts` in a contiguous [`CiphertextList`], e.g. to feed gate outputs to
+    /// [`ServerKey::evaluate_gate_list`].
+    ///
+    /// The encrypted ciphertexts must share their plaintext modulus, [`Ciphertext::Trivial`]
+    /// inputs are encrypted trivially under it (modulo 2 if every input is trivial). The list
+    /// takes the highest noise level of `cts`.
+    pub fn to_ciphertext_list(
+        &self,
+        cts: &[Ciphertext<Scalar>],
+    ) -> Result<CiphertextList<Scalar>, GadgetError> {
+        let mut plaintext_modulus = None;
+        for (pin, ct) in cts.iter().enumerate() {
+            match ct {
+                Ciphertext::Encrypted(_, p, _) => match plaintext_modulus {
+                    None => plaintext_modulus = Some(*p),
+                    Some(expected) if expected != *p => {
+                        return Err(GadgetError::PlaintextModulusMismatch {
+                            pin,
+                            expected,
+                            actual: *p,
+                        });
+                    }
+                    Some(_) => {}
+                },
+                Ciphertext::Trivial(_) => {}
+                Ciphertext::Placeholder => return Err(GadgetError::PlaceholderCiphertext),
+            }
+        }
+        let plaintext_modulus = plaintext_modulus.unwrap_or(2);
+
+        let lwes: Vec<_> = cts
+            .iter()
+            .map(|ct| match ct {
+                Ciphertext::Trivial(bit) => {
+                    self.trivial_encrypt_modular(*bit as u32, plaintext_modulus)
+                }
+                ct => ct.clone(),
+            })
+            .collect();
+        let noise_level = cts
+            .iter()
+            .map(Ciphertext::noise_level)
+            .max()
+            .unwrap_or(NoiseLevel::ZERO);
+
+        Ok(CiphertextList::from_lwe_ciphertexts(
+            lwes.iter().map(|ct| match ct {
+                Ciphertext::Encrypted(lwe, _, _) => lwe,
+                _ => unreachable!("trivial and placeholder inputs handled above"),
+            }),
+            self.ciphertext_lwe_size(),
+            self.ciphertext_modulus(),
+            plaintext_modulus,
+            noise_level,
+        ))
+    }
+
+    /// Evaluates the gate planned by `plan`, see
+    /// [`encoding::decompose`](super::encoding::decompose).
+    ///
+    /// A [`GatePlan::Decomposed`] gate bootstraps each of its cofactors on the cofactor pins of
+    /// `input_ciphertexts`, then the combiner on the outputs of the cofactors and the select pin.
+    pub fn evaluate_gate_plan(
+        &self,
+        mut input_ciphertexts: Vec<Ciphertext<Scalar>>,
+        plan: &GatePlan,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        let decomposition = match plan {
+            GatePlan::Single(encoding) => return self.evaluate_gate(input_ciphertexts, encoding),
+            GatePlan::Decomposed(decomposition) => decomposition,
+        };
+        if input_ciphertexts.len() != decomposition.pin_count {
+            return Err(GadgetError::PinCountMismatch {
+                expected: decomposition.pin_count,
+                actual: input_ciphertexts.len(),
+            });
+        }
+
+        let cofactor_inputs: Vec<_> = decomposition
+            .cofactor_pins
+            .iter()
+            .map(|pin| &input_ciphertexts[*pin])
+            .collect();
+        let mut combiner_inputs = decomposition
+            .cofactors
+            .iter()
+            .map(|cofactor| self.evaluate_gate_ref(&cofactor_inputs, cofactor))
+            .collect::<Result<Vec<_>, _>>()?;
+        combiner_inputs.push(input_ciphertexts.swap_remove(decomposition.select_pin));
+        self.evaluate_gate(combiner_inputs, &decomposition.combiner)
+    }
+
+    /// Computes `constant + sum(weights[i] * input_ciphertexts[i])` modulo `p`, without
+    /// bootstrapping.
+    ///
+    /// Every encrypted input must encrypt a message modulo `p`, trivial inputs count as 0 or 1.
+    /// The noise level of the output is the sum of the noise levels of the inputs weighted by the
+    /// squared weights: it is up to the caller to bootstrap the result (e.g. with
+    /// [`ServerKey::bootstrap`]) before it exceeds [`ServerKey::max_noise_level`]. This allows
+    /// fusing several gates, e.g. chains of XORs under `p = 2`, into a single bootstrap.
+    pub fn linear_combination(
+        &self,
+        input_ciphertexts: &[Ciphertext<Scalar>],
+        weights: &[u32],
+        constant: u32,
+        p: u32,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            engine.weighted_sum(self, input_ciphertexts, weights, constant, p)
+        })
+    }
+
+    /// Evaluates the lookup table described by `encoding` with a single bootstrap, the output
+    /// encrypting the integer `encoding.output(m)` modulo `encoding.new_p()` for `m` the linear
+    /// combination of the inputs.
+    ///
+    /// Unlike gates, whose lookup tables only have two outputs, the lookup table is not cached:
+    /// it is generated on every call.
+    pub fn evaluate_lut(
+        &self,
+        input_ciphertexts: &[&Ciphertext<Scalar>],
+        encoding: &MultiOutputEncoding,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            engine.evaluate_lut(self, encoding, input_ciphertexts)
+        })
+    }
+
+    /// Evaluates the gate described by `encoding`, only bootstrapping when needed.
+    ///
+    /// Inputs are first refreshed if their weighted noise would exceed the correctness margin
+    /// of the encoding, by a bootstrap through the identity lookup table which keeps their
+    /// message whatever the encoding it was computed with. If the encoding is linear (see [`Encoding::is_linear`]) and the noise
+    /// of the linear combination stays below [`ServerKey::max_noise_level`], the combination is
+    /// returned as is and no bootstrap is performed. This allows chaining linear gates (e.g.
+    /// XORs under `p = 2`) for the cost of ciphertext additions.
+    pub fn smart_evaluate_gate(
+        &self,
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+        encoding: &Encoding,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            engine.smart_evaluate_gate(self, encoding, input_ciphertexts)
+        })
+    }
+
+    /// Bootstraps `ct`, encrypting a message modulo `encoding.p()`, into a ciphertext encrypting
+    /// `encoding.new_0()` or `encoding.new_1()` modulo `encoding.new_p()`.
+    ///
+    /// This enables e.g. switching to `new_p = 2` where XOR gates are plain ciphertext
+    /// additions, before switching back to the modulus expected by the next gates.
+    pub fn bootstrap_with_modulus_switch(
+        &self,
+        ct: Ciphertext<Scalar>,
+        encoding: &Encoding,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            engine.bootstrap_with_modulus_switch(ct, self, encoding)
+        })
+    }
+
+    /// Evaluates several encodings sharing the same linear combination of `input_ciphertexts`
+    /// with a single blind rotation.
+    ///
+    /// All `encodings` must have the same `p`, `new_p`, `pin_count` and input mappings. The
+    /// outputs are returned in the order of `encodings`. Each extra output only costs a sparse
+    /// polynomial product, a sample extraction and a keyswitch, at the price of a noise increase
+    /// proportional to the number of windows of the encoding.
+    pub fn evaluate_gate_multi(
+        &self,
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+        encodings: &[Encoding],
+    ) -> Result<Vec<Ciphertext<Scalar>>, GadgetError> {
+        GadgetEngine::with_thread_local_mut(|engine| {
+            engine.evaluate_gate_multi(self, encodings, input_ciphertexts)
+        })
+    }
+
+    /// Evaluates `K` encodings sharing the same linear combination of `input_ciphertexts` with a
+    /// single blind rotation, e.g. the output bits of an S-box.
+    ///
+    /// Unlike [`ServerKey::evaluate_gate_multi`], the test polynomials of the encodings are
+    /// interleaved in a single lookup table, each output being a sample extraction of the
+    /// rotated accumulator at its own degree: the outputs do not add noise, but the windows of
+    /// the lookup table are `K` times narrower. The encodings must satisfy the constraints of
+    /// [`ServerKey::evaluate_gate_multi`], and the parameters must support the plaintext
+    /// modulus `p * K`.
+    pub fn evaluate_gate_outputs<const K: usize>(
+        &self,
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+        encodings: &[Encoding; K],
+    ) -> Result<[Ciphertext<Scalar>; K], GadgetError> {
+        let outputs = GadgetEngine::with_thread_local_mut(|engine| {
+            engine.evaluate_gate_outputs(self, encodings, input_ciphertexts)
+        })?;
+        Ok(outputs
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("one output per encoding")))
+    }
+}
+
+/// The bootstrapping key of a [`CompressedServerKey`], only holding the bodies of its GGSW
+/// ciphertexts and the seed of their masks.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum CompressedBootstrappingKey<Scalar: GadgetScalar = u32> {
+    Classic(SeededLweBootstrapKeyOwned<Scalar>),
+    MultiBit(SeededLweMultiBitBootstrapKeyOwned<Scalar>),
+}
+
+/// A compressed [`ServerKey`], several times smaller to send from the client to the server.
+///
+/// The masks of the keys are replaced by the seeds they are generated from, it has to be
+/// decompressed (see [`CompressedServerKey::decompress`]) before evaluating gates.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CompressedServerKey<Scalar: GadgetScalar = u32> {
+    pub(crate) bootstrapping_key: CompressedBootstrappingKey<Scalar>,
+    pub(crate) key_switching_key: SeededLweKeyswitchKeyOwned<Scalar>,
+    pub(crate) parameters: GadgetParameters,
+}
+
+impl<Scalar: GadgetScalar> CompressedServerKey<Scalar> {
+    /// # Panics
+    ///
+    /// Panics if the parameters of `client_key` select [`PbsBackend::Ntt`], whose bootstrapping
+    /// keys cannot be compressed.
+    pub fn new(client_key: &ClientKey<Scalar>) -> Self {
+        GadgetEngine::with_thread_local_mut(|engine| Self::new_with_engine(engine, client_key))
+    }
+
+    /// Same as [`CompressedServerKey::new`], drawing the randomness from `engine` instead of the
+    /// thread-local engine.
+    pub fn new_with_engine(engine: &mut GadgetEngine, client_key: &ClientKey<Scalar>) -> Self {
+        engine.create_compressed_server_key(client_key)
+    }
+
+    pub fn parameters(&self) -> &GadgetParameters {
+        &self.parameters
+    }
+
+    /// Regenerates the masks of the keys and converts the bootstrapping key to the Fourier
+    /// domain. The decompressed key holds no packing keyswitching key, see
+    /// [`ServerKey::enable_packing`].
+    pub fn decompress(self) -> ServerKey<Scalar> {
+        let Self {
+            bootstrapping_key,
+            key_switching_key,
+            parameters,
+        } = self;
+
+        let (key_switching_key, bootstrapping_key) = rayon::join(
+            || key_switching_key.par_decompress_into_lwe_keyswitch_key(),
+            || match bootstrapping_key {
+                CompressedBootstrappingKey::Classic(bootstrapping_key) => {
+                    classic_fourier_bootstrapping_key(
+                        &bootstrapping_key.par_decompress_into_lwe_bootstrap_key(),
+                    )
+                }
+                CompressedBootstrappingKey::MultiBit(bootstrapping_key) => {
+                    multi_bit_fourier_bootstrapping_key(
+                        &bootstrapping_key.par_decompress_into_lwe_multi_bit_bootstrap_key(),
+                    )
+                }
+            },
+        );
+
+        ServerKey {
+            bootstrapping_key,
+            key_switching_key,
+            parameters,
+            packing_key: None,
+            lookup_tables: Default::default(),
+            stats: Default::default(),
+        }
+    }
+}
+
+impl<Scalar: GadgetScalar> From<CompressedServerKey<Scalar>> for ServerKey<Scalar> {
+    fn from(value: CompressedServerKey<Scalar>) -> Self {
+        value.decompress()
+    }
+}
+
+/// Same checks as for a [`ServerKey`], on the bodies of the keys.
+impl<Scalar: GadgetScalar> ParameterSetConformant for CompressedServerKey<Scalar> {
+    type ParameterSet = GadgetParameters;
+
+    fn is_conformant(&self, parameters: &GadgetParameters) -> bool {
+        let Ok(ciphertext_modulus) = parameters.scalar_ciphertext_modulus::<Scalar>() else {
+            return false;
+        };
+        let Some(ggsw_count) = bootstrapping_key_ggsw_count(parameters) else {
+            return false;
+        };
+
+        let bsk: &SeededGgswCiphertextListOwned<Scalar> = match (
+            &self.bootstrapping_key,
+            parameters.grouping_factor,
+            parameters.pbs_backend,
+        ) {
+            (CompressedBootstrappingKey::Classic(bsk), None, PbsBackend::Fft) => bsk,
+            (CompressedBootstrappingKey::MultiBit(bsk), Some(grouping_factor), PbsBackend::Fft)
+                if bsk.grouping_factor() == grouping_factor =>
+            {
+                bsk
+            }
+            _ => return false,
+        };
+        let glwe_size = parameters.glwe_dimension.to_glwe_size();
+        let bsk_is_conformant = bsk.glwe_size() == glwe_size
+            && bsk.polynomial_size() == parameters.polynomial_size
+            && bsk.decomposition_base_log() == parameters.pbs_base_log
+            && bsk.decomposition_level_count() == parameters.pbs_level
+            && bsk.ciphertext_modulus() == ciphertext_modulus
+            && bsk.as_ref().len()
+                == ggsw_count
+                    * seeded_ggsw_ciphertext_size(
+                        glwe_size,
+                        parameters.polynomial_size,
+                        parameters.pbs_level,
+                    )
+            && check_content_respects_mod(bsk, ciphertext_modulus);
+
+        let ksk = &self.key_switching_key;
+        let input_lwe_dimension = big_lwe_dimension(parameters);
+
+        self.parameters == *parameters
+            && bsk_is_conformant
+            && ksk.input_key_lwe_dimension() == input_lwe_dimension
+            && ksk.output_key_lwe_dimension() == parameters.lwe_dimension
+            && ksk.decomposition_base_log() == parameters.ks_base_log
+            && ksk.decomposition_level_count() == parameters.ks_level
+            && ksk.ciphertext_modulus() == ciphertext_modulus
+            && ksk.as_ref().len()
+                == input_lwe_dimension.0
+                    * seeded_lwe_keyswitch_key_input_key_element_encrypted_size(parameters.ks_level)
+            && check_content_respects_mod(ksk, ciphertext_modulus)
+    }
+}
+
+impl Named for CompressedServerKey<u32> {
+    const NAME: &'static str = "gadget::CompressedServerKey";
+}
+
+impl Named for CompressedServerKey<u64> {
+    const NAME: &'static str = "gadget::CompressedServerKey64";
+}
+
+/// Returns the unscaled message of every coefficient of the test polynomial of `encoding`.
+///
+/// The p-encoding space is split in `p` windows of `N / p` coefficients, the 0^th window being
+/// split in two halves at both ends of the polynomial.
+pub(crate) fn lookup_table_messages(
+    encoding: &Encoding,
+    polynomial_size: PolynomialSize,
+) -> Vec<u32> {
+    accumulator_messages(&encoding.create_accumulator(), encoding.p, polynomial_size)
+}
+
+/// Spreads the values of the windows of `encoding_acc`, see [`Encoding::create_accumulator`],
+/// over the coefficients of the test polynomial of messages modulo `p`.
+fn accumulator_messages(encoding_acc: &[u32], p: u32, polynomial_size: PolynomialSize) -> Vec<u32> {
+    let p = p as usize;
+    let n = polynomial_size.0;
+    let half_window = n / (2 * p);
+    let mut messages = vec![0u32; n];
+
+    // handle first half of 0^th window
+    messages[..half_window].fill(encoding_acc[0]);
+
+    for (i, value) in encoding_acc.iter().enumerate().take(p).skip(1) {
+        messages[((i - 1) * n / p) + half_window..i * n / p + half_window].fill(*value);
+    }
+
+    // handle second half of 0^th window
+    messages[n - half_window..].fill(encoding_acc[p]);
+
+    messages
+}
+
+/// Returns the unscaled message of every coefficient of the test polynomial interleaving the
+/// ones of `encodings`, which share their plaintext modulus `p`.
+///
+/// Each of the `p` windows is split in `k = encodings.len()` sub-windows of `s = N / (p * k)`
+/// coefficients, the sub-window `j` of the window of a message holding the output of encoding
+/// `j`. The sub-window of the first encoding is centered on the message, as the window of
+/// [`lookup_table_messages`], so that the coefficient of degree `j * s` of the rotated
+/// polynomial is the output of encoding `j`.
+pub(crate) fn many_lookup_table_messages(
+    encodings: &[Encoding],
+    polynomial_size: PolynomialSize,
+) -> Vec<u32> {
+    let p = encodings[0].p as usize;
+    let n = polynomial_size.0;
+    let sub_window = n / (p * encodings.len());
+    let half_sub_window = sub_window / 2;
+    let encoding_accs: Vec<Vec<u32>> = encodings.iter().map(Encoding::create_accumulator).collect();
+    let mut messages = vec![0u32; n];
+
+    for window in 0..p {
+        for (index, encoding_acc) in encoding_accs.iter().enumerate() {
+            let start = window * n / p + index * sub_window;
+            for coefficient in start..start + sub_window {
+                match coefficient.checked_sub(half_sub_window) {
+                    Some(coefficient) => messages[coefficient] = encoding_acc[window],
+                    // the lower half of the sub-window of message 0 wraps around to the end
+                    None => {
+                        messages[coefficient + n - half_sub_window] = encoding_acc[p];
+                    }
+                }
+            }
+        }
+    }
+
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_crypto::commons::generators::DeterministicSeeder;
+    use crate::core_crypto::commons::parameters::CiphertextModulusLog;
+    use crate::core_crypto::prelude::{
+        allocate_and_encrypt_new_lwe_ciphertext, lwe_ciphertext_add_assign,
+        ActivatedRandomGenerator, EncryptionRandomGenerator, LweBskGroupingFactor,
+    };
+    use crate::gadget::boolean::BOOLEAN_PARAMETERS;
+    use crate::gadget::ciphertext::TransportCiphertext;
+    use crate::gadget::parameters::{
+        EncryptionKeyChoice, StandardDev, PLAINTEXT_3_BITS_PARAMETERS,
+    };
+    use crate::gadget::{gen_keys, gen_keys_from_seed, gen_keys_multi_from_seed};
+    use concrete_csprng::seeders::{Seed, Seeder};
+    use std::sync::Arc;
+
+    #[test]
+    fn lookup_table_is_cached() {
+        let (_, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+
+        let first = server_key.lookup_table(&encoding);
+        let second = server_key.lookup_table(&encoding);
+        assert!(Arc::ptr_eq(&first, &second));
+
+        let generated = server_key.generate_lookup_table(&encoding);
+        assert_eq!(generated.acc().as_ref(), first.acc().as_ref());
+
+        server_key.clear_lookup_table_cache();
+        let third = server_key.lookup_table(&encoding);
+        assert!(!Arc::ptr_eq(&first, &third));
+    }
+
+    #[test]
+    fn lookup_table_cache_evicts_least_recently_used() {
+        let (_, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let cache = LookupTableCache::new(2);
+        let and = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let or = Encoding::new_canonical(14, 2, vec![1, 1], vec![0], vec![1, 2], 3);
+        let xor = Encoding::new_canonical(6, 2, vec![1, 1], vec![0, 2], vec![1], 3);
+
+        let cached_and = cache.insert(&and, Arc::new(server_key.generate_lookup_table(&and)));
+        cache.insert(&or, Arc::new(server_key.generate_lookup_table(&or)));
+        // `and` is used again, so `or` is the least recently used table when `xor` comes
+        assert!(Arc::ptr_eq(&cache.get(&and).unwrap(), &cached_and));
+        cache.insert(&xor, Arc::new(server_key.generate_lookup_table(&xor)));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&and).is_some());
+        assert!(cache.get(&or).is_none());
+        assert!(cache.get(&xor).is_some());
+    }
+
+    #[test]
+    fn evaluate_gate_reports_errors() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+
+        let inputs = vec![client_key.encrypt(true)];
+        assert_eq!(
+            server_key.evaluate_gate(inputs, &encoding).unwrap_err(),
+            GadgetError::PinCountMismatch {
+                expected: 2,
+                actual: 1
+            }
+        );
+
+        let inputs = vec![client_key.encrypt(true), Ciphertext::Placeholder];
+        assert_eq!(
+            server_key.evaluate_gate(inputs, &encoding).unwrap_err(),
+            GadgetError::PlaceholderCiphertext
+        );
+
+        // the windows of the lookup table would hold no coefficient
+        let p = BOOLEAN_PARAMETERS.max_supported_p() + 1;
+        let ct = GadgetEngine::with_thread_local_mut(|engine| engine.encrypt(1, &client_key, p));
+        assert_eq!(
+            server_key
+                .bootstrap(ct, &Encoding::new_refresh(p))
+                .unwrap_err(),
+            GadgetError::UnsupportedPlaintextModulus(p)
+        );
+    }
+
+    #[test]
+    fn evaluate_gate_multi_shares_blind_rotation() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+
+        // and, or and xor over the same sum of two canonical inputs
+        let encodings = [
+            Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3),
+            Encoding::new_canonical(14, 2, vec![1, 1], vec![0], vec![1, 2], 3),
+            Encoding::new_canonical(6, 2, vec![1, 1], vec![0, 2], vec![1], 3),
+        ];
+
+        for _ in 0..10 {
+            for lhs in 0..2u32 {
+                for rhs in 0..2u32 {
+                    let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                        vec![
+                            engine.encrypt(lhs, &client_key, 3),
+                            engine.encrypt(rhs, &client_key, 3),
+                        ]
+                    });
+
+                    let outputs = server_key.evaluate_gate_multi(inputs, &encodings).unwrap();
+                    let outputs = GadgetEngine::with_thread_local_mut(|engine| {
+                        outputs
+                            .iter()
+                            .map(|ct| engine.decrypt(ct, &client_key))
+                            .collect::<Vec<_>>()
+                    });
+
+                    assert_eq!(outputs, vec![lhs & rhs, lhs | rhs, lhs ^ rhs]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn evaluate_gate_outputs_shares_blind_rotation() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+
+        // and and xor over the same sum of two canonical inputs
+        let encodings = [
+            Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3),
+            Encoding::new_canonical(6, 2, vec![1, 1], vec![0, 2], vec![1], 3),
+        ];
+
+        server_key.enable_stats(true);
+        for _ in 0..5 {
+            for lhs in 0..2u32 {
+                for rhs in 0..2u32 {
+                    let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                        vec![
+                            engine.encrypt(lhs, &client_key, 3),
+                            engine.encrypt(rhs, &client_key, 3),
+                        ]
+                    });
+
+                    let [and, xor] = server_key
+                        .evaluate_gate_outputs(inputs, &encodings)
+                        .unwrap();
+                    GadgetEngine::with_thread_local_mut(|engine| {
+                        assert_eq!(engine.decrypt(&and, &client_key), lhs & rhs);
+                        assert_eq!(engine.decrypt(&xor, &client_key), lhs ^ rhs);
+                    });
+                }
+            }
+        }
+        let stats = server_key.stats();
+        assert_eq!(stats.pbs_count, 20);
+        assert_eq!(stats.keyswitch_count, 40);
+    }
+
+    #[test]
+    fn many_lookup_table_with_one_encoding() {
+        let polynomial_size = PolynomialSize(1024);
+        let encodings = [Encoding::new_canonical(
+            8,
+            2,
+            vec![1, 1],
+            vec![0, 1],
+            vec![2, 3],
+            4,
+        )];
+        assert_eq!(
+            many_lookup_table_messages(&encodings, polynomial_size),
+            lookup_table_messages(&encodings[0], polynomial_size)
+        );
+
+        // the first sub-window is centered on the message, the second one follows it
+        let encodings = [
+            Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2, 3], 4),
+            Encoding::new_canonical(14, 2, vec![1, 1], vec![0], vec![1, 2, 3], 4),
+        ];
+        let messages = many_lookup_table_messages(&encodings, polynomial_size);
+        assert_eq!(messages[0], 0);
+        assert_eq!(messages[1023], 0);
+        assert_eq!(messages[128], 0);
+        assert_eq!(messages[256], 0);
+        assert_eq!(messages[256 + 128], 1);
+        assert_eq!(messages[2 * 256], 1);
+        assert_eq!(messages[2 * 256 + 128], 1);
+    }
+
+    #[test]
+    fn evaluate_gate_on_64_bits_torus() {
+        let client_key = ClientKey::<u64>::new(&PLAINTEXT_3_BITS_PARAMETERS);
+        let server_key = ServerKey::new(&client_key);
+
+        let and_encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let encodings = [
+            and_encoding.clone(),
+            Encoding::new_canonical(14, 2, vec![1, 1], vec![0], vec![1, 2], 3),
+        ];
+
+        for lhs in 0..2u32 {
+            for rhs in 0..2u32 {
+                let encrypt_inputs = || {
+                    GadgetEngine::with_thread_local_mut(|engine| {
+                        vec![
+                            engine.encrypt(lhs, &client_key, 3),
+                            engine.encrypt(rhs, &client_key, 3),
+                        ]
+                    })
+                };
+
+                let and_ct = server_key
+                    .evaluate_gate(encrypt_inputs(), &and_encoding)
+                    .unwrap();
+                let outputs = server_key
+                    .evaluate_gate_multi(encrypt_inputs(), &encodings)
+                    .unwrap();
+
+                GadgetEngine::with_thread_local_mut(|engine| {
+                    assert_eq!(engine.decrypt(&and_ct, &client_key), lhs & rhs);
+                    assert_eq!(engine.decrypt(&outputs[0], &client_key), lhs & rhs);
+                    assert_eq!(engine.decrypt(&outputs[1], &client_key), lhs | rhs);
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn evaluate_lut_with_integer_outputs() {
+        use crate::gadget::parameters::PLAINTEXT_4_BITS_PARAMETERS;
+
+        const PRESENT_SBOX: [u32; 16] = [
+            0xc, 0x5, 0x6, 0xb, 0x9, 0x0, 0xa, 0xd, 0x3, 0xe, 0xf, 0x8, 0x4, 0x7, 0x1, 0x2,
+        ];
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_4_BITS_PARAMETERS);
+        let sbox =
+            MultiOutputEncoding::from_function(16, 16, |x| PRESENT_SBOX[x as usize]).unwrap();
+
+        server_key.enable_stats(true);
+        for x in [0u32, 3, 9, 15] {
+            let ct =
+                GadgetEngine::with_thread_local_mut(|engine| engine.encrypt(x, &client_key, 16));
+            // the output modulus matches the input one, S-boxes can be chained
+            let once = server_key.evaluate_lut(&[&ct], &sbox).unwrap();
+            let twice = server_key.evaluate_lut(&[&once], &sbox).unwrap();
+            GadgetEngine::with_thread_local_mut(|engine| {
+                let once = engine.decrypt(&once, &client_key);
+                assert_eq!(once, PRESENT_SBOX[x as usize], "S({x})");
+                let twice = engine.decrypt(&twice, &client_key);
+                assert_eq!(twice, PRESENT_SBOX[once as usize], "S(S({x}))");
+            });
+        }
+        assert_eq!(server_key.stats().pbs_count, 8);
+
+        // boolean pins x0 + 2 * x1, output x * x modulo 5
+        let square = MultiOutputEncoding::from_table(&[0, 1, 4, 4], vec![2, 1], 5, 5).unwrap();
+        for x in 0..4u32 {
+            let (x0, x1) = GadgetEngine::with_thread_local_mut(|engine| {
+                (
+                    engine.encrypt(x & 1, &client_key, 5),
+                    engine.encrypt(x >> 1, &client_key, 5),
+                )
+            });
+            let out = server_key.evaluate_lut(&[&x0, &x1], &square).unwrap();
+            let out =
+                GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&out, &client_key));
+            assert_eq!(out, x * x % 5, "{x}^2");
+        }
+
+        // trivial inputs are evaluated in the clear
+        let out = server_key
+            .evaluate_lut(
+                &[&Ciphertext::Trivial(true), &Ciphertext::Trivial(true)],
+                &square,
+            )
+            .unwrap();
+        assert!(matches!(out, Ciphertext::Encrypted(_, 5, _)));
+        assert!(out.noise_level() == NoiseLevel::ZERO);
+        assert_eq!(
+            server_key.evaluate_lut(&[&out], &square).unwrap_err(),
+            GadgetError::PinCountMismatch {
+                expected: 2,
+                actual: 1
+            }
+        );
+    }
+
+    #[test]
+    fn evaluate_gate_with_power_of_two_modulus() {
+        let parameters = GadgetParameters {
+            ciphertext_modulus: CiphertextModulus::try_new_power_of_2(31).unwrap(),
+            ..BOOLEAN_PARAMETERS
+        };
+        let (client_key, server_key) = gen_keys(&parameters);
+        assert_eq!(
+            server_key.ciphertext_modulus().get_custom_modulus(),
+            1 << 31
+        );
+
+        for lhs in [false, true] {
+            for rhs in [false, true] {
+                let lhs_ct = client_key.encrypt(lhs);
+                let rhs_ct = client_key.encrypt(rhs);
+                let out_ct = server_key.and(&lhs_ct, &rhs_ct).unwrap();
+                assert_eq!(client_key.decrypt(&out_ct), lhs && rhs);
+
+                let out_ct = server_key.xor(&lhs_ct, &Ciphertext::Trivial(rhs)).unwrap();
+                assert_eq!(client_key.decrypt(&out_ct), lhs ^ rhs);
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported_ciphertext_modulus_is_rejected() {
+        let prime = (1 << 31) - 1;
+        let parameters = GadgetParameters {
+            ciphertext_modulus: CiphertextModulus::new(prime),
+            ..BOOLEAN_PARAMETERS
+        };
+        assert_eq!(
+            parameters.scalar_ciphertext_modulus::<u32>().unwrap_err(),
+            GadgetError::UnsupportedCiphertextModulus(prime)
+        );
+        assert_eq!(
+            ClientKey::<u32>::try_new(&parameters).map(|_| ()),
+            Err(GadgetError::UnsupportedCiphertextModulus(prime))
+        );
+
+        // fits a 64 bits torus only
+        let parameters = GadgetParameters {
+            ciphertext_modulus: CiphertextModulus::try_new_power_of_2(40).unwrap(),
+            ..BOOLEAN_PARAMETERS
+        };
+        assert!(parameters.scalar_ciphertext_modulus::<u32>().is_err());
+        assert!(parameters.scalar_ciphertext_modulus::<u64>().is_ok());
+    }
+
+    #[test]
+    fn evaluate_gate_many_matches_evaluate_gate() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let and_encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+
+        let inputs = [(0, 0), (0, 1), (1, 0), (1, 1), (1, 1)];
+        let batch = || {
+            GadgetEngine::with_thread_local_mut(|engine| {
+                inputs
+                    .iter()
+                    .map(|(lhs, rhs)| {
+                        vec![
+                            engine.encrypt(*lhs, &client_key, 3),
+                            engine.encrypt(*rhs, &client_key, 3),
+                        ]
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        server_key.enable_stats(true);
+        for pipelined in [false, true] {
+            server_key.reset_stats();
+            let outputs = server_key
+                .evaluate_gate_many(batch(), &and_encoding, pipelined)
+                .unwrap();
+
+            assert_eq!(outputs.len(), inputs.len());
+            assert_eq!(server_key.stats().pbs_count, inputs.len() as u64);
+            GadgetEngine::with_thread_local_mut(|engine| {
+                for ((lhs, rhs), output) in inputs.iter().zip(outputs.iter()) {
+                    assert_eq!(engine.decrypt(output, &client_key), lhs & rhs);
+                }
+            });
+        }
+
+        // sets of trivial inputs keep the noise level given by evaluate_gate
+        let trivial_batch = vec![
+            vec![Ciphertext::Trivial(true), Ciphertext::Trivial(true)],
+            vec![
+                Ciphertext::Trivial(false),
+                GadgetEngine::with_thread_local_mut(|engine| engine.encrypt(1, &client_key, 3)),
+            ],
+        ];
+        for pipelined in [false, true] {
+            let outputs = server_key
+                .evaluate_gate_many(trivial_batch.clone(), &and_encoding, pipelined)
+                .unwrap();
+            for (input_ciphertexts, output) in trivial_batch.iter().zip(outputs.iter()) {
+                let expected = server_key
+                    .evaluate_gate(input_ciphertexts.clone(), &and_encoding)
+                    .unwrap();
+                assert_eq!(output.noise_level(), expected.noise_level());
+                GadgetEngine::with_thread_local_mut(|engine| {
+                    assert_eq!(
+                        engine.decrypt(output, &client_key),
+                        engine.decrypt(&expected, &client_key)
+                    );
+                });
+            }
+        }
+
+        // errors of any set of inputs are reported
+        let mut invalid_batch = batch();
+        invalid_batch[2].pop();
+        assert_eq!(
+            server_key
+                .evaluate_gate_many(invalid_batch, &and_encoding, true)
+                .unwrap_err(),
+            GadgetError::PinCountMismatch {
+                expected: 2,
+                actual: 1
+            }
+        );
+    }
+
+    #[test]
+    fn evaluate_gate_ref_on_shared_inputs() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let and_encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+
+        for lhs in 0..2u32 {
+            for rhs in 0..2u32 {
+                let (lhs_ct, rhs_ct) = GadgetEngine::with_thread_local_mut(|engine| {
+                    (
+                        engine.encrypt(lhs, &client_key, 3),
+                        engine.encrypt(rhs, &client_key, 3),
+                    )
+                });
+
+                // the same ciphertext can feed several pins and gates
+                let and_ct = server_key
+                    .evaluate_gate_ref(&[&lhs_ct, &rhs_ct], &and_encoding)
+                    .unwrap();
+                let square_ct = server_key
+                    .evaluate_gate_ref(&[&lhs_ct, &lhs_ct], &and_encoding)
+                    .unwrap();
+                GadgetEngine::with_thread_local_mut(|engine| {
+                    assert_eq!(engine.decrypt(&and_ct, &client_key), lhs & rhs);
+                    assert_eq!(engine.decrypt(&square_ct, &client_key), lhs);
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn trivial_encryptions_are_evaluated_in_the_clear() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let and_encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        server_key.enable_stats(true);
+
+        for lhs in 0..2u32 {
+            for rhs in 0..2u32 {
+                let lhs_ct = server_key.trivial_encrypt_modular(lhs, 3);
+                assert_eq!(lhs_ct.trivial_message(), Some(lhs));
+                assert_eq!(lhs_ct.noise_level(), NoiseLevel::ZERO);
+
+                // a gate over constants is evaluated without bootstrap and stays trivial
+                let out_ct = server_key
+                    .evaluate_gate(
+                        vec![lhs_ct.clone(), server_key.trivial_encrypt(rhs == 1)],
+                        &and_encoding,
+                    )
+                    .unwrap();
+                assert_eq!(out_ct.trivial_message(), Some(lhs & rhs));
+
+                // and constants mix with encrypted inputs like any ciphertext
+                let rhs_ct = GadgetEngine::with_thread_local_mut(|engine| {
+                    engine.encrypt(rhs, &client_key, 3)
+                });
+                let out_ct = server_key
+                    .evaluate_gate(vec![lhs_ct, rhs_ct], &and_encoding)
+                    .unwrap();
+                assert_eq!(out_ct.trivial_message(), None);
+                GadgetEngine::with_thread_local_mut(|engine| {
+                    assert_eq!(engine.decrypt(&out_ct, &client_key), lhs & rhs);
+                });
+            }
+        }
+        assert_eq!(server_key.stats().pbs_count, 4);
+    }
+
+    #[test]
+    fn trivial_inputs_fold_constant_gates() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let and_encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let maj_encoding =
+            Encoding::new_canonical(0b1110_1000, 3, vec![1, 1, 1], vec![0, 1], vec![2, 3], 4);
+        let encrypt = |value: bool, p: u32| {
+            GadgetEngine::with_thread_local_mut(|engine| {
+                engine.encrypt(value as u32, &client_key, p)
+            })
+        };
+        server_key.enable_stats(true);
+
+        for value in [false, true] {
+            // a false input sets the output of an AND gate, whatever the other input encrypts
+            let out_ct = server_key
+                .evaluate_gate(
+                    vec![encrypt(value, 3), Ciphertext::Trivial(false)],
+                    &and_encoding,
+                )
+                .unwrap();
+            assert!(matches!(out_ct, Ciphertext::Trivial(false)));
+
+            // two equal inputs of a majority set its output
+            let out_ct = server_key
+                .evaluate_gate(
+                    vec![
+                        Ciphertext::Trivial(value),
+                        encrypt(!value, 4),
+                        Ciphertext::Trivial(value),
+                    ],
+                    &maj_encoding,
+                )
+                .unwrap();
+            assert!(matches!(out_ct, Ciphertext::Trivial(out) if out == value));
+
+            let out_ct = server_key
+                .evaluate_gate(
+                    vec![Ciphertext::Trivial(true), Ciphertext::Trivial(value)],
+                    &and_encoding,
+                )
+                .unwrap();
+            assert!(matches!(out_ct, Ciphertext::Trivial(out) if out == value));
+        }
+        assert_eq!(server_key.stats().pbs_count, 0);
+
+        // a true input leaves the output to the other input
+        let out_ct = server_key
+            .evaluate_gate(
+                vec![encrypt(true, 3), Ciphertext::Trivial(true)],
+                &and_encoding,
+            )
+            .unwrap();
+        GadgetEngine::with_thread_local_mut(|engine| {
+            assert_eq!(engine.decrypt(&out_ct, &client_key), 1);
+        });
+        assert_eq!(server_key.stats().pbs_count, 1);
+
+        // other outputs are trivially encrypted modulo new_p
+        let mut encoding = and_encoding.clone();
+        (encoding.new_0, encoding.new_1, encoding.new_p) = (1, 3, 5);
+        let out_ct = server_key
+            .evaluate_gate(
+                vec![Ciphertext::Trivial(false), encrypt(true, 3)],
+                &encoding,
+            )
+            .unwrap();
+        assert_eq!(out_ct.trivial_message(), Some(1));
+        assert_eq!(server_key.stats().pbs_count, 1);
+    }
+
+    #[test]
+    fn linear_combination_without_bootstrap() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        server_key.enable_stats(true);
+
+        for a in 0..2u32 {
+            for b in 0..2u32 {
+                let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                    vec![
+                        engine.encrypt(a, &client_key, 7),
+                        engine.encrypt(b, &client_key, 7),
+                        Ciphertext::Trivial(true),
+                    ]
+                });
+
+                // 1 + 2a + 3b + 4 modulo 7
+                let sum_ct = server_key
+                    .linear_combination(&inputs, &[2, 3, 4], 1, 7)
+                    .unwrap();
+                assert_eq!(sum_ct.noise_level(), NoiseLevel(4 + 9));
+                GadgetEngine::with_thread_local_mut(|engine| {
+                    assert_eq!(
+                        engine.decrypt(&sum_ct, &client_key),
+                        (5 + 2 * a + 3 * b) % 7
+                    );
+                });
+            }
+        }
+        assert_eq!(server_key.stats().pbs_count, 0);
+
+        let inputs = vec![Ciphertext::Trivial(true)];
+        assert_eq!(
+            server_key
+                .linear_combination(&inputs, &[1, 1], 0, 7)
+                .unwrap_err(),
+            GadgetError::PinCountMismatch {
+                expected: 2,
+                actual: 1
+            }
+        );
+    }
+
+    #[test]
+    fn evaluate_gate_into_reuses_output() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let and_encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let refresh_encoding = Encoding::new_refresh(3);
+
+        let mut output = Ciphertext::Placeholder;
+        let mut previous_ptr = None;
+        for lhs in 0..2u32 {
+            for rhs in 0..2u32 {
+                let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                    vec![
+                        engine.encrypt(lhs, &client_key, 3),
+                        engine.encrypt(rhs, &client_key, 3),
+                    ]
+                });
+                server_key
+                    .evaluate_gate_into(inputs, &and_encoding, &mut output)
+                    .unwrap();
+
+                let Ciphertext::Encrypted(lwe_ct, _, _) = &output else {
+                    panic!("expected an encrypted output");
+                };
+                let ptr = lwe_ct.as_ref().as_ptr();
+                if let Some(previous_ptr) = previous_ptr {
+                    assert_eq!(ptr, previous_ptr);
+                }
+                previous_ptr = Some(ptr);
+
+                server_key
+                    .bootstrap_assign(&mut output, &refresh_encoding)
+                    .unwrap();
+                GadgetEngine::with_thread_local_mut(|engine| {
+                    assert_eq!(engine.decrypt(&output, &client_key), lhs & rhs);
+                });
+            }
+        }
+
+        let inputs = vec![Ciphertext::Trivial(true)];
+        assert!(server_key
+            .evaluate_gate_into(inputs, &and_encoding, &mut output)
+            .is_err());
+        assert!(matches!(output, Ciphertext::Placeholder));
+
+        // the linear combination succeeds but the bootstrap fails, the sum of the inputs of
+        // row 3 setting the padding bit of p = 4
+        let padding_encoding = Encoding::new_canonical(8, 2, vec![2, 3], vec![0, 2, 3], vec![1], 4);
+        let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+            vec![
+                engine.encrypt(1, &client_key, 4),
+                engine.encrypt(1, &client_key, 4),
+            ]
+        });
+        let mut output =
+            GadgetEngine::with_thread_local_mut(|engine| engine.encrypt(1, &client_key, 3));
+        assert!(matches!(
+            server_key.evaluate_gate_into(inputs, &padding_encoding, &mut output),
+            Err(GadgetError::InvalidEncoding(_))
+        ));
+        assert!(matches!(output, Ciphertext::Placeholder));
+    }
+
+    #[test]
+    fn evaluate_gate_with_multi_bit_bootstrapping_key() {
+        let parameters = GadgetParameters {
+            grouping_factor: Some(LweBskGroupingFactor(2)),
+            ..PLAINTEXT_3_BITS_PARAMETERS
+        };
+        let (client_key, server_key) = gen_keys(&parameters);
+        assert!(matches!(
+            server_key.bootstrapping_key,
+            GadgetBootstrappingKey::MultiBit { .. }
+        ));
+
+        let and_encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let encodings = [
+            and_encoding.clone(),
+            Encoding::new_canonical(14, 2, vec![1, 1], vec![0], vec![1, 2], 3),
+        ];
+
+        for lhs in 0..2u32 {
+            for rhs in 0..2u32 {
+                let encrypt_inputs = || {
+                    GadgetEngine::with_thread_local_mut(|engine| {
+                        vec![
+                            engine.encrypt(lhs, &client_key, 3),
+                            engine.encrypt(rhs, &client_key, 3),
+                        ]
+                    })
+                };
+
+                let and_ct = server_key
+                    .evaluate_gate(encrypt_inputs(), &and_encoding)
+                    .unwrap();
+                let outputs = server_key
+                    .evaluate_gate_multi(encrypt_inputs(), &encodings)
+                    .unwrap();
+
+                GadgetEngine::with_thread_local_mut(|engine| {
+                    assert_eq!(engine.decrypt(&and_ct, &client_key), lhs & rhs);
+                    assert_eq!(engine.decrypt(&outputs[0], &client_key), lhs & rhs);
+                    assert_eq!(engine.decrypt(&outputs[1], &client_key), lhs | rhs);
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported_grouping_factor_is_rejected() {
+        for factor in [1, 3, 5] {
+            // 640 is not a multiple of 3
+            let parameters = GadgetParameters {
+                grouping_factor: Some(LweBskGroupingFactor(factor)),
+                ..PLAINTEXT_3_BITS_PARAMETERS
+            };
+            assert_eq!(
+                parameters.check_grouping_factor(),
+                Err(GadgetError::UnsupportedGroupingFactor(factor))
+            );
+        }
+
+        let parameters = GadgetParameters {
+            grouping_factor: Some(LweBskGroupingFactor(4)),
+            ..PLAINTEXT_3_BITS_PARAMETERS
+        };
+        assert_eq!(parameters.check_grouping_factor(), Ok(()));
+    }
+
+    #[test]
+    fn bootstrap_with_modulus_switch_to_binary() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+
+        // identity on canonical inputs, switching from p = 3 to new_p = 2
+        let encoding = Encoding::new(2, 1, vec![0], vec![1], vec![0, 2], vec![1], 0, 1, 3, 2);
+
+        for _ in 0..10 {
+            for lhs in 0..2u32 {
+                for rhs in 0..2u32 {
+                    let (lhs_ct, rhs_ct) = GadgetEngine::with_thread_local_mut(|engine| {
+                        (
+                            engine.encrypt(lhs, &client_key, 3),
+                            engine.encrypt(rhs, &client_key, 3),
+                        )
+                    });
+
+                    let lhs_ct = server_key
+                        .bootstrap_with_modulus_switch(lhs_ct, &encoding)
+                        .unwrap();
+                    let rhs_ct = server_key
+                        .bootstrap_with_modulus_switch(rhs_ct, &encoding)
+                        .unwrap();
+
+                    // xor is a plain addition modulo 2
+                    let xor_ct = match (lhs_ct, rhs_ct) {
+                        (
+                            Ciphertext::Encrypted(mut lhs_lwe, lhs_modulus, lhs_noise),
+                            Ciphertext::Encrypted(rhs_lwe, rhs_modulus, rhs_noise),
+                        ) => {
+                            assert_eq!(lhs_modulus, 2);
+                            assert_eq!(rhs_modulus, 2);
+                            lwe_ciphertext_add_assign(&mut lhs_lwe, &rhs_lwe);
+                            Ciphertext::Encrypted(lhs_lwe, 2, NoiseLevel(lhs_noise.0 + rhs_noise.0))
+                        }
+                        _ => unreachable!(),
+                    };
+
+                    let xor = GadgetEngine::with_thread_local_mut(|engine| {
+                        engine.decrypt(&xor_ct, &client_key)
+                    });
+                    assert_eq!(xor, lhs ^ rhs);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn evaluate_gate_with_even_plaintext_modulus() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let and = Encoding::from_mappings(8, vec![1, 1], 4).unwrap();
+        let xor = Encoding::from_mappings(6, vec![1, 1], 4).unwrap();
+
+        for row in 0..8u32 {
+            let (a, b, c) = (row & 1, (row >> 1) & 1, (row >> 2) & 1);
+            let inputs: Vec<_> = GadgetEngine::with_thread_local_mut(|engine| {
+                [a, b, c]
+                    .into_iter()
+                    .map(|bit| engine.encrypt(bit, &client_key, 4))
+                    .collect()
+            });
+
+            // (a & b) ^ c, the output of the first gate feeding the second one modulo 4
+            let and_ct = server_key
+                .evaluate_gate(inputs[..2].to_vec(), &and)
+                .unwrap();
+            let out = server_key
+                .evaluate_gate(vec![and_ct, inputs[2].clone()], &xor)
+                .unwrap();
+            let decrypted =
+                GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&out, &client_key));
+            assert_eq!(decrypted, (a & b) ^ c);
+        }
+
+        // or, valid modulo 4 but the sum 2 + 3 of its last row sets the padding bit
+        let or = Encoding::from_mappings(0b1110, vec![2, 3], 4).unwrap();
+        let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+            vec![
+                engine.encrypt(1, &client_key, 4),
+                engine.encrypt(1, &client_key, 4),
+            ]
+        });
+        assert!(matches!(
+            server_key.evaluate_gate(inputs, &or),
+            Err(GadgetError::InvalidEncoding(_))
+        ));
+    }
+
+    #[test]
+    fn evaluate_gate_plan_of_decomposed_gate() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        // the parity of 7 inputs has no encoding modulo 7
+        let xor7 = (0..128u32).fold(0u128, |tt, row| {
+            tt | ((row.count_ones() % 2) as u128) << row
+        });
+        let plan = crate::gadget::encoding::decompose::plan(xor7, 7, 7).unwrap();
+        assert!(matches!(plan, GatePlan::Decomposed(_)));
+        assert_eq!(plan.bootstrap_count(), 3);
+
+        for row in [0u32, 0b101_0011, 0b111_1111] {
+            let inputs: Vec<_> = GadgetEngine::with_thread_local_mut(|engine| {
+                (0..7)
+                    .map(|pin| engine.encrypt((row >> pin) & 1, &client_key, 7))
+                    .collect()
+            });
+            let out = server_key.evaluate_gate_plan(inputs, &plan).unwrap();
+            let decrypted =
+                GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&out, &client_key));
+            assert_eq!(decrypted, row.count_ones() % 2, "row {row:#09b}");
+        }
+
+        assert_eq!(
+            server_key
+                .evaluate_gate_plan(vec![server_key.trivial_encrypt(true)], &plan)
+                .unwrap_err(),
+            GadgetError::PinCountMismatch {
+                expected: 7,
+                actual: 1
+            }
+        );
+    }
+
+    #[test]
+    fn evaluate_gate_rejects_mismatched_modulus() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+
+        let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+            vec![
+                engine.encrypt(1, &client_key, 3),
+                engine.encrypt(1, &client_key, 5),
+            ]
+        });
+        assert_eq!(
+            server_key.evaluate_gate(inputs, &encoding).unwrap_err(),
+            GadgetError::EncodingMismatch {
+                expected: 3,
+                pins: vec![1]
+            }
+        );
+
+        // every offending pin is reported, trivial inputs fit any encoding
+        let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+            vec![
+                engine.encrypt(1, &client_key, 5),
+                Ciphertext::Trivial(true),
+                engine.encrypt(0, &client_key, 3),
+            ]
+        });
+        let encoding = Encoding::from_mappings(0b1000_0000, vec![1, 1, 1], 4).unwrap();
+        assert_eq!(
+            server_key.evaluate_gate(inputs, &encoding).unwrap_err(),
+            GadgetError::EncodingMismatch {
+                expected: 4,
+                pins: vec![0, 2]
+            }
+        );
+    }
+
+    #[test]
+    fn smart_evaluate_gate_skips_linear_bootstraps() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+
+        // xor as a linear gate over p = 2
+        let xor_encoding = Encoding::new_canonical(6, 2, vec![1, 1], vec![0], vec![1], 2);
+        assert!(xor_encoding.is_linear());
+
+        let bits: Vec<u32> = (0..4).map(|i| i % 2).collect();
+        let mut inputs = GadgetEngine::with_thread_local_mut(|engine| {
+            bits.iter()
+                .map(|bit| engine.encrypt(*bit, &client_key, 2))
+                .collect::<Vec<_>>()
+        });
+
+        let mut acc = inputs.remove(0);
+        for ct in inputs {
+            acc = server_key
+                .smart_evaluate_gate(vec![acc, ct], &xor_encoding)
+                .unwrap();
+        }
+
+        assert_eq!(acc.noise_level(), NoiseLevel(bits.len()));
+        let out = GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&acc, &client_key));
+        assert_eq!(out, bits.iter().fold(0, |acc, bit| acc ^ bit));
+    }
+
+    #[test]
+    fn smart_evaluate_gate_refreshes_non_canonical_inputs() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+
+        // not as a linear gate over booleans encoded as 1 (false) and 2 (true) modulo 3, the
+        // weight of 2 quadrupling the noise level of each evaluation
+        let not_encoding = Encoding::new(1, 1, vec![1], vec![2], vec![1], vec![2], 1, 2, 3, 3);
+        assert!(not_encoding.is_linear());
+
+        let mut ct =
+            GadgetEngine::with_thread_local_mut(|engine| engine.encrypt(1, &client_key, 3));
+        let mut expected = 1;
+        for _ in 0..5 {
+            ct = server_key
+                .smart_evaluate_gate(vec![ct], &not_encoding)
+                .unwrap();
+            expected = 3 - expected;
+            let out =
+                GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&ct, &client_key));
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn stats_count_operations_per_encoding() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let and_encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let or_encoding = Encoding::new_canonical(14, 2, vec![1, 1], vec![0], vec![1, 2], 3);
+        let encrypt_inputs = || {
+            GadgetEngine::with_thread_local_mut(|engine| {
+                vec![
+                    engine.encrypt(1, &client_key, 3),
+                    engine.encrypt(0, &client_key, 3),
+                ]
+            })
+        };
+
+        // nothing is recorded until enabled
+        server_key
+            .evaluate_gate(encrypt_inputs(), &and_encoding)
+            .unwrap();
+        assert_eq!(server_key.stats().pbs_count, 0);
+
+        server_key.enable_stats(true);
+        for _ in 0..2 {
+            server_key
+                .evaluate_gate(encrypt_inputs(), &and_encoding)
+                .unwrap();
+        }
+        server_key
+            .evaluate_gate_multi(
+                encrypt_inputs(),
+                &[and_encoding.clone(), or_encoding.clone()],
+            )
+            .unwrap();
+
+        let stats = server_key.stats();
+        assert_eq!(stats.pbs_count, 3);
+        assert_eq!(stats.keyswitch_count, 4);
+        assert_eq!(stats.linear_op_count, 3);
+        assert_eq!(stats.per_encoding[&and_encoding].bootstraps, 3);
+        assert_eq!(stats.per_encoding[&or_encoding].bootstraps, 1);
+
+        server_key.reset_stats();
+        assert_eq!(server_key.stats().pbs_count, 0);
+        assert!(server_key.stats().per_encoding.is_empty());
+    }
+
+    #[test]
+    fn server_key_serialization_round_trip() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        let serialized = bincode::serialize(&server_key).unwrap();
+        let deserialized: ServerKey = bincode::deserialize(&serialized).unwrap();
+
+        match (
+            &deserialized.bootstrapping_key,
+            &server_key.bootstrapping_key,
+        ) {
+            (GadgetBootstrappingKey::Classic(lhs), GadgetBootstrappingKey::Classic(rhs)) => {
+                assert_eq!(lhs.as_view().data(), rhs.as_view().data());
+            }
+            _ => panic!("expected a classic bootstrapping key"),
+        }
+        assert_eq!(
+            deserialized.key_switching_key.as_ref(),
+            server_key.key_switching_key.as_ref()
+        );
+
+        // The deserialized key must be usable for gate evaluation
+        for lhs in [false, true] {
+            for rhs in [false, true] {
+                let lhs_ct = client_key.encrypt(lhs);
+                let rhs_ct = client_key.encrypt(rhs);
+                let out_ct = deserialized.and(&lhs_ct, &rhs_ct).unwrap();
+                assert_eq!(client_key.decrypt(&out_ct), lhs && rhs);
+            }
+        }
+    }
+
+    #[test]
+    fn keys_are_reproducible_from_seed() {
+        let (client_key, server_key) = gen_keys_from_seed(&BOOLEAN_PARAMETERS, Seed(42));
+        let (same_client_key, same_server_key) = gen_keys_from_seed(&BOOLEAN_PARAMETERS, Seed(42));
+
+        assert_eq!(client_key, same_client_key);
+        assert_eq!(
+            bincode::serialize(&server_key).unwrap(),
+            bincode::serialize(&same_server_key).unwrap()
+        );
+
+        let (other_client_key, _) = gen_keys_from_seed(&BOOLEAN_PARAMETERS, Seed(43));
+        assert_ne!(client_key, other_client_key);
+
+        let ct = server_key
+            .and(&client_key.encrypt(true), &client_key.encrypt(true))
+            .unwrap();
+        assert!(client_key.decrypt(&ct));
+    }
+
+    #[test]
+    fn keys_of_several_parameter_sets_from_one_seed() {
+        let parameter_sets = [BOOLEAN_PARAMETERS, PLAINTEXT_3_BITS_PARAMETERS];
+        let keys = gen_keys_multi_from_seed(&parameter_sets, Seed(42));
+        assert_eq!(keys.len(), parameter_sets.len());
+
+        for ((client_key, server_key), parameters) in keys.iter().zip(parameter_sets.iter()) {
+            assert_eq!(client_key.parameters, *parameters);
+            assert_eq!(server_key.parameters, *parameters);
+
+            let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+            let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                vec![
+                    engine.encrypt(1, client_key, 3),
+                    engine.encrypt(1, client_key, 3),
+                ]
+            });
+            let out = server_key.evaluate_gate(inputs, &encoding).unwrap();
+            GadgetEngine::with_thread_local_mut(|engine| {
+                assert_eq!(engine.decrypt(&out, client_key), 1);
+            });
+        }
+
+        // the keys of a parameter set do not depend on the parameter sets after it
+        let first_keys = gen_keys_multi_from_seed(&parameter_sets[..1], Seed(42));
+        assert_eq!(first_keys[0].0, keys[0].0);
+        assert_eq!(
+            bincode::serialize(&first_keys[0].1).unwrap(),
+            bincode::serialize(&keys[0].1).unwrap()
+        );
+
+        let other_keys = gen_keys_multi_from_seed(&parameter_sets, Seed(43));
+        assert_ne!(other_keys[1].0, keys[1].0);
+    }
+
+    #[test]
+    fn replacing_thread_local_engine() {
+        let (client_key, server_key) = gen_keys_from_seed(&BOOLEAN_PARAMETERS, Seed(42));
+        let serialized_server_key = bincode::serialize(&server_key).unwrap();
+
+        // the engine of the thread draws its randomness from the provided seeder
+        let (thread_client_key, thread_server_key) = std::thread::spawn(|| {
+            let mut seeder = DeterministicSeeder::<ActivatedRandomGenerator>::new(Seed(42));
+            GadgetEngine::replace_thread_local(GadgetEngine::new_from_seeder(&mut seeder));
+            gen_keys(&BOOLEAN_PARAMETERS)
+        })
+        .join()
+        .unwrap();
+        assert_eq!(thread_client_key, client_key);
+        assert_eq!(
+            bincode::serialize(&thread_server_key).unwrap(),
+            serialized_server_key
+        );
+
+        // other threads are not affected
+        let (other_client_key, _) = std::thread::spawn(|| gen_keys(&BOOLEAN_PARAMETERS))
+            .join()
+            .unwrap();
+        assert_ne!(other_client_key, client_key);
+    }
+
+    #[test]
+    fn evaluation_with_explicit_engine() {
+        let mut engine = GadgetEngine::new_from_seed(Seed(7));
+        let client_key = ClientKey::new_with_engine(&mut engine, &BOOLEAN_PARAMETERS);
+        let server_key = ServerKey::new_with_engine(&mut engine, &client_key);
+
+        // the engine was created like the one of gen_keys_from_seed
+        let (same_client_key, _) = gen_keys_from_seed(&BOOLEAN_PARAMETERS, Seed(7));
+        assert_eq!(client_key, same_client_key);
+
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let refresh_encoding = Encoding::new_refresh(3);
+        for (lhs, rhs) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            let inputs = vec![
+                engine.encrypt(lhs, &client_key, 3),
+                engine.encrypt(rhs, &client_key, 3),
+            ];
+            let out = server_key
+                .evaluate_gate_with_engine(&mut engine, inputs, &encoding)
+                .unwrap();
+            assert_eq!(engine.decrypt(&out, &client_key), lhs & rhs);
+
+            let refreshed = server_key
+                .bootstrap_with_engine(&mut engine, out, &refresh_encoding)
+                .unwrap();
+            assert_eq!(engine.decrypt(&refreshed, &client_key), lhs & rhs);
+        }
+    }
+
+    #[test]
+    fn evaluation_with_explicit_context() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+
+        let encrypt = |message| {
+            GadgetEngine::with_thread_local_mut(|engine| engine.encrypt(message, &client_key, 3))
+        };
+        let decrypt = |ct: &Ciphertext| {
+            GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(ct, &client_key))
+        };
+
+        let mut context = EvaluationContext::new();
+        assert_eq!(context.size_in_bytes(), 0);
+        let mut context_size = None;
+        for (lhs, rhs) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            let out = server_key
+                .evaluate_gate_with_context(
+                    &mut context,
+                    vec![encrypt(lhs), encrypt(rhs)],
+                    &encoding,
+                )
+                .unwrap();
+            assert_eq!(decrypt(&out), lhs & rhs);
+
+            // the buffers are sized by the first gate and reused by the next ones
+            assert!(context.size_in_bytes() > 0);
+            assert_eq!(
+                *context_size.get_or_insert(context.size_in_bytes()),
+                context.size_in_bytes()
+            );
+        }
+
+        // the context can be used on any thread
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let out = server_key
+                    .evaluate_gate_with_context(
+                        &mut context,
+                        vec![encrypt(1), encrypt(1)],
+                        &encoding,
+                    )
+                    .unwrap();
+                assert_eq!(decrypt(&out), 1);
+            });
+        });
+    }
+
+    #[test]
+    fn concurrent_evaluation_with_shared_key() {
+        const THREAD_COUNT: usize = 8;
+        const GATES_PER_THREAD: usize = 20;
+
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let client_key = Arc::new(client_key);
+        let server_key = Arc::new(server_key);
+        server_key.enable_stats(true);
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+
+        let handles: Vec<_> = (0..THREAD_COUNT)
+            .map(|thread| {
+                let client_key = client_key.clone();
+                let server_key = server_key.clone();
+                let encoding = encoding.clone();
+                std::thread::spawn(move || {
+                    for gate in 0..GATES_PER_THREAD {
+                        let (lhs, rhs) = (((thread + gate) % 2) as u32, (gate / 2 % 2) as u32);
+                        let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                            vec![
+                                engine.encrypt(lhs, &client_key, 3),
+                                engine.encrypt(rhs, &client_key, 3),
+                            ]
+                        });
+                        let out = server_key.evaluate_gate(inputs, &encoding).unwrap();
+                        let decrypted = GadgetEngine::with_thread_local_mut(|engine| {
+                            engine.decrypt(&out, &client_key)
+                        });
+                        assert_eq!(decrypted, lhs & rhs);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // every thread shared the cached lookup table and recorded its bootstraps
+        assert_eq!(server_key.lookup_tables.len(), 1);
+        assert_eq!(
+            server_key.stats().pbs_count,
+            (THREAD_COUNT * GATES_PER_THREAD) as u64
+        );
+    }
+
+    #[test]
+    fn compressed_server_key() {
+        let multi_bit_parameters = GadgetParameters {
+            grouping_factor: Some(LweBskGroupingFactor(2)),
+            ..PLAINTEXT_3_BITS_PARAMETERS
+        };
+        for parameters in [BOOLEAN_PARAMETERS, multi_bit_parameters] {
+            let client_key = ClientKey::new(&parameters);
+            let compressed_server_key = CompressedServerKey::new(&client_key);
+
+            let serialized = bincode::serialize(&compressed_server_key).unwrap();
+            let server_key = ServerKey::new(&client_key);
+            assert!(serialized.len() < bincode::serialize(&server_key).unwrap().len());
+
+            let deserialized: CompressedServerKey = bincode::deserialize(&serialized).unwrap();
+            let server_key = deserialized.decompress();
+            assert_eq!(server_key.parameters(), &parameters);
+
+            let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+            for (lhs, rhs) in [(0, 1), (1, 1)] {
+                let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                    vec![
+                        engine.encrypt(lhs, &client_key, 3),
+                        engine.encrypt(rhs, &client_key, 3),
+                    ]
+                });
+                let out = server_key.evaluate_gate(inputs, &encoding).unwrap();
+                let decrypted =
+                    GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&out, &client_key));
+                assert_eq!(decrypted, lhs & rhs);
+            }
+        }
+    }
+
+    #[test]
+    fn switch_outputs_to_small_key() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let ciphertext_modulus = server_key.ciphertext_modulus();
+
+        let mut seeder = DeterministicSeeder::<ActivatedRandomGenerator>::new(Seed(0));
+        let mut generator =
+            EncryptionRandomGenerator::<ActivatedRandomGenerator>::new(seeder.seed(), &mut seeder);
+        let big_lwe_secret_key = client_key.glwe_secret_key.as_lwe_secret_key();
+        for message in 0..3 {
+            let lwe = allocate_and_encrypt_new_lwe_ciphertext(
+                &big_lwe_secret_key,
+                Plaintext(encode(message, 3, ciphertext_modulus)),
+                PLAINTEXT_3_BITS_PARAMETERS.glwe_modular_std_dev,
+                ciphertext_modulus,
+                &mut generator,
+            );
+            let ct = Ciphertext::Encrypted(lwe, 3, NoiseLevel::NOMINAL);
+
+            let small = server_key.switch_to_small_key(&ct).unwrap();
+            let Ciphertext::Encrypted(small_lwe, 3, NoiseLevel::NOMINAL) = &small else {
+                panic!("the plaintext modulus and the noise level are kept");
+            };
+            assert_eq!(
+                small_lwe.lwe_size().to_lwe_dimension(),
+                PLAINTEXT_3_BITS_PARAMETERS.lwe_dimension
+            );
+            assert_eq!(client_key.decrypt_small(&small), message);
+        }
+
+        // gate outputs are already under the small key
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+            vec![
+                engine.encrypt(1, &client_key, 3),
+                engine.encrypt(1, &client_key, 3),
+            ]
+        });
+        let out = server_key.evaluate_gate(inputs, &encoding).unwrap();
+        let small = server_key.switch_to_small_key(&out).unwrap();
+        assert_eq!(client_key.decrypt_small(&small), 1);
+        assert_eq!(
+            server_key
+                .switch_to_small_key(&Ciphertext::Placeholder)
+                .unwrap_err(),
+            GadgetError::PlaceholderCiphertext
+        );
+    }
+
+    #[test]
+    fn evaluate_gates_under_big_encryption_key() {
+        let parameters = GadgetParameters {
+            encryption_key_choice: EncryptionKeyChoice::Big,
+            ..PLAINTEXT_3_BITS_PARAMETERS
+        };
+        assert_eq!(parameters.pbs_order(), PBSOrder::KeyswitchBootstrap);
+        let big_lwe_dimension =
+            LweDimension(parameters.glwe_dimension.0 * parameters.polynomial_size.0);
+        assert_eq!(parameters.encryption_lwe_dimension(), big_lwe_dimension);
+
+        let (client_key, server_key) = gen_keys(&parameters);
+        let encodings = [
+            Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3),
+            Encoding::new_canonical(6, 2, vec![1, 1], vec![0, 2], vec![1], 3),
+        ];
+        for lhs in 0..2u32 {
+            for rhs in 0..2u32 {
+                let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                    vec![
+                        engine.encrypt(lhs, &client_key, 3),
+                        engine.encrypt(rhs, &client_key, 3),
+                    ]
+                });
+                assert!(inputs.iter().all(|ct| ct.is_conformant(&parameters)));
+
+                let out = server_key
+                    .evaluate_gate(inputs.clone(), &encodings[0])
+                    .unwrap();
+                let Ciphertext::Encrypted(lwe, _, _) = &out else {
+                    panic!("gate outputs are encrypted");
+                };
+                assert_eq!(lwe.lwe_size().to_lwe_dimension(), big_lwe_dimension);
+                let decrypted =
+                    GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&out, &client_key));
+                assert_eq!(decrypted, lhs & rhs);
+
+                let small = server_key.switch_to_small_key(&out).unwrap();
+                assert_eq!(client_key.decrypt_small(&small), lhs & rhs);
+
+                let outputs = server_key.evaluate_gate_multi(inputs, &encodings).unwrap();
+                let outputs = GadgetEngine::with_thread_local_mut(|engine| {
+                    outputs
+                        .iter()
+                        .map(|ct| engine.decrypt(ct, &client_key))
+                        .collect::<Vec<_>>()
+                });
+                assert_eq!(outputs, vec![lhs & rhs, lhs ^ rhs]);
+            }
+        }
+    }
+
+    #[test]
+    fn evaluate_gates_with_ntt_backend() {
+        let parameters = GadgetParameters {
+            pbs_backend: PbsBackend::Ntt,
+            ..PLAINTEXT_3_BITS_PARAMETERS
+        };
+        let (client_key, server_key) = gen_keys(&parameters);
+        assert!(matches!(
+            server_key.bootstrapping_key,
+            GadgetBootstrappingKey::Ntt(_)
+        ));
+        assert!(server_key.is_conformant(&parameters));
+        assert!(!server_key.is_conformant(&PLAINTEXT_3_BITS_PARAMETERS));
+        assert_eq!(server_key.diagnose_fft_kernels(), FftKernels::Scalar);
+
+        let encodings = [
+            Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3),
+            Encoding::new_canonical(6, 2, vec![1, 1], vec![0, 2], vec![1], 3),
+        ];
+        for lhs in 0..2u32 {
+            for rhs in 0..2u32 {
+                let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                    vec![
+                        engine.encrypt(lhs, &client_key, 3),
+                        engine.encrypt(rhs, &client_key, 3),
+                    ]
+                });
+                let out = server_key
+                    .evaluate_gate(inputs.clone(), &encodings[0])
+                    .unwrap();
+                let decrypted =
+                    GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&out, &client_key));
+                assert_eq!(decrypted, lhs & rhs);
+
+                let outputs = server_key.evaluate_gate_multi(inputs, &encodings).unwrap();
+                let outputs = GadgetEngine::with_thread_local_mut(|engine| {
+                    outputs
+                        .iter()
+                        .map(|ct| engine.decrypt(ct, &client_key))
+                        .collect::<Vec<_>>()
+                });
+                assert_eq!(outputs, vec![lhs & rhs, lhs ^ rhs]);
+            }
+        }
+
+        let multi_bit_parameters = GadgetParameters {
+            grouping_factor: Some(LweBskGroupingFactor(2)),
+            ..parameters
+        };
+        assert!(matches!(
+            multi_bit_parameters.check_pbs_backend(),
+            Err(GadgetError::UnsupportedNttBackend(_))
+        ));
+    }
+
+    #[test]
+    fn batch_encryption_and_decryption() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let messages = [0, 1, 1, 0, 1, 1];
+
+        let list = client_key.encrypt_many(&messages, 3);
+        assert_eq!(list.len(), messages.len());
+        assert_eq!(list.plaintext_modulus(), 3);
+        assert_eq!(client_key.decrypt_list(&list), messages);
+        assert!(list.get(messages.len()).is_none());
+        assert!(client_key.encrypt_many(&[], 3).is_empty());
+
+        // the AND of consecutive pairs of the list
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let inputs: Vec<Ciphertext> = list.into();
+        let outputs: Vec<_> = inputs
+            .chunks(2)
+            .map(|pair| server_key.evaluate_gate(pair.to_vec(), &encoding).unwrap())
+            .collect();
+        assert_eq!(client_key.decrypt_many(&outputs, 3), vec![0, 0, 1]);
+        assert_eq!(
+            client_key.decrypt_many(&[inputs[1].clone(), Ciphertext::Trivial(true)], 3),
+            vec![1, 1]
+        );
+    }
+
+    #[test]
+    fn gate_evaluation_over_ciphertext_lists() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let lhs = client_key.encrypt_many(&[0, 0, 1, 1], 3);
+        let rhs = client_key.encrypt_many(&[0, 1, 0, 1], 3);
+
+        // views borrow the flat storage of the list
+        let view = lhs.view(1).unwrap();
+        let lwe_size = lhs.lwe_list().lwe_size().0;
+        assert_eq!(
+            view.as_ref().as_ptr(),
+            lhs.lwe_list().as_ref()[lwe_size..].as_ptr()
+        );
+        assert_eq!(lhs.views().count(), 4);
+        assert!(lhs.view(4).is_none());
+
+        let and = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let outputs = server_key.evaluate_gate_list(&[&lhs, &rhs], &and).unwrap();
+        assert_eq!(outputs.len(), 4);
+        assert_eq!(outputs.noise_level(), NoiseLevel::NOMINAL);
+        assert_eq!(client_key.decrypt_list(&outputs), vec![0, 0, 0, 1]);
+
+        // lists go through serialization in one piece
+        let serialized = bincode::serialize(&outputs).unwrap();
+        let outputs: CiphertextList = bincode::deserialize(&serialized).unwrap();
+        assert!(outputs.is_conformant(&PLAINTEXT_3_BITS_PARAMETERS));
+
+        let not = Encoding::new_canonical(1, 1, vec![1], vec![1], vec![0, 2], 3);
+        let negated = server_key.bootstrap_list(&outputs, &not).unwrap();
+        assert_eq!(client_key.decrypt_list(&negated), vec![1, 1, 1, 0]);
+
+        let gathered = server_key
+            .to_ciphertext_list(&[negated.get(3).unwrap(), Ciphertext::Trivial(true)])
+            .unwrap();
+        assert_eq!(client_key.decrypt_list(&gathered), vec![0, 1]);
+
+        assert_eq!(
+            server_key
+                .evaluate_gate_list(&[&lhs, &gathered], &and)
+                .unwrap_err(),
+            GadgetError::ListLengthMismatch {
+                pin: 1,
+                expected: 4,
+                actual: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn gate_outputs_compressed_for_transport() {
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        for (lhs, rhs) in [(0, 1), (1, 1)] {
+            let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                vec![
+                    engine.encrypt(lhs, &client_key, 3),
+                    engine.encrypt(rhs, &client_key, 3),
+                ]
+            });
+            let out = server_key.evaluate_gate(inputs, &encoding).unwrap();
+            let size = bincode::serialize(&out).unwrap().len();
+
+            // `log_modulus` bits per coefficient instead of 32
+            for log_modulus in [16, 12] {
+                let compressed = out
+                    .compress_for_transport(CiphertextModulusLog(log_modulus))
+                    .unwrap();
+                let serialized = bincode::serialize(&compressed).unwrap();
+                assert!(serialized.len() * 32 < size * (log_modulus + 1));
+
+                let deserialized: TransportCiphertext = bincode::deserialize(&serialized).unwrap();
+                assert_eq!(client_key.decrypt_transport(&deserialized), lhs & rhs);
+            }
+        }
+
+        let compressed = Ciphertext::<u32>::Trivial(true)
+            .compress_for_transport(CiphertextModulusLog(12))
+            .unwrap();
+        assert_eq!(client_key.decrypt_transport(&compressed), 1);
+
+        let out = server_key.trivial_encrypt_modular(2, 3);
+        assert_eq!(
+            out.compress_for_transport(CiphertextModulusLog(32))
+                .unwrap_err(),
+            GadgetError::UnsupportedTransportModulus(32)
+        );
+        assert_eq!(
+            Ciphertext::<u32>::Placeholder
+                .compress_for_transport(CiphertextModulusLog(12))
+                .unwrap_err(),
+            GadgetError::PlaceholderCiphertext
+        );
+    }
+
+    #[test]
+    fn conformance_to_parameters() {
+        let multi_bit_parameters = GadgetParameters {
+            grouping_factor: Some(LweBskGroupingFactor(2)),
+            ..PLAINTEXT_3_BITS_PARAMETERS
+        };
+        for parameters in [BOOLEAN_PARAMETERS, multi_bit_parameters] {
+            let client_key = ClientKey::<u64>::new(&parameters);
+            let server_key = ServerKey::new(&client_key);
+            let compressed_server_key = CompressedServerKey::new(&client_key);
+            let (ct, compressed_ct) = GadgetEngine::with_thread_local_mut(|engine| {
+                (
+                    engine.encrypt(1, &client_key, 3),
+                    engine.encrypt_compressed(1, &client_key, 3),
+                )
+            });
+
+            assert!(client_key.is_conformant(&parameters));
+            assert!(server_key.is_conformant(&parameters));
+            assert!(compressed_server_key.is_conformant(&parameters));
+            assert!(ct.is_conformant(&parameters));
+            assert!(compressed_ct.is_conformant(&parameters));
+            assert!(Ciphertext::<u32>::Trivial(true).is_conformant(&parameters));
+            assert!(!Ciphertext::<u32>::Placeholder.is_conformant(&parameters));
+
+            // keys claiming other parameters than the ones they were generated with
+            let other_parameters = GadgetParameters {
+                lwe_dimension: LweDimension(parameters.lwe_dimension.0 + 2),
+                ..parameters
+            };
+            assert!(!ct.is_conformant(&other_parameters));
+            assert!(!compressed_ct.is_conformant(&other_parameters));
+
+            let mut forged_client_key = client_key.clone();
+            forged_client_key.parameters = other_parameters;
+            assert!(!forged_client_key.is_conformant(&other_parameters));
+
+            let mut forged_server_key = server_key.clone();
+            forged_server_key.parameters = other_parameters;
+            assert!(!server_key.is_conformant(&other_parameters));
+            assert!(!forged_server_key.is_conformant(&other_parameters));
+
+            let mut forged_server_key = compressed_server_key.clone();
+            forged_server_key.parameters = other_parameters;
+            assert!(!forged_server_key.is_conformant(&other_parameters));
+
+            let other_grouping = GadgetParameters {
+                grouping_factor: match parameters.grouping_factor {
+                    Some(_) => None,
+                    None => Some(LweBskGroupingFactor(2)),
+                },
+                ..parameters
+            };
+            let mut forged_server_key = server_key.clone();
+            forged_server_key.parameters = other_grouping;
+            assert!(!forged_server_key.is_conformant(&other_grouping));
+
+            let Ciphertext::Encrypted(lwe, _, noise_level) = ct else {
+                unreachable!()
+            };
+            assert!(!Ciphertext::Encrypted(lwe, 0, noise_level).is_conformant(&parameters));
+        }
+    }
+
+    #[test]
+    fn secret_keys_are_redacted_from_debug_output() {
+        let (client_key, _) = gen_keys(&PLAINTEXT_3_BITS_PARAMETERS);
+        let redacted = format!("{client_key:?}");
+        assert!(redacted.contains("<redacted>"));
+        assert!(redacted.contains("lwe_dimension"));
+        assert!(!redacted.contains("LweSecretKey") && !redacted.contains("GlweSecretKey"));
+
+        let revealed = format!("{:?}", client_key.debug_secrets());
+        assert!(revealed.contains("LweSecretKey") && revealed.contains("GlweSecretKey"));
+
+        let share = &client_key.share(2, StandardDev(2f64.powi(-8))).unwrap()[0];
+        assert!(format!("{share:?}").contains("<redacted>"));
+        assert!(!format!("{:?}", share.debug_secrets()).contains("<redacted>"));
+    }
+
+    #[test]
+    fn diagnosed_fft_kernels_match_cpu_features() {
+        let (_, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let kernels = server_key.diagnose_fft_kernels();
+
+        #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+        {
+            let has_avx2_fma = is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma");
+            // the AVX2 kernels also require some of the other instructions of x86-64-v3
+            if kernels != FftKernels::Scalar {
+                assert!(has_avx2_fma);
+            }
+            if cfg!(not(feature = "nightly-avx512")) {
+                assert_ne!(kernels, FftKernels::Avx512);
+            }
+        }
+        #[cfg(not(any(target_arch = "x86_64", target_arch = "x86")))]
+        assert_eq!(kernels, FftKernels::Scalar);
+    }
 }
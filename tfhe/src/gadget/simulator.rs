@@ -0,0 +1,244 @@
+//! Cleartext simulation of the gadget evaluation API.
+//!
+//! The [`SimulatorKey`] mirrors the gate and circuit evaluation methods of the
+//! [`ServerKey`](super::server_key::ServerKey) but computes directly on messages modulo the
+//! plaintext modulus. It follows the p-encoding arithmetic of the encrypted evaluation, i.e. it
+//! computes the linear combination of the inputs with the input mappings and looks the result up
+//! in the output sets of the encoding, so logic and encoding errors can be found instantly before
+//! running on ciphertexts.
+//!
+//! Unlike a bootstrap, which maps every value outside of `output_encodings_0` to `new_1`, the
+//! simulator reports values found in neither output set as an invalid encoding.
+
+use crate::gadget::circuit::Circuit;
+use crate::gadget::encoding::Encoding;
+use crate::gadget::engine::{check_bootstrappable, check_encoding, check_input_moduli};
+use crate::gadget::error::GadgetError;
+use std::collections::HashMap;
+
+/// A message modulo its plaintext modulus, standing for a ciphertext in simulations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SimulatedCiphertext {
+    pub(crate) message: u32,
+    pub(crate) plaintext_modulus: u32,
+}
+
+impl SimulatedCiphertext {
+    pub fn message(&self) -> u32 {
+        self.message
+    }
+
+    pub fn plaintext_modulus(&self) -> u32 {
+        self.plaintext_modulus
+    }
+}
+
+/// Evaluates gates and circuits on cleartext messages, see the [module](self) documentation.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SimulatorKey;
+
+impl SimulatorKey {
+    pub fn new() -> SimulatorKey {
+        SimulatorKey
+    }
+
+    /// Simulates the encryption of `message` modulo `p`.
+    pub fn encrypt(&self, message: u32, p: u32) -> SimulatedCiphertext {
+        SimulatedCiphertext {
+            message: message % p,
+            plaintext_modulus: p,
+        }
+    }
+
+    pub fn decrypt(&self, ct: &SimulatedCiphertext) -> u32 {
+        ct.message
+    }
+
+    pub fn bootstrap(
+        &self,
+        ct: SimulatedCiphertext,
+        encoding: &Encoding,
+    ) -> Result<SimulatedCiphertext, GadgetError> {
+        check_encoding(encoding)?;
+        check_bootstrappable(encoding)?;
+
+        if ct.plaintext_modulus != encoding.p {
+            return Err(GadgetError::PlaintextModulusMismatch {
+                pin: 0,
+                expected: encoding.p,
+                actual: ct.plaintext_modulus,
+            });
+        }
+
+        let message = if encoding.output_encodings_0.contains(&ct.message) {
+            encoding.new_0
+        } else if encoding.output_encodings_1.contains(&ct.message) {
+            encoding.new_1
+        } else {
+            return Err(GadgetError::InvalidEncoding(format!(
+                "value {} modulo {} is in no output set",
+                ct.message, encoding.p
+            )));
+        };
+
+        Ok(SimulatedCiphertext {
+            message,
+            plaintext_modulus: encoding.new_p,
+        })
+    }
+
+    pub fn evaluate_gate(
+        &self,
+        input_ciphertexts: Vec<SimulatedCiphertext>,
+        encoding: &Encoding,
+    ) -> Result<SimulatedCiphertext, GadgetError> {
+        let sum = self.linear_combination(encoding, &input_ciphertexts)?;
+        self.bootstrap(sum, encoding)
+    }
+
+    pub fn evaluate_gate_multi(
+        &self,
+        input_ciphertexts: Vec<SimulatedCiphertext>,
+        encodings: &[Encoding],
+    ) -> Result<Vec<SimulatedCiphertext>, GadgetError> {
+        if encodings.is_empty() {
+            return Err(GadgetError::InvalidEncoding(
+                "multi-value evaluation needs an encoding".to_string(),
+            ));
+        }
+
+        encodings
+            .iter()
+            .map(|encoding| self.evaluate_gate(input_ciphertexts.clone(), encoding))
+            .collect()
+    }
+
+    /// Evaluates `circuit` on the simulated ciphertexts of its named `inputs`, returning the
+    /// simulated ciphertexts of its named outputs.
+    pub fn evaluate_circuit(
+        &self,
+        circuit: &Circuit,
+        inputs: &HashMap<String, SimulatedCiphertext>,
+    ) -> Result<HashMap<String, SimulatedCiphertext>, GadgetError> {
+        let mut signals: HashMap<&str, SimulatedCiphertext> = HashMap::new();
+        for input in circuit.inputs.iter() {
+            let ct = inputs
+                .get(input)
+                .ok_or_else(|| GadgetError::MissingCircuitInput(input.clone()))?;
+            signals.insert(input, *ct);
+        }
+
+        for (gate, encoding) in circuit.gates.iter().zip(circuit.encodings.iter()) {
+            let output = match encoding {
+                Some(encoding) => {
+                    let input_ciphertexts = gate
+                        .inputs
+                        .iter()
+                        .map(|input| signals[input.as_str()])
+                        .collect();
+                    self.evaluate_gate(input_ciphertexts, encoding)?
+                }
+                None => self.encrypt((gate.tt_value & 1) as u32, circuit.plaintext_modulus),
+            };
+            signals.insert(&gate.output, output);
+        }
+
+        Ok(circuit
+            .outputs
+            .iter()
+            .map(|output| (output.clone(), signals[output.as_str()]))
+            .collect())
+    }
+
+    fn linear_combination(
+        &self,
+        encoding: &Encoding,
+        input_ciphertexts: &[SimulatedCiphertext],
+    ) -> Result<SimulatedCiphertext, GadgetError> {
+        check_encoding(encoding)?;
+        if encoding.pin_count != input_ciphertexts.len() {
+            return Err(GadgetError::PinCountMismatch {
+                expected: encoding.pin_count,
+                actual: input_ciphertexts.len(),
+            });
+        }
+
+        check_input_moduli(
+            encoding.p,
+            input_ciphertexts
+                .iter()
+                .map(|ct| Some(ct.plaintext_modulus)),
+        )?;
+
+        let mut sum = 0u64;
+        // input_mappings_1 stores pin mappings in reverse order of the input ciphertexts
+        for (scalar_val, ct) in encoding
+            .input_mappings_1
+            .iter()
+            .rev()
+            .zip(input_ciphertexts.iter())
+        {
+            sum = (sum + *scalar_val as u64 * ct.message as u64) % encoding.p as u64;
+        }
+
+        Ok(SimulatedCiphertext {
+            message: sum as u32,
+            plaintext_modulus: encoding.p,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadget::encoding::library;
+
+    #[test]
+    fn simulate_library_cells() {
+        let simulator = SimulatorKey::new();
+
+        for name in library::names() {
+            let encoding = library::get(name).unwrap();
+            let p = encoding.p();
+
+            for row in 0..(1u128 << encoding.pin_count) {
+                let inputs = (0..encoding.pin_count)
+                    .map(|pin| simulator.encrypt(((row >> pin) & 1) as u32, p))
+                    .collect();
+                let output = simulator.evaluate_gate(inputs, encoding).unwrap();
+                let expected = ((encoding.tt_value() >> row) & 1) as u32;
+                assert_eq!(simulator.decrypt(&output), expected, "{name}, row {row}");
+            }
+        }
+    }
+
+    #[test]
+    fn simulate_circuit() {
+        let circuit = Circuit::from_blif(
+            ".model xor\n.inputs a b\n.outputs y\n.names a b y\n10 1\n01 1\n.end\n",
+        )
+        .unwrap();
+        let simulator = SimulatorKey::new();
+        let p = circuit.plaintext_modulus();
+
+        for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            let mut inputs = HashMap::new();
+            inputs.insert("a".to_string(), simulator.encrypt(a, p));
+            inputs.insert("b".to_string(), simulator.encrypt(b, p));
+            let outputs = simulator.evaluate_circuit(&circuit, &inputs).unwrap();
+            assert_eq!(simulator.decrypt(&outputs["y"]), a ^ b);
+        }
+    }
+
+    #[test]
+    fn simulator_flags_bad_encodings() {
+        let simulator = SimulatorKey::new();
+        // and with the value of the true row missing from the output sets
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![], 3);
+        let inputs = vec![simulator.encrypt(1, 3), simulator.encrypt(1, 3)];
+        assert!(matches!(
+            simulator.evaluate_gate(inputs, &encoding),
+            Err(GadgetError::InvalidEncoding(_))
+        ));
+    }
+}
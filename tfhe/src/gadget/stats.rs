@@ -0,0 +1,116 @@
+//! Runtime statistics of a [`ServerKey`](super::server_key::ServerKey).
+//!
+//! Collection is opt-in, see [`ServerKey::enable_stats`](super::server_key::ServerKey::enable_stats).
+//! While disabled, recording an operation costs a single relaxed atomic load.
+
+use crate::gadget::encoding::Encoding;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Statistics of the bootstraps performed with one encoding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EncodingStats {
+    pub bootstraps: u64,
+    /// Wall-time spent bootstrapping, split evenly between the encodings of a multi-value
+    /// bootstrap
+    pub time: Duration,
+}
+
+/// Snapshot of the operations performed by a server key since statistics were enabled or
+/// last reset.
+#[derive(Clone, Debug, Default)]
+pub struct ServerKeyStats {
+    /// Number of blind rotations, a multi-value bootstrap counts as one
+    pub pbs_count: u64,
+    pub keyswitch_count: u64,
+    /// Number of linear combinations of ciphertexts, e.g. the weighted sum of gate inputs
+    pub linear_op_count: u64,
+    pub per_encoding: HashMap<Encoding, EncodingStats>,
+}
+
+impl ServerKeyStats {
+    /// Returns the encodings sorted by decreasing bootstrapping time.
+    pub fn most_expensive_encodings(&self) -> Vec<(&Encoding, &EncodingStats)> {
+        let mut encodings: Vec<_> = self.per_encoding.iter().collect();
+        encodings.sort_unstable_by_key(|(_, stats)| std::cmp::Reverse(stats.time));
+        encodings
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct StatsCollector {
+    enabled: AtomicBool,
+    pbs_count: AtomicU64,
+    keyswitch_count: AtomicU64,
+    linear_op_count: AtomicU64,
+    per_encoding: Mutex<HashMap<Encoding, EncodingStats>>,
+}
+
+impl StatsCollector {
+    pub(crate) fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_linear_op(&self) {
+        if self.is_enabled() {
+            self.linear_op_count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records a keyswitch outside of a bootstrap, e.g. to another client key.
+    pub(crate) fn record_keyswitch(&self) {
+        if self.is_enabled() {
+            self.keyswitch_count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records a blind rotation followed by one keyswitch per encoding of `encodings`.
+    pub(crate) fn record_bootstrap(&self, encodings: &[Encoding], time: Duration) {
+        if !self.is_enabled() || encodings.is_empty() {
+            return;
+        }
+
+        self.pbs_count.fetch_add(1, Ordering::Relaxed);
+        self.keyswitch_count
+            .fetch_add(encodings.len() as u64, Ordering::Relaxed);
+
+        let time = time / encodings.len() as u32;
+        let mut per_encoding = self.per_encoding.lock().unwrap();
+        for encoding in encodings.iter() {
+            let stats = per_encoding.entry(encoding.clone()).or_default();
+            stats.bootstraps += 1;
+            stats.time += time;
+        }
+    }
+
+    /// Records the bootstrap of a lookup table with more than two outputs, which is not
+    /// attributed to an encoding.
+    pub(crate) fn record_lut_bootstrap(&self) {
+        if self.is_enabled() {
+            self.pbs_count.fetch_add(1, Ordering::Relaxed);
+            self.keyswitch_count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> ServerKeyStats {
+        ServerKeyStats {
+            pbs_count: self.pbs_count.load(Ordering::Relaxed),
+            keyswitch_count: self.keyswitch_count.load(Ordering::Relaxed),
+            linear_op_count: self.linear_op_count.load(Ordering::Relaxed),
+            per_encoding: self.per_encoding.lock().unwrap().clone(),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        self.pbs_count.store(0, Ordering::Relaxed);
+        self.keyswitch_count.store(0, Ordering::Relaxed);
+        self.linear_op_count.store(0, Ordering::Relaxed);
+        self.per_encoding.lock().unwrap().clear();
+    }
+}
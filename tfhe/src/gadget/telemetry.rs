@@ -0,0 +1,237 @@
+//! Tracing spans around the costly steps of the gadget engine.
+//!
+//! With the `telemetry` feature, the engine enters a `tracing` span of target `tfhe::gadget`
+//! around each of these steps, so that a service can attribute the latency of an encrypted
+//! evaluation with its usual subscriber:
+//! * `keygen`, with the `key` generated, `client` or `server`, and the LWE and GLWE dimensions;
+//! * `encrypt`, with the plaintext modulus and the LWE dimension;
+//! * `linear_combination`, with the hash of the encoding of the gate, see [`encoding_hash`], its
+//!   pin count and the LWE dimension;
+//! * `pbs`, with the input LWE dimension and the polynomial size of the bootstrapping key;
+//! * `keyswitch`, with the input and output LWE dimensions.
+//!
+//! Without the feature the spans compile to nothing and their fields are not evaluated.
+//! [`ENABLED`] reports whether the feature is enabled.
+
+use crate::gadget::encoding::Encoding;
+
+pub const ENABLED: bool = cfg!(feature = "telemetry");
+
+/// Enters the span `$name` of target `tfhe::gadget` with the given fields, until the returned
+/// guard is dropped.
+#[cfg(feature = "telemetry")]
+macro_rules! span {
+    ($name:literal $(, $field:ident = $value:expr)* $(,)?) => {
+        tracing::info_span!(target: "tfhe::gadget", $name $(, $field = $value)*).entered()
+    };
+}
+
+/// Without the `telemetry` feature, returns a guard entering no span.
+#[cfg(not(feature = "telemetry"))]
+macro_rules! span {
+    ($name:literal $(, $field:ident = $value:expr)* $(,)?) => {
+        $crate::gadget::telemetry::DisabledSpan
+    };
+}
+
+pub(crate) use span;
+
+/// Guard of a span when the `telemetry` feature is disabled.
+#[cfg(not(feature = "telemetry"))]
+pub(crate) struct DisabledSpan;
+
+/// 64-bit FNV-1a hasher, whose output only depends on the bytes written to it.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn write_u32s(&mut self, values: &[u32]) {
+        self.write(&(values.len() as u64).to_le_bytes());
+        for value in values {
+            self.write(&value.to_le_bytes());
+        }
+    }
+}
+
+/// Returns the hash of `encoding`, identifying the gates of the same encoding across spans.
+///
+/// The hash is the FNV-1a hash of the little-endian fields of the encoding, so it is the same
+/// for every process, platform and release, and traces of several servers can be joined on it.
+pub fn encoding_hash(encoding: &Encoding) -> u64 {
+    let mut hasher = Fnv1a(Fnv1a::OFFSET_BASIS);
+    hasher.write(&encoding.tt_value.to_le_bytes());
+    hasher.write(&(encoding.pin_count as u64).to_le_bytes());
+    hasher.write_u32s(&encoding.input_mappings_0);
+    hasher.write_u32s(&encoding.input_mappings_1);
+    hasher.write_u32s(&encoding.output_encodings_0);
+    hasher.write_u32s(&encoding.output_encodings_1);
+    for value in [encoding.new_0, encoding.new_1, encoding.p, encoding.new_p] {
+        hasher.write(&value.to_le_bytes());
+    }
+    hasher.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding_hash_identifies_encodings() {
+        let and = Encoding::from_mappings(8, vec![1, 1], 3).unwrap();
+        let or = Encoding::from_mappings(14, vec![1, 1], 3).unwrap();
+        assert_eq!(encoding_hash(&and), encoding_hash(&and.clone()));
+        assert_ne!(encoding_hash(&and), encoding_hash(&or));
+
+        // spans are entered whether or not the feature is enabled
+        let _span = span!("linear_combination", encoding = encoding_hash(&and));
+    }
+
+    #[test]
+    fn encoding_hash_is_stable() {
+        // FNV-1a test vectors
+        let mut hasher = Fnv1a(Fnv1a::OFFSET_BASIS);
+        assert_eq!(hasher.0, 0xcbf2_9ce4_8422_2325);
+        hasher.write(b"a");
+        assert_eq!(hasher.0, 0xaf63_dc4c_8601_ec8c);
+
+        let and = Encoding::from_mappings(8, vec![1, 1], 3).unwrap();
+        assert_eq!(encoding_hash(&and), ENCODING_HASH_OF_AND);
+    }
+
+    /// Hash of the 2-input AND encoding modulo 3, which must not change across releases.
+    const ENCODING_HASH_OF_AND: u64 = 0xd3b5_4394_5c60_fbfe;
+
+    #[cfg(feature = "telemetry")]
+    mod spans {
+        use super::*;
+        use crate::boolean::engine::WithThreadLocalEngine;
+        use crate::gadget::boolean::BOOLEAN_PARAMETERS;
+        use crate::gadget::engine::GadgetEngine;
+        use crate::gadget::gen_keys;
+        use std::fmt::Debug;
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        /// Name and fields of a span.
+        type RecordedSpan = (&'static str, Vec<(&'static str, String)>);
+
+        /// Records the spans of target `tfhe::gadget` created while it is the default subscriber.
+        #[derive(Clone, Default)]
+        struct RecordingSubscriber {
+            spans: Arc<Mutex<Vec<RecordedSpan>>>,
+        }
+
+        struct FieldRecorder<'a>(&'a mut Vec<(&'static str, String)>);
+
+        impl Visit for FieldRecorder<'_> {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.0.push((field.name(), format!("{value:?}")));
+            }
+        }
+
+        impl Subscriber for RecordingSubscriber {
+            fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+                metadata.target() == "tfhe::gadget"
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut fields = Vec::new();
+                span.record(&mut FieldRecorder(&mut fields));
+                let mut spans = self.spans.lock().unwrap();
+                spans.push((span.metadata().name(), fields));
+                Id::from_u64(spans.len() as u64)
+            }
+
+            fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+            fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+            fn event(&self, _event: &Event<'_>) {}
+
+            fn enter(&self, _span: &Id) {}
+
+            fn exit(&self, _span: &Id) {}
+        }
+
+        impl RecordingSubscriber {
+            /// Returns the fields of the first recorded span named `name`.
+            fn fields(&self, name: &str) -> Vec<(&'static str, String)> {
+                let spans = self.spans.lock().unwrap();
+                let (_, fields) = spans
+                    .iter()
+                    .find(|(span_name, _)| *span_name == name)
+                    .unwrap_or_else(|| panic!("no {name} span in {spans:?}"));
+                fields.clone()
+            }
+        }
+
+        fn field(name: &'static str, value: impl Debug) -> (&'static str, String) {
+            (name, format!("{value:?}"))
+        }
+
+        #[test]
+        fn evaluation_emits_spans() {
+            let parameters = BOOLEAN_PARAMETERS;
+            let and = Encoding::from_mappings(8, vec![1, 1], 3).unwrap();
+            let subscriber = RecordingSubscriber::default();
+
+            let output = tracing::subscriber::with_default(subscriber.clone(), || {
+                let (client_key, server_key) = gen_keys(&parameters);
+                GadgetEngine::with_thread_local_mut(|engine| {
+                    let inputs = vec![
+                        engine.encrypt(1, &client_key, 3),
+                        engine.encrypt(1, &client_key, 3),
+                    ];
+                    let output = engine.evaluate_gate(&server_key, &and, inputs).unwrap();
+                    engine.decrypt(&output, &client_key)
+                })
+            });
+            assert_eq!(output, 1);
+
+            let dimensions = [
+                field("lwe_dimension", parameters.lwe_dimension.0),
+                field("glwe_dimension", parameters.glwe_dimension.0),
+                field("polynomial_size", parameters.polynomial_size.0),
+            ];
+            let keygens: Vec<_> = subscriber
+                .spans
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(name, _)| *name == "keygen")
+                .map(|(_, fields)| fields.clone())
+                .collect();
+            assert_eq!(keygens.len(), 2);
+            for (fields, key) in keygens.iter().zip(["client", "server"]) {
+                assert_eq!(fields[0], field("key", key));
+                assert_eq!(fields[1..], dimensions);
+            }
+
+            let encrypt = subscriber.fields("encrypt");
+            assert_eq!(encrypt[0], field("plaintext_modulus", 3));
+            assert_eq!(encrypt[1].0, "lwe_dimension");
+            assert_eq!(
+                subscriber.fields("linear_combination")[..2],
+                [
+                    field("encoding", encoding_hash(&and)),
+                    field("pin_count", 2)
+                ]
+            );
+            assert_eq!(
+                subscriber.fields("pbs")[1],
+                field("polynomial_size", parameters.polynomial_size.0)
+            );
+            assert_eq!(subscriber.fields("keyswitch").len(), 2);
+        }
+    }
+}
@@ -0,0 +1,338 @@
+//! Threshold decryption of gadget ciphertexts.
+//!
+//! The LWE secret key `s` of a [`ClientKey`] is additively shared across `n` parties, party `i`
+//! holding a uniformly random share `s_i` with `s = s_0 + ... + s_(n-1)`. Each party decrypts a
+//! ciphertext `(a, b)` partially into `<a, s_i> + e_i`, and the plaintext is recovered from all
+//! `n` shares as `b - sum(<a, s_i> + e_i)`. Fewer than `n` shares reveal nothing about `s`.
+//!
+//! Combining the shares reveals the noise `e` of the ciphertext, which depends on `s`, up to the
+//! flooding noise `e_i`. The flooding noise must therefore be at least as large as the
+//! [nominal noise](crate::gadget::parameters::GadgetParameters::nominal_noise_variance) of the
+//! ciphertexts, which [`ClientKey::share`] enforces. The 32 and 64 bits tori leave no room for a
+//! flooding noise exponentially larger than `e`, so the partial decryptions only blur `e` and do
+//! not hide it statistically: the shares of a ciphertext should only be published when leaking
+//! an approximation of its noise is acceptable.
+//!
+//! The flooding noise adds up over the parties and must stay within the `1 / (2p)` decryption
+//! window of the torus, `1 / (4p)` for an even `p`, which [`ClientKeyShare::partial_decrypt`]
+//! checks for each ciphertext.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Formatter};
+
+use crate::boolean::engine::WithThreadLocalEngine;
+use crate::core_crypto::algorithms::slice_algorithms::slice_wrapping_dot_product;
+use crate::core_crypto::commons::dispersion::DispersionParameter;
+use crate::core_crypto::commons::parameters::CiphertextModulus;
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::constant_time::decode_secret;
+use crate::gadget::engine::{torus_modulus, zeroize, GadgetEngine, GadgetScalar};
+use crate::gadget::error::GadgetError;
+use crate::gadget::parameters::{GadgetParameters, StandardDev, CORRECTNESS_STANDARD_SCORE};
+
+/// The share of party `party` of the LWE secret key of a [`ClientKey`], see
+/// [`ClientKey::share`]. The share is overwritten with zeros when dropped, and redacted from the
+/// [`Debug`] output unless printed through [`ClientKeyShare::debug_secrets`].
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ClientKeyShare<Scalar: GadgetScalar = u32> {
+    pub(crate) party: usize,
+    pub(crate) party_count: usize,
+    pub(crate) lwe_key_share: Vec<Scalar>,
+    pub(crate) flooding_noise: StandardDev,
+    pub(crate) parameters: GadgetParameters,
+}
+
+/// The partial decryption of a ciphertext by party `party`, see
+/// [`ClientKeyShare::partial_decrypt`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DecryptionShare<Scalar: GadgetScalar = u32> {
+    party: usize,
+    party_count: usize,
+    value: Scalar,
+}
+
+impl<Scalar: GadgetScalar> ClientKey<Scalar> {
+    /// Shares the encryption key of the client key across `party_count` parties, each partial
+    /// decryption being flooded with a Gaussian noise of standard deviation `flooding_noise`.
+    ///
+    /// The client key must be discarded once its shares are distributed.
+    ///
+    /// Returns [`GadgetError::InvalidFloodingNoise`] if `party_count` is 0 or if
+    /// `flooding_noise` is below the nominal noise of the ciphertexts, see
+    /// [`threshold`](self).
+    pub fn share(
+        &self,
+        party_count: usize,
+        flooding_noise: StandardDev,
+    ) -> Result<Vec<ClientKeyShare<Scalar>>, GadgetError> {
+        check_flooding_noise(&self.parameters, party_count, flooding_noise)?;
+
+        let (lwe_secret_key, _) = self.encryption_key();
+        let lwe_dimension = lwe_secret_key.lwe_dimension().0;
+        let mut last_share: Vec<Scalar> = lwe_secret_key.as_ref().to_vec();
+        let mut shares: Vec<Vec<Scalar>> = GadgetEngine::with_thread_local_mut(|engine| {
+            (1..party_count)
+                .map(|_| {
+                    let mut share = vec![Scalar::ZERO; lwe_dimension];
+                    engine.fill_with_random_uniform(&mut share);
+                    for (last, share) in last_share.iter_mut().zip(share.iter()) {
+                        *last = last.wrapping_sub(*share);
+                    }
+                    share
+                })
+                .collect()
+        });
+        shares.push(last_share);
+
+        Ok(shares
+            .into_iter()
+            .enumerate()
+            .map(|(party, lwe_key_share)| ClientKeyShare {
+                party,
+                party_count,
+                lwe_key_share,
+                flooding_noise,
+                parameters: self.parameters,
+            })
+            .collect())
+    }
+}
+
+/// Checks that the key is shared across at least one party and that `flooding_noise` is at
+/// least the nominal noise of the ciphertexts under `parameters`.
+pub(crate) fn check_flooding_noise(
+    parameters: &GadgetParameters,
+    party_count: usize,
+    flooding_noise: StandardDev,
+) -> Result<(), GadgetError> {
+    if party_count == 0 {
+        return Err(GadgetError::InvalidFloodingNoise(
+            "the key must be shared across at least one party".to_string(),
+        ));
+    }
+    let nominal_noise = parameters.nominal_noise_variance().sqrt();
+    if flooding_noise.0 < nominal_noise {
+        return Err(GadgetError::InvalidFloodingNoise(format!(
+            "flooding noise {:e} below the nominal noise {nominal_noise:e} of the ciphertexts",
+            flooding_noise.0
+        )));
+    }
+
+    Ok(())
+}
+
+impl<Scalar: GadgetScalar> Debug for ClientKeyShare<Scalar> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientKeyShare")
+            .field("party", &self.party)
+            .field("party_count", &self.party_count)
+            .field("lwe_key_share", &format_args!("<redacted>"))
+            .field("flooding_noise", &self.flooding_noise)
+            .finish()
+    }
+}
+
+impl<Scalar: GadgetScalar> Drop for ClientKeyShare<Scalar> {
+    fn drop(&mut self) {
+        zeroize(&mut self.lwe_key_share);
+    }
+}
+
+impl<Scalar: GadgetScalar> ClientKeyShare<Scalar> {
+    /// Returns a value whose [`Debug`] output includes the key share, see
+    /// [`ClientKey::debug_secrets`].
+    pub fn debug_secrets(&self) -> impl Debug + '_ {
+        struct SecretDebug<'a, Scalar: GadgetScalar>(&'a ClientKeyShare<Scalar>);
+
+        impl<Scalar: GadgetScalar> Debug for SecretDebug<'_, Scalar> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct("ClientKeyShare")
+                    .field("party", &self.0.party)
+                    .field("party_count", &self.0.party_count)
+                    .field("lwe_key_share", &self.0.lwe_key_share)
+                    .field("flooding_noise", &self.0.flooding_noise)
+                    .finish()
+            }
+        }
+
+        SecretDebug(self)
+    }
+
+    pub fn party(&self) -> usize {
+        self.party
+    }
+
+    pub fn party_count(&self) -> usize {
+        self.party_count
+    }
+
+    /// Partially decrypts `ct` with the key share of the party.
+    ///
+    /// Returns [`GadgetError::InvalidFloodingNoise`] if the flooding noise of all the parties
+    /// added to the noise of `ct` overflows the decryption window of its plaintext modulus.
+    pub fn partial_decrypt(
+        &self,
+        ct: &Ciphertext<Scalar>,
+    ) -> Result<DecryptionShare<Scalar>, GadgetError> {
+        let value = match ct {
+            Ciphertext::Encrypted(lwe, plaintext_modulus, noise_level) => {
+                self.check_decryption_budget(*plaintext_modulus, noise_level.0)?;
+                if lwe.lwe_size().to_lwe_dimension().0 != self.lwe_key_share.len() {
+                    return Err(GadgetError::InvalidDecryptionShares(format!(
+                        "ciphertext of LWE dimension {} decrypted with a key share of dimension {}",
+                        lwe.lwe_size().to_lwe_dimension().0,
+                        self.lwe_key_share.len()
+                    )));
+                }
+
+                let noise: Scalar = GadgetEngine::with_thread_local_mut(|engine| {
+                    engine.random_noise(self.flooding_noise)
+                });
+                slice_wrapping_dot_product(lwe.get_mask().as_ref(), &self.lwe_key_share)
+                    .wrapping_add(noise)
+            }
+            // the plaintext of a trivial ciphertext is public
+            Ciphertext::Trivial(_) => Scalar::ZERO,
+            Ciphertext::Placeholder => return Err(GadgetError::PlaceholderCiphertext),
+        };
+
+        Ok(DecryptionShare {
+            party: self.party,
+            party_count: self.party_count,
+            value,
+        })
+    }
+
+    /// Checks that the flooding noise of the parties added to `noise_level` nominal noises stays
+    /// within the decryption window of messages modulo `plaintext_modulus`, with the
+    /// [`CORRECTNESS_STANDARD_SCORE`] of the bootstraps.
+    fn check_decryption_budget(
+        &self,
+        plaintext_modulus: u32,
+        noise_level: usize,
+    ) -> Result<(), GadgetError> {
+        let window = 1. / (2. * torus_modulus(plaintext_modulus) as f64);
+        let variance = self.party_count as f64 * self.flooding_noise.get_variance()
+            + noise_level as f64 * self.parameters.nominal_noise_variance();
+        if CORRECTNESS_STANDARD_SCORE * variance.sqrt() > window {
+            return Err(GadgetError::InvalidFloodingNoise(format!(
+                "flooding noise {:e} of {} parties overflows the decryption window of p = \
+                 {plaintext_modulus}",
+                self.flooding_noise.0, self.party_count
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Recovers the plaintext of `ct` from the decryption shares of every party, as decoded by
+/// [`GadgetEngine::decrypt`].
+pub fn combine_shares<Scalar: GadgetScalar>(
+    ct: &Ciphertext<Scalar>,
+    shares: &[DecryptionShare<Scalar>],
+) -> Result<u32, GadgetError> {
+    let (lwe, plaintext_modulus) = match ct {
+        Ciphertext::Encrypted(lwe, plaintext_modulus, _) => (lwe, *plaintext_modulus),
+        Ciphertext::Trivial(value) => return Ok(*value as u32),
+        Ciphertext::Placeholder => return Err(GadgetError::PlaceholderCiphertext),
+    };
+
+    let party_count = shares.first().map_or(0, |share| share.party_count);
+    let mut seen = vec![false; party_count];
+    for share in shares {
+        if share.party_count != party_count
+            || share.party >= party_count
+            || std::mem::replace(&mut seen[share.party], true)
+        {
+            return Err(GadgetError::InvalidDecryptionShares(format!(
+                "share of party {} out of {} parties does not fit the other shares",
+                share.party, share.party_count
+            )));
+        }
+    }
+    if shares.len() != party_count || party_count == 0 {
+        return Err(GadgetError::InvalidDecryptionShares(format!(
+            "{} shares given, {party_count} parties expected",
+            shares.len()
+        )));
+    }
+
+    let ciphertext_modulus: CiphertextModulus<Scalar> = lwe.ciphertext_modulus();
+    let mut value = *lwe.get_body().data;
+    for share in shares {
+        value = value.wrapping_sub(share.value);
+    }
+    if !ciphertext_modulus.is_native_modulus() {
+        value = value.wrapping_div(ciphertext_modulus.get_power_of_two_scaling_to_native_torus());
+    }
+
+    Ok(decode_secret(value, plaintext_modulus, ciphertext_modulus))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadget::boolean::BOOLEAN_PARAMETERS;
+    use crate::gadget::gen_keys;
+
+    #[test]
+    fn threshold_decryption() -> Result<(), GadgetError> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let shares = client_key.share(3, StandardDev(2f64.powi(-8)))?;
+
+        for (lhs, rhs) in [(false, false), (false, true), (true, false), (true, true)] {
+            let out = server_key.or(&client_key.encrypt(lhs), &client_key.encrypt(rhs))?;
+            let decryption_shares = shares
+                .iter()
+                .map(|share| share.partial_decrypt(&out))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            // the boolean layer encodes false as 1 and true as 2
+            let expected = if lhs || rhs { 2 } else { 1 };
+            assert_eq!(combine_shares(&out, &decryption_shares)?, expected);
+
+            assert!(matches!(
+                combine_shares(&out, &decryption_shares[1..]),
+                Err(GadgetError::InvalidDecryptionShares(_))
+            ));
+            let duplicated = [
+                decryption_shares[0].clone(),
+                decryption_shares[0].clone(),
+                decryption_shares[2].clone(),
+            ];
+            assert!(matches!(
+                combine_shares(&out, &duplicated),
+                Err(GadgetError::InvalidDecryptionShares(_))
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn flooding_noise_is_bounded() -> Result<(), GadgetError> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        // below the nominal noise of 2^-8.3 of the ciphertexts
+        assert!(matches!(
+            client_key.share(3, StandardDev(2f64.powi(-12))),
+            Err(GadgetError::InvalidFloodingNoise(_))
+        ));
+        assert!(matches!(
+            client_key.share(0, StandardDev(2f64.powi(-8))),
+            Err(GadgetError::InvalidFloodingNoise(_))
+        ));
+
+        // 3 flooding noises of 2^-5 overflow the window of 1/6 modulo 3
+        let shares = client_key.share(3, StandardDev(2f64.powi(-5)))?;
+        let out = server_key.or(&client_key.encrypt(true), &client_key.encrypt(false))?;
+        assert!(matches!(
+            shares[0].partial_decrypt(&out),
+            Err(GadgetError::InvalidFloodingNoise(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,330 @@
+//! Transciphering of data encrypted with the Trivium stream cipher.
+//!
+//! A gadget ciphertext of one bit is an LWE ciphertext of hundreds of torus elements, which
+//! makes uploading encrypted data expensive. A client can instead encrypt its data with
+//! [`Trivium`] under a symmetric key, sending one bit per message bit, together with the gadget
+//! encryptions of the 80 bits of the symmetric key, once. The server evaluates Trivium
+//! homomorphically with [`FheTrivium`] to get the encrypted keystream, whose xor with the
+//! symmetric ciphertext gives the gadget ciphertexts of the messages.
+//!
+//! ```rust,ignore
+//! // client
+//! let symmetric_ct = Trivium::new(&key, &iv).apply_keystream(&message);
+//! let encrypted_key: Vec<_> = key.iter().map(|bit| client_key.encrypt(*bit)).collect();
+//!
+//! // server
+//! let mut trivium = FheTrivium::new(&server_key, &encrypted_key, &iv)?;
+//! let message_cts = trivium.transcipher(&symmetric_ct)?;
+//! ```
+//!
+//! A bit of the state is only read 66 rounds after being computed, so the server computes 64
+//! rounds at a time in parallel. The initialization of the cipher costs about 14,000
+//! bootstraps, the keystream 14 bootstraps per bit.
+
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::error::GadgetError;
+use crate::gadget::server_key::ServerKey;
+use rayon::prelude::*;
+use std::collections::VecDeque;
+
+pub const TRIVIUM_KEY_BITS: usize = 80;
+pub const TRIVIUM_IV_BITS: usize = 80;
+
+/// Sizes of the 3 shift registers of the 288 bits state.
+const REGISTER_SIZES: [usize; 3] = [93, 84, 111];
+
+/// Rounds of the initialization, whose keystream is discarded.
+const WARMUP_ROUNDS: usize = 4 * 288;
+
+/// Rounds computed in parallel by [`FheTrivium`], a bit computed in a round is first read
+/// 66 rounds later.
+const BATCH_ROUNDS: usize = 64;
+
+/// Returns the initial registers, holding `key` then `iv` followed by zeros, and three ones at
+/// the end of the last register.
+fn initial_registers<T: Clone>(key: &[T], iv: &[T], zero: T, one: T) -> [Vec<T>; 3] {
+    let mut a = key.to_vec();
+    a.resize(REGISTER_SIZES[0], zero.clone());
+    let mut b = iv.to_vec();
+    b.resize(REGISTER_SIZES[1], zero.clone());
+    let mut c = vec![zero; REGISTER_SIZES[2] - 3];
+    c.resize(REGISTER_SIZES[2], one);
+    [a, b, c]
+}
+
+/// Shifts `register` by the number of `new_bits`, given in the order they were computed.
+fn shift<T>(register: &mut Vec<T>, new_bits: Vec<T>) {
+    let kept = register.len() - new_bits.len();
+    let mut shifted: Vec<T> = new_bits.into_iter().rev().collect();
+    shifted.extend(register.drain(..kept));
+    *register = shifted;
+}
+
+/// Trivium stream cipher, used by clients to encrypt the data transciphered by [`FheTrivium`].
+///
+/// Bit `i` of the key and of the IV is loaded in the state bit `i + 1` and `i + 94` of the
+/// specification.
+#[derive(Clone, Debug)]
+pub struct Trivium {
+    registers: [Vec<bool>; 3],
+}
+
+impl Trivium {
+    pub fn new(key: &[bool; TRIVIUM_KEY_BITS], iv: &[bool; TRIVIUM_IV_BITS]) -> Trivium {
+        Self::with_warmup(key, iv, WARMUP_ROUNDS)
+    }
+
+    pub(crate) fn with_warmup(key: &[bool], iv: &[bool], warmup_rounds: usize) -> Trivium {
+        let mut trivium = Trivium {
+            registers: initial_registers(key, iv, false, true),
+        };
+        for _ in 0..warmup_rounds {
+            trivium.next_bit();
+        }
+        trivium
+    }
+
+    /// Returns the next bit of the keystream.
+    pub fn next_bit(&mut self) -> bool {
+        let [a, b, c] = &self.registers;
+        let t1 = a[65] ^ a[92];
+        let t2 = b[68] ^ b[83];
+        let t3 = c[65] ^ c[110];
+        let new_a = t3 ^ (c[108] & c[109]) ^ a[68];
+        let new_b = t1 ^ (a[90] & a[91]) ^ b[77];
+        let new_c = t2 ^ (b[81] & b[82]) ^ c[86];
+
+        let [a, b, c] = &mut self.registers;
+        shift(a, vec![new_a]);
+        shift(b, vec![new_b]);
+        shift(c, vec![new_c]);
+        t1 ^ t2 ^ t3
+    }
+
+    /// Xors `bits` with the next bits of the keystream, encrypting or decrypting them.
+    pub fn apply_keystream(&mut self, bits: &[bool]) -> Vec<bool> {
+        bits.iter().map(|bit| bit ^ self.next_bit()).collect()
+    }
+}
+
+/// Homomorphic evaluation of [`Trivium`] under an encrypted key, with the boolean gates of a
+/// [`ServerKey`].
+pub struct FheTrivium<'a> {
+    server_key: &'a ServerKey,
+    registers: [Vec<Ciphertext>; 3],
+    /// Keystream bits computed ahead, in order
+    keystream: VecDeque<Ciphertext>,
+}
+
+impl<'a> FheTrivium<'a> {
+    /// Initializes the cipher with the encrypted `key` and the public `iv`.
+    ///
+    /// Fails with [`GadgetError::SymmetricKeyLength`] if `key` does not hold
+    /// [`TRIVIUM_KEY_BITS`] ciphertexts.
+    pub fn new(
+        server_key: &'a ServerKey,
+        key: &[Ciphertext],
+        iv: &[bool; TRIVIUM_IV_BITS],
+    ) -> Result<FheTrivium<'a>, GadgetError> {
+        Self::with_warmup(server_key, key, iv, WARMUP_ROUNDS)
+    }
+
+    pub(crate) fn with_warmup(
+        server_key: &'a ServerKey,
+        key: &[Ciphertext],
+        iv: &[bool],
+        warmup_rounds: usize,
+    ) -> Result<FheTrivium<'a>, GadgetError> {
+        if key.len() != TRIVIUM_KEY_BITS {
+            return Err(GadgetError::SymmetricKeyLength {
+                expected: TRIVIUM_KEY_BITS,
+                actual: key.len(),
+            });
+        }
+
+        let iv: Vec<_> = iv.iter().map(|bit| Ciphertext::Trivial(*bit)).collect();
+        let mut trivium = FheTrivium {
+            server_key,
+            registers: initial_registers(
+                key,
+                &iv,
+                Ciphertext::Trivial(false),
+                Ciphertext::Trivial(true),
+            ),
+            keystream: VecDeque::new(),
+        };
+
+        let mut remaining = warmup_rounds;
+        while remaining > 0 {
+            let rounds = remaining.min(BATCH_ROUNDS);
+            trivium.clock(rounds, false)?;
+            remaining -= rounds;
+        }
+        Ok(trivium)
+    }
+
+    /// Returns the encryption of the next bit of the keystream.
+    pub fn next_bit(&mut self) -> Result<Ciphertext, GadgetError> {
+        if self.keystream.is_empty() {
+            self.fill_keystream(BATCH_ROUNDS)?;
+        }
+        Ok(self.keystream.pop_front().unwrap())
+    }
+
+    /// Returns the encryptions of the messages of the symmetric ciphertext `bits`, i.e. the
+    /// xor of `bits` with the next bits of the encrypted keystream.
+    pub fn transcipher(&mut self, bits: &[bool]) -> Result<Vec<Ciphertext>, GadgetError> {
+        self.fill_keystream(bits.len())?;
+        bits.iter()
+            .map(|bit| {
+                let keystream_bit = self.next_bit()?;
+                if *bit {
+                    self.server_key.not(&keystream_bit)
+                } else {
+                    Ok(keystream_bit)
+                }
+            })
+            .collect()
+    }
+
+    /// Computes keystream bits ahead until at least `count` are available.
+    fn fill_keystream(&mut self, count: usize) -> Result<(), GadgetError> {
+        while self.keystream.len() < count {
+            let rounds = (count - self.keystream.len()).min(BATCH_ROUNDS);
+            let keystream = self.clock(rounds, true)?;
+            self.keystream.extend(keystream);
+        }
+        Ok(())
+    }
+
+    /// Computes `rounds` rounds in parallel, returning their keystream bits if `keystream` is
+    /// set, an empty list otherwise.
+    fn clock(&mut self, rounds: usize, keystream: bool) -> Result<Vec<Ciphertext>, GadgetError> {
+        const A: usize = 0;
+        const B: usize = 1;
+        const C: usize = 2;
+
+        let server_key = self.server_key;
+        let registers = &self.registers;
+        let outputs = (0..rounds)
+            .into_par_iter()
+            .map(|round| {
+                // bits computed by the previous rounds of the batch are not read yet
+                let tap = |register: usize, index: usize| &registers[register][index - round];
+                let (t1, t2, t3) = (
+                    server_key.xor(tap(A, 65), tap(A, 92))?,
+                    server_key.xor(tap(B, 68), tap(B, 83))?,
+                    server_key.xor(tap(C, 65), tap(C, 110))?,
+                );
+                let new_a =
+                    server_key.xor3(&t3, &server_key.and(tap(C, 108), tap(C, 109))?, tap(A, 68))?;
+                let new_b =
+                    server_key.xor3(&t1, &server_key.and(tap(A, 90), tap(A, 91))?, tap(B, 77))?;
+                let new_c =
+                    server_key.xor3(&t2, &server_key.and(tap(B, 81), tap(B, 82))?, tap(C, 86))?;
+                let z = if keystream {
+                    Some(server_key.xor3(&t1, &t2, &t3)?)
+                } else {
+                    None
+                };
+                Ok((z, new_a, new_b, new_c))
+            })
+            .collect::<Result<Vec<_>, GadgetError>>()?;
+
+        let mut keystream_bits = Vec::with_capacity(rounds);
+        let mut new_bits: [Vec<Ciphertext>; 3] = Default::default();
+        for (z, new_a, new_b, new_c) in outputs {
+            keystream_bits.extend(z);
+            new_bits[0].push(new_a);
+            new_bits[1].push(new_b);
+            new_bits[2].push(new_c);
+        }
+        for (register, new_bits) in self.registers.iter_mut().zip(new_bits) {
+            shift(register, new_bits);
+        }
+        Ok(keystream_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadget::boolean::BOOLEAN_PARAMETERS;
+    use crate::gadget::gen_keys;
+    use rand::Rng;
+
+    #[test]
+    fn trivium_round_trip() {
+        let mut rng = rand::thread_rng();
+        let key: [bool; TRIVIUM_KEY_BITS] = std::array::from_fn(|_| rng.gen());
+        let iv: [bool; TRIVIUM_IV_BITS] = std::array::from_fn(|_| rng.gen());
+        let message: Vec<bool> = (0..256).map(|_| rng.gen()).collect();
+
+        let encrypted = Trivium::new(&key, &iv).apply_keystream(&message);
+        assert_ne!(encrypted, message);
+        assert_eq!(Trivium::new(&key, &iv).apply_keystream(&encrypted), message);
+
+        // the keystream depends on the iv
+        let mut other_iv = iv;
+        other_iv[0] = !other_iv[0];
+        assert_ne!(
+            Trivium::new(&key, &other_iv).apply_keystream(&message),
+            encrypted
+        );
+    }
+
+    #[test]
+    fn trivium_known_answer() {
+        // eSTREAM test vector of the null key and IV, the keystream bits being packed in bytes
+        // least significant bit first
+        let stream_0_63 = "FBE0BF265859051B517A2E4E239FC97F563203161907CF2DE7A8790FA1B2E9CD\
+                           F75292030268B7382B4C1A759AA2599A285549986E74805903801A4CB5A5D4F2";
+        let stream_192_255 = "0F1BE95091B8EA857B062AD52BADF47784AC6D9B2E3F85A9D79995043302F0FD\
+                              F8B76E5BC8B7B4F0AA46CD20DDA04FDD197BC5E1635496828F2DBFB23F6BD5D0";
+
+        let mut trivium = Trivium::new(&[false; TRIVIUM_KEY_BITS], &[false; TRIVIUM_IV_BITS]);
+        let mut next_bytes = |count: usize| -> String {
+            (0..count)
+                .map(|_| {
+                    let byte = (0..8).fold(0u8, |byte, i| byte | (trivium.next_bit() as u8) << i);
+                    format!("{byte:02X}")
+                })
+                .collect()
+        };
+        assert_eq!(next_bytes(64), stream_0_63);
+        next_bytes(128);
+        assert_eq!(next_bytes(64), stream_192_255);
+    }
+
+    #[test]
+    fn transcipher_matches_trivium() -> Result<(), GadgetError> {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+        let mut rng = rand::thread_rng();
+        let key: Vec<bool> = (0..TRIVIUM_KEY_BITS).map(|_| rng.gen()).collect();
+        let iv: Vec<bool> = (0..TRIVIUM_IV_BITS).map(|_| rng.gen()).collect();
+        let message: Vec<bool> = (0..8).map(|_| rng.gen()).collect();
+
+        // a shortened initialization keeps the test fast, batches still span several rounds
+        let warmup_rounds = 100;
+        let symmetric_ct = Trivium::with_warmup(&key, &iv, warmup_rounds).apply_keystream(&message);
+
+        let encrypted_key: Vec<_> = key.iter().map(|bit| client_key.encrypt(*bit)).collect();
+        let mut trivium = FheTrivium::with_warmup(&server_key, &encrypted_key, &iv, warmup_rounds)?;
+        let message_cts = trivium.transcipher(&symmetric_ct)?;
+        let decrypted: Vec<bool> = message_cts
+            .iter()
+            .map(|ct| client_key.decrypt(ct))
+            .collect();
+        assert_eq!(decrypted, message);
+
+        assert_eq!(
+            FheTrivium::new(&server_key, &encrypted_key[1..], &[false; TRIVIUM_IV_BITS]).err(),
+            Some(GadgetError::SymmetricKeyLength {
+                expected: TRIVIUM_KEY_BITS,
+                actual: TRIVIUM_KEY_BITS - 1,
+            })
+        );
+
+        Ok(())
+    }
+}
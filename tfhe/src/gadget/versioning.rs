@@ -0,0 +1,412 @@
+//! Versioned serialization of the gadget entities.
+//!
+//! The serialized form of an [`Encoding`], a [`Ciphertext`], a [`ClientKey`] or a [`ServerKey`]
+//! follows the definition of its type: data stored with one version of the crate fails to
+//! deserialize once a field is added or changed. For long-term storage, serialize the dispatch
+//! enum returned by [`Versionize::versionize`] instead. It tags the data with the version of its
+//! type and [`Versionize::unversionize`] upgrades older versions to the current one.
+//!
+//! ```rust
+//! use tfhe::gadget::encoding::Encoding;
+//! use tfhe::gadget::versioning::{EncodingVersions, Versionize};
+//!
+//! let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+//! let stored = bincode::serialize(&encoding.clone().versionize()).unwrap();
+//!
+//! let versioned: EncodingVersions = bincode::deserialize(&stored).unwrap();
+//! assert_eq!(Encoding::unversionize(versioned), encoding);
+//! ```
+//!
+//! When the definition of one of these types changes, its previous definition is kept as e.g.
+//! `EncodingV0` in the variant of the old version, the new definition gets the next variant and
+//! `unversionize` converts the old one. Variants are identified by their index in binary formats
+//! and must never be reordered or removed.
+
+use crate::core_crypto::commons::dispersion::StandardDev;
+use crate::core_crypto::commons::parameters::{
+    DecompositionBaseLog, DecompositionLevelCount, EncryptionKeyChoice, GlweDimension,
+    LweBskGroupingFactor, LweDimension, PolynomialSize,
+};
+use crate::core_crypto::entities::{
+    GlweSecretKeyOwned, LweKeyswitchKeyOwned, LwePackingKeyswitchKeyOwned, LweSecretKeyOwned,
+};
+use crate::gadget::ciphertext::Ciphertext;
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::encoding::Encoding;
+use crate::gadget::engine::GadgetScalar;
+use crate::gadget::parameters::{CiphertextModulus, GadgetParameters, PbsBackend};
+use crate::gadget::server_key::{GadgetBootstrappingKey, ServerKey};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// A type whose serialized form is tagged with its version, see the [module](self) documentation.
+pub trait Versionize: Sized {
+    /// The dispatch enum holding every version of the type.
+    type Versioned: Serialize + DeserializeOwned;
+
+    /// Tags `self` with the current version of its type.
+    fn versionize(self) -> Self::Versioned;
+
+    /// Upgrades `versioned` to the current version of the type.
+    fn unversionize(versioned: Self::Versioned) -> Self;
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum EncodingVersions {
+    V0(Encoding),
+}
+
+impl Versionize for Encoding {
+    type Versioned = EncodingVersions;
+
+    fn versionize(self) -> EncodingVersions {
+        EncodingVersions::V0(self)
+    }
+
+    fn unversionize(versioned: EncodingVersions) -> Self {
+        match versioned {
+            EncodingVersions::V0(encoding) => encoding,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum CiphertextVersions<Scalar: GadgetScalar = u32> {
+    V0(Ciphertext<Scalar>),
+}
+
+impl<Scalar: GadgetScalar + DeserializeOwned> Versionize for Ciphertext<Scalar> {
+    type Versioned = CiphertextVersions<Scalar>;
+
+    fn versionize(self) -> CiphertextVersions<Scalar> {
+        CiphertextVersions::V0(self)
+    }
+
+    fn unversionize(versioned: CiphertextVersions<Scalar>) -> Self {
+        match versioned {
+            CiphertextVersions::V0(ct) => ct,
+        }
+    }
+}
+
+/// [`GadgetParameters`] as defined before the encryption key choice was added, ciphertexts
+/// being encrypted under the small LWE key.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GadgetParametersV0 {
+    pub lwe_dimension: LweDimension,
+    pub glwe_dimension: GlweDimension,
+    pub polynomial_size: PolynomialSize,
+    pub lwe_modular_std_dev: StandardDev,
+    pub glwe_modular_std_dev: StandardDev,
+    pub pbs_base_log: DecompositionBaseLog,
+    pub pbs_level: DecompositionLevelCount,
+    pub ks_base_log: DecompositionBaseLog,
+    pub ks_level: DecompositionLevelCount,
+    pub ciphertext_modulus: CiphertextModulus,
+    pub grouping_factor: Option<LweBskGroupingFactor>,
+}
+
+impl From<GadgetParametersV0> for GadgetParameters {
+    fn from(parameters: GadgetParametersV0) -> Self {
+        Self {
+            lwe_dimension: parameters.lwe_dimension,
+            glwe_dimension: parameters.glwe_dimension,
+            polynomial_size: parameters.polynomial_size,
+            lwe_modular_std_dev: parameters.lwe_modular_std_dev,
+            glwe_modular_std_dev: parameters.glwe_modular_std_dev,
+            pbs_base_log: parameters.pbs_base_log,
+            pbs_level: parameters.pbs_level,
+            ks_base_log: parameters.ks_base_log,
+            ks_level: parameters.ks_level,
+            ciphertext_modulus: parameters.ciphertext_modulus,
+            grouping_factor: parameters.grouping_factor,
+            encryption_key_choice: EncryptionKeyChoice::Small,
+            pbs_backend: PbsBackend::Fft,
+        }
+    }
+}
+
+/// [`GadgetParameters`] as defined before the blind rotation backend was added, bootstrapping
+/// with the FFT.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GadgetParametersV1 {
+    pub lwe_dimension: LweDimension,
+    pub glwe_dimension: GlweDimension,
+    pub polynomial_size: PolynomialSize,
+    pub lwe_modular_std_dev: StandardDev,
+    pub glwe_modular_std_dev: StandardDev,
+    pub pbs_base_log: DecompositionBaseLog,
+    pub pbs_level: DecompositionLevelCount,
+    pub ks_base_log: DecompositionBaseLog,
+    pub ks_level: DecompositionLevelCount,
+    pub ciphertext_modulus: CiphertextModulus,
+    pub grouping_factor: Option<LweBskGroupingFactor>,
+    pub encryption_key_choice: EncryptionKeyChoice,
+}
+
+impl From<GadgetParametersV1> for GadgetParameters {
+    fn from(parameters: GadgetParametersV1) -> Self {
+        Self {
+            lwe_dimension: parameters.lwe_dimension,
+            glwe_dimension: parameters.glwe_dimension,
+            polynomial_size: parameters.polynomial_size,
+            lwe_modular_std_dev: parameters.lwe_modular_std_dev,
+            glwe_modular_std_dev: parameters.glwe_modular_std_dev,
+            pbs_base_log: parameters.pbs_base_log,
+            pbs_level: parameters.pbs_level,
+            ks_base_log: parameters.ks_base_log,
+            ks_level: parameters.ks_level,
+            ciphertext_modulus: parameters.ciphertext_modulus,
+            grouping_factor: parameters.grouping_factor,
+            encryption_key_choice: parameters.encryption_key_choice,
+            pbs_backend: PbsBackend::Fft,
+        }
+    }
+}
+
+/// A [`ClientKey`] as defined before the encryption key choice was added to its parameters.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ClientKeyV0<Scalar: GadgetScalar = u32> {
+    pub(crate) lwe_secret_key: LweSecretKeyOwned<Scalar>,
+    pub(crate) glwe_secret_key: GlweSecretKeyOwned<Scalar>,
+    pub(crate) parameters: GadgetParametersV0,
+}
+
+/// A [`ClientKey`] as defined before the blind rotation backend was added to its parameters.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ClientKeyV1<Scalar: GadgetScalar = u32> {
+    pub(crate) lwe_secret_key: LweSecretKeyOwned<Scalar>,
+    pub(crate) glwe_secret_key: GlweSecretKeyOwned<Scalar>,
+    pub(crate) parameters: GadgetParametersV1,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum ClientKeyVersions<Scalar: GadgetScalar = u32> {
+    V0(ClientKeyV0<Scalar>),
+    V1(ClientKeyV1<Scalar>),
+    V2(ClientKey<Scalar>),
+}
+
+impl<Scalar: GadgetScalar + DeserializeOwned> Versionize for ClientKey<Scalar> {
+    type Versioned = ClientKeyVersions<Scalar>;
+
+    fn versionize(self) -> ClientKeyVersions<Scalar> {
+        ClientKeyVersions::V2(self)
+    }
+
+    fn unversionize(versioned: ClientKeyVersions<Scalar>) -> Self {
+        match versioned {
+            ClientKeyVersions::V0(client_key) => Self {
+                lwe_secret_key: client_key.lwe_secret_key,
+                glwe_secret_key: client_key.glwe_secret_key,
+                parameters: client_key.parameters.into(),
+            },
+            ClientKeyVersions::V1(client_key) => Self {
+                lwe_secret_key: client_key.lwe_secret_key,
+                glwe_secret_key: client_key.glwe_secret_key,
+                parameters: client_key.parameters.into(),
+            },
+            ClientKeyVersions::V2(client_key) => client_key,
+        }
+    }
+}
+
+/// A [`ServerKey`] as defined before the packing keyswitching key was added.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ServerKeyV0<Scalar: GadgetScalar = u32> {
+    pub(crate) bootstrapping_key: GadgetBootstrappingKey,
+    pub(crate) key_switching_key: LweKeyswitchKeyOwned<Scalar>,
+    pub(crate) parameters: GadgetParametersV0,
+}
+
+/// A [`ServerKey`] as defined before the encryption key choice was added to its parameters.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ServerKeyV1<Scalar: GadgetScalar = u32> {
+    pub(crate) bootstrapping_key: GadgetBootstrappingKey,
+    pub(crate) key_switching_key: LweKeyswitchKeyOwned<Scalar>,
+    pub(crate) parameters: GadgetParametersV0,
+    pub(crate) packing_key: Option<LwePackingKeyswitchKeyOwned<Scalar>>,
+}
+
+/// A [`ServerKey`] as defined before the blind rotation backend was added to its parameters.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ServerKeyV2<Scalar: GadgetScalar = u32> {
+    pub(crate) bootstrapping_key: GadgetBootstrappingKey,
+    pub(crate) key_switching_key: LweKeyswitchKeyOwned<Scalar>,
+    pub(crate) parameters: GadgetParametersV1,
+    pub(crate) packing_key: Option<LwePackingKeyswitchKeyOwned<Scalar>>,
+}
+
+/// The lookup table cache and the statistics of the key are not part of any version, as for the
+/// unversioned [`ServerKey`].
+#[derive(Clone, Serialize, Deserialize)]
+pub enum ServerKeyVersions<Scalar: GadgetScalar = u32> {
+    V0(ServerKeyV0<Scalar>),
+    V1(ServerKeyV1<Scalar>),
+    V2(ServerKeyV2<Scalar>),
+    V3(ServerKey<Scalar>),
+}
+
+impl<Scalar: GadgetScalar + DeserializeOwned> Versionize for ServerKey<Scalar> {
+    type Versioned = ServerKeyVersions<Scalar>;
+
+    fn versionize(self) -> ServerKeyVersions<Scalar> {
+        ServerKeyVersions::V3(self)
+    }
+
+    fn unversionize(versioned: ServerKeyVersions<Scalar>) -> Self {
+        match versioned {
+            ServerKeyVersions::V0(server_key) => Self {
+                bootstrapping_key: server_key.bootstrapping_key,
+                key_switching_key: server_key.key_switching_key,
+                parameters: server_key.parameters.into(),
+                packing_key: None,
+                lookup_tables: Default::default(),
+                stats: Default::default(),
+            },
+            ServerKeyVersions::V1(server_key) => Self {
+                bootstrapping_key: server_key.bootstrapping_key,
+                key_switching_key: server_key.key_switching_key,
+                parameters: server_key.parameters.into(),
+                packing_key: server_key.packing_key,
+                lookup_tables: Default::default(),
+                stats: Default::default(),
+            },
+            ServerKeyVersions::V2(server_key) => Self {
+                bootstrapping_key: server_key.bootstrapping_key,
+                key_switching_key: server_key.key_switching_key,
+                parameters: server_key.parameters.into(),
+                packing_key: server_key.packing_key,
+                lookup_tables: Default::default(),
+                stats: Default::default(),
+            },
+            ServerKeyVersions::V3(server_key) => server_key,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolean::engine::WithThreadLocalEngine;
+    use crate::gadget::engine::GadgetEngine;
+    use crate::gadget::gen_keys;
+    use crate::gadget::parameters::PLAINTEXT_2_BITS_PARAMETERS;
+
+    #[test]
+    fn versioned_round_trip() {
+        let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+        let serialized = bincode::serialize(&encoding.clone().versionize()).unwrap();
+        // the version is the first field of the data, V0 being encoded as the variant index 0
+        assert_eq!(serialized[..4], [0, 0, 0, 0]);
+        assert_eq!(serialized[4..], bincode::serialize(&encoding).unwrap());
+        let versioned: EncodingVersions = bincode::deserialize(&serialized).unwrap();
+        assert_eq!(Encoding::unversionize(versioned), encoding);
+
+        let json = serde_json::to_string(&encoding.clone().versionize()).unwrap();
+        assert!(json.starts_with(r#"{"V0":"#));
+        let versioned: EncodingVersions = serde_json::from_str(&json).unwrap();
+        assert_eq!(Encoding::unversionize(versioned), encoding);
+
+        let (client_key, server_key) = gen_keys(&PLAINTEXT_2_BITS_PARAMETERS);
+        let serialized = bincode::serialize(&client_key.clone().versionize()).unwrap();
+        let versioned: ClientKeyVersions = bincode::deserialize(&serialized).unwrap();
+        let client_key_back = ClientKey::unversionize(versioned);
+        assert_eq!(client_key_back, client_key);
+
+        // keys stored before the encryption key choice was added encrypt under the small key
+        let parameters_v0 = GadgetParametersV0 {
+            lwe_dimension: client_key.parameters.lwe_dimension,
+            glwe_dimension: client_key.parameters.glwe_dimension,
+            polynomial_size: client_key.parameters.polynomial_size,
+            lwe_modular_std_dev: client_key.parameters.lwe_modular_std_dev,
+            glwe_modular_std_dev: client_key.parameters.glwe_modular_std_dev,
+            pbs_base_log: client_key.parameters.pbs_base_log,
+            pbs_level: client_key.parameters.pbs_level,
+            ks_base_log: client_key.parameters.ks_base_log,
+            ks_level: client_key.parameters.ks_level,
+            ciphertext_modulus: client_key.parameters.ciphertext_modulus,
+            grouping_factor: client_key.parameters.grouping_factor,
+        };
+        let client_key_v0 = ClientKeyVersions::V0(ClientKeyV0 {
+            lwe_secret_key: client_key.lwe_secret_key.clone(),
+            glwe_secret_key: client_key.glwe_secret_key.clone(),
+            parameters: parameters_v0,
+        });
+        let serialized = bincode::serialize(&client_key_v0).unwrap();
+        let versioned: ClientKeyVersions = bincode::deserialize(&serialized).unwrap();
+        assert_eq!(ClientKey::unversionize(versioned), client_key);
+
+        // keys stored before the packing key was added still load, without a packing key
+        let server_key_v0 = ServerKeyVersions::V0(ServerKeyV0 {
+            bootstrapping_key: server_key.bootstrapping_key.clone(),
+            key_switching_key: server_key.key_switching_key.clone(),
+            parameters: parameters_v0,
+        });
+        let serialized = bincode::serialize(&server_key_v0).unwrap();
+        let versioned: ServerKeyVersions = bincode::deserialize(&serialized).unwrap();
+        let server_key_back = ServerKey::unversionize(versioned);
+        assert!(server_key_back.packing_key.is_none());
+        assert_eq!(server_key_back.parameters, server_key.parameters);
+
+        // keys stored before the blind rotation backend was added bootstrap with the FFT
+        let parameters_v1 = GadgetParametersV1 {
+            lwe_dimension: client_key.parameters.lwe_dimension,
+            glwe_dimension: client_key.parameters.glwe_dimension,
+            polynomial_size: client_key.parameters.polynomial_size,
+            lwe_modular_std_dev: client_key.parameters.lwe_modular_std_dev,
+            glwe_modular_std_dev: client_key.parameters.glwe_modular_std_dev,
+            pbs_base_log: client_key.parameters.pbs_base_log,
+            pbs_level: client_key.parameters.pbs_level,
+            ks_base_log: client_key.parameters.ks_base_log,
+            ks_level: client_key.parameters.ks_level,
+            ciphertext_modulus: client_key.parameters.ciphertext_modulus,
+            grouping_factor: client_key.parameters.grouping_factor,
+            encryption_key_choice: client_key.parameters.encryption_key_choice,
+        };
+        let client_key_v1 = ClientKeyVersions::V1(ClientKeyV1 {
+            lwe_secret_key: client_key.lwe_secret_key.clone(),
+            glwe_secret_key: client_key.glwe_secret_key.clone(),
+            parameters: parameters_v1,
+        });
+        let serialized = bincode::serialize(&client_key_v1).unwrap();
+        let versioned: ClientKeyVersions = bincode::deserialize(&serialized).unwrap();
+        assert_eq!(ClientKey::unversionize(versioned), client_key);
+
+        let server_key_v2 = ServerKeyVersions::V2(ServerKeyV2 {
+            bootstrapping_key: server_key.bootstrapping_key.clone(),
+            key_switching_key: server_key.key_switching_key.clone(),
+            parameters: parameters_v1,
+            packing_key: None,
+        });
+        let serialized = bincode::serialize(&server_key_v2).unwrap();
+        let versioned: ServerKeyVersions = bincode::deserialize(&serialized).unwrap();
+        let server_key_back = ServerKey::unversionize(versioned);
+        assert_eq!(server_key_back.parameters.pbs_backend, PbsBackend::Fft);
+        assert_eq!(server_key_back.parameters, server_key.parameters);
+
+        let serialized = bincode::serialize(&server_key.versionize()).unwrap();
+        assert_eq!(serialized[..4], [3, 0, 0, 0]);
+        let versioned: ServerKeyVersions = bincode::deserialize(&serialized).unwrap();
+        let server_key = ServerKey::unversionize(versioned);
+
+        let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+            vec![
+                engine.encrypt(1, &client_key, 3),
+                engine.encrypt(1, &client_key, 3),
+            ]
+        });
+        let inputs = inputs
+            .into_iter()
+            .map(|ct| {
+                let serialized = bincode::serialize(&ct.versionize()).unwrap();
+                let versioned: CiphertextVersions = bincode::deserialize(&serialized).unwrap();
+                Ciphertext::unversionize(versioned)
+            })
+            .collect();
+        let out = server_key.evaluate_gate(inputs, &encoding).unwrap();
+        let decrypted =
+            GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&out, &client_key));
+        assert_eq!(decrypted, 1);
+    }
+}
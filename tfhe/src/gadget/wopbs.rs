@@ -0,0 +1,737 @@
+//! Evaluation of gates without padding bit (WoP-PBS) for plaintext moduli too large for the
+//! lookup tables.
+//!
+//! A bootstrap looks the linear combination of the inputs of a gate up in a negacyclic lookup
+//! table of `N` coefficients, which holds at most `N / 2` messages, see
+//! [`GadgetParameters::max_supported_p`](super::parameters::GadgetParameters::max_supported_p).
+//! [`ServerKey::evaluate_gate_wopbs`] instead extracts the bits of the linear combination one
+//! bootstrap at a time, circuit bootstraps each bit to a GGSW ciphertext and selects the output of
+//! the gate in a lookup table of `p` entries with a vertical packing. The plaintext modulus is then
+//! only bounded by the noise of the linear combination, whose bits are extracted up to the
+//! `log2(p)`-th one.
+//!
+//! The evaluation costs `log2(p) - 1` bootstraps for the bit extraction and `log2(p) * cbs_level`
+//! for the circuit bootstraps, and needs the circuit bootstrapping keys of a [`WopbsKey`] on top
+//! of the [`ServerKey`]. Plaintext moduli must be powers of two, whose messages keep their padding
+//! bit, and the bootstrapping key must be a classic one.
+//!
+//! The keys of [`WOPBS_5_BITS_PARAMETERS`] and [`WOPBS_5_BITS_CBS_PARAMETERS`] evaluate gates
+//! modulo up to 32 over a 64 bits torus. Secure parameters leave no room for plaintext moduli
+//! beyond the lookup tables, whose size is bounded by the polynomial size rather than by the
+//! noise: the bit extraction only saves the modulus switching noise of the bootstraps.
+//!
+//! [`WOPBS_5_BITS_PARAMETERS`]: super::parameters::WOPBS_5_BITS_PARAMETERS
+//! [`WOPBS_5_BITS_CBS_PARAMETERS`]: super::parameters::WOPBS_5_BITS_CBS_PARAMETERS
+//!
+//! The same circuit bootstrapping evaluates gates of up to [`MAX_WIDE_GATE_PINS`] pins with
+//! [`ServerKey::evaluate_wide_gate`], looking the truth table up with the bits of the inputs
+//! rather than with their linear combination.
+
+use crate::boolean::engine::WithThreadLocalEngine;
+use crate::core_crypto::fft_impl::fft64::math::fft::FftView;
+use crate::core_crypto::prelude::{
+    circuit_bootstrap_boolean_vertical_packing_lwe_ciphertext_list_mem_optimized,
+    circuit_bootstrap_boolean_vertical_packing_lwe_ciphertext_list_mem_optimized_requirement,
+    keyswitch_lwe_ciphertext, lwe_ciphertext_sub_assign,
+    programmable_bootstrap_lwe_ciphertext_mem_optimized,
+    programmable_bootstrap_lwe_ciphertext_mem_optimized_requirement, ComputationBuffers,
+    ContiguousEntityContainer, ContiguousEntityContainerMut, EncryptionKeyChoice, Fft,
+    FourierLweBootstrapKeyOwned, GlweCiphertextOwned, LweCiphertextCount, LweCiphertextListOwned,
+    LweCiphertextOwned, LwePrivateFunctionalPackingKeyswitchKeyListOwned, PolynomialList,
+};
+use crate::gadget::ciphertext::{trivial_lwe_message, Ciphertext, NoiseLevel};
+use crate::gadget::client_key::ClientKey;
+use crate::gadget::encoding::Encoding;
+use crate::gadget::engine::{
+    check_bootstrappable, check_encoding, encode_coefficient, encode_slot, torus_modulus,
+    GadgetEngine, GadgetScalar,
+};
+use crate::gadget::error::GadgetError;
+use crate::gadget::parameters::GadgetWopbsParameters;
+use crate::gadget::server_key::{GadgetBootstrappingKey, ServerKey};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Maximum number of pins of a gate evaluated by [`ServerKey::evaluate_wide_gate`], whose lookup
+/// table holds one scalar per row.
+pub const MAX_WIDE_GATE_PINS: usize = 16;
+
+/// The circuit bootstrapping keys of a client key, see the [module](self) documentation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WopbsKey<Scalar: GadgetScalar = u32> {
+    pub(crate) cbs_pfpksk: LwePrivateFunctionalPackingKeyswitchKeyListOwned<Scalar>,
+    pub(crate) parameters: GadgetWopbsParameters,
+}
+
+impl<Scalar: GadgetScalar> WopbsKey<Scalar> {
+    /// Generates the circuit bootstrapping keys of `client_key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ciphertext modulus of `client_key` is not the native one.
+    pub fn new(client_key: &ClientKey<Scalar>, parameters: GadgetWopbsParameters) -> Self {
+        assert!(
+            client_key.ciphertext_modulus().is_native_modulus(),
+            "the circuit bootstrapping needs the native ciphertext modulus"
+        );
+
+        let cbs_pfpksk = GadgetEngine::with_thread_local_mut(|engine| {
+            engine.create_circuit_bootstrap_pfpksk_list(client_key, &parameters)
+        });
+
+        Self {
+            cbs_pfpksk,
+            parameters,
+        }
+    }
+
+    pub fn parameters(&self) -> GadgetWopbsParameters {
+        self.parameters
+    }
+}
+
+impl<Scalar: GadgetScalar> ServerKey<Scalar> {
+    /// Evaluates the gate described by `encoding` without a lookup table of `p` messages, see
+    /// the [module](self) documentation.
+    ///
+    /// The output is encrypted modulo `encoding.new_p` under the same key as the inputs, like
+    /// the output of [`ServerKey::evaluate_gate`].
+    ///
+    /// Fails with [`GadgetError::UnsupportedPlaintextModulus`] if `encoding.p` is not a power of
+    /// two, with [`GadgetError::UnsupportedMultiBitKey`] if the bootstrapping key is a multi-bit
+    /// one, with [`GadgetError::UnsupportedNttBackend`] if it is an NTT one and with
+    /// [`GadgetError::UnsupportedBigEncryptionKey`] under [`EncryptionKeyChoice::Big`].
+    pub fn evaluate_gate_wopbs(
+        &self,
+        wopbs_key: &WopbsKey<Scalar>,
+        input_ciphertexts: Vec<Ciphertext<Scalar>>,
+        encoding: &Encoding,
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        check_encoding(encoding)?;
+        check_bootstrappable(encoding)?;
+        if !encoding.p.is_power_of_two() || encoding.p < 2 {
+            return Err(GadgetError::UnsupportedPlaintextModulus(encoding.p));
+        }
+        let ciphertext_modulus = self.ciphertext_modulus();
+        if !ciphertext_modulus.is_native_modulus() {
+            return Err(GadgetError::UnsupportedCiphertextModulus(
+                ciphertext_modulus.get_custom_modulus(),
+            ));
+        }
+        let fourier_bsk = match &self.bootstrapping_key {
+            GadgetBootstrappingKey::Classic(fourier_bsk) => fourier_bsk,
+            GadgetBootstrappingKey::MultiBit { .. } => {
+                return Err(GadgetError::UnsupportedMultiBitKey)
+            }
+            GadgetBootstrappingKey::Ntt(_) => {
+                return Err(GadgetError::UnsupportedNttBackend(
+                    "the circuit bootstrapping".to_string(),
+                ))
+            }
+        };
+        if self.parameters.encryption_key_choice == EncryptionKeyChoice::Big {
+            return Err(GadgetError::UnsupportedBigEncryptionKey);
+        }
+
+        let (mut sum_ct, _) = GadgetEngine::linear_combination(self, encoding, &input_ciphertexts)?;
+
+        let output = |message: u32| {
+            if encoding.output_encodings_0.contains(&message) {
+                encoding.new_0
+            } else {
+                encoding.new_1
+            }
+        };
+
+        // a trivial linear combination is evaluated in the clear and stays trivial
+        if let Some(message) = trivial_lwe_message(&sum_ct, encoding.p) {
+            *sum_ct.get_mut_body().data =
+                encode_coefficient(output(message), encoding.new_p, ciphertext_modulus);
+            return Ok(Ciphertext::Encrypted(
+                sum_ct,
+                encoding.new_p,
+                NoiseLevel::ZERO,
+            ));
+        }
+
+        let start = Instant::now();
+        let fft = Fft::new(fourier_bsk.polynomial_size());
+        let fft = fft.as_view();
+        let mut computation_buffers = ComputationBuffers::default();
+
+        let bits = self.extract_bits(
+            &sum_ct,
+            encoding.p,
+            fourier_bsk,
+            fft,
+            &mut computation_buffers,
+        );
+
+        // entry s of the lookup table is the output of the gate for the sum s
+        let lut = (0..encoding.p)
+            .map(|message| encode_coefficient(output(message), encoding.new_p, ciphertext_modulus))
+            .collect();
+        let output_ct = self.circuit_bootstrap_vertical_packing(
+            wopbs_key,
+            &bits,
+            lut,
+            fourier_bsk,
+            fft,
+            &mut computation_buffers,
+        );
+        self.stats
+            .record_bootstrap(std::slice::from_ref(encoding), start.elapsed());
+
+        Ok(Ciphertext::Encrypted(
+            output_ct,
+            encoding.new_p,
+            NoiseLevel::NOMINAL,
+        ))
+    }
+
+    /// Evaluates the gate of truth table `tt_value` on the bits encrypted by `inputs`, without
+    /// a linear combination of the inputs, for gates too wide for an [`Encoding`].
+    ///
+    /// Row `r` of the truth table, for the input pins set to the bits of `r`, pin 0 being the
+    /// least significant one, is bit `r % 128` of `tt_value[r / 128]`. Each encrypted input is
+    /// circuit bootstrapped to a GGSW ciphertext and the output is selected by a tree of CMUXes
+    /// followed by a blind rotation over the `2^k` rows of the `k` encrypted pins, the trivial
+    /// pins being fixed in the truth table beforehand. Unlike [`ServerKey::evaluate_gate`], the
+    /// number of pins is not bounded by the messages a plaintext modulus can hold, at the cost of
+    /// `1 + cbs_level` bootstraps per encrypted pin.
+    ///
+    /// The encrypted inputs must encrypt 0 or 1 modulo the same plaintext modulus `p`, which
+    /// the output is encrypted modulo under the same key as the inputs. Fails with
+    /// [`GadgetError::InvalidEncoding`] if `inputs` does not hold 1 to
+    /// [`MAX_WIDE_GATE_PINS`] pins or `tt_value` does not hold their rows, and with
+    /// [`GadgetError::UnsupportedMultiBitKey`] if the bootstrapping key is a multi-bit one,
+    /// [`GadgetError::UnsupportedNttBackend`] if it is an NTT one or
+    /// [`GadgetError::UnsupportedBigEncryptionKey`] under [`EncryptionKeyChoice::Big`].
+    pub fn evaluate_wide_gate(
+        &self,
+        wopbs_key: &WopbsKey<Scalar>,
+        inputs: &[Ciphertext<Scalar>],
+        tt_value: &[u128],
+    ) -> Result<Ciphertext<Scalar>, GadgetError> {
+        let pin_count = inputs.len();
+        if pin_count == 0 || pin_count > MAX_WIDE_GATE_PINS {
+            return Err(GadgetError::InvalidEncoding(format!(
+                "pin count {pin_count} is not in [1, {MAX_WIDE_GATE_PINS}]"
+            )));
+        }
+        let row_count = 1usize << pin_count;
+        if tt_value.len() != (row_count + 127) / 128
+            || (row_count < 128 && tt_value[0] >> row_count != 0)
+        {
+            return Err(GadgetError::InvalidEncoding(format!(
+                "truth table of {} words does not hold {row_count} rows",
+                tt_value.len()
+            )));
+        }
+
+        // the trivial pins are folded in the row, the encrypted ones are stored by pin
+        let mut trivial_row = 0;
+        let mut encrypted_pins = vec![];
+        let mut plaintext_modulus = None;
+        for (pin, input) in inputs.iter().enumerate() {
+            match input {
+                Ciphertext::Encrypted(ct, p, _) => {
+                    match plaintext_modulus {
+                        None => plaintext_modulus = Some(*p),
+                        Some(expected) if expected != *p => {
+                            return Err(GadgetError::PlaintextModulusMismatch {
+                                pin,
+                                expected,
+                                actual: *p,
+                            });
+                        }
+                        Some(_) => {}
+                    }
+                    match trivial_lwe_message(ct, *p) {
+                        Some(message) => trivial_row |= ((message != 0) as usize) << pin,
+                        None => encrypted_pins.push((pin, ct)),
+                    }
+                }
+                Ciphertext::Trivial(bit) => trivial_row |= (*bit as usize) << pin,
+                Ciphertext::Placeholder => return Err(GadgetError::PlaceholderCiphertext),
+            }
+        }
+        let row_output = |row: usize| (tt_value[row / 128] >> (row % 128)) & 1 == 1;
+
+        let Some(p) = plaintext_modulus else {
+            return Ok(Ciphertext::Trivial(row_output(trivial_row)));
+        };
+        let ciphertext_modulus = self.ciphertext_modulus();
+        if encrypted_pins.is_empty() {
+            let mut output_ct = LweCiphertextOwned::new(
+                Scalar::ZERO,
+                self.ciphertext_lwe_size(),
+                ciphertext_modulus,
+            );
+            *output_ct.get_mut_body().data =
+                encode_coefficient(row_output(trivial_row) as u32, p, ciphertext_modulus);
+            return Ok(Ciphertext::Encrypted(output_ct, p, NoiseLevel::ZERO));
+        }
+        if p < 2 {
+            return Err(GadgetError::UnsupportedPlaintextModulus(p));
+        }
+        if !ciphertext_modulus.is_native_modulus() {
+            return Err(GadgetError::UnsupportedCiphertextModulus(
+                ciphertext_modulus.get_custom_modulus(),
+            ));
+        }
+        let fourier_bsk = match &self.bootstrapping_key {
+            GadgetBootstrappingKey::Classic(fourier_bsk) => fourier_bsk,
+            GadgetBootstrappingKey::MultiBit { .. } => {
+                return Err(GadgetError::UnsupportedMultiBitKey)
+            }
+            GadgetBootstrappingKey::Ntt(_) => {
+                return Err(GadgetError::UnsupportedNttBackend(
+                    "the circuit bootstrapping".to_string(),
+                ))
+            }
+        };
+        if self.parameters.encryption_key_choice == EncryptionKeyChoice::Big {
+            return Err(GadgetError::UnsupportedBigEncryptionKey);
+        }
+
+        let fft = Fft::new(fourier_bsk.polynomial_size());
+        let fft = fft.as_view();
+        let mut computation_buffers = ComputationBuffers::default();
+
+        // the vertical packing expects the most significant bit of the row first
+        let bit_cts: Vec<_> = encrypted_pins.iter().rev().map(|(_, ct)| *ct).collect();
+        let bits = self.bits_to_msb(&bit_cts, p, fourier_bsk, fft, &mut computation_buffers);
+
+        // entry j of the lookup table is the output of the row setting encrypted pin i to bit i
+        // of j
+        let lut = (0..1usize << encrypted_pins.len())
+            .map(|index| {
+                let row = encrypted_pins
+                    .iter()
+                    .enumerate()
+                    .fold(trivial_row, |row, (bit, (pin, _))| {
+                        row | ((index >> bit) & 1) << pin
+                    });
+                encode_coefficient(row_output(row) as u32, p, ciphertext_modulus)
+            })
+            .collect();
+        let output_ct = self.circuit_bootstrap_vertical_packing(
+            wopbs_key,
+            &bits,
+            lut,
+            fourier_bsk,
+            fft,
+            &mut computation_buffers,
+        );
+
+        Ok(Ciphertext::Encrypted(output_ct, p, NoiseLevel::NOMINAL))
+    }
+
+    /// Extracts the `log2(p)` bits of the message modulo `p` of `ct`, from the most significant
+    /// one to the least significant one, each bit being encrypted in the most significant bit of
+    /// the torus under the small LWE key.
+    ///
+    /// The bits are extracted from the least significant one: shifting the bit to the most
+    /// significant position of a copy of `ct` encrypts it, and bootstrapping that copy gives the
+    /// encryption of the bit at its position in `ct`, which is then subtracted from `ct`. Unlike
+    /// the bit extraction of `core_crypto`, the keyswitch follows the bootstrap, so that `ct`
+    /// stays under the small LWE key.
+    fn extract_bits(
+        &self,
+        ct: &LweCiphertextOwned<Scalar>,
+        p: u32,
+        fourier_bsk: &FourierLweBootstrapKeyOwned,
+        fft: FftView<'_>,
+        computation_buffers: &mut ComputationBuffers,
+    ) -> LweCiphertextListOwned<Scalar> {
+        let ciphertext_modulus = ct.ciphertext_modulus();
+        let bit_count = p.trailing_zeros() as usize;
+        // messages modulo an even p are scaled by q / 2p, keeping the padding bit
+        let delta_log = Scalar::BITS - 1 - bit_count;
+
+        let mut remainder = ct.clone();
+        let mut bits = LweCiphertextListOwned::new(
+            Scalar::ZERO,
+            ct.lwe_size(),
+            LweCiphertextCount(bit_count),
+            ciphertext_modulus,
+        );
+        let mut shifted = LweCiphertextOwned::new(Scalar::ZERO, ct.lwe_size(), ciphertext_modulus);
+        let mut accumulator = GlweCiphertextOwned::new(
+            Scalar::ZERO,
+            fourier_bsk.glwe_size(),
+            fourier_bsk.polynomial_size(),
+            ciphertext_modulus,
+        );
+        let mut bootstrapped = LweCiphertextOwned::new(
+            Scalar::ZERO,
+            fourier_bsk.output_lwe_dimension().to_lwe_size(),
+            ciphertext_modulus,
+        );
+        let mut keyswitched =
+            LweCiphertextOwned::new(Scalar::ZERO, ct.lwe_size(), ciphertext_modulus);
+
+        computation_buffers.resize(
+            programmable_bootstrap_lwe_ciphertext_mem_optimized_requirement::<Scalar>(
+                fourier_bsk.glwe_size(),
+                fourier_bsk.polynomial_size(),
+                fft,
+            )
+            .unwrap()
+            .unaligned_bytes_required(),
+        );
+
+        // the most significant bit is stored first
+        for (bit_index, mut bit) in bits.iter_mut().rev().enumerate() {
+            // shift the bit to extract to the most significant position, dropping the padding
+            // bit and the bits not extracted yet
+            for (bit_value, value) in bit.as_mut().iter_mut().zip(remainder.as_ref()) {
+                *bit_value = *value << (Scalar::BITS - delta_log - bit_index - 1);
+            }
+
+            if bit_index == bit_count - 1 {
+                break;
+            }
+
+            // add q/4 to center the noise around the windows of the negacyclic lookup table,
+            // which outputs -alpha for a bit 0 and alpha for a bit 1, alpha being half the bit
+            // at its position in the remainder
+            shifted.as_mut().copy_from_slice(bit.as_ref());
+            let body = shifted.get_mut_body().data;
+            *body = (*body).wrapping_add(Scalar::ONE << (Scalar::BITS - 2));
+            let alpha = Scalar::ONE << (delta_log + bit_index - 1);
+            accumulator
+                .get_mut_body()
+                .as_mut()
+                .fill(Scalar::ZERO.wrapping_sub(alpha));
+
+            programmable_bootstrap_lwe_ciphertext_mem_optimized(
+                &shifted,
+                &mut bootstrapped,
+                &accumulator,
+                fourier_bsk,
+                fft,
+                computation_buffers.stack(),
+            );
+            let body = bootstrapped.get_mut_body().data;
+            *body = (*body).wrapping_add(alpha);
+
+            keyswitch_lwe_ciphertext(&self.key_switching_key, &bootstrapped, &mut keyswitched);
+            lwe_ciphertext_sub_assign(&mut remainder, &keyswitched);
+        }
+
+        bits
+    }
+
+    /// Moves the bit encrypted modulo `p` by each of `cts` to the most significant bit of the
+    /// torus, under the small LWE key, as expected by the circuit bootstrap.
+    ///
+    /// Shifting `ct` by half a slot puts the bits 0 and 1 on either side of 0, where a bootstrap
+    /// with a constant lookup table of q/4 outputs -q/4 and q/4 respectively.
+    fn bits_to_msb(
+        &self,
+        cts: &[&LweCiphertextOwned<Scalar>],
+        p: u32,
+        fourier_bsk: &FourierLweBootstrapKeyOwned,
+        fft: FftView<'_>,
+        computation_buffers: &mut ComputationBuffers,
+    ) -> LweCiphertextListOwned<Scalar> {
+        let ciphertext_modulus = self.ciphertext_modulus();
+        let lwe_size = self.key_switching_key.output_lwe_size();
+        let half_slot = encode_slot(1, 2 * torus_modulus(p), ciphertext_modulus);
+        let quarter = Scalar::ONE << (Scalar::BITS - 2);
+
+        let mut bits = LweCiphertextListOwned::new(
+            Scalar::ZERO,
+            lwe_size,
+            LweCiphertextCount(cts.len()),
+            ciphertext_modulus,
+        );
+        let mut shifted = LweCiphertextOwned::new(Scalar::ZERO, lwe_size, ciphertext_modulus);
+        let mut accumulator = GlweCiphertextOwned::new(
+            Scalar::ZERO,
+            fourier_bsk.glwe_size(),
+            fourier_bsk.polynomial_size(),
+            ciphertext_modulus,
+        );
+        accumulator.get_mut_body().as_mut().fill(quarter);
+        let mut bootstrapped = LweCiphertextOwned::new(
+            Scalar::ZERO,
+            fourier_bsk.output_lwe_dimension().to_lwe_size(),
+            ciphertext_modulus,
+        );
+
+        computation_buffers.resize(
+            programmable_bootstrap_lwe_ciphertext_mem_optimized_requirement::<Scalar>(
+                fourier_bsk.glwe_size(),
+                fourier_bsk.polynomial_size(),
+                fft,
+            )
+            .unwrap()
+            .unaligned_bytes_required(),
+        );
+
+        for (ct, mut bit) in cts.iter().zip(bits.iter_mut()) {
+            shifted.as_mut().copy_from_slice(ct.as_ref());
+            let body = shifted.get_mut_body().data;
+            *body = (*body).wrapping_sub(half_slot);
+
+            programmable_bootstrap_lwe_ciphertext_mem_optimized(
+                &shifted,
+                &mut bootstrapped,
+                &accumulator,
+                fourier_bsk,
+                fft,
+                computation_buffers.stack(),
+            );
+            let body = bootstrapped.get_mut_body().data;
+            *body = (*body).wrapping_add(quarter);
+
+            keyswitch_lwe_ciphertext(&self.key_switching_key, &bootstrapped, &mut bit);
+        }
+
+        bits
+    }
+
+    /// Circuit bootstraps `bits`, the most significant one first, and returns the entry of `lut`
+    /// they index, keyswitched to the small LWE key.
+    fn circuit_bootstrap_vertical_packing(
+        &self,
+        wopbs_key: &WopbsKey<Scalar>,
+        bits: &LweCiphertextListOwned<Scalar>,
+        mut lut: Vec<Scalar>,
+        fourier_bsk: &FourierLweBootstrapKeyOwned,
+        fft: FftView<'_>,
+        computation_buffers: &mut ComputationBuffers,
+    ) -> LweCiphertextOwned<Scalar> {
+        let ciphertext_modulus = self.ciphertext_modulus();
+
+        // the lookup table spans at least one polynomial
+        let polynomial_size = fourier_bsk.polynomial_size();
+        lut.resize(lut.len().max(polynomial_size.0), Scalar::ZERO);
+        let lut = PolynomialList::from_container(lut, polynomial_size);
+
+        let mut outputs = LweCiphertextListOwned::new(
+            Scalar::ZERO,
+            fourier_bsk.output_lwe_dimension().to_lwe_size(),
+            LweCiphertextCount(1),
+            ciphertext_modulus,
+        );
+        computation_buffers.resize(
+            circuit_bootstrap_boolean_vertical_packing_lwe_ciphertext_list_mem_optimized_requirement::<
+                Scalar,
+            >(
+                bits.lwe_ciphertext_count(),
+                outputs.lwe_ciphertext_count(),
+                bits.lwe_size(),
+                lut.polynomial_count(),
+                fourier_bsk.output_lwe_dimension().to_lwe_size(),
+                fourier_bsk.glwe_size(),
+                wopbs_key.cbs_pfpksk.output_polynomial_size(),
+                wopbs_key.parameters.cbs_level,
+                fft,
+            )
+            .unwrap()
+            .unaligned_bytes_required(),
+        );
+        circuit_bootstrap_boolean_vertical_packing_lwe_ciphertext_list_mem_optimized(
+            bits,
+            &mut outputs,
+            &lut,
+            fourier_bsk,
+            &wopbs_key.cbs_pfpksk,
+            wopbs_key.parameters.cbs_base_log,
+            wopbs_key.parameters.cbs_level,
+            fft,
+            computation_buffers.stack(),
+        );
+
+        let mut output_ct = LweCiphertextOwned::new(
+            Scalar::ZERO,
+            self.key_switching_key.output_lwe_size(),
+            ciphertext_modulus,
+        );
+        keyswitch_lwe_ciphertext(&self.key_switching_key, &outputs.get(0), &mut output_ct);
+        output_ct
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_crypto::prelude::{DecompositionBaseLog, DecompositionLevelCount, StandardDev};
+    use crate::gadget::parameters::{
+        GadgetParameters, PLAINTEXT_3_BITS_PARAMETERS, WOPBS_5_BITS_CBS_PARAMETERS,
+        WOPBS_5_BITS_PARAMETERS,
+    };
+
+    /// Returns keys over a 64 bits torus with little noise (these parameters are not secure).
+    fn wopbs_keys() -> (ClientKey<u64>, ServerKey<u64>, WopbsKey<u64>) {
+        let parameters = GadgetParameters {
+            lwe_modular_std_dev: StandardDev(2f64.powi(-40)),
+            glwe_modular_std_dev: StandardDev(2f64.powi(-50)),
+            pbs_base_log: DecompositionBaseLog(15),
+            pbs_level: DecompositionLevelCount(2),
+            ks_base_log: DecompositionBaseLog(4),
+            ks_level: DecompositionLevelCount(8),
+            ..PLAINTEXT_3_BITS_PARAMETERS
+        };
+        let client_key = ClientKey::<u64>::new(&parameters);
+        let server_key = ServerKey::new(&client_key);
+        let wopbs_key = WopbsKey::new(
+            &client_key,
+            GadgetWopbsParameters::new(
+                DecompositionBaseLog(15),
+                DecompositionLevelCount(2),
+                StandardDev(2f64.powi(-50)),
+                DecompositionBaseLog(7),
+                DecompositionLevelCount(2),
+            ),
+        );
+        (client_key, server_key, wopbs_key)
+    }
+
+    #[test]
+    fn evaluate_gate_wopbs_beyond_lookup_tables() {
+        // the little noise leaves room for the 10 bits of p = 1024, twice the largest modulus of
+        // the lookup tables of N = 1024
+        let (client_key, server_key, wopbs_key) = wopbs_keys();
+        let p = 1024;
+        assert!(p > server_key.parameters().max_supported_p());
+
+        // and of two pins mapped to 300 and 700, the sum 1000 being the only one set
+        let encoding = Encoding::from_mappings(8, vec![700, 300], p).unwrap();
+        for lhs in 0..2u32 {
+            for rhs in 0..2u32 {
+                let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                    vec![
+                        engine.encrypt(lhs, &client_key, p),
+                        engine.encrypt(rhs, &client_key, p),
+                    ]
+                });
+                let out = server_key
+                    .evaluate_gate_wopbs(&wopbs_key, inputs, &encoding)
+                    .unwrap();
+                let decrypted =
+                    GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&out, &client_key));
+                assert_eq!(decrypted, lhs & rhs);
+            }
+        }
+
+        // the modulus of the encoding must be a power of two
+        let encoding = Encoding::from_mappings(8, vec![1, 1], 3).unwrap();
+        let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+            vec![
+                engine.encrypt(1, &client_key, 3),
+                engine.encrypt(1, &client_key, 3),
+            ]
+        });
+        assert_eq!(
+            server_key
+                .evaluate_gate_wopbs(&wopbs_key, inputs, &encoding)
+                .unwrap_err(),
+            GadgetError::UnsupportedPlaintextModulus(3)
+        );
+    }
+
+    #[test]
+    fn evaluate_wide_gate_with_trivial_pins() {
+        let (client_key, server_key, wopbs_key) = wopbs_keys();
+        let p = 4;
+        // 8 pins, far beyond the sums of an encoding modulo 4
+        let tt_value: [u128; 2] = [
+            0x0123_4567_89ab_cdef_fedc_ba98_7654_3210,
+            0x9e37_79b9_7f4a_7c15_f39c_c060_5ced_c834,
+        ];
+        let row_output = |row: usize| ((tt_value[row / 128] >> (row % 128)) & 1) as u32;
+
+        for row in [0usize, 0b1010_0101, 0b1111_1111, 0b0110_1001] {
+            let inputs: Vec<_> = GadgetEngine::with_thread_local_mut(|engine| {
+                (0..8)
+                    .map(|pin| engine.encrypt(((row >> pin) & 1) as u32, &client_key, p))
+                    .collect()
+            });
+            let out = server_key
+                .evaluate_wide_gate(&wopbs_key, &inputs, &tt_value)
+                .unwrap();
+            let decrypted =
+                GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&out, &client_key));
+            assert_eq!(decrypted, row_output(row), "row {row:#010b}");
+        }
+
+        // trivial pins are fixed in the truth table, only pins 0 and 5 are circuit bootstrapped
+        let row = 0b1101_0110;
+        let inputs: Vec<_> = GadgetEngine::with_thread_local_mut(|engine| {
+            (0..8)
+                .map(|pin| {
+                    let bit = ((row >> pin) & 1) as u32;
+                    match pin {
+                        0 | 5 => engine.encrypt(bit, &client_key, p),
+                        1 | 2 => server_key.trivial_encrypt(bit == 1),
+                        _ => server_key.trivial_encrypt_modular(bit, p),
+                    }
+                })
+                .collect()
+        });
+        let out = server_key
+            .evaluate_wide_gate(&wopbs_key, &inputs, &tt_value)
+            .unwrap();
+        let decrypted =
+            GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&out, &client_key));
+        assert_eq!(decrypted, row_output(row));
+
+        let out = server_key
+            .evaluate_wide_gate(
+                &wopbs_key,
+                &[
+                    server_key.trivial_encrypt(true),
+                    server_key.trivial_encrypt(false),
+                ],
+                &[0b0010],
+            )
+            .unwrap();
+        assert!(matches!(out, Ciphertext::Trivial(true)));
+
+        // the truth table must hold one bit per row
+        assert!(matches!(
+            server_key.evaluate_wide_gate(&wopbs_key, &inputs, &tt_value[..1]),
+            Err(GadgetError::InvalidEncoding(_))
+        ));
+        let inputs = vec![server_key.trivial_encrypt(true); MAX_WIDE_GATE_PINS + 1];
+        assert!(matches!(
+            server_key.evaluate_wide_gate(&wopbs_key, &inputs, &[0; 1 << 10]),
+            Err(GadgetError::InvalidEncoding(_))
+        ));
+    }
+
+    #[test]
+    fn evaluate_gate_wopbs_with_secure_parameters() {
+        let client_key = ClientKey::<u64>::new(&WOPBS_5_BITS_PARAMETERS);
+        let server_key = ServerKey::new(&client_key);
+        let wopbs_key = WopbsKey::new(&client_key, WOPBS_5_BITS_CBS_PARAMETERS);
+
+        // the lookup tables of the set cannot bootstrap a nominal ciphertext modulo 32
+        let p = 32;
+        assert_eq!(server_key.max_noise_level(p), NoiseLevel::ZERO);
+
+        // and of two pins mapped to 20 and 11, the sum 31 being the only one set
+        let encoding = Encoding::from_mappings(8, vec![11, 20], p).unwrap();
+        for lhs in 0..2u32 {
+            for rhs in 0..2u32 {
+                let inputs = GadgetEngine::with_thread_local_mut(|engine| {
+                    vec![
+                        engine.encrypt(lhs, &client_key, p),
+                        engine.encrypt(rhs, &client_key, p),
+                    ]
+                });
+                let out = server_key
+                    .evaluate_gate_wopbs(&wopbs_key, inputs, &encoding)
+                    .unwrap();
+                let decrypted =
+                    GadgetEngine::with_thread_local_mut(|engine| engine.decrypt(&out, &client_key));
+                assert_eq!(decrypted, lhs & rhs);
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use wasm_bindgen::prelude::*;
+
+use crate::boolean::engine::WithThreadLocalEngine;
+use crate::core_crypto::commons::math::random::Seed;
+use crate::gadget::engine::GadgetEngine;
+
+use std::panic::set_hook;
+
+#[wasm_bindgen]
+pub struct GadgetCiphertext(pub(crate) crate::gadget::ciphertext::Ciphertext);
+
+#[wasm_bindgen]
+pub struct GadgetCompressedCiphertext(pub(crate) crate::gadget::ciphertext::CompressedCiphertext);
+
+#[wasm_bindgen]
+pub struct GadgetClientKey(pub(crate) crate::gadget::client_key::ClientKey);
+
+#[wasm_bindgen]
+pub struct GadgetCompressedServerKey(pub(crate) crate::gadget::server_key::CompressedServerKey);
+
+#[wasm_bindgen]
+pub struct Gadget {}
+
+#[wasm_bindgen]
+pub struct GadgetParameters(pub(crate) crate::gadget::parameters::GadgetParameters);
+
+#[wasm_bindgen]
+pub enum GadgetParameterSet {
+    Boolean,
+    Plaintext2Bits,
+    Plaintext3Bits,
+    Plaintext4Bits,
+    Plaintext5Bits,
+}
+
+impl TryFrom<u32> for GadgetParameterSet {
+    type Error = String;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(GadgetParameterSet::Boolean),
+            1 => Ok(GadgetParameterSet::Plaintext2Bits),
+            2 => Ok(GadgetParameterSet::Plaintext3Bits),
+            3 => Ok(GadgetParameterSet::Plaintext4Bits),
+            4 => Ok(GadgetParameterSet::Plaintext5Bits),
+            _ => Err(format!(
+                "Invalid value '{value}' for GadgetParameterSet, use \
+                GadgetParameterSet constants"
+            )),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Gadget {
+    #[wasm_bindgen]
+    pub fn get_parameters(parameter_choice: u32) -> Result<GadgetParameters, JsError> {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        let parameter_choice = GadgetParameterSet::try_from(parameter_choice)
+            .map_err(|e| wasm_bindgen::JsError::new(format!("{e:?}").as_str()))?;
+
+        let parameters = match parameter_choice {
+            GadgetParameterSet::Boolean => crate::gadget::boolean::BOOLEAN_PARAMETERS,
+            GadgetParameterSet::Plaintext2Bits => {
+                crate::gadget::parameters::PLAINTEXT_2_BITS_PARAMETERS
+            }
+            GadgetParameterSet::Plaintext3Bits => {
+                crate::gadget::parameters::PLAINTEXT_3_BITS_PARAMETERS
+            }
+            GadgetParameterSet::Plaintext4Bits => {
+                crate::gadget::parameters::PLAINTEXT_4_BITS_PARAMETERS
+            }
+            GadgetParameterSet::Plaintext5Bits => {
+                crate::gadget::parameters::PLAINTEXT_5_BITS_PARAMETERS
+            }
+        };
+        Ok(GadgetParameters(parameters))
+    }
+
+    #[wasm_bindgen]
+    pub fn new_client_key_from_seed_and_parameters(
+        seed_high_bytes: u64,
+        seed_low_bytes: u64,
+        parameters: &GadgetParameters,
+    ) -> GadgetClientKey {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        let seed_high_bytes: u128 = seed_high_bytes.into();
+        let seed_low_bytes: u128 = seed_low_bytes.into();
+        let seed: u128 = (seed_high_bytes << 64) | seed_low_bytes;
+
+        let mut engine = GadgetEngine::new_from_seed(Seed(seed));
+        GadgetClientKey(crate::gadget::client_key::ClientKey::new_with_engine(
+            &mut engine,
+            &parameters.0,
+        ))
+    }
+
+    #[wasm_bindgen]
+    pub fn new_client_key(parameters: &GadgetParameters) -> GadgetClientKey {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        GadgetClientKey(crate::gadget::client_key::ClientKey::new(&parameters.0))
+    }
+
+    #[wasm_bindgen]
+    pub fn new_compressed_server_key(client_key: &GadgetClientKey) -> GadgetCompressedServerKey {
+        set_hook(Box::new(console_error_panic_hook::hook));
+
+        GadgetCompressedServerKey(crate::gadget::server_key::CompressedServerKey::new(
+            &client_key.0,
+        ))
+    }
+
+    #[wasm_bindgen]
+    pub fn encrypt(client_key: &GadgetClientKey, message: bool) -> GadgetCiphertext {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        GadgetCiphertext(client_key.0.encrypt(message))
+    }
+
+    #[wasm_bindgen]
+    pub fn encrypt_compressed(
+        client_key: &GadgetClientKey,
+        message: bool,
+    ) -> GadgetCompressedCiphertext {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        GadgetCompressedCiphertext(client_key.0.encrypt_compressed(message))
+    }
+
+    #[wasm_bindgen]
+    pub fn encrypt_modular(
+        client_key: &GadgetClientKey,
+        message: u32,
+        plaintext_modulus: u32,
+    ) -> GadgetCiphertext {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        GadgetCiphertext(GadgetEngine::with_thread_local_mut(|engine| {
+            engine.encrypt(message, &client_key.0, plaintext_modulus)
+        }))
+    }
+
+    #[wasm_bindgen]
+    pub fn encrypt_modular_compressed(
+        client_key: &GadgetClientKey,
+        message: u32,
+        plaintext_modulus: u32,
+    ) -> GadgetCompressedCiphertext {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        GadgetCompressedCiphertext(GadgetEngine::with_thread_local_mut(|engine| {
+            engine.encrypt_compressed(message, &client_key.0, plaintext_modulus)
+        }))
+    }
+
+    #[wasm_bindgen]
+    pub fn decompress_ciphertext(
+        compressed_ciphertext: &GadgetCompressedCiphertext,
+    ) -> GadgetCiphertext {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        GadgetCiphertext(compressed_ciphertext.0.clone().into())
+    }
+
+    #[wasm_bindgen]
+    pub fn trivial_encrypt(message: bool) -> GadgetCiphertext {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        GadgetCiphertext(crate::gadget::ciphertext::Ciphertext::Trivial(message))
+    }
+
+    #[wasm_bindgen]
+    pub fn decrypt(client_key: &GadgetClientKey, ct: &GadgetCiphertext) -> Result<bool, JsError> {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        client_key
+            .0
+            .try_decrypt(&ct.0)
+            .map_err(|e| wasm_bindgen::JsError::new(format!("{e}").as_str()))
+    }
+
+    #[wasm_bindgen]
+    pub fn decrypt_modular(
+        client_key: &GadgetClientKey,
+        ct: &GadgetCiphertext,
+    ) -> Result<u32, JsError> {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        client_key
+            .0
+            .try_decrypt_modular(&ct.0)
+            .map(|(message, _)| message)
+            .map_err(|e| wasm_bindgen::JsError::new(format!("{e}").as_str()))
+    }
+
+    #[wasm_bindgen]
+    pub fn serialize_ciphertext(ciphertext: &GadgetCiphertext) -> Result<Vec<u8>, JsError> {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        bincode::serialize(&ciphertext.0)
+            .map_err(|e| wasm_bindgen::JsError::new(format!("{e:?}").as_str()))
+    }
+
+    #[wasm_bindgen]
+    pub fn deserialize_ciphertext(buffer: &[u8]) -> Result<GadgetCiphertext, JsError> {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        bincode::deserialize(buffer)
+            .map_err(|e| wasm_bindgen::JsError::new(format!("{e:?}").as_str()))
+            .map(GadgetCiphertext)
+    }
+
+    #[wasm_bindgen]
+    pub fn serialize_compressed_ciphertext(
+        ciphertext: &GadgetCompressedCiphertext,
+    ) -> Result<Vec<u8>, JsError> {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        bincode::serialize(&ciphertext.0)
+            .map_err(|e| wasm_bindgen::JsError::new(format!("{e:?}").as_str()))
+    }
+
+    #[wasm_bindgen]
+    pub fn deserialize_compressed_ciphertext(
+        buffer: &[u8],
+    ) -> Result<GadgetCompressedCiphertext, JsError> {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        bincode::deserialize(buffer)
+            .map_err(|e| wasm_bindgen::JsError::new(format!("{e:?}").as_str()))
+            .map(GadgetCompressedCiphertext)
+    }
+
+    #[wasm_bindgen]
+    pub fn serialize_client_key(client_key: &GadgetClientKey) -> Result<Vec<u8>, JsError> {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        bincode::serialize(&client_key.0)
+            .map_err(|e| wasm_bindgen::JsError::new(format!("{e:?}").as_str()))
+    }
+
+    #[wasm_bindgen]
+    pub fn deserialize_client_key(buffer: &[u8]) -> Result<GadgetClientKey, JsError> {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        bincode::deserialize(buffer)
+            .map_err(|e| wasm_bindgen::JsError::new(format!("{e:?}").as_str()))
+            .map(GadgetClientKey)
+    }
+
+    #[wasm_bindgen]
+    pub fn serialize_compressed_server_key(
+        server_key: &GadgetCompressedServerKey,
+    ) -> Result<Vec<u8>, JsError> {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        bincode::serialize(&server_key.0)
+            .map_err(|e| wasm_bindgen::JsError::new(format!("{e:?}").as_str()))
+    }
+
+    #[wasm_bindgen]
+    pub fn deserialize_compressed_server_key(
+        buffer: &[u8],
+    ) -> Result<GadgetCompressedServerKey, JsError> {
+        set_hook(Box::new(console_error_panic_hook::hook));
+        bincode::deserialize(buffer)
+            .map_err(|e| wasm_bindgen::JsError::new(format!("{e:?}").as_str()))
+            .map(GadgetCompressedServerKey)
+    }
+}
@@ -8,6 +8,11 @@ mod boolean;
 #[cfg(feature = "boolean-client-js-wasm-api")]
 pub use boolean::*;
 
+#[cfg(feature = "gadget-client-js-wasm-api")]
+mod gadget;
+#[cfg(feature = "gadget-client-js-wasm-api")]
+pub use gadget::*;
+
 #[cfg(feature = "parallel-wasm-api")]
 pub use wasm_bindgen_rayon::init_thread_pool;
 
@@ -292,3 +292,54 @@ mod test_integer {
         assert_eq!(&msg[..], &dec);
     }
 }
+
+#[cfg(all(test, feature = "p-encoding"))]
+mod test_gadget {
+    use crate::boolean::engine::WithThreadLocalEngine;
+    use crate::gadget::boolean::BOOLEAN_PARAMETERS;
+    use crate::gadget::ciphertext::Ciphertext;
+    use crate::gadget::client_key::ClientKey;
+    use crate::gadget::encoding::Encoding;
+    use crate::gadget::engine::GadgetEngine;
+    use crate::gadget::gen_keys;
+    use crate::gadget::parameters::PLAINTEXT_6_BITS_PARAMETERS;
+    use crate::gadget::server_key::ServerKey;
+    use crate::safe_deserialization::{safe_deserialize, safe_serialize};
+
+    #[test]
+    fn safe_deserialization_gadget() {
+        let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+
+        let ct = client_key.encrypt(true);
+        let mut buffer = vec![];
+        safe_serialize(&ct, &mut buffer, 1 << 20).unwrap();
+
+        // the type name and the size limit are checked
+        assert!(safe_deserialize::<ClientKey>(buffer.as_slice(), 1 << 20).is_err());
+        assert!(safe_deserialize::<Ciphertext>(buffer.as_slice(), 1 << 4).is_err());
+        let ct2: Ciphertext = safe_deserialize(buffer.as_slice(), 1 << 20).unwrap();
+        assert!(client_key.decrypt(&ct2));
+
+        let mut buffer = vec![];
+        safe_serialize(&server_key, &mut buffer, 1 << 30).unwrap();
+        assert!(safe_deserialize::<ServerKey>(buffer.as_slice(), 1 << 10).is_err());
+        let server_key2: ServerKey = safe_deserialize(buffer.as_slice(), 1 << 30).unwrap();
+        let ct3 = server_key2.and(&ct2, &client_key.encrypt(true)).unwrap();
+        assert!(client_key.decrypt(&ct3));
+
+        let encoding = Encoding::new_refresh(3);
+        let mut buffer = vec![];
+        safe_serialize(&encoding, &mut buffer, 1 << 10).unwrap();
+        let encoding2: Encoding = safe_deserialize(buffer.as_slice(), 1 << 10).unwrap();
+        assert_eq!(encoding, encoding2);
+
+        // ciphertexts on a 64 bits torus are not mistaken for 32 bits ones
+        let client_key_64 = ClientKey::<u64>::new(&PLAINTEXT_6_BITS_PARAMETERS);
+        let ct_64 =
+            GadgetEngine::with_thread_local_mut(|engine| engine.encrypt(1, &client_key_64, 63));
+        let mut buffer = vec![];
+        safe_serialize(&ct_64, &mut buffer, 1 << 20).unwrap();
+        assert!(safe_deserialize::<Ciphertext>(buffer.as_slice(), 1 << 20).is_err());
+        assert!(safe_deserialize::<Ciphertext<u64>>(buffer.as_slice(), 1 << 20).is_ok());
+    }
+}
@@ -0,0 +1,88 @@
+//! Allocations of the gadget engine, counted by a global allocator replacing the one of this
+//! test binary only.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use tfhe::gadget::boolean::BOOLEAN_PARAMETERS;
+use tfhe::gadget::encoding::Encoding;
+use tfhe::gadget::engine::GadgetEngine;
+use tfhe::gadget::gen_keys;
+
+/// Counts the allocations of each thread, see [`allocation_count`].
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count_allocation() {
+    // the counter is gone while the thread exits
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+/// Returns the number of allocations of the current thread so far.
+fn allocation_count() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn preallocated_evaluation_does_not_allocate() {
+    let (client_key, server_key) = gen_keys(&BOOLEAN_PARAMETERS);
+    let encoding = Encoding::new_canonical(8, 2, vec![1, 1], vec![0, 1], vec![2], 3);
+
+    let mut engine = GadgetEngine::new();
+    engine.preallocate(&server_key);
+    server_key.lookup_table(&encoding);
+
+    let mut output = engine.encrypt(0, &client_key, 3);
+    for (lhs, rhs) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+        let inputs = vec![
+            engine.encrypt(lhs, &client_key, 3),
+            engine.encrypt(rhs, &client_key, 3),
+        ];
+
+        let allocations = allocation_count();
+        engine
+            .evaluate_gate_into(&server_key, &encoding, inputs, &mut output)
+            .unwrap();
+        assert_eq!(allocation_count(), allocations);
+
+        assert_eq!(engine.decrypt(&output, &client_key), lhs & rhs);
+    }
+
+    // without preallocation the first gate sizes the buffers
+    let mut engine = GadgetEngine::new();
+    let inputs = vec![
+        engine.encrypt(1, &client_key, 3),
+        engine.encrypt(1, &client_key, 3),
+    ];
+    let allocations = allocation_count();
+    engine
+        .evaluate_gate_into(&server_key, &encoding, inputs, &mut output)
+        .unwrap();
+    assert!(allocation_count() > allocations);
+}